@@ -0,0 +1,82 @@
+use core::arch::x86_64::{_fxrstor64, _fxsave64};
+
+/// The size of the `FXSAVE` area.
+pub const FPU_STATE_SIZE: usize = 512;
+
+/// The offsets of MXCSR and MXCSR_MASK in the `FXSAVE` area.
+const MXCSR_OFFSET: usize = 24;
+const MXCSR_MASK_OFFSET: usize = 28;
+/// MXCSR_MASK if `FXSAVE` reports zero.
+const MXCSR_MASK_DEFAULT: u32 = 0xffbf;
+
+/// The x87 FPU, MMX and SSE registers in the `FXSAVE` format, i.e.
+/// `struct user_fpregs_struct` and `struct _fpstate`.
+///
+/// The kernel doesn't use these registers: ones in the CPU always belong to
+/// the userspace of the current thread.
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+pub struct FpuState {
+    bytes: [u8; FPU_STATE_SIZE],
+}
+
+/// The offset of FCW in the `FXSAVE` area.
+const FCW_OFFSET: usize = 0;
+/// The initial FCW and MXCSR: all exceptions are masked.
+const FCW_DEFAULT: u16 = 0x037f;
+const MXCSR_DEFAULT: u32 = 0x1f80;
+
+impl FpuState {
+    /// The state after `FNINIT`: registers are cleared and all exceptions are
+    /// masked.
+    pub fn initial() -> FpuState {
+        let mut state = FpuState {
+            bytes: [0; FPU_STATE_SIZE],
+        };
+        state.bytes[FCW_OFFSET..FCW_OFFSET + 2].copy_from_slice(&FCW_DEFAULT.to_ne_bytes());
+        state.write_u32(MXCSR_OFFSET, MXCSR_DEFAULT);
+        state
+    }
+
+    /// Saves the registers of the current CPU.
+    pub fn save() -> FpuState {
+        let mut state = FpuState {
+            bytes: [0; FPU_STATE_SIZE],
+        };
+        unsafe {
+            _fxsave64(state.bytes.as_mut_ptr());
+        }
+        state
+    }
+
+    /// Loads the registers into the current CPU. MXCSR bits not supported
+    /// by the CPU are cleared: `FXRSTOR` raises #GP on them.
+    pub fn restore(&self) {
+        let mxcsr_mask = match FpuState::save().read_u32(MXCSR_MASK_OFFSET) {
+            0 => MXCSR_MASK_DEFAULT,
+            mask => mask,
+        };
+
+        let mut state = *self;
+        state.write_u32(MXCSR_OFFSET, self.read_u32(MXCSR_OFFSET) & mxcsr_mask);
+        unsafe {
+            _fxrstor64(state.bytes.as_ptr());
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        u32::from_ne_bytes(self.bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        self.bytes[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+    }
+}
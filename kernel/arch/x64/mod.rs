@@ -5,12 +5,19 @@ use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
 global_asm!(include_str!("usermode.S"));
 
 mod arch_prctl;
+mod fpu;
 mod process;
 
 pub const KERNEL_STACK_SIZE: usize = PAGE_SIZE * 256;
 pub const USER_VALLOC_END: UserVAddr = unsafe { UserVAddr::new_unchecked(0x0000_0fff_0000_0000) };
 pub const USER_VALLOC_BASE: UserVAddr = unsafe { UserVAddr::new_unchecked(0x0000_000a_0000_0000) };
 pub const USER_STACK_TOP: UserVAddr = USER_VALLOC_BASE;
+/// `AUDIT_ARCH_X86_64`: the architecture reported to seccomp filters.
+pub const AUDIT_ARCH: u32 = 0xc000_003e;
 
 pub use arch_prctl::arch_prctl;
-pub use process::{switch_thread, Process};
+pub use fpu::{FpuState, FPU_STATE_SIZE};
+pub use process::{
+    force_full_restore, restart_syscall, set_single_step, set_syscall_return_value, switch_thread,
+    syscall_instruction_pointer, syscall_number_and_args, syscall_return_value, Process, UserRegs,
+};
@@ -1,7 +1,16 @@
 use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use crate::result::Result;
-use crate::{arch::KERNEL_STACK_SIZE, process::signal::Signal};
+use core::mem::size_of;
+
+use crate::result::{Errno, Result};
+use crate::{
+    arch::{FpuState, FPU_STATE_SIZE, KERNEL_STACK_SIZE},
+    process::signal::{
+        SigAction, SigActionFlags, SigAltStack, SigInfo, SigSet, StackT, SIGINFO_SIZE, SS_DISABLE,
+        SS_ONSTACK,
+    },
+};
 use crossbeam::atomic::AtomicCell;
 use kerla_runtime::address::{UserVAddr, VAddr};
 use kerla_runtime::page_allocator::{alloc_pages_owned, OwnedPages};
@@ -11,10 +20,13 @@ use kerla_runtime::{
     arch::PAGE_SIZE,
     page_allocator::AllocPageFlags,
 };
-use x86::current::segmentation::wrfsbase;
+use x86::current::segmentation::{rdfsbase, wrfsbase};
 
 pub struct Process {
     rsp: UnsafeCell<u64>,
+    /// `true` while a CPU is running the thread, i.e., the context is not yet
+    /// saved by `do_switch_thread`.
+    running: AtomicBool,
     pub(super) fsbase: AtomicCell<u64>,
     pub(super) xsave_area: Option<OwnedPages>,
     // This appears dead, but really we're keeping the pages referenced from the
@@ -32,7 +44,7 @@ extern "C" {
     fn kthread_entry();
     fn userland_entry();
     fn forked_child_entry();
-    fn do_switch_thread(prev_rsp: *const u64, next_rsp: *const u64);
+    fn do_switch_thread(prev_rsp: *const u64, next_rsp: *const u64, prev_running: *const bool);
 }
 
 unsafe fn push_stack(mut rsp: *mut u64, value: u64) -> *mut u64 {
@@ -81,6 +93,7 @@ impl Process {
 
         Process {
             rsp: UnsafeCell::new(rsp as u64),
+            running: AtomicBool::new(false),
             fsbase: AtomicCell::new(0),
             xsave_area: None,
             interrupt_stack,
@@ -134,6 +147,7 @@ impl Process {
 
         Process {
             rsp: UnsafeCell::new(rsp as u64),
+            running: AtomicBool::new(false),
             fsbase: AtomicCell::new(0),
             xsave_area: Some(xsave_area),
             interrupt_stack,
@@ -161,6 +175,7 @@ impl Process {
 
         Process {
             rsp: UnsafeCell::new(0),
+            running: AtomicBool::new(true),
             fsbase: AtomicCell::new(0),
             xsave_area: None,
             interrupt_stack,
@@ -169,7 +184,24 @@ impl Process {
         }
     }
 
-    pub fn fork(&self, frame: &PtRegs) -> Result<Process> {
+    /// Creates a new thread which returns to the userspace as if it returned
+    /// from the system call. `child_stack` and `tls` replace the user stack
+    /// pointer and fsbase of the new thread respectively.
+    pub fn clone(
+        &self,
+        frame: &PtRegs,
+        child_stack: Option<UserVAddr>,
+        tls: Option<UserVAddr>,
+    ) -> Result<Process> {
+        let user_rsp = match child_stack {
+            Some(child_stack) => child_stack.value() as u64,
+            None => frame.rsp,
+        };
+        let fsbase = match tls {
+            Some(tls) => tls.value() as u64,
+            None => self.fsbase.load(),
+        };
+
         let xsave_area =
             alloc_pages_owned(1, AllocPageFlags::KERNEL).expect("failed to allocate xsave area");
         let kernel_stack = alloc_pages_owned(
@@ -183,7 +215,7 @@ impl Process {
 
             // Registers to be restored by IRET.
             rsp = push_stack(rsp, (USER_DS | USER_RPL) as u64); // SS
-            rsp = push_stack(rsp, frame.rsp); // user RSP
+            rsp = push_stack(rsp, user_rsp); // user RSP
             rsp = push_stack(rsp, frame.rflags); // user RFLAGS.
             rsp = push_stack(rsp, (USER_CS64 | USER_RPL) as u64); // CS
             rsp = push_stack(rsp, frame.rip); // user RIP
@@ -224,7 +256,8 @@ impl Process {
 
         Ok(Process {
             rsp: UnsafeCell::new(rsp as u64),
-            fsbase: AtomicCell::new(self.fsbase.load()),
+            running: AtomicBool::new(false),
+            fsbase: AtomicCell::new(fsbase),
             xsave_area: Some(xsave_area),
             interrupt_stack,
             syscall_stack,
@@ -232,6 +265,20 @@ impl Process {
         })
     }
 
+    /// Returns `true` if a CPU is running the thread.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Marks the thread as running on the current CPU. Returns `false` if
+    /// another CPU is still running it. It's cleared once the thread is
+    /// switched out.
+    pub fn try_set_running(&self) -> bool {
+        self.running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
     pub fn setup_execve_stack(
         &self,
         frame: &mut PtRegs,
@@ -243,11 +290,17 @@ impl Process {
         Ok(())
     }
 
+    /// Builds the signal frame (`struct rt_sigframe`) on the user stack and
+    /// modifies `frame` to call the signal handler. `oldmask` is the signal
+    /// mask restored by `rt_sigreturn(2)`.
     pub unsafe fn setup_signal_stack(
         &self,
         frame: &mut PtRegs,
-        signal: Signal,
+        info: &SigInfo,
         sa_handler: UserVAddr,
+        action: &SigAction,
+        sigaltstack: Option<SigAltStack>,
+        oldmask: SigSet,
     ) -> Result<()> {
         const TRAMPOLINE: &[u8] = &[
             0xb8, 0x0f, 0x00, 0x00, 0x00, // mov eax, 15
@@ -255,35 +308,385 @@ impl Process {
             0x90, // nop (for alignment)
         ];
 
-        fn push_to_user_stack(rsp: UserVAddr, value: u64) -> Result<UserVAddr> {
-            let rsp = rsp.sub(8);
-            rsp.write::<u64>(&value)?;
-            Ok(rsp)
+        let user_rsp = frame.rsp as usize;
+        let on_altstack = matches!(sigaltstack, Some(stack) if stack.contains(user_rsp));
+        let mut sp = match sigaltstack {
+            Some(stack) if action.flags.contains(SigActionFlags::SA_ONSTACK) && !on_altstack => {
+                stack.sp.value() + stack.size
+            }
+            // Avoid corrupting the red zone.
+            _ => user_rsp - 128,
+        };
+
+        // Copy the trampoline code if the libc didn't give us the restorer.
+        let restorer = match action.restorer {
+            Some(restorer) => restorer,
+            None => {
+                sp -= TRAMPOLINE.len();
+                let trampoline = UserVAddr::new_nonnull(sp)?;
+                trampoline.write_bytes(TRAMPOLINE)?;
+                trampoline
+            }
+        };
+
+        // Save the FPU/SSE registers above the frame. The handler starts with
+        // the initial state.
+        sp = (sp - FPU_STATE_SIZE) & !0x3f;
+        let fpstate_addr = UserVAddr::new_nonnull(sp)?;
+        fpstate_addr.write_bytes(FpuState::save().as_bytes())?;
+
+        // The handler is called as if the restorer called it: the stack
+        // pointer is 8 bytes off the 16-byte alignment.
+        sp -= size_of::<SigFrame>();
+        sp = (sp & !0xf) - 8;
+
+        let uc_stack = match sigaltstack {
+            Some(stack) => StackT {
+                ss_sp: stack.sp.value(),
+                ss_flags: if on_altstack { SS_ONSTACK } else { 0 },
+                ss_size: stack.size,
+            },
+            None => StackT {
+                ss_sp: 0,
+                ss_flags: SS_DISABLE,
+                ss_size: 0,
+            },
+        };
+
+        let sigframe_addr = UserVAddr::new_nonnull(sp)?;
+        let sigframe = SigFrame {
+            pretcode: restorer.value() as u64,
+            uc: UContext {
+                uc_flags: 0,
+                uc_link: 0,
+                uc_stack,
+                uc_mcontext: SigContext::from_frame(frame, oldmask, fpstate_addr),
+                uc_sigmask: oldmask.as_raw(),
+            },
+            info: info.to_bytes(),
+        };
+        sigframe_addr.write(&sigframe)?;
+        FpuState::initial().restore();
+
+        frame.rip = sa_handler.value() as u64;
+        frame.rsp = sp as u64;
+        frame.rdi = info.signal as u64; // int signal
+        frame.rsi = (sp + offset_of_info()) as u64; // siginfo_t *siginfo
+        frame.rdx = (sp + size_of::<u64>()) as u64; // ucontext_t *ctx
+        frame.rax = 0;
+        // Clear the direction flag and the trap flag.
+        frame.rflags &= !(RFLAGS_DF | RFLAGS_TF);
+
+        Ok(())
+    }
+
+    /// Restores the user context saved in [`Process::setup_signal_stack`].
+    /// Returns the signal mask and the alternate signal stack to be restored.
+    pub fn setup_sigreturn_stack(&self, current_frame: &mut PtRegs) -> Result<(SigSet, StackT)> {
+        // The restorer has popped `pretcode`.
+        let uc_addr = UserVAddr::new_nonnull(current_frame.rsp as usize)?;
+        let uc = uc_addr.read::<UContext>()?;
+        uc.uc_mcontext.restore_frame(current_frame);
+
+        // A handler may have cleared `fpstate` to reset the registers.
+        let mut fpstate = FpuState::initial();
+        if let Some(fpstate_addr) = UserVAddr::new(uc.uc_mcontext.fpstate as usize) {
+            fpstate_addr.read_bytes(fpstate.as_bytes_mut())?;
         }
 
-        // Prepare the sigreturn stack in the userspace.
-        let mut user_rsp = UserVAddr::new_nonnull(frame.rsp as usize)?;
+        fpstate.restore();
+        Ok((SigSet::from_raw(uc.uc_sigmask), uc.uc_stack))
+    }
+}
 
-        // Avoid corrupting the red zone.
-        user_rsp = user_rsp.sub(128);
+const RFLAGS_TF: u64 = 1 << 8;
+const RFLAGS_DF: u64 = 1 << 10;
+/// RFLAGS bits which the userspace is allowed to modify: CF, PF, AF, ZF, SF,
+/// TF, DF, OF, and AC.
+const RFLAGS_USER_MASK: u64 = 0x40dd5;
+/// IF and the reserved bit 1.
+const RFLAGS_FIXED: u64 = 0x202;
+
+/// `struct sigcontext`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SigContext {
+    r8: u64,
+    r9: u64,
+    r10: u64,
+    r11: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rdi: u64,
+    rsi: u64,
+    rbp: u64,
+    rbx: u64,
+    rdx: u64,
+    rax: u64,
+    rcx: u64,
+    rsp: u64,
+    rip: u64,
+    eflags: u64,
+    cs: u16,
+    gs: u16,
+    fs: u16,
+    ss: u16,
+    err: u64,
+    trapno: u64,
+    oldmask: u64,
+    cr2: u64,
+    fpstate: u64,
+    reserved: [u64; 8],
+}
 
-        // Copy the trampoline code.
-        user_rsp = user_rsp.sub(TRAMPOLINE.len());
-        let trampoline_rip = user_rsp;
-        user_rsp.write_bytes(TRAMPOLINE)?;
-        user_rsp = push_to_user_stack(user_rsp, trampoline_rip.as_isize() as u64)?;
+impl SigContext {
+    fn from_frame(frame: &PtRegs, oldmask: SigSet, fpstate: UserVAddr) -> SigContext {
+        SigContext {
+            r8: frame.r8,
+            r9: frame.r9,
+            r10: frame.r10,
+            r11: frame.r11,
+            r12: frame.r12,
+            r13: frame.r13,
+            r14: frame.r14,
+            r15: frame.r15,
+            rdi: frame.rdi,
+            rsi: frame.rsi,
+            rbp: frame.rbp,
+            rbx: frame.rbx,
+            rdx: frame.rdx,
+            rax: frame.rax,
+            rcx: frame.rcx,
+            rsp: frame.rsp,
+            rip: frame.rip,
+            eflags: frame.rflags,
+            cs: USER_CS64 | USER_RPL,
+            gs: 0,
+            fs: 0,
+            ss: USER_DS | USER_RPL,
+            err: 0,
+            trapno: 0,
+            oldmask: oldmask.as_raw(),
+            cr2: 0,
+            fpstate: fpstate.value() as u64,
+            reserved: [0; 8],
+        }
+    }
 
-        frame.rip = sa_handler.as_isize() as u64;
-        frame.rsp = user_rsp.as_isize() as u64;
-        frame.rdi = signal as u64; // int signal
-        frame.rsi = 0; // siginfo_t *siginfo
-        frame.rdx = 0; // void *ctx
+    fn restore_frame(&self, frame: &mut PtRegs) {
+        frame.r8 = self.r8;
+        frame.r9 = self.r9;
+        frame.r10 = self.r10;
+        frame.r11 = self.r11;
+        frame.r12 = self.r12;
+        frame.r13 = self.r13;
+        frame.r14 = self.r14;
+        frame.r15 = self.r15;
+        frame.rdi = self.rdi;
+        frame.rsi = self.rsi;
+        frame.rbp = self.rbp;
+        frame.rbx = self.rbx;
+        frame.rdx = self.rdx;
+        frame.rax = self.rax;
+        frame.rcx = self.rcx;
+        frame.rsp = self.rsp;
+        frame.rip = self.rip;
+        // Don't allow the userspace to disable interrupts or so.
+        frame.rflags = (self.eflags & RFLAGS_USER_MASK) | RFLAGS_FIXED;
+        // Not in a system call: it must not be restarted.
+        frame.orig_rax = NOT_IN_SYSCALL;
+    }
+}
 
-        Ok(())
+/// `struct ucontext`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UContext {
+    uc_flags: u64,
+    uc_link: u64,
+    uc_stack: StackT,
+    uc_mcontext: SigContext,
+    uc_sigmask: u64,
+}
+
+/// `struct rt_sigframe`: the stack frame on the signal handler entry.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SigFrame {
+    /// The return address of the signal handler.
+    pretcode: u64,
+    uc: UContext,
+    info: [u8; SIGINFO_SIZE],
+}
+
+fn offset_of_info() -> usize {
+    size_of::<u64>() + size_of::<UContext>()
+}
+
+/// `orig_rax` of a frame which is not in a system call.
+const NOT_IN_SYSCALL: u64 = u64::MAX;
+/// The length of the `syscall` instruction.
+const SYSCALL_INSN_LEN: u64 = 2;
+
+/// Returns the return value of the system call being returned, or `None` if
+/// the frame is not in a system call.
+pub fn syscall_return_value(frame: &PtRegs) -> Option<isize> {
+    if frame.orig_rax == NOT_IN_SYSCALL {
+        None
+    } else {
+        Some(frame.rax as isize)
     }
+}
+
+/// Sets the return value of the system call.
+pub fn set_syscall_return_value(frame: &mut PtRegs, value: isize) {
+    frame.rax = value as u64;
+}
+
+/// Makes the system call return path restore all registers from `frame`.
+/// `SYSRETQ` overwrites RCX and R11, and ignores the RAX in `frame`.
+pub fn force_full_restore(frame: &mut PtRegs) {
+    frame.orig_rax = NOT_IN_SYSCALL;
+}
+
+/// Makes the thread execute the system call again once it returns to the
+/// userspace.
+pub fn restart_syscall(frame: &mut PtRegs) {
+    frame.rip -= SYSCALL_INSN_LEN;
+    frame.rax = frame.orig_rax;
+}
+
+/// Returns the system call number and its arguments saved in `frame`.
+pub fn syscall_number_and_args(frame: &PtRegs) -> (usize, [usize; 6]) {
+    let args = [
+        frame.rdi as usize,
+        frame.rsi as usize,
+        frame.rdx as usize,
+        frame.r10 as usize,
+        frame.r8 as usize,
+        frame.r9 as usize,
+    ];
+    (frame.orig_rax as usize, args)
+}
 
-    pub fn setup_sigreturn_stack(&self, current_frame: &mut PtRegs, signaled_frame: &PtRegs) {
-        *current_frame = *signaled_frame;
+/// Returns the address of the instruction following the system call.
+pub fn syscall_instruction_pointer(frame: &PtRegs) -> usize {
+    frame.rip as usize
+}
+
+/// Enables or disables single-stepping (the trap flag) once the thread
+/// returns to the userspace.
+pub fn set_single_step(frame: &mut PtRegs, enable: bool) {
+    if enable {
+        frame.rflags |= RFLAGS_TF;
+    } else {
+        frame.rflags &= !RFLAGS_TF;
+    }
+}
+
+/// The end of the lower half of the canonical address space.
+const USER_ADDR_END: u64 = 0x0000_8000_0000_0000;
+
+/// `struct user_regs_struct`: the user registers accessed through
+/// `ptrace(2)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRegs {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl Process {
+    /// Returns the user registers of a stopped thread. `frame` is its saved
+    /// user context.
+    pub fn user_regs(&self, frame: &PtRegs) -> UserRegs {
+        UserRegs {
+            r15: frame.r15,
+            r14: frame.r14,
+            r13: frame.r13,
+            r12: frame.r12,
+            rbp: frame.rbp,
+            rbx: frame.rbx,
+            r11: frame.r11,
+            r10: frame.r10,
+            r9: frame.r9,
+            r8: frame.r8,
+            rax: frame.rax,
+            rcx: frame.rcx,
+            rdx: frame.rdx,
+            rsi: frame.rsi,
+            rdi: frame.rdi,
+            orig_rax: frame.orig_rax,
+            rip: frame.rip,
+            cs: (USER_CS64 | USER_RPL) as u64,
+            eflags: frame.rflags,
+            rsp: frame.rsp,
+            ss: (USER_DS | USER_RPL) as u64,
+            fs_base: self.fsbase.load(),
+            gs_base: 0,
+            ds: 0,
+            es: 0,
+            fs: 0,
+            gs: 0,
+        }
+    }
+
+    /// Updates the user registers of a stopped thread. Segment registers are
+    /// left as they are.
+    pub fn set_user_regs(&self, frame: &mut PtRegs, regs: &UserRegs) -> Result<()> {
+        // SYSRET to a non-canonical address causes a fault in the kernel.
+        if regs.rip >= USER_ADDR_END || regs.fs_base >= USER_ADDR_END {
+            return Err(Errno::EIO.into());
+        }
+
+        frame.r15 = regs.r15;
+        frame.r14 = regs.r14;
+        frame.r13 = regs.r13;
+        frame.r12 = regs.r12;
+        frame.rbp = regs.rbp;
+        frame.rbx = regs.rbx;
+        frame.r11 = regs.r11;
+        frame.r10 = regs.r10;
+        frame.r9 = regs.r9;
+        frame.r8 = regs.r8;
+        frame.rax = regs.rax;
+        frame.rcx = regs.rcx;
+        frame.rdx = regs.rdx;
+        frame.rsi = regs.rsi;
+        frame.rdi = regs.rdi;
+        frame.orig_rax = regs.orig_rax;
+        frame.rip = regs.rip;
+        frame.rflags = (regs.eflags & RFLAGS_USER_MASK) | RFLAGS_FIXED;
+        frame.rsp = regs.rsp;
+        self.fsbase.store(regs.fs_base);
+        Ok(())
     }
 }
 
@@ -312,7 +715,8 @@ pub fn switch_thread(prev: &Process, next: &Process) {
     head.rsp3 = 0xbaad_5a5a_5b5b_baad;
 
     unsafe {
+        prev.fsbase.store(rdfsbase());
         wrfsbase(next.fsbase.load());
-        do_switch_thread(prev.rsp.get(), next.rsp.get());
+        do_switch_thread(prev.rsp.get(), next.rsp.get(), prev.running.as_ptr());
     }
 }
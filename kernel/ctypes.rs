@@ -18,12 +18,15 @@ pub type c_ulong = c_uint64;
 pub type c_time = c_int64;
 pub type c_suseconds = c_int64;
 pub type c_clockid = c_int;
+pub type c_clock = c_long;
 pub type c_nfds = c_ulong;
 pub type c_size = c_ulong;
 pub type c_off = c_uint64;
 
 pub const CLOCK_REALTIME: c_clockid = 0;
 pub const CLOCK_MONOTONIC: c_clockid = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: c_clockid = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: c_clockid = 3;
 
 bitflags! {
     pub struct MMapProt: c_int {
@@ -33,8 +36,32 @@ bitflags! {
     }
 }
 
+bitflags! {
+    pub struct CloneFlags: c_int {
+        const CLONE_VM             = 0x100;
+        const CLONE_FS             = 0x200;
+        const CLONE_FILES          = 0x400;
+        const CLONE_SIGHAND        = 0x800;
+        const CLONE_PTRACE         = 0x2000;
+        const CLONE_VFORK          = 0x4000;
+        const CLONE_PARENT         = 0x8000;
+        const CLONE_THREAD         = 0x10000;
+        const CLONE_NEWNS          = 0x20000;
+        const CLONE_SYSVSEM        = 0x40000;
+        const CLONE_SETTLS         = 0x80000;
+        const CLONE_PARENT_SETTID  = 0x100000;
+        const CLONE_CHILD_CLEARTID = 0x200000;
+        const CLONE_DETACHED       = 0x400000;
+        const CLONE_UNTRACED       = 0x800000;
+        const CLONE_CHILD_SETTID   = 0x1000000;
+        const CLONE_NEWUTS         = 0x4000000;
+        const CLONE_NEWPID         = 0x20000000;
+    }
+}
+
 bitflags! {
     pub struct MMapFlags: c_int {
+        const MAP_SHARED    = 0x01;
         const MAP_PRIVATE   = 0x02;
         const MAP_FIXED     = 0x10;
         const MAP_ANONYMOUS = 0x20;
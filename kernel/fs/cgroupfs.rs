@@ -0,0 +1,391 @@
+//! The cgroup file system (cgroup v2).
+//!
+//! Each directory represents a control group. Create a directory to create a
+//! child group, write a PID to its `cgroup.procs` to move the process into
+//! the group, and write limits into the controller files.
+use core::{
+    cmp::min,
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    fs::{
+        file_system::FileSystem,
+        inode::{DirEntry, Directory, FileLike, FileType, INode, INodeNo, RenameFlags},
+        opened_file::OpenOptions,
+        path::Path,
+        stat::{FileMode, GId, Stat, UId, S_IFDIR, S_IFREG},
+    },
+    prelude::*,
+    process::{cgroup::Cgroup, current_process, PId},
+    user_buffer::{UserBufReader, UserBufWriter, UserBuffer, UserBufferMut},
+};
+
+const CONTROL_LINE_MAX: usize = 256;
+/// Controllers available in every group.
+const CONTROLLERS: &[&str] = &["cpu", "memory", "pids"];
+
+/// An interface file in a cgroup directory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ControlFile {
+    Procs,
+    Controllers,
+    SubtreeControl,
+    CpuWeight,
+    MemoryCurrent,
+    MemoryMax,
+    MemoryEvents,
+    PidsCurrent,
+    PidsMax,
+}
+
+/// Files in the root group. Resources are not limited in the root.
+const ROOT_CONTROL_FILES: &[ControlFile] = &[
+    ControlFile::Procs,
+    ControlFile::Controllers,
+    ControlFile::SubtreeControl,
+];
+
+const CONTROL_FILES: &[ControlFile] = &[
+    ControlFile::Procs,
+    ControlFile::Controllers,
+    ControlFile::SubtreeControl,
+    ControlFile::CpuWeight,
+    ControlFile::MemoryCurrent,
+    ControlFile::MemoryMax,
+    ControlFile::MemoryEvents,
+    ControlFile::PidsCurrent,
+    ControlFile::PidsMax,
+];
+
+impl ControlFile {
+    fn name(self) -> &'static str {
+        match self {
+            ControlFile::Procs => "cgroup.procs",
+            ControlFile::Controllers => "cgroup.controllers",
+            ControlFile::SubtreeControl => "cgroup.subtree_control",
+            ControlFile::CpuWeight => "cpu.weight",
+            ControlFile::MemoryCurrent => "memory.current",
+            ControlFile::MemoryMax => "memory.max",
+            ControlFile::MemoryEvents => "memory.events",
+            ControlFile::PidsCurrent => "pids.current",
+            ControlFile::PidsMax => "pids.max",
+        }
+    }
+
+    fn is_writable(self) -> bool {
+        matches!(
+            self,
+            ControlFile::Procs
+                | ControlFile::SubtreeControl
+                | ControlFile::CpuWeight
+                | ControlFile::MemoryMax
+                | ControlFile::PidsMax
+        )
+    }
+}
+
+fn control_files(cgroup: &Cgroup) -> &'static [ControlFile] {
+    if cgroup.is_root() {
+        ROOT_CONTROL_FILES
+    } else {
+        CONTROL_FILES
+    }
+}
+
+/// Inode numbers are derived from the group ID: the directory is `id << 4` and
+/// its files follow it.
+fn inode_no(cgroup: &Cgroup, file: Option<ControlFile>) -> INodeNo {
+    let index = match file {
+        Some(file) => CONTROL_FILES.iter().position(|f| *f == file).unwrap() + 1,
+        None => 0,
+    };
+
+    INodeNo::new((cgroup.id() << 4) | index)
+}
+
+/// Parses a limit: `max` or a number optionally followed by `K`, `M`, or `G`
+/// if `allow_suffix` is true.
+fn parse_limit(value: &str, allow_suffix: bool) -> Result<Option<usize>> {
+    if value == "max" {
+        return Ok(None);
+    }
+
+    let (digits, unit) = match value.as_bytes().last() {
+        Some(b'K' | b'k') if allow_suffix => (&value[..value.len() - 1], 1024),
+        Some(b'M' | b'm') if allow_suffix => (&value[..value.len() - 1], 1024 * 1024),
+        Some(b'G' | b'g') if allow_suffix => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    usize::from_str(digits)
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .map(Some)
+        .ok_or_else(|| Error::new(Errno::EINVAL))
+}
+
+fn format_limit(limit: Option<usize>) -> String {
+    match limit {
+        Some(limit) => limit.to_string(),
+        None => "max".to_owned(),
+    }
+}
+
+pub struct CgroupFs {}
+
+impl CgroupFs {
+    pub fn new() -> CgroupFs {
+        CgroupFs {}
+    }
+}
+
+impl FileSystem for CgroupFs {
+    fn root_dir(&self) -> Result<Arc<dyn Directory>> {
+        Ok(Arc::new(CgroupDir::new(Cgroup::root())))
+    }
+}
+
+/// A directory representing a control group.
+struct CgroupDir {
+    cgroup: Arc<Cgroup>,
+}
+
+impl CgroupDir {
+    fn new(cgroup: Arc<Cgroup>) -> CgroupDir {
+        CgroupDir { cgroup }
+    }
+}
+
+impl fmt::Debug for CgroupDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CgroupDir")
+            .field("path", &self.cgroup.path())
+            .finish()
+    }
+}
+
+impl Directory for CgroupDir {
+    fn lookup(&self, name: &str) -> Result<INode> {
+        if let Some(file) = control_files(&self.cgroup)
+            .iter()
+            .find(|file| file.name() == name)
+        {
+            let file = Arc::new(CgroupControlFile {
+                cgroup: self.cgroup.clone(),
+                file: *file,
+            });
+            return Ok((file as Arc<dyn FileLike>).into());
+        }
+
+        match self.cgroup.child(name) {
+            Some(child) => Ok((Arc::new(CgroupDir::new(child)) as Arc<dyn Directory>).into()),
+            None => Err(Errno::ENOENT.into()),
+        }
+    }
+
+    fn create_file(&self, _name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode> {
+        Err(Errno::EPERM.into())
+    }
+
+    fn create_dir(&self, name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode> {
+        if CONTROL_FILES.iter().any(|file| file.name() == name) {
+            return Err(Errno::EEXIST.into());
+        }
+
+        let child = self.cgroup.create_child(name)?;
+        Ok((Arc::new(CgroupDir::new(child)) as Arc<dyn Directory>).into())
+    }
+
+    fn stat(&self) -> Result<Stat> {
+        Ok(Stat {
+            inode_no: inode_no(&self.cgroup, None),
+            mode: FileMode::new(S_IFDIR | 0o755),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>> {
+        let files = control_files(&self.cgroup);
+        if let Some(file) = files.get(index) {
+            return Ok(Some(DirEntry {
+                inode_no: inode_no(&self.cgroup, Some(*file)),
+                file_type: FileType::Regular,
+                name: file.name().to_owned(),
+            }));
+        }
+
+        let entry = self
+            .cgroup
+            .child_names()
+            .into_iter()
+            .nth(index - files.len())
+            .and_then(|name| {
+                let child = self.cgroup.child(&name)?;
+                Some(DirEntry {
+                    inode_no: inode_no(&child, None),
+                    file_type: FileType::Directory,
+                    name,
+                })
+            });
+
+        Ok(entry)
+    }
+
+    fn create_symlink(&self, _name: &str, _target: &Path, _uid: UId, _gid: GId) -> Result<INode> {
+        Err(Errno::EPERM.into())
+    }
+
+    fn link(&self, _name: &str, _link_to: &INode) -> Result<()> {
+        Err(Errno::EPERM.into())
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        Err(Errno::EPERM.into())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        if control_files(&self.cgroup)
+            .iter()
+            .any(|file| file.name() == name)
+        {
+            return Err(Errno::ENOTDIR.into());
+        }
+
+        self.cgroup.remove_child(name)
+    }
+
+    fn rename(
+        &self,
+        _old_name: &str,
+        _new_dir: &Arc<dyn Directory>,
+        _new_name: &str,
+        _flags: RenameFlags,
+    ) -> Result<()> {
+        Err(Errno::EPERM.into())
+    }
+}
+
+/// An interface file such as `cgroup.procs` and `memory.max`.
+struct CgroupControlFile {
+    cgroup: Arc<Cgroup>,
+    file: ControlFile,
+}
+
+impl CgroupControlFile {
+    fn contents(&self) -> String {
+        let cgroup = &self.cgroup;
+        match self.file {
+            ControlFile::Procs => {
+                let pid_ns = current_process().pid_ns().clone();
+                let mut procs = String::new();
+                for pid in cgroup.procs(&pid_ns) {
+                    let _ = writeln!(procs, "{}", pid.as_i32());
+                }
+                procs
+            }
+            // All controllers are always enabled.
+            ControlFile::Controllers | ControlFile::SubtreeControl => {
+                format!("{}\n", CONTROLLERS.join(" "))
+            }
+            ControlFile::CpuWeight => format!("{}\n", cgroup.cpu_weight()),
+            ControlFile::MemoryCurrent => format!("{}\n", cgroup.memory_current()),
+            ControlFile::MemoryMax => format!("{}\n", format_limit(cgroup.memory_max())),
+            ControlFile::MemoryEvents => format!("oom_kill {}\n", cgroup.oom_kills()),
+            ControlFile::PidsCurrent => format!("{}\n", cgroup.pids_current()),
+            ControlFile::PidsMax => format!("{}\n", format_limit(cgroup.pids_max())),
+        }
+    }
+
+    fn update(&self, value: &str) -> Result<()> {
+        let cgroup = &self.cgroup;
+        match self.file {
+            ControlFile::Procs => {
+                let pid = i32::from_str(value).map_err(|_| Error::new(Errno::EINVAL))?;
+                let current = current_process();
+                let proc = if pid == 0 {
+                    current.clone()
+                } else {
+                    current
+                        .pid_ns()
+                        .find(PId::new(pid))
+                        .ok_or_else(|| Error::new(Errno::ESRCH))?
+                };
+
+                cgroup.attach(&proc);
+            }
+            ControlFile::SubtreeControl => {
+                for word in value.split_whitespace() {
+                    match word.strip_prefix('+') {
+                        Some(name) if CONTROLLERS.contains(&name) => {}
+                        _ => {
+                            debug_warn!("cgroup: controllers can't be disabled: {}", word);
+                            return Err(Errno::EINVAL.into());
+                        }
+                    }
+                }
+            }
+            ControlFile::CpuWeight => {
+                let weight = u64::from_str(value).map_err(|_| Error::new(Errno::EINVAL))?;
+                cgroup.set_cpu_weight(weight)?;
+            }
+            ControlFile::MemoryMax => cgroup.set_memory_max(parse_limit(value, true)?),
+            ControlFile::PidsMax => cgroup.set_pids_max(parse_limit(value, false)?),
+            _ => return Err(Errno::EINVAL.into()),
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CgroupControlFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CgroupControlFile")
+            .field("path", &self.cgroup.path())
+            .field("name", &self.file.name())
+            .finish()
+    }
+}
+
+impl FileLike for CgroupControlFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn stat(&self) -> Result<Stat> {
+        let perm = if self.file.is_writable() {
+            0o644
+        } else {
+            0o444
+        };
+        Ok(Stat {
+            inode_no: inode_no(&self.cgroup, Some(self.file)),
+            mode: FileMode::new(S_IFREG | perm),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn read(&self, offset: usize, buf: UserBufferMut<'_>, _options: &OpenOptions) -> Result<usize> {
+        let contents = self.contents();
+        if offset > contents.len() {
+            return Ok(0);
+        }
+
+        let mut writer = UserBufWriter::from(buf);
+        writer.write_bytes(&contents.as_bytes()[offset..])
+    }
+
+    fn write(&self, _offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
+        if !self.file.is_writable() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let mut line = vec![0; min(buf.len(), CONTROL_LINE_MAX)];
+        let mut reader = UserBufReader::from(buf);
+        let len = reader.read_bytes(&mut line)?;
+        let line = core::str::from_utf8(&line[..len]).map_err(|_| Errno::EINVAL)?;
+        self.update(line.trim())?;
+        Ok(len)
+    }
+}
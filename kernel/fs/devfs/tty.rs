@@ -2,24 +2,23 @@ use arrayvec::ArrayString;
 use core::fmt;
 
 use crate::{
-    ctypes::*,
     fs::{
         inode::{FileLike, INodeNo},
         opened_file::OpenOptions,
         stat::{FileMode, Stat, S_IFCHR},
     },
     prelude::*,
-    process::process_group::{PgId, ProcessGroup},
+    process::{process_group::ProcessGroup, session::Session},
     result::Result,
     tty::line_discipline::*,
     user_buffer::UserBuffer,
     user_buffer::{UserBufReader, UserBufferMut},
 };
-use kerla_runtime::{address::UserVAddr, print::get_printer, spinlock::SpinLock};
+use kerla_runtime::{print::get_printer, spinlock::SpinLock};
 
 pub struct Tty {
     name: ArrayString<8>,
-    discipline: LineDiscipline,
+    discipline: Arc<LineDiscipline>,
 }
 
 impl Tty {
@@ -28,7 +27,7 @@ impl Tty {
         let _ = name_buf.try_push_str(name);
         Tty {
             name: name_buf,
-            discipline: LineDiscipline::new(),
+            discipline: Arc::new(LineDiscipline::new()),
         }
     }
 
@@ -49,13 +48,17 @@ impl Tty {
             .ok();
     }
 
-    pub fn set_foreground_process_group(&self, pg: Weak<SpinLock<ProcessGroup>>) {
-        self.discipline.set_foreground_process_group(pg);
+    /// Makes the tty the controlling terminal of `session` and `pg` the
+    /// foreground process group.
+    pub fn set_controlling_session(
+        &self,
+        session: &Arc<SpinLock<Session>>,
+        pg: &Arc<SpinLock<ProcessGroup>>,
+    ) {
+        self.discipline.set_controlling_session(session, pg);
     }
 }
 
-const TIOCGPGRP: usize = 0x540f;
-const TIOCSPGRP: usize = 0x5410;
 const TIOCGWINSZ: usize = 0x5413;
 
 impl fmt::Debug for Tty {
@@ -67,29 +70,11 @@ impl fmt::Debug for Tty {
 impl FileLike for Tty {
     fn ioctl(&self, cmd: usize, arg: usize) -> Result<isize> {
         match cmd {
-            TIOCGPGRP => {
-                let process_group = self
-                    .discipline
-                    .foreground_process_group()
-                    .ok_or_else(|| Error::new(Errno::ENOENT))?;
-
-                let pgid = process_group.lock().pgid().as_i32();
-                let arg = UserVAddr::new_nonnull(arg)?;
-                arg.write::<c_int>(&pgid)?;
-            }
-            TIOCSPGRP => {
-                let arg = UserVAddr::new_nonnull(arg)?;
-                let pgid = arg.read::<c_int>()?;
-                let pg = ProcessGroup::find_by_pgid(PgId::new(pgid))
-                    .ok_or_else(|| Error::new(Errno::ESRCH))?;
-                self.discipline
-                    .set_foreground_process_group(Arc::downgrade(&pg));
-            }
             TIOCGWINSZ => {
                 // TODO: It's not yet implemented but should return a successful
                 //       value since it is used in musl's isatty(3).
             }
-            _ => return Err(Errno::ENOSYS.into()),
+            _ => return self.discipline.ioctl(cmd, arg),
         }
 
         Ok(0)
@@ -113,6 +98,8 @@ impl FileLike for Tty {
     }
 
     fn write(&self, _offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
+        self.discipline.check_write_access()?;
+
         let mut tmp = [0; 32];
         let mut total_len = 0;
         let mut reader = UserBufReader::from(buf);
@@ -3,10 +3,12 @@
 use crate::{
     fs::{
         file_system::FileSystem,
-        inode::{DirEntry, Directory, FileLike, FileType, INode, INodeNo},
+        inode::{
+            DirEntry, Directory, FallocFlags, FileLike, FileType, INode, INodeNo, RenameFlags,
+        },
         path::Path,
         stat::FileMode,
-        stat::{FileSize, Stat, S_IFDIR},
+        stat::{FileSize, GId, NLink, Stat, Time, UId, S_IFDIR},
     },
     prelude::*,
     user_buffer::{UserBufWriter, UserBuffer, UserBufferMut},
@@ -36,6 +38,10 @@ struct InitramFsFile {
 }
 
 impl FileLike for InitramFsFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
     fn read(&self, offset: usize, buf: UserBufferMut<'_>, _options: &OpenOptions) -> Result<usize> {
         if offset > self.data.len() {
             return Ok(0);
@@ -49,9 +55,29 @@ impl FileLike for InitramFsFile {
         Err(Error::new(Errno::EROFS))
     }
 
+    fn truncate(&self, _length: usize) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn fallocate(&self, _flags: FallocFlags, _offset: usize, _len: usize) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
     fn stat(&self) -> Result<Stat> {
         Ok(self.stat)
     }
+
+    fn chown(&self, _uid: UId, _gid: GId) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn chmod(&self, _mode: FileMode) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn set_times(&self, _atime: Option<Time>, _mtime: Option<Time>) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
 }
 
 impl fmt::Debug for InitramFsFile {
@@ -79,7 +105,25 @@ impl Directory for InitramFsDir {
     }
 
     fn link(&self, _name: &str, _link_to: &INode) -> Result<()> {
-        Err(Error::new(Errno::ENOSYS))
+        Err(Errno::EROFS.into())
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn rename(
+        &self,
+        _old_name: &str,
+        _new_dir: &Arc<dyn Directory>,
+        _new_name: &str,
+        _flags: RenameFlags,
+    ) -> Result<()> {
+        Err(Errno::EROFS.into())
     }
 
     fn readdir(&self, index: usize) -> Result<Option<DirEntry>> {
@@ -116,11 +160,27 @@ impl Directory for InitramFsDir {
         })
     }
 
-    fn create_file(&self, _name: &str, _mode: FileMode) -> Result<INode> {
+    fn create_file(&self, _name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn create_dir(&self, _name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn create_symlink(&self, _name: &str, _target: &Path, _uid: UId, _gid: GId) -> Result<INode> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn chown(&self, _uid: UId, _gid: GId) -> Result<()> {
+        Err(Errno::EROFS.into())
+    }
+
+    fn chmod(&self, _mode: FileMode) -> Result<()> {
         Err(Errno::EROFS.into())
     }
 
-    fn create_dir(&self, _name: &str, _mode: FileMode) -> Result<INode> {
+    fn set_times(&self, _atime: Option<Time>, _mtime: Option<Time>) -> Result<()> {
         Err(Errno::EROFS.into())
     }
 }
@@ -178,10 +238,11 @@ impl InitramFs {
 
             let ino = parse_hex_field(image.consume_bytes(8).unwrap());
             let mode = FileMode::new(parse_hex_field(image.consume_bytes(8).unwrap()) as u32);
-            let _uid = parse_hex_field(image.consume_bytes(8).unwrap());
-            let _gid = parse_hex_field(image.consume_bytes(8).unwrap());
-            let _nlink = parse_hex_field(image.consume_bytes(8).unwrap());
-            let _mtime = parse_hex_field(image.consume_bytes(8).unwrap());
+            let uid = UId::new(parse_hex_field(image.consume_bytes(8).unwrap()) as u32);
+            let gid = GId::new(parse_hex_field(image.consume_bytes(8).unwrap()) as u32);
+            let nlink = NLink::new(parse_hex_field(image.consume_bytes(8).unwrap()));
+            let mtime_secs = parse_hex_field(image.consume_bytes(8).unwrap());
+            let mtime = Time::from_nanosecs(mtime_secs as u64 * 1_000_000_000);
             let filesize = parse_hex_field(image.consume_bytes(8).unwrap());
             let _dev_major = parse_hex_field(image.consume_bytes(8).unwrap());
             let _dev_minor = parse_hex_field(image.consume_bytes(8).unwrap());
@@ -249,7 +310,13 @@ impl InitramFs {
                         filename,
                         stat: Stat {
                             inode_no: INodeNo::new(ino),
+                            nlink,
                             mode,
+                            uid,
+                            gid,
+                            atime: mtime,
+                            mtime,
+                            ctime: mtime,
                             ..Stat::zeroed()
                         },
                         dst: PathBuf::from(core::str::from_utf8(data).unwrap()),
@@ -264,7 +331,13 @@ impl InitramFs {
                         files: HashMap::new(),
                         stat: Stat {
                             inode_no: INodeNo::new(ino),
+                            nlink,
                             mode,
+                            uid,
+                            gid,
+                            atime: mtime,
+                            mtime,
+                            ctime: mtime,
                             ..Stat::zeroed()
                         },
                     })),
@@ -278,7 +351,13 @@ impl InitramFs {
                         data,
                         stat: Stat {
                             inode_no: INodeNo::new(ino),
+                            nlink,
                             mode,
+                            uid,
+                            gid,
+                            atime: mtime,
+                            mtime,
+                            ctime: mtime,
                             size: FileSize(filesize as isize),
                             ..Stat::zeroed()
                         },
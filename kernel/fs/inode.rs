@@ -1,7 +1,12 @@
 use core::fmt::{self, Debug};
 
-use super::{opened_file::OpenOptions, path::PathBuf, stat::FileMode};
-use crate::ctypes::c_short;
+use super::{
+    opened_file::OpenOptions,
+    path::{Path, PathBuf},
+    stat::{FileMode, GId, Time, UId},
+};
+use crate::ctypes::{c_int, c_short, c_uint};
+use crate::mm::page_cache;
 use crate::prelude::*;
 use crate::{fs::stat::Stat, user_buffer::UserBufferMut};
 use crate::{net::*, user_buffer::UserBuffer};
@@ -38,6 +43,26 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags for `renameat2(2)`.
+    pub struct RenameFlags: c_uint {
+        /// Fail with `EEXIST` if the destination exists.
+        const RENAME_NOREPLACE = 1 << 0;
+        /// Atomically exchange the source and the destination.
+        const RENAME_EXCHANGE  = 1 << 1;
+    }
+}
+
+bitflags! {
+    /// Flags for `fallocate(2)`.
+    pub struct FallocFlags: c_int {
+        /// Don't change the file size even if the range exceeds it.
+        const FALLOC_FL_KEEP_SIZE  = 1 << 0;
+        /// Fill the range with zeroes. Requires `FALLOC_FL_KEEP_SIZE`.
+        const FALLOC_FL_PUNCH_HOLE = 1 << 1;
+    }
+}
+
 /// A file-like object.
 ///
 /// This trait represents an object which behaves like a file such as files on
@@ -53,6 +78,12 @@ pub trait FileLike: Debug + Send + Sync + Downcastable {
         Err(Error::new(Errno::EBADF))
     }
 
+    /// Returns `true` if `lseek(2)` and positional I/O are supported. Pipes,
+    /// sockets, ttys and devices are not seekable.
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
     /// `readlink(2)`.
     fn readlink(&self) -> Result<PathBuf> {
         // "EINVAL - The named file is not a symbolic link." -- readlink(2)
@@ -69,6 +100,47 @@ pub trait FileLike: Debug + Send + Sync + Downcastable {
         Err(Error::new(Errno::EBADF))
     }
 
+    /// `chown(2)`. File systems which don't store the owner ignore it.
+    fn chown(&self, _uid: UId, _gid: GId) -> Result<()> {
+        Ok(())
+    }
+
+    /// `chmod(2)`. Only the permission bits in `mode` are used. File systems
+    /// which don't store the mode ignore it.
+    fn chmod(&self, _mode: FileMode) -> Result<()> {
+        Ok(())
+    }
+
+    /// `utimensat(2)`. `None` leaves the timestamp unchanged. File systems
+    /// which don't store timestamps ignore it.
+    fn set_times(&self, _atime: Option<Time>, _mtime: Option<Time>) -> Result<()> {
+        Ok(())
+    }
+
+    /// `truncate(2)`. Only regular files support it.
+    fn truncate(&self, _length: usize) -> Result<()> {
+        Err(Error::new(Errno::EINVAL))
+    }
+
+    /// `fallocate(2)`. Only regular files support it.
+    fn fallocate(&self, _flags: FallocFlags, _offset: usize, _len: usize) -> Result<()> {
+        Err(Error::new(Errno::ENODEV))
+    }
+
+    /// `lseek(2)` with `SEEK_DATA` (`hole` is false) or `SEEK_HOLE`. Returns
+    /// the next data or hole at or after `offset`.
+    ///
+    /// By default, the whole file is data: there's only the implicit hole at
+    /// the end of the file.
+    fn seek_data(&self, offset: usize, hole: bool) -> Result<usize> {
+        let size = self.stat()?.size.0 as usize;
+        if offset >= size {
+            return Err(Errno::ENXIO.into());
+        }
+
+        Ok(if hole { size } else { offset })
+    }
+
     /// `read(2)`.
     fn read(
         &self,
@@ -84,6 +156,19 @@ pub trait FileLike: Debug + Send + Sync + Downcastable {
         Err(Error::new(Errno::EBADF))
     }
 
+    /// `write(2)` with `O_APPEND`. Returns the offset where the data is
+    /// written and the written length. Files which hold data override it to
+    /// look up the end of the file and write to it atomically.
+    fn append(&self, buf: UserBuffer<'_>, options: &OpenOptions) -> Result<(usize, usize)> {
+        let offset = if self.is_seekable() {
+            self.stat()?.size.0 as usize
+        } else {
+            0
+        };
+
+        Ok((offset, self.write(offset, buf, options)?))
+    }
+
     /// `bind(2)`.
     fn bind(&self, _sockaddr: SockAddr) -> Result<()> {
         Err(Error::new(Errno::EBADF))
@@ -170,20 +255,50 @@ pub struct DirEntry {
 pub trait Directory: Debug + Send + Sync + Downcastable {
     /// Looks for an existing file.
     fn lookup(&self, name: &str) -> Result<INode>;
-    /// Creates a file. Returns `EEXIST` if the it already exists.
-    fn create_file(&self, _name: &str, _mode: FileMode) -> Result<INode>;
-    /// Creates a directory. Returns `EEXIST` if the it already exists.
-    fn create_dir(&self, _name: &str, _mode: FileMode) -> Result<INode>;
+    /// Creates a file owned by `uid` and `gid`. Returns `EEXIST` if the it
+    /// already exists.
+    fn create_file(&self, _name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode>;
+    /// Creates a directory owned by `uid` and `gid`. Returns `EEXIST` if the it
+    /// already exists.
+    fn create_dir(&self, _name: &str, _mode: FileMode, _uid: UId, _gid: GId) -> Result<INode>;
+    /// Creates a symbolic link to `target` owned by `uid` and `gid`. Returns
+    /// `EEXIST` if it already exists.
+    fn create_symlink(&self, _name: &str, _target: &Path, _uid: UId, _gid: GId) -> Result<INode>;
     /// `stat(2)`.
     fn stat(&self) -> Result<Stat>;
     /// `readdir(2)`.
     fn readdir(&self, index: usize) -> Result<Option<DirEntry>>;
     /// `link(2)`.
     fn link(&self, _name: &str, _link_to: &INode) -> Result<()>;
+    /// `unlink(2)`. Returns `EISDIR` if the entry is a directory.
+    fn unlink(&self, _name: &str) -> Result<()>;
+    /// `rmdir(2)`. Returns `ENOTEMPTY` if the directory is not empty.
+    fn rmdir(&self, _name: &str) -> Result<()>;
+    /// `renameat2(2)`: moves `old_name` in this directory to `new_name` in
+    /// `new_dir`. Returns `EXDEV` if `new_dir` is in another file system.
+    fn rename(
+        &self,
+        _old_name: &str,
+        _new_dir: &Arc<dyn Directory>,
+        _new_name: &str,
+        _flags: RenameFlags,
+    ) -> Result<()>;
     /// `fsync(2)`.
     fn fsync(&self) -> Result<()> {
         Ok(())
     }
+    /// `chown(2)`. File systems which don't store the owner ignore it.
+    fn chown(&self, _uid: UId, _gid: GId) -> Result<()> {
+        Ok(())
+    }
+    /// `chmod(2)`. Only the permission bits in `mode` are used.
+    fn chmod(&self, _mode: FileMode) -> Result<()> {
+        Ok(())
+    }
+    /// `utimensat(2)`. `None` leaves the timestamp unchanged.
+    fn set_times(&self, _atime: Option<Time>, _mtime: Option<Time>) -> Result<()> {
+        Ok(())
+    }
     /// `readlink(2)`.
     fn readlink(&self) -> Result<PathBuf> {
         // "EINVAL - The named file is not a symbolic link." -- readlink(2)
@@ -205,6 +320,15 @@ pub trait Symlink: Debug + Send + Sync + Downcastable {
     fn fsync(&self) -> Result<()> {
         Ok(())
     }
+    /// `lchown(2)`. File systems which don't store the owner ignore it.
+    fn chown(&self, _uid: UId, _gid: GId) -> Result<()> {
+        Ok(())
+    }
+    /// `utimensat(2)` with `AT_SYMLINK_NOFOLLOW`. `None` leaves the timestamp
+    /// unchanged.
+    fn set_times(&self, _atime: Option<Time>, _mtime: Option<Time>) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// An inode object.
@@ -277,10 +401,59 @@ impl INode {
         }
     }
 
-    /// `chmod(2)`
-    pub fn chmod(&self, _mode: FileMode) -> Result<()> {
-        // FIXME: Ignore all chmod requests for now.
-        Ok(())
+    /// `truncate(2)`. Cached pages beyond `length` are discarded.
+    pub fn truncate(&self, length: usize) -> Result<()> {
+        match self {
+            INode::FileLike(file) => {
+                // Write back the page which holds the new end of the file.
+                page_cache::sync(file, length, 1)?;
+                file.truncate(length)?;
+                page_cache::reload(file, length, usize::MAX)
+            }
+            INode::Symlink(_) => Err(Error::new(Errno::EINVAL)),
+            INode::Directory(_) => Err(Error::new(Errno::EISDIR)),
+        }
+    }
+
+    /// `fallocate(2)`.
+    pub fn fallocate(&self, flags: FallocFlags, offset: usize, len: usize) -> Result<()> {
+        match self {
+            INode::FileLike(file) => {
+                page_cache::sync(file, offset, len)?;
+                file.fallocate(flags, offset, len)?;
+                page_cache::reload(file, offset, len)
+            }
+            INode::Symlink(_) => Err(Error::new(Errno::ENODEV)),
+            INode::Directory(_) => Err(Error::new(Errno::EISDIR)),
+        }
+    }
+
+    /// `chmod(2)`.
+    pub fn chmod(&self, mode: FileMode) -> Result<()> {
+        match self {
+            INode::FileLike(file) => file.chmod(mode),
+            // The mode of symbolic links is always 0777.
+            INode::Symlink(_) => Err(Error::new(Errno::EOPNOTSUPP)),
+            INode::Directory(dir) => dir.chmod(mode),
+        }
+    }
+
+    /// `chown(2)`.
+    pub fn chown(&self, uid: UId, gid: GId) -> Result<()> {
+        match self {
+            INode::FileLike(file) => file.chown(uid, gid),
+            INode::Symlink(symlink) => symlink.chown(uid, gid),
+            INode::Directory(dir) => dir.chown(uid, gid),
+        }
+    }
+
+    /// `utimensat(2)`. `None` leaves the timestamp unchanged.
+    pub fn set_times(&self, atime: Option<Time>, mtime: Option<Time>) -> Result<()> {
+        match self {
+            INode::FileLike(file) => file.set_times(atime, mtime),
+            INode::Symlink(symlink) => symlink.set_times(atime, mtime),
+            INode::Directory(dir) => dir.set_times(atime, mtime),
+        }
     }
 }
 
@@ -6,16 +6,21 @@ use super::{
     path::Path,
 };
 use crate::prelude::*;
+use crate::process::credentials::{AccessMode, Credentials};
 use crate::syscalls::CwdOrFd;
 
 use hashbrown::HashMap;
 
 const DEFAULT_SYMLINK_FOLLOW_MAX: usize = 8;
 
+#[derive(Clone)]
 pub struct MountPoint {
     fs: Arc<dyn FileSystem>,
 }
 
+/// The root file system and mount points in a mount namespace. A new mount
+/// namespace starts with a copy of them.
+#[derive(Clone)]
 pub struct RootFs {
     root_path: Arc<PathComponent>,
     cwd_path: Arc<PathComponent>,
@@ -47,19 +52,19 @@ impl RootFs {
 
     /// Resolves a path (from the current working directory) into an inode.
     /// This method resolves symbolic links: it will never return `INode::Symlink`.
-    pub fn lookup(&self, path: &Path) -> Result<INode> {
-        self.lookup_inode(path, true)
+    pub fn lookup(&self, path: &Path, creds: &Credentials) -> Result<INode> {
+        self.lookup_inode(path, true, creds)
     }
 
     /// Resolves a path (from the current working directory) into an inode without
     /// following symlinks.
-    pub fn lookup_no_symlink_follow(&self, path: &Path) -> Result<INode> {
-        self.lookup_inode(path, false)
+    pub fn lookup_no_symlink_follow(&self, path: &Path, creds: &Credentials) -> Result<INode> {
+        self.lookup_inode(path, false, creds)
     }
 
     /// Resolves a path (from the current working directory) into an file.
-    pub fn lookup_file(&self, path: &Path) -> Result<Arc<dyn FileLike>> {
-        match self.lookup(path)? {
+    pub fn lookup_file(&self, path: &Path, creds: &Credentials) -> Result<Arc<dyn FileLike>> {
+        match self.lookup(path, creds)? {
             INode::Directory(_) => Err(Error::new(Errno::EISDIR)),
             INode::FileLike(file) => Ok(file),
             // Symbolic links should be already resolved.
@@ -68,8 +73,8 @@ impl RootFs {
     }
 
     /// Resolves a path (from the current working directory) into an directory.
-    pub fn lookup_dir(&self, path: &Path) -> Result<Arc<dyn Directory>> {
-        match self.lookup(path)? {
+    pub fn lookup_dir(&self, path: &Path, creds: &Credentials) -> Result<Arc<dyn Directory>> {
+        match self.lookup(path, creds)? {
             INode::Directory(dir) => Ok(dir),
             INode::FileLike(_) => Err(Error::new(Errno::EISDIR)),
             // Symbolic links should be already resolved.
@@ -78,8 +83,14 @@ impl RootFs {
     }
 
     /// Changes the current working directory.
-    pub fn chdir(&mut self, path: &Path) -> Result<()> {
-        self.cwd_path = self.lookup_path(path, true)?;
+    pub fn chdir(&mut self, path: &Path, creds: &Credentials) -> Result<()> {
+        let path_comp = self.lookup_path(path, true, creds)?;
+        let dir = path_comp
+            .inode
+            .as_dir()
+            .map_err(|_| Error::new(Errno::ENOTDIR))?;
+        creds.check_access(&dir.stat()?, AccessMode::X_OK)?;
+        self.cwd_path = path_comp;
         Ok(())
     }
 
@@ -89,8 +100,13 @@ impl RootFs {
 
     /// Resolves a path into an inode. If `follow_symlink` is `true`, symbolic
     /// linked are resolved and will never return `INode::Symlink`.
-    pub fn lookup_inode(&self, path: &Path, follow_symlink: bool) -> Result<INode> {
-        self.lookup_path(path, follow_symlink)
+    pub fn lookup_inode(
+        &self,
+        path: &Path,
+        follow_symlink: bool,
+        creds: &Credentials,
+    ) -> Result<INode> {
+        self.lookup_path(path, follow_symlink, creds)
             .map(|path_comp| path_comp.inode.clone())
     }
 
@@ -100,9 +116,40 @@ impl RootFs {
         Ok(self.mount_points.get(&inode_no))
     }
 
+    /// Returns `true` if a file system is mounted on `dir`.
+    pub fn is_mount_point(&self, dir: &Arc<dyn Directory>) -> Result<bool> {
+        self.lookup_mount_point(dir)
+            .map(|mount_point| mount_point.is_some())
+    }
+
+    /// Returns the mount point `path` is in: the inode number of the nearest
+    /// ancestor a file system is mounted on, or `None` if it's in the root
+    /// file system.
+    pub fn mount_point_of(&self, path: &PathComponent) -> Result<Option<INodeNo>> {
+        let mut current = path;
+        while let Some(parent) = &current.parent_dir {
+            // The component is the root of a mounted file system if the
+            // entry in the parent directory is a mount point.
+            if let Ok(INode::Directory(dir)) = parent.inode.as_dir()?.lookup(&current.name) {
+                if self.is_mount_point(&dir)? {
+                    return Ok(Some(dir.stat()?.inode_no));
+                }
+            }
+
+            current = parent;
+        }
+
+        Ok(None)
+    }
+
     /// Resolves a path into `PathComponent`. If `follow_symlink` is `true`,
     /// symbolic links are resolved and will never return `INode::Symlink`.
-    pub fn lookup_path(&self, path: &Path, follow_symlink: bool) -> Result<Arc<PathComponent>> {
+    pub fn lookup_path(
+        &self,
+        path: &Path,
+        follow_symlink: bool,
+        creds: &Credentials,
+    ) -> Result<Arc<PathComponent>> {
         let lookup_from = if path.is_absolute() {
             self.root_path.clone()
         } else {
@@ -114,6 +161,7 @@ impl RootFs {
             path,
             follow_symlink,
             self.symlink_follow_limit,
+            creds,
         )
     }
 
@@ -126,12 +174,14 @@ impl RootFs {
         cwd_or_fd: &CwdOrFd,
         path: &Path,
         follow_symlink: bool,
+        creds: &Credentials,
     ) -> Result<Arc<PathComponent>> {
         self.do_lookup_path(
             &self.resolve_cwd_or_fd(opened_files, cwd_or_fd, path)?,
             path,
             follow_symlink,
             self.symlink_follow_limit,
+            creds,
         )
     }
 
@@ -141,14 +191,36 @@ impl RootFs {
         cwd_or_fd: &CwdOrFd,
         path: &'a Path,
         follow_symlink: bool,
+        creds: &Credentials,
     ) -> Result<(Arc<PathComponent>, &'a str)> {
         let (parent_dir, name) = path
             .parent_and_basename()
             .ok_or_else::<Error, _>(|| Errno::EEXIST.into())?;
-        let path = self.lookup_path_at(opened_files, cwd_or_fd, parent_dir, follow_symlink)?;
+        let path =
+            self.lookup_path_at(opened_files, cwd_or_fd, parent_dir, follow_symlink, creds)?;
+        if !path.inode.is_dir() {
+            return Err(Errno::ENOTDIR.into());
+        }
+
         Ok((path, name))
     }
 
+    /// Returns `cwd_or_fd` itself. It's used for an empty path with
+    /// `AT_EMPTY_PATH`.
+    pub fn lookup_cwd_or_fd(
+        &self,
+        opened_files: &OpenedFileTable,
+        cwd_or_fd: &CwdOrFd,
+    ) -> Result<Arc<PathComponent>> {
+        match cwd_or_fd {
+            CwdOrFd::AtCwd => Ok(self.cwd_path.clone()),
+            CwdOrFd::Fd(fd) => {
+                let opened_file = opened_files.get(*fd)?;
+                Ok(opened_file.path().clone())
+            }
+        }
+    }
+
     fn resolve_cwd_or_fd(
         &self,
         opened_files: &OpenedFileTable,
@@ -156,16 +228,15 @@ impl RootFs {
         path: &Path,
     ) -> Result<Arc<PathComponent>> {
         if path.is_absolute() {
-            Ok(self.root_path.clone())
-        } else {
-            match cwd_or_fd {
-                CwdOrFd::AtCwd => Ok(self.cwd_path.clone()),
-                CwdOrFd::Fd(fd) => {
-                    let opened_file = opened_files.get(*fd)?;
-                    Ok(opened_file.path().clone())
-                }
-            }
+            return Ok(self.root_path.clone());
         }
+
+        let path_comp = self.lookup_cwd_or_fd(opened_files, cwd_or_fd)?;
+        if !path_comp.inode.is_dir() {
+            return Err(Errno::ENOTDIR.into());
+        }
+
+        Ok(path_comp)
     }
 
     fn do_lookup_path(
@@ -174,6 +245,7 @@ impl RootFs {
         path: &Path,
         follow_symlink: bool,
         symlink_follow_limit: usize,
+        creds: &Credentials,
     ) -> Result<Arc<PathComponent>> {
         if path.is_empty() {
             return Err(Error::new(Errno::ENOENT));
@@ -194,7 +266,11 @@ impl RootFs {
                     .clone(),
                 // Look for the entry with the name in the directory.
                 _ => {
-                    let inode = match parent_dir.inode.as_dir()?.lookup(name)? {
+                    // Searching a directory requires the execute permission.
+                    let dir = parent_dir.inode.as_dir()?;
+                    creds.check_access(&dir.stat()?, AccessMode::X_OK)?;
+
+                    let inode = match dir.lookup(name)? {
                         // If it is a directory and it's a mount point, go
                         // into the mounted file system's root.
                         INode::Directory(dir) => match self.lookup_mount_point(&dir)? {
@@ -237,6 +313,7 @@ impl RootFs {
                             &linked_to,
                             follow_symlink,
                             symlink_follow_limit - 1,
+                            creds,
                         )?;
 
                         // Check if the desitnation is a directory.
@@ -271,6 +348,7 @@ impl RootFs {
                             &linked_to,
                             follow_symlink,
                             symlink_follow_limit - 1,
+                            creds,
                         );
                     }
                     _ => {
@@ -7,14 +7,17 @@ use super::{
 };
 use crate::ctypes::c_int;
 use crate::fs::inode::PollStatus;
+use crate::mm::page_cache;
 use crate::prelude::*;
 use crate::user_buffer::UserBufferMut;
 use crate::{net::*, user_buffer::UserBuffer};
 use atomic_refcell::AtomicRefCell;
 use bitflags::bitflags;
+use core::cmp::min;
 use crossbeam::atomic::AtomicCell;
 
-const FD_MAX: c_int = 1024;
+/// The maximum number of opened files per process.
+pub const FD_MAX: c_int = 1024;
 
 bitflags! {
     pub struct OpenFlags: i32 {
@@ -28,6 +31,7 @@ bitflags! {
         const O_APPEND = 0o2000;
         const O_NONBLOCK = 0o4000;
         const O_DIRECTORY = 0o200000;
+        const O_NOFOLLOW = 0o400000;
         const O_CLOEXEC  = 0o2000000;
     }
 }
@@ -36,6 +40,10 @@ bitflags! {
 pub struct OpenOptions {
     pub nonblock: bool,
     pub close_on_exec: bool,
+    /// Writes always append data to the end of the file (`O_APPEND`).
+    pub append: bool,
+    /// Opened with `O_WRONLY` or `O_RDWR`.
+    pub writable: bool,
 }
 
 impl OpenOptions {
@@ -43,6 +51,8 @@ impl OpenOptions {
         OpenOptions {
             nonblock,
             close_on_exec: cloexec,
+            append: false,
+            writable: true,
         }
     }
 
@@ -50,6 +60,8 @@ impl OpenOptions {
         OpenOptions {
             nonblock: false,
             close_on_exec: false,
+            append: false,
+            writable: true,
         }
     }
 
@@ -57,6 +69,8 @@ impl OpenOptions {
         OpenOptions {
             nonblock: false,
             close_on_exec: false,
+            append: false,
+            writable: true,
         }
     }
 }
@@ -66,6 +80,36 @@ impl From<OpenFlags> for OpenOptions {
         OpenOptions {
             nonblock: flags.contains(OpenFlags::O_NONBLOCK),
             close_on_exec: flags.contains(OpenFlags::O_CLOEXEC),
+            append: flags.contains(OpenFlags::O_APPEND),
+            writable: flags.intersects(OpenFlags::O_WRONLY | OpenFlags::O_RDWR),
+        }
+    }
+}
+
+/// `whence` in `lseek(2)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekWhence {
+    /// `SEEK_SET`: the offset is relative to the beginning of the file.
+    Set,
+    /// `SEEK_CUR`: the offset is relative to the current position.
+    Current,
+    /// `SEEK_END`: the offset is relative to the end of the file.
+    End,
+    /// `SEEK_DATA`: the next data at or after the offset.
+    Data,
+    /// `SEEK_HOLE`: the next hole at or after the offset.
+    Hole,
+}
+
+impl SeekWhence {
+    pub fn from_user(whence: c_int) -> Result<SeekWhence> {
+        match whence {
+            0 => Ok(SeekWhence::Set),
+            1 => Ok(SeekWhence::Current),
+            2 => Ok(SeekWhence::End),
+            3 => Ok(SeekWhence::Data),
+            4 => Ok(SeekWhence::Hole),
+            _ => Err(Errno::EINVAL.into()),
         }
     }
 }
@@ -184,11 +228,10 @@ impl OpenedFile {
     }
 
     pub fn read(&self, buf: UserBufferMut<'_>) -> Result<usize> {
-        // Avoid holding self.options and self.pos locks by copying.
-        let options = self.options();
+        // Avoid holding self.pos lock by copying.
         let pos = self.pos();
 
-        let read_len = self.as_file()?.read(pos, buf, &options)?;
+        let read_len = self.read_at(pos, buf)?;
         self.pos.fetch_add(read_len);
         Ok(read_len)
     }
@@ -196,23 +239,89 @@ impl OpenedFile {
     pub fn write(&self, buf: UserBuffer<'_>) -> Result<usize> {
         // Avoid holding self.options and self.pos locks by copying.
         let options = self.options();
-        let pos = self.pos();
+        let (pos, written_len) = if options.append {
+            page_cache::append(self.as_file()?, buf, &options)?
+        } else {
+            let pos = self.pos();
+            (pos, self.write_at(pos, buf)?)
+        };
 
-        let written_len = self.as_file()?.write(pos, buf, &options)?;
-        self.pos.fetch_add(written_len);
+        self.pos.store(pos + written_len);
         Ok(written_len)
     }
 
+    /// Reads at `offset` without updating the current position (`pread64(2)`).
+    pub fn read_at(&self, offset: usize, buf: UserBufferMut<'_>) -> Result<usize> {
+        // Avoid holding self.options lock by copying.
+        let options = self.options();
+
+        page_cache::read(self.as_file()?, offset, buf, &options)
+    }
+
+    /// Writes at `offset` without updating the current position
+    /// (`pwrite64(2)`).
+    pub fn write_at(&self, offset: usize, buf: UserBuffer<'_>) -> Result<usize> {
+        // Avoid holding self.options lock by copying.
+        let options = self.options();
+
+        page_cache::write(self.as_file()?, offset, buf, &options)
+    }
+
+    /// Returns `ESPIPE` if the file doesn't have the position (e.g. pipes and
+    /// sockets).
+    pub fn check_seekable(&self) -> Result<()> {
+        match &self.path.inode {
+            INode::FileLike(file) if !file.is_seekable() => Err(Errno::ESPIPE.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// `lseek(2)`. Returns the new position.
+    pub fn seek(&self, offset: isize, whence: SeekWhence) -> Result<usize> {
+        self.check_seekable()?;
+
+        let new_pos = match whence {
+            SeekWhence::Set => Some(offset),
+            SeekWhence::Current => (self.pos() as isize).checked_add(offset),
+            SeekWhence::End => (self.file_size()? as isize).checked_add(offset),
+            SeekWhence::Data | SeekWhence::Hole => {
+                if offset < 0 {
+                    return Err(Errno::ENXIO.into());
+                }
+
+                let file = self.as_file().map_err(|_| Error::new(Errno::EINVAL))?;
+                let pos = file.seek_data(offset as usize, whence == SeekWhence::Hole)?;
+                Some(pos as isize)
+            }
+        };
+
+        let new_pos = match new_pos {
+            Some(pos) if pos >= 0 => pos as usize,
+            Some(_) => return Err(Errno::EINVAL.into()),
+            None => return Err(Errno::EOVERFLOW.into()),
+        };
+
+        self.pos.store(new_pos);
+        Ok(new_pos)
+    }
+
+    fn file_size(&self) -> Result<usize> {
+        let size = self.path.inode.stat()?.size;
+        Ok(size.0 as usize)
+    }
+
     pub fn set_cloexec(&self, cloexec: bool) {
         // FIXME: Modify LocalOpenedFile as well!
         self.options.borrow_mut().close_on_exec = cloexec;
     }
 
     pub fn set_flags(&self, flags: OpenFlags) -> Result<()> {
+        let mut options = self.options.borrow_mut();
         if flags.contains(OpenFlags::O_NONBLOCK) {
-            self.options.borrow_mut().nonblock = true;
+            options.nonblock = true;
         }
 
+        options.append = flags.contains(OpenFlags::O_APPEND);
         Ok(())
     }
 
@@ -302,6 +411,8 @@ struct LocalOpenedFile {
 pub struct OpenedFileTable {
     files: Vec<Option<LocalOpenedFile>>,
     prev_fd: i32,
+    /// File descriptors must be less than this value (`RLIMIT_NOFILE`).
+    fd_limit: c_int,
 }
 
 impl OpenedFileTable {
@@ -309,9 +420,16 @@ impl OpenedFileTable {
         OpenedFileTable {
             files: Vec::new(),
             prev_fd: 1,
+            fd_limit: FD_MAX,
         }
     }
 
+    /// Updates the upper bound of file descriptors (`RLIMIT_NOFILE`).
+    /// Already opened files are kept open.
+    pub fn set_fd_limit(&mut self, limit: usize) {
+        self.fd_limit = min(limit, FD_MAX as usize) as c_int;
+    }
+
     /// Resolves the opened file by the file descriptor.
     pub fn get(&self, fd: Fd) -> Result<&Arc<OpenedFile>> {
         match self.files.get(fd.as_usize()) {
@@ -355,6 +473,10 @@ impl OpenedFileTable {
         mut opened_file: Arc<OpenedFile>,
         options: OpenOptions,
     ) -> Result<()> {
+        if fd.as_int() >= self.fd_limit {
+            return Err(Errno::EBADF.into());
+        }
+
         if let INode::FileLike(file) = &opened_file.path.inode {
             if let Some(new_inode) = file.open(&options)? {
                 // Replace inode if FileLike::open returned Some. Currently it's
@@ -384,9 +506,6 @@ impl OpenedFileTable {
                     close_on_exec: options.close_on_exec,
                 });
             }
-            None if fd.as_int() >= FD_MAX => {
-                return Err(Errno::EBADF.into());
-            }
             None => {
                 self.files.resize(fd.as_usize() + 1, None);
                 self.files[fd.as_usize()] = Some(LocalOpenedFile {
@@ -453,21 +572,20 @@ impl OpenedFileTable {
     /// Allocates an unused fd. Note that this method does not any reservations
     /// for the fd: the caller must register it before unlocking this table.
     fn alloc_fd(&mut self, gte: Option<i32>) -> Result<Fd> {
-        let (mut i, gte) = match gte {
-            Some(gte) => (gte, gte),
-            None => ((self.prev_fd + 1) % FD_MAX, 0),
+        let limit = self.fd_limit;
+        let (first, second) = match gte {
+            Some(gte) => (gte..limit, 0..0),
+            None => (self.prev_fd + 1..limit, 0..min(self.prev_fd, limit)),
         };
 
-        while i != self.prev_fd && i >= gte {
+        for i in first.chain(second) {
             if matches!(self.files.get(i as usize), Some(None) | None) {
                 // It looks the fd number is not in use. Open the file at that fd.
                 return Ok(Fd::new(i));
             }
-
-            i = (i + 1) % FD_MAX;
         }
 
-        Err(Error::new(Errno::ENFILE))
+        Err(Error::new(Errno::EMFILE))
     }
 }
 
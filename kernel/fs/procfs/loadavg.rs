@@ -0,0 +1,67 @@
+use core::fmt;
+
+use crate::{
+    fs::{
+        inode::{FileLike, INodeNo},
+        opened_file::OpenOptions,
+        stat::{FileMode, Stat, S_IFREG},
+    },
+    process::loadavg::read_load_average,
+    result::Result,
+    user_buffer::{UserBufWriter, UserBufferMut},
+};
+
+/// The `/proc/loadavg` file. It returns the load averages over 1, 5, and 15
+/// minutes, and the number of runnable and all processes.
+pub(super) struct LoadAvgFile {}
+
+impl LoadAvgFile {
+    pub fn new() -> LoadAvgFile {
+        LoadAvgFile {}
+    }
+}
+
+impl fmt::Debug for LoadAvgFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadAvg").finish()
+    }
+}
+
+impl FileLike for LoadAvgFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn stat(&self) -> Result<Stat> {
+        Ok(Stat {
+            inode_no: INodeNo::new(3),
+            mode: FileMode::new(S_IFREG | 0o444),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn read(&self, offset: usize, buf: UserBufferMut<'_>, _options: &OpenOptions) -> Result<usize> {
+        use core::fmt::Write;
+
+        if offset > 0 {
+            return Ok(0);
+        }
+
+        let load_avg = read_load_average();
+        let mut writer = UserBufWriter::from(buf);
+        let _ = writeln!(
+            writer,
+            "{}.{:02} {}.{:02} {}.{:02} {}/{}",
+            load_avg.loads[0] / 100,
+            load_avg.loads[0] % 100,
+            load_avg.loads[1] / 100,
+            load_avg.loads[1] % 100,
+            load_avg.loads[2] / 100,
+            load_avg.loads[2] % 100,
+            load_avg.runnable,
+            load_avg.total,
+        );
+
+        Ok(writer.written_len())
+    }
+}
@@ -32,6 +32,10 @@ impl fmt::Debug for MetricsFile {
 }
 
 impl FileLike for MetricsFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
     fn stat(&self) -> Result<Stat> {
         Ok(Stat {
             inode_no: INodeNo::new(2),
@@ -8,14 +8,23 @@ use crate::{
 use alloc::sync::Arc;
 use kerla_utils::once::Once;
 
-use self::metrics::MetricsFile;
+use self::{
+    loadavg::LoadAvgFile,
+    metrics::MetricsFile,
+    syscall_trace::{SyscallTraceControlFile, SyscallTraceFile},
+};
 
 use super::tmpfs::TmpFs;
 
+mod loadavg;
 mod metrics;
+mod syscall_trace;
 
 pub static PROC_FS: Once<Arc<ProcFs>> = Once::new();
 static METRICS_FILE: Once<Arc<dyn FileLike>> = Once::new();
+static LOADAVG_FILE: Once<Arc<dyn FileLike>> = Once::new();
+static SYSCALL_TRACE_CONTROL_FILE: Once<Arc<dyn FileLike>> = Once::new();
+static SYSCALL_TRACE_FILE: Once<Arc<dyn FileLike>> = Once::new();
 
 pub struct ProcFs(TmpFs);
 
@@ -25,8 +34,15 @@ impl ProcFs {
         let root_dir = tmpfs.root_tmpfs_dir();
 
         METRICS_FILE.init(|| Arc::new(MetricsFile::new()) as Arc<dyn FileLike>);
+        LOADAVG_FILE.init(|| Arc::new(LoadAvgFile::new()) as Arc<dyn FileLike>);
+        SYSCALL_TRACE_CONTROL_FILE
+            .init(|| Arc::new(SyscallTraceControlFile::new()) as Arc<dyn FileLike>);
+        SYSCALL_TRACE_FILE.init(|| Arc::new(SyscallTraceFile::new()) as Arc<dyn FileLike>);
 
         root_dir.add_file("metrics", METRICS_FILE.clone());
+        root_dir.add_file("loadavg", LOADAVG_FILE.clone());
+        root_dir.add_file("syscall_trace_control", SYSCALL_TRACE_CONTROL_FILE.clone());
+        root_dir.add_file("syscall_trace", SYSCALL_TRACE_FILE.clone());
 
         ProcFs(tmpfs)
     }
@@ -0,0 +1,120 @@
+use core::{cmp::min, fmt};
+
+use crate::{
+    fs::{
+        inode::{FileLike, INodeNo},
+        opened_file::OpenOptions,
+        stat::{FileMode, Stat, S_IFREG},
+    },
+    prelude::*,
+    syscalls::tracer::{configure, current_settings, TRACE_LOG},
+    user_buffer::{UserBufReader, UserBufWriter, UserBuffer, UserBufferMut},
+};
+
+const CONTROL_LINE_MAX: usize = 256;
+
+/// The `/proc/syscall_trace_control` file. Write a PID or a command name
+/// (optionally followed by `serial`) to start tracing system calls, or `off`
+/// to stop it.
+pub(super) struct SyscallTraceControlFile {}
+
+impl SyscallTraceControlFile {
+    pub fn new() -> SyscallTraceControlFile {
+        SyscallTraceControlFile {}
+    }
+}
+
+impl fmt::Debug for SyscallTraceControlFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyscallTraceControl").finish()
+    }
+}
+
+impl FileLike for SyscallTraceControlFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn stat(&self) -> Result<Stat> {
+        Ok(Stat {
+            inode_no: INodeNo::new(4),
+            mode: FileMode::new(S_IFREG | 0o644),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn read(&self, offset: usize, buf: UserBufferMut<'_>, _options: &OpenOptions) -> Result<usize> {
+        use core::fmt::Write;
+
+        if offset > 0 {
+            return Ok(0);
+        }
+
+        let mut writer = UserBufWriter::from(buf);
+        let _ = writeln!(writer, "{}", current_settings());
+        Ok(writer.written_len())
+    }
+
+    fn write(&self, _offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
+        let mut line = vec![0; min(buf.len(), CONTROL_LINE_MAX)];
+        let mut reader = UserBufReader::from(buf);
+        let len = reader.read_bytes(&mut line)?;
+        let line = core::str::from_utf8(&line[..len]).map_err(|_| Errno::EINVAL)?;
+        configure(line)?;
+        Ok(len)
+    }
+}
+
+/// The `/proc/syscall_trace` file. Reading it consumes the system call trace
+/// log.
+pub(super) struct SyscallTraceFile {}
+
+impl SyscallTraceFile {
+    pub fn new() -> SyscallTraceFile {
+        SyscallTraceFile {}
+    }
+}
+
+impl fmt::Debug for SyscallTraceFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyscallTrace").finish()
+    }
+}
+
+impl FileLike for SyscallTraceFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn stat(&self) -> Result<Stat> {
+        Ok(Stat {
+            inode_no: INodeNo::new(5),
+            mode: FileMode::new(S_IFREG | 0o400),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn read(
+        &self,
+        _offset: usize,
+        buf: UserBufferMut<'_>,
+        _options: &OpenOptions,
+    ) -> Result<usize> {
+        let mut writer = UserBufWriter::from(buf);
+        // Copy the log into a kernel buffer first: we can't access the user
+        // memory while holding a spinlock.
+        let mut data = Vec::with_capacity(writer.remaining_len());
+        {
+            let mut log = TRACE_LOG.lock();
+            while data.len() < writer.remaining_len() {
+                match log.pop_slice(writer.remaining_len() - data.len()) {
+                    Some(slice) => data.extend_from_slice(slice),
+                    None => break,
+                }
+            }
+        }
+
+        writer.write_bytes(&data)?;
+        Ok(writer.written_len())
+    }
+}
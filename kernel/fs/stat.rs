@@ -1,4 +1,5 @@
 use crate::fs::inode::INodeNo;
+use crate::timer::read_wall_clock;
 
 /// The device file's ID.
 #[derive(Debug, Copy, Clone)]
@@ -10,21 +11,57 @@ pub struct DevId(usize);
 #[repr(transparent)]
 pub struct NLink(usize);
 
+impl NLink {
+    pub const fn new(nlink: usize) -> NLink {
+        NLink(nlink)
+    }
+
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
 /// The file size in bytes.
 #[derive(Debug, Copy, Clone)]
 #[repr(transparent)]
 pub struct FileSize(pub isize);
 
 /// The user ID.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct UId(u32);
 
+impl UId {
+    /// The superuser.
+    pub const ROOT: UId = UId(0);
+
+    pub const fn new(uid: u32) -> UId {
+        UId(uid)
+    }
+
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
 /// The Group ID.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct GId(u32);
 
+impl GId {
+    /// The superuser's group.
+    pub const ROOT: GId = GId(0);
+
+    pub const fn new(gid: u32) -> GId {
+        GId(gid)
+    }
+
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
 /// The size in bytes of a block file file system I/O operations.
 #[derive(Debug, Copy, Clone)]
 #[repr(transparent)]
@@ -35,10 +72,36 @@ pub struct BlockSize(isize);
 #[repr(transparent)]
 pub struct BlockCount(isize);
 
-/// The file size in bytes.
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct Time(isize);
+/// A timestamp in `struct stat` (`struct timespec`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(C)]
+pub struct Time {
+    sec: isize,
+    nsec: isize,
+}
+
+impl Time {
+    pub const fn zero() -> Time {
+        Time { sec: 0, nsec: 0 }
+    }
+
+    pub fn from_nanosecs(nanosecs: u64) -> Time {
+        Time {
+            sec: (nanosecs / 1_000_000_000) as isize,
+            nsec: (nanosecs % 1_000_000_000) as isize,
+        }
+    }
+
+    /// Seconds since the epoch.
+    pub fn secs(&self) -> isize {
+        self.sec
+    }
+
+    /// The current wall clock time.
+    pub fn now() -> Time {
+        Time::from_nanosecs(read_wall_clock().nanosecs_from_epoch() as u64)
+    }
+}
 
 pub const S_IFMT: u32 = 0o170000;
 pub const S_IFCHR: u32 = 0o020000;
@@ -46,6 +109,10 @@ pub const S_IFDIR: u32 = 0o040000;
 pub const S_IFREG: u32 = 0o100000;
 pub const S_IFLNK: u32 = 0o120000;
 
+pub const S_ISUID: u32 = 0o4000;
+pub const S_ISGID: u32 = 0o2000;
+pub const S_ISVTX: u32 = 0o1000;
+
 pub const O_ACCMODE: u32 = 0o3;
 
 // FIXME: OpenFlags also define these values.
@@ -63,8 +130,22 @@ impl FileMode {
         FileMode(value)
     }
 
-    pub fn access_mode(self) -> u32 {
-        self.0 & O_ACCMODE
+    /// The file type bits (`S_IFMT`).
+    pub fn file_type(self) -> u32 {
+        self.0 & S_IFMT
+    }
+
+    /// The permission bits including setuid, setgid and sticky bits.
+    pub fn permission(self) -> u32 {
+        self.0 & !S_IFMT
+    }
+
+    pub fn is_setuid(self) -> bool {
+        self.0 & S_ISUID != 0
+    }
+
+    pub fn is_setgid(self) -> bool {
+        self.0 & S_ISGID != 0
     }
 
     pub fn is_directory(self) -> bool {
@@ -78,6 +159,11 @@ impl FileMode {
     pub fn is_symbolic_link(self) -> bool {
         (self.0 & S_IFMT) == S_IFLNK
     }
+
+    /// Replaces the permission bits (`chmod(2)`).
+    pub fn with_permission(self, perm: u32) -> FileMode {
+        FileMode(self.file_type() | (perm & 0o7777))
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -97,9 +183,44 @@ pub struct Stat {
     pub atime: Time,
     pub mtime: Time,
     pub ctime: Time,
+    pub unused: [isize; 3],
 }
 
 impl Stat {
+    /// Converts into `struct statx`.
+    pub fn to_statx(self) -> Statx {
+        let timestamp = |time: Time| StatxTimestamp {
+            sec: time.sec as i64,
+            nsec: time.nsec as u32,
+            reserved: 0,
+        };
+
+        let (dev, rdev) = (self.dev.0, self.rdev.0);
+        Statx {
+            mask: STATX_BASIC_STATS,
+            blksize: self.blksize.0 as u32,
+            attributes: 0,
+            nlink: self.nlink.0 as u32,
+            uid: self.uid.0,
+            gid: self.gid.0,
+            mode: self.mode.0 as u16,
+            spare0: 0,
+            ino: self.inode_no.as_u64(),
+            size: self.size.0 as u64,
+            blocks: self.blocks.0 as u64,
+            attributes_mask: 0,
+            atime: timestamp(self.atime),
+            btime: timestamp(Time::zero()),
+            ctime: timestamp(self.ctime),
+            mtime: timestamp(self.mtime),
+            rdev_major: dev_major(rdev),
+            rdev_minor: dev_minor(rdev),
+            dev_major: dev_major(dev),
+            dev_minor: dev_minor(dev),
+            spare: [0; 14],
+        }
+    }
+
     pub fn zeroed() -> Stat {
         Stat {
             dev: DevId(0),
@@ -113,9 +234,57 @@ impl Stat {
             size: FileSize(0),
             blksize: BlockSize(0),
             blocks: BlockCount(0),
-            atime: Time(0),
-            mtime: Time(0),
-            ctime: Time(0),
+            atime: Time::zero(),
+            mtime: Time::zero(),
+            ctime: Time::zero(),
+            unused: [0; 3],
         }
     }
 }
+
+/// All fields in `struct stat` are filled in `struct statx`.
+const STATX_BASIC_STATS: u32 = 0x7ff;
+
+fn dev_major(dev: usize) -> u32 {
+    ((dev >> 8) & 0xfff) as u32
+}
+
+fn dev_minor(dev: usize) -> u32 {
+    ((dev & 0xff) | ((dev >> 12) & 0xfff00)) as u32
+}
+
+/// `struct statx_timestamp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct StatxTimestamp {
+    pub sec: i64,
+    pub nsec: u32,
+    pub reserved: i32,
+}
+
+/// `struct statx`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct Statx {
+    pub mask: u32,
+    pub blksize: u32,
+    pub attributes: u64,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u16,
+    pub spare0: u16,
+    pub ino: u64,
+    pub size: u64,
+    pub blocks: u64,
+    pub attributes_mask: u64,
+    pub atime: StatxTimestamp,
+    pub btime: StatxTimestamp,
+    pub ctime: StatxTimestamp,
+    pub mtime: StatxTimestamp,
+    pub rdev_major: u32,
+    pub rdev_minor: u32,
+    pub dev_major: u32,
+    pub dev_minor: u32,
+    pub spare: [u64; 14],
+}
@@ -2,16 +2,21 @@ use crate::{
     prelude::*,
     user_buffer::{UserBufReader, UserBufWriter},
 };
+use alloc::collections::BTreeSet;
 use core::{
-    fmt,
+    cmp::min,
+    fmt, ptr,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 use super::{
     file_system::FileSystem,
-    inode::{DirEntry, Directory, FileLike, FileType, INode, INodeNo},
+    inode::{
+        DirEntry, Directory, FallocFlags, FileLike, FileType, INode, INodeNo, RenameFlags, Symlink,
+    },
     opened_file::OpenOptions,
-    stat::{FileMode, Stat, S_IFDIR, S_IFREG},
+    path::{Path, PathBuf},
+    stat::{FileMode, FileSize, GId, NLink, Stat, Time, UId, S_IFDIR, S_IFLNK, S_IFREG, S_ISVTX},
 };
 use crate::{
     result::{Errno, Error, Result},
@@ -19,11 +24,14 @@ use crate::{
     user_buffer::UserBufferMut,
 };
 use hashbrown::HashMap;
-use kerla_runtime::spinlock::SpinLock;
+use kerla_runtime::{arch::PAGE_SIZE, spinlock::SpinLock};
 use kerla_utils::{downcast::downcast, once::Once};
 
 pub static TMP_FS: Once<Arc<TmpFs>> = Once::new();
 
+/// The access time is updated at least once a day on reads.
+const RELATIME_INTERVAL_SECS: isize = 24 * 60 * 60;
+
 fn alloc_inode_no() -> INodeNo {
     // Inode #1 is reserved for the root dir.
     static NEXT_INODE_NO: AtomicUsize = AtomicUsize::new(2);
@@ -37,8 +45,14 @@ pub struct TmpFs {
 
 impl TmpFs {
     pub fn new() -> TmpFs {
+        // Everyone can create files in the root directory (i.e. /tmp).
         TmpFs {
-            root_dir: Arc::new(Dir::new(INodeNo::new(1))),
+            root_dir: Arc::new(Dir::with_owner(
+                INodeNo::new(1),
+                FileMode::new(S_IFDIR | S_ISVTX | 0o777),
+                UId::ROOT,
+                GId::ROOT,
+            )),
         }
     }
 
@@ -53,9 +67,126 @@ impl FileSystem for TmpFs {
     }
 }
 
+#[derive(Clone)]
 enum TmpFsINode {
     File(Arc<dyn FileLike>),
     Directory(Arc<Dir>),
+    Symlink(Arc<SymbolicLink>),
+}
+
+impl TmpFsINode {
+    fn as_inode(&self) -> INode {
+        match self {
+            TmpFsINode::File(file) => file.clone().into(),
+            TmpFsINode::Directory(dir) => (dir.clone() as Arc<dyn Directory>).into(),
+            TmpFsINode::Symlink(symlink) => (symlink.clone() as Arc<dyn Symlink>).into(),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, TmpFsINode::Directory(_))
+    }
+
+    /// Returns `true` if both refer to the same inode.
+    fn ptr_eq(&self, other: &TmpFsINode) -> bool {
+        fn as_ptr(inode: &TmpFsINode) -> *const () {
+            match inode {
+                TmpFsINode::File(file) => Arc::as_ptr(file) as *const (),
+                TmpFsINode::Directory(dir) => Arc::as_ptr(dir) as *const (),
+                TmpFsINode::Symlink(symlink) => Arc::as_ptr(symlink) as *const (),
+            }
+        }
+
+        ptr::eq(as_ptr(self), as_ptr(other))
+    }
+
+    /// Updates the link count when a directory entry is added or removed.
+    /// Those of directories are computed from their entries instead.
+    fn add_nlink(&self, delta: isize) {
+        match self {
+            TmpFsINode::File(file) => {
+                // Device files and others not created in tmpfs don't have
+                // the link count.
+                if let Some(file) = downcast::<_, File>(file) {
+                    add_nlink(&file.stat, delta);
+                }
+            }
+            TmpFsINode::Symlink(symlink) => add_nlink(&symlink.stat, delta),
+            TmpFsINode::Directory(_) => {}
+        }
+    }
+}
+
+fn add_nlink(stat: &SpinLock<Stat>, delta: isize) {
+    let mut stat = stat.lock();
+    let nlink = stat.nlink;
+    stat.nlink = NLink::new((nlink.as_usize() as isize + delta) as usize);
+    touch_changed(&mut stat);
+}
+
+/// The initial metadata of a new inode. All timestamps are set to the current
+/// time.
+fn new_stat(inode_no: INodeNo, mode: FileMode, uid: UId, gid: GId) -> Stat {
+    let now = Time::now();
+    Stat {
+        inode_no,
+        nlink: NLink::new(1),
+        mode,
+        uid,
+        gid,
+        atime: now,
+        mtime: now,
+        ctime: now,
+        ..Stat::zeroed()
+    }
+}
+
+/// Updates the modification and the status change time (e.g. the contents
+/// are written).
+fn touch_modified(stat: &mut Stat) {
+    let now = Time::now();
+    stat.mtime = now;
+    stat.ctime = now;
+}
+
+/// Updates the access time on a read like the `relatime` mount option: only
+/// if it's older than the last modification or status change, or a day old.
+fn touch_accessed(stat: &mut Stat) {
+    let now = Time::now();
+    // Copy the fields: `Stat` is packed.
+    let (atime, mtime, ctime) = (stat.atime, stat.mtime, stat.ctime);
+    if atime <= mtime || atime <= ctime || now.secs() - atime.secs() >= RELATIME_INTERVAL_SECS {
+        stat.atime = now;
+    }
+}
+
+/// Updates the status change time (e.g. the owner is changed).
+fn touch_changed(stat: &mut Stat) {
+    stat.ctime = Time::now();
+}
+
+fn chown(stat: &mut Stat, uid: UId, gid: GId) {
+    stat.uid = uid;
+    stat.gid = gid;
+    touch_changed(stat);
+}
+
+fn chmod(stat: &mut Stat, mode: FileMode) {
+    let old_mode = stat.mode;
+    stat.mode = old_mode.with_permission(mode.permission());
+    touch_changed(stat);
+}
+
+fn set_times(stat: &mut Stat, atime: Option<Time>, mtime: Option<Time>) {
+    if let Some(atime) = atime {
+        stat.atime = atime;
+    }
+
+    if let Some(mtime) = mtime {
+        stat.mtime = mtime;
+    }
+
+    touch_changed(stat);
 }
 
 struct DirInner {
@@ -67,13 +198,18 @@ pub struct Dir(SpinLock<DirInner>);
 
 impl Dir {
     pub fn new(inode_no: INodeNo) -> Dir {
+        Dir::with_owner(
+            inode_no,
+            FileMode::new(S_IFDIR | 0o755),
+            UId::ROOT,
+            GId::ROOT,
+        )
+    }
+
+    fn with_owner(inode_no: INodeNo, mode: FileMode, uid: UId, gid: GId) -> Dir {
         Dir(SpinLock::new(DirInner {
             files: HashMap::new(),
-            stat: Stat {
-                inode_no,
-                mode: FileMode::new(S_IFDIR | 0o755),
-                ..Stat::zeroed()
-            },
+            stat: new_stat(inode_no, mode, uid, gid),
         }))
     }
 
@@ -92,21 +228,114 @@ impl Dir {
             .files
             .insert(name.to_owned(), TmpFsINode::File(file));
     }
-}
 
-impl Directory for Dir {
-    fn lookup(&self, name: &str) -> Result<INode> {
+    fn get(&self, name: &str) -> Result<TmpFsINode> {
         self.0
             .lock()
             .files
             .get(name)
-            .map(|tmpfs_inode| match tmpfs_inode {
-                TmpFsINode::File(file) => file.clone().into(),
-                TmpFsINode::Directory(dir) => (dir.clone() as Arc<dyn Directory>).into(),
-            })
+            .cloned()
             .ok_or_else(|| Error::new(Errno::ENOENT))
     }
 
+    /// Adds an entry. Returns `EEXIST` if it already exists.
+    fn insert_new(&self, name: &str, inode: TmpFsINode) -> Result<()> {
+        let mut dir_lock = self.0.lock();
+        if dir_lock.files.contains_key(name) {
+            return Err(Errno::EEXIST.into());
+        }
+
+        dir_lock.files.insert(name.to_owned(), inode);
+        touch_modified(&mut dir_lock.stat);
+        Ok(())
+    }
+}
+
+/// Taken by operations which lock a directory and then another one (`rmdir`
+/// and `rename`). Other operations lock one directory at a time, so these
+/// can't deadlock whatever order they lock directories in.
+static NESTED_DIR_LOCK: SpinLock<()> = SpinLock::new(());
+
+/// Checks if `old` can replace `new` in `rename(2)`. `locked` are the
+/// directories already locked by the caller.
+fn check_replace(old: &TmpFsINode, new: &TmpFsINode, locked: &[*const Dir]) -> Result<()> {
+    match (old.is_dir(), new) {
+        // The source directory is not empty: it contains `old`.
+        (true, TmpFsINode::Directory(dir)) if locked.contains(&Arc::as_ptr(dir)) => {
+            Err(Errno::ENOTEMPTY.into())
+        }
+        (true, TmpFsINode::Directory(dir)) if !dir.0.lock().files.is_empty() => {
+            Err(Errno::ENOTEMPTY.into())
+        }
+        (true, TmpFsINode::Directory(_)) => Ok(()),
+        (true, _) => Err(Errno::ENOTDIR.into()),
+        (false, TmpFsINode::Directory(_)) => Err(Errno::EISDIR.into()),
+        (false, _) => Ok(()),
+    }
+}
+
+/// Moves `old_name` in `old_dir` to `new_name` in `new_dir` (or `old_dir` if
+/// it's `None`). Both directories are locked by the caller.
+fn rename_locked(
+    old_dir: &mut DirInner,
+    mut new_dir: Option<&mut DirInner>,
+    locked: &[*const Dir],
+    old_name: &str,
+    new_name: &str,
+    flags: RenameFlags,
+) -> Result<()> {
+    fn target<'a>(
+        old_dir: &'a mut DirInner,
+        new_dir: &'a mut Option<&mut DirInner>,
+    ) -> &'a mut DirInner {
+        match new_dir {
+            Some(new_dir) => new_dir,
+            None => old_dir,
+        }
+    }
+
+    let exchange = flags.contains(RenameFlags::RENAME_EXCHANGE);
+    let old = old_dir
+        .files
+        .get(old_name)
+        .cloned()
+        .ok_or_else(|| Error::new(Errno::ENOENT))?;
+    let new = target(old_dir, &mut new_dir).files.get(new_name).cloned();
+    match &new {
+        Some(_) if flags.contains(RenameFlags::RENAME_NOREPLACE) => {
+            return Err(Errno::EEXIST.into());
+        }
+        // Both are links to the same file: do nothing.
+        Some(new) if new.ptr_eq(&old) => return Ok(()),
+        Some(new) if !exchange => check_replace(&old, new, locked)?,
+        None if exchange => return Err(Errno::ENOENT.into()),
+        _ => {}
+    }
+
+    old_dir.files.remove(old_name);
+    touch_modified(&mut old_dir.stat);
+    let replaced = {
+        let new_dir = target(old_dir, &mut new_dir);
+        touch_modified(&mut new_dir.stat);
+        new_dir.files.insert(new_name.to_owned(), old)
+    };
+
+    if let Some(replaced) = replaced {
+        if exchange {
+            old_dir.files.insert(old_name.to_owned(), replaced);
+        } else {
+            replaced.add_nlink(-1);
+        }
+    }
+
+    Ok(())
+}
+
+impl Directory for Dir {
+    fn lookup(&self, name: &str) -> Result<INode> {
+        self.get(name).map(|tmpfs_inode| tmpfs_inode.as_inode())
+    }
+
     fn readdir(&self, index: usize) -> Result<Option<DirEntry>> {
         let dir_lock = self.0.lock();
         let (name, inode) = match dir_lock.files.iter().nth(index) {
@@ -116,19 +345,28 @@ impl Directory for Dir {
             }
         };
 
+        // Don't lock the subdirectory while holding the lock.
+        let (name, inode) = (name.clone(), inode.clone());
+        drop(dir_lock);
+
         let entry = match inode {
             TmpFsINode::Directory(dir) => {
                 let dir = dir.0.lock();
                 DirEntry {
                     inode_no: dir.stat.inode_no,
                     file_type: FileType::Directory,
-                    name: name.clone(),
+                    name,
                 }
             }
             TmpFsINode::File(file) => DirEntry {
                 inode_no: file.stat()?.inode_no,
                 file_type: FileType::Regular,
-                name: name.clone(),
+                name,
+            },
+            TmpFsINode::Symlink(symlink) => DirEntry {
+                inode_no: symlink.stat.lock().inode_no,
+                file_type: FileType::Link,
+                name,
             },
         };
 
@@ -136,46 +374,139 @@ impl Directory for Dir {
     }
 
     fn stat(&self) -> Result<Stat> {
-        Ok(self.0.lock().stat)
+        let dir_lock = self.0.lock();
+        // The entry in the parent, ".", and ".." in each subdirectory.
+        let num_subdirs = dir_lock
+            .files
+            .values()
+            .filter(|inode| inode.is_dir())
+            .count();
+
+        Ok(Stat {
+            nlink: NLink::new(2 + num_subdirs),
+            ..dir_lock.stat
+        })
     }
 
     fn link(&self, name: &str, link_to: &INode) -> Result<()> {
         let tmpfs_inode = match link_to {
             INode::FileLike(file_like) => TmpFsINode::File(file_like.clone()),
-            INode::Directory(dir) => {
-                let dir: &Arc<Dir> = downcast(dir).unwrap();
-                TmpFsINode::Directory(dir.clone())
-            }
-            INode::Symlink(_) => unreachable!(), /* symblic links are not supported yet */
+            // Hard links to directories are not allowed.
+            INode::Directory(_) => return Err(Errno::EPERM.into()),
+            INode::Symlink(symlink) => TmpFsINode::Symlink(
+                downcast::<_, SymbolicLink>(symlink).ok_or_else(|| Error::new(Errno::EXDEV))?,
+            ),
         };
 
-        self.0.lock().files.insert(name.to_owned(), tmpfs_inode);
+        self.insert_new(name, tmpfs_inode.clone())?;
+        tmpfs_inode.add_nlink(1);
         Ok(())
     }
 
-    fn create_file(&self, name: &str, _mode: FileMode) -> Result<INode> {
+    fn unlink(&self, name: &str) -> Result<()> {
         let mut dir_lock = self.0.lock();
-        if dir_lock.files.contains_key(name) {
-            return Err(Errno::EEXIST.into());
+        match dir_lock.files.get(name) {
+            Some(inode) if inode.is_dir() => return Err(Errno::EISDIR.into()),
+            Some(_) => {}
+            None => return Err(Errno::ENOENT.into()),
         }
 
-        let inode = Arc::new(File::new(alloc_inode_no()));
-        dir_lock
-            .files
-            .insert(name.to_owned(), TmpFsINode::File(inode.clone()));
+        if let Some(inode) = dir_lock.files.remove(name) {
+            inode.add_nlink(-1);
+        }
 
-        Ok((inode as Arc<dyn FileLike>).into())
+        touch_modified(&mut dir_lock.stat);
+        Ok(())
     }
 
-    fn create_dir(&self, name: &str, _mode: FileMode) -> Result<INode> {
-        let inode = Arc::new(Dir::new(alloc_inode_no()));
-        self.0
-            .lock()
-            .files
-            .insert(name.to_owned(), TmpFsINode::Directory(inode.clone()));
+    fn rmdir(&self, name: &str) -> Result<()> {
+        let _nested_lock = NESTED_DIR_LOCK.lock();
+        let mut dir_lock = self.0.lock();
+        match dir_lock.files.get(name) {
+            Some(TmpFsINode::Directory(dir)) if !dir.0.lock().files.is_empty() => {
+                return Err(Errno::ENOTEMPTY.into());
+            }
+            Some(TmpFsINode::Directory(_)) => {}
+            Some(_) => return Err(Errno::ENOTDIR.into()),
+            None => return Err(Errno::ENOENT.into()),
+        }
+
+        dir_lock.files.remove(name);
+        touch_modified(&mut dir_lock.stat);
+        Ok(())
+    }
 
+    fn rename(
+        &self,
+        old_name: &str,
+        new_dir: &Arc<dyn Directory>,
+        new_name: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        let new_dir = downcast::<_, Dir>(new_dir).ok_or_else(|| Error::new(Errno::EXDEV))?;
+        let self_ptr = self as *const Dir;
+        let new_dir_ptr = Arc::as_ptr(&new_dir);
+
+        // Both directories are locked during the whole operation, in the
+        // order of their addresses.
+        let _nested_lock = NESTED_DIR_LOCK.lock();
+        if ptr::eq(self_ptr, new_dir_ptr) {
+            let mut dir_lock = self.0.lock();
+            return rename_locked(&mut dir_lock, None, &[self_ptr], old_name, new_name, flags);
+        }
+
+        let (mut dir_lock, mut new_dir_lock) = if self_ptr < new_dir_ptr {
+            let dir_lock = self.0.lock();
+            (dir_lock, new_dir.0.lock())
+        } else {
+            let new_dir_lock = new_dir.0.lock();
+            (self.0.lock(), new_dir_lock)
+        };
+
+        rename_locked(
+            &mut dir_lock,
+            Some(&mut new_dir_lock),
+            &[self_ptr, new_dir_ptr],
+            old_name,
+            new_name,
+            flags,
+        )
+    }
+
+    fn create_file(&self, name: &str, mode: FileMode, uid: UId, gid: GId) -> Result<INode> {
+        let mode = FileMode::new(S_IFREG | mode.permission());
+        let inode = Arc::new(File::new(alloc_inode_no(), mode, uid, gid));
+        self.insert_new(name, TmpFsINode::File(inode.clone()))?;
+        Ok((inode as Arc<dyn FileLike>).into())
+    }
+
+    fn create_dir(&self, name: &str, mode: FileMode, uid: UId, gid: GId) -> Result<INode> {
+        let mode = FileMode::new(S_IFDIR | mode.permission());
+        let inode = Arc::new(Dir::with_owner(alloc_inode_no(), mode, uid, gid));
+        self.insert_new(name, TmpFsINode::Directory(inode.clone()))?;
         Ok((inode as Arc<dyn Directory>).into())
     }
+
+    fn create_symlink(&self, name: &str, target: &Path, uid: UId, gid: GId) -> Result<INode> {
+        let inode = Arc::new(SymbolicLink::new(alloc_inode_no(), target, uid, gid));
+        self.insert_new(name, TmpFsINode::Symlink(inode.clone()))?;
+        Ok((inode as Arc<dyn Symlink>).into())
+    }
+
+    fn chown(&self, uid: UId, gid: GId) -> Result<()> {
+        chown(&mut self.0.lock().stat, uid, gid);
+        Ok(())
+    }
+
+    fn chmod(&self, mode: FileMode) -> Result<()> {
+        chmod(&mut self.0.lock().stat, mode);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<Time>, mtime: Option<Time>) -> Result<()> {
+        set_times(&mut self.0.lock().stat, atime, mtime);
+        Ok(())
+    }
 }
 
 impl fmt::Debug for Dir {
@@ -184,27 +515,127 @@ impl fmt::Debug for Dir {
     }
 }
 
+struct SymbolicLink {
+    linked_to: PathBuf,
+    stat: SpinLock<Stat>,
+}
+
+impl SymbolicLink {
+    fn new(inode_no: INodeNo, linked_to: &Path, uid: UId, gid: GId) -> SymbolicLink {
+        SymbolicLink {
+            linked_to: linked_to.to_path_buf(),
+            stat: SpinLock::new(Stat {
+                size: FileSize(linked_to.as_str().len() as isize),
+                ..new_stat(inode_no, FileMode::new(S_IFLNK | 0o777), uid, gid)
+            }),
+        }
+    }
+}
+
+impl Symlink for SymbolicLink {
+    fn stat(&self) -> Result<Stat> {
+        Ok(*self.stat.lock())
+    }
+
+    fn linked_to(&self) -> Result<PathBuf> {
+        Ok(self.linked_to.clone())
+    }
+
+    fn chown(&self, uid: UId, gid: GId) -> Result<()> {
+        chown(&mut self.stat.lock(), uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<Time>, mtime: Option<Time>) -> Result<()> {
+        set_times(&mut self.stat.lock(), atime, mtime);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SymbolicLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TmpFsSymlink")
+            .field("linked_to", &self.linked_to.as_str())
+            .finish()
+    }
+}
+
 struct File {
     data: SpinLock<Vec<u8>>,
-    stat: Stat,
+    /// The indices of pages punched by `FALLOC_FL_PUNCH_HOLE`. Locked while
+    /// holding `data`.
+    holes: SpinLock<BTreeSet<usize>>,
+    stat: SpinLock<Stat>,
+}
+
+/// Resizes the file contents. Returns `ENOSPC` if we're running out of memory.
+fn resize_data(data: &mut Vec<u8>, new_len: usize) -> Result<()> {
+    if new_len > data.len() {
+        data.try_reserve(new_len - data.len())
+            .map_err(|_| Error::new(Errno::ENOSPC))?;
+    }
+
+    data.resize(new_len, 0);
+    Ok(())
+}
+
+/// Writes `buf` into `data` at `offset`, extending it if needed.
+fn write_data(data: &mut Vec<u8>, offset: usize, buf: UserBuffer<'_>) -> Result<usize> {
+    let mut reader = UserBufReader::from(buf);
+    let end = offset
+        .checked_add(reader.remaining_len())
+        .ok_or_else(|| Error::new(Errno::EFBIG))?;
+    if end > data.len() {
+        resize_data(data, end)?;
+    }
+
+    reader.read_bytes(&mut data[offset..end])
 }
 
 impl File {
-    pub fn new(inode_no: INodeNo) -> File {
+    pub fn new(inode_no: INodeNo, mode: FileMode, uid: UId, gid: GId) -> File {
         File {
             data: SpinLock::new(Vec::new()),
-            stat: Stat {
-                inode_no,
-                mode: FileMode::new(S_IFREG | 0o644),
-                ..Stat::zeroed()
-            },
+            holes: SpinLock::new(BTreeSet::new()),
+            stat: SpinLock::new(new_stat(inode_no, mode, uid, gid)),
+        }
+    }
+
+    /// Marks the pages in `offset..end` as data.
+    fn fill_holes(&self, offset: usize, end: usize) {
+        if offset < end {
+            let pages = (offset / PAGE_SIZE)..=((end - 1) / PAGE_SIZE);
+            self.holes.lock().retain(|page| !pages.contains(page));
         }
     }
 }
 
 impl FileLike for File {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
     fn stat(&self) -> Result<Stat> {
-        Ok(self.stat)
+        let size = self.data.lock().len();
+        Ok(Stat {
+            size: FileSize(size as isize),
+            ..*self.stat.lock()
+        })
+    }
+
+    fn chown(&self, uid: UId, gid: GId) -> Result<()> {
+        chown(&mut self.stat.lock(), uid, gid);
+        Ok(())
+    }
+
+    fn chmod(&self, mode: FileMode) -> Result<()> {
+        chmod(&mut self.stat.lock(), mode);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Option<Time>, mtime: Option<Time>) -> Result<()> {
+        set_times(&mut self.stat.lock(), atime, mtime);
+        Ok(())
     }
 
     fn read(&self, offset: usize, buf: UserBufferMut<'_>, _options: &OpenOptions) -> Result<usize> {
@@ -214,14 +645,106 @@ impl FileLike for File {
         }
 
         let mut writer = UserBufWriter::from(buf);
-        writer.write_bytes(&data[offset..])
+        let read_len = writer.write_bytes(&data[offset..])?;
+        touch_accessed(&mut self.stat.lock());
+        Ok(read_len)
     }
 
     fn write(&self, offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
         let mut data = self.data.lock();
-        let mut reader = UserBufReader::from(buf);
-        data.resize(offset + reader.remaining_len(), 0);
-        reader.read_bytes(&mut data[offset..])
+        let written_len = write_data(&mut data, offset, buf)?;
+        self.fill_holes(offset, offset + written_len);
+        touch_modified(&mut self.stat.lock());
+        Ok(written_len)
+    }
+
+    fn append(&self, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<(usize, usize)> {
+        // Hold the lock until the data is written: other writers may extend
+        // the file.
+        let mut data = self.data.lock();
+        let offset = data.len();
+        let written_len = write_data(&mut data, offset, buf)?;
+        self.fill_holes(offset, offset + written_len);
+        touch_modified(&mut self.stat.lock());
+        Ok((offset, written_len))
+    }
+
+    fn truncate(&self, length: usize) -> Result<()> {
+        let mut data = self.data.lock();
+        resize_data(&mut data, length)?;
+        self.fill_holes(length, usize::MAX);
+        touch_modified(&mut self.stat.lock());
+        Ok(())
+    }
+
+    fn fallocate(&self, flags: FallocFlags, offset: usize, len: usize) -> Result<()> {
+        let mut data = self.data.lock();
+        let end = offset
+            .checked_add(len)
+            .ok_or_else(|| Error::new(Errno::EFBIG))?;
+        if flags.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE) {
+            // The memory is kept allocated: fill the hole with zeroes and
+            // remember the pages entirely in the hole for SEEK_HOLE.
+            let file_len = data.len();
+            if offset >= file_len {
+                return Ok(());
+            }
+
+            data[offset..min(end, file_len)].fill(0);
+            let first = offset.div_ceil(PAGE_SIZE);
+            let last = if end >= file_len {
+                file_len.div_ceil(PAGE_SIZE)
+            } else {
+                end / PAGE_SIZE
+            };
+
+            self.holes.lock().extend(first..last);
+        } else {
+            self.fill_holes(offset, min(end, data.len()));
+            if !flags.contains(FallocFlags::FALLOC_FL_KEEP_SIZE) && end > data.len() {
+                resize_data(&mut data, end)?;
+            } else {
+                return Ok(());
+            }
+        }
+
+        touch_modified(&mut self.stat.lock());
+
+        Ok(())
+    }
+
+    fn seek_data(&self, offset: usize, hole: bool) -> Result<usize> {
+        let size = self.data.lock().len();
+        if offset >= size {
+            return Err(Errno::ENXIO.into());
+        }
+
+        let holes = self.holes.lock();
+        let mut page = offset / PAGE_SIZE;
+        let pos = if hole {
+            // The next punched page, or the implicit hole at the end.
+            match holes.range(page..).next() {
+                Some(&hole_page) if hole_page == page => offset,
+                Some(&hole_page) => hole_page * PAGE_SIZE,
+                None => size,
+            }
+        } else {
+            while holes.contains(&page) {
+                page += 1;
+            }
+
+            if page == offset / PAGE_SIZE {
+                offset
+            } else {
+                page * PAGE_SIZE
+            }
+        };
+
+        if pos >= size && !hole {
+            return Err(Errno::ENXIO.into());
+        }
+
+        Ok(min(pos, size))
     }
 }
 
@@ -50,14 +50,19 @@ use crate::{
         path::Path,
         procfs::{self, PROC_FS},
     },
-    process::{switch, Process},
+    process::{
+        credentials::Credentials,
+        current_process,
+        signal::{SigInfo, SIGTRAP},
+        switch, Process,
+    },
     syscalls::SyscallHandler,
 };
 use alloc::{boxed::Box, sync::Arc};
 use interrupt::attach_irq;
 use kerla_api::kernel_ops::KernelOps;
 use kerla_runtime::{
-    arch::{idle, PageFaultReason, PtRegs},
+    arch::{idle, start_application_processors, PageFaultReason, PtRegs},
     bootinfo::BootInfo,
     profile::StopWatch,
     spinlock::SpinLock,
@@ -90,7 +95,17 @@ impl kerla_runtime::Handler for Handler {
         ip: usize,
         reason: PageFaultReason,
     ) {
+        // The time spent on a page fault in the userspace is the system time.
+        let from_user = reason.contains(PageFaultReason::CAUSED_BY_USER);
+        if from_user {
+            current_process().enter_kernel();
+        }
+
         crate::mm::page_fault::handle_page_fault(unaligned_vaddr, ip, reason);
+
+        if from_user {
+            current_process().exit_kernel();
+        }
     }
 
     fn handle_syscall(
@@ -110,10 +125,26 @@ impl kerla_runtime::Handler for Handler {
             .unwrap_or_else(|err| -(err.errno() as isize))
     }
 
+    fn handle_debug_exception(&self) {
+        current_process().force_signal(SigInfo::kernel(SIGTRAP));
+    }
+
+    fn handle_interrupt_return_to_user(&self, frame: &mut PtRegs) {
+        if let Err(err) = crate::process::Process::try_delivering_signal(frame) {
+            debug_warn!("failed to setup the signal stack: {:?}", err);
+        }
+    }
+
+    fn handle_reschedule_ipi(&self) {
+        process::preempt_if_needed();
+    }
+
+    fn handle_page_freed(&self, paddr: kerla_runtime::address::PAddr) {
+        process::cgroup::uncharge_page(paddr);
+    }
+
     #[cfg(debug_assertions)]
     fn usercopy_hook(&self) {
-        use crate::process::current_process;
-
         // We should not hold the vm lock since we'll try to acquire it in the
         // page fault handler when copying caused a page fault.
         debug_assert!(!current_process().vm().as_ref().unwrap().is_locked());
@@ -193,14 +224,15 @@ pub fn boot_kernel(#[cfg_attr(debug_assertions, allow(unused))] bootinfo: &BootI
 
     // Prepare the root file system.
     let mut root_fs = RootFs::new(INITRAM_FS.clone()).unwrap();
+    let root_creds = Credentials::root();
     let proc_dir = root_fs
-        .lookup_dir(Path::new("/proc"))
+        .lookup_dir(Path::new("/proc"), &root_creds)
         .expect("failed to locate /dev");
     let dev_dir = root_fs
-        .lookup_dir(Path::new("/dev"))
+        .lookup_dir(Path::new("/dev"), &root_creds)
         .expect("failed to locate /dev");
     let tmp_dir = root_fs
-        .lookup_dir(Path::new("/tmp"))
+        .lookup_dir(Path::new("/tmp"), &root_creds)
         .expect("failed to locate /tmp");
     root_fs
         .mount(proc_dir, PROC_FS.clone())
@@ -214,7 +246,7 @@ pub fn boot_kernel(#[cfg_attr(debug_assertions, allow(unused))] bootinfo: &BootI
 
     // Open /dev/console for the init process.
     let console = root_fs
-        .lookup_path(Path::new("/dev/console"), true)
+        .lookup_path(Path::new("/dev/console"), true, &root_creds)
         .expect("failed to open /dev/console");
 
     // Open the init's executable.
@@ -224,7 +256,7 @@ pub fn boot_kernel(#[cfg_attr(debug_assertions, allow(unused))] bootinfo: &BootI
         "/sbin/init"
     };
     let executable_path = root_fs
-        .lookup_path(Path::new(argv0), true)
+        .lookup_path(Path::new(argv0), true, &root_creds)
         .expect("failed to open the init executable");
 
     // We cannot initialize the process subsystem until INITIAL_ROOT_FS is initialized.
@@ -235,6 +267,11 @@ pub fn boot_kernel(#[cfg_attr(debug_assertions, allow(unused))] bootinfo: &BootI
     process::init();
     profiler.lap_time("process init");
 
+    unsafe {
+        start_application_processors();
+    }
+    profiler.lap_time("smp init");
+
     // Create the init process.
     if let Some(script) = option_env!("INIT_SCRIPT") {
         let argv = &[b"sh", b"-c", script.as_bytes()];
@@ -261,6 +298,13 @@ pub fn boot_kernel(#[cfg_attr(debug_assertions, allow(unused))] bootinfo: &BootI
     idle_thread();
 }
 
+/// The entry point of Application Processors (APs).
+#[no_mangle]
+pub fn boot_ap() -> ! {
+    process::init_ap();
+    idle_thread();
+}
+
 pub fn interval_work() {
     process::gc_exited_processes();
 }
@@ -268,6 +312,12 @@ pub fn interval_work() {
 fn idle_thread() -> ! {
     loop {
         interval_work();
+
+        // Switch to a runnable thread if any.
+        process::preempt_if_needed();
+
+        // Stop the periodic timer tick until the next timer expires.
+        timer::program_next_event(false);
         idle();
     }
 }
@@ -1,2 +1,3 @@
+pub mod page_cache;
 pub mod page_fault;
 pub mod vm;
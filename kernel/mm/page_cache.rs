@@ -0,0 +1,401 @@
+//! The page cache: memory pages which hold contents of files.
+//!
+//! Pages are shared among file mappings (`mmap(2)`) of the same file. Writes
+//! through `MAP_SHARED` mappings are written back to the file in `msync(2)`,
+//! `munmap(2)`, `close(2)`, and on exit. Until then, cached pages hold the
+//! latest contents: `read(2)` reads from them and `write(2)` updates them as
+//! well as the file.
+//!
+//! Clean pages no longer mapped are evicted in `munmap(2)` and `close(2)`. A
+//! page is charged to the control group of the process which has filled it
+//! until it's evicted.
+use super::page_fault::{alloc_user_page, FaultError};
+use crate::{
+    fs::{inode::FileLike, opened_file::OpenOptions},
+    prelude::*,
+    user_buffer::{UserBufReader, UserBufWriter, UserBuffer, UserBufferMut},
+};
+use alloc::collections::BTreeMap;
+use core::{
+    cmp::{max, min},
+    slice,
+};
+use kerla_runtime::{
+    address::PAddr,
+    arch::PAGE_SIZE,
+    page_allocator::{page_ref_count, ref_page, unref_page, AllocPageFlags},
+    spinlock::SpinLock,
+};
+use kerla_utils::alignment::align_down;
+
+static PAGE_CACHE: SpinLock<PageCache> = SpinLock::new(PageCache::new());
+
+struct CachedPage {
+    paddr: PAddr,
+    /// `true` if the page has been mapped as writable in a `MAP_SHARED`
+    /// mapping. We don't track the dirty bits in page tables: the flag is
+    /// cleared only if the page is no longer mapped when it's written back.
+    dirty: bool,
+}
+
+struct CachedFile {
+    file: Weak<dyn FileLike>,
+    /// Cached pages indexed by the offset in the file.
+    pages: BTreeMap<usize, CachedPage>,
+}
+
+impl Drop for CachedFile {
+    fn drop(&mut self) {
+        for page in self.pages.values() {
+            unref_page(page.paddr);
+        }
+    }
+}
+
+struct PageCache {
+    /// Cached files indexed by the address of the file object.
+    files: BTreeMap<usize, CachedFile>,
+}
+
+impl PageCache {
+    const fn new() -> PageCache {
+        PageCache {
+            files: BTreeMap::new(),
+        }
+    }
+
+    fn get_mut(&mut self, file: &Arc<dyn FileLike>) -> Option<&mut CachedFile> {
+        let key = file_key(file);
+        match self.files.get(&key) {
+            // The file object has been freed and its address is reused.
+            Some(cached) if cached.file.strong_count() == 0 => {
+                self.files.remove(&key);
+                None
+            }
+            Some(_) => self.files.get_mut(&key),
+            None => None,
+        }
+    }
+
+    fn get_or_insert_mut(&mut self, file: &Arc<dyn FileLike>) -> &mut CachedFile {
+        if self.get_mut(file).is_none() {
+            // Evict pages of files which no longer exist.
+            self.files
+                .retain(|_, cached| cached.file.strong_count() > 0);
+            self.files.insert(
+                file_key(file),
+                CachedFile {
+                    file: Arc::downgrade(file),
+                    pages: BTreeMap::new(),
+                },
+            );
+        }
+
+        self.get_mut(file).unwrap()
+    }
+}
+
+fn file_key(file: &Arc<dyn FileLike>) -> usize {
+    Arc::as_ptr(file) as *const () as usize
+}
+
+fn page_as_slice(paddr: PAddr) -> &'static [u8] {
+    unsafe { slice::from_raw_parts(paddr.as_ptr(), PAGE_SIZE) }
+}
+
+fn page_as_slice_mut(paddr: PAddr) -> &'static mut [u8] {
+    unsafe { slice::from_raw_parts_mut(paddr.as_mut_ptr(), PAGE_SIZE) }
+}
+
+fn read_page(file: &Arc<dyn FileLike>, offset: usize, paddr: PAddr) -> Result<()> {
+    let buf = page_as_slice_mut(paddr);
+    let read_len = file.read(offset, buf.into(), &OpenOptions::readwrite())?;
+    buf[read_len..].fill(0);
+    Ok(())
+}
+
+/// Returns the page which holds the file contents at `offset`. The page is
+/// referenced for the caller: unreference it by `unref_page` when it's no
+/// longer used (e.g. unmapped from the page table).
+///
+/// If `writable` is true, the page is considered to be modified by the caller
+/// and will be written back to the file.
+pub(super) fn get_page(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    writable: bool,
+) -> core::result::Result<PAddr, FaultError> {
+    debug_assert!(offset % PAGE_SIZE == 0);
+
+    let mut cache = PAGE_CACHE.lock();
+    if let Some(page) = cache
+        .get_mut(file)
+        .and_then(|cached| cached.pages.get_mut(&offset))
+    {
+        page.dirty |= writable;
+        ref_page(page.paddr);
+        return Ok(page.paddr);
+    }
+
+    // Don't hold the lock while reading the file.
+    drop(cache);
+    let paddr = alloc_user_page(AllocPageFlags::USER | AllocPageFlags::DIRTY_OK)?;
+    if read_page(file, offset, paddr).is_err() {
+        unref_page(paddr);
+        return Err(FaultError::Io);
+    }
+
+    let mut cache = PAGE_CACHE.lock();
+    let cached = cache.get_or_insert_mut(file);
+    let page = cached.pages.entry(offset).or_insert(CachedPage {
+        paddr,
+        dirty: false,
+    });
+
+    if page.paddr != paddr {
+        // Another process has filled the page in the meantime.
+        unref_page(paddr);
+    }
+
+    page.dirty |= writable;
+    ref_page(page.paddr);
+    Ok(page.paddr)
+}
+
+/// Marks the cached page as modified through a `MAP_SHARED` mapping.
+pub fn mark_dirty(file: &Arc<dyn FileLike>, offset: usize) {
+    let mut cache = PAGE_CACHE.lock();
+    if let Some(page) = cache
+        .get_mut(file)
+        .and_then(|cached| cached.pages.get_mut(&offset))
+    {
+        page.dirty = true;
+    }
+}
+
+/// Returns cached pages in `[offset, offset + len)`. Pages are referenced so
+/// that they're not freed while the caller uses them.
+fn collect_pages(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    len: usize,
+    dirty_only: bool,
+) -> Vec<(usize, PAddr)> {
+    let mut cache = PAGE_CACHE.lock();
+    let cached = match cache.get_mut(file) {
+        Some(cached) => cached,
+        None => return Vec::new(),
+    };
+
+    let mut pages = Vec::new();
+    for (page_offset, page) in cached
+        .pages
+        .range(align_down(offset, PAGE_SIZE)..offset.saturating_add(len))
+    {
+        if !dirty_only || page.dirty {
+            ref_page(page.paddr);
+            pages.push((*page_offset, page.paddr));
+        }
+    }
+
+    pages
+}
+
+/// Writes modified pages in `[offset, offset + len)` back to the file.
+pub fn sync(file: &Arc<dyn FileLike>, offset: usize, len: usize) -> Result<()> {
+    let pages = collect_pages(file, offset, len, true);
+    if pages.is_empty() {
+        return Ok(());
+    }
+
+    let file_size = file.stat()?.size.0 as usize;
+    let mut result = Ok(());
+    for (page_offset, paddr) in pages {
+        if page_offset < file_size && result.is_ok() {
+            // Don't extend the file.
+            let len = min(PAGE_SIZE, file_size - page_offset);
+            let buf = &page_as_slice(paddr)[..len];
+            result = file
+                .write(page_offset, buf.into(), &OpenOptions::readwrite())
+                .map(|_| ());
+        }
+
+        unref_page(paddr);
+        if result.is_ok() {
+            mark_clean(file, page_offset, paddr);
+        }
+    }
+
+    result
+}
+
+/// Writes back modified pages in `[offset, offset + len)` and evicts clean
+/// pages no longer mapped. Called when the range is unmapped or the file is
+/// closed.
+pub fn release(file: &Arc<dyn FileLike>, offset: usize, len: usize) -> Result<()> {
+    let result = sync(file, offset, len);
+
+    let mut cache = PAGE_CACHE.lock();
+    let key = file_key(file);
+    if let Some(cached) = cache.get_mut(file) {
+        // The page cache holds the only reference to pages no longer mapped.
+        // They're referenced only while holding the lock.
+        let evicted: Vec<usize> = cached
+            .pages
+            .range(align_down(offset, PAGE_SIZE)..offset.saturating_add(len))
+            .filter(|(_, page)| !page.dirty && page_ref_count(page.paddr) == 1)
+            .map(|(page_offset, _)| *page_offset)
+            .collect();
+        for page_offset in evicted {
+            if let Some(page) = cached.pages.remove(&page_offset) {
+                unref_page(page.paddr);
+            }
+        }
+
+        if cached.pages.is_empty() {
+            cache.files.remove(&key);
+        }
+    }
+
+    result
+}
+
+/// Clears the dirty flag of a page which has been written back unless it's
+/// still mapped: it may be modified again without page faults.
+fn mark_clean(file: &Arc<dyn FileLike>, offset: usize, paddr: PAddr) {
+    let mut cache = PAGE_CACHE.lock();
+    if let Some(page) = cache
+        .get_mut(file)
+        .and_then(|cached| cached.pages.get_mut(&offset))
+    {
+        // The page cache holds the only reference.
+        if page.paddr == paddr && page_ref_count(paddr) == 1 {
+            page.dirty = false;
+        }
+    }
+}
+
+/// Reads the file at `offset` (`read(2)`). Cached pages are read instead of
+/// the file since they may have been modified through `MAP_SHARED` mappings.
+pub fn read(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    buf: UserBufferMut<'_>,
+    options: &OpenOptions,
+) -> Result<usize> {
+    let pages = collect_pages(file, offset, buf.len(), false);
+    if pages.is_empty() {
+        return file.read(offset, buf, options);
+    }
+
+    let result = read_with_pages(file, offset, buf, options, &pages);
+    for (_, paddr) in pages {
+        unref_page(paddr);
+    }
+
+    result
+}
+
+fn read_with_pages(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    buf: UserBufferMut<'_>,
+    options: &OpenOptions,
+    pages: &[(usize, PAddr)],
+) -> Result<usize> {
+    let file_size = file.stat()?.size.0 as usize;
+    let end = min(offset.saturating_add(buf.len()), file_size);
+    let mut writer = UserBufWriter::from(buf);
+    let mut tmp = vec![0; PAGE_SIZE];
+    let mut pos = offset;
+    while pos < end {
+        let page_offset = align_down(pos, PAGE_SIZE);
+        let range = (pos - page_offset)..(min(page_offset + PAGE_SIZE, end) - page_offset);
+        let copy_len = match pages.iter().find(|(o, _)| *o == page_offset) {
+            Some((_, paddr)) => writer.write_bytes(&page_as_slice(*paddr)[range])?,
+            None => {
+                let len = range.len();
+                let read_len = file.read(pos, (&mut tmp[..len]).into(), options)?;
+                writer.write_bytes(&tmp[..read_len])?;
+                if read_len < len {
+                    break;
+                }
+
+                read_len
+            }
+        };
+
+        pos += copy_len;
+    }
+
+    Ok(writer.written_len())
+}
+
+/// Writes into the file at `offset` (`write(2)`) and updates cached pages.
+pub fn write(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    buf: UserBuffer<'_>,
+    options: &OpenOptions,
+) -> Result<usize> {
+    let written_len = file.write(offset, buf.clone(), options)?;
+    update_pages(file, offset, written_len, buf)?;
+    Ok(written_len)
+}
+
+/// Appends to the end of the file (`write(2)` with `O_APPEND`) and updates
+/// cached pages. Returns the offset where the data is written and the written
+/// length.
+pub fn append(
+    file: &Arc<dyn FileLike>,
+    buf: UserBuffer<'_>,
+    options: &OpenOptions,
+) -> Result<(usize, usize)> {
+    let (offset, written_len) = file.append(buf.clone(), options)?;
+    update_pages(file, offset, written_len, buf)?;
+    Ok((offset, written_len))
+}
+
+/// Copies data written into the file at `offset` into cached pages.
+fn update_pages(
+    file: &Arc<dyn FileLike>,
+    offset: usize,
+    written_len: usize,
+    buf: UserBuffer<'_>,
+) -> Result<()> {
+    let pages = collect_pages(file, offset, written_len, false);
+    let mut reader = UserBufReader::from(buf);
+    let mut result = Ok(());
+    for (page_offset, paddr) in pages {
+        if result.is_ok() {
+            let start = max(offset, page_offset);
+            let end = min(offset + written_len, page_offset + PAGE_SIZE);
+            let page = page_as_slice_mut(paddr);
+            result = reader
+                .skip(start - offset - reader.pos())
+                .and_then(|_| {
+                    reader.read_bytes(&mut page[(start - page_offset)..(end - page_offset)])
+                })
+                .map(|_| ());
+        }
+
+        unref_page(paddr);
+    }
+
+    result
+}
+
+/// Reloads cached pages in `[offset, offset + len)` from the file. It's called
+/// after the file is modified other than by `write(2)` (e.g. `truncate(2)`).
+pub fn reload(file: &Arc<dyn FileLike>, offset: usize, len: usize) -> Result<()> {
+    let mut result = Ok(());
+    for (page_offset, paddr) in collect_pages(file, offset, len, false) {
+        if result.is_ok() {
+            result = read_page(file, page_offset, paddr);
+        }
+
+        unref_page(paddr);
+    }
+
+    result
+}
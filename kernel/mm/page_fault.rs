@@ -1,31 +1,35 @@
-use kerla_utils::alignment::align_down;
+use kerla_utils::alignment::{align_down, is_aligned};
 
-use super::vm::VmAreaType;
+use super::{
+    page_cache,
+    vm::{Vm, VmArea, VmAreaType},
+};
 use crate::{
+    ctypes::{c_int, MMapProt},
     fs::opened_file::OpenOptions,
+    prelude::*,
     process::{
+        cgroup::{self, Cgroup},
         current_process,
-        signal::{self, SIGSEGV},
+        signal::{SigInfo, Signal, BUS_ADRERR, SEGV_ACCERR, SEGV_MAPERR, SIGBUS, SIGKILL, SIGSEGV},
         Process,
     },
 };
 use core::cmp::min;
-use core::slice;
+use core::{ptr, slice};
 use kerla_runtime::{
-    address::UserVAddr,
-    arch::{PageFaultReason, PAGE_SIZE},
-    page_allocator::{alloc_pages, AllocPageFlags},
+    address::{PAddr, UserVAddr},
+    arch::{PageFaultReason, PageProtection, PAGE_SIZE},
+    page_allocator::{alloc_pages, page_ref_count, unref_page, AllocPageFlags},
 };
 
-pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, _reason: PageFaultReason) {
+pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, reason: PageFaultReason) {
     let unaligned_vaddr = match unaligned_vaddr {
         Some(unaligned_vaddr) => unaligned_vaddr,
         None => {
-            debug_warn!(
-                "null pointer access (ip={:x}), killing the current process...",
-                ip
-            );
-            Process::exit_by_signal(signal::SIGSEGV);
+            debug_warn!("null pointer access (ip={:x}), sending SIGSEGV...", ip);
+            send_fault_signal(reason, SIGSEGV, SEGV_MAPERR, 0);
+            return;
         }
     };
 
@@ -35,11 +39,12 @@ pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, _reason:
         Ok(uaddr) => uaddr,
         _ => {
             debug_warn!(
-                "invalid memory access at {} (ip={:x}), killing the current process...",
+                "invalid memory access at {} (ip={:x}), sending SIGSEGV...",
                 unaligned_vaddr,
                 ip
             );
-            Process::exit_by_signal(SIGSEGV);
+            send_fault_signal(reason, SIGSEGV, SEGV_MAPERR, unaligned_vaddr.value());
+            return;
         }
     };
 
@@ -51,21 +56,215 @@ pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, _reason:
         .iter()
         .find(|vma| vma.contains(unaligned_vaddr))
     {
-        Some(vma) => vma,
+        Some(vma) => vma.clone(),
         None => {
             debug_warn!(
-                "no VMAs for address {} (ip={:x}), killing the current process...",
+                "no VMAs for address {} (ip={:x}), sending SIGSEGV...",
                 unaligned_vaddr,
                 ip,
             );
             drop(vm);
             drop(vm_ref);
-            Process::exit_by_signal(SIGSEGV);
+            send_fault_signal(reason, SIGSEGV, SEGV_MAPERR, unaligned_vaddr.value());
+            return;
+        }
+    };
+
+    // Check if the access is allowed in the area. x86 does not distinguish
+    // reads and executions from writable or executable pages.
+    let prot = vma.prot();
+    let allowed = if reason.contains(PageFaultReason::CAUSED_BY_WRITE) {
+        prot.contains(MMapProt::PROT_WRITE)
+    } else if reason.contains(PageFaultReason::CAUSED_BY_INST_FETCH) {
+        prot.contains(MMapProt::PROT_EXEC)
+    } else {
+        !prot.is_empty()
+    };
+
+    if !allowed {
+        debug_warn!(
+            "access violation at {} (ip={:x}, prot={:?}), sending SIGSEGV...",
+            unaligned_vaddr,
+            ip,
+            prot,
+        );
+        drop(vm);
+        drop(vm_ref);
+        send_fault_signal(reason, SIGSEGV, SEGV_ACCERR, unaligned_vaddr.value());
+        return;
+    }
+
+    let result = populate_page(&mut vm, &vma, unaligned_vaddr, aligned_vaddr, reason);
+    drop(vm);
+    drop(vm_ref);
+    match result {
+        Ok(()) => {}
+        Err(FaultError::MemoryLimit(memcg)) => memcg.oom_kill_current(),
+        Err(FaultError::OutOfMemory) => {
+            warn!(
+                "out of memory, killing pid {} ({})",
+                current.pid().as_i32(),
+                current.cmdline().argv0()
+            );
+            Process::exit_by_signal(SIGKILL);
+        }
+        Err(FaultError::Io) => {
+            debug_warn!(
+                "failed to read the file mapped at {} (ip={:x}), sending SIGBUS...",
+                unaligned_vaddr,
+                ip,
+            );
+            send_fault_signal(reason, SIGBUS, BUS_ADRERR, unaligned_vaddr.value());
         }
+    }
+}
+
+/// Why `populate_page` failed.
+pub(super) enum FaultError {
+    /// The control group would exceed its memory limit.
+    MemoryLimit(Arc<Cgroup>),
+    /// No free pages.
+    OutOfMemory,
+    /// Failed to read the mapped file.
+    Io,
+}
+
+/// Returns the physical page mapped at `vaddr` in `vm` regardless of the page
+/// protection (e.g. to insert a breakpoint by `ptrace(2)`). The page is filled
+/// if it's not yet mapped, and copied first if `write` is `true` and it's
+/// shared with others. Returns `EIO` if no VMAs contain `vaddr`.
+pub fn lookup_page_forcibly(vm: &mut Vm, vaddr: UserVAddr, write: bool) -> Result<PAddr> {
+    let aligned_vaddr = UserVAddr::new_nonnull(align_down(vaddr.value(), PAGE_SIZE))
+        .map_err(|_| Error::new(Errno::EIO))?;
+    let vma = match vm.vm_areas().iter().find(|vma| vma.contains(vaddr)) {
+        Some(vma) => vma.clone(),
+        None => return Err(Errno::EIO.into()),
+    };
+
+    let reason = match (vm.page_table().lookup_user_page(aligned_vaddr), write) {
+        (Some(paddr), false) => return Ok(paddr),
+        (Some(_), true) => PageFaultReason::PRESENT | PageFaultReason::CAUSED_BY_WRITE,
+        (None, false) => PageFaultReason::empty(),
+        (None, true) => PageFaultReason::CAUSED_BY_WRITE,
     };
 
+    populate_page(vm, &vma, vaddr, aligned_vaddr, reason).map_err(|err| match err {
+        FaultError::MemoryLimit(_) | FaultError::OutOfMemory => Error::new(Errno::ENOMEM),
+        FaultError::Io => Error::new(Errno::EIO),
+    })?;
+    vm.page_table()
+        .lookup_user_page(aligned_vaddr)
+        .ok_or_else(|| Error::new(Errno::EIO))
+}
+
+/// Reads or writes the memory at `addr` in `vm` (which may be another
+/// process's one) regardless of the page protection.
+pub fn access_memory_forcibly(vm: &mut Vm, addr: usize, buf: &mut [u8], write: bool) -> Result<()> {
+    let mut offset = 0;
+    while offset < buf.len() {
+        let vaddr = addr
+            .checked_add(offset)
+            .and_then(UserVAddr::new)
+            .ok_or(Errno::EIO)?;
+        let paddr = lookup_page_forcibly(vm, vaddr, write)?;
+        let offset_in_page = vaddr.value() % PAGE_SIZE;
+        let len = min(PAGE_SIZE - offset_in_page, buf.len() - offset);
+        let page = paddr.add(offset_in_page);
+        unsafe {
+            if write {
+                ptr::copy_nonoverlapping(buf[offset..].as_ptr(), page.as_mut_ptr::<u8>(), len);
+            } else {
+                ptr::copy_nonoverlapping(page.as_ptr::<u8>(), buf[offset..].as_mut_ptr(), len);
+            }
+        }
+
+        offset += len;
+    }
+
+    Ok(())
+}
+
+/// Maps a page at `aligned_vaddr` in `vma` as requested in `reason`: fills a
+/// new page, or makes a present page writable by copy-on-write.
+///
+/// New pages are charged to the current process's control group.
+fn populate_page(
+    vm: &mut Vm,
+    vma: &VmArea,
+    unaligned_vaddr: UserVAddr,
+    aligned_vaddr: UserVAddr,
+    reason: PageFaultReason,
+) -> core::result::Result<(), FaultError> {
+    let prot = vma.prot();
+    if reason.contains(PageFaultReason::PRESENT | PageFaultReason::CAUSED_BY_WRITE) {
+        let page_table = vm.page_table_mut();
+        if let Some(mapped_paddr) = page_table.lookup_user_page(aligned_vaddr) {
+            if let VmAreaType::File {
+                file,
+                offset,
+                shared: true,
+                ..
+            } = vma.area_type()
+            {
+                // A page in the page cache shared with other processes. Make it
+                // writable and write back to the file later.
+                page_cache::mark_dirty(file, offset + vma.offset_in_vma(aligned_vaddr));
+                page_table.map_user_page(aligned_vaddr, mapped_paddr, vma.page_protection());
+            } else if page_ref_count(mapped_paddr) == 1 {
+                // No one else shares the page. Just make it writable.
+                page_table.map_user_page(aligned_vaddr, mapped_paddr, vma.page_protection());
+            } else {
+                // The page is shared with other processes by fork(2) or the
+                // page cache. Copy the page to make it writable (copy-on-write).
+                let paddr = copy_page(mapped_paddr)?;
+                page_table.map_user_page(aligned_vaddr, paddr, vma.page_protection());
+                unref_page(mapped_paddr);
+            }
+
+            return Ok(());
+        }
+    }
+
+    // Use the page in the page cache if the whole page is backed by the file.
+    if let VmAreaType::File {
+        file,
+        offset,
+        file_size,
+        shared,
+        ..
+    } = vma.area_type()
+    {
+        if aligned_vaddr >= vma.start() {
+            let offset_in_vma = vma.offset_in_vma(aligned_vaddr);
+            let offset_in_file = offset + offset_in_vma;
+            if is_aligned(offset_in_file, PAGE_SIZE)
+                && (*shared || offset_in_vma + PAGE_SIZE <= *file_size)
+            {
+                let writable = *shared && prot.contains(MMapProt::PROT_WRITE);
+                let cached_paddr = page_cache::get_page(file, offset_in_file, writable)?;
+
+                let mut page_prot = vma.page_protection();
+                let paddr = if *shared {
+                    cached_paddr
+                } else if reason.contains(PageFaultReason::CAUSED_BY_WRITE) {
+                    let paddr = copy_page(cached_paddr);
+                    unref_page(cached_paddr);
+                    paddr?
+                } else {
+                    // Copy the page on the first write.
+                    page_prot.remove(PageProtection::WRITABLE);
+                    cached_paddr
+                };
+
+                vm.page_table_mut()
+                    .map_user_page(aligned_vaddr, paddr, page_prot);
+                return Ok(());
+            }
+        }
+    }
+
     // Allocate and fill the page.
-    let paddr = alloc_pages(1, AllocPageFlags::USER).expect("failed to allocate an anonymous page");
+    let paddr = alloc_user_page(AllocPageFlags::USER)?;
     unsafe {
         paddr.as_mut_ptr::<u8>().write_bytes(0, PAGE_SIZE);
     }
@@ -75,6 +274,7 @@ pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, _reason:
             file,
             offset,
             file_size,
+            ..
         } => {
             let buf = unsafe { slice::from_raw_parts_mut(paddr.as_mut_ptr(), PAGE_SIZE) };
             let offset_in_page;
@@ -97,16 +297,58 @@ pub fn handle_page_fault(unaligned_vaddr: Option<UserVAddr>, ip: usize, _reason:
             }
 
             if copy_len > 0 {
-                file.read(
+                let result = file.read(
                     offset_in_file,
                     (&mut buf[offset_in_page..(offset_in_page + copy_len)]).into(),
                     &OpenOptions::readwrite(),
-                )
-                .expect("failed to read file");
+                );
+
+                if result.is_err() {
+                    unref_page(paddr);
+                    return Err(FaultError::Io);
+                }
             }
         }
     }
 
     // Map the page in the page table.
-    vm.page_table_mut().map_user_page(aligned_vaddr, paddr);
+    vm.page_table_mut()
+        .map_user_page(aligned_vaddr, paddr, vma.page_protection());
+    Ok(())
+}
+
+/// Sends `SIGSEGV` or `SIGBUS` to the current process. It's delivered when the
+/// process returns to the userspace.
+///
+/// If the fault has occurred in the kernel (i.e. while copying from/to the
+/// user memory), the process is killed immediately since we can't continue
+/// the system call.
+fn send_fault_signal(reason: PageFaultReason, signal: Signal, code: c_int, addr: usize) {
+    if !reason.contains(PageFaultReason::CAUSED_BY_USER) {
+        Process::exit_by_signal(signal);
+    }
+
+    current_process().force_signal(SigInfo::fault(signal, code, addr));
+}
+
+/// Allocates a page for the current process and charges it to its control
+/// group.
+pub(super) fn alloc_user_page(flags: AllocPageFlags) -> core::result::Result<PAddr, FaultError> {
+    let paddr = alloc_pages(1, flags).map_err(|_| FaultError::OutOfMemory)?;
+    if let Err(memcg) = cgroup::try_charge_page(paddr) {
+        unref_page(paddr);
+        return Err(FaultError::MemoryLimit(memcg));
+    }
+
+    Ok(paddr)
+}
+
+/// Allocates a new page and copies the contents of `src` into it.
+fn copy_page(src: PAddr) -> core::result::Result<PAddr, FaultError> {
+    let paddr = alloc_user_page(AllocPageFlags::USER | AllocPageFlags::DIRTY_OK)?;
+    unsafe {
+        ptr::copy_nonoverlapping::<u8>(src.as_ptr(), paddr.as_mut_ptr(), PAGE_SIZE);
+    }
+
+    Ok(paddr)
 }
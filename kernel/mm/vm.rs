@@ -1,13 +1,15 @@
 use crate::fs::inode::FileLike;
 use crate::{
     arch::{USER_STACK_TOP, USER_VALLOC_BASE, USER_VALLOC_END},
+    ctypes::MMapProt,
     result::{Errno, Result},
 };
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use kerla_runtime::{
     address::UserVAddr,
-    arch::{PageTable, PAGE_SIZE},
+    arch::{PageProtection, PageTable, PAGE_SIZE},
+    page_allocator::{page_ref_count, unref_page},
 };
 use kerla_utils::alignment::{align_up, is_aligned};
 
@@ -18,14 +20,28 @@ pub enum VmAreaType {
         file: Arc<dyn FileLike>,
         offset: usize,
         file_size: usize,
+        /// `MAP_SHARED` or not: pages in the page cache are mapped directly
+        /// and modifications are written back to the file.
+        shared: bool,
+        /// The file is opened for writing: a shared mapping can't be made
+        /// writable otherwise.
+        writable: bool,
     },
 }
 
+/// A range of a file mapped into the memory space.
+pub struct FileRange {
+    pub file: Arc<dyn FileLike>,
+    pub offset: usize,
+    pub len: usize,
+}
+
 #[derive(Clone)]
 pub struct VmArea {
     start: UserVAddr,
     len: usize,
     area_type: VmAreaType,
+    prot: MMapProt,
 }
 
 impl VmArea {
@@ -33,6 +49,25 @@ impl VmArea {
         &self.area_type
     }
 
+    pub fn prot(&self) -> MMapProt {
+        self.prot
+    }
+
+    /// The page table permissions of pages in this area.
+    pub fn page_protection(&self) -> PageProtection {
+        let mut prot = PageProtection::empty();
+        if self.prot.contains(MMapProt::PROT_READ) {
+            prot |= PageProtection::READABLE;
+        }
+        if self.prot.contains(MMapProt::PROT_WRITE) {
+            prot |= PageProtection::WRITABLE;
+        }
+        if self.prot.contains(MMapProt::PROT_EXEC) {
+            prot |= PageProtection::EXECUTABLE;
+        }
+        prot
+    }
+
     pub fn start(&self) -> UserVAddr {
         self.start
     }
@@ -51,7 +86,51 @@ impl VmArea {
     }
 
     pub fn overlaps(&self, other: UserVAddr, len: usize) -> bool {
-        self.start.value() <= other.value() + len && other.value() < self.start.value() + self.len
+        self.start.value() < other.value() + len && other.value() < self.start.value() + self.len
+    }
+
+    /// Splits the area at `vaddr`. `self` is shrinked to `[start, vaddr)` and
+    /// the returned area covers `[vaddr, end)`.
+    fn split_at(&mut self, vaddr: UserVAddr) -> VmArea {
+        debug_assert!(self.contains(vaddr));
+        let offset_in_vma = self.offset_in_vma(vaddr);
+        let area_type = match &self.area_type {
+            VmAreaType::Anonymous => VmAreaType::Anonymous,
+            VmAreaType::File {
+                file,
+                offset,
+                file_size,
+                shared,
+                writable,
+            } => VmAreaType::File {
+                file: file.clone(),
+                offset: offset + offset_in_vma,
+                file_size: file_size.saturating_sub(offset_in_vma),
+                shared: *shared,
+                writable: *writable,
+            },
+        };
+
+        let right = VmArea {
+            start: vaddr,
+            len: self.len - offset_in_vma,
+            area_type,
+            prot: self.prot,
+        };
+
+        self.len = offset_in_vma;
+        right
+    }
+
+    /// Returns true if `next` follows this area and they can be merged into
+    /// one area.
+    fn is_mergeable_with(&self, next: &VmArea) -> bool {
+        self.end() == next.start
+            && self.prot == next.prot
+            && matches!(
+                (&self.area_type, &next.area_type),
+                (VmAreaType::Anonymous, VmAreaType::Anonymous)
+            )
     }
 }
 
@@ -59,6 +138,8 @@ pub struct Vm {
     page_table: PageTable,
     vm_areas: Vec<VmArea>,
     valloc_next: UserVAddr,
+    /// The maximum size of the address space in bytes (`RLIMIT_AS`).
+    address_space_limit: usize,
 }
 
 impl Vm {
@@ -70,12 +151,14 @@ impl Vm {
             start: stack_bottom,
             len: USER_STACK_TOP.value() - stack_bottom.value(),
             area_type: VmAreaType::Anonymous,
+            prot: MMapProt::PROT_READ | MMapProt::PROT_WRITE,
         };
 
         let heap_vma = VmArea {
             start: heap_bottom,
             len: 0,
             area_type: VmAreaType::Anonymous,
+            prot: MMapProt::PROT_READ | MMapProt::PROT_WRITE,
         };
 
         Ok(Vm {
@@ -84,6 +167,7 @@ impl Vm {
             // and `heap_vma_mut` depends on it.
             vm_areas: vec![stack_vma, heap_vma],
             valloc_next: USER_VALLOC_BASE,
+            address_space_limit: usize::MAX,
         })
     }
 
@@ -116,6 +200,7 @@ impl Vm {
         start: UserVAddr,
         len: usize,
         area_type: VmAreaType,
+        prot: MMapProt,
     ) -> Result<()> {
         start.access_ok(len)?;
 
@@ -123,15 +208,170 @@ impl Vm {
             return Err(Errno::EINVAL.into());
         }
 
+        self.check_address_space_limit(len)?;
         self.vm_areas.push(VmArea {
             start,
             len,
             area_type,
+            prot,
         });
 
         Ok(())
     }
 
+    /// Removes mappings in `[start, start + len)`: splits areas across the
+    /// boundaries, removes areas in the range and frees their pages.
+    ///
+    /// Returns the ranges of unmapped files. Pass them to
+    /// [`page_cache::release`](super::page_cache::release) after unlocking
+    /// the `Vm` to write back and evict cached pages.
+    pub fn unmap(&mut self, start: UserVAddr, len: usize) -> Result<Vec<FileRange>> {
+        debug_assert!(is_aligned(start.value(), PAGE_SIZE));
+        let len = align_up(len, PAGE_SIZE);
+        start.access_ok(len)?;
+
+        self.split_vm_areas(start, len);
+        let mut file_ranges = Vec::new();
+        for area in &mut self.vm_areas {
+            if start <= area.start && area.end() <= start.add(len) {
+                if let VmAreaType::File { file, offset, .. } = &area.area_type {
+                    file_ranges.push(FileRange {
+                        file: file.clone(),
+                        offset: *offset,
+                        len: area.len,
+                    });
+                }
+
+                area.len = 0;
+            }
+        }
+
+        // The stack and heap areas are referred by their indices. Keep them
+        // as empty ones.
+        let mut i = 0;
+        self.vm_areas.retain(|area| {
+            i += 1;
+            i <= 2 || area.len > 0
+        });
+
+        // Free the pages after TLB is flushed in all CPUs.
+        for paddr in self.page_table.unmap_user_pages(start, len) {
+            unref_page(paddr);
+        }
+
+        Ok(file_ranges)
+    }
+
+    /// Returns the ranges of files mapped with `MAP_SHARED`.
+    pub fn shared_file_ranges(&self) -> Vec<FileRange> {
+        self.vm_areas
+            .iter()
+            .filter_map(|area| match &area.area_type {
+                VmAreaType::File {
+                    file,
+                    offset,
+                    shared: true,
+                    ..
+                } if area.len > 0 => Some(FileRange {
+                    file: file.clone(),
+                    offset: *offset,
+                    len: area.len,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Changes the protection of `[start, start + len)`. Returns `ENOMEM` if
+    /// the range contains unmapped pages, or `EACCES` if it makes a shared
+    /// mapping of a file not opened for writing writable.
+    pub fn protect(&mut self, start: UserVAddr, len: usize, prot: MMapProt) -> Result<()> {
+        debug_assert!(is_aligned(start.value(), PAGE_SIZE));
+        let len = align_up(len, PAGE_SIZE);
+        start.access_ok(len)?;
+
+        if !self.is_mapped_vaddr_range(start, len) {
+            return Err(Errno::ENOMEM.into());
+        }
+
+        if prot.contains(MMapProt::PROT_WRITE)
+            && self.vm_areas.iter().any(|area| {
+                area.overlaps(start, len)
+                    && matches!(
+                        area.area_type,
+                        VmAreaType::File {
+                            shared: true,
+                            writable: false,
+                            ..
+                        }
+                    )
+            })
+        {
+            return Err(Errno::EACCES.into());
+        }
+
+        let end = start.add(len);
+        self.split_vm_areas(start, len);
+        for area in &mut self.vm_areas {
+            if area.len > 0 && start <= area.start && area.end() <= end {
+                area.prot = prot;
+            }
+        }
+
+        // Update pages which have already been mapped.
+        let page_prot = self
+            .vm_areas
+            .iter()
+            .find(|area| area.contains(start))
+            .unwrap()
+            .page_protection();
+        self.page_table.protect_user_pages(start, len, |paddr| {
+            let mut page_prot = page_prot;
+            if page_ref_count(paddr) > 1 {
+                // The page is shared (copy-on-write). Keep it read-only
+                // until it's copied in the page fault handler.
+                page_prot.remove(PageProtection::WRITABLE);
+            }
+
+            page_prot
+        });
+
+        self.merge_vm_areas();
+        Ok(())
+    }
+
+    /// Splits areas across `start` or `start + len` so that every area is
+    /// either inside or outside the range.
+    fn split_vm_areas(&mut self, start: UserVAddr, len: usize) {
+        for boundary in [start, start.add(len)] {
+            let mut new_areas = Vec::new();
+            for area in &mut self.vm_areas {
+                if area.start < boundary && area.contains(boundary) {
+                    new_areas.push(area.split_at(boundary));
+                }
+            }
+
+            self.vm_areas.extend(new_areas);
+        }
+    }
+
+    /// Merges adjacent areas split by `split_vm_areas`.
+    fn merge_vm_areas(&mut self) {
+        // Sort areas except stack and heap ones since they're referred by
+        // their indices.
+        self.vm_areas[2..].sort_by_key(|area| area.start);
+
+        let mut i = 2;
+        while i + 1 < self.vm_areas.len() {
+            if self.vm_areas[i].is_mergeable_with(&self.vm_areas[i + 1]) {
+                let next = self.vm_areas.remove(i + 1);
+                self.vm_areas[i].len += next.len;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
     pub fn heap_end(&self) -> UserVAddr {
         self.heap_vma().end()
     }
@@ -148,30 +388,72 @@ impl Vm {
     pub fn expand_heap_by(&mut self, increment: usize) -> Result<()> {
         let stack_bottom = self.stack_vma().start();
         let increment = align_up(increment, PAGE_SIZE);
-        let heap_vma = self.heap_vma_mut();
-        let new_heap_top = heap_vma.end().add(increment);
+        let heap_end = self.heap_vma().end();
+        let new_heap_top = heap_end.add(increment);
 
         if new_heap_top >= stack_bottom {
             return Err(Errno::ENOMEM.into());
         }
 
-        heap_vma.len += increment;
+        if !self.is_free_vaddr_range(heap_end, increment) {
+            return Err(Errno::ENOMEM.into());
+        }
+
+        self.check_address_space_limit(increment)?;
+
+        self.heap_vma_mut().len += increment;
         Ok(())
     }
 
-    pub fn fork(&self) -> Result<Vm> {
+    pub fn fork(&mut self) -> Result<Vm> {
         Ok(Vm {
-            page_table: PageTable::duplicate_from(&self.page_table)?,
+            page_table: PageTable::duplicate_from(&mut self.page_table)?,
             vm_areas: self.vm_areas.clone(),
             valloc_next: self.valloc_next,
+            address_space_limit: self.address_space_limit,
         })
     }
 
+    /// Updates the maximum size of the address space (`RLIMIT_AS`). Existing
+    /// mappings are kept even if they exceed the new limit.
+    pub fn set_address_space_limit(&mut self, limit: usize) {
+        self.address_space_limit = limit;
+    }
+
+    /// The total size of the mapped areas in bytes.
+    fn address_space_size(&self) -> usize {
+        self.vm_areas.iter().map(|area| area.len).sum()
+    }
+
+    /// Returns `ENOMEM` if mapping `len` bytes more exceeds `RLIMIT_AS`.
+    fn check_address_space_limit(&self, len: usize) -> Result<()> {
+        match self.address_space_size().checked_add(len) {
+            Some(size) if size <= self.address_space_limit => Ok(()),
+            _ => Err(Errno::ENOMEM.into()),
+        }
+    }
+
     pub fn is_free_vaddr_range(&self, start: UserVAddr, len: usize) -> bool {
         self.vm_areas.iter().all(|area| !area.overlaps(start, len))
     }
 
+    /// Returns true if the whole range is covered by areas.
+    pub fn is_mapped_vaddr_range(&self, start: UserVAddr, len: usize) -> bool {
+        let end = start.add(len);
+        let mut next = start;
+        while next < end {
+            match self.vm_areas.iter().find(|area| area.contains(next)) {
+                Some(area) => next = area.end(),
+                None => return false,
+            }
+        }
+
+        true
+    }
+
     pub fn alloc_vaddr_range(&mut self, len: usize) -> Result<UserVAddr> {
+        self.check_address_space_limit(len)?;
+
         let next = self.valloc_next;
         self.valloc_next = self.valloc_next.add(align_up(len, PAGE_SIZE));
         if self.valloc_next >= USER_VALLOC_END {
@@ -0,0 +1,419 @@
+//! Control groups (cgroup v2).
+//!
+//! Processes are organized in a hierarchy of groups exposed by the cgroup file
+//! system. All controllers are enabled in every group:
+//!
+//! - memory: user pages allocated on page faults are charged to the group of
+//!   the faulting process until they're freed. A process exceeding
+//!   `memory.max` of its group or one of its ancestors is killed.
+//! - pids: `fork(2)` and `clone(2)` fail with `EAGAIN` if the number of threads
+//!   in the group and its descendants would exceed `pids.max`.
+//! - cpu: threads in the group share the CPU time in proportion to
+//!   `cpu.weight`, i.e. the weight is divided among its runnable threads.
+use core::{
+    cmp::{max, min},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use kerla_runtime::{address::PAddr, arch::PAGE_SIZE, spinlock::SpinLock};
+use kerla_utils::once::Once;
+
+use crate::{
+    prelude::*,
+    process::{
+        current_process, namespace::PidNamespace, signal::SIGKILL, PId, Process, ProcessState,
+    },
+};
+
+use super::{scheduler::SchedGroup, SCHEDULER};
+
+/// The smallest `cpu.weight`.
+pub const CPU_WEIGHT_MIN: u64 = 1;
+/// The default `cpu.weight`: the same share as the parent group.
+pub const CPU_WEIGHT_DEFAULT: u64 = 100;
+/// The largest `cpu.weight`.
+pub const CPU_WEIGHT_MAX: u64 = 10000;
+
+static ROOT_CGROUP: Once<Arc<Cgroup>> = Once::new();
+static NEXT_CGROUP_ID: AtomicUsize = AtomicUsize::new(1);
+/// The groups charged for user pages indexed by their physical addresses.
+static PAGE_OWNERS: SpinLock<BTreeMap<usize, Arc<Cgroup>>> = SpinLock::new(BTreeMap::new());
+
+/// A resource limited by a controller.
+#[derive(Debug, Copy, Clone)]
+enum Resource {
+    /// The memory in bytes.
+    Memory,
+    /// The number of threads.
+    Pids,
+}
+
+/// A control group.
+pub struct Cgroup {
+    id: usize,
+    name: String,
+    parent: Option<Arc<Cgroup>>,
+    children: SpinLock<BTreeMap<String, Arc<Cgroup>>>,
+    /// Threads in the group (excluding descendant groups) indexed by their
+    /// thread IDs.
+    threads: SpinLock<BTreeMap<PId, Weak<Process>>>,
+    /// The memory charged to the group and its descendants in bytes.
+    memory_current: AtomicUsize,
+    /// `memory.max` in bytes. `usize::MAX` if it's unlimited.
+    memory_max: AtomicUsize,
+    /// The number of processes killed due to `memory.max`.
+    oom_kills: AtomicUsize,
+    /// The number of threads in the group and its descendants.
+    pids_current: AtomicUsize,
+    /// `pids.max`. `usize::MAX` if it's unlimited.
+    pids_max: AtomicUsize,
+    cpu_weight: AtomicU64,
+    /// The scheduling states of the group. `None` if it's the root group.
+    sched_group: Option<Arc<SchedGroup>>,
+}
+
+impl Cgroup {
+    fn new(name: &str, parent: Option<Arc<Cgroup>>) -> Arc<Cgroup> {
+        // A new group has the default weight: the same as the parent.
+        let sched_group = parent
+            .as_ref()
+            .map(|parent| Arc::new(SchedGroup::new(parent.effective_cpu_weight())));
+
+        Arc::new(Cgroup {
+            id: NEXT_CGROUP_ID.fetch_add(1, Ordering::SeqCst),
+            name: name.to_owned(),
+            parent,
+            children: SpinLock::new(BTreeMap::new()),
+            threads: SpinLock::new(BTreeMap::new()),
+            memory_current: AtomicUsize::new(0),
+            memory_max: AtomicUsize::new(usize::MAX),
+            oom_kills: AtomicUsize::new(0),
+            pids_current: AtomicUsize::new(0),
+            pids_max: AtomicUsize::new(usize::MAX),
+            cpu_weight: AtomicU64::new(CPU_WEIGHT_DEFAULT),
+            sched_group,
+        })
+    }
+
+    /// The root group. Processes belong to it by default.
+    pub fn root() -> Arc<Cgroup> {
+        ROOT_CGROUP.clone()
+    }
+
+    /// The unique ID of the group. The root group's one is 1.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// The path from the root of the hierarchy (e.g. `/foo/bar`).
+    pub fn path(&self) -> String {
+        match &self.parent {
+            Some(parent) if parent.is_root() => format!("/{}", self.name),
+            Some(parent) => format!("{}/{}", parent.path(), self.name),
+            None => "/".to_owned(),
+        }
+    }
+
+    /// Creates a child group. Returns `EEXIST` if it already exists.
+    pub fn create_child(self: &Arc<Cgroup>, name: &str) -> Result<Arc<Cgroup>> {
+        let mut children = self.children.lock();
+        if children.contains_key(name) {
+            return Err(Errno::EEXIST.into());
+        }
+
+        let child = Cgroup::new(name, Some(self.clone()));
+        children.insert(name.to_owned(), child.clone());
+        Ok(child)
+    }
+
+    /// Removes a child group. Returns `EBUSY` if it still has threads or
+    /// child groups.
+    pub fn remove_child(&self, name: &str) -> Result<()> {
+        let mut children = self.children.lock();
+        let child = children.get(name).ok_or(Errno::ENOENT)?;
+        if child.pids_current() > 0 || !child.children.lock().is_empty() {
+            return Err(Errno::EBUSY.into());
+        }
+
+        children.remove(name);
+        Ok(())
+    }
+
+    pub fn child(&self, name: &str) -> Option<Arc<Cgroup>> {
+        self.children.lock().get(name).cloned()
+    }
+
+    pub fn child_names(&self) -> Vec<String> {
+        self.children.lock().keys().cloned().collect()
+    }
+
+    /// The group and its ancestors.
+    fn lineage(self: &Arc<Cgroup>) -> Vec<Arc<Cgroup>> {
+        let mut lineage = vec![self.clone()];
+        while let Some(parent) = lineage.last().unwrap().parent.clone() {
+            lineage.push(parent);
+        }
+
+        lineage
+    }
+
+    fn counter(&self, resource: Resource) -> &AtomicUsize {
+        match resource {
+            Resource::Memory => &self.memory_current,
+            Resource::Pids => &self.pids_current,
+        }
+    }
+
+    fn limit(&self, resource: Resource) -> usize {
+        match resource {
+            Resource::Memory => self.memory_max.load(Ordering::SeqCst),
+            Resource::Pids => self.pids_max.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Charges `amount` to the group and its ancestors. Returns the group
+    /// whose limit would be exceeded without charging anything.
+    fn try_charge(
+        self: &Arc<Cgroup>,
+        resource: Resource,
+        amount: usize,
+    ) -> core::result::Result<(), Arc<Cgroup>> {
+        let lineage = self.lineage();
+        for (i, group) in lineage.iter().enumerate() {
+            let prev = group.counter(resource).fetch_add(amount, Ordering::SeqCst);
+            if prev + amount > group.limit(resource) {
+                for charged in &lineage[..=i] {
+                    charged
+                        .counter(resource)
+                        .fetch_sub(amount, Ordering::SeqCst);
+                }
+
+                return Err(group.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Charges `amount` to the group and its ancestors regardless of the
+    /// limits.
+    fn force_charge(self: &Arc<Cgroup>, resource: Resource, amount: usize) {
+        for group in self.lineage() {
+            group.counter(resource).fetch_add(amount, Ordering::SeqCst);
+        }
+    }
+
+    fn uncharge(self: &Arc<Cgroup>, resource: Resource, amount: usize) {
+        for group in self.lineage() {
+            group.counter(resource).fetch_sub(amount, Ordering::SeqCst);
+        }
+    }
+
+    /// `memory.current`.
+    pub fn memory_current(&self) -> usize {
+        self.memory_current.load(Ordering::SeqCst)
+    }
+
+    /// `memory.max`. `None` if it's unlimited.
+    pub fn memory_max(&self) -> Option<usize> {
+        Some(self.memory_max.load(Ordering::SeqCst)).filter(|max| *max != usize::MAX)
+    }
+
+    /// Updates `memory.max`. Memory already charged is kept even if it
+    /// exceeds the new limit.
+    pub fn set_memory_max(&self, max: Option<usize>) {
+        self.memory_max
+            .store(max.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
+    /// The number of processes killed by the OOM killer due to `memory.max`.
+    pub fn oom_kills(&self) -> usize {
+        self.oom_kills.load(Ordering::SeqCst)
+    }
+
+    /// `pids.current`.
+    pub fn pids_current(&self) -> usize {
+        self.pids_current.load(Ordering::SeqCst)
+    }
+
+    /// `pids.max`. `None` if it's unlimited.
+    pub fn pids_max(&self) -> Option<usize> {
+        Some(self.pids_max.load(Ordering::SeqCst)).filter(|max| *max != usize::MAX)
+    }
+
+    /// Updates `pids.max`. Existing threads are kept even if they exceed the
+    /// new limit.
+    pub fn set_pids_max(&self, max: Option<usize>) {
+        self.pids_max
+            .store(max.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
+    /// `cpu.weight`.
+    pub fn cpu_weight(&self) -> u64 {
+        self.cpu_weight.load(Ordering::SeqCst)
+    }
+
+    /// Updates `cpu.weight` and the scheduling weights of the group and its
+    /// descendants.
+    pub fn set_cpu_weight(self: &Arc<Cgroup>, weight: u64) -> Result<()> {
+        if !(CPU_WEIGHT_MIN..=CPU_WEIGHT_MAX).contains(&weight) {
+            return Err(Errno::ERANGE.into());
+        }
+
+        self.cpu_weight.store(weight, Ordering::SeqCst);
+        self.update_sched_weights();
+        Ok(())
+    }
+
+    fn update_sched_weights(self: &Arc<Cgroup>) {
+        if let Some(sched_group) = &self.sched_group {
+            sched_group.set_weight(self.effective_cpu_weight());
+        }
+
+        let children: Vec<Arc<Cgroup>> = self.children.lock().values().cloned().collect();
+        for child in children {
+            child.update_sched_weights();
+        }
+    }
+
+    /// The scheduling states shared among threads in the group.
+    pub fn sched_group(&self) -> Option<Arc<SchedGroup>> {
+        self.sched_group.clone()
+    }
+
+    /// The scheduling weight of threads in the group relative to ones in the
+    /// root group: `cpu.weight` of the group and its ancestors multiplied
+    /// together. [`CPU_WEIGHT_DEFAULT`] means the same weight.
+    pub fn effective_cpu_weight(self: &Arc<Cgroup>) -> u64 {
+        self.lineage().iter().filter(|group| !group.is_root()).fold(
+            CPU_WEIGHT_DEFAULT,
+            |weight, group| {
+                let weight = weight * group.cpu_weight() / CPU_WEIGHT_DEFAULT;
+                min(max(weight, CPU_WEIGHT_MIN), CPU_WEIGHT_MAX)
+            },
+        )
+    }
+
+    /// The process IDs of processes in the group seen from `pid_ns`. Ones not
+    /// visible from the namespace are omitted.
+    pub fn procs(&self, pid_ns: &PidNamespace) -> Vec<PId> {
+        let pids: BTreeSet<PId> = self
+            .threads
+            .lock()
+            .values()
+            .filter_map(|thread| thread.upgrade())
+            .map(|thread| pid_ns.pid_of(&thread))
+            .filter(|pid| pid.as_i32() != 0)
+            .collect();
+
+        pids.into_iter().collect()
+    }
+
+    /// Moves all threads in the process into the group. The memory charged
+    /// so far stays in the original group.
+    pub fn attach(self: &Arc<Cgroup>, proc: &Process) {
+        let threads: Vec<Arc<Process>> = proc
+            .thread_group()
+            .threads()
+            .iter()
+            .filter_map(|thread| thread.upgrade())
+            .collect();
+
+        for thread in threads {
+            self.attach_thread(&thread);
+        }
+    }
+
+    fn attach_thread(self: &Arc<Cgroup>, thread: &Arc<Process>) {
+        {
+            let mut cgroup = thread.cgroup_mut();
+            if Arc::ptr_eq(&cgroup, self) || matches!(thread.state(), ProcessState::ExitedWith(_)) {
+                return;
+            }
+
+            cgroup.threads.lock().remove(&thread.tid());
+            cgroup.uncharge(Resource::Pids, 1);
+            // Like Linux, migrating a process may exceed the limit.
+            self.force_charge(Resource::Pids, 1);
+            self.threads
+                .lock()
+                .insert(thread.tid(), Arc::downgrade(thread));
+            *cgroup = self.clone();
+        }
+
+        SCHEDULER.set_group(thread, self.sched_group());
+    }
+
+    /// Charges a new thread to the group. Returns `EAGAIN` if it exceeds
+    /// `pids.max` of the group or one of its ancestors.
+    pub(super) fn try_charge_thread(self: &Arc<Cgroup>) -> Result<()> {
+        self.try_charge(Resource::Pids, 1)
+            .map_err(|_| Error::new(Errno::EAGAIN))
+    }
+
+    /// Kills the current process which has exceeded `memory.max` of the
+    /// group.
+    pub fn oom_kill_current(&self) -> ! {
+        let current = current_process();
+        warn!(
+            "cgroup {}: memory limit exceeded, killing pid {} ({})",
+            self.path(),
+            current.pid().as_i32(),
+            current.cmdline().argv0()
+        );
+
+        self.oom_kills.fetch_add(1, Ordering::SeqCst);
+        Process::exit_by_signal(SIGKILL);
+    }
+}
+
+/// Adds a new thread charged by [`Cgroup::try_charge_thread`] into its group.
+pub(super) fn add_thread(thread: &Arc<Process>) {
+    thread
+        .cgroup()
+        .threads
+        .lock()
+        .insert(thread.tid(), Arc::downgrade(thread));
+}
+
+/// Updates the thread ID of a thread which has taken over its thread group
+/// leader in `execve(2)`.
+pub(super) fn change_thread_id(thread: &Arc<Process>, old_tid: PId) {
+    let cgroup = thread.cgroup();
+    let mut threads = cgroup.threads.lock();
+    threads.remove(&old_tid);
+    threads.insert(thread.tid(), Arc::downgrade(thread));
+}
+
+/// Removes an exited thread from its group.
+pub(super) fn remove_exited_thread(thread: &Process) {
+    let cgroup = thread.cgroup_mut();
+    cgroup.threads.lock().remove(&thread.tid());
+    cgroup.uncharge(Resource::Pids, 1);
+}
+
+/// Charges a user page allocated on behalf of the current process to its
+/// group. Returns the group whose `memory.max` would be exceeded.
+pub fn try_charge_page(paddr: PAddr) -> core::result::Result<(), Arc<Cgroup>> {
+    let cgroup = current_process().cgroup();
+    cgroup.try_charge(Resource::Memory, PAGE_SIZE)?;
+    PAGE_OWNERS.lock().insert(paddr.value(), cgroup);
+    Ok(())
+}
+
+/// Uncharges a freed page if it has been charged by [`try_charge_page`].
+pub fn uncharge_page(paddr: PAddr) {
+    let owner = PAGE_OWNERS.lock().remove(&paddr.value());
+    if let Some(cgroup) = owner {
+        cgroup.uncharge(Resource::Memory, PAGE_SIZE);
+    }
+}
+
+pub fn init() {
+    ROOT_CGROUP.init(|| Cgroup::new("", None));
+}
@@ -0,0 +1,197 @@
+//! Core dumps of processes killed by signals.
+use core::{mem::size_of, ptr, slice};
+
+use crate::{
+    ctypes::MMapProt,
+    fs::{
+        inode::{FileLike, INode},
+        opened_file::OpenOptions,
+        stat::FileMode,
+    },
+    prelude::*,
+    process::{current_process, rlimit::RLIMIT_CORE},
+};
+use goblin::elf64::{
+    header::{
+        Header, EI_CLASS, EI_DATA, EI_VERSION, ELFCLASS64, ELFDATA2LSB, ELFMAG, EM_X86_64, ET_CORE,
+        EV_CURRENT,
+    },
+    program_header::{ProgramHeader, PF_R, PF_W, PF_X, PT_LOAD},
+};
+use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
+use kerla_utils::alignment::align_up;
+
+/// The name of the core dump file created in the current working directory.
+const CORE_FILE_NAME: &str = "core";
+
+/// A memory area to be dumped.
+struct Segment {
+    start: usize,
+    end: usize,
+    prot: MMapProt,
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// Writes `buf` at `offset` in the core file as long as it fits in `limit`.
+fn write_limited(file: &Arc<dyn FileLike>, offset: usize, buf: &[u8], limit: usize) -> Result<()> {
+    if offset >= limit {
+        return Ok(());
+    }
+
+    let len = core::cmp::min(buf.len(), limit - offset);
+    file.write(offset, buf[..len].into(), &OpenOptions::readwrite())?;
+    Ok(())
+}
+
+/// Opens the core file in the current working directory, truncating the
+/// existing one.
+fn open_core_file() -> Result<Arc<dyn FileLike>> {
+    let current = current_process();
+    let creds = current.credentials();
+    let cwd = current.root_fs().lock().cwd_path().inode.clone();
+    let dir = cwd.as_dir()?;
+    let inode = match dir.create_file(CORE_FILE_NAME, FileMode::new(0o600), creds.euid, creds.egid)
+    {
+        Ok(inode) => inode,
+        Err(err) if err.errno() == Errno::EEXIST => dir.lookup(CORE_FILE_NAME)?,
+        Err(err) => return Err(err),
+    };
+
+    match inode {
+        INode::FileLike(file) => {
+            file.truncate(0)?;
+            Ok(file)
+        }
+        _ => Err(Errno::EISDIR.into()),
+    }
+}
+
+/// Writes the memory of the current process into an ELF core file. Pages
+/// which have never been touched are left as holes. The file is cut off at
+/// `RLIMIT_CORE`.
+fn write_core(limit: usize) -> Result<()> {
+    let current = current_process();
+    let vm = current.vm().as_ref().cloned().ok_or(Errno::ESRCH)?;
+    let segments: Vec<Segment> = vm
+        .lock()
+        .vm_areas()
+        .iter()
+        .map(|area| Segment {
+            start: area.start().value(),
+            end: area.end().value(),
+            prot: area.prot(),
+        })
+        .collect();
+
+    let file = open_core_file()?;
+
+    let mut header = Header::default();
+    header.e_ident[..ELFMAG.len()].copy_from_slice(ELFMAG);
+    header.e_ident[EI_CLASS] = ELFCLASS64;
+    header.e_ident[EI_DATA] = ELFDATA2LSB;
+    header.e_ident[EI_VERSION] = EV_CURRENT;
+    header.e_type = ET_CORE;
+    header.e_machine = EM_X86_64;
+    header.e_version = EV_CURRENT as u32;
+    header.e_phoff = size_of::<Header>() as u64;
+    header.e_ehsize = size_of::<Header>() as u16;
+    header.e_phentsize = size_of::<ProgramHeader>() as u16;
+    header.e_phnum = segments.len() as u16;
+    write_limited(&file, 0, as_bytes(&header), limit)?;
+
+    // Segments are placed at page-aligned offsets after the headers.
+    let headers_len = size_of::<Header>() + segments.len() * size_of::<ProgramHeader>();
+    let mut data_offset = align_up(headers_len, PAGE_SIZE);
+    let mut phdr_offset = size_of::<Header>();
+    let mut data_offsets = Vec::with_capacity(segments.len());
+    for segment in &segments {
+        let mut flags = 0;
+        if segment.prot.contains(MMapProt::PROT_READ) {
+            flags |= PF_R;
+        }
+        if segment.prot.contains(MMapProt::PROT_WRITE) {
+            flags |= PF_W;
+        }
+        if segment.prot.contains(MMapProt::PROT_EXEC) {
+            flags |= PF_X;
+        }
+
+        let len = (segment.end - segment.start) as u64;
+        let phdr = ProgramHeader {
+            p_type: PT_LOAD,
+            p_flags: flags,
+            p_offset: data_offset as u64,
+            p_vaddr: segment.start as u64,
+            p_paddr: 0,
+            p_filesz: len,
+            p_memsz: len,
+            p_align: PAGE_SIZE as u64,
+        };
+        write_limited(&file, phdr_offset, as_bytes(&phdr), limit)?;
+        data_offsets.push(data_offset);
+        phdr_offset += size_of::<ProgramHeader>();
+        data_offset += len as usize;
+    }
+
+    let mut page = vec![0; PAGE_SIZE];
+    for (segment, offset) in segments.iter().zip(data_offsets) {
+        for vaddr in (segment.start..segment.end).step_by(PAGE_SIZE) {
+            let page_offset = offset + (vaddr - segment.start);
+            if page_offset >= limit {
+                return Ok(());
+            }
+
+            let present = {
+                let vm = vm.lock();
+                match vm
+                    .page_table()
+                    .lookup_user_page(UserVAddr::new_nonnull(vaddr)?)
+                {
+                    Some(paddr) => {
+                        unsafe {
+                            ptr::copy_nonoverlapping(
+                                paddr.as_ptr::<u8>(),
+                                page.as_mut_ptr(),
+                                PAGE_SIZE,
+                            );
+                        }
+                        true
+                    }
+                    None => false,
+                }
+            };
+
+            if present {
+                write_limited(&file, page_offset, &page, limit)?;
+            }
+        }
+    }
+
+    // Extend the file to cover trailing holes.
+    file.truncate(core::cmp::min(data_offset, limit))?;
+    Ok(())
+}
+
+/// Dumps the core of the current process if `RLIMIT_CORE` allows it. Returns
+/// `true` if the core file is written.
+pub fn dump_core() -> bool {
+    let limit = match current_process().rlimit(RLIMIT_CORE) {
+        Ok(limit) => limit.cur_as_usize(),
+        Err(_) => return false,
+    };
+
+    if limit == 0 {
+        return false;
+    }
+
+    match write_core(limit) {
+        Ok(()) => true,
+        Err(err) => {
+            debug_warn!("failed to dump the core: {:?}", err);
+            false
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{
+    fs::stat::{GId, Stat, UId},
+    prelude::*,
+};
+use bitflags::bitflags;
+
+/// The maximum number of supplementary groups.
+pub const NGROUPS_MAX: usize = 65536;
+
+bitflags! {
+    /// Permissions to be checked (`access(2)`).
+    pub struct AccessMode: u32 {
+        const R_OK = 4;
+        const W_OK = 2;
+        const X_OK = 1;
+    }
+}
+
+/// User and group IDs of a process.
+///
+/// The effective IDs are used for permission checks. The saved IDs allow an
+/// unprivileged process to switch back to the effective IDs it had on
+/// `execve(2)`.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub uid: UId,
+    pub euid: UId,
+    pub suid: UId,
+    pub gid: GId,
+    pub egid: GId,
+    pub sgid: GId,
+    /// Supplementary group IDs.
+    pub groups: Vec<GId>,
+}
+
+impl Credentials {
+    /// The credentials of the superuser.
+    pub fn root() -> Credentials {
+        Credentials {
+            uid: UId::ROOT,
+            euid: UId::ROOT,
+            suid: UId::ROOT,
+            gid: GId::ROOT,
+            egid: GId::ROOT,
+            sgid: GId::ROOT,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the process bypasses permission checks.
+    // TODO: Check capabilities instead.
+    pub fn is_privileged(&self) -> bool {
+        self.euid == UId::ROOT
+    }
+
+    /// Returns `true` if the process belongs to the group.
+    pub fn in_group(&self, gid: GId) -> bool {
+        self.egid == gid || self.groups.contains(&gid)
+    }
+
+    /// Credentials whose effective IDs are replaced with the real ones
+    /// (`access(2)`).
+    pub fn with_real_ids(&self) -> Credentials {
+        Credentials {
+            euid: self.uid,
+            egid: self.gid,
+            ..self.clone()
+        }
+    }
+
+    /// Checks if the process is permitted to access the file in `access`
+    /// mode. Returns `EACCES` if it's not.
+    pub fn check_access(&self, stat: &Stat, access: AccessMode) -> Result<()> {
+        let mode = stat.mode;
+        let (uid, gid) = (stat.uid, stat.gid);
+        let perm = mode.permission();
+
+        if self.is_privileged() {
+            // The superuser can execute a file only if any of execute bits is
+            // set.
+            if access.contains(AccessMode::X_OK) && !mode.is_directory() && perm & 0o111 == 0 {
+                return Err(Errno::EACCES.into());
+            }
+
+            return Ok(());
+        }
+
+        let granted = if self.euid == uid {
+            perm >> 6
+        } else if self.in_group(gid) {
+            perm >> 3
+        } else {
+            perm
+        };
+
+        if AccessMode::from_bits_truncate(granted & 0o7).contains(access) {
+            Ok(())
+        } else {
+            Err(Errno::EACCES.into())
+        }
+    }
+
+    /// Returns `true` if the process is permitted to send a signal to a
+    /// process with `target` credentials.
+    pub fn can_signal(&self, target: &Credentials) -> bool {
+        self.is_privileged()
+            || self.uid == target.uid
+            || self.uid == target.suid
+            || self.euid == target.uid
+            || self.euid == target.suid
+    }
+
+    /// Returns `true` if the process is permitted to change the scheduling
+    /// parameters of a process with `target` credentials.
+    pub fn can_reschedule(&self, target: &Credentials) -> bool {
+        self.is_privileged() || self.euid == target.uid || self.euid == target.euid
+    }
+
+    /// Returns `true` if the process is permitted to trace a process with
+    /// `target` credentials (or access its resource limits): all of its user
+    /// and group IDs must match ours.
+    pub fn can_trace(&self, target: &Credentials) -> bool {
+        self.is_privileged()
+            || (self.uid == target.uid
+                && self.uid == target.euid
+                && self.uid == target.suid
+                && self.gid == target.gid
+                && self.gid == target.egid
+                && self.gid == target.sgid)
+    }
+
+    /// Returns `true` if the process is the owner of the file or privileged.
+    pub fn owns(&self, stat: &Stat) -> bool {
+        let uid = stat.uid;
+        self.is_privileged() || self.euid == uid
+    }
+
+    /// `setuid(2)`.
+    pub fn set_uid(&mut self, uid: UId) -> Result<()> {
+        if self.is_privileged() {
+            self.uid = uid;
+            self.suid = uid;
+        } else if uid != self.uid && uid != self.suid {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.euid = uid;
+        Ok(())
+    }
+
+    /// `setgid(2)`.
+    pub fn set_gid(&mut self, gid: GId) -> Result<()> {
+        if self.is_privileged() {
+            self.gid = gid;
+            self.sgid = gid;
+        } else if gid != self.gid && gid != self.sgid {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.egid = gid;
+        Ok(())
+    }
+
+    /// `setresuid(2)`. `None` leaves the ID unchanged.
+    pub fn set_res_uid(
+        &mut self,
+        uid: Option<UId>,
+        euid: Option<UId>,
+        suid: Option<UId>,
+    ) -> Result<()> {
+        let (cur_uid, cur_euid, cur_suid) = (self.uid, self.euid, self.suid);
+        let permitted = |id: Option<UId>| match id {
+            Some(id) => id == cur_uid || id == cur_euid || id == cur_suid,
+            None => true,
+        };
+
+        let allowed = permitted(uid) && permitted(euid) && permitted(suid);
+        if !self.is_privileged() && !allowed {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.uid = uid.unwrap_or(self.uid);
+        self.euid = euid.unwrap_or(self.euid);
+        self.suid = suid.unwrap_or(self.suid);
+        Ok(())
+    }
+
+    /// `setresgid(2)`. `None` leaves the ID unchanged.
+    pub fn set_res_gid(
+        &mut self,
+        gid: Option<GId>,
+        egid: Option<GId>,
+        sgid: Option<GId>,
+    ) -> Result<()> {
+        let (cur_gid, cur_egid, cur_sgid) = (self.gid, self.egid, self.sgid);
+        let permitted = |id: Option<GId>| match id {
+            Some(id) => id == cur_gid || id == cur_egid || id == cur_sgid,
+            None => true,
+        };
+
+        let allowed = permitted(gid) && permitted(egid) && permitted(sgid);
+        if !self.is_privileged() && !allowed {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.gid = gid.unwrap_or(self.gid);
+        self.egid = egid.unwrap_or(self.egid);
+        self.sgid = sgid.unwrap_or(self.sgid);
+        Ok(())
+    }
+
+    /// `setreuid(2)`. `None` leaves the ID unchanged.
+    pub fn set_re_uid(&mut self, uid: Option<UId>, euid: Option<UId>) -> Result<()> {
+        let privileged = self.is_privileged();
+        if !privileged {
+            let uid_ok = uid.map_or(true, |id| id == self.uid || id == self.euid);
+            let euid_ok = euid.map_or(true, |id| {
+                id == self.uid || id == self.euid || id == self.suid
+            });
+            if !uid_ok || !euid_ok {
+                return Err(Errno::EPERM.into());
+            }
+        }
+
+        let old_uid = self.uid;
+        self.uid = uid.unwrap_or(self.uid);
+        self.euid = euid.unwrap_or(self.euid);
+        // The saved ID follows the effective one if the real ID is set or the
+        // effective ID differs from the previous real ID.
+        if uid.is_some() || euid.map_or(false, |id| id != old_uid) {
+            self.suid = self.euid;
+        }
+
+        Ok(())
+    }
+
+    /// `setregid(2)`. `None` leaves the ID unchanged.
+    pub fn set_re_gid(&mut self, gid: Option<GId>, egid: Option<GId>) -> Result<()> {
+        let privileged = self.is_privileged();
+        if !privileged {
+            let gid_ok = gid.map_or(true, |id| id == self.gid || id == self.egid);
+            let egid_ok = egid.map_or(true, |id| {
+                id == self.gid || id == self.egid || id == self.sgid
+            });
+            if !gid_ok || !egid_ok {
+                return Err(Errno::EPERM.into());
+            }
+        }
+
+        let old_gid = self.gid;
+        self.gid = gid.unwrap_or(self.gid);
+        self.egid = egid.unwrap_or(self.egid);
+        if gid.is_some() || egid.map_or(false, |id| id != old_gid) {
+            self.sgid = self.egid;
+        }
+
+        Ok(())
+    }
+
+    /// `setgroups(2)`.
+    pub fn set_groups(&mut self, groups: Vec<GId>) -> Result<()> {
+        if !self.is_privileged() {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.groups = groups;
+        Ok(())
+    }
+
+    /// Updates the credentials on `execve(2)`: the effective IDs are changed
+    /// to the file owner if the setuid (or setgid) bit is set, and the saved
+    /// IDs are set to the effective ones. The setuid and setgid bits are
+    /// ignored if `ignore_setid` is set (e.g. by `no_new_privs`).
+    pub fn exec(&mut self, stat: &Stat, ignore_setid: bool) {
+        let mode = stat.mode;
+        if mode.is_setuid() && !ignore_setid {
+            self.euid = stat.uid;
+        }
+
+        // The setgid bit without the group execute bit means mandatory
+        // locking, not set-group-ID.
+        if mode.is_setgid() && mode.permission() & 0o010 != 0 && !ignore_setid {
+            self.egid = stat.gid;
+        }
+
+        self.suid = self.euid;
+        self.sgid = self.egid;
+    }
+}
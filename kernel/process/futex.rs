@@ -0,0 +1,346 @@
+//! Fast userspace mutexes (`futex(2)`).
+//!
+//! Waiters are kept in a hashed table of wait queues. A futex is identified by
+//! the physical address for `MAP_SHARED` mappings (shared between processes) or
+//! by the pair of the address space and the virtual address otherwise.
+use super::{current_process, switch, Process, ProcessState};
+use crate::{
+    mm::vm::VmAreaType,
+    prelude::*,
+    timer::{cancel_timeout, is_timeout_expired, set_timeout, MonotonicClock},
+};
+use core::cmp::Ordering;
+use core::sync::atomic::{self, AtomicBool};
+use crossbeam::atomic::AtomicCell;
+use kerla_runtime::{
+    address::{PAddr, UserVAddr},
+    arch::PAGE_SIZE,
+    spinlock::{SpinLock, SpinLockGuard},
+};
+use kerla_utils::alignment::align_down;
+
+/// Wakes all waiters regardless of their bitsets.
+pub const FUTEX_BITSET_MATCH_ANY: u32 = 0xffff_ffff;
+
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
+/// The maximum number of entries in a robust list. It prevents an infinite
+/// loop on a corrupted list.
+const ROBUST_LIST_LIMIT: usize = 2048;
+
+const NUM_BUCKETS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: SpinLock<Vec<Arc<FutexWaiter>>> = SpinLock::new(Vec::new());
+static FUTEX_QUEUES: [SpinLock<Vec<Arc<FutexWaiter>>>; NUM_BUCKETS] = [EMPTY_BUCKET; NUM_BUCKETS];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FutexKey {
+    /// A futex in a `MAP_SHARED` mapping.
+    Shared(PAddr),
+    /// A futex only visible in the address space (the address of its `Vm`).
+    Private { vm: usize, uaddr: usize },
+}
+
+impl FutexKey {
+    /// Determines the key of the futex at `uaddr` in the current process.
+    pub fn new(uaddr: UserVAddr, private: bool) -> Result<FutexKey> {
+        if uaddr.value() % 4 != 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        let vm_ref = current.vm();
+        let vm_arc = vm_ref.as_ref().unwrap();
+        let vm = vm_arc.lock();
+        let vma = vm
+            .vm_areas()
+            .iter()
+            .find(|vma| vma.contains(uaddr))
+            .ok_or_else(|| Error::new(Errno::EFAULT))?;
+
+        if !private && matches!(vma.area_type(), VmAreaType::File { shared: true, .. }) {
+            let page = UserVAddr::new_nonnull(align_down(uaddr.value(), PAGE_SIZE))?;
+            if let Some(paddr) = vm.page_table().lookup_user_page(page) {
+                return Ok(FutexKey::Shared(paddr.add(uaddr.value() % PAGE_SIZE)));
+            }
+
+            // The page is not yet mapped. Fault it in and retry.
+            drop(vm);
+            drop(vm_ref);
+            uaddr.read::<u32>()?;
+            return FutexKey::new(uaddr, private);
+        }
+
+        Ok(FutexKey::Private {
+            vm: Arc::as_ptr(vm_arc) as usize,
+            uaddr: uaddr.value(),
+        })
+    }
+
+    fn bucket_index(&self) -> usize {
+        let (a, b) = match *self {
+            FutexKey::Shared(paddr) => (0, paddr.value()),
+            FutexKey::Private { vm, uaddr } => (vm, uaddr),
+        };
+
+        let hash = (a ^ b.rotate_left(17)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (hash >> 32) % NUM_BUCKETS
+    }
+
+    fn bucket(&self) -> SpinLockGuard<'static, Vec<Arc<FutexWaiter>>> {
+        FUTEX_QUEUES[self.bucket_index()].lock()
+    }
+}
+
+struct FutexWaiter {
+    process: Arc<Process>,
+    /// The futex the process waits for. Updated when it's requeued.
+    key: AtomicCell<FutexKey>,
+    bitset: u32,
+    woken: AtomicBool,
+}
+
+impl FutexWaiter {
+    fn wake(&self) {
+        self.woken.store(true, atomic::Ordering::SeqCst);
+        self.process.resume();
+    }
+}
+
+/// Removes `waiter` from its wait queue. Returns `false` if it has already been
+/// woken up.
+fn remove_waiter(waiter: &Arc<FutexWaiter>) -> bool {
+    loop {
+        let key = waiter.key.load();
+        let mut bucket = key.bucket();
+        if waiter.woken.load(atomic::Ordering::SeqCst) {
+            return false;
+        }
+
+        if let Some(index) = bucket.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            bucket.remove(index);
+            return true;
+        }
+
+        // The waiter is being requeued into another bucket. Try again.
+    }
+}
+
+/// Sleeps on the futex at `uaddr` if it contains `val`.
+///
+/// `timeout` is the deadline to wait until. It returns `ETIMEDOUT` if the
+/// timeout has been expired.
+pub fn futex_wait(
+    uaddr: UserVAddr,
+    key: FutexKey,
+    val: u32,
+    bitset: u32,
+    timeout: Option<MonotonicClock>,
+) -> Result<()> {
+    if bitset == 0 {
+        return Err(Errno::EINVAL.into());
+    }
+
+    // Read the value before locking the queue: it may cause a page fault.
+    uaddr.read::<u32>()?;
+
+    let current = current_process();
+    let waiter = Arc::new(FutexWaiter {
+        process: current.clone(),
+        key: AtomicCell::new(key),
+        bitset,
+        woken: AtomicBool::new(false),
+    });
+
+    {
+        let mut bucket = key.bucket();
+        // Check the value while holding the queue lock so that we never miss
+        // a wake up from a thread which has changed the value.
+        if uaddr.read::<u32>()? != val {
+            return Err(Errno::EAGAIN.into());
+        }
+
+        bucket.push(waiter.clone());
+    }
+
+    let timer = timeout.map(set_timeout);
+    let result = loop {
+        current.set_state(ProcessState::BlockedSignalable);
+
+        if waiter.woken.load(atomic::Ordering::SeqCst) {
+            break Ok(());
+        }
+
+        if current.has_pending_signals() {
+            break Err(Errno::EINTR.into());
+        }
+
+        if matches!(timer, Some(timer) if is_timeout_expired(timer)) {
+            break Err(Errno::ETIMEDOUT.into());
+        }
+
+        switch();
+    };
+
+    current.resume();
+    if let Some(timer) = timer {
+        cancel_timeout(timer);
+    }
+
+    match result {
+        // We've been woken up in the meantime.
+        Err(_) if !remove_waiter(&waiter) => Ok(()),
+        result => result,
+    }
+}
+
+/// Wakes up at most `max` waiters whose bitsets match `bitset`. Returns the
+/// number of woken waiters.
+pub fn futex_wake(key: FutexKey, max: usize, bitset: u32) -> Result<usize> {
+    if bitset == 0 {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let mut bucket = key.bucket();
+    let mut woken = 0;
+    bucket.retain(|waiter| {
+        if woken < max && waiter.key.load() == key && waiter.bitset & bitset != 0 {
+            waiter.wake();
+            woken += 1;
+            false
+        } else {
+            true
+        }
+    });
+
+    Ok(woken)
+}
+
+/// Wakes up at most `max_wake` waiters on `key` and moves at most
+/// `max_requeue` remaining waiters into `new_key`. If `expected` is `Some`,
+/// the futex at `uaddr` must contain the value (`FUTEX_CMP_REQUEUE`).
+pub fn futex_requeue(
+    uaddr: UserVAddr,
+    key: FutexKey,
+    new_key: FutexKey,
+    max_wake: usize,
+    max_requeue: usize,
+    expected: Option<u32>,
+) -> Result<usize> {
+    if expected.is_some() {
+        // Read the value before locking the queue: it may cause a page fault.
+        uaddr.read::<u32>()?;
+    }
+
+    // Lock buckets in the order of their indices to avoid dead locks.
+    let index = key.bucket_index();
+    let new_index = new_key.bucket_index();
+    let (mut bucket, mut new_bucket) = match index.cmp(&new_index) {
+        Ordering::Equal => (key.bucket(), None),
+        Ordering::Less => {
+            let bucket = key.bucket();
+            (bucket, Some(new_key.bucket()))
+        }
+        Ordering::Greater => {
+            let new_bucket = new_key.bucket();
+            (key.bucket(), Some(new_bucket))
+        }
+    };
+
+    if let Some(expected) = expected {
+        if uaddr.read::<u32>()? != expected {
+            return Err(Errno::EAGAIN.into());
+        }
+    }
+
+    let mut woken = 0;
+    let mut requeued = 0;
+    let mut moved = Vec::new();
+    bucket.retain(|waiter| {
+        if waiter.key.load() != key {
+            return true;
+        }
+
+        if woken < max_wake {
+            waiter.wake();
+            woken += 1;
+            false
+        } else if requeued < max_requeue {
+            waiter.key.store(new_key);
+            requeued += 1;
+            if new_bucket.is_some() {
+                moved.push(waiter.clone());
+                false
+            } else {
+                true
+            }
+        } else {
+            true
+        }
+    });
+
+    if let Some(new_bucket) = new_bucket.as_mut() {
+        new_bucket.extend(moved);
+    }
+
+    Ok(woken + requeued)
+}
+
+/// Marks the futex at `uaddr` as its owner died and wakes up a waiter if the
+/// exiting thread (`tid`) owns the futex.
+fn handle_futex_death(uaddr: UserVAddr, tid: u32) -> Result<()> {
+    let value = uaddr.read::<u32>()?;
+    if value & FUTEX_TID_MASK != tid {
+        return Ok(());
+    }
+
+    uaddr.write::<u32>(&((value & FUTEX_WAITERS) | FUTEX_OWNER_DIED))?;
+    if value & FUTEX_WAITERS != 0 {
+        futex_wake(FutexKey::new(uaddr, false)?, 1, FUTEX_BITSET_MATCH_ANY)?;
+    }
+
+    Ok(())
+}
+
+/// Releases futexes in the robust list (`set_robust_list(2)`) of the current
+/// thread.
+///
+/// ```text
+/// struct robust_list_head {
+///     struct robust_list *next;
+///     long futex_offset;
+///     struct robust_list *list_op_pending;
+/// };
+/// ```
+pub fn exit_robust_list(head: UserVAddr) -> Result<()> {
+    let current = current_process();
+    let tid = current.pid_ns().tid_of(current).as_i32() as u32;
+    let futex_offset = head.add(8).read::<isize>()?;
+    let futex_uaddr =
+        |entry: usize| UserVAddr::new_nonnull((entry as isize).wrapping_add(futex_offset) as usize);
+
+    // The lowest bit indicates that the futex is a PI futex.
+    let mut entry = head.read::<usize>()? & !1;
+    let pending = head.add(16).read::<usize>()? & !1;
+    for _ in 0..ROBUST_LIST_LIMIT {
+        if entry == head.value() {
+            break;
+        }
+
+        let next = UserVAddr::new_nonnull(entry)?.read::<usize>()? & !1;
+        if entry != pending {
+            handle_futex_death(futex_uaddr(entry)?, tid)?;
+        }
+
+        entry = next;
+    }
+
+    if pending != 0 {
+        handle_futex_death(futex_uaddr(pending)?, tid)?;
+    }
+
+    Ok(())
+}
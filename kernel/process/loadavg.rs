@@ -0,0 +1,94 @@
+//! The system load average: exponentially-damped moving averages of the
+//! number of runnable processes over 1, 5, and 15 minutes.
+use core::{
+    cmp::min,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use kerla_runtime::spinlock::SpinLock;
+
+use super::{process::PROCESSES, ProcessState};
+
+/// The number of bits of the fractional part of load averages.
+const FSHIFT: u32 = 11;
+/// 1.0 in the fixed-point representation.
+const FIXED_1: u64 = 1 << FSHIFT;
+/// `1 / exp(5sec / 1min)`, `1 / exp(5sec / 5min)`, and
+/// `1 / exp(5sec / 15min)` in the fixed-point representation.
+const EXP: [u64; 3] = [1884, 2014, 2037];
+/// The interval of updates in nanoseconds.
+const LOAD_FREQ: u64 = 5_000_000_000;
+
+/// The load averages in the fixed-point representation.
+static LOAD_AVG: SpinLock<[u64; 3]> = SpinLock::new([0; 3]);
+/// The monotonic clock when the load averages should be updated next.
+static NEXT_UPDATE: AtomicU64 = AtomicU64::new(LOAD_FREQ);
+
+/// The load averages and the number of runnable and all processes.
+pub struct LoadAverage {
+    /// The load averages over 1, 5, and 15 minutes multiplied by 100.
+    pub loads: [u64; 3],
+    pub runnable: usize,
+    pub total: usize,
+}
+
+fn calc_load(load: u64, exp: u64, active: u64) -> u64 {
+    (load * exp + active * (FIXED_1 - exp) + FIXED_1 / 2) >> FSHIFT
+}
+
+/// Returns the number of runnable processes and the number of all processes.
+fn count_processes() -> (usize, usize) {
+    let processes = PROCESSES.lock();
+    let runnable = processes
+        .values()
+        .filter(|proc| proc.state() == ProcessState::Runnable)
+        .count();
+    (runnable, processes.len())
+}
+
+/// Updates the load averages every 5 seconds. Called from the timer
+/// interrupt handler with the current monotonic clock in nanoseconds.
+pub fn update_load_average(now: u64) {
+    let next = NEXT_UPDATE.load(Ordering::Relaxed);
+    if now < next {
+        return;
+    }
+
+    // The timer interrupt may not occur for a while in the tickless idle.
+    // Decay the averages for each missed interval.
+    let periods = (now - next) / LOAD_FREQ + 1;
+    if NEXT_UPDATE
+        .compare_exchange(
+            next,
+            next + periods * LOAD_FREQ,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        // Another CPU is updating the averages.
+        return;
+    }
+
+    // An hour is long enough for all averages to converge.
+    let active = count_processes().0 as u64 * FIXED_1;
+    let mut load_avg = LOAD_AVG.lock();
+    for _ in 0..min(periods, 720) {
+        for (load, exp) in load_avg.iter_mut().zip(EXP) {
+            *load = calc_load(*load, exp, active);
+        }
+    }
+}
+
+/// Returns the current load averages.
+pub fn read_load_average() -> LoadAverage {
+    let loads = LOAD_AVG
+        .lock()
+        .map(|load| (load * 100 + FIXED_1 / 2) >> FSHIFT);
+    let (runnable, total) = count_processes();
+    LoadAverage {
+        loads,
+        runnable,
+        total,
+    }
+}
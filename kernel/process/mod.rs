@@ -1,23 +1,35 @@
 use alloc::sync::Arc;
 
-use kerla_runtime::{address::UserVAddr, spinlock::SpinLock};
+use kerla_runtime::address::UserVAddr;
 
 use kerla_utils::lazy::Lazy;
 use kerla_utils::once::Once;
 
+pub mod cgroup;
 mod cmdline;
+mod coredump;
+pub mod credentials;
 mod elf;
+pub mod futex;
 mod init_stack;
+pub mod loadavg;
+pub mod namespace;
+pub mod pidfd;
 #[allow(clippy::module_inception)]
 mod process;
 pub mod process_group;
-mod scheduler;
+pub mod ptrace;
+pub mod rlimit;
+pub mod scheduler;
+pub mod seccomp;
+pub mod session;
 pub mod signal;
 mod switch;
+pub mod thread_group;
 mod wait_queue;
 
-pub use process::{gc_exited_processes, read_process_stats, PId, Process, ProcessState};
-pub use switch::switch;
+pub use process::{gc_exited_processes, read_process_stats, PId, Process, ProcessState, WCOREFLAG};
+pub use switch::{preempt_if_needed, switch, yield_now};
 pub use wait_queue::WaitQueue;
 
 use self::scheduler::Scheduler;
@@ -31,7 +43,7 @@ cpu_local! {
     pub static ref IDLE_THREAD: Lazy<Arc<Process>> = Lazy::new();
 }
 
-static SCHEDULER: Once<SpinLock<Scheduler>> = Once::new();
+static SCHEDULER: Once<Scheduler> = Once::new();
 pub static JOIN_WAIT_QUEUE: Once<WaitQueue> = Once::new();
 
 pub fn current_process() -> &'static Arc<Process> {
@@ -39,8 +51,19 @@ pub fn current_process() -> &'static Arc<Process> {
 }
 
 pub fn init() {
+    namespace::init();
+    cgroup::init();
     JOIN_WAIT_QUEUE.init(WaitQueue::new);
-    SCHEDULER.init(|| SpinLock::new(Scheduler::new()));
+    SCHEDULER.init(Scheduler::new);
+    init_idle_thread();
+}
+
+/// Initializes the current Application Processor (AP).
+pub fn init_ap() {
+    init_idle_thread();
+}
+
+fn init_idle_thread() {
     let idle_thread = Process::new_idle_thread().unwrap();
     IDLE_THREAD.as_mut().set(idle_thread.clone());
     CURRENT.as_mut().set(idle_thread);
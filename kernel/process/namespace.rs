@@ -0,0 +1,382 @@
+//! PID and UTS namespaces.
+//!
+//! Mount namespaces are represented by [`RootFs`](crate::fs::mount::RootFs):
+//! each mount namespace has its own root file system and mount table.
+use core::{
+    ptr,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use alloc::collections::BTreeMap;
+use kerla_runtime::spinlock::SpinLock;
+use kerla_utils::once::Once;
+
+use crate::{
+    ctypes::CloneFlags,
+    prelude::*,
+    process::{signal::SIGKILL, PId, Process, ProcessState},
+};
+
+/// The maximum nesting depth of PID namespaces.
+const PID_NS_LEVEL_MAX: usize = 32;
+/// The maximum length of the host name and the domain name.
+pub const UTS_NAME_LEN_MAX: usize = 64;
+
+static ROOT_NAMESPACES: Once<Arc<Namespaces>> = Once::new();
+
+/// The process ID and the thread ID in a PID namespace.
+#[derive(Debug, Copy, Clone)]
+pub struct NsIds {
+    pub pid: PId,
+    pub tid: PId,
+}
+
+/// A PID namespace.
+///
+/// A process has an ID in each namespace from the root one to the one it
+/// belongs to. IDs in the root namespace are the ones used in the kernel
+/// (`Process::pid` and `Process::tid`).
+pub struct PidNamespace {
+    parent: Option<Arc<PidNamespace>>,
+    level: usize,
+    /// Threads in the namespace indexed by their thread IDs in it. Not used
+    /// in the root namespace: use the process table instead.
+    threads: SpinLock<BTreeMap<PId, Weak<Process>>>,
+    next_pid: AtomicI32,
+    /// `false` once its init process has exited.
+    alive: AtomicBool,
+}
+
+impl PidNamespace {
+    fn new(parent: Option<Arc<PidNamespace>>) -> Result<Arc<PidNamespace>> {
+        let level = match &parent {
+            Some(parent) if parent.level + 1 >= PID_NS_LEVEL_MAX => {
+                return Err(Errno::ENOSPC.into());
+            }
+            Some(parent) => parent.level + 1,
+            None => 0,
+        };
+
+        Ok(Arc::new(PidNamespace {
+            parent,
+            level,
+            threads: SpinLock::new(BTreeMap::new()),
+            next_pid: AtomicI32::new(1),
+            alive: AtomicBool::new(true),
+        }))
+    }
+
+    /// Returns `true` if `self` is `ns` or one of its descendants.
+    pub fn is_descendant_of(&self, ns: &PidNamespace) -> bool {
+        let mut current = self;
+        loop {
+            if ptr::eq(current, ns) {
+                return true;
+            }
+
+            match &current.parent {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns namespaces from the root one to `self`.
+    fn lineage(self: &Arc<PidNamespace>) -> Vec<Arc<PidNamespace>> {
+        let mut lineage = vec![self.clone()];
+        while let Some(parent) = lineage.last().unwrap().parent.clone() {
+            lineage.push(parent);
+        }
+
+        lineage.reverse();
+        lineage
+    }
+
+    /// Returns an unused ID in the namespace. As `alloc_pid`, keep the process
+    /// table locked until you register the thread!
+    fn alloc_id(&self) -> Result<PId> {
+        if !self.alive.load(Ordering::SeqCst) {
+            return Err(Errno::ENOMEM.into());
+        }
+
+        let threads = self.threads.lock();
+        // IDs from `start` are searched until they wrap around and reach
+        // `start` again.
+        let start = match self.next_pid.load(Ordering::SeqCst) {
+            pid if pid <= 0 => i32::MAX,
+            pid => pid,
+        };
+        let mut wrapped = false;
+        loop {
+            let pid = self.next_pid.fetch_add(1, Ordering::SeqCst);
+            if pid <= 0 {
+                if wrapped {
+                    return Err(Errno::EAGAIN.into());
+                }
+
+                // The ID 1 is reserved for the init process.
+                wrapped = true;
+                self.next_pid.store(2, Ordering::SeqCst);
+                continue;
+            }
+
+            if wrapped && pid >= start {
+                return Err(Errno::EAGAIN.into());
+            }
+
+            let in_use = threads
+                .get(&PId::new(pid))
+                .map_or(false, |proc| proc.strong_count() > 0);
+            if !in_use {
+                return Ok(PId::new(pid));
+            }
+        }
+    }
+
+    /// Returns the process (or the thread) with the given ID in the namespace.
+    pub fn find(&self, pid: PId) -> Option<Arc<Process>> {
+        if self.level == 0 {
+            return Process::find_by_pid(pid);
+        }
+
+        self.threads
+            .lock()
+            .get(&pid)
+            .and_then(|proc| proc.upgrade())
+    }
+
+    /// The process ID of `proc` seen from the namespace. Returns 0 if it's not
+    /// visible from the namespace.
+    pub fn pid_of(&self, proc: &Process) -> PId {
+        self.ids_of(proc).map_or(PId::new(0), |ids| ids.pid)
+    }
+
+    /// The thread ID of `proc` seen from the namespace. Returns 0 if it's not
+    /// visible from the namespace.
+    pub fn tid_of(&self, proc: &Process) -> PId {
+        self.ids_of(proc).map_or(PId::new(0), |ids| ids.tid)
+    }
+
+    fn ids_of(&self, proc: &Process) -> Option<NsIds> {
+        if proc.pid_ns().is_descendant_of(self) {
+            proc.ns_ids().get(self.level).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Translates a process ID (or a process group ID and a session ID) in
+    /// the namespace into the one used in the kernel.
+    pub fn global_id(&self, pid: i32) -> Option<i32> {
+        if self.level == 0 {
+            return Some(pid);
+        }
+
+        self.find(PId::new(pid)).map(|proc| proc.pid().as_i32())
+    }
+
+    /// Translates a process ID (or a process group ID and a session ID) used
+    /// in the kernel into the one in the namespace. Returns 0 if it's not
+    /// visible from the namespace.
+    pub fn local_id(&self, pid: i32) -> i32 {
+        if self.level == 0 {
+            return pid;
+        }
+
+        Process::find_by_pid(PId::new(pid)).map_or(0, |proc| self.pid_of(&proc).as_i32())
+    }
+
+    /// The process which adopts orphaned processes in the namespace: its init
+    /// process. If it has exited, the one of the parent namespace.
+    pub fn child_reaper(&self) -> Option<Arc<Process>> {
+        let mut ns = self;
+        loop {
+            let init = ns
+                .find(PId::new(1))
+                .filter(|init| !matches!(init.state(), ProcessState::ExitedWith(_)));
+            if init.is_some() {
+                return init;
+            }
+
+            ns = ns.parent.as_deref()?;
+        }
+    }
+
+    /// Kills all processes in the namespace. Called when its init process
+    /// exits.
+    fn kill_all(&self, init: &Process) {
+        self.alive.store(false, Ordering::SeqCst);
+        let threads: Vec<Arc<Process>> = self
+            .threads
+            .lock()
+            .values()
+            .filter_map(|proc| proc.upgrade())
+            .collect();
+        for proc in threads {
+            if !ptr::eq(proc.as_ref(), init) {
+                proc.send_signal(SIGKILL);
+            }
+        }
+    }
+}
+
+/// Allocates IDs in `pid_ns` and its ancestors for a new thread. `ids` are the
+/// IDs in the root namespace. If `leader` is `Some`, the new thread joins the
+/// leader's process.
+///
+/// Call this with the process table locked.
+pub(super) fn alloc_ns_ids(
+    pid_ns: &Arc<PidNamespace>,
+    ids: NsIds,
+    leader: Option<&Process>,
+) -> Result<Vec<NsIds>> {
+    let mut ns_ids = vec![ids];
+    for ns in pid_ns.lineage().iter().skip(1) {
+        let tid = ns.alloc_id()?;
+        let pid = match leader {
+            Some(leader) => leader.ns_ids()[ns.level].pid,
+            None => tid,
+        };
+        ns_ids.push(NsIds { pid, tid });
+    }
+
+    Ok(ns_ids)
+}
+
+/// Registers a new thread into its PID namespaces. Call this with the process
+/// table locked.
+pub(super) fn register_ns_ids(proc: &Arc<Process>) {
+    for ns in proc.pid_ns().lineage().iter().skip(1) {
+        let tid = proc.ns_ids()[ns.level].tid;
+        ns.threads.lock().insert(tid, Arc::downgrade(proc));
+    }
+}
+
+/// Removes an exited thread from its PID namespaces. If it's the init process
+/// of its namespace, other processes in the namespace are killed.
+pub(super) fn unregister_ns_ids(proc: &Process) {
+    let pid_ns = proc.pid_ns();
+    for ns in pid_ns.lineage().iter().skip(1) {
+        ns.threads.lock().remove(&proc.ns_ids()[ns.level].tid);
+    }
+
+    let is_init = pid_ns.level > 0 && pid_ns.pid_of(proc) == PId::new(1);
+    if is_init && proc.is_thread_group_exited() {
+        pid_ns.kill_all(proc);
+    }
+}
+
+/// A UTS namespace: the host name and the NIS domain name.
+pub struct UtsNamespace {
+    hostname: SpinLock<String>,
+    domainname: SpinLock<String>,
+}
+
+impl UtsNamespace {
+    fn new() -> Arc<UtsNamespace> {
+        Arc::new(UtsNamespace {
+            hostname: SpinLock::new(String::new()),
+            domainname: SpinLock::new(String::new()),
+        })
+    }
+
+    /// Creates a copy of the namespace.
+    fn duplicate(&self) -> Arc<UtsNamespace> {
+        Arc::new(UtsNamespace {
+            hostname: SpinLock::new(self.hostname()),
+            domainname: SpinLock::new(self.domainname()),
+        })
+    }
+
+    pub fn hostname(&self) -> String {
+        self.hostname.lock().clone()
+    }
+
+    pub fn set_hostname(&self, name: &str) {
+        *self.hostname.lock() = name.to_owned();
+    }
+
+    pub fn domainname(&self) -> String {
+        self.domainname.lock().clone()
+    }
+
+    pub fn set_domainname(&self, name: &str) {
+        *self.domainname.lock() = name.to_owned();
+    }
+}
+
+/// Namespaces a process belongs to except the mount namespace and its own PID
+/// namespace. Replaced as a whole when they're changed.
+#[derive(Clone)]
+pub struct Namespaces {
+    uts: Arc<UtsNamespace>,
+    /// The PID namespace for children created by the process.
+    pid_for_children: Arc<PidNamespace>,
+}
+
+impl Namespaces {
+    /// The namespaces of idle threads and the init process.
+    pub fn root() -> Arc<Namespaces> {
+        ROOT_NAMESPACES.clone()
+    }
+
+    pub fn uts(&self) -> &Arc<UtsNamespace> {
+        &self.uts
+    }
+
+    pub fn pid_for_children(&self) -> &Arc<PidNamespace> {
+        &self.pid_for_children
+    }
+
+    /// Creates new namespaces as specified in `CLONE_NEWUTS` and
+    /// `CLONE_NEWPID` in `flags`. Others are shared.
+    pub fn unshare(&self, flags: CloneFlags) -> Result<Namespaces> {
+        let uts = if flags.contains(CloneFlags::CLONE_NEWUTS) {
+            self.uts.duplicate()
+        } else {
+            self.uts.clone()
+        };
+
+        let pid_for_children = if flags.contains(CloneFlags::CLONE_NEWPID) {
+            PidNamespace::new(Some(self.pid_for_children.clone()))?
+        } else {
+            self.pid_for_children.clone()
+        };
+
+        Ok(Namespaces {
+            uts,
+            pid_for_children,
+        })
+    }
+
+    /// Joins the namespaces of `target` as specified in `CLONE_NEWUTS` and
+    /// `CLONE_NEWPID` in `flags`.
+    pub fn join(&self, target: &Process, flags: CloneFlags) -> Namespaces {
+        let target_namespaces = target.namespaces();
+        let uts = if flags.contains(CloneFlags::CLONE_NEWUTS) {
+            target_namespaces.uts.clone()
+        } else {
+            self.uts.clone()
+        };
+
+        let pid_for_children = if flags.contains(CloneFlags::CLONE_NEWPID) {
+            target.pid_ns().clone()
+        } else {
+            self.pid_for_children.clone()
+        };
+
+        Namespaces {
+            uts,
+            pid_for_children,
+        }
+    }
+}
+
+pub fn init() {
+    ROOT_NAMESPACES.init(|| {
+        Arc::new(Namespaces {
+            uts: UtsNamespace::new(),
+            pid_for_children: PidNamespace::new(None).unwrap(),
+        })
+    });
+}
@@ -0,0 +1,57 @@
+use core::fmt;
+
+use crate::{
+    fs::{
+        inode::{FileLike, PollStatus},
+        stat::{FileMode, Stat, S_IFREG},
+    },
+    prelude::*,
+    process::{Process, ProcessState},
+};
+
+/// A file descriptor referring to a process (`pidfd_open(2)`). It's used to
+/// join its namespaces in `setns(2)`.
+pub struct PidFd {
+    process: Arc<Process>,
+}
+
+impl PidFd {
+    pub fn new(process: Arc<Process>) -> PidFd {
+        PidFd { process }
+    }
+
+    /// The process. Returns `ESRCH` if it has exited.
+    pub fn process(&self) -> Result<&Arc<Process>> {
+        if matches!(self.process.state(), ProcessState::ExitedWith(_)) {
+            return Err(Errno::ESRCH.into());
+        }
+
+        Ok(&self.process)
+    }
+}
+
+impl fmt::Debug for PidFd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PidFd")
+            .field("pid", &self.process.pid())
+            .finish()
+    }
+}
+
+impl FileLike for PidFd {
+    fn stat(&self) -> Result<Stat> {
+        Ok(Stat {
+            mode: FileMode::new(S_IFREG | 0o600),
+            ..Stat::zeroed()
+        })
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        // Readable once the process has exited.
+        if matches!(self.process.state(), ProcessState::ExitedWith(_)) {
+            Ok(PollStatus::POLLIN)
+        } else {
+            Ok(PollStatus::empty())
+        }
+    }
+}
@@ -7,17 +7,41 @@ use crate::{
         mount::RootFs,
         opened_file::{Fd, OpenFlags, OpenOptions, OpenedFile, OpenedFileTable, PathComponent},
         path::Path,
+        stat::Stat,
+    },
+    mm::{
+        page_cache,
+        page_fault::access_memory_forcibly,
+        vm::{Vm, VmAreaType},
     },
-    mm::vm::{Vm, VmAreaType},
     prelude::*,
     process::{
+        cgroup::{self, Cgroup},
         cmdline::Cmdline,
+        coredump,
+        credentials::{AccessMode, Credentials},
         current_process,
         elf::{Elf, ProgramHeader},
+        futex::{exit_robust_list, futex_wake, FutexKey, FUTEX_BITSET_MATCH_ANY},
         init_stack::{estimate_user_init_stack_size, init_user_stack, Auxv},
-        process_group::{PgId, ProcessGroup},
-        signal::{SigAction, SigSet, Signal, SignalDelivery, SignalMask, SIGCHLD, SIGKILL},
-        switch, UserVAddr, JOIN_WAIT_QUEUE, SCHEDULER,
+        namespace::{
+            alloc_ns_ids, register_ns_ids, unregister_ns_ids, Namespaces, NsIds, PidNamespace,
+        },
+        process_group::{self, PgId, ProcessGroup},
+        ptrace::{clone_event, Ptrace, PTRACE_EVENT_EXIT},
+        rlimit::{RLimit, ResourceLimits, RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_NPROC, RLIMIT_STACK},
+        scheduler::{SchedEntity, SchedPolicy},
+        seccomp::SeccompMode,
+        session::{Session, SessionId},
+        signal::{
+            default_action, DefaultAction, SigAction, SigActionFlags, SigAltStack, SigHandler,
+            SigInfo, SigInfoFields, SigSet, Signal, SignalDelivery, SignalMask, CLD_CONTINUED,
+            CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, SIGCHLD, SIGCONT, SIGKILL, SIGPROF,
+            SIGSEGV, SIGSTOP, SIGVTALRM, SS_DISABLE, STOP_SIGNALS,
+        },
+        switch,
+        thread_group::{CpuTimes, ThreadGroup},
+        UserVAddr, JOIN_WAIT_QUEUE, SCHEDULER,
     },
     random::read_secure_random,
     result::Errno,
@@ -28,16 +52,22 @@ use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
 use atomic_refcell::{AtomicRef, AtomicRefCell};
 use core::mem::size_of;
-use core::sync::atomic::{AtomicI32, Ordering};
-use core::{cmp::max, sync::atomic::AtomicUsize};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use core::{
+    cmp::{max, min},
+    sync::atomic::AtomicUsize,
+};
 use crossbeam::atomic::AtomicCell;
-use goblin::{elf::header::ET_DYN, elf64::program_header::PT_LOAD};
+use goblin::{
+    elf::header::ET_DYN,
+    elf64::program_header::{PF_R, PF_W, PF_X, PT_LOAD},
+};
 use kerla_runtime::{
-    arch::{PtRegs, PAGE_SIZE},
+    arch::{PageProtection, PtRegs, PAGE_SIZE},
     page_allocator::{alloc_pages, AllocPageFlags},
     spinlock::{SpinLock, SpinLockGuard},
 };
-use kerla_utils::alignment::{align_down_u64, align_up, align_up_u64};
+use kerla_utils::alignment::{align_down, align_down_u64, align_up, align_up_u64};
 
 type ProcessTable = BTreeMap<PId, Arc<Process>>;
 
@@ -101,26 +131,83 @@ pub enum ProcessState {
     Runnable,
     /// The process is sleeping. It can be resumed by signals.
     BlockedSignalable,
-    /// The process has exited.
+    /// The process has been stopped by a signal (job control). It's resumed
+    /// by `SIGCONT` or `SIGKILL`.
+    Stopped,
+    /// The process has exited. The value is the wait status reported by
+    /// `wait4(2)`.
     ExitedWith(c_int),
 }
 
 /// The process control block.
+///
+/// Each thread has its own one: threads in a process share memory space,
+/// opened files, and signal handlers.
 pub struct Process {
     arch: arch::Process,
     is_idle: bool,
     process_group: AtomicRefCell<Weak<SpinLock<ProcessGroup>>>,
     pid: PId,
+    /// Replaced with the process ID when a thread calls `execve(2)`.
+    tid: AtomicCell<PId>,
+    thread_group: Arc<SpinLock<ThreadGroup>>,
+    /// The address set by `set_tid_address(2)` or `CLONE_CHILD_CLEARTID`.
+    clear_child_tid: AtomicCell<Option<UserVAddr>>,
+    /// The robust futex list set by `set_robust_list(2)`.
+    robust_list: AtomicCell<Option<UserVAddr>>,
     state: AtomicCell<ProcessState>,
-    parent: Weak<Process>,
+    /// Replaced with the child reaper when the parent exits.
+    parent: SpinLock<Weak<Process>>,
+    /// Set while the parent waits for the child created by `vfork(2)` to
+    /// call `execve(2)` or exit.
+    in_vfork: AtomicBool,
     cmdline: AtomicRefCell<Cmdline>,
-    children: SpinLock<Vec<Arc<Process>>>,
+    children: Arc<SpinLock<Vec<Arc<Process>>>>,
     vm: AtomicRefCell<Option<Arc<SpinLock<Vm>>>>,
     opened_files: Arc<SpinLock<OpenedFileTable>>,
-    root_fs: Arc<SpinLock<RootFs>>,
+    /// The root file system of the mount namespace.
+    root_fs: AtomicRefCell<Arc<SpinLock<RootFs>>>,
+    /// The PID namespace the process belongs to.
+    pid_ns: Arc<PidNamespace>,
+    /// The process ID and the thread ID in each PID namespace from the root
+    /// one to `pid_ns`.
+    ns_ids: SpinLock<Vec<NsIds>>,
+    /// Other namespaces. Replaced as a whole when they're changed.
+    namespaces: SpinLock<Arc<Namespaces>>,
+    /// User and group IDs. Replaced as a whole when they're changed.
+    credentials: SpinLock<Arc<Credentials>>,
     signals: Arc<SpinLock<SignalDelivery>>,
-    signaled_frame: AtomicCell<Option<PtRegs>>,
+    /// The blocked signals.
     sigset: SpinLock<SigSet>,
+    /// The signal mask to be restored once a signal is delivered (e.g.
+    /// `rt_sigsuspend(2)`).
+    saved_sigset: AtomicCell<Option<SigSet>>,
+    /// The alternate signal stack set by `sigaltstack(2)`.
+    sigaltstack: AtomicCell<Option<SigAltStack>>,
+    sched_entity: SpinLock<SchedEntity>,
+    /// CPU times used by the thread.
+    cpu_times: SpinLock<CpuTimes>,
+    /// Whether the thread is executing in the kernel (a system call or a page
+    /// fault): the CPU time is charged as the system time.
+    in_kernel: AtomicBool,
+    /// The tracing state if the thread is traced by `ptrace(2)`.
+    ptrace: SpinLock<Option<Ptrace>>,
+    /// Set when the thread stops in a ptrace-stop.
+    pub(super) ptrace_stopped: AtomicBool,
+    /// Threads traced by this thread.
+    tracees: SpinLock<Vec<Arc<Process>>>,
+    /// The control group the thread belongs to.
+    cgroup: SpinLock<Arc<Cgroup>>,
+    /// The system call filter set by `seccomp(2)`.
+    seccomp: SpinLock<SeccompMode>,
+    /// Whether `seccomp` is not `SeccompMode::Disabled`. Checked on every
+    /// system call without locking `seccomp`.
+    seccomp_enabled: AtomicBool,
+    /// If set, `execve(2)` never grants privileges (`PR_SET_NO_NEW_PRIVS`).
+    no_new_privs: AtomicBool,
+    /// Whether the command name matches the target of the system call tracer,
+    /// cached by [`crate::syscalls::tracer`]. Cleared in `execve(2)`.
+    trace_match: AtomicU64,
 }
 
 impl Process {
@@ -129,25 +216,52 @@ impl Process {
     /// An idle thread is a special type of kernel threads which is executed
     /// only if there're no other runnable processes.
     pub fn new_idle_thread() -> Result<Arc<Process>> {
-        let process_group = ProcessGroup::new(PgId::new(0));
+        let process_group = ProcessGroup::new(PgId::new(0), Session::new(SessionId::new(0)));
+        let thread_group = ThreadGroup::new(ResourceLimits::new(), None);
+        let namespaces = Namespaces::root();
         let proc = Arc::new(Process {
             is_idle: true,
             process_group: AtomicRefCell::new(Arc::downgrade(&process_group)),
             arch: arch::Process::new_idle_thread(),
             state: AtomicCell::new(ProcessState::Runnable),
-            parent: Weak::new(),
+            parent: SpinLock::new(Weak::new()),
+            in_vfork: AtomicBool::new(false),
             cmdline: AtomicRefCell::new(Cmdline::new()),
-            children: SpinLock::new(Vec::new()),
+            children: Arc::new(SpinLock::new(Vec::new())),
             vm: AtomicRefCell::new(None),
             pid: PId::new(0),
-            root_fs: INITIAL_ROOT_FS.clone(),
+            tid: AtomicCell::new(PId::new(0)),
+            thread_group: thread_group.clone(),
+            clear_child_tid: AtomicCell::new(None),
+            robust_list: AtomicCell::new(None),
+            root_fs: AtomicRefCell::new(INITIAL_ROOT_FS.clone()),
+            pid_ns: namespaces.pid_for_children().clone(),
+            ns_ids: SpinLock::new(vec![NsIds {
+                pid: PId::new(0),
+                tid: PId::new(0),
+            }]),
+            namespaces: SpinLock::new(namespaces),
+            credentials: SpinLock::new(Arc::new(Credentials::root())),
             opened_files: Arc::new(SpinLock::new(OpenedFileTable::new())),
             signals: Arc::new(SpinLock::new(SignalDelivery::new())),
-            signaled_frame: AtomicCell::new(None),
-            sigset: SpinLock::new(SigSet::ZERO),
+            sigset: SpinLock::new(SigSet::EMPTY),
+            saved_sigset: AtomicCell::new(None),
+            sigaltstack: AtomicCell::new(None),
+            sched_entity: SpinLock::new(SchedEntity::new(SchedPolicy::Normal, 0, None)),
+            cpu_times: SpinLock::new(CpuTimes::default()),
+            in_kernel: AtomicBool::new(false),
+            ptrace: SpinLock::new(None),
+            ptrace_stopped: AtomicBool::new(false),
+            tracees: SpinLock::new(Vec::new()),
+            cgroup: SpinLock::new(Cgroup::root()),
+            seccomp: SpinLock::new(SeccompMode::Disabled),
+            seccomp_enabled: AtomicBool::new(false),
+            trace_match: AtomicU64::new(0),
+            no_new_privs: AtomicBool::new(false),
         });
 
         process_group.lock().add(Arc::downgrade(&proc));
+        thread_group.lock().add(Arc::downgrade(&proc));
         Ok(proc)
     }
 
@@ -188,39 +302,86 @@ impl Process {
             OpenOptions::empty(),
         )?;
 
-        let entry = setup_userspace(executable_path, argv, &[], &root_fs)?;
+        let rlimits = ResourceLimits::new();
+        let entry = setup_userspace(
+            executable_path,
+            argv,
+            &[],
+            &root_fs,
+            &Credentials::root(),
+            &rlimits,
+        )?;
         let pid = PId::new(1);
-        let process_group = ProcessGroup::new(PgId::new(1));
+        let session = Session::new(SessionId::new(1));
+        let process_group = ProcessGroup::new(PgId::new(1), session.clone());
+        let thread_group = ThreadGroup::new(rlimits, None);
+        let namespaces = Namespaces::root();
+        let cgroup = Cgroup::root();
+        cgroup.try_charge_thread()?;
         let process = Arc::new(Process {
             is_idle: false,
             process_group: AtomicRefCell::new(Arc::downgrade(&process_group)),
             pid,
-            parent: Weak::new(),
-            children: SpinLock::new(Vec::new()),
+            tid: AtomicCell::new(pid),
+            thread_group: thread_group.clone(),
+            clear_child_tid: AtomicCell::new(None),
+            robust_list: AtomicCell::new(None),
+            parent: SpinLock::new(Weak::new()),
+            in_vfork: AtomicBool::new(false),
+            children: Arc::new(SpinLock::new(Vec::new())),
             state: AtomicCell::new(ProcessState::Runnable),
             cmdline: AtomicRefCell::new(Cmdline::from_argv(argv)),
             arch: arch::Process::new_user_thread(entry.ip, entry.user_sp),
             vm: AtomicRefCell::new(Some(Arc::new(SpinLock::new(entry.vm)))),
             opened_files: Arc::new(SpinLock::new(opened_files)),
-            root_fs,
+            root_fs: AtomicRefCell::new(root_fs),
+            pid_ns: namespaces.pid_for_children().clone(),
+            ns_ids: SpinLock::new(vec![NsIds { pid, tid: pid }]),
+            namespaces: SpinLock::new(namespaces),
+            credentials: SpinLock::new(Arc::new(Credentials::root())),
             signals: Arc::new(SpinLock::new(SignalDelivery::new())),
-            signaled_frame: AtomicCell::new(None),
-            sigset: SpinLock::new(SigSet::ZERO),
+            sigset: SpinLock::new(SigSet::EMPTY),
+            saved_sigset: AtomicCell::new(None),
+            sigaltstack: AtomicCell::new(None),
+            sched_entity: SpinLock::new(SchedEntity::new(SchedPolicy::Normal, 0, None)),
+            cpu_times: SpinLock::new(CpuTimes::default()),
+            in_kernel: AtomicBool::new(false),
+            ptrace: SpinLock::new(None),
+            ptrace_stopped: AtomicBool::new(false),
+            tracees: SpinLock::new(Vec::new()),
+            cgroup: SpinLock::new(Cgroup::root()),
+            seccomp: SpinLock::new(SeccompMode::Disabled),
+            seccomp_enabled: AtomicBool::new(false),
+            trace_match: AtomicU64::new(0),
+            no_new_privs: AtomicBool::new(false),
         });
 
         process_group.lock().add(Arc::downgrade(&process));
-        PROCESSES.lock().insert(pid, process);
-        SCHEDULER.lock().enqueue(pid);
+        thread_group.lock().add(Arc::downgrade(&process));
+        cgroup::add_thread(&process);
+        PROCESSES.lock().insert(pid, process.clone());
+        SCHEDULER.enqueue(&process, &mut process.sched_entity.lock());
 
-        SERIAL_TTY.set_foreground_process_group(Arc::downgrade(&process_group));
+        SERIAL_TTY.set_controlling_session(&session, &process_group);
         Ok(())
     }
 
-    /// Returns the process with the given process ID.
+    /// Returns the process (or the thread) with the given process ID (or the
+    /// thread ID).
     pub fn find_by_pid(pid: PId) -> Option<Arc<Process>> {
         PROCESSES.lock().get(&pid).cloned()
     }
 
+    /// Returns all processes, i.e. the main thread of each thread group.
+    pub fn all_processes() -> Vec<Arc<Process>> {
+        PROCESSES
+            .lock()
+            .values()
+            .filter(|proc| proc.pid == proc.tid())
+            .cloned()
+            .collect()
+    }
+
     /// Returns true if the process is a idle kernel thread.
     pub fn is_idle(&self) -> bool {
         self.is_idle
@@ -235,7 +396,27 @@ impl Process {
     pub fn tid(&self) -> PId {
         // In a single-threaded process, the thread ID is equal to the process ID (PID).
         // https://man7.org/linux/man-pages/man2/gettid.2.html
-        self.pid
+        self.tid.load()
+    }
+
+    /// Returns `true` if all threads in the process have exited.
+    pub fn is_thread_group_exited(&self) -> bool {
+        self.thread_group.lock().is_empty()
+    }
+
+    /// The thread group, i.e. the process the thread belongs to.
+    pub fn thread_group(&self) -> SpinLockGuard<'_, ThreadGroup> {
+        self.thread_group.lock()
+    }
+
+    /// Sets the address to be cleared when the thread exits.
+    pub fn set_clear_child_tid(&self, uaddr: Option<UserVAddr>) {
+        self.clear_child_tid.store(uaddr);
+    }
+
+    /// Sets the head of the robust futex list.
+    pub fn set_robust_list(&self, head: Option<UserVAddr>) {
+        self.robust_list.store(head);
     }
 
     /// The arch-specific information.
@@ -243,15 +424,104 @@ impl Process {
         &self.arch
     }
 
+    pub(super) fn sched_entity(&self) -> &SpinLock<SchedEntity> {
+        &self.sched_entity
+    }
+
+    /// The scheduling policy.
+    pub fn sched_policy(&self) -> SchedPolicy {
+        self.sched_entity.lock().policy()
+    }
+
+    /// The nice value.
+    pub fn nice(&self) -> i32 {
+        self.sched_entity.lock().nice()
+    }
+
+    /// Updates the scheduling policy and the nice value.
+    pub fn set_sched_params(&self, policy: SchedPolicy, nice: i32) {
+        SCHEDULER.set_params(self, policy, nice);
+    }
+
+    /// The control group the thread belongs to.
+    pub fn cgroup(&self) -> Arc<Cgroup> {
+        self.cgroup.lock().clone()
+    }
+
+    pub(super) fn cgroup_mut(&self) -> SpinLockGuard<'_, Arc<Cgroup>> {
+        self.cgroup.lock()
+    }
+
+    /// The seccomp mode of the thread.
+    pub fn seccomp(&self) -> SeccompMode {
+        self.seccomp.lock().clone()
+    }
+
+    /// Returns `true` if the thread is in the strict or filter mode.
+    pub fn is_seccomp_enabled(&self) -> bool {
+        self.seccomp_enabled.load(Ordering::Acquire)
+    }
+
+    pub fn set_seccomp(&self, mode: SeccompMode) {
+        let enabled = !matches!(mode, SeccompMode::Disabled);
+        *self.seccomp.lock() = mode;
+        self.seccomp_enabled.store(enabled, Ordering::Release);
+    }
+
+    /// The cached result of matching the command name with the target of the
+    /// system call tracer.
+    pub fn trace_match(&self) -> u64 {
+        self.trace_match.load(Ordering::Relaxed)
+    }
+
+    pub fn set_trace_match(&self, value: u64) {
+        self.trace_match.store(value, Ordering::Relaxed);
+    }
+
+    /// Whether `execve(2)` is prohibited from granting privileges.
+    pub fn no_new_privs(&self) -> bool {
+        self.no_new_privs.load(Ordering::Relaxed)
+    }
+
+    /// Sets the no_new_privs bit. It can't be unset.
+    pub fn set_no_new_privs(&self) {
+        self.no_new_privs.store(true, Ordering::Relaxed);
+    }
+
+    /// Wakes the parent waiting in `vfork(2)`: the child no longer uses its
+    /// memory space.
+    fn release_vfork_parent(&self) {
+        if self.in_vfork.swap(false, Ordering::SeqCst) {
+            JOIN_WAIT_QUEUE.wake_all();
+        }
+    }
+
+    /// Sleeps until `child` created with `CLONE_VFORK` calls `execve(2)` or
+    /// exits. Like Linux, only `SIGKILL` and `SIGSTOP` interrupt it.
+    pub fn wait_for_vfork_done(&self, child: &Process) {
+        let saved_mask = self.set_signal_mask(SignalMask::Block, SigSet::from_raw(!0));
+        JOIN_WAIT_QUEUE
+            .sleep_signalable_until(|| {
+                if child.in_vfork.load(Ordering::SeqCst) {
+                    Ok(None)
+                } else {
+                    Ok(Some(()))
+                }
+            })
+            .ok();
+        self.set_signal_mask(SignalMask::Set, saved_mask);
+    }
+
     /// The process parent.
-    fn parent(&self) -> Option<Arc<Process>> {
-        self.parent.upgrade().as_ref().cloned()
+    pub fn parent(&self) -> Option<Arc<Process>> {
+        self.parent.lock().upgrade()
     }
 
-    /// The ID of process being parent of this process.
+    /// The ID of process being parent of this process seen from its PID
+    /// namespace. It's 0 if the parent is not in the namespace.
     pub fn ppid(&self) -> PId {
         if let Some(parent) = self.parent() {
-            parent.pid()
+            self.pid_ns.pid_of(&parent)
         } else {
             PId::new(0)
         }
@@ -267,8 +537,76 @@ impl Process {
     }
 
     /// The process's path resolution info.
-    pub fn root_fs(&self) -> &Arc<SpinLock<RootFs>> {
-        &self.root_fs
+    pub fn root_fs(&self) -> AtomicRef<'_, Arc<SpinLock<RootFs>>> {
+        self.root_fs.borrow()
+    }
+
+    /// Switches the mount namespace of the **current** single-threaded
+    /// process.
+    pub fn set_root_fs(&self, root_fs: Arc<SpinLock<RootFs>>) {
+        *self.root_fs.borrow_mut() = root_fs;
+    }
+
+    /// The PID namespace the process belongs to.
+    pub fn pid_ns(&self) -> &Arc<PidNamespace> {
+        &self.pid_ns
+    }
+
+    /// The process ID and the thread ID in each PID namespace from the root
+    /// one.
+    pub fn ns_ids(&self) -> SpinLockGuard<'_, Vec<NsIds>> {
+        self.ns_ids.lock()
+    }
+
+    /// Namespaces other than the mount namespace and its own PID namespace.
+    pub fn namespaces(&self) -> Arc<Namespaces> {
+        self.namespaces.lock().clone()
+    }
+
+    /// Replaces namespaces.
+    pub fn set_namespaces(&self, namespaces: Namespaces) {
+        *self.namespaces.lock() = Arc::new(namespaces);
+    }
+
+    /// User and group IDs.
+    pub fn credentials(&self) -> Arc<Credentials> {
+        self.credentials.lock().clone()
+    }
+
+    /// Replaces user and group IDs.
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.credentials.lock() = Arc::new(credentials);
+    }
+
+    /// The resource limit of `resource`.
+    pub fn rlimit(&self, resource: c_int) -> Result<RLimit> {
+        self.thread_group.lock().rlimits().get(resource)
+    }
+
+    /// Updates the resource limit of `resource` and applies it to the opened
+    /// files table and the address space.
+    pub fn set_rlimit(&self, resource: c_int, new_limit: RLimit) -> Result<()> {
+        let privileged = current_process().credentials().is_privileged();
+        self.thread_group
+            .lock()
+            .rlimits_mut()
+            .set(resource, new_limit, privileged)?;
+
+        match resource {
+            RLIMIT_NOFILE => {
+                self.opened_files
+                    .lock()
+                    .set_fd_limit(new_limit.cur_as_usize());
+            }
+            RLIMIT_AS => {
+                if let Some(vm) = self.vm().as_ref() {
+                    vm.lock().set_address_space_limit(new_limit.cur_as_usize());
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
     }
 
     /// The ppened files table.
@@ -308,18 +646,23 @@ impl Process {
 
     /// Updates the process state.
     pub fn set_state(&self, new_state: ProcessState) {
-        let scheduler = SCHEDULER.lock();
+        // Update the state while holding the lock not to race with the
+        // scheduler (see `Scheduler::put_prev`).
+        let mut entity = self.sched_entity.lock();
         self.state.store(new_state);
         match new_state {
             ProcessState::Runnable => {}
-            ProcessState::BlockedSignalable | ProcessState::ExitedWith(_) => {
-                scheduler.remove(self.pid);
+            ProcessState::BlockedSignalable
+            | ProcessState::Stopped
+            | ProcessState::ExitedWith(_) => {
+                SCHEDULER.remove(self, &mut entity);
             }
         }
     }
 
     /// Resumes a process.
     pub fn resume(&self) {
+        let mut entity = self.sched_entity.lock();
         let old_state = self.state.swap(ProcessState::Runnable);
 
         debug_assert!(!matches!(old_state, ProcessState::ExitedWith(_)));
@@ -328,7 +671,68 @@ impl Process {
             return;
         }
 
-        SCHEDULER.lock().enqueue(self.pid);
+        // It's not enqueued if it's running: the CPU enqueues it when it's
+        // switched out.
+        SCHEDULER.enqueue(self, &mut entity);
+    }
+
+    /// Charges the CPU time used since the last call to the **current**
+    /// process as the user or system time and sends `SIGVTALRM` / `SIGPROF`
+    /// if the CPU-time interval timers have expired.
+    ///
+    /// It's called on every timer tick: the time is charged to where the
+    /// thread is running at the moment.
+    pub fn account_cpu_time(&self) {
+        let cpu_time = SCHEDULER.consume_exec_runtime(self);
+        let in_userspace = !self.in_kernel.load(Ordering::Relaxed);
+        {
+            let mut cpu_times = self.cpu_times.lock();
+            if in_userspace {
+                cpu_times.utime += cpu_time;
+            } else {
+                cpu_times.stime += cpu_time;
+            }
+        }
+
+        let mut thread_group = self.thread_group.lock();
+        // ITIMER_VIRTUAL counts only the time spent in the userspace.
+        let virtual_expired = in_userspace && thread_group.virtual_timer_mut().charge(cpu_time);
+        let prof_expired = thread_group.prof_timer_mut().charge(cpu_time);
+        drop(thread_group);
+
+        if virtual_expired {
+            self.send_signal(SIGVTALRM);
+        }
+
+        if prof_expired {
+            self.send_signal(SIGPROF);
+        }
+    }
+
+    /// Called when the **current** thread enters the kernel from the
+    /// userspace by a system call or a page fault.
+    pub fn enter_kernel(&self) {
+        self.in_kernel.store(true, Ordering::Relaxed);
+    }
+
+    /// Called when the **current** thread returns to the userspace.
+    pub fn exit_kernel(&self) {
+        self.in_kernel.store(false, Ordering::Relaxed);
+    }
+
+    /// CPU times used by the thread.
+    pub fn cpu_times(&self) -> CpuTimes {
+        *self.cpu_times.lock()
+    }
+
+    /// The tracing state by `ptrace(2)`.
+    pub fn ptrace(&self) -> SpinLockGuard<'_, Option<Ptrace>> {
+        self.ptrace.lock()
+    }
+
+    /// Threads traced by this thread.
+    pub fn tracees(&self) -> SpinLockGuard<'_, Vec<Arc<Process>>> {
+        self.tracees.lock()
     }
 
     /// Searches the opned file table by the file descriptor.
@@ -336,26 +740,141 @@ impl Process {
         Ok(self.opened_files.lock().get(fd)?.clone())
     }
 
-    /// Terminates the **current** process.
+    /// Terminates the **current** thread with the exit code. The process exits
+    /// when its last thread exits.
     pub fn exit(status: c_int) -> ! {
+        Process::do_exit(exited_wait_status(status))
+    }
+
+    /// Terminates the **current** thread. `wait_status` is the status reported
+    /// by `wait4(2)`.
+    fn do_exit(wait_status: c_int) -> ! {
         let current = current_process();
+        if let Some(head) = current.robust_list.take() {
+            // Release mutexes held by the thread.
+            exit_robust_list(head).ok();
+        }
+
+        // Keep the reference because we're using its kernel stack. Postpone
+        // freeing the stack until we move from the current thread.
+        EXITED_PROCESSES.lock().push(current.clone());
+        current.release_vfork_parent();
+
+        if let Some(clear_child_tid) = current.clear_child_tid.take() {
+            // Notify threads waiting for the exit (e.g. pthread_join).
+            if clear_child_tid.write::<c_int>(&0).is_ok() {
+                if let Ok(key) = FutexKey::new(clear_child_tid, false) {
+                    futex_wake(key, 1, FUTEX_BITSET_MATCH_ANY).ok();
+                }
+            }
+        }
+
+        // Tracees are no longer traced by the exiting thread.
+        current.detach_tracees();
+
+        let is_last_thread = {
+            current.account_cpu_time();
+            let mut thread_group = current.thread_group.lock();
+            thread_group.remove(&Arc::downgrade(current), current.cpu_times());
+            thread_group.is_empty()
+        };
+
+        current.set_state(ProcessState::ExitedWith(wait_status));
+        {
+            // Lock the table not to race with the thread taking over the
+            // thread ID in `execve(2)`.
+            let mut process_table = PROCESSES.lock();
+            process_table.remove(&current.tid());
+            unregister_ns_ids(current);
+            cgroup::remove_exited_thread(current);
+        }
+
+        // The tracer waits for the exit of the tracee as well as its parent.
+        if let Some(tracer) = current.tracer() {
+            let (code, status) = child_exit_code_and_status(wait_status);
+            tracer.send_siginfo(SigInfo::child(
+                tracer.pid_ns().tid_of(current),
+                current.credentials().uid,
+                code,
+                status,
+            ));
+            JOIN_WAIT_QUEUE.wake_all();
+        }
+
+        if !is_last_thread {
+            // Other threads are still alive. The thread group leader is kept
+            // in its parent's children list until all threads exit. Wake the
+            // thread waiting in `execve(2)` if any.
+            JOIN_WAIT_QUEUE.wake_all();
+            switch();
+            unreachable!();
+        }
+
         if current.pid == PId::new(1) {
             panic!("init (pid=0) tried to exit")
         }
 
-        current.set_state(ProcessState::ExitedWith(status));
-        if let Some(parent) = current.parent.upgrade() {
-            if parent.signals().lock().get_action(SIGCHLD) == SigAction::Ignore {
-                // If the parent process is not waiting for a child,
-                // remove the child from its list.
-                parent.children().retain(|p| p.pid() != current.pid);
+        // The process is represented by the thread group leader. Drop the
+        // reference before switching: we never return here.
+        {
+            let leader = current.thread_group.lock().leader().upgrade();
+            let leader = leader.as_ref().unwrap_or(current);
+            if !Arc::ptr_eq(leader, current) {
+                leader.state.store(ProcessState::ExitedWith(wait_status));
+            }
 
-                // Keep the reference because we're using its kernel stack. Postpone
-                // freeing the stack until we move from the current thread.
-                EXITED_PROCESSES.lock().push(current.clone());
+            let exit_signal = current.thread_group.lock().exit_signal();
+            if let Some(parent) = leader.parent() {
+                let action = parent.signals().lock().get_action(SIGCHLD);
+                if exit_signal == Some(SIGCHLD)
+                    && (action.handler == SigHandler::Ignore
+                        || action.flags.contains(SigActionFlags::SA_NOCLDWAIT))
+                {
+                    // If the parent process is not waiting for a child,
+                    // remove the child from its list.
+                    parent.children().retain(|p| p.pid() != leader.pid);
+                } else if let Some(exit_signal) = exit_signal {
+                    let (code, status) = child_exit_code_and_status(wait_status);
+                    let mut info = SigInfo::child(
+                        parent.pid_ns().pid_of(leader),
+                        leader.credentials().uid,
+                        code,
+                        status,
+                    );
+                    info.signal = exit_signal;
+                    parent.send_siginfo(info);
+                }
+            }
+        }
+
+        process_group::hang_up_orphaned_groups(current);
+        current.reparent_children();
+
+        // The session leader has exited: disassociate the controlling
+        // terminal and hang up the foreground process group.
+        let terminal = {
+            let session = current.process_group().lock().session().clone();
+            let session = session.lock();
+            if session.sid().as_i32() == current.pid.as_i32() {
+                session.controlling_terminal()
             } else {
-                parent.send_signal(SIGCHLD)
+                None
             }
+        };
+
+        if let Some(terminal) = terminal {
+            terminal.disassociate();
+        }
+
+        // Write back pages modified through shared file mappings: the memory
+        // space is freed later in the idle thread.
+        let file_ranges = current
+            .vm()
+            .as_ref()
+            .map(|vm| vm.lock().shared_file_ranges())
+            .unwrap_or_default();
+        for range in file_ranges {
+            page_cache::sync(&range.file, range.offset, range.len).ok();
         }
 
         // Close opened files here instead of in Drop::drop because `proc` is
@@ -363,102 +882,506 @@ impl Process {
         // make pipes closed.
         current.opened_files.lock().close_all();
 
-        PROCESSES.lock().remove(&current.pid);
         JOIN_WAIT_QUEUE.wake_all();
         switch();
         unreachable!();
     }
 
+    /// Passes the children of the exiting process to the child reaper, i.e.
+    /// the init process of the PID namespace.
+    fn reparent_children(&self) {
+        let children = core::mem::take(&mut *self.children());
+        let reaper = match self.pid_ns.child_reaper() {
+            Some(reaper) => reaper,
+            // No one waits for them.
+            None => return,
+        };
+
+        for child in children {
+            let threads: Vec<Arc<Process>> = child
+                .thread_group()
+                .threads()
+                .iter()
+                .filter_map(|thread| thread.upgrade())
+                .collect();
+            for thread in threads.iter().chain(core::iter::once(&child)) {
+                *thread.parent.lock() = Arc::downgrade(&reaper);
+            }
+
+            // Notify the reaper of zombies adopted by it.
+            let exited = match child.state() {
+                ProcessState::ExitedWith(wait_status) => Some(wait_status),
+                _ => None,
+            };
+            reaper.children().push(child.clone());
+            if let Some(wait_status) = exited {
+                let (code, status) = child_exit_code_and_status(wait_status);
+                reaper.send_siginfo(SigInfo::child(
+                    reaper.pid_ns().pid_of(&child),
+                    child.credentials().uid,
+                    code,
+                    status,
+                ));
+            }
+        }
+
+        JOIN_WAIT_QUEUE.wake_all();
+    }
+
     /// Terminates the **current** thread and other threads belonging to the same thread group.
     pub fn exit_group(status: c_int) -> ! {
-        // TODO: Kill other threads belonging to the same thread group.
-        Process::exit(status)
+        Process::do_exit_group(exited_wait_status(status))
+    }
+
+    fn do_exit_group(wait_status: c_int) -> ! {
+        let current = current_process();
+        {
+            let mut thread_group = current.thread_group.lock();
+            thread_group.set_exit_status(wait_status);
+
+            // Other threads exit when they return to the userspace. Wake them
+            // up if they're sleeping.
+            for thread in thread_group.threads() {
+                if let Some(thread) = thread.upgrade() {
+                    if !Arc::ptr_eq(&thread, current) {
+                        thread.resume();
+                    }
+                }
+            }
+        }
+
+        Process::do_exit(wait_status)
+    }
+
+    /// Terminates the **current** thread if another thread has called
+    /// `exit_group(2)` or `execve(2)`.
+    pub fn exit_if_thread_group_exiting() {
+        let current = current_process();
+        if current.is_idle {
+            return;
+        }
+
+        let (exit_status, killed_by_exec) = {
+            let thread_group = current.thread_group.lock();
+            (
+                thread_group.exit_status(),
+                thread_group.is_killed_by_exec(current),
+            )
+        };
+        if let Some(wait_status) = exit_status {
+            Process::do_exit(wait_status);
+        }
+
+        if killed_by_exec {
+            Process::do_exit(SIGKILL);
+        }
     }
 
     /// Terminates the **current** process by a signal.
-    pub fn exit_by_signal(_signal: Signal) -> ! {
-        Process::exit(1 /* FIXME: how should we compute the exit status? */);
+    pub fn exit_by_signal(signal: Signal) -> ! {
+        // The wait status of a process killed by a signal is the signal
+        // number.
+        let mut wait_status = signal;
+        if default_action(signal) == DefaultAction::Core && coredump::dump_core() {
+            wait_status |= WCOREFLAG;
+        }
+
+        Process::do_exit_group(wait_status)
+    }
+
+    /// Terminates the **current** thread by a signal. Other threads keep
+    /// running.
+    pub fn exit_thread_by_signal(signal: Signal) -> ! {
+        Process::do_exit(signal)
     }
 
-    /// Sends a signal.
+    /// Sends a signal from the kernel.
     pub fn send_signal(&self, signal: Signal) {
-        self.signals.lock().signal(signal);
-        self.resume();
+        self.send_siginfo(SigInfo::kernel(signal));
+    }
+
+    /// Sends a signal with its information.
+    pub fn send_siginfo(&self, info: SigInfo) {
+        let signal = info.signal;
+        if signal == SIGCONT {
+            // SIGCONT continues the process even if it's blocked or ignored.
+            self.continue_thread_group();
+        } else if STOP_SIGNALS.contains(signal) {
+            let mut sigcont = SigSet::EMPTY;
+            sigcont.add(SIGCONT);
+            self.signals.lock().discard(sigcont);
+        }
+
+        {
+            let mut signals = self.signals.lock();
+            // An ignored signal is discarded unless it's blocked: the action
+            // might be changed before it's unblocked.
+            if signals.is_ignored(signal) && !self.signal_mask().contains(signal) {
+                return;
+            }
+
+            if !signals.signal(info) {
+                return;
+            }
+        }
+
+        // Signals are shared among threads. Wake up one of them which has not
+        // yet exited and does not block the signal.
+        if !matches!(self.state(), ProcessState::ExitedWith(_))
+            && !self.signal_mask().contains(signal)
+        {
+            self.resume();
+            return;
+        }
+
+        let thread = self
+            .thread_group
+            .lock()
+            .threads()
+            .iter()
+            .filter_map(|t| t.upgrade())
+            .find(|t| !t.signal_mask().contains(signal));
+        if let Some(thread) = thread {
+            thread.resume();
+        }
+    }
+
+    /// Sends a signal caused by the **current** thread itself (e.g. a page
+    /// fault). Unlike [`Process::send_siginfo`], it's delivered even if it's
+    /// blocked or ignored: the default action is taken instead.
+    pub fn force_signal(&self, info: SigInfo) {
+        let signal = info.signal;
+        let mut signals = self.signals.lock();
+        if signals.is_ignored(signal) || self.signal_mask().contains(signal) {
+            signals.set_action(signal, SigAction::DEFAULT).ok();
+            self.sigset.lock().remove(signal);
+        }
+
+        signals.signal(info);
     }
 
-    /// Returns `true` if there's a pending signal.
+    /// Returns `true` if there's a pending signal which is not blocked, or the
+    /// thread group is exiting or stopped.
     pub fn has_pending_signals(&self) -> bool {
-        self.signals.lock().is_pending()
+        let pending = self.signals.lock().pending();
+        if !(pending & !self.signal_mask()).is_empty() {
+            return true;
+        }
+
+        let thread_group = self.thread_group.lock();
+        thread_group.exit_status().is_some()
+            || thread_group.stop_signal().is_some()
+            || thread_group.is_killed_by_exec(self)
     }
 
-    /// Sets signal mask.
-    pub fn set_signal_mask(
-        &self,
-        how: SignalMask,
-        set: Option<UserVAddr>,
-        oldset: Option<UserVAddr>,
-        _length: usize,
-    ) -> Result<()> {
-        let mut sigset = self.sigset.lock();
+    /// Stops the thread group of the **current** thread by a signal and
+    /// waits until it's continued.
+    fn stop_thread_group(signal: Signal) {
+        let current = current_process();
+        let leader = {
+            let mut thread_group = current.thread_group.lock();
+            if thread_group.stop(signal) {
+                // Other threads stop when they return to the userspace. Wake
+                // them up if they're sleeping.
+                for thread in thread_group.threads() {
+                    if let Some(thread) = thread.upgrade() {
+                        if !Arc::ptr_eq(&thread, current) {
+                            thread.resume();
+                        }
+                    }
+                }
+
+                thread_group.leader().upgrade()
+            } else {
+                None
+            }
+        };
 
-        if let Some(old) = oldset {
-            old.write_bytes(sigset.as_raw_slice())?;
+        if let Some(leader) = leader {
+            leader.notify_job_control_event(CLD_STOPPED, signal);
         }
 
-        if let Some(new) = set {
-            let new_set = new.read::<[u8; 128]>()?;
-            let new_set = SigSet::new(new_set);
-            match how {
-                SignalMask::Block => *sigset |= new_set,
-                SignalMask::Unblock => *sigset &= !new_set,
-                SignalMask::Set => *sigset = new_set,
+        Process::wait_while_stopped();
+    }
+
+    /// Sleeps while the thread group of the **current** thread is stopped.
+    fn wait_while_stopped() {
+        let current = current_process();
+        loop {
+            current.set_state(ProcessState::Stopped);
+
+            let stopped = {
+                let thread_group = current.thread_group.lock();
+                thread_group.stop_signal().is_some() && thread_group.exit_status().is_none()
+            };
+
+            // SIGKILL terminates the stopped process.
+            if !stopped || current.signals.lock().pending().contains(SIGKILL) {
+                break;
             }
+
+            switch();
         }
 
-        Ok(())
+        current.resume();
+    }
+
+    /// Continues the stopped thread group by `SIGCONT`. Pending stop signals
+    /// are discarded.
+    fn continue_thread_group(&self) {
+        self.signals.lock().discard(STOP_SIGNALS);
+
+        let leader = {
+            let mut thread_group = self.thread_group.lock();
+            if !thread_group.cont() {
+                return;
+            }
+
+            for thread in thread_group.threads() {
+                if let Some(thread) = thread.upgrade() {
+                    if thread.state() == ProcessState::Stopped {
+                        thread.resume();
+                    }
+                }
+            }
+
+            thread_group.leader().upgrade()
+        };
+
+        if let Some(leader) = leader {
+            leader.notify_job_control_event(CLD_CONTINUED, SIGCONT);
+        }
+    }
+
+    /// Notifies the parent that the process (the thread group leader) has
+    /// been stopped or continued.
+    fn notify_job_control_event(&self, code: c_int, status: c_int) {
+        if let Some(parent) = self.parent() {
+            let action = parent.signals().lock().get_action(SIGCHLD);
+            if !action.flags.contains(SigActionFlags::SA_NOCLDSTOP) {
+                parent.send_siginfo(SigInfo::child(
+                    parent.pid_ns().pid_of(self),
+                    self.credentials().uid,
+                    code,
+                    status,
+                ));
+            }
+        }
+
+        JOIN_WAIT_QUEUE.wake_all();
+    }
+
+    /// Pending signals which are blocked (`rt_sigpending(2)`).
+    pub fn blocked_pending_signals(&self) -> SigSet {
+        self.signals.lock().pending() & self.signal_mask()
+    }
+
+    /// Dequeues a pending signal in `set` regardless of the signal mask
+    /// (`rt_sigtimedwait(2)`).
+    pub fn dequeue_signal(&self, set: SigSet) -> Option<SigInfo> {
+        self.pop_pending_signal(!set).map(|(info, _)| info)
+    }
+
+    /// Dequeues a pending signal which is not in `blocked`. The overrun count
+    /// of a POSIX timer is fixed here.
+    fn pop_pending_signal(&self, blocked: SigSet) -> Option<(SigInfo, SigAction)> {
+        let (mut info, action) = self.signals.lock().pop_pending(blocked)?;
+        if let SigInfoFields::Timer {
+            id,
+            ref mut overrun,
+            ..
+        } = info.fields
+        {
+            if let Ok(posix_timer) = self.thread_group().posix_timer(id) {
+                *overrun = posix_timer.timer.deliver().min(c_int::MAX as u64) as c_int;
+            }
+        }
+
+        Some((info, action))
+    }
+
+    /// The blocked signals.
+    pub fn signal_mask(&self) -> SigSet {
+        *self.sigset.lock()
+    }
+
+    /// Updates the signal mask. Returns the previous one.
+    pub fn set_signal_mask(&self, how: SignalMask, set: SigSet) -> SigSet {
+        let mut sigset = self.sigset.lock();
+        let old = *sigset;
+        let new = match how {
+            SignalMask::Block => old | set,
+            SignalMask::Unblock => old & !set,
+            SignalMask::Set => set,
+        };
+
+        *sigset = new.without_unblockable();
+        old
+    }
+
+    /// Replaces the signal mask until a signal is delivered: the current one
+    /// is restored after that (`rt_sigsuspend(2)`).
+    pub fn set_temporary_signal_mask(&self, mask: SigSet) {
+        let old = self.set_signal_mask(SignalMask::Set, mask);
+        if self.saved_sigset.load().is_none() {
+            self.saved_sigset.store(Some(old));
+        }
+    }
+
+    /// The alternate signal stack.
+    pub fn sigaltstack(&self) -> Option<SigAltStack> {
+        self.sigaltstack.load()
+    }
+
+    /// Sets the alternate signal stack.
+    pub fn set_sigaltstack(&self, stack: Option<SigAltStack>) {
+        self.sigaltstack.store(stack);
     }
 
     /// Tries to delivering a pending signal to the current process.
     ///
     /// If there's a pending signal, it may modify `frame` (e.g. user return
     /// address and stack pointer) to call the registered user's signal handler.
+    /// It also restarts or aborts the interrupted system call.
     pub fn try_delivering_signal(frame: &mut PtRegs) -> Result<()> {
+        Process::exit_if_thread_group_exiting();
+
         let current = current_process();
-        if let Some((signal, sigaction)) = current.signals.lock().pop_pending() {
-            let sigset = current.sigset.lock();
-            if !sigset.get(signal as usize).as_deref().unwrap_or(&true) {
-                match sigaction {
-                    SigAction::Ignore => {}
-                    SigAction::Terminate => {
-                        trace!("terminating {:?} by {:?}", current.pid, signal,);
-                        Process::exit(1 /* FIXME: */);
-                    }
-                    SigAction::Handler { handler } => {
-                        trace!("delivering {:?} to {:?}", signal, current.pid,);
-                        current.signaled_frame.store(Some(*frame));
-                        unsafe {
-                            current.arch.setup_signal_stack(frame, signal, handler)?;
-                        }
-                    }
+        let stopped = current.thread_group.lock().stop_signal().is_some();
+        if stopped {
+            // Another thread has stopped the thread group.
+            Process::wait_while_stopped();
+            Process::exit_if_thread_group_exiting();
+        }
+
+        let mask = current.signal_mask();
+        let saved_mask = current.saved_sigset.take();
+        let mut popped = current.pop_pending_signal(mask);
+        if let Some((info, _)) = popped {
+            if info.signal != SIGKILL && current.is_traced() {
+                // Signal-delivery-stop: the tracer may suppress or replace the
+                // signal.
+                popped = Process::ptrace_signal_stop(frame, info).map(|info| {
+                    let action = current.signals.lock().get_action(info.signal);
+                    (info, action)
+                });
+            }
+        }
+
+        let (info, action) = match popped {
+            Some((info, action)) => (info, action),
+            None => {
+                restart_syscall_if_interrupted(frame, None);
+                if let Some(saved_mask) = saved_mask {
+                    current.set_signal_mask(SignalMask::Set, saved_mask);
                 }
+
+                return Ok(());
             }
+        };
+
+        let signal = info.signal;
+        let handler = match action.handler {
+            SigHandler::Handler(handler) => handler,
+            SigHandler::Default
+                if matches!(
+                    default_action(signal),
+                    DefaultAction::Terminate | DefaultAction::Core
+                ) =>
+            {
+                trace!("terminating {:?} by {:?}", current.pid, signal);
+                Process::ptrace_event(frame, PTRACE_EVENT_EXIT, signal as u64);
+                Process::exit_by_signal(signal);
+            }
+            SigHandler::Default | SigHandler::Ignore => {
+                // Processes in an orphaned process group are not stopped by
+                // SIGTSTP, SIGTTIN and SIGTTOU: no one would continue them.
+                if action.handler == SigHandler::Default
+                    && default_action(signal) == DefaultAction::Stop
+                    && (signal == SIGSTOP || !process_group::is_orphaned(&current.process_group()))
+                {
+                    Process::stop_thread_group(signal);
+                    Process::exit_if_thread_group_exiting();
+                }
+
+                restart_syscall_if_interrupted(frame, None);
+                if let Some(saved_mask) = saved_mask {
+                    current.set_signal_mask(SignalMask::Set, saved_mask);
+                }
+
+                return Ok(());
+            }
+        };
+
+        trace!("delivering {:?} to {:?}", signal, current.pid);
+        restart_syscall_if_interrupted(frame, Some(action.flags));
+        let result = unsafe {
+            current.arch.setup_signal_stack(
+                frame,
+                &info,
+                handler,
+                &action,
+                current.sigaltstack(),
+                saved_mask.unwrap_or(mask),
+            )
+        };
+
+        if let Err(err) = result {
+            // The user stack is not available.
+            debug_warn!("failed to set up the signal stack: {:?}", err);
+            Process::exit_by_signal(SIGSEGV);
+        }
+
+        let mut new_mask = mask | action.mask;
+        if !action.flags.contains(SigActionFlags::SA_NODEFER) {
+            new_mask.add(signal);
+        }
+        current.set_signal_mask(SignalMask::Set, new_mask);
+
+        if action.flags.contains(SigActionFlags::SA_RESETHAND) {
+            current
+                .signals
+                .lock()
+                .set_action(signal, SigAction::DEFAULT)
+                .ok();
         }
 
         Ok(())
     }
 
     /// So-called `sigreturn`: restores the user context when the signal is
-    /// delivered to a signal handler.
-    pub fn restore_signaled_user_stack(current: &Arc<Process>, current_frame: &mut PtRegs) {
-        if let Some(signaled_frame) = current.signaled_frame.swap(None) {
-            current
-                .arch
-                .setup_sigreturn_stack(current_frame, &signaled_frame);
-        } else {
-            // The user intentionally called sigreturn(2) while it is not signaled.
-            // TODO: Should we ignore instead of the killing the process?
-            Process::exit_by_signal(SIGKILL);
+    /// delivered to a signal handler. Returns the restored `rax`.
+    pub fn restore_signaled_user_stack(
+        current: &Arc<Process>,
+        current_frame: &mut PtRegs,
+    ) -> isize {
+        let on_altstack = matches!(current.sigaltstack(), Some(stack) if stack.contains(current_frame.rsp as usize));
+        let (mask, stack) = match current.arch.setup_sigreturn_stack(current_frame) {
+            Ok(restored) => restored,
+            Err(err) => {
+                // The signal context on the user stack is broken.
+                debug_warn!("failed to restore the signal context: {:?}", err);
+                Process::exit_by_signal(SIGSEGV);
+            }
+        };
+
+        current.set_signal_mask(SignalMask::Set, mask);
+
+        // Like Linux, the alternate signal stack cannot be changed while
+        // we're on it.
+        if !on_altstack {
+            let stack = if stack.ss_flags & SS_DISABLE != 0 {
+                None
+            } else {
+                UserVAddr::new(stack.ss_sp).map(|sp| SigAltStack {
+                    sp,
+                    size: stack.ss_size,
+                })
+            };
+            current.set_sigaltstack(stack);
         }
+
+        current_frame.rax as isize
     }
 
     /// Creates a new virtual memory space, loads the executable, and overwrites
@@ -476,14 +1399,52 @@ impl Process {
         let current = current_process();
         current.opened_files.lock().close_cloexec_files();
         current.cmdline.borrow_mut().set_by_argv(argv);
+        current.set_trace_match(0);
+
+        let mut credentials = (*current.credentials()).clone();
+        let rlimits = current.thread_group.lock().rlimits().clone();
+        let entry = setup_userspace(
+            executable_path,
+            argv,
+            envp,
+            &current.root_fs(),
+            &credentials,
+            &rlimits,
+        )?;
+
+        // Other threads are gone with the old executable.
+        current.kill_other_threads()?;
+
+        // Switch to the owner of the executable if it's a setuid binary. Like
+        // Linux, setuid and setgid bits are ignored while an unprivileged
+        // tracer is attached: it could take over the elevated process. The
+        // ptrace lock keeps new tracers from attaching in the meantime.
+        {
+            let ptrace = current.ptrace.lock();
+            let traced_unprivileged = ptrace
+                .as_ref()
+                .and_then(|ptrace| ptrace.tracer())
+                .map_or(false, |tracer| !tracer.credentials().is_privileged());
+            credentials.exec(
+                &entry.executable_stat,
+                current.no_new_privs() || traced_unprivileged,
+            );
+            current.set_credentials(credentials);
+        }
 
-        let entry = setup_userspace(executable_path, argv, envp, &current.root_fs)?;
+        // Signal handlers are gone with the old executable.
+        current.signals.lock().reset_handlers();
+        current.sigaltstack.store(None);
 
-        // FIXME: Should we prevent try_delivering_signal()?
-        current.signaled_frame.store(None);
+        // POSIX timers are deleted while interval timers are preserved.
+        let posix_timers = current.thread_group.lock().take_posix_timers();
+        for posix_timer in posix_timers {
+            posix_timer.timer.set(None, 0);
+        }
 
         entry.vm.page_table().switch();
         *current.vm.borrow_mut() = Some(Arc::new(SpinLock::new(entry.vm)));
+        current.release_vfork_parent();
 
         current
             .arch
@@ -492,45 +1453,369 @@ impl Process {
         Ok(())
     }
 
+    /// Terminates other threads in the thread group of the **current** thread
+    /// and waits for them to exit. The current thread takes over the thread
+    /// group leader.
+    fn kill_other_threads(self: &Arc<Process>) -> Result<()> {
+        let leader = {
+            let mut thread_group = self.thread_group.lock();
+            if thread_group.threads().len() == 1 {
+                return Ok(());
+            }
+
+            // Another thread is calling `execve(2)` or `exit_group(2)`.
+            if thread_group.exit_status().is_some() || thread_group.is_killed_by_exec(self) {
+                return Err(Errno::ERESTARTSYS.into());
+            }
+
+            // Other threads exit when they return to the userspace. Wake them
+            // up if they're sleeping.
+            thread_group.set_exec_thread(Some(Arc::downgrade(self)));
+            for thread in thread_group.threads() {
+                if let Some(thread) = thread.upgrade() {
+                    if !Arc::ptr_eq(&thread, self) {
+                        thread.resume();
+                    }
+                }
+            }
+
+            thread_group.leader().upgrade()
+        };
+
+        let result = JOIN_WAIT_QUEUE.sleep_signalable_until(|| {
+            if self.thread_group.lock().threads().len() > 1 {
+                return Ok(None);
+            }
+
+            // Wait for the leader to release its thread ID as well.
+            let released = match &leader {
+                Some(leader) if !Arc::ptr_eq(leader, self) => PROCESSES
+                    .lock()
+                    .get(&leader.tid())
+                    .map_or(true, |proc| !Arc::ptr_eq(proc, leader)),
+                _ => true,
+            };
+            Ok(if released { Some(()) } else { None })
+        });
+        self.thread_group.lock().set_exec_thread(None);
+        result?;
+
+        if let Some(leader) = leader {
+            if !Arc::ptr_eq(&leader, self) {
+                self.take_over_leader(&leader);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the exited thread group leader with the **current** thread:
+    /// it takes over the thread ID, the parent, and the process group.
+    fn take_over_leader(self: &Arc<Process>, leader: &Arc<Process>) {
+        {
+            let mut process_table = PROCESSES.lock();
+            let old_tid = self.tid();
+            process_table.remove(&old_tid);
+            unregister_ns_ids(self);
+            self.tid.store(self.pid);
+            *self.ns_ids.lock() = leader.ns_ids().clone();
+            register_ns_ids(self);
+            process_table.insert(self.pid, self.clone());
+            cgroup::change_thread_id(self, old_tid);
+        }
+
+        self.thread_group.lock().set_leader(Arc::downgrade(self));
+        *self.parent.lock() = leader.parent.lock().clone();
+        if let Some(parent) = self.parent() {
+            for child in parent.children().iter_mut() {
+                if Arc::ptr_eq(child, leader) {
+                    *child = self.clone();
+                }
+            }
+        }
+
+        let process_group = leader.process_group();
+        {
+            let mut process_group = process_group.lock();
+            process_group.add(Arc::downgrade(self));
+            process_group.remove(&Arc::downgrade(leader));
+        }
+        self.set_process_group(Arc::downgrade(&process_group));
+    }
+
     /// Creates a new process. The calling process (`self`) will be the parent
     /// process of the created process. Returns the created child process.
     pub fn fork(parent: &Arc<Process>, parent_frame: &PtRegs) -> Result<Arc<Process>> {
-        let parent_weak = Arc::downgrade(parent);
+        Process::clone(
+            parent,
+            parent_frame,
+            CloneFlags::empty(),
+            Some(SIGCHLD),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Creates a new process or a thread as specified in `flags`. Returns the
+    /// created child process (or thread).
+    #[allow(clippy::too_many_arguments)]
+    pub fn clone(
+        parent: &Arc<Process>,
+        parent_frame: &PtRegs,
+        flags: CloneFlags,
+        exit_signal: Option<Signal>,
+        child_stack: Option<UserVAddr>,
+        tls: Option<UserVAddr>,
+        parent_tid: Option<UserVAddr>,
+        child_tid: Option<UserVAddr>,
+    ) -> Result<Arc<Process>> {
+        let credentials = parent.credentials();
+        let nproc_limit = parent.rlimit(RLIMIT_NPROC)?.cur_as_usize();
+
+        // Copy the memory space and other states before locking the process
+        // table: the scheduler takes the lock on every CPU.
+        let tls = if flags.contains(CloneFlags::CLONE_SETTLS) {
+            Some(tls.ok_or_else(|| Error::new(Errno::EINVAL))?)
+        } else {
+            None
+        };
+        let arch = parent.arch.clone(parent_frame, child_stack, tls)?;
+
+        let vm = if flags.contains(CloneFlags::CLONE_VM) {
+            parent.vm().as_ref().unwrap().clone()
+        } else {
+            Arc::new(SpinLock::new(parent.vm().as_ref().unwrap().lock().fork()?))
+        };
+
+        let opened_files = if flags.contains(CloneFlags::CLONE_FILES) {
+            parent.opened_files().clone()
+        } else {
+            // TODO: #88 has to address this
+            Arc::new(SpinLock::new(parent.opened_files().lock().clone()))
+        };
+
+        let signals = if flags.contains(CloneFlags::CLONE_SIGHAND) {
+            parent.signals.clone()
+        } else {
+            Arc::new(SpinLock::new(parent.signals.lock().fork()))
+        };
+
+        // A thread sharing the memory space must not share the alternate
+        // signal stack.
+        let sigaltstack = if flags.contains(CloneFlags::CLONE_VM) {
+            None
+        } else {
+            parent.sigaltstack()
+        };
+
+        let root_fs = if flags.contains(CloneFlags::CLONE_NEWNS) {
+            Arc::new(SpinLock::new(parent.root_fs().lock().clone()))
+        } else {
+            parent.root_fs().clone()
+        };
+
+        let namespaces = if flags.intersects(CloneFlags::CLONE_NEWUTS | CloneFlags::CLONE_NEWPID) {
+            Arc::new(parent.namespaces().unshare(flags)?)
+        } else {
+            parent.namespaces()
+        };
+
+        // A sibling notifies the parent as the calling process does.
+        let exit_signal = if flags.contains(CloneFlags::CLONE_PARENT) {
+            parent.thread_group.lock().exit_signal()
+        } else {
+            exit_signal
+        };
+
         let mut process_table = PROCESSES.lock();
-        let pid = alloc_pid(&mut process_table)?;
-        let arch = parent.arch.fork(parent_frame)?;
-        let vm = parent.vm().as_ref().unwrap().lock().fork()?;
-        let opened_files = parent.opened_files().lock().clone(); // TODO: #88 has to address this
+        // RLIMIT_NPROC: the number of processes owned by the real user ID.
+        // Threads are not counted.
+        let nproc = process_table
+            .values()
+            .filter(|proc| proc.pid == proc.tid() && proc.credentials().uid == credentials.uid)
+            .count();
+        if nproc >= nproc_limit && !credentials.is_privileged() {
+            return Err(Errno::EAGAIN.into());
+        }
+
+        let tid = alloc_pid(&mut process_table)?;
+        let is_thread = flags.contains(CloneFlags::CLONE_THREAD);
+        let (pid, thread_group, parent_weak, children) = if is_thread {
+            (
+                parent.pid,
+                parent.thread_group.clone(),
+                parent.parent.lock().clone(),
+                parent.children.clone(),
+            )
+        } else {
+            // The parent of the new process is the parent thread's process,
+            // i.e. the thread group leader, or its parent for CLONE_PARENT.
+            let (leader, rlimits) = {
+                let thread_group = parent.thread_group.lock();
+                (
+                    thread_group.leader().clone(),
+                    thread_group.rlimits().clone(),
+                )
+            };
+            let parent_weak = if flags.contains(CloneFlags::CLONE_PARENT) {
+                parent.parent.lock().clone()
+            } else {
+                leader
+            };
+            (
+                tid,
+                ThreadGroup::new(rlimits, exit_signal),
+                parent_weak,
+                Arc::new(SpinLock::new(Vec::new())),
+            )
+        };
+
+        let pid_ns = if is_thread {
+            parent.pid_ns.clone()
+        } else {
+            namespaces.pid_for_children().clone()
+        };
+        let ns_ids = alloc_ns_ids(
+            &pid_ns,
+            NsIds { pid, tid },
+            if is_thread { Some(parent) } else { None },
+        )?;
+
+        let clear_child_tid = if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
+            child_tid
+        } else {
+            None
+        };
+
+        // The tracer may trace the new child as well. CLONE_PTRACE forces it
+        // and CLONE_UNTRACED prevents it.
+        let ptrace = if flags.contains(CloneFlags::CLONE_UNTRACED) {
+            None
+        } else {
+            parent.ptrace().as_ref().and_then(|ptrace| {
+                ptrace.inherit(clone_event(flags), flags.contains(CloneFlags::CLONE_PTRACE))
+            })
+        };
+        let tracer = ptrace.as_ref().and_then(|ptrace| ptrace.tracer());
+
+        // The pids controller limits the number of threads in the group.
+        let cgroup = parent.cgroup();
+        cgroup.try_charge_thread()?;
+
         let process_group = parent.process_group();
         let sig_set = parent.sigset.lock();
-
         let child = Arc::new(Process {
             is_idle: false,
             process_group: AtomicRefCell::new(Arc::downgrade(&process_group)),
             pid,
+            tid: AtomicCell::new(tid),
+            thread_group: thread_group.clone(),
+            clear_child_tid: AtomicCell::new(clear_child_tid),
+            robust_list: AtomicCell::new(None),
             state: AtomicCell::new(ProcessState::Runnable),
-            parent: parent_weak,
+            parent: SpinLock::new(parent_weak),
+            in_vfork: AtomicBool::new(flags.contains(CloneFlags::CLONE_VFORK)),
             cmdline: AtomicRefCell::new(parent.cmdline().clone()),
-            children: SpinLock::new(Vec::new()),
-            vm: AtomicRefCell::new(Some(Arc::new(SpinLock::new(vm)))),
-            opened_files: Arc::new(SpinLock::new(opened_files)),
-            root_fs: parent.root_fs().clone(),
+            children,
+            vm: AtomicRefCell::new(Some(vm)),
+            opened_files,
+            root_fs: AtomicRefCell::new(root_fs),
+            pid_ns,
+            ns_ids: SpinLock::new(ns_ids),
+            namespaces: SpinLock::new(namespaces),
+            credentials: SpinLock::new(credentials),
             arch,
-            signals: Arc::new(SpinLock::new(SignalDelivery::new())), // TODO: #88 has to address this
-            signaled_frame: AtomicCell::new(None),
+            signals,
             sigset: SpinLock::new(*sig_set),
+            saved_sigset: AtomicCell::new(None),
+            sigaltstack: AtomicCell::new(sigaltstack),
+            sched_entity: SpinLock::new(SchedEntity::new(
+                parent.sched_policy(),
+                parent.nice(),
+                cgroup.sched_group(),
+            )),
+            cpu_times: SpinLock::new(CpuTimes::default()),
+            in_kernel: AtomicBool::new(false),
+            ptrace: SpinLock::new(ptrace),
+            ptrace_stopped: AtomicBool::new(false),
+            tracees: SpinLock::new(Vec::new()),
+            cgroup: SpinLock::new(cgroup),
+            seccomp: SpinLock::new(parent.seccomp()),
+            seccomp_enabled: AtomicBool::new(parent.is_seccomp_enabled()),
+            trace_match: AtomicU64::new(parent.trace_match()),
+            no_new_privs: AtomicBool::new(parent.no_new_privs()),
         });
+        drop(sig_set);
+
+        // Reserve the IDs. The child is added to its parent and groups after
+        // writing the thread ID below.
+        process_table.insert(tid, child.clone());
+        register_ns_ids(&child);
+        drop(process_table);
+
+        if let Err(err) = write_child_tid(parent, &child, flags, parent_tid, child_tid) {
+            PROCESSES.lock().remove(&tid);
+            unregister_ns_ids(&child);
+            cgroup::remove_exited_thread(&child);
+            return Err(err);
+        }
+
+        if !is_thread {
+            process_group.lock().add(Arc::downgrade(&child));
+            if let Some(new_parent) = child.parent() {
+                new_parent.children().push(child.clone());
+            }
+        }
+
+        thread_group.lock().add(Arc::downgrade(&child));
+        cgroup::add_thread(&child);
+
+        if let Some(tracer) = tracer {
+            // The child starts in a signal-delivery-stop.
+            tracer.tracees().push(child.clone());
+            child.send_signal(SIGSTOP);
+        }
 
-        process_group.lock().add(Arc::downgrade(&child));
-        parent.children().push(child.clone());
-        process_table.insert(pid, child.clone());
-        SCHEDULER.lock().enqueue(pid);
+        SCHEDULER.enqueue(&child, &mut child.sched_entity.lock());
 
         FORK_TOTAL.fetch_add(1, Ordering::Relaxed);
         Ok(child)
     }
 }
 
+/// Writes the thread ID of a new child for `CLONE_PARENT_SETTID` and
+/// `CLONE_CHILD_SETTID` before it starts running.
+fn write_child_tid(
+    parent: &Process,
+    child: &Process,
+    flags: CloneFlags,
+    parent_tid: Option<UserVAddr>,
+    child_tid: Option<UserVAddr>,
+) -> Result<()> {
+    let vtid = parent.pid_ns.tid_of(child);
+    if let Some(parent_tid) = parent_tid.filter(|_| flags.contains(CloneFlags::CLONE_PARENT_SETTID))
+    {
+        parent_tid.write::<c_int>(&vtid.as_i32())?;
+    }
+
+    if let Some(child_tid) = child_tid.filter(|_| flags.contains(CloneFlags::CLONE_CHILD_SETTID)) {
+        if flags.contains(CloneFlags::CLONE_VM) {
+            child_tid.write::<c_int>(&vtid.as_i32())?;
+        } else {
+            // The child has its own copy of the memory space.
+            let mut bytes = vtid.as_i32().to_ne_bytes();
+            let vm = child.vm();
+            let mut vm = vm.as_ref().unwrap().lock();
+            access_memory_forcibly(&mut vm, child_tid.value(), &mut bytes, true)
+                .map_err(|_| Error::new(Errno::EFAULT))?;
+        }
+    }
+
+    Ok(())
+}
+
 impl Drop for Process {
     fn drop(&mut self) {
         trace!(
@@ -550,6 +1835,9 @@ struct UserspaceEntry {
     vm: Vm,
     ip: UserVAddr,
     user_sp: UserVAddr,
+    /// The metadata of the loaded ELF file. For a script, it's the
+    /// interpreter: the setuid and setgid bits of scripts are ignored.
+    executable_stat: Stat,
 }
 
 fn setup_userspace(
@@ -557,8 +1845,10 @@ fn setup_userspace(
     argv: &[&[u8]],
     envp: &[&[u8]],
     root_fs: &Arc<SpinLock<RootFs>>,
+    creds: &Credentials,
+    rlimits: &ResourceLimits,
 ) -> Result<UserspaceEntry> {
-    do_setup_userspace(executable_path, argv, envp, root_fs, true)
+    do_setup_userspace(executable_path, argv, envp, root_fs, creds, rlimits, true)
 }
 
 fn do_script_binfmt(
@@ -566,6 +1856,8 @@ fn do_script_binfmt(
     script_argv: &[&[u8]],
     envp: &[&[u8]],
     root_fs: &Arc<SpinLock<RootFs>>,
+    creds: &Credentials,
+    rlimits: &ResourceLimits,
     buf: &[u8],
 ) -> Result<UserspaceEntry> {
     // Set up argv[] with the interpreter and its arguments from the shebang line.
@@ -591,9 +1883,10 @@ fn do_script_binfmt(
     let shebang_path = root_fs.lock().lookup_path(
         Path::new(core::str::from_utf8(argv[0]).map_err(|_| Error::new(Errno::EINVAL))?),
         true,
+        creds,
     )?;
 
-    do_setup_userspace(shebang_path, &argv, envp, root_fs, false)
+    do_setup_userspace(shebang_path, &argv, envp, root_fs, creds, rlimits, false)
 }
 
 /// Loads an ELF file's PT_LOAD sections into a process's memory.  The offset
@@ -610,7 +1903,7 @@ fn load_elf(vm: &mut Vm, executable: &Arc<dyn FileLike>, elf: &Elf, offset: u64)
         let vaddr = align_down_u64(load_vaddr, PAGE_SIZE as u64);
         let align_offset = load_vaddr - vaddr;
         let file_size = phdr.p_filesz + align_offset;
-        let mem_size = align_up_u64(phdr.p_memsz + align_offset, PAGE_SIZE as u64) - 1;
+        let mem_size = align_up_u64(phdr.p_memsz + align_offset, PAGE_SIZE as u64);
         let offset = phdr.p_offset - align_offset;
 
         let area_type = if phdr.p_filesz > 0 {
@@ -618,15 +1911,29 @@ fn load_elf(vm: &mut Vm, executable: &Arc<dyn FileLike>, elf: &Elf, offset: u64)
                 file: executable.clone(),
                 offset: offset as usize,
                 file_size: file_size as usize,
+                shared: false,
+                writable: false,
             }
         } else {
             VmAreaType::Anonymous
         };
 
+        let mut prot = MMapProt::empty();
+        if phdr.p_flags & PF_R != 0 {
+            prot |= MMapProt::PROT_READ;
+        }
+        if phdr.p_flags & PF_W != 0 {
+            prot |= MMapProt::PROT_WRITE;
+        }
+        if phdr.p_flags & PF_X != 0 {
+            prot |= MMapProt::PROT_EXEC;
+        }
+
         vm.add_vm_area(
             UserVAddr::new_nonnull(vaddr as usize)?,
             mem_size as usize,
             area_type,
+            prot,
         )?;
     }
 
@@ -635,6 +1942,8 @@ fn load_elf(vm: &mut Vm, executable: &Arc<dyn FileLike>, elf: &Elf, offset: u64)
 
 fn do_elf_binfmt(
     root_fs: &Arc<SpinLock<RootFs>>,
+    creds: &Credentials,
+    rlimits: &ResourceLimits,
     executable: &Arc<dyn FileLike>,
     argv: &[&[u8]],
     envp: &[&[u8]],
@@ -656,7 +1965,9 @@ fn do_elf_binfmt(
             .map_err(|_| Error::new(Errno::EINVAL))?
             .trim_end_matches('\0');
 
-        let interpreter_path = root_fs.lock().lookup_path(Path::new(interpreter), true)?;
+        let interpreter_path = root_fs
+            .lock()
+            .lookup_path(Path::new(interpreter), true, creds)?;
         let interpreter = interpreter_path.inode.as_file()?;
 
         interpreter.read(
@@ -714,12 +2025,20 @@ fn do_elf_binfmt(
         Auxv::Entry(at_entry as usize),
         Auxv::Base(interpreter_offset as usize),
     ];
-    const USER_STACK_LEN: usize = 128 * 1024; // TODO: Implement rlimit
+    // The stack size is RLIMIT_STACK, capped to leave room for the heap.
+    const USER_STACK_LEN_MAX: usize = 1024 * 1024 * 1024;
+    let user_stack_len = align_down(
+        min(
+            rlimits.get(RLIMIT_STACK)?.cur_as_usize(),
+            USER_STACK_LEN_MAX,
+        ),
+        PAGE_SIZE,
+    );
     let init_stack_top = USER_STACK_TOP;
-    let user_stack_bottom = init_stack_top.sub(USER_STACK_LEN).value();
+    let user_stack_bottom = init_stack_top.sub(user_stack_len).value();
     let user_heap_bottom = align_up(end_of_image, PAGE_SIZE);
     let init_stack_len = align_up(estimate_user_init_stack_size(argv, envp, auxv), PAGE_SIZE);
-    if user_heap_bottom >= user_stack_bottom || init_stack_len >= USER_STACK_LEN {
+    if user_heap_bottom >= user_stack_bottom || init_stack_len >= user_stack_len {
         return Err(Errno::E2BIG.into());
     }
 
@@ -737,11 +2056,13 @@ fn do_elf_binfmt(
         UserVAddr::new(user_stack_bottom).unwrap(),
         UserVAddr::new(user_heap_bottom).unwrap(),
     )?;
+    vm.set_address_space_limit(rlimits.get(RLIMIT_AS)?.cur_as_usize());
 
     for i in 0..(init_stack_len / PAGE_SIZE) {
         vm.page_table_mut().map_user_page(
             init_stack_top.sub(((init_stack_len / PAGE_SIZE) - i) * PAGE_SIZE),
             init_stack_pages.add(i * PAGE_SIZE),
+            PageProtection::READABLE | PageProtection::WRITABLE,
         );
     }
 
@@ -765,6 +2086,7 @@ fn do_elf_binfmt(
         vm,
         ip: UserVAddr::new_nonnull(entry as usize)?,
         user_sp,
+        executable_stat: executable.stat()?,
     })
 }
 
@@ -775,24 +2097,74 @@ fn do_setup_userspace(
     argv: &[&[u8]],
     envp: &[&[u8]],
     root_fs: &Arc<SpinLock<RootFs>>,
+    creds: &Credentials,
+    rlimits: &ResourceLimits,
     handle_shebang: bool,
 ) -> Result<UserspaceEntry> {
+    // The executable must be a regular file with the execute permission.
+    let stat = executable_path.inode.stat()?;
+    if !stat.mode.is_regular_file() {
+        return Err(Errno::EACCES.into());
+    }
+    creds.check_access(&stat, AccessMode::X_OK)?;
+
     // Read the ELF header in the executable file.
     let mut buf = [0u8; PAGE_SIZE];
     let executable = executable_path.inode.as_file()?;
     executable.read(0, (&mut buf[..]).into(), &OpenOptions::readwrite())?;
 
     if handle_shebang && buf.starts_with(b"#!") && buf.contains(&b'\n') {
-        return do_script_binfmt(&executable_path, argv, envp, root_fs, &buf);
+        return do_script_binfmt(&executable_path, argv, envp, root_fs, creds, rlimits, &buf);
+    }
+
+    do_elf_binfmt(root_fs, creds, rlimits, executable, argv, envp, &buf)
+}
+
+/// Restarts the system call interrupted by a signal or makes it fail with
+/// `EINTR`. `handler_flags` is the flags of the signal handler to be called.
+fn restart_syscall_if_interrupted(frame: &mut PtRegs, handler_flags: Option<SigActionFlags>) {
+    let errno = match arch::syscall_return_value(frame) {
+        Some(value) if value == -(Errno::ERESTARTSYS as isize) => Errno::ERESTARTSYS,
+        Some(value) if value == -(Errno::ERESTARTNOHAND as isize) => Errno::ERESTARTNOHAND,
+        _ => return,
+    };
+
+    let restart = match handler_flags {
+        None => true,
+        Some(flags) => errno == Errno::ERESTARTSYS && flags.contains(SigActionFlags::SA_RESTART),
+    };
+
+    if restart {
+        arch::restart_syscall(frame);
+    } else {
+        arch::set_syscall_return_value(frame, -(Errno::EINTR as isize));
     }
+}
+
+/// The bit set in the wait status if the process has dumped the core.
+pub const WCOREFLAG: c_int = 0x80;
 
-    do_elf_binfmt(root_fs, executable, argv, envp, &buf)
+/// Returns the `wait4(2)` status of a process exited with `exit_code`.
+fn exited_wait_status(exit_code: c_int) -> c_int {
+    (exit_code & 0xff) << 8
+}
+
+/// Returns `si_code` and `si_status` of `SIGCHLD` sent on a child's exit.
+fn child_exit_code_and_status(wait_status: c_int) -> (c_int, c_int) {
+    match wait_status & 0x7f {
+        0 => (CLD_EXITED, (wait_status >> 8) & 0xff),
+        signal if wait_status & WCOREFLAG != 0 => (CLD_DUMPED, signal),
+        signal => (CLD_KILLED, signal),
+    }
 }
 
 pub fn gc_exited_processes() {
     if current_process().is_idle() {
         // If we're in an idle thread, it's safe to free kernel stacks allocated
-        // for other exited processes.
-        EXITED_PROCESSES.lock().clear();
+        // for other exited processes unless they're still running on other
+        // CPUs.
+        EXITED_PROCESSES
+            .lock()
+            .retain(|proc| proc.arch().is_running());
     }
 }
@@ -2,7 +2,11 @@ use crate::prelude::*;
 use alloc::{collections::BTreeMap, vec::Vec};
 use kerla_runtime::spinlock::SpinLock;
 
-use super::{signal::Signal, Process};
+use super::{
+    session::Session,
+    signal::{SigInfo, SIGCONT, SIGHUP},
+    Process, ProcessState,
+};
 
 pub static PROCESS_GROUPS: SpinLock<BTreeMap<PgId, Arc<SpinLock<ProcessGroup>>>> =
     SpinLock::new(BTreeMap::new());
@@ -24,14 +28,17 @@ impl PgId {
 /// A process group.
 pub struct ProcessGroup {
     pgid: PgId,
+    /// The session which the group belongs to.
+    session: Arc<SpinLock<Session>>,
     processes: Vec<Weak<Process>>,
 }
 
 impl ProcessGroup {
-    /// Create a new process group.
-    pub fn new(pgid: PgId) -> Arc<SpinLock<ProcessGroup>> {
+    /// Create a new process group in the session.
+    pub fn new(pgid: PgId, session: Arc<SpinLock<Session>>) -> Arc<SpinLock<ProcessGroup>> {
         let pg = Arc::new(SpinLock::new(ProcessGroup {
             pgid,
+            session,
             processes: Vec::new(),
         }));
 
@@ -46,10 +53,13 @@ impl ProcessGroup {
     }
 
     /// Looks for the process group with the given process group ID. If it does
-    /// not exist, create a new process group.
-    pub fn find_or_create_by_pgid(pgid: PgId) -> Arc<SpinLock<ProcessGroup>> {
+    /// not exist, create a new process group in the session.
+    pub fn find_or_create_by_pgid(
+        pgid: PgId,
+        session: Arc<SpinLock<Session>>,
+    ) -> Arc<SpinLock<ProcessGroup>> {
         let pg = { PROCESS_GROUPS.lock().get(&pgid).cloned() };
-        pg.unwrap_or_else(|| ProcessGroup::new(pgid))
+        pg.unwrap_or_else(|| ProcessGroup::new(pgid, session))
     }
 
     /// The process group ID.
@@ -57,6 +67,11 @@ impl ProcessGroup {
         self.pgid
     }
 
+    /// The session which the group belongs to.
+    pub fn session(&self) -> &Arc<SpinLock<Session>> {
+        &self.session
+    }
+
     /// Adds a process into the group.
     pub fn add(&mut self, proc: Weak<Process>) {
         self.processes.push(proc);
@@ -78,10 +93,86 @@ impl ProcessGroup {
         }
     }
 
+    /// Processes in the group.
+    pub fn processes(&self) -> Vec<Arc<Process>> {
+        self.processes
+            .iter()
+            .filter_map(|proc| proc.upgrade())
+            .collect()
+    }
+
     /// Sends a signal to all processes in the proces group.
-    pub fn signal(&mut self, signal: Signal) {
+    pub fn signal(&mut self, info: SigInfo) {
         for proc in &self.processes {
-            proc.upgrade().unwrap().send_signal(signal);
+            proc.upgrade().unwrap().send_siginfo(info);
+        }
+    }
+}
+
+/// Returns `true` if `proc` keeps `pg` from being orphaned, i.e. it's alive
+/// and in another process group in the same session.
+fn links_to_group(proc: &Process, pg: &Arc<SpinLock<ProcessGroup>>) -> bool {
+    if matches!(proc.state(), ProcessState::ExitedWith(_)) {
+        return false;
+    }
+
+    let proc_pg = proc.process_group();
+    if Arc::ptr_eq(&proc_pg, pg) {
+        return false;
+    }
+
+    let session = proc_pg.lock().session().clone();
+    Arc::ptr_eq(&session, pg.lock().session())
+}
+
+/// Returns `true` if the process group is orphaned: no processes in the
+/// group have a parent in another process group in the same session.
+pub fn is_orphaned(pg: &Arc<SpinLock<ProcessGroup>>) -> bool {
+    let processes = pg.lock().processes();
+    !processes.iter().any(|proc| {
+        !matches!(proc.state(), ProcessState::ExitedWith(_))
+            && proc
+                .parent()
+                .map_or(false, |parent| links_to_group(&parent, pg))
+    })
+}
+
+/// Sends `SIGHUP` and `SIGCONT` to process groups orphaned by the exit of
+/// `proc` if they have stopped processes.
+pub fn hang_up_orphaned_groups(proc: &Process) {
+    // The exit of `proc` may orphan its own group (if the parent is the link)
+    // and the groups of its children (if `proc` is the link).
+    let own_pg = proc.process_group();
+    let own_session = own_pg.lock().session().clone();
+    let mut candidates = Vec::new();
+    if proc
+        .parent()
+        .map_or(false, |parent| links_to_group(&parent, &own_pg))
+    {
+        candidates.push(own_pg.clone());
+    }
+
+    let children = proc.children().clone();
+    for child in children {
+        let pg = child.process_group();
+        if !Arc::ptr_eq(&pg, &own_pg)
+            && Arc::ptr_eq(pg.lock().session(), &own_session)
+            && !candidates.iter().any(|c| Arc::ptr_eq(c, &pg))
+        {
+            candidates.push(pg);
+        }
+    }
+
+    for pg in candidates {
+        let processes = pg.lock().processes();
+        let has_stopped = processes
+            .iter()
+            .any(|proc| proc.thread_group().stop_signal().is_some());
+        if has_stopped && is_orphaned(&pg) {
+            for proc in processes {
+                proc.send_siginfo(SigInfo::kernel(SIGHUP));
+                proc.send_siginfo(SigInfo::kernel(SIGCONT));
+            }
         }
     }
 }
@@ -0,0 +1,442 @@
+//! Process tracing (`ptrace(2)`): the tracee side.
+//!
+//! A tracee stops (ptrace-stop) on signal deliveries, system call entries and
+//! exits, and events such as `fork(2)` and `execve(2)`. The tracer is
+//! notified by `SIGCHLD` and `wait4(2)`, inspects and modifies the saved user
+//! context, and resumes the tracee.
+use crate::{
+    arch::{self, FpuState},
+    ctypes::{c_int, CloneFlags},
+    prelude::*,
+};
+use alloc::sync::{Arc, Weak};
+use bitflags::bitflags;
+use core::{ptr, sync::atomic::Ordering};
+use kerla_runtime::arch::PtRegs;
+
+use super::{
+    current_process,
+    signal::{SigInfo, Signal, CLD_TRAPPED, SIGKILL, SIGTRAP},
+    switch, Process, ProcessState, JOIN_WAIT_QUEUE,
+};
+
+pub const PTRACE_EVENT_FORK: c_int = 1;
+pub const PTRACE_EVENT_VFORK: c_int = 2;
+pub const PTRACE_EVENT_CLONE: c_int = 3;
+pub const PTRACE_EVENT_EXEC: c_int = 4;
+pub const PTRACE_EVENT_EXIT: c_int = 6;
+pub const PTRACE_EVENT_SECCOMP: c_int = 7;
+
+/// The bit set in the stop status of system call stops if
+/// `PTRACE_O_TRACESYSGOOD` is enabled.
+const SYSCALL_STOP_BIT: c_int = 0x80;
+
+bitflags! {
+    pub struct PtraceOptions: c_int {
+        const PTRACE_O_TRACESYSGOOD   = 0x1;
+        const PTRACE_O_TRACEFORK      = 0x2;
+        const PTRACE_O_TRACEVFORK     = 0x4;
+        const PTRACE_O_TRACECLONE     = 0x8;
+        const PTRACE_O_TRACEEXEC      = 0x10;
+        const PTRACE_O_TRACEVFORKDONE = 0x20;
+        const PTRACE_O_TRACEEXIT      = 0x40;
+        const PTRACE_O_TRACESECCOMP   = 0x80;
+        const PTRACE_O_EXITKILL       = 0x100000;
+    }
+}
+
+/// Returns the ptrace event reported on `clone(2)` with `flags`.
+pub fn clone_event(flags: CloneFlags) -> c_int {
+    if flags.contains(CloneFlags::CLONE_VFORK) {
+        PTRACE_EVENT_VFORK
+    } else if flags.intersects(CloneFlags::CLONE_VM | CloneFlags::CLONE_THREAD) {
+        PTRACE_EVENT_CLONE
+    } else {
+        PTRACE_EVENT_FORK
+    }
+}
+
+/// How the tracee continues from a ptrace-stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PtraceResume {
+    /// `PTRACE_CONT`.
+    Continue,
+    /// `PTRACE_SYSCALL`: stops at the next system call entry or exit.
+    Syscall,
+    /// `PTRACE_SINGLESTEP`: stops after executing an instruction.
+    SingleStep,
+}
+
+/// The tracing state of a tracee.
+pub struct Ptrace {
+    tracer: Weak<Process>,
+    options: PtraceOptions,
+    resume: PtraceResume,
+    /// Whether the tracee is in a ptrace-stop.
+    stopped: bool,
+    /// The stop status not yet reported by `wait4(2)`.
+    report: Option<c_int>,
+    /// The user context saved in the ptrace-stop. The tracer reads and
+    /// modifies it while the tracee is stopped.
+    regs: Option<PtRegs>,
+    /// The FPU registers saved in the ptrace-stop.
+    fpregs: Option<FpuState>,
+    /// The signal delivered to the tracee once it's resumed.
+    resume_signal: Option<Signal>,
+    /// The signal which has caused the signal-delivery-stop.
+    siginfo: Option<SigInfo>,
+    /// The value returned by `PTRACE_GETEVENTMSG`.
+    event_msg: u64,
+    /// The tracer has detached while the tracee is stopped.
+    detached: bool,
+}
+
+impl Ptrace {
+    pub fn new(tracer: &Arc<Process>, options: PtraceOptions) -> Ptrace {
+        Ptrace {
+            tracer: Arc::downgrade(tracer),
+            options,
+            resume: PtraceResume::Continue,
+            stopped: false,
+            report: None,
+            regs: None,
+            fpregs: None,
+            resume_signal: None,
+            siginfo: None,
+            event_msg: 0,
+            detached: false,
+        }
+    }
+
+    pub fn tracer(&self) -> Option<Arc<Process>> {
+        self.tracer.upgrade()
+    }
+
+    pub fn is_traced_by(&self, tracer: &Process) -> bool {
+        ptr::eq(self.tracer.as_ptr(), tracer)
+    }
+
+    pub fn set_options(&mut self, options: PtraceOptions) {
+        self.options = options;
+    }
+
+    /// Whether the tracee is in a ptrace-stop.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped && !self.detached
+    }
+
+    /// The user context of the stopped tracee.
+    pub fn regs_mut(&mut self) -> Option<&mut PtRegs> {
+        if self.is_stopped() {
+            self.regs.as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// The FPU registers of the stopped tracee.
+    pub fn fpregs_mut(&mut self) -> Option<&mut FpuState> {
+        if self.is_stopped() {
+            self.fpregs.as_mut()
+        } else {
+            None
+        }
+    }
+
+    pub fn siginfo(&self) -> Option<SigInfo> {
+        self.siginfo
+    }
+
+    pub fn event_msg(&self) -> u64 {
+        self.event_msg
+    }
+
+    /// Returns the stop status to be reported by `wait4(2)`. It's reported
+    /// only once if `consume` is `true`.
+    pub fn take_report(&mut self, consume: bool) -> Option<c_int> {
+        if consume {
+            self.report.take()
+        } else {
+            self.report
+        }
+    }
+
+    /// Whether the tracer has requested stops at `event`.
+    fn traces_event(&self, event: c_int) -> bool {
+        let option = match event {
+            PTRACE_EVENT_FORK => PtraceOptions::PTRACE_O_TRACEFORK,
+            PTRACE_EVENT_VFORK => PtraceOptions::PTRACE_O_TRACEVFORK,
+            PTRACE_EVENT_CLONE => PtraceOptions::PTRACE_O_TRACECLONE,
+            PTRACE_EVENT_EXEC => PtraceOptions::PTRACE_O_TRACEEXEC,
+            PTRACE_EVENT_EXIT => PtraceOptions::PTRACE_O_TRACEEXIT,
+            PTRACE_EVENT_SECCOMP => PtraceOptions::PTRACE_O_TRACESECCOMP,
+            _ => return false,
+        };
+
+        self.options.contains(option)
+    }
+
+    /// Returns the tracing state of a new child created by the tracee if the
+    /// tracer has requested to trace it on `event`, or if it's `forced`
+    /// (`CLONE_PTRACE`).
+    pub fn inherit(&self, event: c_int, forced: bool) -> Option<Ptrace> {
+        if !forced && !self.traces_event(event) {
+            return None;
+        }
+
+        self.tracer()
+            .map(|tracer| Ptrace::new(&tracer, self.options))
+    }
+}
+
+impl Process {
+    /// Whether the thread is being traced.
+    pub fn is_traced(&self) -> bool {
+        self.ptrace().is_some()
+    }
+
+    /// Returns `true` if the thread has been in a ptrace-stop since the last
+    /// call: the tracer might have modified any registers.
+    pub fn take_ptrace_stopped(&self) -> bool {
+        self.ptrace_stopped.swap(false, Ordering::Relaxed)
+    }
+
+    /// The tracer of the thread.
+    pub fn tracer(&self) -> Option<Arc<Process>> {
+        self.ptrace().as_ref().and_then(|ptrace| ptrace.tracer())
+    }
+
+    /// Starts tracing `tracee` by `tracer`. If `check_permission` is set, the
+    /// tracer must be permitted to trace the tracee's credentials: they're
+    /// checked under the ptrace lock not to race with a setuid `execve(2)`.
+    pub fn ptrace_attach(
+        tracee: &Arc<Process>,
+        tracer: &Arc<Process>,
+        options: PtraceOptions,
+        check_permission: bool,
+    ) -> Result<()> {
+        {
+            let mut ptrace = tracee.ptrace();
+            if ptrace.is_some()
+                || (check_permission && !tracer.credentials().can_trace(&tracee.credentials()))
+            {
+                return Err(Errno::EPERM.into());
+            }
+
+            *ptrace = Some(Ptrace::new(tracer, options));
+        }
+
+        tracer.tracees().push(tracee.clone());
+        Ok(())
+    }
+
+    /// Resumes the tracee from the ptrace-stop. `signal` is delivered to it.
+    pub fn ptrace_resume(&self, how: PtraceResume, signal: Option<Signal>) -> Result<()> {
+        {
+            let mut ptrace = self.ptrace();
+            let ptrace = match ptrace.as_mut() {
+                Some(ptrace) if ptrace.is_stopped() => ptrace,
+                _ => return Err(Errno::ESRCH.into()),
+            };
+
+            ptrace.resume = how;
+            ptrace.resume_signal = signal;
+            ptrace.stopped = false;
+        }
+
+        self.resume();
+        Ok(())
+    }
+
+    /// Stops tracing the thread. It continues with `signal` if it's stopped.
+    pub fn ptrace_detach(&self, signal: Option<Signal>) {
+        let stopped = {
+            let mut ptrace = self.ptrace();
+            match ptrace.as_mut() {
+                Some(ptrace) if ptrace.stopped => {
+                    // The tracee restores the modified user context and
+                    // forgets the tracer by itself.
+                    ptrace.detached = true;
+                    ptrace.stopped = false;
+                    ptrace.resume = PtraceResume::Continue;
+                    ptrace.resume_signal = signal;
+                    true
+                }
+                _ => {
+                    *ptrace = None;
+                    false
+                }
+            }
+        };
+
+        if stopped && self.state() == ProcessState::Stopped {
+            self.resume();
+        }
+    }
+
+    /// Detaches all tracees of the exiting tracer. Tracees are killed if the
+    /// tracer has requested by `PTRACE_O_EXITKILL`.
+    pub(super) fn detach_tracees(&self) {
+        let tracees = core::mem::take(&mut *self.tracees());
+        for tracee in tracees {
+            let exit_kill = tracee.ptrace().as_ref().map_or(false, |ptrace| {
+                ptrace.options.contains(PtraceOptions::PTRACE_O_EXITKILL)
+            });
+
+            tracee.ptrace_detach(None);
+            if exit_kill {
+                tracee.send_signal(SIGKILL);
+            }
+        }
+    }
+
+    /// Stops the **current** thread until the tracer resumes it. `status` is
+    /// reported by `wait4(2)` as the stop signal. Returns the signal given by
+    /// the tracer, or `None` if the thread is not traced.
+    fn ptrace_stop(frame: &mut PtRegs, status: c_int, siginfo: Option<SigInfo>) -> Option<Signal> {
+        let current = current_process();
+        let tracer = {
+            let mut ptrace = current.ptrace();
+            let ptrace = ptrace.as_mut()?;
+            ptrace.stopped = true;
+            ptrace.report = Some(status);
+            ptrace.regs = Some(*frame);
+            ptrace.fpregs = Some(FpuState::save());
+            ptrace.siginfo = siginfo;
+            ptrace.resume_signal = None;
+            ptrace.tracer()
+        };
+
+        match tracer {
+            Some(tracer) => {
+                tracer.send_siginfo(SigInfo::child(
+                    tracer.pid_ns().tid_of(current),
+                    current.credentials().uid,
+                    CLD_TRAPPED,
+                    status & 0x7f,
+                ));
+            }
+            None => {
+                // The tracer has already exited.
+                current.ptrace().take();
+                return None;
+            }
+        }
+
+        current.ptrace_stopped.store(true, Ordering::Relaxed);
+        JOIN_WAIT_QUEUE.wake_all();
+
+        loop {
+            current.set_state(ProcessState::Stopped);
+
+            let stopped = current
+                .ptrace()
+                .as_ref()
+                .map_or(false, |ptrace| ptrace.stopped);
+            let killed = current.signals().lock().pending().contains(SIGKILL)
+                || current.thread_group().exit_status().is_some();
+            if !stopped || killed {
+                break;
+            }
+
+            switch();
+        }
+
+        current.resume();
+
+        let signal = {
+            let mut ptrace_lock = current.ptrace();
+            match ptrace_lock.as_mut() {
+                Some(ptrace) => {
+                    ptrace.stopped = false;
+                    ptrace.report = None;
+                    if let Some(regs) = ptrace.regs.take() {
+                        *frame = regs;
+                    }
+                    if let Some(fpregs) = ptrace.fpregs.take() {
+                        fpregs.restore();
+                    }
+
+                    arch::set_single_step(frame, ptrace.resume == PtraceResume::SingleStep);
+                    let signal = ptrace.resume_signal.take();
+                    if ptrace.detached {
+                        *ptrace_lock = None;
+                    }
+
+                    signal
+                }
+                None => {
+                    arch::set_single_step(frame, false);
+                    None
+                }
+            }
+        };
+
+        Process::exit_if_thread_group_exiting();
+        if current.signals().lock().pending().contains(SIGKILL) {
+            Process::exit_by_signal(SIGKILL);
+        }
+
+        signal
+    }
+
+    /// Stops the **current** thread at a system call entry or exit if the
+    /// tracer has requested by `PTRACE_SYSCALL`. Returns `true` if it has
+    /// stopped: the tracer might have modified the system call.
+    pub fn ptrace_syscall_stop(frame: &mut PtRegs, entry: bool) -> bool {
+        let current = current_process();
+        let status = match current.ptrace().as_ref() {
+            Some(ptrace) if ptrace.resume == PtraceResume::Syscall => {
+                if ptrace
+                    .options
+                    .contains(PtraceOptions::PTRACE_O_TRACESYSGOOD)
+                {
+                    SIGTRAP | SYSCALL_STOP_BIT
+                } else {
+                    SIGTRAP
+                }
+            }
+            _ => return false,
+        };
+
+        if entry {
+            // The return value seen by the tracer until the system call is
+            // executed.
+            arch::set_syscall_return_value(frame, -(Errno::ENOSYS as isize));
+        }
+
+        if let Some(signal) = Process::ptrace_stop(frame, status, None) {
+            current.send_signal(signal);
+        }
+
+        true
+    }
+
+    /// Reports a signal to be delivered to the **current** thread to the
+    /// tracer (signal-delivery-stop). Returns the signal to be delivered
+    /// instead, or `None` if the tracer has suppressed it.
+    pub fn ptrace_signal_stop(frame: &mut PtRegs, info: SigInfo) -> Option<SigInfo> {
+        match Process::ptrace_stop(frame, info.signal, Some(info))? {
+            signal if signal == info.signal => Some(info),
+            signal => Some(SigInfo::kernel(signal)),
+        }
+    }
+
+    /// Stops the **current** thread at `event` if the tracer has requested by
+    /// `PTRACE_SETOPTIONS`. `msg` is returned by `PTRACE_GETEVENTMSG`.
+    /// Returns `true` if it has stopped.
+    pub fn ptrace_event(frame: &mut PtRegs, event: c_int, msg: u64) -> bool {
+        {
+            let mut ptrace = current_process().ptrace();
+            match ptrace.as_mut() {
+                Some(ptrace) if ptrace.traces_event(event) => {
+                    ptrace.event_msg = msg;
+                }
+                _ => return false,
+            }
+        }
+
+        Process::ptrace_stop(frame, SIGTRAP | (event << 8), None);
+        true
+    }
+}
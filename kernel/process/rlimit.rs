@@ -0,0 +1,112 @@
+use crate::{ctypes::c_int, fs::opened_file::FD_MAX, prelude::*};
+
+#[allow(unused)]
+pub const RLIMIT_CPU: c_int = 0;
+#[allow(unused)]
+pub const RLIMIT_FSIZE: c_int = 1;
+#[allow(unused)]
+pub const RLIMIT_DATA: c_int = 2;
+pub const RLIMIT_STACK: c_int = 3;
+pub const RLIMIT_CORE: c_int = 4;
+#[allow(unused)]
+pub const RLIMIT_RSS: c_int = 5;
+pub const RLIMIT_NPROC: c_int = 6;
+pub const RLIMIT_NOFILE: c_int = 7;
+pub const RLIMIT_MEMLOCK: c_int = 8;
+pub const RLIMIT_AS: c_int = 9;
+#[allow(unused)]
+pub const RLIMIT_LOCKS: c_int = 10;
+#[allow(unused)]
+pub const RLIMIT_SIGPENDING: c_int = 11;
+pub const RLIMIT_MSGQUEUE: c_int = 12;
+pub const RLIMIT_NICE: c_int = 13;
+pub const RLIMIT_RTPRIO: c_int = 14;
+#[allow(unused)]
+pub const RLIMIT_RTTIME: c_int = 15;
+const RLIM_NLIMITS: usize = 16;
+
+/// No limit.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// `struct rlimit`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct RLimit {
+    /// The soft limit enforced by the kernel.
+    pub cur: u64,
+    /// The hard limit: the ceiling for the soft limit.
+    pub max: u64,
+}
+
+impl RLimit {
+    pub const fn new(cur: u64, max: u64) -> RLimit {
+        RLimit { cur, max }
+    }
+
+    pub const fn infinity() -> RLimit {
+        RLimit::new(RLIM_INFINITY, RLIM_INFINITY)
+    }
+
+    /// The soft limit as `usize`. `usize::MAX` if it's unlimited.
+    pub fn cur_as_usize(&self) -> usize {
+        self.cur.try_into().unwrap_or(usize::MAX)
+    }
+}
+
+/// Resource limits of a process. Inherited over `fork(2)` and preserved
+/// across `execve(2)`.
+#[derive(Debug, Clone)]
+pub struct ResourceLimits {
+    limits: [RLimit; RLIM_NLIMITS],
+}
+
+impl ResourceLimits {
+    /// The default limits of the init process.
+    pub fn new() -> ResourceLimits {
+        let mut limits = [RLimit::infinity(); RLIM_NLIMITS];
+        limits[RLIMIT_STACK as usize] = RLimit::new(8 * 1024 * 1024, RLIM_INFINITY);
+        limits[RLIMIT_CORE as usize] = RLimit::new(0, RLIM_INFINITY);
+        limits[RLIMIT_NOFILE as usize] = RLimit::new(FD_MAX as u64, FD_MAX as u64);
+        limits[RLIMIT_MEMLOCK as usize] = RLimit::new(8 * 1024 * 1024, 8 * 1024 * 1024);
+        limits[RLIMIT_MSGQUEUE as usize] = RLimit::new(819200, 819200);
+        limits[RLIMIT_NICE as usize] = RLimit::new(0, 0);
+        limits[RLIMIT_RTPRIO as usize] = RLimit::new(0, 0);
+        ResourceLimits { limits }
+    }
+
+    /// Returns the limit of `resource`. Returns `EINVAL` if it's unknown.
+    pub fn get(&self, resource: c_int) -> Result<RLimit> {
+        usize::try_from(resource)
+            .ok()
+            .and_then(|i| self.limits.get(i))
+            .copied()
+            .ok_or_else(|| Error::new(Errno::EINVAL))
+    }
+
+    /// Updates the limit of `resource`. Only a privileged process can raise
+    /// the hard limit.
+    pub fn set(&mut self, resource: c_int, new_limit: RLimit, privileged: bool) -> Result<()> {
+        let old_limit = self.get(resource)?;
+        if new_limit.cur > new_limit.max {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if new_limit.max > old_limit.max && !privileged {
+            return Err(Errno::EPERM.into());
+        }
+
+        // The opened file table can't grow beyond FD_MAX.
+        if resource == RLIMIT_NOFILE && new_limit.max > FD_MAX as u64 {
+            return Err(Errno::EPERM.into());
+        }
+
+        self.limits[resource as usize] = new_limit;
+        Ok(())
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> ResourceLimits {
+        ResourceLimits::new()
+    }
+}
@@ -1,37 +1,470 @@
-use crate::process::PId;
-use alloc::collections::VecDeque;
-use kerla_runtime::spinlock::SpinLock;
+use crate::{
+    process::{cgroup::CPU_WEIGHT_DEFAULT, process::PROCESSES, PId, Process, ProcessState},
+    timer::{read_monotonic_clock, MonotonicClock},
+};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::max;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use kerla_runtime::{
+    arch::{cpu_id, is_cpu_online, num_cpus, send_reschedule_ipi},
+    spinlock::SpinLock,
+};
+
+/// The lowest nice value (i.e. the highest priority).
+pub const NICE_MIN: i32 = -20;
+/// The highest nice value (i.e. the lowest priority).
+pub const NICE_MAX: i32 = 19;
+/// The lowest priority of `SCHED_FIFO`.
+pub const RT_PRIORITY_MIN: i32 = 1;
+/// The highest priority of `SCHED_FIFO`.
+pub const RT_PRIORITY_MAX: i32 = 99;
+
+/// The period in which every runnable process in a run queue runs at least
+/// once, in nanoseconds.
+const SCHED_LATENCY: u64 = 20_000_000;
+/// The minimum time slice in nanoseconds.
+const SCHED_MIN_GRANULARITY: u64 = 3_000_000;
+
+/// The weight of nice 0.
+const NICE_0_WEIGHT: u64 = 1024;
+/// Weights indexed by `nice - NICE_MIN`. A process gets ~10% more CPU time
+/// than one with the next higher nice value.
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// A scheduling policy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// `SCHED_OTHER`: processes share the CPU time in proportion to their
+    /// weights determined by nice values.
+    Normal,
+    /// `SCHED_FIFO`: a real-time process with the priority
+    /// ([`RT_PRIORITY_MIN`] to [`RT_PRIORITY_MAX`]). It runs until it blocks,
+    /// yields, or a higher priority one becomes runnable. Always preferred to
+    /// `SCHED_OTHER` processes.
+    Fifo(i32),
+}
+
+/// Scheduling states shared among threads in a non-root control group: the
+/// group as a whole gets the CPU time in proportion to its weight.
+pub struct SchedGroup {
+    /// The CPU weight of the group (see
+    /// [`Cgroup::effective_cpu_weight`](super::cgroup::Cgroup::effective_cpu_weight)).
+    weight: AtomicU64,
+    /// The number of runnable threads in the group.
+    nr_runnable: AtomicUsize,
+}
+
+impl SchedGroup {
+    pub fn new(weight: u64) -> SchedGroup {
+        SchedGroup {
+            weight: AtomicU64::new(weight),
+            nr_runnable: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn set_weight(&self, weight: u64) {
+        self.weight.store(weight, Ordering::Relaxed);
+    }
+}
+
+/// Per-process scheduling parameters and states.
+pub struct SchedEntity {
+    policy: SchedPolicy,
+    nice: i32,
+    /// The control group. `None` if it's the root group.
+    group: Option<Arc<SchedGroup>>,
+    /// `true` if the process is counted as runnable in the group, i.e. it's
+    /// in a run queue or running.
+    runnable: bool,
+    /// The CPU whose run queue holds the process.
+    queued_on: Option<usize>,
+    /// `true` while a CPU runs the process: from [`Scheduler::pick_next`] to
+    /// [`Scheduler::put_prev`].
+    on_cpu: bool,
+    /// The elapsed running time weighted by the nice value, in nanoseconds.
+    vruntime: u64,
+    /// The monotonic clock when the statistics have been updated.
+    exec_start: MonotonicClock,
+    /// The total CPU time used by the process in nanoseconds.
+    sum_exec_runtime: u64,
+    /// `sum_exec_runtime` when the process has been switched in.
+    prev_sum_exec_runtime: u64,
+    /// `sum_exec_runtime` when [`Scheduler::consume_exec_runtime`] has been
+    /// called.
+    consumed_exec_runtime: u64,
+}
+
+impl SchedEntity {
+    pub fn new(policy: SchedPolicy, nice: i32, group: Option<Arc<SchedGroup>>) -> SchedEntity {
+        SchedEntity {
+            policy,
+            nice,
+            group,
+            runnable: false,
+            queued_on: None,
+            on_cpu: false,
+            vruntime: 0,
+            exec_start: MonotonicClock::from_nanosecs(0),
+            sum_exec_runtime: 0,
+            prev_sum_exec_runtime: 0,
+            consumed_exec_runtime: 0,
+        }
+    }
+
+    pub fn policy(&self) -> SchedPolicy {
+        self.policy
+    }
+
+    pub fn nice(&self) -> i32 {
+        self.nice
+    }
+
+    /// The weight determined by the nice value. In a non-root control group,
+    /// it's scaled by the group's CPU weight divided among its runnable
+    /// threads.
+    fn weight(&self) -> u64 {
+        let weight = NICE_TO_WEIGHT[(self.nice - NICE_MIN) as usize];
+        match &self.group {
+            Some(group) => {
+                let nr_runnable = max(group.nr_runnable.load(Ordering::Relaxed), 1) as u64;
+                let group_weight = group.weight.load(Ordering::Relaxed);
+                max(weight * group_weight / CPU_WEIGHT_DEFAULT / nr_runnable, 1)
+            }
+            None => weight,
+        }
+    }
+
+    /// Counts the process as runnable in the group.
+    fn set_runnable(&mut self, runnable: bool) {
+        if self.runnable == runnable {
+            return;
+        }
+
+        self.runnable = runnable;
+        if let Some(group) = &self.group {
+            if runnable {
+                group.nr_runnable.fetch_add(1, Ordering::Relaxed);
+            } else {
+                group.nr_runnable.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Charges the time elapsed since the last update to the running process.
+    /// Use [`Scheduler::update_curr`] instead: the run queue is keyed by the
+    /// virtual runtime.
+    fn update_curr(&mut self) {
+        let now = read_monotonic_clock();
+        let delta = self.exec_start.nanosecs_until(now);
+        self.exec_start = now;
+        self.sum_exec_runtime += delta;
+        self.vruntime += (delta as u128 * NICE_0_WEIGHT as u128 / self.weight() as u128) as u64;
+    }
+}
+
+struct RunQueue {
+    /// `SCHED_FIFO` processes sorted by their priorities (highest first).
+    rt: VecDeque<(i32, PId)>,
+    /// `SCHED_OTHER` processes sorted by their virtual runtimes. The value is
+    /// the weight.
+    fair: BTreeMap<(u64, PId), u64>,
+}
+
+impl RunQueue {
+    fn new() -> RunQueue {
+        RunQueue {
+            rt: VecDeque::new(),
+            fair: BTreeMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.rt.len() + self.fair.len()
+    }
+
+    fn remove(&mut self, pid: PId, vruntime: u64) {
+        self.rt.retain(|(_, p)| *p != pid);
+        self.fair.remove(&(vruntime, pid));
+    }
+
+    fn peek_next(&self) -> Option<PId> {
+        if let Some((_, pid)) = self.rt.front() {
+            return Some(*pid);
+        }
+
+        self.fair.keys().next().map(|(_, pid)| *pid)
+    }
+}
 
 /// The process scheduler.
 ///
-/// Currently, it implements a round-robin algorithm.
+/// It implements a weighted fair scheduling (like Linux's CFS) with per-CPU
+/// run queues: the process with the smallest virtual runtime runs next.
+/// `SCHED_FIFO` processes precede them. Runnable processes are enqueued into
+/// the least loaded CPU and an idle CPU steals processes from other CPUs.
+///
+/// Run queues are modified only while holding the lock on the scheduling
+/// entity of the process (and then the run queue): [`SchedEntity::queued_on`]
+/// always tells which run queue holds the process.
 pub struct Scheduler {
-    run_queue: SpinLock<VecDeque<PId>>,
+    /// The run queues indexed by the CPU ID.
+    run_queues: Vec<SpinLock<RunQueue>>,
+    /// The number of processes in each run queue. They're read without locking
+    /// run queues to choose the CPU.
+    queue_lens: Vec<AtomicUsize>,
+    /// The virtual runtime of the most recently picked process. Processes
+    /// woken up start from here not to monopolize CPUs.
+    min_vruntime: AtomicU64,
 }
 
 impl Scheduler {
     /// Creates a scheduler.
     pub fn new() -> Scheduler {
         Scheduler {
-            run_queue: SpinLock::new(VecDeque::new()),
+            run_queues: (0..num_cpus())
+                .map(|_| SpinLock::new(RunQueue::new()))
+                .collect(),
+            queue_lens: (0..num_cpus()).map(|_| AtomicUsize::new(0)).collect(),
+            min_vruntime: AtomicU64::new(0),
         }
     }
 
-    /// Enqueues a process into the runqueue.
-    pub fn enqueue(&self, pid: PId) {
-        self.run_queue.lock().push_back(pid);
+    /// Enqueues a process into a run queue unless it's already in one or
+    /// running: the CPU running it enqueues it when it's switched out.
+    ///
+    /// `entity` is the locked scheduling entity of the process: hold the lock
+    /// while updating the process state not to race with
+    /// [`Scheduler::put_prev`].
+    pub fn enqueue(&self, proc: &Process, entity: &mut SchedEntity) {
+        self.do_enqueue(proc.tid(), entity, false);
     }
 
-    /// Returns the next process to run.
+    fn do_enqueue(&self, pid: PId, entity: &mut SchedEntity, at_head: bool) {
+        if entity.queued_on.is_some() || entity.on_cpu {
+            return;
+        }
+
+        entity.vruntime = max(entity.vruntime, self.min_vruntime.load(Ordering::Relaxed));
+        entity.set_runnable(true);
+
+        // Prefer the current CPU if run queues are equally loaded.
+        let current = cpu_id();
+        let mut target = current;
+        let mut min_len = self.queue_lens[current].load(Ordering::Relaxed);
+        for (cpu, len) in self.queue_lens.iter().enumerate() {
+            let len = len.load(Ordering::Relaxed);
+            if len < min_len && is_cpu_online(cpu) {
+                target = cpu;
+                min_len = len;
+            }
+        }
+
+        let mut queue = self.run_queues[target].lock();
+        match entity.policy {
+            SchedPolicy::Normal => {
+                queue.fair.insert((entity.vruntime, pid), entity.weight());
+            }
+            SchedPolicy::Fifo(prio) => {
+                let index = queue
+                    .rt
+                    .iter()
+                    .position(|(p, _)| if at_head { *p <= prio } else { *p < prio })
+                    .unwrap_or(queue.rt.len());
+                queue.rt.insert(index, (prio, pid));
+            }
+        }
+
+        self.queue_lens[target].store(queue.len(), Ordering::Relaxed);
+        entity.queued_on = Some(target);
+        drop(queue);
+
+        if target != current {
+            send_reschedule_ipi(target);
+        }
+    }
+
+    /// Removes the process from its run queue if it's queued.
+    fn dequeue(&self, pid: PId, entity: &mut SchedEntity) {
+        if let Some(cpu) = entity.queued_on.take() {
+            let mut queue = self.run_queues[cpu].lock();
+            queue.remove(pid, entity.vruntime);
+            self.queue_lens[cpu].store(queue.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Charges the time elapsed since the last update to the process. If it's
+    /// in a run queue, it's moved to the position for the new virtual runtime.
+    fn update_curr(&self, pid: PId, entity: &mut SchedEntity) {
+        match entity.queued_on {
+            Some(cpu) => {
+                let mut queue = self.run_queues[cpu].lock();
+                let weight = queue.fair.remove(&(entity.vruntime, pid));
+                entity.update_curr();
+                if let Some(weight) = weight {
+                    queue.fair.insert((entity.vruntime, pid), weight);
+                }
+            }
+            None => entity.update_curr(),
+        }
+    }
+
+    /// Returns the CPU time in nanoseconds the running process has used since
+    /// the last call.
+    pub fn consume_exec_runtime(&self, proc: &Process) -> u64 {
+        let mut entity = proc.sched_entity().lock();
+        self.update_curr(proc.tid(), &mut entity);
+        let delta = entity.sum_exec_runtime - entity.consumed_exec_runtime;
+        entity.consumed_exec_runtime = entity.sum_exec_runtime;
+        delta
+    }
+
+    /// Updates the statistics of the process being switched out and enqueues
+    /// it again if it's still runnable.
     ///
-    /// The process is removed from the runqueue so you need to enqueue it by
-    /// [`Scheduler::enqueue`] again.
-    pub fn pick_next(&self) -> Option<PId> {
-        self.run_queue.lock().pop_front()
+    /// A preempted `SCHED_FIFO` process stays at the head of the queue for
+    /// its priority unless it has yielded the CPU.
+    pub fn put_prev(&self, proc: &Process, yielded: bool) {
+        if proc.is_idle() {
+            return;
+        }
+
+        let mut entity = proc.sched_entity().lock();
+        self.update_curr(proc.tid(), &mut entity);
+        entity.on_cpu = false;
+
+        if yielded && entity.policy == SchedPolicy::Normal {
+            // Move behind all other processes in the run queue.
+            let queue = self.run_queues[cpu_id()].lock();
+            if let Some((last_vruntime, _)) = queue.fair.keys().next_back() {
+                entity.vruntime = max(entity.vruntime, *last_vruntime + 1);
+            }
+        }
+
+        // The state is updated while holding the lock (see
+        // `Process::resume`): it's enqueued here or there.
+        if proc.state() == ProcessState::Runnable {
+            self.do_enqueue(proc.tid(), &mut entity, !yielded);
+        }
+    }
+
+    /// Removes the next process to run from the run queue and starts its time
+    /// slice. It's enqueued again by [`Scheduler::put_prev`].
+    pub fn pick_next(&self) -> Option<Arc<Process>> {
+        loop {
+            let (cpu, pid) = self.peek_next()?;
+            let proc = match PROCESSES.lock().get(&pid) {
+                Some(proc) => proc.clone(),
+                // It has exited in the meantime.
+                None => continue,
+            };
+
+            let mut entity = proc.sched_entity().lock();
+            if entity.queued_on != Some(cpu) {
+                // Another CPU has dequeued it in the meantime.
+                continue;
+            }
+
+            self.dequeue(pid, &mut entity);
+            entity.on_cpu = true;
+            entity.exec_start = read_monotonic_clock();
+            entity.prev_sum_exec_runtime = entity.sum_exec_runtime;
+            if entity.policy == SchedPolicy::Normal {
+                self.min_vruntime
+                    .fetch_max(entity.vruntime, Ordering::Relaxed);
+            }
+
+            drop(entity);
+            return Some(proc);
+        }
+    }
+
+    /// Returns the CPU and the process at the head of its run queue.
+    fn peek_next(&self) -> Option<(usize, PId)> {
+        let current = cpu_id();
+        if let Some(pid) = self.run_queues[current].lock().peek_next() {
+            return Some((current, pid));
+        }
+
+        // The run queue is empty. Steal one from the most loaded CPU.
+        let (busiest, _) = self
+            .queue_lens
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, len)| len.load(Ordering::Relaxed))?;
+        let pid = self.run_queues[busiest].lock().peek_next()?;
+        Some((busiest, pid))
+    }
+
+    /// Returns `true` if the current process on the current CPU should yield
+    /// the CPU to another one.
+    pub fn should_preempt(&self, current: &Process) -> bool {
+        if current.is_idle() {
+            return self
+                .queue_lens
+                .iter()
+                .any(|len| len.load(Ordering::Relaxed) > 0);
+        }
+
+        let mut entity = current.sched_entity().lock();
+        self.update_curr(current.tid(), &mut entity);
+        let queue = self.run_queues[cpu_id()].lock();
+        match entity.policy {
+            SchedPolicy::Fifo(prio) => matches!(queue.rt.front(), Some((p, _)) if *p > prio),
+            SchedPolicy::Normal if !queue.rt.is_empty() => true,
+            SchedPolicy::Normal if queue.fair.is_empty() => false,
+            SchedPolicy::Normal => {
+                // The time slice is proportional to the weight.
+                let weight = entity.weight();
+                let total_weight = weight + queue.fair.values().sum::<u64>();
+                let slice = max(SCHED_LATENCY * weight / total_weight, SCHED_MIN_GRANULARITY);
+                entity.sum_exec_runtime - entity.prev_sum_exec_runtime >= slice
+            }
+        }
+    }
+
+    /// Updates the scheduling parameters of the process.
+    pub fn set_params(&self, proc: &Process, policy: SchedPolicy, nice: i32) {
+        debug_assert!((NICE_MIN..=NICE_MAX).contains(&nice));
+
+        self.update_entity(proc, |entity| {
+            entity.policy = policy;
+            entity.nice = nice;
+        });
+    }
+
+    /// Moves the process into another control group.
+    pub fn set_group(&self, proc: &Process, group: Option<Arc<SchedGroup>>) {
+        self.update_entity(proc, |entity| {
+            let runnable = entity.runnable;
+            entity.set_runnable(false);
+            entity.group = group;
+            entity.set_runnable(runnable);
+        });
+    }
+
+    /// Updates the scheduling entity of the process. It's enqueued again since
+    /// the run queue holds its weight.
+    fn update_entity<F: FnOnce(&mut SchedEntity)>(&self, proc: &Process, f: F) {
+        let pid = proc.tid();
+        let mut entity = proc.sched_entity().lock();
+        let queued = entity.queued_on.is_some();
+        self.dequeue(pid, &mut entity);
+        f(&mut entity);
+        if queued {
+            self.do_enqueue(pid, &mut entity, false);
+        }
     }
 
-    /// Removes the process from the runqueue.
-    pub fn remove(&self, pid: PId) {
-        self.run_queue.lock().retain(|p| *p != pid);
+    /// Removes the process from the run queue. `entity` is the locked
+    /// scheduling entity of the process (see [`Scheduler::enqueue`]).
+    pub fn remove(&self, proc: &Process, entity: &mut SchedEntity) {
+        entity.set_runnable(false);
+        self.dequeue(proc.tid(), entity);
     }
 }
@@ -0,0 +1,505 @@
+//! Secure computing (`seccomp(2)`): system call filtering.
+//!
+//! In the strict mode, only `read(2)`, `write(2)`, `exit(2)`, and
+//! `rt_sigreturn(2)` are allowed and other system calls kill the process. In
+//! the filter mode, classic BPF programs decide what to do on each system
+//! call. Both are inherited by children and kept across `execve(2)`.
+use core::{cmp::min, mem::size_of};
+
+use kerla_runtime::{address::UserVAddr, arch::PtRegs};
+
+use crate::{
+    arch,
+    ctypes::c_int,
+    prelude::*,
+    process::{
+        current_process,
+        ptrace::PTRACE_EVENT_SECCOMP,
+        signal::{SigInfo, SIGKILL, SIGSYS},
+        Process,
+    },
+    syscalls::syscall_name_by_number,
+};
+
+pub const SECCOMP_MODE_DISABLED: c_int = 0;
+pub const SECCOMP_MODE_STRICT: c_int = 1;
+pub const SECCOMP_MODE_FILTER: c_int = 2;
+
+pub const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+pub const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+pub const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+pub const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+pub const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+/// The largest error number returned by `SECCOMP_RET_ERRNO`.
+const MAX_ERRNO: u32 = 4095;
+
+/// The maximum number of instructions in a filter (`BPF_MAXINSNS`).
+const BPF_MAX_INSNS: usize = 4096;
+/// The number of words in the scratch memory (`BPF_MEMWORDS`).
+const BPF_MEM_WORDS: usize = 16;
+/// The size of `struct seccomp_data`.
+const SECCOMP_DATA_SIZE: u32 = 64;
+
+// Instruction classes.
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Load sizes and modes.
+const BPF_W: u16 = 0x00;
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+
+// ALU operations.
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// Jump operations.
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Operand sources.
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations.
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+/// A classic BPF instruction (`struct sock_filter`).
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+/// `struct sock_fprog`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct SockFprog {
+    len: u16,
+    filter: usize,
+}
+
+/// The input of seccomp filters (`struct seccomp_data`).
+pub struct SeccompData {
+    pub nr: i32,
+    pub arch: u32,
+    pub instruction_pointer: u64,
+    pub args: [u64; 6],
+}
+
+impl SeccompData {
+    /// Loads a 32-bit word at `offset` as if it's in the C structure.
+    fn load_word(&self, offset: u32) -> u32 {
+        match offset {
+            0 => self.nr as u32,
+            4 => self.arch,
+            8 => self.instruction_pointer as u32,
+            12 => (self.instruction_pointer >> 32) as u32,
+            _ => {
+                let arg = self.args[(offset as usize - 16) / 8];
+                if offset % 8 == 0 {
+                    arg as u32
+                } else {
+                    (arg >> 32) as u32
+                }
+            }
+        }
+    }
+}
+
+/// A seccomp filter program.
+pub struct SeccompFilter {
+    prog: Vec<SockFilter>,
+    /// The filter installed before this one. It's evaluated as well.
+    prev: Option<Arc<SeccompFilter>>,
+}
+
+impl SeccompFilter {
+    /// Reads a filter program (`struct sock_fprog`) from the user memory.
+    /// Returns `EINVAL` if it contains instructions not allowed in seccomp.
+    pub fn from_user(
+        uaddr: UserVAddr,
+        prev: Option<Arc<SeccompFilter>>,
+    ) -> Result<Arc<SeccompFilter>> {
+        let fprog = uaddr.read::<SockFprog>()?;
+        let len = fprog.len as usize;
+        if len == 0 || len > BPF_MAX_INSNS {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let filter = UserVAddr::new_nonnull(fprog.filter)?;
+        let mut prog = Vec::with_capacity(len);
+        for i in 0..len {
+            prog.push(
+                filter
+                    .add(i * size_of::<SockFilter>())
+                    .read::<SockFilter>()?,
+            );
+        }
+
+        check_filter(&prog)?;
+        Ok(Arc::new(SeccompFilter { prog, prev }))
+    }
+
+    /// Runs the program and returns the action with its data.
+    fn run(&self, data: &SeccompData) -> u32 {
+        let mut a: u32 = 0;
+        let mut x: u32 = 0;
+        let mut mem = [0u32; BPF_MEM_WORDS];
+        let mut pc = 0;
+        loop {
+            let insn = &self.prog[pc];
+            pc += 1;
+            let code = insn.code;
+            let k = insn.k;
+            match code & 0x07 {
+                BPF_LD => {
+                    a = match code & 0xe0 {
+                        BPF_ABS => data.load_word(k),
+                        BPF_LEN => SECCOMP_DATA_SIZE,
+                        BPF_MEM => mem[k as usize],
+                        _ => k,
+                    }
+                }
+                BPF_LDX => {
+                    x = match code & 0xe0 {
+                        BPF_LEN => SECCOMP_DATA_SIZE,
+                        BPF_MEM => mem[k as usize],
+                        _ => k,
+                    }
+                }
+                BPF_ST => mem[k as usize] = a,
+                BPF_STX => mem[k as usize] = x,
+                BPF_ALU => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    a = match code & 0xf0 {
+                        BPF_ADD => a.wrapping_add(operand),
+                        BPF_SUB => a.wrapping_sub(operand),
+                        BPF_MUL => a.wrapping_mul(operand),
+                        // Division by zero (only possible with X) terminates
+                        // the program with 0, i.e. SECCOMP_RET_KILL_THREAD.
+                        BPF_DIV if operand == 0 => return 0,
+                        BPF_DIV => a / operand,
+                        BPF_MOD if operand == 0 => return 0,
+                        BPF_MOD => a % operand,
+                        BPF_OR => a | operand,
+                        BPF_AND => a & operand,
+                        BPF_XOR => a ^ operand,
+                        BPF_LSH => a.checked_shl(operand).unwrap_or(0),
+                        BPF_RSH => a.checked_shr(operand).unwrap_or(0),
+                        _ => a.wrapping_neg(),
+                    }
+                }
+                BPF_JMP => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    let taken = match code & 0xf0 {
+                        BPF_JA => {
+                            pc += k as usize;
+                            continue;
+                        }
+                        BPF_JEQ => a == operand,
+                        BPF_JGT => a > operand,
+                        BPF_JGE => a >= operand,
+                        _ => a & operand != 0,
+                    };
+                    pc += if taken { insn.jt } else { insn.jf } as usize;
+                }
+                BPF_RET => {
+                    return if code & 0x18 == BPF_A { a } else { k };
+                }
+                _ => {
+                    if code & 0xf8 == BPF_TXA {
+                        a = x;
+                    } else {
+                        x = a;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks that the program only contains instructions allowed in seccomp and
+/// never jumps out of the program. Loads from the input must be aligned
+/// 32-bit words.
+fn check_filter(prog: &[SockFilter]) -> Result<()> {
+    let len = prog.len();
+    for (pc, insn) in prog.iter().enumerate() {
+        let k = insn.k;
+        let valid = match insn.code {
+            code if code == BPF_LD | BPF_W | BPF_ABS => k < SECCOMP_DATA_SIZE && k % 4 == 0,
+            code if code == BPF_LD | BPF_W | BPF_LEN || code == BPF_LDX | BPF_W | BPF_LEN => true,
+            code if code == BPF_LD | BPF_IMM || code == BPF_LDX | BPF_IMM => true,
+            code if code == BPF_LD | BPF_MEM
+                || code == BPF_LDX | BPF_MEM
+                || code == BPF_ST
+                || code == BPF_STX =>
+            {
+                (k as usize) < BPF_MEM_WORDS
+            }
+            code if code == BPF_ALU | BPF_NEG => true,
+            code if code & 0x07 == BPF_ALU && code & !0xf8 == BPF_ALU => {
+                let op = code & 0xf0;
+                let known = matches!(
+                    op,
+                    BPF_ADD
+                        | BPF_SUB
+                        | BPF_MUL
+                        | BPF_DIV
+                        | BPF_OR
+                        | BPF_AND
+                        | BPF_LSH
+                        | BPF_RSH
+                        | BPF_MOD
+                        | BPF_XOR
+                );
+                let divides_by_zero =
+                    matches!(op, BPF_DIV | BPF_MOD) && code & BPF_X == 0 && k == 0;
+                known && !divides_by_zero
+            }
+            code if code == BPF_JMP | BPF_JA => (k as usize) < len - pc - 1,
+            code if code & 0x07 == BPF_JMP && code & !0xf8 == BPF_JMP => {
+                matches!(code & 0xf0, BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET)
+                    && (insn.jt as usize) < len - pc - 1
+                    && (insn.jf as usize) < len - pc - 1
+            }
+            code if code == BPF_RET | BPF_K || code == BPF_RET | BPF_A => true,
+            code if code == BPF_MISC | BPF_TAX || code == BPF_MISC | BPF_TXA => true,
+            _ => false,
+        };
+
+        if !valid {
+            debug_warn!("seccomp: invalid instruction at {}: {:x?}", pc, insn);
+            return Err(Errno::EINVAL.into());
+        }
+    }
+
+    // The program must end with a return.
+    match prog.last() {
+        Some(insn) if insn.code & 0x07 == BPF_RET => Ok(()),
+        _ => Err(Errno::EINVAL.into()),
+    }
+}
+
+/// The seccomp mode of a thread.
+#[derive(Clone)]
+pub enum SeccompMode {
+    Disabled,
+    Strict,
+    /// The most recently installed filter. Older ones are linked from it.
+    Filter(Arc<SeccompFilter>),
+}
+
+impl SeccompMode {
+    /// The mode number returned by `prctl(PR_GET_SECCOMP)`.
+    pub fn as_c_int(&self) -> c_int {
+        match self {
+            SeccompMode::Disabled => SECCOMP_MODE_DISABLED,
+            SeccompMode::Strict => SECCOMP_MODE_STRICT,
+            SeccompMode::Filter(_) => SECCOMP_MODE_FILTER,
+        }
+    }
+
+    /// Returns `true` if `filter` is built on top of this mode, i.e. this
+    /// mode is disabled or its filter is `filter` or one of its predecessors.
+    pub fn is_ancestor_of(&self, filter: Option<&Arc<SeccompFilter>>) -> bool {
+        match self {
+            SeccompMode::Disabled => true,
+            SeccompMode::Strict => false,
+            SeccompMode::Filter(ancestor) => {
+                let mut next = filter;
+                while let Some(filter) = next {
+                    if Arc::ptr_eq(filter, ancestor) {
+                        return true;
+                    }
+                    next = filter.prev.as_ref();
+                }
+                false
+            }
+        }
+    }
+
+    /// Evaluates the system call and returns the action with its data.
+    fn evaluate(&self, data: &SeccompData) -> u32 {
+        match self {
+            SeccompMode::Disabled => SECCOMP_RET_ALLOW,
+            SeccompMode::Strict => {
+                if is_allowed_in_strict_mode(data.nr as usize) {
+                    SECCOMP_RET_ALLOW
+                } else {
+                    SECCOMP_RET_KILL_PROCESS
+                }
+            }
+            SeccompMode::Filter(filter) => {
+                // All filters are evaluated and the action with the highest
+                // precedence (the lowest value as signed) wins.
+                let mut ret = SECCOMP_RET_ALLOW;
+                let mut next = Some(filter);
+                while let Some(filter) = next {
+                    let action = filter.run(data);
+                    if ((action & SECCOMP_RET_ACTION_FULL) as i32)
+                        < ((ret & SECCOMP_RET_ACTION_FULL) as i32)
+                    {
+                        ret = action;
+                    }
+                    next = filter.prev.as_ref();
+                }
+                ret
+            }
+        }
+    }
+}
+
+fn is_allowed_in_strict_mode(n: usize) -> bool {
+    // read(2), write(2), rt_sigreturn(2), and exit(2).
+    matches!(n, 0 | 1 | 15 | 60)
+}
+
+/// Returns `true` if the action can be returned from filters, i.e.
+/// `SECCOMP_GET_ACTION_AVAIL`.
+pub fn is_action_available(action: u32) -> bool {
+    matches!(
+        action,
+        SECCOMP_RET_KILL_PROCESS
+            | SECCOMP_RET_KILL_THREAD
+            | SECCOMP_RET_TRAP
+            | SECCOMP_RET_ERRNO
+            | SECCOMP_RET_TRACE
+            | SECCOMP_RET_LOG
+            | SECCOMP_RET_ALLOW
+    )
+}
+
+/// The result of checking a system call against seccomp filters.
+enum Verdict {
+    Allow,
+    /// Skips the system call with the return value.
+    Skip(isize),
+    /// Lets the tracer handle the system call (`SECCOMP_RET_TRACE`) with the
+    /// data returned by `PTRACE_GETEVENTMSG`.
+    Trace(u32),
+}
+
+/// Checks the system call against the seccomp mode of the **current** thread.
+/// Returns `Some` with the return value if the system call must be skipped.
+/// It may not return if the thread or the process is killed.
+///
+/// On `SECCOMP_RET_TRACE`, the tracer may replace the system call and its
+/// arguments in `n` and `args`, or skip it by setting the number to -1.
+pub fn filter_syscall(frame: &mut PtRegs, n: &mut usize, args: &mut [usize; 6]) -> Option<isize> {
+    let current = current_process();
+    if !current.is_seccomp_enabled() {
+        return None;
+    }
+
+    let mode = current.seccomp();
+
+    let action_data = match check_syscall(&mode, frame, *n, args, false) {
+        Verdict::Allow => return None,
+        Verdict::Skip(value) => return Some(value),
+        Verdict::Trace(action_data) => action_data,
+    };
+
+    // The tracer sees ENOSYS as the return value until the system call is
+    // executed.
+    arch::set_syscall_return_value(frame, -(Errno::ENOSYS as isize));
+    if !Process::ptrace_event(frame, PTRACE_EVENT_SECCOMP, action_data as u64) {
+        // No tracer handles the system call.
+        return Some(-(Errno::ENOSYS as isize));
+    }
+
+    let (new_n, new_args) = arch::syscall_number_and_args(frame);
+    if new_n == usize::MAX {
+        // Skipped by the tracer. It has set the return value.
+        return Some(frame.rax as isize);
+    }
+
+    // Like Linux, the modified system call is checked again. Another
+    // SECCOMP_RET_TRACE allows it.
+    *n = new_n;
+    *args = new_args;
+    match check_syscall(&mode, frame, *n, args, true) {
+        Verdict::Allow | Verdict::Trace(_) => None,
+        Verdict::Skip(value) => Some(value),
+    }
+}
+
+/// Evaluates the system call and performs the action other than
+/// `SECCOMP_RET_TRACE`, which is allowed if `recheck` is set.
+fn check_syscall(
+    mode: &SeccompMode,
+    frame: &PtRegs,
+    n: usize,
+    args: &[usize; 6],
+    recheck: bool,
+) -> Verdict {
+    let current = current_process();
+    let ip = arch::syscall_instruction_pointer(frame);
+    let data = SeccompData {
+        nr: n as i32,
+        arch: arch::AUDIT_ARCH,
+        instruction_pointer: ip as u64,
+        args: args.map(|arg| arg as u64),
+    };
+
+    let ret = mode.evaluate(&data);
+    let action_data = ret & SECCOMP_RET_DATA;
+    match ret & SECCOMP_RET_ACTION_FULL {
+        SECCOMP_RET_ALLOW => Verdict::Allow,
+        SECCOMP_RET_LOG => {
+            info!(
+                "seccomp: pid={} ({}): syscall {} ({})",
+                current.pid().as_i32(),
+                current.cmdline().argv0(),
+                n,
+                syscall_name_by_number(n)
+            );
+            Verdict::Allow
+        }
+        SECCOMP_RET_ERRNO => Verdict::Skip(-(min(action_data, MAX_ERRNO) as isize)),
+        SECCOMP_RET_TRAP => {
+            current.force_signal(SigInfo::seccomp(
+                ip,
+                n as c_int,
+                arch::AUDIT_ARCH,
+                action_data as c_int,
+            ));
+            Verdict::Skip(-(Errno::ENOSYS as isize))
+        }
+        SECCOMP_RET_TRACE if recheck => Verdict::Allow,
+        SECCOMP_RET_TRACE => Verdict::Trace(action_data),
+        SECCOMP_RET_KILL_THREAD => Process::exit_thread_by_signal(SIGSYS),
+        _ if matches!(mode, SeccompMode::Strict) => Process::exit_by_signal(SIGKILL),
+        _ => Process::exit_by_signal(SIGSYS),
+    }
+}
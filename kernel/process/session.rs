@@ -0,0 +1,49 @@
+use crate::{prelude::*, tty::line_discipline::LineDiscipline};
+use kerla_runtime::spinlock::SpinLock;
+
+/// A session ID. It's the process ID of the session leader.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SessionId(i32);
+
+impl SessionId {
+    pub const fn new(sid: i32) -> SessionId {
+        SessionId(sid)
+    }
+
+    pub const fn as_i32(self) -> i32 {
+        self.0
+    }
+}
+
+/// A session, i.e. a collection of process groups sharing a controlling
+/// terminal.
+pub struct Session {
+    sid: SessionId,
+    /// The controlling terminal.
+    controlling_terminal: Option<Arc<LineDiscipline>>,
+}
+
+impl Session {
+    /// Creates a new session without a controlling terminal.
+    pub fn new(sid: SessionId) -> Arc<SpinLock<Session>> {
+        Arc::new(SpinLock::new(Session {
+            sid,
+            controlling_terminal: None,
+        }))
+    }
+
+    /// The session ID.
+    pub fn sid(&self) -> SessionId {
+        self.sid
+    }
+
+    /// The controlling terminal. `None` if it's not associated yet.
+    pub fn controlling_terminal(&self) -> Option<Arc<LineDiscipline>> {
+        self.controlling_terminal.clone()
+    }
+
+    /// Associates (or disassociates if `None`) the controlling terminal.
+    pub fn set_controlling_terminal(&mut self, terminal: Option<Arc<LineDiscipline>>) {
+        self.controlling_terminal = terminal;
+    }
+}
@@ -1,5 +1,12 @@
-use crate::{ctypes::c_int, prelude::*};
-use bitvec::prelude::*;
+use crate::{
+    ctypes::{c_int, c_uint},
+    fs::stat::UId,
+    prelude::*,
+    process::PId,
+};
+use alloc::collections::VecDeque;
+use bitflags::bitflags;
+use core::ops::{BitAnd, BitOr, Not};
 use kerla_runtime::address::UserVAddr;
 
 pub type Signal = c_int;
@@ -66,64 +73,374 @@ pub const SIGPWR: Signal = 30;
 #[allow(unused)]
 pub const SIGSYS: Signal = 31;
 
-const SIGMAX: c_int = 32;
+/// The lowest realtime signal.
+pub const SIGRTMIN: Signal = 32;
+/// The highest signal number (the highest realtime signal).
+pub const SIGMAX: Signal = 64;
 
 pub const SIG_DFL: usize = 0;
 pub const SIG_IGN: usize = 1;
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum SigAction {
-    Ignore,
+/// Sent by `kill(2)`.
+pub const SI_USER: c_int = 0;
+/// Sent by the kernel.
+pub const SI_KERNEL: c_int = 0x80;
+/// Sent by a POSIX timer.
+pub const SI_TIMER: c_int = -2;
+/// `SIGSEGV`: the address is not mapped.
+pub const SEGV_MAPERR: c_int = 1;
+/// `SIGSEGV`: the access is not permitted.
+pub const SEGV_ACCERR: c_int = 2;
+/// `SIGBUS`: the object mapped at the address can't be accessed.
+pub const BUS_ADRERR: c_int = 2;
+/// `SIGCHLD`: the child has exited.
+pub const CLD_EXITED: c_int = 1;
+/// `SIGCHLD`: the child has been killed by a signal.
+pub const CLD_KILLED: c_int = 2;
+/// `SIGCHLD`: the child has been killed by a signal and dumped the core.
+pub const CLD_DUMPED: c_int = 3;
+/// `SIGCHLD`: the traced child has stopped.
+pub const CLD_TRAPPED: c_int = 4;
+/// `SIGCHLD`: the child has been stopped.
+pub const CLD_STOPPED: c_int = 5;
+/// `SIGCHLD`: the stopped child has been continued.
+pub const CLD_CONTINUED: c_int = 6;
+/// `SIGSYS`: the system call has been trapped by seccomp.
+pub const SYS_SECCOMP: c_int = 1;
+
+/// The size of `siginfo_t`.
+pub const SIGINFO_SIZE: usize = 128;
+/// The maximum number of queued signals per process.
+const MAX_QUEUED_SIGNALS: usize = 1024;
+
+bitflags! {
+    pub struct SigActionFlags: u64 {
+        const SA_NOCLDSTOP = 0x0000_0001;
+        const SA_NOCLDWAIT = 0x0000_0002;
+        const SA_SIGINFO = 0x0000_0004;
+        const SA_RESTORER = 0x0400_0000;
+        const SA_ONSTACK = 0x0800_0000;
+        const SA_RESTART = 0x1000_0000;
+        const SA_NODEFER = 0x4000_0000;
+        const SA_RESETHAND = 0x8000_0000;
+    }
+}
+
+/// A set of signals (`sigset_t`). The bit `n - 1` corresponds to the signal
+/// `n`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct SigSet(u64);
+
+impl SigSet {
+    pub const EMPTY: SigSet = SigSet(0);
+
+    pub const fn from_raw(raw: u64) -> SigSet {
+        SigSet(raw)
+    }
+
+    pub fn as_raw(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, signal: Signal) -> bool {
+        (1..=SIGMAX).contains(&signal) && self.0 & (1 << (signal - 1)) != 0
+    }
+
+    pub fn add(&mut self, signal: Signal) {
+        debug_assert!((1..=SIGMAX).contains(&signal));
+        self.0 |= 1 << (signal - 1);
+    }
+
+    pub fn remove(&mut self, signal: Signal) {
+        debug_assert!((1..=SIGMAX).contains(&signal));
+        self.0 &= !(1 << (signal - 1));
+    }
+
+    /// Returns the lowest signal in the set.
+    pub fn first(self) -> Option<Signal> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as Signal + 1)
+        }
+    }
+
+    /// Removes signals which cannot be blocked: `SIGKILL` and `SIGSTOP`.
+    pub fn without_unblockable(mut self) -> SigSet {
+        self.remove(SIGKILL);
+        self.remove(SIGSTOP);
+        self
+    }
+}
+
+impl BitOr for SigSet {
+    type Output = SigSet;
+    fn bitor(self, rhs: SigSet) -> SigSet {
+        SigSet(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for SigSet {
+    type Output = SigSet;
+    fn bitand(self, rhs: SigSet) -> SigSet {
+        SigSet(self.0 & rhs.0)
+    }
+}
+
+impl Not for SigSet {
+    type Output = SigSet;
+    fn not(self) -> SigSet {
+        SigSet(!self.0)
+    }
+}
+
+/// What to do when the signal is delivered and no handler is registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
     Terminate,
-    Handler { handler: UserVAddr },
-}
-
-// TODO: Fill correct default actions
-pub const DEFAULT_ACTIONS: [SigAction; SIGMAX as usize] = [
-    /* (unused) */ SigAction::Ignore,
-    /* SIGHUP */ SigAction::Ignore,
-    /* SIGINT */ SigAction::Terminate,
-    /* SIGQUIT */ SigAction::Ignore,
-    /* SIGILL */ SigAction::Ignore,
-    /* SIGTRAP */ SigAction::Ignore,
-    /* SIGABRT */ SigAction::Ignore,
-    /* SIGBUS */ SigAction::Ignore,
-    /* SIGFPE */ SigAction::Ignore,
-    /* SIGKILL */ SigAction::Ignore,
-    /* SIGUSR1 */ SigAction::Ignore,
-    /* SIGSEGV */ SigAction::Ignore,
-    /* SIGUSR2 */ SigAction::Ignore,
-    /* SIGPIPE */ SigAction::Ignore,
-    /* SIGALRM */ SigAction::Ignore,
-    /* SIGTERM */ SigAction::Ignore,
-    /* SIGSTKFLT */ SigAction::Ignore,
-    /* SIGCHLD */ SigAction::Ignore,
-    /* SIGCONT */ SigAction::Ignore,
-    /* SIGSTOP */ SigAction::Ignore,
-    /* SIGTSTP */ SigAction::Ignore,
-    /* SIGTTIN */ SigAction::Ignore,
-    /* SIGTTOU */ SigAction::Ignore,
-    /* SIGURG */ SigAction::Ignore,
-    /* SIGXCPU */ SigAction::Ignore,
-    /* SIGXFSZ */ SigAction::Ignore,
-    /* SIGVTALRM */ SigAction::Ignore,
-    /* SIGPROF */ SigAction::Ignore,
-    /* SIGWINCH */ SigAction::Ignore,
-    /* SIGIO */ SigAction::Ignore,
-    /* SIGPWR */ SigAction::Ignore,
-    /* SIGSYS */ SigAction::Ignore,
-];
+    /// Terminate the process with a core dump if `RLIMIT_CORE` allows it.
+    Core,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+/// Returns the default action of the signal.
+pub fn default_action(signal: Signal) -> DefaultAction {
+    match signal {
+        SIGCHLD | SIGURG | SIGWINCH => DefaultAction::Ignore,
+        SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => DefaultAction::Stop,
+        SIGCONT => DefaultAction::Continue,
+        SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV | SIGXCPU | SIGXFSZ
+        | SIGSYS => DefaultAction::Core,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// Signals which stop the process by default.
+pub const STOP_SIGNALS: SigSet = SigSet::from_raw(
+    1 << (SIGSTOP - 1) | 1 << (SIGTSTP - 1) | 1 << (SIGTTIN - 1) | 1 << (SIGTTOU - 1),
+);
+
+/// The signal disposition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigHandler {
+    /// `SIG_DFL`
+    Default,
+    /// `SIG_IGN`
+    Ignore,
+    /// The user's signal handler.
+    Handler(UserVAddr),
+}
+
+/// `struct sigaction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigAction {
+    pub handler: SigHandler,
+    pub flags: SigActionFlags,
+    /// Signals to be blocked during the handler.
+    pub mask: SigSet,
+    /// The function to return from the handler (calls `rt_sigreturn(2)`).
+    pub restorer: Option<UserVAddr>,
+}
+
+impl SigAction {
+    pub const DEFAULT: SigAction = SigAction {
+        handler: SigHandler::Default,
+        flags: SigActionFlags::empty(),
+        mask: SigSet::EMPTY,
+        restorer: None,
+    };
+
+    /// Returns `true` if the signal is discarded on delivery.
+    pub fn is_ignored(&self, signal: Signal) -> bool {
+        match self.handler {
+            SigHandler::Ignore => true,
+            SigHandler::Default => default_action(signal) == DefaultAction::Ignore,
+            SigHandler::Handler(_) => false,
+        }
+    }
+}
+
+/// The signal-specific fields in `siginfo_t`.
+#[derive(Debug, Clone, Copy)]
+pub enum SigInfoFields {
+    /// Sent by a process or the kernel.
+    Sender { pid: PId, uid: c_uint, value: usize },
+    /// Caused by a hardware fault.
+    Fault { addr: usize },
+    /// Sent by a POSIX timer.
+    Timer {
+        id: c_int,
+        overrun: c_int,
+        value: usize,
+    },
+    /// A child process has changed its state.
+    Child {
+        pid: PId,
+        uid: c_uint,
+        status: c_int,
+    },
+    /// A system call has been trapped by seccomp.
+    Sys {
+        call_addr: usize,
+        syscall: c_int,
+        arch: c_uint,
+        /// `si_errno`: the data returned from the filter.
+        errno: c_int,
+    },
+}
 
+/// The information of a signal (`siginfo_t`).
+#[derive(Debug, Clone, Copy)]
+pub struct SigInfo {
+    pub signal: Signal,
+    /// `si_code`: why the signal has been sent.
+    pub code: c_int,
+    pub fields: SigInfoFields,
+}
+
+impl SigInfo {
+    /// A signal sent by the kernel.
+    pub fn kernel(signal: Signal) -> SigInfo {
+        SigInfo {
+            signal,
+            code: SI_KERNEL,
+            fields: SigInfoFields::Sender {
+                pid: PId::new(0),
+                uid: 0,
+                value: 0,
+            },
+        }
+    }
+
+    /// A signal sent by `kill(2)` from `pid` whose real user ID is `uid`.
+    pub fn user(signal: Signal, pid: PId, uid: UId) -> SigInfo {
+        SigInfo {
+            signal,
+            code: SI_USER,
+            fields: SigInfoFields::Sender {
+                pid,
+                uid: uid.as_u32(),
+                value: 0,
+            },
+        }
+    }
+
+    /// `SIGCHLD` sent to the parent of `pid` whose real user ID is `uid`.
+    pub fn child(pid: PId, uid: UId, code: c_int, status: c_int) -> SigInfo {
+        SigInfo {
+            signal: SIGCHLD,
+            code,
+            fields: SigInfoFields::Child {
+                pid,
+                uid: uid.as_u32(),
+                status,
+            },
+        }
+    }
+
+    /// A signal caused by an invalid memory access.
+    pub fn fault(signal: Signal, code: c_int, addr: usize) -> SigInfo {
+        SigInfo {
+            signal,
+            code,
+            fields: SigInfoFields::Fault { addr },
+        }
+    }
+
+    /// `SIGSYS` sent by a seccomp filter on the system call at `call_addr`.
+    pub fn seccomp(call_addr: usize, syscall: c_int, arch: c_uint, errno: c_int) -> SigInfo {
+        SigInfo {
+            signal: SIGSYS,
+            code: SYS_SECCOMP,
+            fields: SigInfoFields::Sys {
+                call_addr,
+                syscall,
+                arch,
+                errno,
+            },
+        }
+    }
+
+    /// Returns the user-visible `siginfo_t`.
+    pub fn to_bytes(self) -> [u8; SIGINFO_SIZE] {
+        fn put(buf: &mut [u8], offset: usize, bytes: &[u8]) {
+            buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        let mut buf = [0; SIGINFO_SIZE];
+        put(&mut buf, 0, &self.signal.to_ne_bytes());
+        put(&mut buf, 8, &self.code.to_ne_bytes());
+        match self.fields {
+            SigInfoFields::Sender { pid, uid, value } => {
+                put(&mut buf, 16, &pid.as_i32().to_ne_bytes());
+                put(&mut buf, 20, &uid.to_ne_bytes());
+                put(&mut buf, 24, &value.to_ne_bytes());
+            }
+            SigInfoFields::Fault { addr } => {
+                put(&mut buf, 16, &addr.to_ne_bytes());
+            }
+            SigInfoFields::Timer { id, overrun, value } => {
+                put(&mut buf, 16, &id.to_ne_bytes());
+                put(&mut buf, 20, &overrun.to_ne_bytes());
+                put(&mut buf, 24, &value.to_ne_bytes());
+            }
+            SigInfoFields::Child { pid, uid, status } => {
+                put(&mut buf, 16, &pid.as_i32().to_ne_bytes());
+                put(&mut buf, 20, &uid.to_ne_bytes());
+                put(&mut buf, 24, &status.to_ne_bytes());
+            }
+            SigInfoFields::Sys {
+                call_addr,
+                syscall,
+                arch,
+                errno,
+            } => {
+                put(&mut buf, 4, &errno.to_ne_bytes());
+                put(&mut buf, 16, &call_addr.to_ne_bytes());
+                put(&mut buf, 24, &syscall.to_ne_bytes());
+                put(&mut buf, 28, &arch.to_ne_bytes());
+            }
+        }
+
+        buf
+    }
+}
+
+/// Signal dispositions and pending signals shared among threads.
 pub struct SignalDelivery {
-    pending: u32,
-    actions: [SigAction; SIGMAX as usize],
+    /// Pending signals.
+    pending: SigSet,
+    /// The information of pending signals in the arrival order. A standard
+    /// signal is queued at most once while realtime signals are queued as
+    /// many times as they're sent.
+    queue: VecDeque<SigInfo>,
+    /// Signal actions indexed by the signal number.
+    actions: [SigAction; SIGMAX as usize + 1],
 }
 
 impl SignalDelivery {
     pub fn new() -> SignalDelivery {
         SignalDelivery {
-            pending: 0,
-            actions: DEFAULT_ACTIONS,
+            pending: SigSet::EMPTY,
+            queue: VecDeque::new(),
+            actions: [SigAction::DEFAULT; SIGMAX as usize + 1],
+        }
+    }
+
+    /// Returns a copy for a child process: signal actions are inherited but
+    /// pending signals are not.
+    pub fn fork(&self) -> SignalDelivery {
+        SignalDelivery {
+            pending: SigSet::EMPTY,
+            queue: VecDeque::new(),
+            actions: self.actions,
         }
     }
 
@@ -132,37 +449,124 @@ impl SignalDelivery {
     }
 
     pub fn set_action(&mut self, signal: Signal, action: SigAction) -> Result<()> {
-        if signal > SIGMAX {
+        if !(1..=SIGMAX).contains(&signal) || signal == SIGKILL || signal == SIGSTOP {
             return Err(Errno::EINVAL.into());
         }
 
         self.actions[signal as usize] = action;
+        if action.is_ignored(signal) {
+            // Discard pending ones.
+            self.pending.remove(signal);
+            self.queue.retain(|info| info.signal != signal);
+        }
+
         Ok(())
     }
 
-    pub fn is_pending(&self) -> bool {
-        self.pending != 0
+    /// Resets signal handlers to the default on `execve(2)`. Ignored signals
+    /// stay ignored.
+    pub fn reset_handlers(&mut self) {
+        for action in self.actions.iter_mut() {
+            if matches!(action.handler, SigHandler::Handler(_)) {
+                *action = SigAction::DEFAULT;
+            }
+        }
+    }
+
+    /// Returns `true` if the signal would be discarded on delivery.
+    pub fn is_ignored(&self, signal: Signal) -> bool {
+        self.actions[signal as usize].is_ignored(signal)
     }
 
-    pub fn pop_pending(&mut self) -> Option<(Signal, SigAction)> {
-        if self.pending == 0 {
-            return None;
+    /// Pending signals.
+    pub fn pending(&self) -> SigSet {
+        self.pending
+    }
+
+    /// Dequeues the lowest pending signal which is not in `blocked`. `SIGKILL`
+    /// always comes first.
+    pub fn pop_pending(&mut self, blocked: SigSet) -> Option<(SigInfo, SigAction)> {
+        let unblocked = self.pending & !blocked;
+        let signal = if unblocked.contains(SIGKILL) {
+            SIGKILL
+        } else {
+            unblocked.first()?
+        };
+
+        let index = self
+            .queue
+            .iter()
+            .position(|info| info.signal == signal)
+            .unwrap();
+        let info = self.queue.remove(index).unwrap();
+        if !self.queue.iter().any(|info| info.signal == signal) {
+            self.pending.remove(signal);
         }
 
-        let signal = self.pending.trailing_zeros();
-        self.pending &= !(1 << signal);
-        Some((signal as Signal, self.actions[signal as usize]))
+        Some((info, self.actions[signal as usize]))
     }
 
-    pub fn signal(&mut self, signal: Signal) {
-        self.pending |= 1 << (signal);
+    /// Discards the pending signals in `set`.
+    pub fn discard(&mut self, set: SigSet) {
+        self.pending = self.pending & !set;
+        self.queue.retain(|info| !set.contains(info.signal));
+    }
+
+    /// Makes the signal pending. Returns `false` if it has been discarded.
+    pub fn signal(&mut self, info: SigInfo) -> bool {
+        let signal = info.signal;
+        if !(1..=SIGMAX).contains(&signal) {
+            return false;
+        }
+
+        if signal < SIGRTMIN && self.pending.contains(signal) {
+            // A standard signal is not queued: it's already pending.
+            return true;
+        }
+
+        if self.queue.len() >= MAX_QUEUED_SIGNALS {
+            return false;
+        }
+
+        self.pending.add(signal);
+        self.queue.push_back(info);
+        true
     }
 }
 
-//pub type SigSet = BitMap<128 /* 1024 / 8 */>;
-pub type SigSet = BitArray<[u8; 1024 / 8], LocalBits>;
 pub enum SignalMask {
     Block,
     Unblock,
     Set,
 }
+
+/// Disables the alternate signal stack.
+pub const SS_DISABLE: c_int = 2;
+/// The thread is running on the alternate signal stack.
+pub const SS_ONSTACK: c_int = 1;
+/// The minimum size of the alternate signal stack.
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// `stack_t`
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct StackT {
+    pub ss_sp: usize,
+    pub ss_flags: c_int,
+    pub ss_size: usize,
+}
+
+/// The alternate signal stack set by `sigaltstack(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigAltStack {
+    pub sp: UserVAddr,
+    pub size: usize,
+}
+
+impl SigAltStack {
+    /// Returns `true` if `sp` points to the stack.
+    pub fn contains(&self, sp: usize) -> bool {
+        let base = self.sp.value();
+        base < sp && sp <= base + self.size
+    }
+}
@@ -1,33 +1,49 @@
 use super::*;
-use crate::process::PId;
 use crate::{
     arch::{self},
-    process::process::PROCESSES,
+    timer,
 };
 
 use alloc::sync::Arc;
+use kerla_runtime::arch::SavedInterruptStatus;
 
+use core::hint::spin_loop;
 use core::mem::{self};
 
 /// Yields execution to another thread.
 pub fn switch() {
+    do_switch(false);
+}
+
+/// Yields the CPU to another runnable thread (`sched_yield(2)`): the current
+/// thread is moved to the end of the runqueue.
+pub fn yield_now() {
+    do_switch(true);
+}
+
+/// Switches to another thread if the current thread has used up its time
+/// slice or a higher priority thread is runnable.
+pub fn preempt_if_needed() {
+    let preempt = SCHEDULER.should_preempt(current_process());
+    if preempt {
+        switch();
+    }
+}
+
+fn do_switch(yielded: bool) {
     let prev = current_process().clone();
-    let prev_pid = prev.pid();
-    let prev_state = prev.state();
     let next = {
-        let scheduler = SCHEDULER.lock();
+        // Don't switch in interrupt handlers until the next thread is chosen.
+        let _saved_intr_status = SavedInterruptStatus::disable();
 
         // Push back the currently running thread to the runqueue if it's still
         // ready for running, in other words, it's not blocked.
-        if prev_pid != PId::new(0) && prev_state == ProcessState::Runnable {
-            scheduler.enqueue(prev_pid);
-        }
+        SCHEDULER.put_prev(&prev, yielded);
 
         // Pick a thread to run next.
-        match scheduler.pick_next() {
-            Some(next_pid) => PROCESSES.lock().get(&next_pid).unwrap().clone(),
-            None => IDLE_THREAD.get().get().clone(),
-        }
+        SCHEDULER
+            .pick_next()
+            .unwrap_or_else(|| IDLE_THREAD.get().get().clone())
     };
 
     if Arc::ptr_eq(&prev, &next) {
@@ -37,6 +53,17 @@ pub fn switch() {
 
     debug_assert!(next.state() == ProcessState::Runnable);
 
+    // The next thread might be still running on another CPU: it has been
+    // resumed before switching out. Wait for the CPU to save its context.
+    while !next.arch().try_set_running() {
+        spin_loop();
+    }
+
+    if prev.is_idle() && !next.is_idle() {
+        // Restart the periodic timer tick stopped in the idle thread.
+        timer::program_next_event(true);
+    }
+
     if let Some(vm) = next.vm().clone() {
         let lock = vm.lock();
         lock.page_table().switch();
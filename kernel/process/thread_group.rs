@@ -0,0 +1,339 @@
+use crate::{
+    ctypes::{c_clockid, c_int},
+    prelude::*,
+    timer::IntervalTimer,
+};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Add;
+use kerla_runtime::spinlock::SpinLock;
+
+use super::{
+    rlimit::ResourceLimits,
+    signal::{SigInfo, Signal, SIGALRM},
+    Process,
+};
+
+/// An interval timer which counts down the CPU time used by the process:
+/// `ITIMER_VIRTUAL` and `ITIMER_PROF`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CpuTimer {
+    /// The remaining CPU time in nanoseconds. Zero if the timer is disarmed.
+    pub remaining: u64,
+    /// The interval in nanoseconds. Zero if the timer is a one-shot.
+    pub interval: u64,
+}
+
+impl CpuTimer {
+    /// Charges the CPU time used by the process. Returns `true` if the timer
+    /// has expired.
+    pub fn charge(&mut self, cpu_time: u64) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        if cpu_time < self.remaining {
+            self.remaining -= cpu_time;
+            return false;
+        }
+
+        let overrun = cpu_time - self.remaining;
+        self.remaining = match self.interval {
+            0 => 0,
+            interval => interval - overrun % interval,
+        };
+        true
+    }
+}
+
+/// User and system CPU times in nanoseconds.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CpuTimes {
+    /// The time spent in the userspace.
+    pub utime: u64,
+    /// The time spent in the kernel on behalf of the process.
+    pub stime: u64,
+}
+
+impl CpuTimes {
+    /// The total CPU time.
+    pub fn total(&self) -> u64 {
+        self.utime + self.stime
+    }
+}
+
+impl Add for CpuTimes {
+    type Output = CpuTimes;
+
+    fn add(self, rhs: CpuTimes) -> CpuTimes {
+        CpuTimes {
+            utime: self.utime + rhs.utime,
+            stime: self.stime + rhs.stime,
+        }
+    }
+}
+
+/// A POSIX per-process timer created by `timer_create(2)`.
+#[derive(Clone)]
+pub struct PosixTimer {
+    pub clock: c_clockid,
+    pub timer: Arc<IntervalTimer>,
+}
+
+/// A change of the job control state not yet reported to the parent by
+/// `wait4(2)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JobControlEvent {
+    /// Stopped by the signal.
+    Stopped(Signal),
+    /// Continued by `SIGCONT`.
+    Continued,
+}
+
+/// A thread group, i.e. threads in a userspace process. Threads in the same
+/// group share the process ID.
+pub struct ThreadGroup {
+    /// The first thread in the group. Its thread ID is the process ID.
+    leader: Weak<Process>,
+    /// Threads which have not yet exited.
+    threads: Vec<Weak<Process>>,
+    /// The wait status set by `exit_group(2)` or a fatal signal: other threads
+    /// in the group exit with it.
+    exit_status: Option<c_int>,
+    /// The thread calling `execve(2)`. Other threads in the group exit.
+    exec_thread: Option<Weak<Process>>,
+    /// The signal sent to the parent on exit. `SIGCHLD` unless specified in
+    /// `clone(2)`.
+    exit_signal: Option<Signal>,
+    /// The signal which has stopped the group. `None` if it's not stopped.
+    stop_signal: Option<Signal>,
+    /// The latest job control event not yet reported to the parent.
+    job_control_event: Option<JobControlEvent>,
+    /// `ITIMER_REAL`. Created on demand.
+    real_timer: Option<Arc<IntervalTimer>>,
+    /// `ITIMER_VIRTUAL`.
+    virtual_timer: CpuTimer,
+    /// `ITIMER_PROF`.
+    prof_timer: CpuTimer,
+    /// POSIX timers indexed by their IDs.
+    posix_timers: BTreeMap<c_int, PosixTimer>,
+    next_posix_timer_id: c_int,
+    /// Resource limits shared by threads in the group.
+    rlimits: ResourceLimits,
+    /// CPU times used by exited threads.
+    exited_cpu_times: CpuTimes,
+    /// CPU times used by reaped children and their descendants.
+    children_cpu_times: CpuTimes,
+}
+
+impl ThreadGroup {
+    /// Creates a new thread group with the resource limits.
+    pub fn new(rlimits: ResourceLimits, exit_signal: Option<Signal>) -> Arc<SpinLock<ThreadGroup>> {
+        Arc::new(SpinLock::new(ThreadGroup {
+            leader: Weak::new(),
+            threads: Vec::new(),
+            exit_status: None,
+            exec_thread: None,
+            exit_signal,
+            stop_signal: None,
+            job_control_event: None,
+            real_timer: None,
+            virtual_timer: CpuTimer::default(),
+            prof_timer: CpuTimer::default(),
+            posix_timers: BTreeMap::new(),
+            next_posix_timer_id: 0,
+            rlimits,
+            exited_cpu_times: CpuTimes::default(),
+            children_cpu_times: CpuTimes::default(),
+        }))
+    }
+
+    /// The thread group leader.
+    pub fn leader(&self) -> &Weak<Process> {
+        &self.leader
+    }
+
+    /// Makes the thread calling `execve(2)` the leader.
+    pub fn set_leader(&mut self, leader: Weak<Process>) {
+        self.leader = leader;
+    }
+
+    /// The signal sent to the parent on exit.
+    pub fn exit_signal(&self) -> Option<Signal> {
+        self.exit_signal
+    }
+
+    /// Returns `true` if `thread` has to exit because another thread is
+    /// calling `execve(2)`.
+    pub fn is_killed_by_exec(&self, thread: &Process) -> bool {
+        self.exec_thread.as_ref().map_or(false, |exec_thread| {
+            !core::ptr::eq(exec_thread.as_ptr(), thread)
+        })
+    }
+
+    /// Sets or clears the thread calling `execve(2)`.
+    pub fn set_exec_thread(&mut self, thread: Option<Weak<Process>>) {
+        self.exec_thread = thread;
+    }
+
+    /// Resource limits.
+    pub fn rlimits(&self) -> &ResourceLimits {
+        &self.rlimits
+    }
+
+    pub fn rlimits_mut(&mut self) -> &mut ResourceLimits {
+        &mut self.rlimits
+    }
+
+    /// Threads which have not yet exited.
+    pub fn threads(&self) -> &[Weak<Process>] {
+        &self.threads
+    }
+
+    /// Returns `true` if all threads have exited.
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty()
+    }
+
+    /// Adds a thread into the group. The first one becomes the leader.
+    pub fn add(&mut self, thread: Weak<Process>) {
+        if self.threads.is_empty() {
+            self.leader = thread.clone();
+        }
+
+        self.threads.push(thread);
+    }
+
+    /// Removes an exited thread from the group. `cpu_times` is the CPU times
+    /// used by the thread.
+    pub fn remove(&mut self, thread: &Weak<Process>, cpu_times: CpuTimes) {
+        self.threads.retain(|t| !Weak::ptr_eq(t, thread));
+        self.exited_cpu_times = self.exited_cpu_times + cpu_times;
+    }
+
+    /// CPU times used by all threads in the group including exited ones.
+    pub fn cpu_times(&self) -> CpuTimes {
+        self.threads
+            .iter()
+            .filter_map(|thread| thread.upgrade())
+            .fold(self.exited_cpu_times, |sum, thread| {
+                sum + thread.cpu_times()
+            })
+    }
+
+    /// CPU times used by reaped children and their descendants.
+    pub fn children_cpu_times(&self) -> CpuTimes {
+        self.children_cpu_times
+    }
+
+    /// Adds CPU times of a reaped child.
+    pub fn add_children_cpu_times(&mut self, cpu_times: CpuTimes) {
+        self.children_cpu_times = self.children_cpu_times + cpu_times;
+    }
+
+    /// The wait status set by `exit_group(2)` or a fatal signal. `None` if the
+    /// group is not exiting.
+    pub fn exit_status(&self) -> Option<c_int> {
+        self.exit_status
+    }
+
+    /// Makes all threads in the group exit.
+    pub fn set_exit_status(&mut self, status: c_int) {
+        if self.exit_status.is_none() {
+            self.exit_status = Some(status);
+        }
+    }
+
+    /// The signal which has stopped the group. `None` if it's not stopped.
+    pub fn stop_signal(&self) -> Option<Signal> {
+        self.stop_signal
+    }
+
+    /// Stops all threads in the group. Returns `false` if it's already
+    /// stopped.
+    pub fn stop(&mut self, signal: Signal) -> bool {
+        if self.stop_signal.is_some() {
+            return false;
+        }
+
+        self.stop_signal = Some(signal);
+        self.job_control_event = Some(JobControlEvent::Stopped(signal));
+        true
+    }
+
+    /// Continues the stopped group. Returns `false` if it's not stopped.
+    pub fn cont(&mut self) -> bool {
+        if self.stop_signal.take().is_none() {
+            return false;
+        }
+
+        self.job_control_event = Some(JobControlEvent::Continued);
+        true
+    }
+
+    /// The latest job control event not yet reported to the parent.
+    pub fn job_control_event(&self) -> Option<JobControlEvent> {
+        self.job_control_event
+    }
+
+    /// Marks the job control event as reported.
+    pub fn clear_job_control_event(&mut self) {
+        self.job_control_event = None;
+    }
+
+    /// `ITIMER_REAL`: sends `SIGALRM` to the process on expiry.
+    pub fn real_timer(&mut self) -> Arc<IntervalTimer> {
+        let leader = &self.leader;
+        self.real_timer
+            .get_or_insert_with(|| {
+                IntervalTimer::new(leader.clone(), Some(SigInfo::kernel(SIGALRM)))
+            })
+            .clone()
+    }
+
+    /// `ITIMER_VIRTUAL`.
+    pub fn virtual_timer_mut(&mut self) -> &mut CpuTimer {
+        &mut self.virtual_timer
+    }
+
+    /// `ITIMER_PROF`.
+    pub fn prof_timer_mut(&mut self) -> &mut CpuTimer {
+        &mut self.prof_timer
+    }
+
+    /// Registers a POSIX timer created by `new_timer`, which takes the timer
+    /// ID. Returns the ID.
+    pub fn add_posix_timer(
+        &mut self,
+        clock: c_clockid,
+        new_timer: impl FnOnce(c_int) -> Arc<IntervalTimer>,
+    ) -> c_int {
+        let id = self.next_posix_timer_id;
+        self.next_posix_timer_id += 1;
+        let timer = new_timer(id);
+        self.posix_timers.insert(id, PosixTimer { clock, timer });
+        id
+    }
+
+    /// Looks for the POSIX timer.
+    pub fn posix_timer(&self, id: c_int) -> Result<PosixTimer> {
+        self.posix_timers
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Errno::EINVAL.into())
+    }
+
+    /// Unregisters the POSIX timer.
+    pub fn remove_posix_timer(&mut self, id: c_int) -> Result<PosixTimer> {
+        self.posix_timers
+            .remove(&id)
+            .ok_or_else(|| Errno::EINVAL.into())
+    }
+
+    /// Unregisters all POSIX timers.
+    pub fn take_posix_timers(&mut self) -> Vec<PosixTimer> {
+        core::mem::take(&mut self.posix_timers)
+            .into_values()
+            .collect()
+    }
+}
@@ -18,7 +18,9 @@ impl WaitQueue {
 
     /// Sleeps on the wait queue until `sleep_if_none` returns `Some`.
     ///
-    /// If a signal is arrived, this method returns `Err(Errno::EINTR)`.
+    /// If a signal is arrived, this method returns `Err(Errno::ERESTARTSYS)`:
+    /// the system call is restarted or fails with `EINTR` depending on the
+    /// signal handler.
     pub fn sleep_signalable_until<F, R>(&self, mut sleep_if_none: F) -> Result<R>
     where
         F: FnMut() -> Result<Option<R>>,
@@ -46,7 +48,7 @@ impl WaitQueue {
                 self.queue
                     .lock()
                     .retain(|proc| !Arc::ptr_eq(proc, current_process()));
-                return Err(Errno::EINTR.into());
+                return Err(Errno::ERESTARTSYS.into());
             }
 
             let ret_value = match sleep_if_none() {
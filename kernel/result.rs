@@ -49,8 +49,12 @@ pub enum Errno {
     ERANGE = 34,
 
     ENOSYS = 38,
+    ENOTEMPTY = 39,
     ELOOP = 40,
 
+    EOVERFLOW = 75,
+
+    EOPNOTSUPP = 95,
     EADDRINUSE = 98,
     EADDRNOTAVAIL = 99,
     ENETDOWN = 100,
@@ -61,6 +65,15 @@ pub enum Errno {
     ENOBUFS = 105,
     EISCONN = 106,
     ENOTCONN = 107,
+
+    ETIMEDOUT = 110,
+
+    /// The system call is restarted if the signal handler has `SA_RESTART`.
+    /// Never returned to the userspace.
+    ERESTARTSYS = 512,
+    /// The system call is restarted unless a signal handler is called. Never
+    /// returned to the userspace.
+    ERESTARTNOHAND = 514,
 }
 
 pub type Result<T> = ::core::result::Result<T, Error>;
@@ -21,6 +21,8 @@ impl<'a> SyscallHandler<'a> {
         let options = OpenOptions {
             nonblock: false,
             close_on_exec: false,
+            append: false,
+            writable: true,
         };
         let fd = current_process()
             .opened_files()
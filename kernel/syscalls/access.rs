@@ -0,0 +1,12 @@
+use crate::{
+    ctypes::*,
+    fs::path::Path,
+    prelude::*,
+    syscalls::{AtFlags, CwdOrFd, SyscallHandler},
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_access(&mut self, path: &Path, mode: c_int) -> Result<isize> {
+        self.sys_faccessat(CwdOrFd::AtCwd, path, mode, AtFlags::empty())
+    }
+}
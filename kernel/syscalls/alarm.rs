@@ -0,0 +1,18 @@
+use crate::{ctypes::c_uint, prelude::*, syscalls::SyscallHandler};
+
+use super::setitimer::{set_itimer, ITIMER_REAL};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_alarm(&mut self, seconds: c_uint) -> Result<isize> {
+        let (remaining, _) = set_itimer(ITIMER_REAL, seconds as u64 * 1_000_000_000, 0)?;
+
+        // Round to the nearest second but don't return 0 if the previous alarm
+        // is still pending.
+        let remaining_secs = (remaining + 500_000_000) / 1_000_000_000;
+        if remaining > 0 && remaining_secs == 0 {
+            return Ok(1);
+        }
+
+        Ok(remaining_secs as isize)
+    }
+}
@@ -4,7 +4,11 @@ use crate::{process::current_process, syscalls::SyscallHandler};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_chdir(&mut self, path: &Path) -> Result<isize> {
-        current_process().root_fs().lock().chdir(path)?;
+        let current = current_process();
+        current
+            .root_fs()
+            .lock()
+            .chdir(path, &current.credentials())?;
         Ok(0)
     }
 }
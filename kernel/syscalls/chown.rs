@@ -0,0 +1,64 @@
+use crate::{
+    fs::{
+        inode::INode,
+        path::Path,
+        stat::{FileMode, GId, UId, S_ISGID, S_ISUID},
+    },
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+};
+
+/// Changes the owner and the group of `inode`. `None` leaves the ID unchanged.
+pub(super) fn chown_inode(inode: &INode, uid: Option<UId>, gid: Option<GId>) -> Result<()> {
+    let creds = current_process().credentials();
+    let stat = inode.stat()?;
+    let (old_uid, old_gid) = (stat.uid, stat.gid);
+
+    // Only the superuser can change the owner. The owner can change the group
+    // to one which it belongs to.
+    if let Some(uid) = uid {
+        let permitted = creds.is_privileged() || (creds.owns(&stat) && uid == old_uid);
+        if !permitted {
+            return Err(Errno::EPERM.into());
+        }
+    }
+
+    if let Some(gid) = gid {
+        let permitted =
+            creds.is_privileged() || (creds.owns(&stat) && (gid == old_gid || creds.in_group(gid)));
+        if !permitted {
+            return Err(Errno::EPERM.into());
+        }
+    }
+
+    inode.chown(uid.unwrap_or(old_uid), gid.unwrap_or(old_gid))?;
+
+    // Changing the owner of an executable drops the setuid bit, and the
+    // setgid bit unless it means mandatory locking.
+    let mode = stat.mode;
+    if (uid.is_some() || gid.is_some()) && !mode.is_directory() {
+        let mut perm = mode.permission() & !S_ISUID;
+        if perm & 0o010 != 0 {
+            perm &= !S_ISGID;
+        }
+
+        if perm != mode.permission() {
+            inode.chmod(FileMode::new(perm))?;
+        }
+    }
+
+    Ok(())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_chown(&mut self, path: &Path, uid: Option<UId>, gid: Option<GId>) -> Result<isize> {
+        let current = current_process();
+        let inode = current
+            .root_fs()
+            .lock()
+            .lookup(path, &current.credentials())?;
+        chown_inode(&inode, uid, gid)?;
+        Ok(0)
+    }
+}
@@ -3,7 +3,11 @@ use kerla_runtime::address::UserVAddr;
 use crate::result::{Errno, Result};
 use crate::user_buffer::UserBufWriter;
 use crate::{
-    ctypes::{c_clockid, c_long, c_time, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    ctypes::{
+        c_clockid, c_long, c_time, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME,
+        CLOCK_THREAD_CPUTIME_ID,
+    },
+    process::current_process,
     timer::read_wall_clock,
 };
 use crate::{syscalls::SyscallHandler, timer::read_monotonic_clock};
@@ -14,11 +18,26 @@ impl<'a> SyscallHandler<'a> {
         let (tv_sec, tv_nsec) = match clock {
             CLOCK_REALTIME => {
                 let now = read_wall_clock();
-                (now.secs_from_epoch(), now.nanosecs_from_epoch())
+                (
+                    now.secs_from_epoch(),
+                    now.nanosecs_from_epoch() % 1_000_000_000,
+                )
             }
             CLOCK_MONOTONIC => {
                 let now = read_monotonic_clock();
-                (now.secs(), now.nanosecs())
+                (now.secs(), now.nanosecs() % 1_000_000_000)
+            }
+            CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+                let current = current_process();
+                current.account_cpu_time();
+                let cpu_times = if clock == CLOCK_PROCESS_CPUTIME_ID {
+                    current.thread_group().cpu_times()
+                } else {
+                    current.cpu_times()
+                };
+
+                let nanosecs = cpu_times.total() as usize;
+                (nanosecs / 1_000_000_000, nanosecs % 1_000_000_000)
             }
             _ => {
                 debug_warn!("clock_gettime: unsupported clock id: {}", clock);
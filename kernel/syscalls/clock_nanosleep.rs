@@ -0,0 +1,52 @@
+use crate::{
+    ctypes::{c_clockid, c_int, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    prelude::*,
+    syscalls::SyscallHandler,
+    timer::{read_monotonic_clock, sleep_until, MonotonicClock, Timespec, WallClock},
+};
+use kerla_runtime::address::UserVAddr;
+
+/// `req` is an absolute time.
+const TIMER_ABSTIME: c_int = 1;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_clock_nanosleep(
+        &mut self,
+        clock: c_clockid,
+        flags: c_int,
+        req: UserVAddr,
+        rem: Option<UserVAddr>,
+    ) -> Result<isize> {
+        if !matches!(clock, CLOCK_REALTIME | CLOCK_MONOTONIC) {
+            debug_warn!("clock_nanosleep: unsupported clock id: {}", clock);
+            return Err(Errno::EINVAL.into());
+        }
+
+        let time = req.read::<Timespec>()?;
+        if !time.is_valid() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let absolute = flags & TIMER_ABSTIME != 0;
+        let deadline = if !absolute {
+            read_monotonic_clock().add_nanosecs(time.as_nanosecs())
+        } else if clock == CLOCK_REALTIME {
+            WallClock::from_nanosecs_from_epoch(time.as_nanosecs()).to_monotonic()
+        } else {
+            MonotonicClock::from_nanosecs(time.as_nanosecs())
+        };
+
+        if let Err(err) = sleep_until(deadline) {
+            // Interrupted by a signal. Write back the remaining time unless
+            // the deadline is absolute.
+            if let Some(rem) = rem.filter(|_| !absolute) {
+                let remaining = read_monotonic_clock().nanosecs_until(deadline);
+                rem.write(&Timespec::from_nanosecs(remaining))?;
+            }
+
+            return Err(err);
+        }
+
+        Ok(0)
+    }
+}
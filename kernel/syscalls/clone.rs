@@ -0,0 +1,81 @@
+use crate::{
+    ctypes::*,
+    prelude::*,
+    process::{
+        current_process,
+        ptrace::clone_event,
+        signal::{Signal, SIGMAX},
+        PId, Process,
+    },
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+use super::unshare::NAMESPACE_FLAGS;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_clone(
+        &mut self,
+        flags: CloneFlags,
+        exit_signal: Signal,
+        child_stack: Option<UserVAddr>,
+        parent_tid: Option<UserVAddr>,
+        child_tid: Option<UserVAddr>,
+        tls: Option<UserVAddr>,
+    ) -> Result<isize> {
+        // Threads share signal handlers and signal handlers are meaningful
+        // only in the same address space.
+        if flags.contains(CloneFlags::CLONE_THREAD) && !flags.contains(CloneFlags::CLONE_SIGHAND) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if flags.contains(CloneFlags::CLONE_SIGHAND) && !flags.contains(CloneFlags::CLONE_VM) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // A thread can't be in another PID namespace or mount namespace.
+        if flags.contains(CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_THREAD)
+            || flags.contains(CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_FS)
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if exit_signal > SIGMAX {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        if flags.intersects(NAMESPACE_FLAGS) && !current.credentials().is_privileged() {
+            return Err(Errno::EPERM.into());
+        }
+
+        // The init process of a PID namespace can't create a sibling.
+        if flags.contains(CloneFlags::CLONE_PARENT)
+            && current.pid_ns().pid_of(current) == PId::new(1)
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let child = Process::clone(
+            current,
+            self.frame,
+            flags,
+            Some(exit_signal).filter(|signal| *signal != 0),
+            child_stack,
+            tls,
+            parent_tid,
+            child_tid,
+        )?;
+
+        let tid = current.pid_ns().tid_of(&child).as_i32();
+        if !flags.contains(CloneFlags::CLONE_UNTRACED) {
+            Process::ptrace_event(self.frame, clone_event(flags), tid as u64);
+        }
+
+        if flags.contains(CloneFlags::CLONE_VFORK) {
+            current.wait_for_vfork_done(&child);
+        }
+
+        Ok(tid as isize)
+    }
+}
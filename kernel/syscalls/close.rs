@@ -1,9 +1,16 @@
-use crate::{fs::opened_file::Fd, result::Result};
+use crate::{fs::opened_file::Fd, mm::page_cache, result::Result};
 use crate::{process::current_process, syscalls::SyscallHandler};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_close(&mut self, fd: Fd) -> Result<isize> {
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
         current_process().opened_files().lock().close(fd)?;
+
+        // Write back and evict cached pages no longer mapped.
+        if let Ok(file) = opened_file.as_file() {
+            page_cache::release(file, 0, usize::MAX)?;
+        }
+
         Ok(0)
     }
 }
@@ -1,6 +1,6 @@
 use crate::fs::path::Path;
 use crate::prelude::*;
-use crate::process::Process;
+use crate::process::{ptrace::PTRACE_EVENT_EXEC, signal::SIGTRAP, Process};
 use crate::user_buffer::UserCStr;
 use crate::{process::current_process, syscalls::SyscallHandler};
 use core::mem::size_of;
@@ -19,7 +19,11 @@ impl<'a> SyscallHandler<'a> {
         envp_uaddr: UserVAddr,
     ) -> Result<isize> {
         let current = current_process();
-        let executable = current.root_fs().lock().lookup_path(path, true)?;
+        let executable =
+            current
+                .root_fs()
+                .lock()
+                .lookup_path(path, true, &current.credentials())?;
 
         let mut argv = Vec::new();
         for i in 0..ARG_MAX {
@@ -42,6 +46,15 @@ impl<'a> SyscallHandler<'a> {
         let argv_slice: Vec<&[u8]> = argv.as_slice().iter().map(|s| s.as_bytes()).collect();
         let envp_slice: Vec<&[u8]> = envp.as_slice().iter().map(|s| s.as_bytes()).collect();
         Process::execve(self.frame, executable, &argv_slice, &envp_slice)?;
+
+        // A traced process stops after execve(2) so that the tracer can
+        // insert breakpoints in the new executable.
+        if current.is_traced()
+            && !Process::ptrace_event(self.frame, PTRACE_EVENT_EXEC, current.pid().as_i32() as u64)
+        {
+            current.send_signal(SIGTRAP);
+        }
+
         Ok(0)
     }
 }
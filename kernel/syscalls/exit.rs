@@ -1,7 +1,13 @@
-use crate::{ctypes::*, process::Process, syscalls::SyscallHandler};
+use crate::{
+    ctypes::*,
+    process::{ptrace::PTRACE_EVENT_EXIT, Process},
+    syscalls::SyscallHandler,
+};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_exit(&mut self, status: c_int) -> ! {
+        // The tracer can inspect the process before it exits.
+        Process::ptrace_event(self.frame, PTRACE_EVENT_EXIT, ((status & 0xff) << 8) as u64);
         Process::exit(status);
     }
 }
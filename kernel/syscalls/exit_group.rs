@@ -1,8 +1,13 @@
-use crate::{ctypes::*, process::Process, syscalls::SyscallHandler};
+use crate::{
+    ctypes::*,
+    process::{ptrace::PTRACE_EVENT_EXIT, Process},
+    syscalls::SyscallHandler,
+};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_exit_group(&mut self, status: c_int) -> ! {
-        // TODO: Kill other threads belonging to the same thread group.
+        // The tracer can inspect the process before it exits.
+        Process::ptrace_event(self.frame, PTRACE_EVENT_EXIT, ((status & 0xff) << 8) as u64);
         Process::exit_group(status);
     }
 }
@@ -0,0 +1,41 @@
+use crate::{
+    ctypes::*,
+    fs::path::Path,
+    prelude::*,
+    process::{
+        credentials::{AccessMode, Credentials},
+        current_process,
+    },
+    syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler},
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_faccessat(
+        &mut self,
+        dir: CwdOrFd,
+        path: &Path,
+        mode: c_int,
+        flags: AtFlags,
+    ) -> Result<isize> {
+        let supported_flags =
+            AtFlags::AT_EACCESS | AtFlags::AT_SYMLINK_NOFOLLOW | AtFlags::AT_EMPTY_PATH;
+        if !(flags - supported_flags).is_empty() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // F_OK (0) only checks if the file exists.
+        let access = AccessMode::from_bits(mode as u32).ok_or_else(|| Error::new(Errno::EINVAL))?;
+
+        // Permissions are checked with the real IDs unless AT_EACCESS is set.
+        let current = current_process();
+        let creds: Credentials = if flags.contains(AtFlags::AT_EACCESS) {
+            (*current.credentials()).clone()
+        } else {
+            current.credentials().with_real_ids()
+        };
+
+        let path_comp = lookup_path_at(&dir, path, flags, &creds)?;
+        creds.check_access(&path_comp.inode.stat()?, access)?;
+        Ok(0)
+    }
+}
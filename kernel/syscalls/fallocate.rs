@@ -0,0 +1,35 @@
+use crate::fs::{inode::FallocFlags, opened_file::Fd};
+use crate::{ctypes::c_int, prelude::*};
+use crate::{process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_fallocate(
+        &mut self,
+        fd: Fd,
+        mode: c_int,
+        offset: isize,
+        len: isize,
+    ) -> Result<isize> {
+        if offset < 0 || len <= 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let flags = FallocFlags::from_bits(mode).ok_or_else(|| Error::new(Errno::EOPNOTSUPP))?;
+        if flags.contains(FallocFlags::FALLOC_FL_PUNCH_HOLE)
+            && !flags.contains(FallocFlags::FALLOC_FL_KEEP_SIZE)
+        {
+            return Err(Errno::EOPNOTSUPP.into());
+        }
+
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        if !opened_file.options().writable {
+            return Err(Errno::EBADF.into());
+        }
+
+        opened_file.check_seekable()?;
+        opened_file
+            .inode()
+            .fallocate(flags, offset as usize, len as usize)?;
+        Ok(0)
+    }
+}
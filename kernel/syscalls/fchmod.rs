@@ -0,0 +1,16 @@
+use crate::{
+    fs::{opened_file::Fd, stat::FileMode},
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+};
+
+use super::fchmodat::chmod_inode;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_fchmod(&mut self, fd: Fd, mode: FileMode) -> Result<isize> {
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        chmod_inode(&opened_file.path().inode, mode)?;
+        Ok(0)
+    }
+}
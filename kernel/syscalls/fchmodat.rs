@@ -0,0 +1,36 @@
+use crate::fs::{
+    inode::INode,
+    path::Path,
+    stat::{FileMode, S_ISGID},
+};
+use crate::prelude::*;
+use crate::{
+    process::current_process,
+    syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler},
+};
+
+/// Changes the permission bits of `inode`. Only the owner can change them.
+pub(super) fn chmod_inode(inode: &INode, mode: FileMode) -> Result<()> {
+    let creds = current_process().credentials();
+    let stat = inode.stat()?;
+    if !creds.owns(&stat) {
+        return Err(Errno::EPERM.into());
+    }
+
+    // The setgid bit is cleared if the owner doesn't belong to the group.
+    let mut perm = mode.permission();
+    if !creds.is_privileged() && !creds.in_group(stat.gid) {
+        perm &= !S_ISGID;
+    }
+
+    inode.chmod(FileMode::new(perm))
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_fchmodat(&mut self, dir: CwdOrFd, path: &Path, mode: FileMode) -> Result<isize> {
+        let creds = current_process().credentials();
+        let path_comp = lookup_path_at(&dir, path, AtFlags::empty(), &creds)?;
+        chmod_inode(&path_comp.inode, mode)?;
+        Ok(0)
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{
+    fs::{
+        opened_file::Fd,
+        stat::{GId, UId},
+    },
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+};
+
+use super::chown::chown_inode;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_fchown(&mut self, fd: Fd, uid: Option<UId>, gid: Option<GId>) -> Result<isize> {
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        chown_inode(&opened_file.path().inode, uid, gid)?;
+        Ok(0)
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{
-    process::{current_process, Process},
+    ctypes::CloneFlags,
+    process::{current_process, ptrace::clone_event, Process},
     result::Result,
     syscalls::SyscallHandler,
 };
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_fork(&mut self) -> Result<isize> {
-        Process::fork(current_process(), self.frame).map(|child| child.pid().as_i32() as isize)
+        let current = current_process();
+        let child = Process::fork(current, self.frame)?;
+        let pid = current.pid_ns().pid_of(&child).as_i32();
+        Process::ptrace_event(self.frame, clone_event(CloneFlags::empty()), pid as u64);
+        Ok(pid as isize)
     }
 }
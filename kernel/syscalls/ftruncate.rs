@@ -0,0 +1,18 @@
+use crate::{fs::opened_file::Fd, prelude::*};
+use crate::{process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_ftruncate(&mut self, fd: Fd, length: isize) -> Result<isize> {
+        if length < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        if opened_file.inode().is_dir() || !opened_file.options().writable {
+            return Err(Errno::EINVAL.into());
+        }
+
+        opened_file.inode().truncate(length as usize)?;
+        Ok(0)
+    }
+}
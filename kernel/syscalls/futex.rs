@@ -0,0 +1,90 @@
+use crate::{
+    ctypes::*,
+    prelude::*,
+    process::futex::{futex_requeue, futex_wait, futex_wake, FutexKey, FUTEX_BITSET_MATCH_ANY},
+    syscalls::SyscallHandler,
+    timer::{read_monotonic_clock, MonotonicClock, Timespec, WallClock},
+};
+use kerla_runtime::address::UserVAddr;
+
+const FUTEX_WAIT: c_int = 0;
+const FUTEX_WAKE: c_int = 1;
+const FUTEX_REQUEUE: c_int = 3;
+const FUTEX_CMP_REQUEUE: c_int = 4;
+const FUTEX_WAIT_BITSET: c_int = 9;
+const FUTEX_WAKE_BITSET: c_int = 10;
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+const FUTEX_CLOCK_REALTIME: c_int = 256;
+const FUTEX_CMD_MASK: c_int = !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+
+/// Reads the timeout in nanoseconds.
+fn read_timeout(uaddr: Option<UserVAddr>) -> Result<Option<u64>> {
+    match uaddr {
+        Some(uaddr) => {
+            let timeout = uaddr.read::<Timespec>()?;
+            if !timeout.is_valid() {
+                return Err(Errno::EINVAL.into());
+            }
+
+            Ok(Some(timeout.as_nanosecs()))
+        }
+        None => Ok(None),
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_futex(
+        &mut self,
+        uaddr: UserVAddr,
+        op: c_int,
+        val: u32,
+        timeout_or_val2: usize,
+        uaddr2: Option<UserVAddr>,
+        val3: u32,
+    ) -> Result<isize> {
+        let private = op & FUTEX_PRIVATE_FLAG != 0;
+        let key = FutexKey::new(uaddr, private)?;
+        match op & FUTEX_CMD_MASK {
+            FUTEX_WAIT => {
+                // The timeout is relative.
+                let timeout = read_timeout(UserVAddr::new(timeout_or_val2))?
+                    .map(|timeout| read_monotonic_clock().add_nanosecs(timeout));
+                futex_wait(uaddr, key, val, FUTEX_BITSET_MATCH_ANY, timeout)?;
+                Ok(0)
+            }
+            FUTEX_WAIT_BITSET => {
+                // The timeout is absolute.
+                let timeout = read_timeout(UserVAddr::new(timeout_or_val2))?.map(|deadline| {
+                    if op & FUTEX_CLOCK_REALTIME != 0 {
+                        WallClock::from_nanosecs_from_epoch(deadline).to_monotonic()
+                    } else {
+                        MonotonicClock::from_nanosecs(deadline)
+                    }
+                });
+
+                futex_wait(uaddr, key, val, val3, timeout)?;
+                Ok(0)
+            }
+            FUTEX_WAKE => Ok(futex_wake(key, val as usize, FUTEX_BITSET_MATCH_ANY)? as isize),
+            FUTEX_WAKE_BITSET => Ok(futex_wake(key, val as usize, val3)? as isize),
+            cmd @ (FUTEX_REQUEUE | FUTEX_CMP_REQUEUE) => {
+                let uaddr2 = uaddr2.ok_or_else(|| Error::new(Errno::EFAULT))?;
+                let new_key = FutexKey::new(uaddr2, private)?;
+                let expected = if cmd == FUTEX_CMP_REQUEUE {
+                    Some(val3)
+                } else {
+                    None
+                };
+
+                let count =
+                    futex_requeue(uaddr, key, new_key, val as usize, timeout_or_val2, expected)?;
+
+                Ok(count as isize)
+            }
+            _ => {
+                debug_warn!("futex: unsupported op: {}", op);
+                Err(Errno::ENOSYS.into())
+            }
+        }
+    }
+}
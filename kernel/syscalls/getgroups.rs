@@ -0,0 +1,31 @@
+use core::mem::size_of;
+
+use kerla_runtime::address::UserVAddr;
+
+use crate::{
+    ctypes::*, prelude::*, process::current_process, syscalls::SyscallHandler,
+    user_buffer::UserBufWriter,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getgroups(&mut self, size: c_int, list: Option<UserVAddr>) -> Result<isize> {
+        let creds = current_process().credentials();
+        let num_groups = creds.groups.len();
+        // The size 0 only queries the number of groups.
+        if size == 0 {
+            return Ok(num_groups as isize);
+        }
+
+        if size < 0 || (size as usize) < num_groups {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let list = list.ok_or_else(|| Error::new(Errno::EFAULT))?;
+        let mut writer = UserBufWriter::from_uaddr(list, num_groups * size_of::<u32>());
+        for gid in &creds.groups {
+            writer.write::<u32>(gid.as_u32())?;
+        }
+
+        Ok(num_groups as isize)
+    }
+}
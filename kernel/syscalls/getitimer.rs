@@ -0,0 +1,12 @@
+use crate::{ctypes::c_int, prelude::*, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+use super::setitimer::{get_itimer, ITimerVal};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getitimer(&mut self, which: c_int, curr_value: UserVAddr) -> Result<isize> {
+        let (value, interval) = get_itimer(which)?;
+        curr_value.write(&ITimerVal::from_nanosecs(value, interval))?;
+        Ok(0)
+    }
+}
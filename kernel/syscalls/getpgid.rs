@@ -1,14 +1,17 @@
-use crate::{process::current_process, syscalls::SyscallHandler};
+use crate::{prelude::*, process::current_process, syscalls::SyscallHandler};
 use crate::{process::PId, result::Result};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_getpgid(&mut self, pid: PId) -> Result<isize> {
+        let current = current_process();
         let pgid = if pid.as_i32() == 0 {
-            current_process().process_group().lock().pgid()
+            current.process_group().lock().pgid()
         } else {
-            todo!()
+            let proc = current.pid_ns().find(pid).ok_or(Errno::ESRCH)?;
+            let pgid = proc.process_group().lock().pgid();
+            pgid
         };
 
-        Ok(pgid.as_i32() as isize)
+        Ok(current.pid_ns().local_id(pgid.as_i32()) as isize)
     }
 }
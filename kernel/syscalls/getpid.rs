@@ -3,6 +3,7 @@ use crate::{process::current_process, syscalls::SyscallHandler};
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_getpid(&mut self) -> Result<isize> {
-        Ok(current_process().pid().as_i32() as isize)
+        let current = current_process();
+        Ok(current.pid_ns().pid_of(current).as_i32() as isize)
     }
 }
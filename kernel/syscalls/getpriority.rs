@@ -0,0 +1,63 @@
+use crate::{
+    ctypes::c_int,
+    fs::stat::UId,
+    prelude::*,
+    process::{current_process, process_group::ProcessGroup, PId, Process},
+    syscalls::{global_pgid, SyscallHandler},
+};
+
+const PRIO_PROCESS: c_int = 0;
+const PRIO_PGRP: c_int = 1;
+const PRIO_USER: c_int = 2;
+
+/// Returns processes specified by `which` and `who` in `getpriority(2)` and
+/// `setpriority(2)`.
+pub(super) fn priority_targets(which: c_int, who: c_int) -> Result<Vec<Arc<Process>>> {
+    let procs = match which {
+        PRIO_PROCESS if who == 0 => vec![current_process().clone()],
+        PRIO_PROCESS => vec![current_process()
+            .pid_ns()
+            .find(PId::new(who))
+            .ok_or(Errno::ESRCH)?],
+        PRIO_PGRP if who == 0 => current_process().process_group().lock().processes(),
+        PRIO_PGRP => ProcessGroup::find_by_pgid(global_pgid(who))
+            .ok_or(Errno::ESRCH)?
+            .lock()
+            .processes(),
+        PRIO_USER => {
+            let current = current_process();
+            let uid = if who == 0 {
+                current.credentials().uid
+            } else {
+                UId::new(who as u32)
+            };
+
+            let pid_ns = current.pid_ns();
+            Process::all_processes()
+                .into_iter()
+                .filter(|proc| pid_ns.pid_of(proc).as_i32() > 0 && proc.credentials().uid == uid)
+                .collect()
+        }
+        _ => return Err(Errno::EINVAL.into()),
+    };
+
+    if procs.is_empty() {
+        return Err(Errno::ESRCH.into());
+    }
+
+    Ok(procs)
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getpriority(&mut self, which: c_int, who: c_int) -> Result<isize> {
+        let nice = priority_targets(which, who)?
+            .iter()
+            .map(|proc| proc.nice())
+            .min()
+            .unwrap();
+
+        // The system call returns `20 - nice` (1 to 40) to avoid negative
+        // values. The libc converts it into the nice value.
+        Ok((20 - nice) as isize)
+    }
+}
@@ -0,0 +1,18 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::{process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getresgid(
+        &mut self,
+        gid: UserVAddr,
+        egid: UserVAddr,
+        sgid: UserVAddr,
+    ) -> Result<isize> {
+        let creds = current_process().credentials();
+        gid.write::<u32>(&creds.gid.as_u32())?;
+        egid.write::<u32>(&creds.egid.as_u32())?;
+        sgid.write::<u32>(&creds.sgid.as_u32())?;
+        Ok(0)
+    }
+}
@@ -0,0 +1,18 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::{process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getresuid(
+        &mut self,
+        uid: UserVAddr,
+        euid: UserVAddr,
+        suid: UserVAddr,
+    ) -> Result<isize> {
+        let creds = current_process().credentials();
+        uid.write::<u32>(&creds.uid.as_u32())?;
+        euid.write::<u32>(&creds.euid.as_u32())?;
+        suid.write::<u32>(&creds.suid.as_u32())?;
+        Ok(0)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    ctypes::{c_int, c_long},
+    prelude::*,
+    process::{current_process, thread_group::CpuTimes},
+    syscalls::SyscallHandler,
+    timer::Timeval,
+};
+use kerla_runtime::address::UserVAddr;
+
+const RUSAGE_SELF: c_int = 0;
+const RUSAGE_CHILDREN: c_int = -1;
+const RUSAGE_THREAD: c_int = 1;
+
+/// `struct rusage`. Only CPU times are reported.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct RUsage {
+    ru_utime: Timeval,
+    ru_stime: Timeval,
+    /// From `ru_maxrss` to `ru_nivcsw`. Not supported.
+    unsupported: [c_long; 14],
+}
+
+impl RUsage {
+    pub fn from_cpu_times(cpu_times: CpuTimes) -> RUsage {
+        RUsage {
+            ru_utime: Timeval::from_nanosecs(cpu_times.utime),
+            ru_stime: Timeval::from_nanosecs(cpu_times.stime),
+            unsupported: [0; 14],
+        }
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getrusage(&mut self, who: c_int, usage: UserVAddr) -> Result<isize> {
+        let current = current_process();
+        current.account_cpu_time();
+        let cpu_times = match who {
+            RUSAGE_SELF => current.thread_group().cpu_times(),
+            RUSAGE_CHILDREN => current.thread_group().children_cpu_times(),
+            RUSAGE_THREAD => current.cpu_times(),
+            _ => return Err(Errno::EINVAL.into()),
+        };
+
+        usage.write(&RUsage::from_cpu_times(cpu_times))?;
+        Ok(0)
+    }
+}
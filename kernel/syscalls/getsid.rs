@@ -0,0 +1,22 @@
+use crate::{
+    prelude::*,
+    process::{current_process, PId},
+    result::Result,
+    syscalls::SyscallHandler,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getsid(&mut self, pid: PId) -> Result<isize> {
+        let proc = if pid.as_i32() == 0 {
+            current_process().clone()
+        } else {
+            current_process()
+                .pid_ns()
+                .find(pid)
+                .ok_or_else(|| Error::new(Errno::ESRCH))?
+        };
+
+        let sid = proc.process_group().lock().session().lock().sid();
+        Ok(current_process().pid_ns().local_id(sid.as_i32()) as isize)
+    }
+}
@@ -0,0 +1,7 @@
+use crate::{process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_getuid(&mut self) -> Result<isize> {
+        Ok(current_process().credentials().uid.as_u32() as isize)
+    }
+}
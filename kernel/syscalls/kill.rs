@@ -1,25 +1,115 @@
-use crate::process::{current_process, signal::Signal, PId, Process};
+use crate::prelude::*;
+use crate::process::{
+    current_process,
+    process_group::ProcessGroup,
+    signal::{SigInfo, Signal, SIGMAX},
+    PId, Process,
+};
 use crate::result::Errno;
 use crate::result::Result;
-use crate::syscalls::SyscallHandler;
+use crate::syscalls::{global_pgid, SyscallHandler};
+use kerla_runtime::spinlock::SpinLock;
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_kill(&self, pid: PId, sig: Signal) -> Result<isize> {
+        if !(0..=SIGMAX).contains(&sig) {
+            return Err(Errno::EINVAL.into());
+        }
+
         let pid_int = pid.as_i32();
         match pid_int {
-            pid_int if pid_int > 0 => match Process::find_by_pid(pid) {
-                Some(proc) => proc.send_signal(sig),
+            // The signal 0 only checks if the process exists.
+            pid_int if pid_int > 0 => {
+                let proc = current_process().pid_ns().find(pid).ok_or(Errno::ESRCH)?;
+                if !current_process()
+                    .credentials()
+                    .can_signal(&proc.credentials())
+                {
+                    return Err(Errno::EPERM.into());
+                }
+
+                if sig != 0 {
+                    proc.send_siginfo(user_siginfo(&proc, sig));
+                }
+            }
+            0 => signal_group(&current_process().process_group(), sig)?,
+            -1 => signal_all(sig)?,
+            pid_int if pid_int < -1 => match ProcessGroup::find_by_pgid(global_pgid(-pid_int)) {
+                Some(pg) => signal_group(&pg, sig)?,
                 None => return Err(Errno::ESRCH.into()),
             },
-            0 => current_process().process_group().lock().signal(sig),
-            -1 => {
-                // TODO: check for permissions once linux capabilities is implemented
-                current_process().send_signal(sig);
-            }
-            pid_int if pid_int < -1 => current_process().process_group().lock().signal(sig),
             _ => (),
         }
 
         Ok(0)
     }
 }
+
+/// The signal information sent from the current process to `target`. The
+/// sender's process ID is the one seen from the target's PID namespace.
+fn user_siginfo(target: &Process, sig: Signal) -> SigInfo {
+    let current = current_process();
+    SigInfo::user(
+        sig,
+        target.pid_ns().pid_of(current),
+        current.credentials().uid,
+    )
+}
+
+/// Sends a signal to all processes in the current PID namespace which the
+/// current process is permitted to signal, except the init process and the
+/// current process itself.
+fn signal_all(sig: Signal) -> Result<()> {
+    let current = current_process();
+    let pid_ns = current.pid_ns();
+    let creds = current.credentials();
+    let targets: Vec<Arc<Process>> = Process::all_processes()
+        .into_iter()
+        .filter(|proc| proc.pid() != current.pid() && pid_ns.pid_of(proc).as_i32() > 1)
+        .collect();
+
+    if targets.is_empty() {
+        return Err(Errno::ESRCH.into());
+    }
+
+    let permitted: Vec<Arc<Process>> = targets
+        .into_iter()
+        .filter(|proc| creds.can_signal(&proc.credentials()))
+        .collect();
+
+    if permitted.is_empty() {
+        return Err(Errno::EPERM.into());
+    }
+
+    if sig != 0 {
+        for proc in permitted {
+            proc.send_siginfo(user_siginfo(&proc, sig));
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a signal to processes in the group which the current process is
+/// permitted to signal. Returns `EPERM` if there're no such processes.
+fn signal_group(pg: &Arc<SpinLock<ProcessGroup>>, sig: Signal) -> Result<()> {
+    let creds = current_process().credentials();
+    let permitted: Vec<Arc<Process>> = pg
+        .lock()
+        .processes()
+        .into_iter()
+        .filter(|proc| creds.can_signal(&proc.credentials()))
+        .collect();
+
+    if permitted.is_empty() {
+        return Err(Errno::EPERM.into());
+    }
+
+    if sig != 0 {
+        for proc in permitted {
+            proc.send_siginfo(user_siginfo(&proc, sig));
+        }
+    }
+
+    Ok(())
+}
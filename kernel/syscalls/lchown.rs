@@ -0,0 +1,23 @@
+use crate::{
+    fs::{
+        path::Path,
+        stat::{GId, UId},
+    },
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+};
+
+use super::chown::chown_inode;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_lchown(&mut self, path: &Path, uid: Option<UId>, gid: Option<GId>) -> Result<isize> {
+        let current = current_process();
+        let inode = current
+            .root_fs()
+            .lock()
+            .lookup_no_symlink_follow(path, &current.credentials())?;
+        chown_inode(&inode, uid, gid)?;
+        Ok(0)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::fs::path::Path;
 use crate::result::Result;
 use crate::{
-    process::current_process,
+    process::{credentials::AccessMode, current_process},
     syscalls::{AtFlags, CwdOrFd, SyscallHandler},
 };
 
@@ -15,17 +15,22 @@ impl<'a> SyscallHandler<'a> {
         flags: AtFlags,
     ) -> Result<isize> {
         let current = current_process();
-        let root_fs = current.root_fs().lock();
+        let root_fs = current.root_fs().clone();
+        let root_fs = root_fs.lock();
         let opened_files = current.opened_files().lock();
+        let creds = current.credentials();
         let src = root_fs.lookup_path_at(
             &opened_files,
             &src_dir,
             src_path,
             flags.contains(AtFlags::AT_SYMLINK_FOLLOW),
+            &creds,
         )?;
         let (parent_dir, dst_name) =
-            root_fs.lookup_parent_path_at(&opened_files, &dst_dir, dst_path, true)?;
-        parent_dir.inode.as_dir()?.link(dst_name, &src.inode)?;
+            root_fs.lookup_parent_path_at(&opened_files, &dst_dir, dst_path, true, &creds)?;
+        let parent_dir = parent_dir.inode.as_dir()?;
+        creds.check_access(&parent_dir.stat()?, AccessMode::W_OK | AccessMode::X_OK)?;
+        parent_dir.link(dst_name, &src.inode)?;
         Ok(0)
     }
 }
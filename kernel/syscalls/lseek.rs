@@ -0,0 +1,12 @@
+use crate::fs::opened_file::{Fd, SeekWhence};
+use crate::{ctypes::c_int, prelude::*};
+use crate::{process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_lseek(&mut self, fd: Fd, offset: isize, whence: c_int) -> Result<isize> {
+        let whence = SeekWhence::from_user(whence)?;
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        let new_pos = opened_file.seek(offset, whence)?;
+        Ok(new_pos as isize)
+    }
+}
@@ -0,0 +1,24 @@
+use crate::fs::{path::Path, stat::FileMode};
+use crate::prelude::*;
+use crate::{
+    process::{credentials::AccessMode, current_process},
+    syscalls::{CwdOrFd, SyscallHandler},
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_mkdirat(&mut self, dir: CwdOrFd, path: &Path, mode: FileMode) -> Result<isize> {
+        let current = current_process();
+        let creds = current.credentials();
+        let root_fs = current.root_fs().clone();
+        let opened_files = current.opened_files().lock();
+        let (parent_dir, name) =
+            root_fs
+                .lock()
+                .lookup_parent_path_at(&opened_files, &dir, path, true, &creds)?;
+
+        let parent_dir = parent_dir.inode.as_dir()?;
+        creds.check_access(&parent_dir.stat()?, AccessMode::W_OK | AccessMode::X_OK)?;
+        parent_dir.create_dir(name, mode, creds.euid, creds.egid)?;
+        Ok(0)
+    }
+}
@@ -2,7 +2,11 @@ use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
 use kerla_utils::alignment::is_aligned;
 
 use crate::{
-    ctypes::*, fs::opened_file::Fd, mm::vm::VmAreaType, prelude::*, process::current_process,
+    ctypes::*,
+    fs::opened_file::Fd,
+    mm::{page_cache, vm::VmAreaType},
+    prelude::*,
+    process::current_process,
     syscalls::SyscallHandler,
 };
 
@@ -11,13 +15,11 @@ impl<'a> SyscallHandler<'a> {
         &mut self,
         addr_hint: Option<UserVAddr>,
         len: c_size,
-        _prot: MMapProt,
+        prot: MMapProt,
         flags: MMapFlags,
         fd: Fd,
         offset: c_off,
     ) -> Result<isize> {
-        // TODO: Respect `prot`.
-
         if !is_aligned(len as usize, PAGE_SIZE) {
             return Err(Errno::EINVAL.into());
         }
@@ -29,17 +31,22 @@ impl<'a> SyscallHandler<'a> {
         let area_type = if flags.contains(MMapFlags::MAP_ANONYMOUS) {
             VmAreaType::Anonymous
         } else {
-            let file = current_process()
-                .opened_files()
-                .lock()
-                .get(fd)?
-                .as_file()?
-                .clone();
+            let opened_file = current_process().get_opened_file_by_fd(fd)?;
+            let file = opened_file.as_file()?.clone();
+            let shared = flags.contains(MMapFlags::MAP_SHARED);
+            let writable = opened_file.options().writable;
+
+            // Writes to a shared mapping go to the file.
+            if shared && prot.contains(MMapProt::PROT_WRITE) && !writable {
+                return Err(Errno::EACCES.into());
+            }
 
             VmAreaType::File {
                 file,
                 offset: offset as usize,
                 file_size: len as usize,
+                shared,
+                writable,
             }
         };
 
@@ -47,20 +54,30 @@ impl<'a> SyscallHandler<'a> {
         let current = current_process();
         let vm_ref = current.vm();
         let mut vm = vm_ref.as_ref().unwrap().lock();
+        let mut unmapped_files = Vec::new();
         let mapped_uaddr = match addr_hint {
-            Some(addr_hint) if vm.is_free_vaddr_range(addr_hint, len as usize) => addr_hint,
-            Some(_) => {
-                // [addr_hint, addr_hint + len) is already in use or invalid.
-                if flags.contains(MMapFlags::MAP_FIXED) {
+            Some(addr_hint) if flags.contains(MMapFlags::MAP_FIXED) => {
+                if !is_aligned(addr_hint.value(), PAGE_SIZE) {
                     return Err(Errno::EINVAL.into());
                 }
 
-                vm.alloc_vaddr_range(len as usize)?
+                // Replace existing mappings in the range.
+                unmapped_files = vm.unmap(addr_hint, len as usize)?;
+                addr_hint
             }
-            None => vm.alloc_vaddr_range(len as usize)?,
+            Some(addr_hint) if vm.is_free_vaddr_range(addr_hint, len as usize) => addr_hint,
+            _ => vm.alloc_vaddr_range(len as usize)?,
         };
 
-        vm.add_vm_area(mapped_uaddr, len as usize, area_type)?;
+        let result = vm.add_vm_area(mapped_uaddr, len as usize, area_type, prot);
+        drop(vm);
+
+        // Don't hold the lock while writing back to files.
+        for range in unmapped_files {
+            page_cache::release(&range.file, range.offset, range.len)?;
+        }
+
+        result?;
         Ok(mapped_uaddr.value() as isize)
     }
 }
@@ -1,77 +1,170 @@
 use crate::{
+    arch,
     ctypes::*,
     fs::path::PathBuf,
     fs::{
-        opened_file::{Fd, OpenFlags},
+        inode::RenameFlags,
+        opened_file::{Fd, OpenFlags, PathComponent},
         path::Path,
-        stat::FileMode,
+        stat::{FileMode, GId, UId},
     },
     net::{RecvFromFlags, SendToFlags},
-    process::{current_process, process_group::PgId, PId, Process},
+    process::{self, credentials::Credentials, current_process, process_group::PgId, PId, Process},
     result::{Errno, Error, Result},
-    syscalls::{getrandom::GetRandomFlags, wait4::WaitOptions},
+    syscalls::{getrandom::GetRandomFlags, msync::MSyncFlags, wait4::WaitOptions},
     timer::Timeval,
     user_buffer::UserCStr,
 };
+use alloc::sync::Arc;
 use bitflags::bitflags;
 use kerla_runtime::{address::UserVAddr, arch::PtRegs};
 
 mod accept;
+mod access;
+mod alarm;
 mod arch_prctl;
 mod bind;
 mod brk;
 mod chdir;
 mod chmod;
+mod chown;
 mod clock_gettime;
+mod clock_nanosleep;
+mod clone;
 mod close;
 mod connect;
 mod dup2;
 mod execve;
 mod exit;
 mod exit_group;
+mod faccessat;
+mod fallocate;
+mod fchmod;
+mod fchmodat;
+mod fchown;
 mod fcntl;
 mod fork;
 mod fstat;
 mod fsync;
+mod ftruncate;
+mod futex;
 mod getcwd;
 mod getdents64;
+mod getegid;
+mod geteuid;
+mod getgid;
+mod getgroups;
+mod getitimer;
 mod getpeername;
 mod getpgid;
 mod getpid;
 mod getppid;
+mod getpriority;
 mod getrandom;
+mod getresgid;
+mod getresuid;
+mod getrlimit;
+mod getrusage;
+mod getsid;
 mod getsockname;
 mod getsockopt;
 mod gettid;
+mod getuid;
 mod ioctl;
 mod kill;
+mod lchown;
 mod link;
 mod linkat;
 mod listen;
+mod lseek;
 mod lstat;
 mod mkdir;
+mod mkdirat;
 mod mmap;
+mod mount;
+mod mprotect;
+mod msync;
+mod munmap;
+mod nanosleep;
+mod newfstatat;
 mod open;
+mod openat;
+mod pidfd_open;
 mod pipe;
 mod poll;
+mod prctl;
+mod pread64;
+mod preadv;
+mod prlimit64;
+mod ptrace;
+mod pwrite64;
+mod pwritev;
 mod read;
 mod readlink;
+mod readlinkat;
+mod readv;
 mod reboot;
 mod recvfrom;
+mod rename;
+mod renameat2;
+mod rmdir;
 mod rt_sigaction;
+mod rt_sigpending;
 mod rt_sigprocmask;
 mod rt_sigreturn;
+mod rt_sigsuspend;
+mod rt_sigtimedwait;
+mod sched_get_priority_max;
+mod sched_get_priority_min;
+mod sched_getparam;
+mod sched_getscheduler;
+mod sched_setparam;
+mod sched_setscheduler;
+mod sched_yield;
+mod seccomp;
 mod select;
 mod sendto;
+mod set_robust_list;
 mod set_tid_address;
+mod setdomainname;
+mod setgid;
+mod setgroups;
+mod sethostname;
+mod setitimer;
+mod setns;
 mod setpgid;
+mod setpriority;
+mod setregid;
+mod setresgid;
+mod setresuid;
+mod setreuid;
+mod setrlimit;
+mod setsid;
+mod setuid;
 mod shutdown;
+mod sigaltstack;
 mod socket;
 mod stat;
+mod statx;
+mod symlink;
+mod symlinkat;
 mod syslog;
+mod timer_create;
+mod timer_delete;
+mod timer_getoverrun;
+mod timer_gettime;
+mod timer_settime;
+mod times;
+pub mod tracer;
+mod truncate;
 mod uname;
+mod unlink;
+mod unlinkat;
+mod unshare;
+mod utimensat;
 mod utimes;
 mod wait4;
+mod waitid;
 mod write;
 mod writev;
 
@@ -92,7 +185,19 @@ impl CwdOrFd {
 
 bitflags! {
     pub struct AtFlags: c_int {
+        const AT_SYMLINK_NOFOLLOW = 0x100;
+        const AT_EACCESS = 0x200;
+        /// `unlinkat(2)`: remove a directory. Shares the value with `AT_EACCESS`.
+        const AT_REMOVEDIR = 0x200;
         const AT_SYMLINK_FOLLOW = 0x400;
+        /// Ignored: we don't have automounts.
+        const AT_NO_AUTOMOUNT = 0x800;
+        /// Operate on the file referred by the directory fd if the path is
+        /// empty.
+        const AT_EMPTY_PATH = 0x1000;
+        /// `statx(2)`: ignored since we don't have network file systems.
+        const AT_STATX_FORCE_SYNC = 0x2000;
+        const AT_STATX_DONT_SYNC = 0x4000;
     }
 }
 
@@ -113,16 +218,29 @@ const SYS_STAT: usize = 4;
 const SYS_FSTAT: usize = 5;
 const SYS_LSTAT: usize = 6;
 const SYS_POLL: usize = 7;
+const SYS_LSEEK: usize = 8;
 const SYS_MMAP: usize = 9;
+const SYS_MPROTECT: usize = 10;
+const SYS_MUNMAP: usize = 11;
 const SYS_BRK: usize = 12;
 const SYS_RT_SIGACTION: usize = 13;
 const SYS_RT_SIGPROCMASK: usize = 14;
 const SYS_RT_SIGRETURN: usize = 15;
 const SYS_IOCTL: usize = 16;
+const SYS_PREAD64: usize = 17;
+const SYS_PWRITE64: usize = 18;
+const SYS_READV: usize = 19;
 const SYS_WRITEV: usize = 20;
+const SYS_ACCESS: usize = 21;
 const SYS_PIPE: usize = 22;
 const SYS_SELECT: usize = 23;
+const SYS_SCHED_YIELD: usize = 24;
+const SYS_MSYNC: usize = 26;
 const SYS_DUP2: usize = 33;
+const SYS_NANOSLEEP: usize = 35;
+const SYS_GETITIMER: usize = 36;
+const SYS_ALARM: usize = 37;
+const SYS_SETITIMER: usize = 38;
 const SYS_GETPID: usize = 39;
 const SYS_SOCKET: usize = 41;
 const SYS_CONNECT: usize = 42;
@@ -135,7 +253,9 @@ const SYS_LISTEN: usize = 50;
 const SYS_GETSOCKNAME: usize = 51;
 const SYS_GETPEERNAME: usize = 52;
 const SYS_GETSOCKOPT: usize = 55;
+const SYS_CLONE: usize = 56;
 const SYS_FORK: usize = 57;
+const SYS_VFORK: usize = 58;
 const SYS_EXECVE: usize = 59;
 const SYS_EXIT: usize = 60;
 const SYS_WAIT4: usize = 61;
@@ -143,38 +263,152 @@ const SYS_KILL: usize = 62;
 const SYS_UNAME: usize = 63;
 const SYS_FCNTL: usize = 72;
 const SYS_FSYNC: usize = 74;
+const SYS_TRUNCATE: usize = 76;
+const SYS_FTRUNCATE: usize = 77;
 const SYS_GETCWD: usize = 79;
 const SYS_CHDIR: usize = 80;
+const SYS_RENAME: usize = 82;
 const SYS_MKDIR: usize = 83;
+const SYS_RMDIR: usize = 84;
 const SYS_LINK: usize = 86;
+const SYS_UNLINK: usize = 87;
+const SYS_SYMLINK: usize = 88;
 const SYS_READLINK: usize = 89;
 const SYS_CHMOD: usize = 90;
+const SYS_FCHMOD: usize = 91;
 const SYS_CHOWN: usize = 92;
+const SYS_FCHOWN: usize = 93;
+const SYS_LCHOWN: usize = 94;
+const SYS_GETRLIMIT: usize = 97;
+const SYS_GETRUSAGE: usize = 98;
+const SYS_TIMES: usize = 100;
+const SYS_PTRACE: usize = 101;
 const SYS_GETUID: usize = 102;
 const SYS_SYSLOG: usize = 103;
+const SYS_GETGID: usize = 104;
 const SYS_SETUID: usize = 105;
 const SYS_SETGID: usize = 106;
 const SYS_GETEUID: usize = 107;
+const SYS_GETEGID: usize = 108;
 const SYS_SETPGID: usize = 109;
 const SYS_GETPPID: usize = 110;
-const SYS_GETPGID: usize = 121;
+const SYS_SETSID: usize = 112;
+const SYS_SETREUID: usize = 113;
+const SYS_SETREGID: usize = 114;
+const SYS_GETGROUPS: usize = 115;
 const SYS_SETGROUPS: usize = 116;
+const SYS_SETRESUID: usize = 117;
+const SYS_GETRESUID: usize = 118;
+const SYS_SETRESGID: usize = 119;
+const SYS_GETRESGID: usize = 120;
+const SYS_GETPGID: usize = 121;
+const SYS_GETSID: usize = 124;
+const SYS_RT_SIGPENDING: usize = 127;
+const SYS_RT_SIGTIMEDWAIT: usize = 128;
+const SYS_RT_SIGSUSPEND: usize = 130;
+const SYS_SIGALTSTACK: usize = 131;
+const SYS_GETPRIORITY: usize = 140;
+const SYS_SETPRIORITY: usize = 141;
+const SYS_SCHED_SETPARAM: usize = 142;
+const SYS_SCHED_GETPARAM: usize = 143;
+const SYS_SCHED_SETSCHEDULER: usize = 144;
+const SYS_SCHED_GETSCHEDULER: usize = 145;
+const SYS_SCHED_GET_PRIORITY_MAX: usize = 146;
+const SYS_SCHED_GET_PRIORITY_MIN: usize = 147;
+const SYS_PRCTL: usize = 157;
 const SYS_ARCH_PRCTL: usize = 158;
+const SYS_SETRLIMIT: usize = 160;
+const SYS_MOUNT: usize = 165;
 const SYS_REBOOT: usize = 169;
+const SYS_SETHOSTNAME: usize = 170;
+const SYS_SETDOMAINNAME: usize = 171;
 const SYS_GETTID: usize = 186;
+const SYS_FUTEX: usize = 202;
 const SYS_GETDENTS64: usize = 217;
 const SYS_SET_TID_ADDRESS: usize = 218;
+const SYS_TIMER_CREATE: usize = 222;
+const SYS_TIMER_SETTIME: usize = 223;
+const SYS_TIMER_GETTIME: usize = 224;
+const SYS_TIMER_GETOVERRUN: usize = 225;
+const SYS_TIMER_DELETE: usize = 226;
 const SYS_CLOCK_GETTIME: usize = 228;
+const SYS_CLOCK_NANOSLEEP: usize = 230;
 const SYS_EXIT_GROUP: usize = 231;
 const SYS_UTIMES: usize = 235;
+const SYS_WAITID: usize = 247;
+const SYS_OPENAT: usize = 257;
+const SYS_MKDIRAT: usize = 258;
+const SYS_NEWFSTATAT: usize = 262;
+const SYS_UNLINKAT: usize = 263;
+const SYS_RENAMEAT: usize = 264;
 const SYS_LINKAT: usize = 265;
+const SYS_SYMLINKAT: usize = 266;
+const SYS_READLINKAT: usize = 267;
+const SYS_FCHMODAT: usize = 268;
+const SYS_FACCESSAT: usize = 269;
+const SYS_UNSHARE: usize = 272;
+const SYS_SET_ROBUST_LIST: usize = 273;
+const SYS_UTIMENSAT: usize = 280;
+const SYS_FALLOCATE: usize = 285;
+const SYS_PREADV: usize = 295;
+const SYS_PWRITEV: usize = 296;
+const SYS_PRLIMIT64: usize = 302;
+const SYS_SETNS: usize = 308;
+const SYS_RENAMEAT2: usize = 316;
+const SYS_SECCOMP: usize = 317;
 const SYS_GETRANDOM: usize = 318;
+const SYS_STATX: usize = 332;
+const SYS_PIDFD_OPEN: usize = 434;
+const SYS_FACCESSAT2: usize = 439;
+
+/// Parses a user ID argument. `-1` means the ID is unchanged.
+fn uid_from_user(value: usize) -> Option<UId> {
+    match value as u32 {
+        u32::MAX => None,
+        uid => Some(UId::new(uid)),
+    }
+}
+
+/// Parses a group ID argument. `-1` means the ID is unchanged.
+fn gid_from_user(value: usize) -> Option<GId> {
+    match value as u32 {
+        u32::MAX => None,
+        gid => Some(GId::new(gid)),
+    }
+}
 
 fn resolve_path(uaddr: usize) -> Result<PathBuf> {
     const PATH_MAX: usize = 512;
     Ok(Path::new(UserCStr::new(UserVAddr::new_nonnull(uaddr)?, PATH_MAX)?.as_str()).to_path_buf())
 }
 
+/// Resolves `path` from `dir` in `*at` system calls. `AT_SYMLINK_NOFOLLOW` and
+/// `AT_EMPTY_PATH` in `flags` are respected.
+fn lookup_path_at(
+    dir: &CwdOrFd,
+    path: &Path,
+    flags: AtFlags,
+    creds: &Credentials,
+) -> Result<Arc<PathComponent>> {
+    let current = current_process();
+    let root_fs = current.root_fs().clone();
+    let root_fs = root_fs.lock();
+    let opened_files = current.opened_files().lock();
+    if path.is_empty() && flags.contains(AtFlags::AT_EMPTY_PATH) {
+        root_fs.lookup_cwd_or_fd(&opened_files, dir)
+    } else {
+        let follow_symlink = !flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW);
+        root_fs.lookup_path_at(&opened_files, dir, path, follow_symlink, creds)
+    }
+}
+
+/// Translates a process group ID in the current PID namespace into the one used
+/// in the kernel. The returned ID matches no groups if it's not in the
+/// namespace.
+fn global_pgid(pgid: c_int) -> PgId {
+    PgId::new(current_process().pid_ns().global_id(pgid).unwrap_or(0))
+}
+
 pub struct SyscallHandler<'a> {
     pub frame: &'a mut PtRegs,
 }
@@ -195,6 +429,23 @@ impl<'a> SyscallHandler<'a> {
         a6: usize,
         n: usize,
     ) -> Result<isize> {
+        current_process().enter_kernel();
+
+        // Syscall-entry-stop: the tracer may modify the system call and its
+        // arguments.
+        let (mut n, mut args) = if Process::ptrace_syscall_stop(self.frame, true) {
+            arch::syscall_number_and_args(self.frame)
+        } else {
+            (n, [a1, a2, a3, a4, a5, a6])
+        };
+
+        // Seccomp filters may skip the system call with a return value, or
+        // let the tracer modify it.
+        let skipped = process::seccomp::filter_syscall(self.frame, &mut n, &mut args);
+        let [a1, a2, a3, a4, a5, a6] = args;
+
+        tracer::trace_syscall_entry(n, &args);
+
         if !((n == 0 && a1 == 0)
             || (n == 1) && (a1 == 1)
             || (n == 1) && (a1 == 2)
@@ -216,16 +467,51 @@ impl<'a> SyscallHandler<'a> {
             );
         }
 
-        let ret = self.do_dispatch(a1, a2, a3, a4, a5, a6, n).map_err(|err| {
-            debug_warn!("{}: error: {:?}", syscall_name_by_number(n), err);
-            err
-        });
+        let ret = match skipped {
+            Some(value) => Ok(value),
+            None => self.do_dispatch(a1, a2, a3, a4, a5, a6, n).map_err(|err| {
+                debug_warn!("{}: error: {:?}", syscall_name_by_number(n), err);
+                err
+            }),
+        };
+
+        tracer::trace_syscall_exit(n, &ret);
+
+        let value = match ret {
+            Ok(value) => value,
+            Err(err) => -(err.errno() as isize),
+        };
+        arch::set_syscall_return_value(self.frame, value);
+
+        // Syscall-exit-stop: the tracer may modify the return value.
+        Process::ptrace_syscall_stop(self.frame, false);
 
         if let Err(err) = Process::try_delivering_signal(self.frame) {
             debug_warn!("failed to setup the signal stack: {:?}", err);
         }
 
-        ret
+        let current = current_process();
+        current.exit_kernel();
+
+        // The return value might have been updated: the system call is
+        // interrupted or restarted.
+        let value = match arch::syscall_return_value(self.frame) {
+            // A restart request is consumed in try_delivering_signal.
+            Some(value) if value == -(Errno::ERESTARTSYS as isize) => -(Errno::EINTR as isize),
+            Some(value) if value == -(Errno::ERESTARTNOHAND as isize) => -(Errno::EINTR as isize),
+            Some(value) => value,
+            // rt_sigreturn(2): the restored RAX.
+            None => self.frame.rax as isize,
+        };
+
+        // The tracer might have modified registers which are not restored
+        // on the fast return path.
+        if current.take_ptrace_stopped() {
+            arch::set_syscall_return_value(self.frame, value);
+            arch::force_full_restore(self.frame);
+        }
+
+        Ok(value)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -249,6 +535,32 @@ impl<'a> SyscallHandler<'a> {
             SYS_READ => self.sys_read(Fd::new(a1 as i32), UserVAddr::new_nonnull(a2)?, a3),
             SYS_WRITE => self.sys_write(Fd::new(a1 as i32), UserVAddr::new_nonnull(a2)?, a3),
             SYS_WRITEV => self.sys_writev(Fd::new(a1 as i32), UserVAddr::new_nonnull(a2)?, a3),
+            SYS_READV => self.sys_readv(Fd::new(a1 as i32), UserVAddr::new_nonnull(a2)?, a3),
+            SYS_PREAD64 => self.sys_pread64(
+                Fd::new(a1 as i32),
+                UserVAddr::new_nonnull(a2)?,
+                a3,
+                a4 as isize,
+            ),
+            SYS_PWRITE64 => self.sys_pwrite64(
+                Fd::new(a1 as i32),
+                UserVAddr::new_nonnull(a2)?,
+                a3,
+                a4 as isize,
+            ),
+            SYS_PREADV => self.sys_preadv(
+                Fd::new(a1 as i32),
+                UserVAddr::new_nonnull(a2)?,
+                a3,
+                a4 as isize,
+            ),
+            SYS_PWRITEV => self.sys_pwritev(
+                Fd::new(a1 as i32),
+                UserVAddr::new_nonnull(a2)?,
+                a3,
+                a4 as isize,
+            ),
+            SYS_LSEEK => self.sys_lseek(Fd::new(a1 as i32), a2 as isize, a3 as c_int),
             SYS_MMAP => self.sys_mmap(
                 UserVAddr::new(a1),
                 a2 as c_size,
@@ -257,11 +569,96 @@ impl<'a> SyscallHandler<'a> {
                 Fd::new(a5 as i32),
                 a6 as c_off,
             ),
+            SYS_MPROTECT => self.sys_mprotect(
+                UserVAddr::new_nonnull(a1)?,
+                a2 as c_size,
+                bitflags_from_user!(MMapProt, a3 as c_int)?,
+            ),
+            SYS_MUNMAP => self.sys_munmap(UserVAddr::new_nonnull(a1)?, a2 as c_size),
+            SYS_MSYNC => self.sys_msync(
+                UserVAddr::new_nonnull(a1)?,
+                a2 as c_size,
+                bitflags_from_user!(MSyncFlags, a3 as c_int)?,
+            ),
             SYS_STAT => self.sys_stat(&resolve_path(a1)?, UserVAddr::new_nonnull(a2)?),
             SYS_FSTAT => self.sys_fstat(Fd::new(a1 as c_int), UserVAddr::new_nonnull(a2)?),
             SYS_LSTAT => self.sys_lstat(&resolve_path(a1)?, UserVAddr::new_nonnull(a2)?),
             SYS_FCNTL => self.sys_fcntl(Fd::new(a1 as i32), a2 as c_int, a3),
             SYS_LINK => self.sys_link(&resolve_path(a1)?, &resolve_path(a2)?),
+            SYS_UNLINK => self.sys_unlink(&resolve_path(a1)?),
+            SYS_SYMLINK => self.sys_symlink(&resolve_path(a1)?, &resolve_path(a2)?),
+            SYS_SYMLINKAT => self.sys_symlinkat(
+                &resolve_path(a1)?,
+                CwdOrFd::parse(a2 as c_int),
+                &resolve_path(a3)?,
+            ),
+            SYS_OPENAT => self.sys_openat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                bitflags_from_user!(OpenFlags, a3 as i32)?,
+                FileMode::new(a4 as u32),
+            ),
+            SYS_NEWFSTATAT => self.sys_newfstatat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                UserVAddr::new_nonnull(a3)?,
+                AtFlags::from_bits(a4 as c_int).ok_or_else(|| Error::new(Errno::EINVAL))?,
+            ),
+            SYS_STATX => self.sys_statx(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                bitflags_from_user!(AtFlags, a3 as c_int)?,
+                a4 as c_uint,
+                UserVAddr::new_nonnull(a5)?,
+            ),
+            SYS_MKDIRAT => self.sys_mkdirat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                FileMode::new(a3 as u32),
+            ),
+            SYS_UNLINKAT => self.sys_unlinkat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                bitflags_from_user!(AtFlags, a3 as c_int)?,
+            ),
+            SYS_READLINKAT => self.sys_readlinkat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                UserVAddr::new_nonnull(a3)?,
+                a4,
+            ),
+            SYS_FCHMODAT => self.sys_fchmodat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                FileMode::new(a3 as u32),
+            ),
+            SYS_UTIMENSAT => {
+                let path = match a2 {
+                    0 => None,
+                    _ => Some(resolve_path(a2)?),
+                };
+                self.sys_utimensat(
+                    CwdOrFd::parse(a1 as c_int),
+                    path.as_deref(),
+                    UserVAddr::new(a3),
+                    bitflags_from_user!(AtFlags, a4 as c_int)?,
+                )
+            }
+            SYS_RENAME => self.sys_rename(&resolve_path(a1)?, &resolve_path(a2)?),
+            SYS_RENAMEAT => self.sys_renameat2(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                CwdOrFd::parse(a3 as c_int),
+                &resolve_path(a4)?,
+                RenameFlags::empty(),
+            ),
+            SYS_RENAMEAT2 => self.sys_renameat2(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                CwdOrFd::parse(a3 as c_int),
+                &resolve_path(a4)?,
+                bitflags_from_user!(RenameFlags, a5 as c_uint)?,
+            ),
             SYS_LINKAT => self.sys_linkat(
                 CwdOrFd::parse(a1 as c_int),
                 &resolve_path(a2)?,
@@ -271,8 +668,31 @@ impl<'a> SyscallHandler<'a> {
             ),
             SYS_READLINK => self.sys_readlink(&resolve_path(a1)?, UserVAddr::new_nonnull(a2)?, a3),
             SYS_CHMOD => self.sys_chmod(&resolve_path(a1)?, FileMode::new(a2 as u32)),
-            SYS_CHOWN => Ok(0), // TODO:
+            SYS_FCHMOD => self.sys_fchmod(Fd::new(a1 as c_int), FileMode::new(a2 as u32)),
+            SYS_CHOWN => self.sys_chown(&resolve_path(a1)?, uid_from_user(a2), gid_from_user(a3)),
+            SYS_FCHOWN => {
+                self.sys_fchown(Fd::new(a1 as c_int), uid_from_user(a2), gid_from_user(a3))
+            }
+            SYS_LCHOWN => self.sys_lchown(&resolve_path(a1)?, uid_from_user(a2), gid_from_user(a3)),
+            SYS_ACCESS => self.sys_access(&resolve_path(a1)?, a2 as c_int),
+            SYS_FACCESSAT => self.sys_faccessat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                a3 as c_int,
+                AtFlags::empty(),
+            ),
+            SYS_FACCESSAT2 => self.sys_faccessat(
+                CwdOrFd::parse(a1 as c_int),
+                &resolve_path(a2)?,
+                a3 as c_int,
+                bitflags_from_user!(AtFlags, a4 as c_int)?,
+            ),
             SYS_FSYNC => self.sys_fsync(Fd::new(a1 as i32)),
+            SYS_TRUNCATE => self.sys_truncate(&resolve_path(a1)?, a2 as isize),
+            SYS_FTRUNCATE => self.sys_ftruncate(Fd::new(a1 as i32), a2 as isize),
+            SYS_FALLOCATE => {
+                self.sys_fallocate(Fd::new(a1 as i32), a2 as c_int, a3 as isize, a4 as isize)
+            }
             SYS_UTIMES => self.sys_utimes(&resolve_path(a1)?, UserVAddr::new(a2)),
             SYS_GETDENTS64 => {
                 self.sys_getdents64(Fd::new(a1 as i32), UserVAddr::new_nonnull(a2)?, a3)
@@ -291,34 +711,105 @@ impl<'a> SyscallHandler<'a> {
             SYS_GETCWD => self.sys_getcwd(UserVAddr::new_nonnull(a1)?, a2 as c_size),
             SYS_CHDIR => self.sys_chdir(&resolve_path(a1)?),
             SYS_MKDIR => self.sys_mkdir(&resolve_path(a1)?, FileMode::new(a2 as u32)),
+            SYS_RMDIR => self.sys_rmdir(&resolve_path(a1)?),
+            SYS_PRCTL => self.sys_prctl(a1 as c_int, [a2, a3, a4, a5]),
             SYS_ARCH_PRCTL => self.sys_arch_prctl(a1 as i32, UserVAddr::new_nonnull(a2)?),
             SYS_BRK => self.sys_brk(UserVAddr::new(a1)),
             SYS_IOCTL => self.sys_ioctl(Fd::new(a1 as i32), a2, a3),
             SYS_GETPID => self.sys_getpid(),
             SYS_GETPGID => self.sys_getpgid(PId::new(a1 as i32)),
-            SYS_GETUID => Ok(0),    // TODO:
-            SYS_GETEUID => Ok(0),   // TODO:
-            SYS_SETUID => Ok(0),    // TODO:
-            SYS_SETGID => Ok(0),    // TODO:
-            SYS_SETGROUPS => Ok(0), // TODO:
+            SYS_GETUID => self.sys_getuid(),
+            SYS_GETEUID => self.sys_geteuid(),
+            SYS_GETGID => self.sys_getgid(),
+            SYS_GETEGID => self.sys_getegid(),
+            SYS_SETUID => self.sys_setuid(UId::new(a1 as u32)),
+            SYS_SETGID => self.sys_setgid(GId::new(a1 as u32)),
+            SYS_SETREUID => self.sys_setreuid(uid_from_user(a1), uid_from_user(a2)),
+            SYS_SETREGID => self.sys_setregid(gid_from_user(a1), gid_from_user(a2)),
+            SYS_SETRESUID => {
+                self.sys_setresuid(uid_from_user(a1), uid_from_user(a2), uid_from_user(a3))
+            }
+            SYS_SETRESGID => {
+                self.sys_setresgid(gid_from_user(a1), gid_from_user(a2), gid_from_user(a3))
+            }
+            SYS_GETRESUID => self.sys_getresuid(
+                UserVAddr::new_nonnull(a1)?,
+                UserVAddr::new_nonnull(a2)?,
+                UserVAddr::new_nonnull(a3)?,
+            ),
+            SYS_GETRESGID => self.sys_getresgid(
+                UserVAddr::new_nonnull(a1)?,
+                UserVAddr::new_nonnull(a2)?,
+                UserVAddr::new_nonnull(a3)?,
+            ),
+            SYS_GETGROUPS => self.sys_getgroups(a1 as c_int, UserVAddr::new(a2)),
+            SYS_SETGROUPS => self.sys_setgroups(a1, UserVAddr::new(a2)),
+            SYS_GETRUSAGE => self.sys_getrusage(a1 as c_int, UserVAddr::new_nonnull(a2)?),
+            SYS_TIMES => self.sys_times(UserVAddr::new(a1)),
+            SYS_PTRACE => self.sys_ptrace(a1 as c_long, PId::new(a2 as i32), a3, a4),
+            SYS_GETRLIMIT => self.sys_getrlimit(a1 as c_int, UserVAddr::new_nonnull(a2)?),
+            SYS_SETRLIMIT => self.sys_setrlimit(a1 as c_int, UserVAddr::new_nonnull(a2)?),
+            SYS_PRLIMIT64 => self.sys_prlimit64(
+                PId::new(a1 as i32),
+                a2 as c_int,
+                UserVAddr::new(a3),
+                UserVAddr::new(a4),
+            ),
             SYS_SETPGID => self.sys_setpgid(PId::new(a1 as i32), PgId::new(a2 as i32)),
             SYS_GETPPID => self.sys_getppid(),
-            SYS_SET_TID_ADDRESS => self.sys_set_tid_address(UserVAddr::new_nonnull(a1)?),
+            SYS_SETSID => self.sys_setsid(),
+            SYS_GETSID => self.sys_getsid(PId::new(a1 as i32)),
+            SYS_SET_TID_ADDRESS => self.sys_set_tid_address(UserVAddr::new(a1)),
             SYS_PIPE => self.sys_pipe(UserVAddr::new_nonnull(a1)?),
-            SYS_RT_SIGACTION => self.sys_rt_sigaction(a1 as c_int, a2, UserVAddr::new(a3)),
+            SYS_RT_SIGACTION => {
+                self.sys_rt_sigaction(a1 as c_int, UserVAddr::new(a2), UserVAddr::new(a3), a4)
+            }
             SYS_RT_SIGRETURN => self.sys_rt_sigreturn(),
+            SYS_RT_SIGPENDING => self.sys_rt_sigpending(UserVAddr::new_nonnull(a1)?, a2),
+            SYS_RT_SIGTIMEDWAIT => self.sys_rt_sigtimedwait(
+                UserVAddr::new_nonnull(a1)?,
+                UserVAddr::new(a2),
+                UserVAddr::new(a3),
+                a4,
+            ),
+            SYS_RT_SIGSUSPEND => self.sys_rt_sigsuspend(UserVAddr::new_nonnull(a1)?, a2),
+            SYS_SIGALTSTACK => self.sys_sigaltstack(UserVAddr::new(a1), UserVAddr::new(a2)),
             SYS_EXECVE => self.sys_execve(
                 &resolve_path(a1)?,
                 UserVAddr::new_nonnull(a2)?,
                 UserVAddr::new_nonnull(a3)?,
             ),
+            SYS_CLONE => self.sys_clone(
+                bitflags_from_user!(CloneFlags, (a1 & !0xff) as c_int)?,
+                // The lowest byte is the signal sent to the parent on exit.
+                (a1 & 0xff) as c_int,
+                UserVAddr::new(a2),
+                UserVAddr::new(a3),
+                UserVAddr::new(a4),
+                UserVAddr::new(a5),
+            ),
             SYS_FORK => self.sys_fork(),
+            SYS_VFORK => self.sys_clone(
+                CloneFlags::CLONE_VM | CloneFlags::CLONE_VFORK,
+                process::signal::SIGCHLD,
+                None,
+                None,
+                None,
+                None,
+            ),
             SYS_WAIT4 => self.sys_wait4(
                 PId::new(a1 as i32),
                 UserVAddr::new(a2),
                 bitflags_from_user!(WaitOptions, a3 as c_int)?,
                 UserVAddr::new(a4),
             ),
+            SYS_WAITID => self.sys_waitid(
+                a1 as c_int,
+                a2 as c_int,
+                UserVAddr::new(a3),
+                bitflags_from_user!(WaitOptions, a4 as c_int)?,
+                UserVAddr::new(a5),
+            ),
             SYS_KILL => self.sys_kill(PId::new(a1 as i32), a2 as c_int),
             SYS_EXIT => self.sys_exit(a1 as i32),
             SYS_EXIT_GROUP => self.sys_exit_group(a1 as i32),
@@ -367,6 +858,33 @@ impl<'a> SyscallHandler<'a> {
             SYS_CLOCK_GETTIME => {
                 self.sys_clock_gettime(a1 as c_clockid, UserVAddr::new_nonnull(a2)?)
             }
+            SYS_NANOSLEEP => self.sys_nanosleep(UserVAddr::new_nonnull(a1)?, UserVAddr::new(a2)),
+            SYS_CLOCK_NANOSLEEP => self.sys_clock_nanosleep(
+                a1 as c_clockid,
+                a2 as c_int,
+                UserVAddr::new_nonnull(a3)?,
+                UserVAddr::new(a4),
+            ),
+            SYS_GETITIMER => self.sys_getitimer(a1 as c_int, UserVAddr::new_nonnull(a2)?),
+            SYS_SETITIMER => {
+                self.sys_setitimer(a1 as c_int, UserVAddr::new_nonnull(a2)?, UserVAddr::new(a3))
+            }
+            SYS_ALARM => self.sys_alarm(a1 as c_uint),
+            SYS_TIMER_CREATE => self.sys_timer_create(
+                a1 as c_clockid,
+                UserVAddr::new(a2),
+                UserVAddr::new_nonnull(a3)?,
+            ),
+            SYS_TIMER_SETTIME => self.sys_timer_settime(
+                a1 as c_int,
+                a2 as c_int,
+                UserVAddr::new_nonnull(a3)?,
+                UserVAddr::new(a4),
+            ),
+            SYS_TIMER_GETTIME => self.sys_timer_gettime(a1 as c_int, UserVAddr::new_nonnull(a2)?),
+            SYS_TIMER_GETOVERRUN => self.sys_timer_getoverrun(a1 as c_int),
+            SYS_TIMER_DELETE => self.sys_timer_delete(a1 as c_int),
+            SYS_SECCOMP => self.sys_seccomp(a1 as c_uint, a2 as c_uint, UserVAddr::new(a3)),
             SYS_GETRANDOM => self.sys_getrandom(
                 UserVAddr::new_nonnull(a1)?,
                 a2,
@@ -374,7 +892,43 @@ impl<'a> SyscallHandler<'a> {
             ),
             SYS_SYSLOG => self.sys_syslog(a1 as c_int, UserVAddr::new(a2), a3 as c_int),
             SYS_REBOOT => self.sys_reboot(a1 as c_int, a2 as c_int, a3),
+            SYS_MOUNT => self.sys_mount(
+                &resolve_path(a2)?,
+                UserVAddr::new_nonnull(a3)?,
+                a4 as c_ulong,
+            ),
+            SYS_SETHOSTNAME => self.sys_sethostname(UserVAddr::new_nonnull(a1)?, a2 as c_int),
+            SYS_SETDOMAINNAME => self.sys_setdomainname(UserVAddr::new_nonnull(a1)?, a2 as c_int),
+            SYS_UNSHARE => self.sys_unshare(bitflags_from_user!(CloneFlags, a1 as c_int)?),
+            SYS_SETNS => self.sys_setns(Fd::new(a1 as c_int), a2 as c_int),
+            SYS_PIDFD_OPEN => self.sys_pidfd_open(PId::new(a1 as i32), a2 as c_int),
             SYS_GETTID => self.sys_gettid(),
+            SYS_FUTEX => self.sys_futex(
+                UserVAddr::new_nonnull(a1)?,
+                a2 as c_int,
+                a3 as u32,
+                a4,
+                UserVAddr::new(a5),
+                a6 as u32,
+            ),
+            SYS_SET_ROBUST_LIST => self.sys_set_robust_list(UserVAddr::new(a1), a2),
+            SYS_SCHED_YIELD => self.sys_sched_yield(),
+            SYS_GETPRIORITY => self.sys_getpriority(a1 as c_int, a2 as c_int),
+            SYS_SETPRIORITY => self.sys_setpriority(a1 as c_int, a2 as c_int, a3 as c_int),
+            SYS_SCHED_SETPARAM => {
+                self.sys_sched_setparam(PId::new(a1 as i32), UserVAddr::new_nonnull(a2)?)
+            }
+            SYS_SCHED_GETPARAM => {
+                self.sys_sched_getparam(PId::new(a1 as i32), UserVAddr::new_nonnull(a2)?)
+            }
+            SYS_SCHED_SETSCHEDULER => self.sys_sched_setscheduler(
+                PId::new(a1 as i32),
+                a2 as c_int,
+                UserVAddr::new_nonnull(a3)?,
+            ),
+            SYS_SCHED_GETSCHEDULER => self.sys_sched_getscheduler(PId::new(a1 as i32)),
+            SYS_SCHED_GET_PRIORITY_MAX => self.sys_sched_get_priority_max(a1 as c_int),
+            SYS_SCHED_GET_PRIORITY_MIN => self.sys_sched_get_priority_min(a1 as c_int),
             SYS_RT_SIGPROCMASK => {
                 self.sys_rt_sigprocmask(a1, UserVAddr::new(a2), UserVAddr::new(a3), a4)
             }
@@ -390,7 +944,7 @@ impl<'a> SyscallHandler<'a> {
     }
 }
 
-fn syscall_name_by_number(n: usize) -> &'static str {
+pub(crate) fn syscall_name_by_number(n: usize) -> &'static str {
     match n {
         0 => "read",
         1 => "write",
@@ -727,6 +1281,8 @@ fn syscall_name_by_number(n: usize) -> &'static str {
         332 => "statx",
         333 => "io_pgetevents",
         334 => "rseq",
+        434 => "pidfd_open",
+        439 => "faccessat2",
         _ => "(unknown)",
     }
 }
@@ -0,0 +1,54 @@
+use crate::{
+    ctypes::c_ulong,
+    fs::{
+        cgroupfs::CgroupFs, devfs::DEV_FS, file_system::FileSystem, path::Path, procfs::PROC_FS,
+        tmpfs::TmpFs,
+    },
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+    user_buffer::UserCStr,
+};
+use kerla_runtime::address::UserVAddr;
+
+const FS_TYPE_LEN_MAX: usize = 64;
+
+const MS_REMOUNT: c_ulong = 32;
+const MS_BIND: c_ulong = 4096;
+const MS_MOVE: c_ulong = 8192;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_mount(
+        &mut self,
+        target: &Path,
+        fs_type: UserVAddr,
+        flags: c_ulong,
+    ) -> Result<isize> {
+        let current = current_process();
+        let creds = current.credentials();
+        if !creds.is_privileged() {
+            return Err(Errno::EPERM.into());
+        }
+
+        if flags & (MS_REMOUNT | MS_BIND | MS_MOVE) != 0 {
+            debug_warn!("mount: unsupported flags: {:x}", flags);
+            return Err(Errno::EINVAL.into());
+        }
+
+        let fs_type = UserCStr::new(fs_type, FS_TYPE_LEN_MAX)?;
+        let fs: Arc<dyn FileSystem> = match fs_type.as_str() {
+            "tmpfs" => Arc::new(TmpFs::new()),
+            "proc" => PROC_FS.clone(),
+            "devtmpfs" => DEV_FS.clone(),
+            "cgroup2" => Arc::new(CgroupFs::new()),
+            _ => return Err(Errno::ENODEV.into()),
+        };
+
+        // The mount point is added to the current mount namespace.
+        let root_fs = current.root_fs().clone();
+        let mut root_fs = root_fs.lock();
+        let dir = root_fs.lookup_dir(target, &creds)?;
+        root_fs.mount(dir, fs)?;
+        Ok(0)
+    }
+}
@@ -0,0 +1,22 @@
+use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
+use kerla_utils::alignment::is_aligned;
+
+use crate::{ctypes::*, prelude::*, process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_mprotect(&mut self, addr: UserVAddr, len: c_size, prot: MMapProt) -> Result<isize> {
+        if !is_aligned(addr.value(), PAGE_SIZE) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let current = current_process();
+        let vm_ref = current.vm();
+        let mut vm = vm_ref.as_ref().unwrap().lock();
+        vm.protect(addr, len as usize, prot)?;
+        Ok(0)
+    }
+}
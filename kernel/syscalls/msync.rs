@@ -0,0 +1,71 @@
+use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
+use kerla_utils::alignment::{align_up, is_aligned};
+
+use crate::{
+    ctypes::*,
+    mm::{page_cache, vm::VmAreaType},
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+};
+use bitflags::bitflags;
+use core::cmp::{max, min};
+
+bitflags! {
+    pub struct MSyncFlags: c_int {
+        const MS_ASYNC = 1;
+        const MS_INVALIDATE = 2;
+        const MS_SYNC = 4;
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_msync(&mut self, addr: UserVAddr, len: c_size, flags: MSyncFlags) -> Result<isize> {
+        if !is_aligned(addr.value(), PAGE_SIZE)
+            || flags.contains(MSyncFlags::MS_ASYNC | MSyncFlags::MS_SYNC)
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let start = addr.value();
+        let end = start + align_up(len as usize, PAGE_SIZE);
+
+        // Collect ranges of shared file mappings in the range. Don't hold the
+        // lock while writing back to files.
+        let mut ranges = Vec::new();
+        {
+            let current = current_process();
+            let vm_ref = current.vm();
+            let vm = vm_ref.as_ref().unwrap().lock();
+            if !vm.is_mapped_vaddr_range(addr, end - start) {
+                return Err(Errno::ENOMEM.into());
+            }
+
+            for area in vm.vm_areas() {
+                if let VmAreaType::File {
+                    file,
+                    offset,
+                    shared: true,
+                    ..
+                } = area.area_type()
+                {
+                    let sync_start = max(start, area.start().value());
+                    let sync_end = min(end, area.end().value());
+                    if sync_start < sync_end {
+                        let offset_in_file = offset + (sync_start - area.start().value());
+                        ranges.push((file.clone(), offset_in_file, sync_end - sync_start));
+                    }
+                }
+            }
+        }
+
+        for (file, offset, len) in ranges {
+            page_cache::sync(&file, offset, len)?;
+            if flags.contains(MSyncFlags::MS_SYNC) {
+                file.fsync()?;
+            }
+        }
+
+        Ok(0)
+    }
+}
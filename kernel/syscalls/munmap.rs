@@ -0,0 +1,28 @@
+use kerla_runtime::{address::UserVAddr, arch::PAGE_SIZE};
+use kerla_utils::alignment::is_aligned;
+
+use crate::{
+    ctypes::*, mm::page_cache, prelude::*, process::current_process, syscalls::SyscallHandler,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_munmap(&mut self, addr: UserVAddr, len: c_size) -> Result<isize> {
+        if !is_aligned(addr.value(), PAGE_SIZE) || len == 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let file_ranges = {
+            let current = current_process();
+            let vm_ref = current.vm();
+            let mut vm = vm_ref.as_ref().unwrap().lock();
+            vm.unmap(addr, len as usize)?
+        };
+
+        // Don't hold the lock while writing back to files.
+        for range in file_ranges {
+            page_cache::release(&range.file, range.offset, range.len)?;
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::fs::path::Path;
+use crate::result::{Errno, Result};
+use crate::{
+    process::current_process,
+    syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler},
+};
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_newfstatat(
+        &mut self,
+        dir: CwdOrFd,
+        path: &Path,
+        buf: UserVAddr,
+        flags: AtFlags,
+    ) -> Result<isize> {
+        let supported_flags =
+            AtFlags::AT_SYMLINK_NOFOLLOW | AtFlags::AT_NO_AUTOMOUNT | AtFlags::AT_EMPTY_PATH;
+        if !(flags - supported_flags).is_empty() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let creds = current_process().credentials();
+        let stat = lookup_path_at(&dir, path, flags, &creds)?.inode.stat()?;
+        buf.write(&stat)?;
+        Ok(0)
+    }
+}
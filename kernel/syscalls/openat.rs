@@ -0,0 +1,104 @@
+use super::CwdOrFd;
+use crate::fs::stat::{O_ACCMODE, O_RDWR, O_WRONLY};
+use crate::fs::{inode::INode, opened_file::OpenFlags, path::Path, stat::FileMode};
+use crate::prelude::*;
+use crate::process::credentials::{AccessMode, Credentials};
+use crate::{process::current_process, syscalls::SyscallHandler};
+
+fn create_file(
+    dir: &CwdOrFd,
+    path: &Path,
+    flags: OpenFlags,
+    mode: FileMode,
+    creds: &Credentials,
+) -> Result<INode> {
+    if flags.contains(OpenFlags::O_DIRECTORY) {
+        // A directory should be created through mkdir(2).
+        return Err(Errno::EINVAL.into());
+    }
+
+    let current = current_process();
+    let root_fs = current.root_fs().clone();
+    let opened_files = current.opened_files().lock();
+    let (parent_dir, name) =
+        root_fs
+            .lock()
+            .lookup_parent_path_at(&opened_files, dir, path, true, creds)?;
+    let parent_dir = parent_dir.inode.as_dir()?;
+    creds.check_access(&parent_dir.stat()?, AccessMode::W_OK | AccessMode::X_OK)?;
+    parent_dir.create_file(name, mode, creds.euid, creds.egid)
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_openat(
+        &mut self,
+        dir: CwdOrFd,
+        path: &Path,
+        flags: OpenFlags,
+        mode: FileMode,
+    ) -> Result<isize> {
+        let current = current_process();
+        trace!(
+            "[{}:{}] openat(\"{}\")",
+            current.pid().as_i32(),
+            current.cmdline().argv0(),
+            path.as_str()
+        );
+
+        let creds = current.credentials();
+        let mut created = false;
+        if flags.contains(OpenFlags::O_CREAT) {
+            match create_file(&dir, path, flags, mode, &creds) {
+                Ok(_) => created = true,
+                Err(err) if !flags.contains(OpenFlags::O_EXCL) && err.errno() == Errno::EEXIST => {}
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let root_fs = current.root_fs().clone();
+        let root_fs = root_fs.lock();
+        let mut opened_files = current.opened_files().lock();
+
+        let follow_symlink = !flags.contains(OpenFlags::O_NOFOLLOW);
+        let path_comp =
+            root_fs.lookup_path_at(&opened_files, &dir, path, follow_symlink, &creds)?;
+        if matches!(path_comp.inode, INode::Symlink(_)) {
+            return Err(Error::new(Errno::ELOOP));
+        }
+
+        if flags.contains(OpenFlags::O_DIRECTORY) && !path_comp.inode.is_dir() {
+            return Err(Error::new(Errno::ENOTDIR));
+        }
+
+        let access_mode = flags.bits() as u32 & O_ACCMODE;
+        if path_comp.inode.is_dir() && (access_mode == O_WRONLY || access_mode == O_RDWR) {
+            return Err(Error::new(Errno::EISDIR));
+        }
+
+        let mut access = match access_mode {
+            O_WRONLY => AccessMode::W_OK,
+            O_RDWR => AccessMode::R_OK | AccessMode::W_OK,
+            _ => AccessMode::R_OK,
+        };
+        if flags.contains(OpenFlags::O_TRUNC) {
+            access |= AccessMode::W_OK;
+        }
+
+        // The file just created is accessible regardless of its mode.
+        if !created {
+            let stat = path_comp.inode.stat()?;
+            creds.check_access(&stat, access)?;
+
+            // O_TRUNC is ignored for device files and FIFOs.
+            let mode = stat.mode;
+            if flags.contains(OpenFlags::O_TRUNC) && mode.is_regular_file() {
+                path_comp.inode.truncate(0)?;
+            }
+        }
+
+        let fd = opened_files.open(path_comp, flags.into())?;
+        Ok(fd.as_usize() as isize)
+    }
+}
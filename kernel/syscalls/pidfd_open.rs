@@ -0,0 +1,36 @@
+use crate::{
+    ctypes::c_int,
+    fs::{
+        inode::{FileLike, INode},
+        opened_file::{OpenOptions, PathComponent},
+    },
+    prelude::*,
+    process::{current_process, pidfd::PidFd, PId},
+    syscalls::SyscallHandler,
+};
+
+/// `PIDFD_NONBLOCK`, the same value as `O_NONBLOCK`.
+const PIDFD_NONBLOCK: c_int = 0o4000;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_pidfd_open(&mut self, pid: PId, flags: c_int) -> Result<isize> {
+        if flags & !PIDFD_NONBLOCK != 0 || pid.as_i32() <= 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        let process = current.pid_ns().find(pid).ok_or(Errno::ESRCH)?;
+        // Only a process (i.e. a thread group leader) can be referred.
+        if process.pid() != process.tid() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let pidfd = Arc::new(PidFd::new(process)) as Arc<dyn FileLike>;
+        let options = OpenOptions::new(flags & PIDFD_NONBLOCK != 0, true);
+        let fd = current.opened_files().lock().open(
+            PathComponent::new_anonymous(INode::FileLike(pidfd)),
+            options,
+        )?;
+        Ok(fd.as_int() as isize)
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
     prelude::*,
     process::current_process,
     syscalls::SyscallHandler,
-    timer::read_monotonic_clock,
+    timer::{cancel_timeout, read_monotonic_clock, set_timeout},
     user_buffer::UserBuffer,
 };
 
@@ -17,12 +17,11 @@ use crate::user_buffer::UserBufReader;
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_poll(&mut self, fds: UserVAddr, nfds: c_nfds, timeout: c_int) -> Result<isize> {
-        let started_at = read_monotonic_clock();
-        POLL_WAIT_QUEUE.sleep_signalable_until(|| {
-            if timeout > 0 && started_at.elapsed_msecs() >= (timeout as usize) {
-                return Ok(Some(0));
-            }
-
+        // A negative timeout means an infinite timeout.
+        let deadline =
+            (timeout >= 0).then(|| read_monotonic_clock().add_nanosecs(timeout as u64 * 1_000_000));
+        let timer = deadline.filter(|_| timeout > 0).map(set_timeout);
+        let result = POLL_WAIT_QUEUE.sleep_signalable_until(|| {
             // Check the statuses of all specified files one by one.
             let mut ready_fds = 0;
             let fds_len = (nfds as usize) * (size_of::<Fd>() + 2 * size_of::<c_short>());
@@ -53,10 +52,27 @@ impl<'a> SyscallHandler<'a> {
 
             if ready_fds > 0 {
                 Ok(Some(ready_fds))
+            } else if matches!(deadline, Some(deadline) if read_monotonic_clock() >= deadline) {
+                Ok(Some(0))
             } else {
                 // Sleep until any changes in files or sockets occur...
                 Ok(None)
             }
-        })
+        });
+
+        if let Some(timer) = timer {
+            cancel_timeout(timer);
+        }
+
+        match result {
+            // Restarting with the same timeout would wait longer than
+            // requested.
+            Err(err) if err.errno() == Errno::ERESTARTSYS && deadline.is_some() => {
+                Err(Errno::EINTR.into())
+            }
+            // Never restarted after a signal handler (like Linux).
+            Err(err) if err.errno() == Errno::ERESTARTSYS => Err(Errno::ERESTARTNOHAND.into()),
+            result => result,
+        }
     }
 }
@@ -0,0 +1,57 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{
+        current_process,
+        seccomp::{SECCOMP_MODE_FILTER, SECCOMP_MODE_STRICT},
+    },
+    syscalls::SyscallHandler,
+};
+
+use super::seccomp::{install_filter, set_strict_mode};
+
+const PR_GET_SECCOMP: c_int = 21;
+const PR_SET_SECCOMP: c_int = 22;
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+const PR_GET_NO_NEW_PRIVS: c_int = 39;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_prctl(&mut self, option: c_int, args: [usize; 4]) -> Result<isize> {
+        let current = current_process();
+        match option {
+            PR_GET_SECCOMP => Ok(current.seccomp().as_c_int() as isize),
+            PR_SET_SECCOMP => {
+                match args[0] as c_int {
+                    SECCOMP_MODE_STRICT => set_strict_mode()?,
+                    SECCOMP_MODE_FILTER => {
+                        install_filter(UserVAddr::new_nonnull(args[1])?, false)?;
+                    }
+                    _ => return Err(Errno::EINVAL.into()),
+                }
+
+                Ok(0)
+            }
+            PR_SET_NO_NEW_PRIVS => {
+                if args != [1, 0, 0, 0] {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                current.set_no_new_privs();
+                Ok(0)
+            }
+            PR_GET_NO_NEW_PRIVS => {
+                if args != [0; 4] {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                Ok(current.no_new_privs() as isize)
+            }
+            _ => {
+                debug_warn!("prctl: unsupported option: {}", option);
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+}
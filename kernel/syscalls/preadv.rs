@@ -0,0 +1,25 @@
+use super::readv::read_iovecs;
+use crate::{fs::opened_file::Fd, prelude::*};
+use crate::{process::current_process, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_preadv(
+        &mut self,
+        fd: Fd,
+        iov_base: UserVAddr,
+        iov_count: usize,
+        offset: isize,
+    ) -> Result<isize> {
+        if offset < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        opened_file.check_seekable()?;
+        let total_len = read_iovecs(&opened_file, iov_base, iov_count, Some(offset as usize))?;
+
+        // MAX_READ_WRITE_LEN limit guarantees total_len is in the range of isize.
+        Ok(total_len as isize)
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{current_process, rlimit::RLimit, PId},
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_prlimit64(
+        &mut self,
+        pid: PId,
+        resource: c_int,
+        new_limit: Option<UserVAddr>,
+        old_limit: Option<UserVAddr>,
+    ) -> Result<isize> {
+        let current = current_process();
+        let target = if pid.as_i32() == 0 {
+            current.clone()
+        } else {
+            let target = current_process().pid_ns().find(pid).ok_or(Errno::ESRCH)?;
+            if !current.credentials().can_trace(&target.credentials()) {
+                return Err(Errno::EPERM.into());
+            }
+
+            target
+        };
+
+        let old = target.rlimit(resource)?;
+        if let Some(new_limit) = new_limit {
+            target.set_rlimit(resource, new_limit.read::<RLimit>()?)?;
+        }
+
+        if let Some(old_limit) = old_limit {
+            old_limit.write(&old)?;
+        }
+
+        Ok(0)
+    }
+}
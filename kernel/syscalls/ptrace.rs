@@ -0,0 +1,309 @@
+use crate::{
+    arch::{FpuState, UserRegs, FPU_STATE_SIZE},
+    ctypes::{c_int, c_long},
+    mm::page_fault::access_memory_forcibly,
+    prelude::*,
+    process::{
+        current_process,
+        ptrace::{PtraceOptions, PtraceResume},
+        signal::{Signal, SIGKILL, SIGSTOP},
+        PId, Process,
+    },
+    syscalls::SyscallHandler,
+};
+use core::{cmp::min, mem::size_of, slice};
+use kerla_runtime::{address::UserVAddr, arch::PtRegs};
+
+const PTRACE_TRACEME: c_long = 0;
+const PTRACE_PEEKTEXT: c_long = 1;
+const PTRACE_PEEKDATA: c_long = 2;
+const PTRACE_PEEKUSER: c_long = 3;
+const PTRACE_POKETEXT: c_long = 4;
+const PTRACE_POKEDATA: c_long = 5;
+const PTRACE_POKEUSER: c_long = 6;
+const PTRACE_CONT: c_long = 7;
+const PTRACE_KILL: c_long = 8;
+const PTRACE_SINGLESTEP: c_long = 9;
+const PTRACE_GETREGS: c_long = 12;
+const PTRACE_SETREGS: c_long = 13;
+const PTRACE_GETFPREGS: c_long = 14;
+const PTRACE_SETFPREGS: c_long = 15;
+const PTRACE_ATTACH: c_long = 16;
+const PTRACE_DETACH: c_long = 17;
+const PTRACE_SYSCALL: c_long = 24;
+const PTRACE_SETOPTIONS: c_long = 0x4200;
+const PTRACE_GETEVENTMSG: c_long = 0x4201;
+const PTRACE_GETSIGINFO: c_long = 0x4202;
+const PTRACE_GETREGSET: c_long = 0x4204;
+const PTRACE_SETREGSET: c_long = 0x4205;
+const PTRACE_SEIZE: c_long = 0x4206;
+
+/// `PTRACE_GETREGSET`: general-purpose registers (`struct user_regs_struct`).
+const NT_PRSTATUS: usize = 1;
+/// `PTRACE_GETREGSET`: floating-point registers (`struct user_fpregs_struct`).
+const NT_PRFPREG: usize = 2;
+
+/// The offset of `u_debugreg` in `struct user`.
+const USER_DEBUGREG_OFFSET: usize = 848;
+/// The size of `struct user`: it ends with eight debug registers.
+const USER_SIZE: usize = USER_DEBUGREG_OFFSET + 64;
+
+/// `struct iovec` passed to `PTRACE_GETREGSET` and `PTRACE_SETREGSET`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+/// Reads or writes the memory of the stopped tracee regardless of the page
+/// protection.
+fn access_tracee_memory(tracee: &Process, addr: usize, buf: &mut [u8], write: bool) -> Result<()> {
+    let vm_ref = tracee.vm();
+    let mut vm = vm_ref.as_ref().ok_or(Errno::EIO)?.lock();
+    access_memory_forcibly(&mut vm, addr, buf, write)
+}
+
+fn user_regs_as_bytes(regs: &UserRegs) -> &[u8] {
+    unsafe { slice::from_raw_parts(regs as *const _ as *const u8, size_of::<UserRegs>()) }
+}
+
+fn user_regs_as_bytes_mut(regs: &mut UserRegs) -> &mut [u8] {
+    unsafe { slice::from_raw_parts_mut(regs as *mut _ as *mut u8, size_of::<UserRegs>()) }
+}
+
+/// Returns the user registers of the stopped tracee.
+fn get_user_regs(tracee: &Process) -> Result<UserRegs> {
+    let mut ptrace = tracee.ptrace();
+    let frame: &PtRegs = ptrace
+        .as_mut()
+        .and_then(|ptrace| ptrace.regs_mut())
+        .ok_or(Errno::ESRCH)?;
+    Ok(tracee.arch().user_regs(frame))
+}
+
+/// Returns the FPU registers of the stopped tracee.
+fn get_fpregs(tracee: &Process) -> Result<FpuState> {
+    let mut ptrace = tracee.ptrace();
+    ptrace
+        .as_mut()
+        .and_then(|ptrace| ptrace.fpregs_mut())
+        .map(|fpregs| *fpregs)
+        .ok_or_else(|| Errno::ESRCH.into())
+}
+
+/// Updates the FPU registers of the stopped tracee.
+fn set_fpregs(tracee: &Process, fpregs: &FpuState) -> Result<()> {
+    let mut ptrace = tracee.ptrace();
+    let current = ptrace
+        .as_mut()
+        .and_then(|ptrace| ptrace.fpregs_mut())
+        .ok_or(Errno::ESRCH)?;
+    *current = *fpregs;
+    Ok(())
+}
+
+/// Updates the user registers of the stopped tracee.
+fn set_user_regs(tracee: &Process, regs: &UserRegs) -> Result<()> {
+    let mut ptrace = tracee.ptrace();
+    let frame = ptrace
+        .as_mut()
+        .and_then(|ptrace| ptrace.regs_mut())
+        .ok_or(Errno::ESRCH)?;
+    tracee.arch().set_user_regs(frame, regs)
+}
+
+/// The signal injected on `PTRACE_CONT` and so on. Zero means no signals.
+fn signal_from_data(data: usize) -> Result<Option<Signal>> {
+    match data {
+        0 => Ok(None),
+        1..=64 => Ok(Some(data as Signal)),
+        _ => Err(Errno::EIO.into()),
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_ptrace(
+        &mut self,
+        request: c_long,
+        pid: PId,
+        addr: usize,
+        data: usize,
+    ) -> Result<isize> {
+        let current = current_process();
+        match request {
+            PTRACE_TRACEME => {
+                let parent = current.parent().ok_or(Errno::EPERM)?;
+                Process::ptrace_attach(current, &parent, PtraceOptions::empty(), false)?;
+                return Ok(0);
+            }
+            PTRACE_ATTACH | PTRACE_SEIZE => {
+                let tracee = current_process().pid_ns().find(pid).ok_or(Errno::ESRCH)?;
+                // A thread can't trace threads in the same process.
+                if tracee.pid() == current.pid() {
+                    return Err(Errno::EPERM.into());
+                }
+
+                let options = if request == PTRACE_SEIZE {
+                    PtraceOptions::from_bits(data as c_int).ok_or(Errno::EINVAL)?
+                } else {
+                    PtraceOptions::empty()
+                };
+
+                Process::ptrace_attach(&tracee, current, options, true)?;
+                if request == PTRACE_ATTACH {
+                    tracee.send_signal(SIGSTOP);
+                }
+
+                return Ok(0);
+            }
+            _ => {}
+        }
+
+        let tracee = current
+            .tracees()
+            .iter()
+            .find(|tracee| current.pid_ns().tid_of(tracee) == pid)
+            .cloned()
+            .ok_or(Errno::ESRCH)?;
+
+        if request == PTRACE_KILL {
+            tracee.send_signal(SIGKILL);
+            return Ok(0);
+        }
+
+        // Other requests are allowed only while the tracee is stopped.
+        let stopped = tracee
+            .ptrace()
+            .as_ref()
+            .map_or(false, |ptrace| ptrace.is_stopped());
+        if !stopped {
+            return Err(Errno::ESRCH.into());
+        }
+
+        match request {
+            PTRACE_PEEKTEXT | PTRACE_PEEKDATA => {
+                let mut word = [0; size_of::<u64>()];
+                access_tracee_memory(&tracee, addr, &mut word, false)?;
+                UserVAddr::new_nonnull(data)?.write_bytes(&word)?;
+            }
+            PTRACE_POKETEXT | PTRACE_POKEDATA => {
+                let mut word = (data as u64).to_ne_bytes();
+                access_tracee_memory(&tracee, addr, &mut word, true)?;
+            }
+            PTRACE_PEEKUSER => {
+                if addr % size_of::<u64>() != 0 || addr >= USER_SIZE {
+                    return Err(Errno::EIO.into());
+                }
+
+                // Fields other than registers (e.g. debug registers) are not
+                // supported.
+                let regs = get_user_regs(&tracee)?;
+                let value = match user_regs_as_bytes(&regs).get(addr..addr + size_of::<u64>()) {
+                    Some(bytes) => u64::from_ne_bytes(bytes.try_into().unwrap()),
+                    None => 0,
+                };
+
+                UserVAddr::new_nonnull(data)?.write(&value)?;
+            }
+            PTRACE_POKEUSER => {
+                if addr % size_of::<u64>() != 0 || addr >= size_of::<UserRegs>() {
+                    return Err(Errno::EIO.into());
+                }
+
+                let mut regs = get_user_regs(&tracee)?;
+                user_regs_as_bytes_mut(&mut regs)[addr..addr + size_of::<u64>()]
+                    .copy_from_slice(&(data as u64).to_ne_bytes());
+                set_user_regs(&tracee, &regs)?;
+            }
+            PTRACE_GETREGS => {
+                UserVAddr::new_nonnull(data)?.write(&get_user_regs(&tracee)?)?;
+            }
+            PTRACE_SETREGS => {
+                let regs = UserVAddr::new_nonnull(data)?.read::<UserRegs>()?;
+                set_user_regs(&tracee, &regs)?;
+            }
+            PTRACE_GETFPREGS => {
+                UserVAddr::new_nonnull(data)?.write_bytes(get_fpregs(&tracee)?.as_bytes())?;
+            }
+            PTRACE_SETFPREGS => {
+                let mut fpregs = get_fpregs(&tracee)?;
+                UserVAddr::new_nonnull(data)?.read_bytes(fpregs.as_bytes_mut())?;
+                set_fpregs(&tracee, &fpregs)?;
+            }
+            PTRACE_GETREGSET | PTRACE_SETREGSET => {
+                let iov_addr = UserVAddr::new_nonnull(data)?;
+                let mut iov = iov_addr.read::<IoVec>()?;
+                let base = UserVAddr::new_nonnull(iov.base)?;
+                match (addr, request) {
+                    (NT_PRSTATUS, PTRACE_GETREGSET) => {
+                        let regs = get_user_regs(&tracee)?;
+                        iov.len = min(iov.len, size_of::<UserRegs>());
+                        base.write_bytes(&user_regs_as_bytes(&regs)[..iov.len])?;
+                    }
+                    (NT_PRSTATUS, _) => {
+                        let mut regs = get_user_regs(&tracee)?;
+                        iov.len = min(iov.len, size_of::<UserRegs>());
+                        base.read_bytes(&mut user_regs_as_bytes_mut(&mut regs)[..iov.len])?;
+                        set_user_regs(&tracee, &regs)?;
+                    }
+                    (NT_PRFPREG, PTRACE_GETREGSET) => {
+                        let fpregs = get_fpregs(&tracee)?;
+                        iov.len = min(iov.len, FPU_STATE_SIZE);
+                        base.write_bytes(&fpregs.as_bytes()[..iov.len])?;
+                    }
+                    (NT_PRFPREG, _) => {
+                        let mut fpregs = get_fpregs(&tracee)?;
+                        iov.len = min(iov.len, FPU_STATE_SIZE);
+                        base.read_bytes(&mut fpregs.as_bytes_mut()[..iov.len])?;
+                        set_fpregs(&tracee, &fpregs)?;
+                    }
+                    _ => return Err(Errno::EINVAL.into()),
+                }
+
+                iov_addr.write(&iov)?;
+            }
+            PTRACE_CONT => {
+                tracee.ptrace_resume(PtraceResume::Continue, signal_from_data(data)?)?;
+            }
+            PTRACE_SYSCALL => {
+                tracee.ptrace_resume(PtraceResume::Syscall, signal_from_data(data)?)?;
+            }
+            PTRACE_SINGLESTEP => {
+                tracee.ptrace_resume(PtraceResume::SingleStep, signal_from_data(data)?)?;
+            }
+            PTRACE_DETACH => {
+                let signal = signal_from_data(data)?;
+                current.tracees().retain(|proc| !Arc::ptr_eq(proc, &tracee));
+                tracee.ptrace_detach(signal);
+            }
+            PTRACE_SETOPTIONS => {
+                let options = PtraceOptions::from_bits(data as c_int).ok_or(Errno::EINVAL)?;
+                if let Some(ptrace) = tracee.ptrace().as_mut() {
+                    ptrace.set_options(options);
+                }
+            }
+            PTRACE_GETEVENTMSG => {
+                let msg = tracee
+                    .ptrace()
+                    .as_ref()
+                    .map_or(0, |ptrace| ptrace.event_msg());
+                UserVAddr::new_nonnull(data)?.write(&msg)?;
+            }
+            PTRACE_GETSIGINFO => {
+                let siginfo = tracee
+                    .ptrace()
+                    .as_ref()
+                    .and_then(|ptrace| ptrace.siginfo())
+                    .ok_or(Errno::EINVAL)?;
+                UserVAddr::new_nonnull(data)?.write_bytes(&siginfo.to_bytes())?;
+            }
+            _ => {
+                debug_warn!("ptrace: unsupported request {}", request);
+                return Err(Errno::EIO.into());
+            }
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,28 @@
+use super::MAX_READ_WRITE_LEN;
+use crate::{fs::opened_file::Fd, prelude::*, user_buffer::UserBuffer};
+use crate::{process::current_process, syscalls::SyscallHandler};
+use core::cmp::min;
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_pwrite64(
+        &mut self,
+        fd: Fd,
+        uaddr: UserVAddr,
+        len: usize,
+        offset: isize,
+    ) -> Result<isize> {
+        if offset < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let len = min(len, MAX_READ_WRITE_LEN);
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        opened_file.check_seekable()?;
+        let written_len =
+            opened_file.write_at(offset as usize, UserBuffer::from_uaddr(uaddr, len))?;
+
+        // MAX_READ_WRITE_LEN limit guarantees written_len is in the range of isize.
+        Ok(written_len as isize)
+    }
+}
@@ -0,0 +1,49 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::result::{Error, Result};
+use crate::syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler};
+use crate::{
+    fs::{opened_file::Fd, path::Path},
+    process::current_process,
+    result::Errno,
+};
+
+use crate::user_buffer::UserBufWriter;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_readlinkat(
+        &mut self,
+        dir: CwdOrFd,
+        path: &Path,
+        buf: UserVAddr,
+        buf_size: usize,
+    ) -> Result<isize> {
+        let resolved_path = if path.as_str().starts_with("/proc/self/fd/") {
+            // TODO: Implement procfs
+            let fd = path.as_str()["/proc/self/fd/".len()..]
+                .parse()
+                .map_err(|_| Error::new(Errno::ENOENT))?;
+            current_process()
+                .opened_files()
+                .lock()
+                .get(Fd::new(fd))?
+                .path()
+                .resolve_absolute_path()
+        } else {
+            let creds = current_process().credentials();
+            lookup_path_at(&dir, path, AtFlags::AT_SYMLINK_NOFOLLOW, &creds)?
+                .inode
+                .readlink()?
+        };
+
+        if buf_size == 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // The contents are truncated to the buffer size and not terminated by
+        // NUL.
+        let mut writer = UserBufWriter::from_uaddr(buf, buf_size);
+        writer.write_bytes(resolved_path.as_str().as_bytes())?;
+        Ok(writer.pos() as isize)
+    }
+}
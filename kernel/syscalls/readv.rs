@@ -0,0 +1,67 @@
+use super::{IoVec, IOV_MAX, MAX_READ_WRITE_LEN};
+use crate::prelude::*;
+use crate::{
+    fs::opened_file::{Fd, OpenedFile},
+    user_buffer::UserBufferMut,
+};
+use crate::{process::current_process, syscalls::SyscallHandler};
+use core::cmp::min;
+use kerla_runtime::address::UserVAddr;
+
+use core::mem::size_of;
+
+/// Reads the file into buffers in `iov_base`. Reads at the current position
+/// if `offset` is `None` (`readv(2)`) or at `offset` otherwise (`preadv(2)`).
+pub(super) fn read_iovecs(
+    opened_file: &OpenedFile,
+    iov_base: UserVAddr,
+    iov_count: usize,
+    offset: Option<usize>,
+) -> Result<usize> {
+    let iov_count = min(iov_count, IOV_MAX);
+
+    let mut total_len: usize = 0;
+    for i in 0..iov_count {
+        // Read an entry from the userspace.
+        let mut iov: IoVec = iov_base.add(i * size_of::<IoVec>()).read()?;
+
+        // Handle the case when total_len exceed the limit.
+        match total_len.checked_add(iov.len) {
+            Some(len) if len > MAX_READ_WRITE_LEN => {
+                iov.len = MAX_READ_WRITE_LEN - total_len;
+            }
+            None => {
+                iov.len = MAX_READ_WRITE_LEN - total_len;
+            }
+            _ => {}
+        }
+
+        if iov.len == 0 {
+            continue;
+        }
+
+        let buf = UserBufferMut::from_uaddr(iov.base, iov.len);
+        let read_len = match offset {
+            Some(offset) => opened_file.read_at(offset + total_len, buf)?,
+            None => opened_file.read(buf)?,
+        };
+
+        total_len += read_len;
+        if read_len < iov.len {
+            // Stop at a short read (e.g. EOF).
+            break;
+        }
+    }
+
+    Ok(total_len)
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_readv(&mut self, fd: Fd, iov_base: UserVAddr, iov_count: usize) -> Result<isize> {
+        let opened_file = current_process().get_opened_file_by_fd(fd)?;
+        let total_len = read_iovecs(&opened_file, iov_base, iov_count, None)?;
+
+        // MAX_READ_WRITE_LEN limit guarantees total_len is in the range of isize.
+        Ok(total_len as isize)
+    }
+}
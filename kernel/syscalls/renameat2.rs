@@ -0,0 +1,111 @@
+use core::ptr;
+
+use crate::{
+    fs::{
+        inode::{Directory, INode, RenameFlags},
+        opened_file::PathComponent,
+        path::Path,
+    },
+    prelude::*,
+    process::{credentials::AccessMode, current_process},
+    syscalls::{CwdOrFd, SyscallHandler},
+};
+
+use super::unlinkat::check_removable;
+
+/// Returns `true` if `path` is `dir` or one of its descendants.
+fn is_in_dir(path: &Arc<PathComponent>, dir: &Arc<dyn Directory>) -> bool {
+    let mut current = Some(path);
+    while let Some(path_comp) = current {
+        if let INode::Directory(path_dir) = &path_comp.inode {
+            if ptr::eq(
+                Arc::as_ptr(path_dir) as *const (),
+                Arc::as_ptr(dir) as *const (),
+            ) {
+                return true;
+            }
+        }
+
+        current = path_comp.parent_dir.as_ref();
+    }
+
+    false
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_renameat2(
+        &mut self,
+        old_dir: CwdOrFd,
+        old_path: &Path,
+        new_dir: CwdOrFd,
+        new_path: &Path,
+        flags: RenameFlags,
+    ) -> Result<isize> {
+        if flags.contains(RenameFlags::RENAME_NOREPLACE | RenameFlags::RENAME_EXCHANGE) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        let creds = current.credentials();
+        let root_fs = current.root_fs().clone();
+        let root_fs = root_fs.lock();
+        let ((old_parent, old_name), (new_parent, new_name)) = {
+            let opened_files = current.opened_files().lock();
+            (
+                root_fs.lookup_parent_path_at(&opened_files, &old_dir, old_path, true, &creds)?,
+                root_fs.lookup_parent_path_at(&opened_files, &new_dir, new_path, true, &creds)?,
+            )
+        };
+
+        if [old_name, new_name]
+            .iter()
+            .any(|name| *name == "." || *name == "..")
+        {
+            return Err(Errno::EBUSY.into());
+        }
+
+        // Files can't be moved across mount points even if both are in the
+        // same kind of file system.
+        if root_fs.mount_point_of(&old_parent)? != root_fs.mount_point_of(&new_parent)? {
+            return Err(Errno::EXDEV.into());
+        }
+
+        let old_parent_dir = old_parent.inode.as_dir()?;
+        let new_parent_dir = new_parent.inode.as_dir()?;
+        for (dir, name) in [(old_parent_dir, old_name), (new_parent_dir, new_name)] {
+            if let Ok(INode::Directory(dir)) = dir.lookup(name) {
+                if root_fs.is_mount_point(&dir)? {
+                    return Err(Errno::EBUSY.into());
+                }
+            }
+        }
+
+        check_removable(old_parent_dir, old_name, &creds)?;
+        match new_parent_dir.lookup(new_name) {
+            // The existing destination is replaced.
+            Ok(_) => check_removable(new_parent_dir, new_name, &creds)?,
+            Err(_) => {
+                creds.check_access(&new_parent_dir.stat()?, AccessMode::W_OK | AccessMode::X_OK)?
+            }
+        }
+
+        // A directory can't be moved into itself or its descendants.
+        let exchange = flags.contains(RenameFlags::RENAME_EXCHANGE);
+        if let INode::Directory(dir) = old_parent_dir.lookup(old_name)? {
+            if is_in_dir(&new_parent, &dir) {
+                return Err(Errno::EINVAL.into());
+            }
+        }
+
+        if exchange {
+            if let INode::Directory(dir) = new_parent_dir.lookup(new_name)? {
+                if is_in_dir(&old_parent, &dir) {
+                    return Err(Errno::EINVAL.into());
+                }
+            }
+        }
+
+        old_parent_dir.rename(old_name, new_parent_dir, new_name, flags)?;
+        Ok(0)
+    }
+}
@@ -1,37 +1,86 @@
 use crate::ctypes::*;
 use crate::prelude::*;
 use crate::process::current_process;
-use crate::process::signal::{SigAction, DEFAULT_ACTIONS, SIG_DFL, SIG_IGN};
+use crate::process::signal::{
+    SigAction, SigActionFlags, SigHandler, SigSet, SIGMAX, SIG_DFL, SIG_IGN,
+};
 use crate::syscalls::SyscallHandler;
+use core::mem::size_of;
 use kerla_runtime::address::UserVAddr;
 
+/// `struct sigaction` in the kernel ABI (`struct kernel_sigaction` in glibc).
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct KernelSigAction {
+    handler: usize,
+    flags: u64,
+    restorer: usize,
+    mask: u64,
+}
+
 impl<'a> SyscallHandler<'a> {
     pub fn sys_rt_sigaction(
         &mut self,
         signum: c_int,
-        act: usize,
-        _oldact: Option<UserVAddr>,
+        act: Option<UserVAddr>,
+        oldact: Option<UserVAddr>,
+        sigsetsize: usize,
     ) -> Result<isize> {
-        if let Some(act) = UserVAddr::new(act) {
-            let handler = act.read::<usize>()?;
-            let new_action = match handler {
-                SIG_IGN => SigAction::Ignore,
-                SIG_DFL => match DEFAULT_ACTIONS.get(signum as usize) {
-                    Some(default_action) => *default_action,
-                    None => return Err(Errno::EINVAL.into()),
-                },
-                _ => SigAction::Handler {
-                    handler: UserVAddr::new(handler).ok_or_else(|| Error::new(Errno::EFAULT))?,
-                },
+        if sigsetsize != size_of::<SigSet>() || !(1..=SIGMAX).contains(&signum) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let new_action = match act {
+            Some(act) => {
+                let act = act.read::<KernelSigAction>()?;
+                let handler = match act.handler {
+                    SIG_DFL => SigHandler::Default,
+                    SIG_IGN => SigHandler::Ignore,
+                    handler => SigHandler::Handler(UserVAddr::new_nonnull(handler)?),
+                };
+
+                let flags = SigActionFlags::from_bits_truncate(act.flags);
+                let restorer = if flags.contains(SigActionFlags::SA_RESTORER) {
+                    UserVAddr::new(act.restorer)
+                } else {
+                    None
+                };
+
+                Some(SigAction {
+                    handler,
+                    flags,
+                    mask: SigSet::from_raw(act.mask).without_unblockable(),
+                    restorer,
+                })
+            }
+            None => None,
+        };
+
+        let old_action = {
+            let mut signals = current_process().signals().lock();
+            let old_action = signals.get_action(signum);
+            if let Some(new_action) = new_action {
+                signals.set_action(signum, new_action)?;
+            }
+
+            old_action
+        };
+
+        if let Some(oldact) = oldact {
+            let handler = match old_action.handler {
+                SigHandler::Default => SIG_DFL,
+                SigHandler::Ignore => SIG_IGN,
+                SigHandler::Handler(handler) => handler.value(),
             };
 
-            current_process()
-                .signals()
-                .lock()
-                .set_action(signum, new_action)?;
+            oldact.write(&KernelSigAction {
+                handler,
+                flags: old_action.flags.bits(),
+                restorer: old_action.restorer.map(|r| r.value()).unwrap_or(0),
+                mask: old_action.mask.as_raw(),
+            })?;
         }
 
-        // TODO: Support oldact
         Ok(0)
     }
 }
@@ -0,0 +1,18 @@
+use crate::prelude::*;
+use crate::process::current_process;
+use crate::process::signal::SigSet;
+use crate::syscalls::SyscallHandler;
+use core::mem::size_of;
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_rt_sigpending(&mut self, set: UserVAddr, sigsetsize: usize) -> Result<isize> {
+        if sigsetsize != size_of::<SigSet>() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let pending = current_process().blocked_pending_signals();
+        set.write::<u64>(&pending.as_raw())?;
+        Ok(0)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::prelude::*;
 use crate::process::current_process;
 
-use crate::process::signal::SignalMask;
+use crate::process::signal::{SigSet, SignalMask};
 use crate::syscalls::SyscallHandler;
+use core::mem::size_of;
 use kerla_runtime::address::UserVAddr;
 
 impl SyscallHandler<'_> {
@@ -13,8 +14,8 @@ impl SyscallHandler<'_> {
         oldset: Option<UserVAddr>,
         length: usize,
     ) -> Result<isize> {
-        if length != 8 {
-            debug_warn!("sys_rt_sigprocmask length argument is not equal 8");
+        if length != size_of::<SigSet>() {
+            return Err(Errno::EINVAL.into());
         }
 
         let how = match how {
@@ -24,7 +25,18 @@ impl SyscallHandler<'_> {
             _ => return Err(Errno::EINVAL.into()),
         };
 
-        current_process().set_signal_mask(how, set, oldset, length)?;
+        let current = current_process();
+        let old = match set {
+            Some(set) => {
+                let set = SigSet::from_raw(set.read::<u64>()?);
+                current.set_signal_mask(how, set)
+            }
+            None => current.signal_mask(),
+        };
+
+        if let Some(oldset) = oldset {
+            oldset.write::<u64>(&old.as_raw())?;
+        }
 
         Ok(0)
     }
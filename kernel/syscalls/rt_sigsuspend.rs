@@ -0,0 +1,31 @@
+use crate::prelude::*;
+use crate::process::signal::SigSet;
+use crate::process::{current_process, switch, ProcessState};
+use crate::syscalls::SyscallHandler;
+use core::mem::size_of;
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_rt_sigsuspend(&mut self, mask: UserVAddr, sigsetsize: usize) -> Result<isize> {
+        if sigsetsize != size_of::<SigSet>() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let mask = SigSet::from_raw(mask.read::<u64>()?);
+        let current = current_process();
+
+        // The original mask is restored once a signal is delivered.
+        current.set_temporary_signal_mask(mask);
+        loop {
+            current.set_state(ProcessState::BlockedSignalable);
+            if current.has_pending_signals() {
+                break;
+            }
+
+            switch();
+        }
+
+        current.resume();
+        Err(Errno::ERESTARTNOHAND.into())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::prelude::*;
+use crate::process::signal::{SigInfo, SigSet, SignalMask};
+use crate::process::{current_process, switch, ProcessState};
+use crate::syscalls::SyscallHandler;
+use crate::timer::{
+    cancel_timeout, is_timeout_expired, read_monotonic_clock, set_timeout, Timespec,
+};
+use core::mem::size_of;
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_rt_sigtimedwait(
+        &mut self,
+        set: UserVAddr,
+        info: Option<UserVAddr>,
+        timeout: Option<UserVAddr>,
+        sigsetsize: usize,
+    ) -> Result<isize> {
+        if sigsetsize != size_of::<SigSet>() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let set = SigSet::from_raw(set.read::<u64>()?).without_unblockable();
+        let deadline = match timeout {
+            Some(timeout) => {
+                let timeout = timeout.read::<Timespec>()?;
+                if !timeout.is_valid() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                Some(read_monotonic_clock().add_nanosecs(timeout.as_nanosecs()))
+            }
+            None => None,
+        };
+
+        let current = current_process();
+        let timer = deadline.map(set_timeout);
+
+        // Unblock the signals while waiting so that they wake us up.
+        let old_mask = current.set_signal_mask(SignalMask::Unblock, set);
+        let result: Result<SigInfo> = loop {
+            current.set_state(ProcessState::BlockedSignalable);
+
+            if let Some(info) = current.dequeue_signal(set) {
+                break Ok(info);
+            }
+
+            if current.has_pending_signals() {
+                break Err(Errno::EINTR.into());
+            }
+
+            if matches!(timer, Some(timer) if is_timeout_expired(timer)) {
+                break Err(Errno::EAGAIN.into());
+            }
+
+            switch();
+        };
+
+        current.resume();
+        current.set_signal_mask(SignalMask::Set, old_mask);
+        if let Some(timer) = timer {
+            cancel_timeout(timer);
+        }
+
+        let siginfo = result?;
+        if let Some(info) = info {
+            info.write_bytes(&siginfo.to_bytes())?;
+        }
+
+        Ok(siginfo.signal as isize)
+    }
+}
@@ -0,0 +1,15 @@
+use crate::{
+    ctypes::c_int, prelude::*, process::scheduler::RT_PRIORITY_MAX, syscalls::SyscallHandler,
+};
+
+use super::sched_setscheduler::{SCHED_FIFO, SCHED_OTHER};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_get_priority_max(&mut self, policy: c_int) -> Result<isize> {
+        match policy {
+            SCHED_OTHER => Ok(0),
+            SCHED_FIFO => Ok(RT_PRIORITY_MAX as isize),
+            _ => Err(Errno::EINVAL.into()),
+        }
+    }
+}
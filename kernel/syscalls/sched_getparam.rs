@@ -0,0 +1,20 @@
+use crate::{
+    process::{scheduler::SchedPolicy, PId},
+    result::Result,
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+use super::sched_setscheduler::{find_target, SchedParam};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_getparam(&mut self, pid: PId, param: UserVAddr) -> Result<isize> {
+        let sched_priority = match find_target(pid)?.sched_policy() {
+            SchedPolicy::Normal => 0,
+            SchedPolicy::Fifo(priority) => priority,
+        };
+
+        param.write(&SchedParam { sched_priority })?;
+        Ok(0)
+    }
+}
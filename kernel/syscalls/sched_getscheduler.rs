@@ -0,0 +1,18 @@
+use crate::{
+    process::{scheduler::SchedPolicy, PId},
+    result::Result,
+    syscalls::SyscallHandler,
+};
+
+use super::sched_setscheduler::{find_target, SCHED_FIFO, SCHED_OTHER};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_getscheduler(&mut self, pid: PId) -> Result<isize> {
+        let policy = match find_target(pid)?.sched_policy() {
+            SchedPolicy::Normal => SCHED_OTHER,
+            SchedPolicy::Fifo(_) => SCHED_FIFO,
+        };
+
+        Ok(policy as isize)
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{
+    prelude::*,
+    process::{
+        scheduler::{SchedPolicy, RT_PRIORITY_MAX, RT_PRIORITY_MIN},
+        PId,
+    },
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+use super::sched_setscheduler::{check_policy_permission, find_target, SchedParam};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_setparam(&mut self, pid: PId, param: UserVAddr) -> Result<isize> {
+        let proc = find_target(pid)?;
+        let priority = param.read::<SchedParam>()?.sched_priority;
+        let policy = match proc.sched_policy() {
+            SchedPolicy::Normal if priority == 0 => SchedPolicy::Normal,
+            SchedPolicy::Fifo(_) if (RT_PRIORITY_MIN..=RT_PRIORITY_MAX).contains(&priority) => {
+                SchedPolicy::Fifo(priority)
+            }
+            _ => return Err(Errno::EINVAL.into()),
+        };
+
+        check_policy_permission(&proc, policy)?;
+        proc.set_sched_params(policy, proc.nice());
+        Ok(0)
+    }
+}
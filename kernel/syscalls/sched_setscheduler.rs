@@ -0,0 +1,89 @@
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{
+        current_process,
+        rlimit::RLIMIT_RTPRIO,
+        scheduler::{SchedPolicy, RT_PRIORITY_MAX, RT_PRIORITY_MIN},
+        PId, Process,
+    },
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+pub(super) const SCHED_OTHER: c_int = 0;
+pub(super) const SCHED_FIFO: c_int = 1;
+
+/// `struct sched_param`
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct SchedParam {
+    pub sched_priority: c_int,
+}
+
+/// Returns the process specified by `pid` in `sched_*(2)`. `0` means the
+/// current process.
+pub(super) fn find_target(pid: PId) -> Result<Arc<Process>> {
+    match pid.as_i32() {
+        0 => Ok(current_process().clone()),
+        pid if pid < 0 => Err(Errno::EINVAL.into()),
+        _ => current_process()
+            .pid_ns()
+            .find(pid)
+            .ok_or_else(|| Errno::ESRCH.into()),
+    }
+}
+
+/// Checks if the current process is permitted to change the scheduling
+/// policy of `proc` to `policy`. Raising the real-time priority above
+/// `RLIMIT_RTPRIO` requires the privilege.
+pub(super) fn check_policy_permission(proc: &Process, policy: SchedPolicy) -> Result<()> {
+    let creds = current_process().credentials();
+    if creds.is_privileged() {
+        return Ok(());
+    }
+
+    if !creds.can_reschedule(&proc.credentials()) {
+        return Err(Errno::EPERM.into());
+    }
+
+    if let SchedPolicy::Fifo(priority) = policy {
+        let current_priority = match proc.sched_policy() {
+            SchedPolicy::Fifo(priority) => priority,
+            SchedPolicy::Normal => 0,
+        };
+
+        if priority > current_priority && priority as u64 > proc.rlimit(RLIMIT_RTPRIO)?.cur {
+            return Err(Errno::EPERM.into());
+        }
+    }
+
+    Ok(())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_setscheduler(
+        &mut self,
+        pid: PId,
+        policy: c_int,
+        param: UserVAddr,
+    ) -> Result<isize> {
+        let proc = find_target(pid)?;
+        let priority = param.read::<SchedParam>()?.sched_priority;
+        let policy = match policy {
+            SCHED_OTHER if priority == 0 => SchedPolicy::Normal,
+            SCHED_FIFO if (RT_PRIORITY_MIN..=RT_PRIORITY_MAX).contains(&priority) => {
+                SchedPolicy::Fifo(priority)
+            }
+            SCHED_OTHER | SCHED_FIFO => return Err(Errno::EINVAL.into()),
+            _ => {
+                debug_warn!("sched_setscheduler: unsupported policy {}", policy);
+                return Err(Errno::EINVAL.into());
+            }
+        };
+
+        check_policy_permission(&proc, policy)?;
+        proc.set_sched_params(policy, proc.nice());
+        Ok(0)
+    }
+}
@@ -0,0 +1,8 @@
+use crate::{process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sched_yield(&mut self) -> Result<isize> {
+        process::yield_now();
+        Ok(0)
+    }
+}
@@ -0,0 +1,109 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::{
+    ctypes::c_uint,
+    prelude::*,
+    process::{
+        current_process,
+        seccomp::{is_action_available, SeccompFilter, SeccompMode},
+        Process,
+    },
+    syscalls::SyscallHandler,
+};
+
+const SECCOMP_SET_MODE_STRICT: c_uint = 0;
+const SECCOMP_SET_MODE_FILTER: c_uint = 1;
+const SECCOMP_GET_ACTION_AVAIL: c_uint = 2;
+
+const SECCOMP_FILTER_FLAG_TSYNC: c_uint = 1;
+
+/// Enters the strict mode. A thread in the filter mode can't switch to it.
+pub(super) fn set_strict_mode() -> Result<()> {
+    let current = current_process();
+    if matches!(current.seccomp(), SeccompMode::Filter(_)) {
+        return Err(Errno::EINVAL.into());
+    }
+
+    current.set_seccomp(SeccompMode::Strict);
+    Ok(())
+}
+
+/// Installs a filter program (`struct sock_fprog`) on top of the existing
+/// ones. Unprivileged threads must set no_new_privs first.
+///
+/// If `tsync` is set, the filter is installed in all threads in the thread
+/// group as well. It fails and returns the thread ID of a thread whose filter
+/// is not an ancestor of the current one.
+pub(super) fn install_filter(fprog: UserVAddr, tsync: bool) -> Result<isize> {
+    let current = current_process();
+    if !current.no_new_privs() && !current.credentials().is_privileged() {
+        return Err(Errno::EACCES.into());
+    }
+
+    let prev = match current.seccomp() {
+        SeccompMode::Disabled => None,
+        SeccompMode::Filter(filter) => Some(filter),
+        SeccompMode::Strict => return Err(Errno::EINVAL.into()),
+    };
+
+    let filter = SeccompFilter::from_user(fprog, prev.clone())?;
+    if !tsync {
+        current.set_seccomp(SeccompMode::Filter(filter));
+        return Ok(0);
+    }
+
+    // Hold the thread group lock not to let new threads in while syncing.
+    let thread_group = current.thread_group();
+    let threads: Vec<Arc<Process>> = thread_group
+        .threads()
+        .iter()
+        .filter_map(|thread| thread.upgrade())
+        .collect();
+
+    for thread in &threads {
+        if !thread.seccomp().is_ancestor_of(prev.as_ref()) {
+            return Ok(current.pid_ns().tid_of(thread).as_i32() as isize);
+        }
+    }
+
+    for thread in &threads {
+        thread.set_seccomp(SeccompMode::Filter(filter.clone()));
+        if current.no_new_privs() {
+            thread.set_no_new_privs();
+        }
+    }
+
+    Ok(0)
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_seccomp(
+        &mut self,
+        op: c_uint,
+        flags: c_uint,
+        uargs: Option<UserVAddr>,
+    ) -> Result<isize> {
+        // Flags other than SECCOMP_FILTER_FLAG_TSYNC are not supported.
+        if op != SECCOMP_SET_MODE_FILTER && flags != 0 || flags & !SECCOMP_FILTER_FLAG_TSYNC != 0 {
+            debug_warn!("seccomp: unsupported flags: {:x}", flags);
+            return Err(Errno::EINVAL.into());
+        }
+
+        match op {
+            SECCOMP_SET_MODE_STRICT if uargs.is_none() => set_strict_mode()?,
+            SECCOMP_SET_MODE_FILTER => {
+                let tsync = flags & SECCOMP_FILTER_FLAG_TSYNC != 0;
+                return install_filter(uargs.ok_or(Errno::EFAULT)?, tsync);
+            }
+            SECCOMP_GET_ACTION_AVAIL => {
+                let action = uargs.ok_or(Errno::EFAULT)?.read::<u32>()?;
+                if !is_action_available(action) {
+                    return Err(Errno::EOPNOTSUPP.into());
+                }
+            }
+            _ => return Err(Errno::EINVAL.into()),
+        }
+
+        Ok(0)
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     prelude::*,
     process::current_process,
     syscalls::SyscallHandler,
-    timer::{read_monotonic_clock, Timeval},
+    timer::{cancel_timeout, read_monotonic_clock, set_timeout, Timeval},
 };
 
 fn check_fd_statuses<F>(max_fd: c_int, fds: UserVAddr, is_ready: F) -> Result<isize>
@@ -55,16 +55,14 @@ impl<'a> SyscallHandler<'a> {
         _errorfds: Option<UserVAddr>,
         timeout: Option<Timeval>,
     ) -> Result<isize> {
-        let started_at = read_monotonic_clock();
-        let timeout_ms = timeout.map(|timeval| timeval.as_msecs());
-        POLL_WAIT_QUEUE.sleep_signalable_until(|| {
-            match timeout_ms {
-                Some(timeout_ms) if started_at.elapsed_msecs() >= timeout_ms => {
-                    return Ok(Some(0));
-                }
-                _ => {}
-            }
+        if matches!(timeout, Some(timeout) if !timeout.is_valid()) {
+            return Err(Errno::EINVAL.into());
+        }
 
+        let deadline =
+            timeout.map(|timeout| read_monotonic_clock().add_nanosecs(timeout.as_nanosecs()));
+        let timer = deadline.map(set_timeout);
+        let result = POLL_WAIT_QUEUE.sleep_signalable_until(|| {
             // Check the statuses of all specified files one by one.
             // TODO: Support errorfds
             let mut ready_fds = 0;
@@ -79,10 +77,27 @@ impl<'a> SyscallHandler<'a> {
 
             if ready_fds > 0 {
                 Ok(Some(ready_fds))
+            } else if matches!(deadline, Some(deadline) if read_monotonic_clock() >= deadline) {
+                Ok(Some(0))
             } else {
                 // Sleep until any changes in files or sockets occur...
                 Ok(None)
             }
-        })
+        });
+
+        if let Some(timer) = timer {
+            cancel_timeout(timer);
+        }
+
+        match result {
+            // Restarting with the same timeout would wait longer than
+            // requested.
+            Err(err) if err.errno() == Errno::ERESTARTSYS && deadline.is_some() => {
+                Err(Errno::EINTR.into())
+            }
+            // Never restarted after a signal handler (like Linux).
+            Err(err) if err.errno() == Errno::ERESTARTSYS => Err(Errno::ERESTARTNOHAND.into()),
+            result => result,
+        }
     }
 }
@@ -0,0 +1,16 @@
+use crate::{prelude::*, process::current_process, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+/// `sizeof(struct robust_list_head)`
+const ROBUST_LIST_HEAD_SIZE: usize = 24;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_set_robust_list(&mut self, head: Option<UserVAddr>, len: usize) -> Result<isize> {
+        if len != ROBUST_LIST_HEAD_SIZE {
+            return Err(Errno::EINVAL.into());
+        }
+
+        current_process().set_robust_list(head);
+        Ok(0)
+    }
+}
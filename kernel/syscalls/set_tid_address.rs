@@ -1,10 +1,11 @@
 use crate::result::Result;
-use crate::syscalls::SyscallHandler;
+use crate::{process::current_process, syscalls::SyscallHandler};
 use kerla_runtime::address::UserVAddr;
 
 impl<'a> SyscallHandler<'a> {
-    pub fn sys_set_tid_address(&mut self, _uaddr: UserVAddr) -> Result<isize> {
-        /* TODO: */
-        Ok(0)
+    pub fn sys_set_tid_address(&mut self, uaddr: Option<UserVAddr>) -> Result<isize> {
+        let current = current_process();
+        current.set_clear_child_tid(uaddr);
+        Ok(current.pid_ns().tid_of(current).as_i32() as isize)
     }
 }
@@ -0,0 +1,12 @@
+use crate::{ctypes::c_int, prelude::*, process::current_process, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+use super::sethostname::read_uts_name;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setdomainname(&mut self, name: UserVAddr, len: c_int) -> Result<isize> {
+        let name = read_uts_name(name, len)?;
+        current_process().namespaces().uts().set_domainname(&name);
+        Ok(0)
+    }
+}
@@ -0,0 +1,33 @@
+use core::mem::size_of;
+
+use kerla_runtime::address::UserVAddr;
+
+use crate::{
+    fs::stat::GId,
+    prelude::*,
+    process::{credentials::NGROUPS_MAX, current_process},
+    syscalls::SyscallHandler,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setgroups(&mut self, size: usize, list: Option<UserVAddr>) -> Result<isize> {
+        if size > NGROUPS_MAX {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let mut groups = Vec::with_capacity(size);
+        if size > 0 {
+            let list = list.ok_or_else(|| Error::new(Errno::EFAULT))?;
+            for i in 0..size {
+                let gid = list.add(i * size_of::<u32>()).read::<u32>()?;
+                groups.push(GId::new(gid));
+            }
+        }
+
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_groups(groups)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
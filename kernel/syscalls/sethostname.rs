@@ -0,0 +1,30 @@
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{current_process, namespace::UTS_NAME_LEN_MAX},
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+/// Reads a new host name or domain name from the userspace.
+pub(super) fn read_uts_name(name: UserVAddr, len: c_int) -> Result<String> {
+    if !current_process().credentials().is_privileged() {
+        return Err(Errno::EPERM.into());
+    }
+
+    if len < 0 || len as usize > UTS_NAME_LEN_MAX {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let mut buf = vec![0; len as usize];
+    name.read_bytes(&mut buf)?;
+    String::from_utf8(buf).map_err(|_| Errno::EINVAL.into())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sethostname(&mut self, name: UserVAddr, len: c_int) -> Result<isize> {
+        let name = read_uts_name(name, len)?;
+        current_process().namespaces().uts().set_hostname(&name);
+        Ok(0)
+    }
+}
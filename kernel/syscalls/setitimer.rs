@@ -0,0 +1,98 @@
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{current_process, thread_group::CpuTimer},
+    syscalls::SyscallHandler,
+    timer::{read_monotonic_clock, Timeval},
+};
+use kerla_runtime::address::UserVAddr;
+
+pub(super) const ITIMER_REAL: c_int = 0;
+pub(super) const ITIMER_VIRTUAL: c_int = 1;
+pub(super) const ITIMER_PROF: c_int = 2;
+
+/// `struct itimerval`
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct ITimerVal {
+    pub it_interval: Timeval,
+    pub it_value: Timeval,
+}
+
+impl ITimerVal {
+    pub fn from_nanosecs(value: u64, interval: u64) -> ITimerVal {
+        ITimerVal {
+            it_interval: Timeval::from_nanosecs(interval),
+            it_value: Timeval::from_nanosecs(value),
+        }
+    }
+}
+
+/// Returns the remaining time and the interval of the interval timer in
+/// nanoseconds.
+pub(super) fn get_itimer(which: c_int) -> Result<(u64, u64)> {
+    let current = current_process();
+    let cpu_timer = match which {
+        ITIMER_REAL => {
+            let real_timer = current.thread_group().real_timer();
+            return Ok(real_timer.get());
+        }
+        ITIMER_VIRTUAL => *current.thread_group().virtual_timer_mut(),
+        ITIMER_PROF => *current.thread_group().prof_timer_mut(),
+        _ => return Err(Errno::EINVAL.into()),
+    };
+
+    Ok((cpu_timer.remaining, cpu_timer.interval))
+}
+
+/// Updates the interval timer. `value` of zero disarms the timer. Returns the
+/// previous remaining time and the interval.
+pub(super) fn set_itimer(which: c_int, value: u64, interval: u64) -> Result<(u64, u64)> {
+    let old = get_itimer(which)?;
+    let current = current_process();
+    let new_cpu_timer = CpuTimer {
+        remaining: value,
+        interval,
+    };
+
+    match which {
+        ITIMER_REAL => {
+            // Don't hold the thread group lock: the timer interrupt handler
+            // locks it to send the signal.
+            let real_timer = current.thread_group().real_timer();
+            let deadline = (value > 0).then(|| read_monotonic_clock().add_nanosecs(value));
+            real_timer.set(deadline, interval);
+        }
+        ITIMER_VIRTUAL => *current.thread_group().virtual_timer_mut() = new_cpu_timer,
+        ITIMER_PROF => *current.thread_group().prof_timer_mut() = new_cpu_timer,
+        _ => unreachable!(),
+    }
+
+    Ok(old)
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setitimer(
+        &mut self,
+        which: c_int,
+        new_value: UserVAddr,
+        old_value: Option<UserVAddr>,
+    ) -> Result<isize> {
+        let new_value = new_value.read::<ITimerVal>()?;
+        if !new_value.it_value.is_valid() || !new_value.it_interval.is_valid() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let (value, interval) = set_itimer(
+            which,
+            new_value.it_value.as_nanosecs(),
+            new_value.it_interval.as_nanosecs(),
+        )?;
+
+        if let Some(old_value) = old_value {
+            old_value.write(&ITimerVal::from_nanosecs(value, interval))?;
+        }
+
+        Ok(0)
+    }
+}
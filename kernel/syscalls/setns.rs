@@ -0,0 +1,49 @@
+use crate::{
+    ctypes::{c_int, CloneFlags},
+    fs::opened_file::Fd,
+    prelude::*,
+    process::{current_process, pidfd::PidFd},
+    syscalls::SyscallHandler,
+};
+use kerla_utils::downcast::downcast;
+
+use super::unshare::{check_single_threaded, NAMESPACE_FLAGS};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setns(&mut self, fd: Fd, nstype: c_int) -> Result<isize> {
+        let current = current_process();
+        let file = current.get_opened_file_by_fd(fd)?;
+        // Namespaces are referred by pidfds: namespace files in /proc are not
+        // supported.
+        let pidfd: Arc<PidFd> = downcast(file.as_file()?).ok_or(Errno::EINVAL)?;
+        let target = pidfd.process()?;
+
+        let flags = CloneFlags::from_bits(nstype)
+            .filter(|flags| !flags.is_empty() && NAMESPACE_FLAGS.contains(*flags))
+            .ok_or(Errno::EINVAL)?;
+
+        if !current.credentials().is_privileged() {
+            return Err(Errno::EPERM.into());
+        }
+
+        // A process can't join PID namespaces outside its own.
+        if flags.contains(CloneFlags::CLONE_NEWPID)
+            && !target.pid_ns().is_descendant_of(current.pid_ns())
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if flags.contains(CloneFlags::CLONE_NEWNS) {
+            check_single_threaded(current)?;
+            let root_fs = target.root_fs().clone();
+            current.set_root_fs(root_fs);
+        }
+
+        if flags.intersects(CloneFlags::CLONE_NEWUTS | CloneFlags::CLONE_NEWPID) {
+            let namespaces = current.namespaces().join(target, flags);
+            current.set_namespaces(namespaces);
+        }
+
+        Ok(0)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     prelude::*,
     process::process_group::PgId,
-    process::{current_process, process_group::ProcessGroup, PId, Process},
+    process::{current_process, process_group::ProcessGroup, PId},
     result::Result,
     syscalls::SyscallHandler,
 };
@@ -11,19 +11,43 @@ impl<'a> SyscallHandler<'a> {
         let current = if pid.as_i32() == 0 {
             current_process().clone()
         } else {
-            Process::find_by_pid(pid).ok_or_else(|| Error::new(Errno::ESRCH))?
+            current_process()
+                .pid_ns()
+                .find(pid)
+                .ok_or_else(|| Error::new(Errno::ESRCH))?
+        };
+
+        let pgid = if pgid.as_i32() == 0 {
+            PgId::new(current.pid().as_i32())
+        } else {
+            // The process group ID is in the current PID namespace.
+            let pgid = current_process()
+                .pid_ns()
+                .global_id(pgid.as_i32())
+                .ok_or(Errno::EPERM)?;
+            PgId::new(pgid)
         };
 
-        let new_pg = ProcessGroup::find_or_create_by_pgid(pgid);
-        let proc_weak = Arc::downgrade(&current);
         let old_pg = current.process_group();
+        let session = old_pg.lock().session().clone();
+        // The session leader cannot change its process group.
+        if session.lock().sid().as_i32() == current.pid().as_i32() {
+            return Err(Errno::EPERM.into());
+        }
+
+        let new_pg = ProcessGroup::find_or_create_by_pgid(pgid, session.clone());
+        // The process group must be in the same session.
+        if !Arc::ptr_eq(new_pg.lock().session(), &session) {
+            return Err(Errno::EPERM.into());
+        }
 
+        let proc_weak = Arc::downgrade(&current);
         if !Arc::ptr_eq(&old_pg, &new_pg) {
             old_pg.lock().remove(&proc_weak);
             new_pg.lock().add(proc_weak);
             current.set_process_group(Arc::downgrade(&new_pg));
         }
 
-        Ok(pgid.as_i32() as isize)
+        Ok(0)
     }
 }
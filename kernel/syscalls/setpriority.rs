@@ -0,0 +1,48 @@
+use crate::{
+    ctypes::c_int,
+    prelude::*,
+    process::{
+        current_process,
+        rlimit::RLIMIT_NICE,
+        scheduler::{NICE_MAX, NICE_MIN},
+        Process,
+    },
+    syscalls::SyscallHandler,
+};
+
+use super::getpriority::priority_targets;
+
+/// Checks if the current process is permitted to change the nice value of
+/// `proc` to `nice`. Lowering it below `20 - RLIMIT_NICE` requires the
+/// privilege.
+fn check_nice_permission(proc: &Process, nice: c_int) -> Result<()> {
+    let creds = current_process().credentials();
+    if !creds.can_reschedule(&proc.credentials()) {
+        return Err(Errno::EPERM.into());
+    }
+
+    if nice < proc.nice()
+        && !creds.is_privileged()
+        && (20 - nice) as u64 > proc.rlimit(RLIMIT_NICE)?.cur
+    {
+        return Err(Errno::EACCES.into());
+    }
+
+    Ok(())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setpriority(&mut self, which: c_int, who: c_int, nice: c_int) -> Result<isize> {
+        let nice = nice.clamp(NICE_MIN, NICE_MAX);
+        // Processes are updated even if some of them are not permitted.
+        let mut result = Ok(0);
+        for proc in priority_targets(which, who)? {
+            match check_nice_permission(&proc, nice) {
+                Ok(()) => proc.set_sched_params(proc.sched_policy(), nice),
+                Err(err) => result = Err(err),
+            }
+        }
+
+        result
+    }
+}
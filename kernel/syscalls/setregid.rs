@@ -0,0 +1,11 @@
+use crate::{fs::stat::GId, process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setregid(&mut self, gid: Option<GId>, egid: Option<GId>) -> Result<isize> {
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_re_gid(gid, egid)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
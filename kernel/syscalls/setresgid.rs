@@ -0,0 +1,16 @@
+use crate::{fs::stat::GId, process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setresgid(
+        &mut self,
+        gid: Option<GId>,
+        egid: Option<GId>,
+        sgid: Option<GId>,
+    ) -> Result<isize> {
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_res_gid(gid, egid, sgid)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
@@ -0,0 +1,16 @@
+use crate::{fs::stat::UId, process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setresuid(
+        &mut self,
+        uid: Option<UId>,
+        euid: Option<UId>,
+        suid: Option<UId>,
+    ) -> Result<isize> {
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_res_uid(uid, euid, suid)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
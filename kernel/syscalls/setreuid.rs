@@ -0,0 +1,11 @@
+use crate::{fs::stat::UId, process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setreuid(&mut self, uid: Option<UId>, euid: Option<UId>) -> Result<isize> {
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_re_uid(uid, euid)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
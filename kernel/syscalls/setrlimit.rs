@@ -0,0 +1,8 @@
+use crate::{ctypes::c_int, prelude::*, process::PId, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setrlimit(&mut self, resource: c_int, rlim: UserVAddr) -> Result<isize> {
+        self.sys_prlimit64(PId::new(0), resource, Some(rlim), None)
+    }
+}
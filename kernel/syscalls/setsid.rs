@@ -0,0 +1,30 @@
+use crate::{
+    prelude::*,
+    process::{
+        current_process,
+        process_group::{PgId, ProcessGroup},
+        session::{Session, SessionId},
+    },
+    result::Result,
+    syscalls::SyscallHandler,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setsid(&mut self) -> Result<isize> {
+        let current = current_process();
+        let pgid = PgId::new(current.pid().as_i32());
+        // A process group leader cannot create a new session.
+        if ProcessGroup::find_by_pgid(pgid).is_some() {
+            return Err(Errno::EPERM.into());
+        }
+
+        let sid = SessionId::new(current.pid().as_i32());
+        let new_pg = ProcessGroup::new(pgid, Session::new(sid));
+        let proc_weak = Arc::downgrade(current);
+        current.process_group().lock().remove(&proc_weak);
+        new_pg.lock().add(proc_weak);
+        current.set_process_group(Arc::downgrade(&new_pg));
+
+        Ok(sid.as_i32() as isize)
+    }
+}
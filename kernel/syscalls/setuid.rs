@@ -0,0 +1,11 @@
+use crate::{fs::stat::UId, process::current_process, result::Result, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_setuid(&mut self, uid: UId) -> Result<isize> {
+        let current = current_process();
+        let mut creds = (*current.credentials()).clone();
+        creds.set_uid(uid)?;
+        current.set_credentials(creds);
+        Ok(0)
+    }
+}
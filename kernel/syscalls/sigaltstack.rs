@@ -0,0 +1,63 @@
+use crate::prelude::*;
+use crate::process::current_process;
+use crate::process::signal::{SigAltStack, StackT, MINSIGSTKSZ, SS_DISABLE, SS_ONSTACK};
+use crate::syscalls::SyscallHandler;
+use kerla_runtime::address::UserVAddr;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_sigaltstack(
+        &mut self,
+        ss: Option<UserVAddr>,
+        old_ss: Option<UserVAddr>,
+    ) -> Result<isize> {
+        let current = current_process();
+        let current_stack = current.sigaltstack();
+        let on_stack =
+            matches!(current_stack, Some(stack) if stack.contains(self.frame.rsp as usize));
+
+        if let Some(ss) = ss {
+            let ss = ss.read::<StackT>()?;
+            if on_stack {
+                // Can't change the stack while we're using it.
+                return Err(Errno::EPERM.into());
+            }
+
+            if ss.ss_flags & !SS_DISABLE != 0 {
+                return Err(Errno::EINVAL.into());
+            }
+
+            let new_stack = if ss.ss_flags & SS_DISABLE != 0 {
+                None
+            } else {
+                if ss.ss_size < MINSIGSTKSZ {
+                    return Err(Errno::ENOMEM.into());
+                }
+
+                Some(SigAltStack {
+                    sp: UserVAddr::new_nonnull(ss.ss_sp)?,
+                    size: ss.ss_size,
+                })
+            };
+
+            current.set_sigaltstack(new_stack);
+        }
+
+        if let Some(old_ss) = old_ss {
+            let old = match current_stack {
+                Some(stack) => StackT {
+                    ss_sp: stack.sp.value(),
+                    ss_flags: if on_stack { SS_ONSTACK } else { 0 },
+                    ss_size: stack.size,
+                },
+                None => StackT {
+                    ss_sp: 0,
+                    ss_flags: SS_DISABLE,
+                    ss_size: 0,
+                },
+            };
+            old_ss.write(&old)?;
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,41 @@
+use crate::fs::path::Path;
+use crate::prelude::*;
+use crate::{
+    ctypes::c_uint,
+    process::current_process,
+    syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler},
+};
+use kerla_runtime::address::UserVAddr;
+
+/// Reserved for future extensions of `struct statx`.
+const STATX_RESERVED: c_uint = 0x8000_0000;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_statx(
+        &mut self,
+        dir: CwdOrFd,
+        path: &Path,
+        flags: AtFlags,
+        mask: c_uint,
+        buf: UserVAddr,
+    ) -> Result<isize> {
+        let supported_flags = AtFlags::AT_SYMLINK_NOFOLLOW
+            | AtFlags::AT_NO_AUTOMOUNT
+            | AtFlags::AT_EMPTY_PATH
+            | AtFlags::AT_STATX_FORCE_SYNC
+            | AtFlags::AT_STATX_DONT_SYNC;
+        let sync_flags = AtFlags::AT_STATX_FORCE_SYNC | AtFlags::AT_STATX_DONT_SYNC;
+        if !(flags - supported_flags).is_empty()
+            || flags.contains(sync_flags)
+            || mask & STATX_RESERVED != 0
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // We always fill all fields regardless of `mask`.
+        let creds = current_process().credentials();
+        let stat = lookup_path_at(&dir, path, flags, &creds)?.inode.stat()?;
+        buf.write(&stat.to_statx())?;
+        Ok(0)
+    }
+}
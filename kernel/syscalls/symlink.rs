@@ -0,0 +1,9 @@
+use crate::fs::path::Path;
+use crate::result::Result;
+use crate::syscalls::{CwdOrFd, SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_symlink(&mut self, target: &Path, link_path: &Path) -> Result<isize> {
+        self.sys_symlinkat(target, CwdOrFd::AtCwd, link_path)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::fs::path::Path;
+use crate::result::Result;
+use crate::{
+    process::{credentials::AccessMode, current_process},
+    syscalls::{CwdOrFd, SyscallHandler},
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_symlinkat(
+        &mut self,
+        target: &Path,
+        new_dir: CwdOrFd,
+        link_path: &Path,
+    ) -> Result<isize> {
+        let current = current_process();
+        let creds = current.credentials();
+        let root_fs = current.root_fs().clone();
+        let opened_files = current.opened_files().lock();
+        let (parent_dir, name) = root_fs.lock().lookup_parent_path_at(
+            &opened_files,
+            &new_dir,
+            link_path,
+            true,
+            &creds,
+        )?;
+        let parent_dir = parent_dir.inode.as_dir()?;
+        creds.check_access(&parent_dir.stat()?, AccessMode::W_OK | AccessMode::X_OK)?;
+        parent_dir.create_symlink(name, target, creds.euid, creds.egid)?;
+        Ok(0)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    ctypes::{c_clockid, c_int, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    prelude::*,
+    process::{
+        current_process,
+        signal::{SigInfo, SigInfoFields, Signal, SIGALRM, SIGMAX, SI_TIMER},
+    },
+    syscalls::SyscallHandler,
+    timer::IntervalTimer,
+};
+use kerla_runtime::address::UserVAddr;
+
+/// Sends the signal on expiry.
+const SIGEV_SIGNAL: c_int = 0;
+/// Does nothing on expiry.
+const SIGEV_NONE: c_int = 1;
+
+/// The head of `struct sigevent`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SigEvent {
+    /// Passed to the signal handler through `siginfo_t`.
+    sigev_value: usize,
+    sigev_signo: Signal,
+    sigev_notify: c_int,
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_timer_create(
+        &mut self,
+        clock: c_clockid,
+        sevp: Option<UserVAddr>,
+        timerid: UserVAddr,
+    ) -> Result<isize> {
+        if !matches!(clock, CLOCK_REALTIME | CLOCK_MONOTONIC) {
+            debug_warn!("timer_create: unsupported clock id: {}", clock);
+            return Err(Errno::EINVAL.into());
+        }
+
+        let (signal, value) = match sevp {
+            Some(sevp) => {
+                let sigevent = sevp.read::<SigEvent>()?;
+                match sigevent.sigev_notify {
+                    SIGEV_SIGNAL if (1..=SIGMAX).contains(&sigevent.sigev_signo) => {
+                        (Some(sigevent.sigev_signo), sigevent.sigev_value)
+                    }
+                    SIGEV_NONE => (None, 0),
+                    SIGEV_SIGNAL => return Err(Errno::EINVAL.into()),
+                    notify => {
+                        debug_warn!("timer_create: unsupported sigev_notify: {}", notify);
+                        return Err(Errno::EINVAL.into());
+                    }
+                }
+            }
+            None => (Some(SIGALRM), 0),
+        };
+
+        let current = current_process();
+        let mut thread_group = current.thread_group();
+        let leader = thread_group.leader().clone();
+        let id = thread_group.add_posix_timer(clock, |id| {
+            let info = signal.map(|signal| SigInfo {
+                signal,
+                code: SI_TIMER,
+                fields: SigInfoFields::Timer {
+                    id,
+                    overrun: 0,
+                    // The timer ID if sigevent is not given.
+                    value: if sevp.is_some() { value } else { id as usize },
+                },
+            });
+
+            IntervalTimer::new(leader, info)
+        });
+        drop(thread_group);
+
+        if let Err(err) = timerid.write::<c_int>(&id) {
+            current.thread_group().remove_posix_timer(id)?;
+            return Err(err.into());
+        }
+
+        Ok(0)
+    }
+}
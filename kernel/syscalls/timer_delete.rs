@@ -0,0 +1,11 @@
+use crate::{ctypes::c_int, prelude::*, process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_timer_delete(&mut self, timerid: c_int) -> Result<isize> {
+        let posix_timer = current_process()
+            .thread_group()
+            .remove_posix_timer(timerid)?;
+        posix_timer.timer.set(None, 0);
+        Ok(0)
+    }
+}
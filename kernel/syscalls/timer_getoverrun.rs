@@ -0,0 +1,9 @@
+use crate::{ctypes::c_int, prelude::*, process::current_process, syscalls::SyscallHandler};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_timer_getoverrun(&mut self, timerid: c_int) -> Result<isize> {
+        let posix_timer = current_process().thread_group().posix_timer(timerid)?;
+        let overrun = posix_timer.timer.overrun();
+        Ok(overrun.min(c_int::MAX as u64) as isize)
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{ctypes::c_int, prelude::*, process::current_process, syscalls::SyscallHandler};
+use kerla_runtime::address::UserVAddr;
+
+use super::timer_settime::ITimerSpec;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_timer_gettime(&mut self, timerid: c_int, curr_value: UserVAddr) -> Result<isize> {
+        let posix_timer = current_process().thread_group().posix_timer(timerid)?;
+        curr_value.write(&ITimerSpec::from_timer(&posix_timer.timer))?;
+        Ok(0)
+    }
+}
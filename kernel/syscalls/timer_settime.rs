@@ -0,0 +1,67 @@
+use crate::{
+    ctypes::{c_int, CLOCK_REALTIME},
+    prelude::*,
+    process::current_process,
+    syscalls::SyscallHandler,
+    timer::{read_monotonic_clock, IntervalTimer, MonotonicClock, Timespec, WallClock},
+};
+use kerla_runtime::address::UserVAddr;
+
+/// `it_value` is an absolute time.
+const TIMER_ABSTIME: c_int = 1;
+
+/// `struct itimerspec`
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct ITimerSpec {
+    pub it_interval: Timespec,
+    pub it_value: Timespec,
+}
+
+impl ITimerSpec {
+    /// Returns the current setting of the timer.
+    pub fn from_timer(timer: &IntervalTimer) -> ITimerSpec {
+        let (remaining, interval) = timer.get();
+        ITimerSpec {
+            it_interval: Timespec::from_nanosecs(interval),
+            it_value: Timespec::from_nanosecs(remaining),
+        }
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_timer_settime(
+        &mut self,
+        timerid: c_int,
+        flags: c_int,
+        new_value: UserVAddr,
+        old_value: Option<UserVAddr>,
+    ) -> Result<isize> {
+        let posix_timer = current_process().thread_group().posix_timer(timerid)?;
+        let new_value = new_value.read::<ITimerSpec>()?;
+        if !new_value.it_value.is_valid() || !new_value.it_interval.is_valid() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if let Some(old_value) = old_value {
+            old_value.write(&ITimerSpec::from_timer(&posix_timer.timer))?;
+        }
+
+        let value = new_value.it_value.as_nanosecs();
+        let deadline = if value == 0 {
+            // Disarm the timer.
+            None
+        } else if flags & TIMER_ABSTIME == 0 {
+            Some(read_monotonic_clock().add_nanosecs(value))
+        } else if posix_timer.clock == CLOCK_REALTIME {
+            Some(WallClock::from_nanosecs_from_epoch(value).to_monotonic())
+        } else {
+            Some(MonotonicClock::from_nanosecs(value))
+        };
+
+        posix_timer
+            .timer
+            .set(deadline, new_value.it_interval.as_nanosecs());
+        Ok(0)
+    }
+}
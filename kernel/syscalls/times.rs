@@ -0,0 +1,46 @@
+use crate::{
+    ctypes::c_clock, prelude::*, process::current_process, syscalls::SyscallHandler,
+    timer::read_monotonic_clock,
+};
+use kerla_runtime::address::UserVAddr;
+
+/// The unit of `clock_t` values (`sysconf(_SC_CLK_TCK)`).
+const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+fn nanosecs_to_clock_ticks(nanosecs: u64) -> c_clock {
+    (nanosecs / (1_000_000_000 / CLOCK_TICKS_PER_SEC)) as c_clock
+}
+
+/// `struct tms`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct Tms {
+    tms_utime: c_clock,
+    tms_stime: c_clock,
+    tms_cutime: c_clock,
+    tms_cstime: c_clock,
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_times(&mut self, buf: Option<UserVAddr>) -> Result<isize> {
+        let current = current_process();
+        current.account_cpu_time();
+
+        if let Some(buf) = buf {
+            let (cpu_times, children_cpu_times) = {
+                let thread_group = current.thread_group();
+                (thread_group.cpu_times(), thread_group.children_cpu_times())
+            };
+
+            buf.write(&Tms {
+                tms_utime: nanosecs_to_clock_ticks(cpu_times.utime),
+                tms_stime: nanosecs_to_clock_ticks(cpu_times.stime),
+                tms_cutime: nanosecs_to_clock_ticks(children_cpu_times.utime),
+                tms_cstime: nanosecs_to_clock_ticks(children_cpu_times.stime),
+            })?;
+        }
+
+        // The elapsed real time since an arbitrary point in the past.
+        Ok(nanosecs_to_clock_ticks(read_monotonic_clock().nanosecs() as u64) as isize)
+    }
+}
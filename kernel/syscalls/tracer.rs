@@ -0,0 +1,329 @@
+//! The in-kernel system call tracer.
+//!
+//! Write a PID or a command name to `/proc/syscall_trace_control` and every
+//! system call issued by the matching processes is logged into a ring buffer
+//! readable from `/proc/syscall_trace`. Append ` serial` to the target to
+//! copy the log to the serial console as well, and write `off` to stop
+//! tracing.
+//!
+//! Don't read the trace log from a traced process: the log grows as it reads.
+use core::{
+    fmt::{self, Write},
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering},
+};
+
+use kerla_runtime::spinlock::SpinLock;
+use kerla_utils::ring_buffer::RingBuffer;
+
+use crate::{
+    prelude::*,
+    process::{current_process, PId},
+};
+
+use super::*;
+
+pub const TRACE_LOG_SIZE: usize = 16384;
+
+/// The processes to be traced.
+#[derive(Debug, Clone)]
+pub enum TraceTarget {
+    Pid(PId),
+    /// The command name (`argv[0]` or its basename).
+    Comm(String),
+}
+
+impl fmt::Display for TraceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceTarget::Pid(pid) => write!(f, "{}", pid.as_i32()),
+            TraceTarget::Comm(comm) => write!(f, "{}", comm),
+        }
+    }
+}
+
+/// Checked on every system call without taking the lock.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOG_TO_SERIAL: AtomicBool = AtomicBool::new(false);
+/// The PID of `TraceTarget::Pid`, or 0 if the target is a command name.
+static TARGET_PID: AtomicI32 = AtomicI32::new(0);
+/// Incremented on every change of the target. Each process caches whether
+/// its command name matches the target along with the generation not to lock
+/// `TARGET` on every system call (see [`crate::process::Process::trace_match`]).
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+static TARGET: SpinLock<Option<TraceTarget>> = SpinLock::new(None);
+pub static TRACE_LOG: SpinLock<RingBuffer<u8, TRACE_LOG_SIZE>> = SpinLock::new(RingBuffer::new());
+
+/// Updates the tracer settings from a line written to the control file:
+/// `off`, or a PID or a command name optionally followed by `serial`.
+pub fn configure(line: &str) -> Result<()> {
+    let mut words = line.split_whitespace();
+    let target = match words.next() {
+        None | Some("off") => None,
+        Some(word) => match i32::from_str(word) {
+            Ok(pid) if pid > 0 => Some(TraceTarget::Pid(PId::new(pid))),
+            Ok(_) => return Err(Errno::EINVAL.into()),
+            Err(_) => Some(TraceTarget::Comm(word.to_owned())),
+        },
+    };
+
+    let serial = match words.next() {
+        None => false,
+        Some("serial") if target.is_some() => true,
+        Some(_) => return Err(Errno::EINVAL.into()),
+    };
+
+    if words.next().is_some() {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let pid = match &target {
+        Some(TraceTarget::Pid(pid)) => pid.as_i32(),
+        _ => 0,
+    };
+
+    let enabled = target.is_some();
+    let mut target_lock = TARGET.lock();
+    *target_lock = target;
+    TARGET_PID.store(pid, Ordering::SeqCst);
+    // Invalidate the cached results. 0 means unknown: skip it on wraparound.
+    if GENERATION.fetch_add(1, Ordering::SeqCst) == u32::MAX {
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+    drop(target_lock);
+
+    LOG_TO_SERIAL.store(serial, Ordering::SeqCst);
+    ENABLED.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Returns the current settings in the format accepted by [`configure`].
+pub fn current_settings() -> String {
+    match TARGET.lock().as_ref() {
+        Some(target) if LOG_TO_SERIAL.load(Ordering::SeqCst) => format!("{} serial", target),
+        Some(target) => target.to_string(),
+        None => "off".to_owned(),
+    }
+}
+
+/// Returns `true` if the current process is being traced.
+fn is_tracing_current() -> bool {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let current = current_process();
+    let pid = TARGET_PID.load(Ordering::Relaxed);
+    if pid != 0 {
+        return current.pid().as_i32() == pid;
+    }
+
+    // The cached value is the generation shifted left by one bit with the
+    // result in the lowest bit.
+    let generation = GENERATION.load(Ordering::Relaxed) as u64;
+    let cached = current.trace_match();
+    if cached >> 1 == generation {
+        return cached & 1 != 0;
+    }
+
+    let target = TARGET.lock();
+    let matched = match target.as_ref() {
+        Some(TraceTarget::Comm(comm)) => {
+            let cmdline = current.cmdline();
+            let argv0 = cmdline.argv0();
+            argv0 == comm || argv0.rsplit('/').next() == Some(comm.as_str())
+        }
+        _ => false,
+    };
+
+    let generation = GENERATION.load(Ordering::Relaxed) as u64;
+    current.set_trace_match((generation << 1) | matched as u64);
+    matched
+}
+
+fn record(line: &str) {
+    {
+        let mut log = TRACE_LOG.lock();
+        let mut remaining = line.as_bytes();
+        while !remaining.is_empty() {
+            let written = log.push_slice(remaining);
+            remaining = &remaining[written..];
+            if !remaining.is_empty() {
+                // The buffer is full: drop the oldest entries.
+                log.pop_slice(remaining.len());
+            }
+        }
+    }
+
+    if LOG_TO_SERIAL.load(Ordering::Relaxed) {
+        info!("strace: {}", line.trim_end());
+    }
+}
+
+/// How a system call argument is decoded.
+#[derive(Clone, Copy)]
+enum Arg {
+    Int,
+    Hex,
+    Ptr,
+    Fd,
+    /// A directory file descriptor or `AT_FDCWD`.
+    DirFd,
+    Path,
+    OpenFlags,
+    Mode,
+}
+
+fn arg_kinds(n: usize) -> &'static [Arg] {
+    use Arg::*;
+    match n {
+        SYS_READ | SYS_WRITE | SYS_WRITEV | SYS_GETDENTS64 => &[Fd, Ptr, Int],
+        SYS_OPEN => &[Path, OpenFlags, Mode],
+        SYS_CLOSE | SYS_FSYNC => &[Fd],
+        SYS_FSTAT => &[Fd, Ptr],
+        SYS_STAT | SYS_LSTAT | SYS_UTIMES => &[Path, Ptr],
+        SYS_POLL => &[Ptr, Int, Int],
+        SYS_MMAP => &[Ptr, Int, Hex, Hex, Fd, Int],
+        SYS_MPROTECT => &[Ptr, Int, Hex],
+        SYS_MUNMAP => &[Ptr, Int],
+        SYS_BRK | SYS_PIPE => &[Ptr],
+        SYS_IOCTL => &[Fd, Hex, Ptr],
+        SYS_ACCESS | SYS_MKDIR | SYS_CHMOD => &[Path, Mode],
+        SYS_DUP2 => &[Fd, Fd],
+        SYS_KILL => &[Int, Int],
+        SYS_EXECVE => &[Path, Ptr, Ptr],
+        SYS_EXIT | SYS_EXIT_GROUP => &[Int],
+        SYS_WAIT4 => &[Int, Ptr, Hex, Ptr],
+        SYS_FCNTL => &[Fd, Int, Hex],
+        SYS_CHDIR => &[Path],
+        SYS_READLINK => &[Path, Ptr, Int],
+        SYS_CHOWN | SYS_LCHOWN => &[Path, Int, Int],
+        SYS_FCHOWN => &[Fd, Int, Int],
+        SYS_FCHMOD => &[Fd, Mode],
+        SYS_LINK => &[Path, Path],
+        SYS_LINKAT => &[DirFd, Path, DirFd, Path, Hex],
+        SYS_FACCESSAT | SYS_FACCESSAT2 => &[DirFd, Path, Mode, Hex],
+        SYS_OPENAT => &[DirFd, Path, OpenFlags, Mode],
+        SYS_NEWFSTATAT | SYS_UTIMENSAT => &[DirFd, Path, Ptr, Hex],
+        SYS_STATX => &[DirFd, Path, Hex, Hex, Ptr],
+        SYS_MKDIRAT | SYS_FCHMODAT => &[DirFd, Path, Mode],
+        SYS_UNLINKAT => &[DirFd, Path, Hex],
+        SYS_READLINKAT => &[DirFd, Path, Ptr, Int],
+        SYS_SOCKET => &[Int, Int, Int],
+        SYS_CONNECT | SYS_BIND | SYS_ACCEPT => &[Fd, Ptr, Int],
+        SYS_SENDTO | SYS_RECVFROM => &[Fd, Ptr, Int, Hex, Ptr, Int],
+        SYS_LISTEN | SYS_SHUTDOWN => &[Fd, Int],
+        SYS_GETPID | SYS_GETPPID | SYS_GETTID | SYS_GETUID | SYS_GETGID | SYS_GETEUID
+        | SYS_GETEGID | SYS_SETSID | SYS_SCHED_YIELD | SYS_FORK | SYS_VFORK | SYS_RT_SIGRETURN => {
+            &[]
+        }
+        _ => &[Hex, Hex, Hex, Hex, Hex, Hex],
+    }
+}
+
+const OPEN_FLAG_NAMES: &[(usize, &str)] = &[
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o100000, "O_LARGEFILE"),
+    (0o200000, "O_DIRECTORY"),
+    (0o400000, "O_NOFOLLOW"),
+    (0o2000000, "O_CLOEXEC"),
+];
+
+fn write_open_flags(w: &mut String, flags: usize) -> fmt::Result {
+    w.write_str(match flags & 0o3 {
+        0o0 => "O_RDONLY",
+        0o1 => "O_WRONLY",
+        0o2 => "O_RDWR",
+        _ => "O_ACCMODE",
+    })?;
+
+    let mut rest = flags & !0o3;
+    for (bit, name) in OPEN_FLAG_NAMES {
+        if rest & bit != 0 {
+            write!(w, "|{}", name)?;
+            rest &= !bit;
+        }
+    }
+
+    if rest != 0 {
+        write!(w, "|{:#o}", rest)?;
+    }
+
+    Ok(())
+}
+
+fn write_arg(w: &mut String, kind: Arg, value: usize) -> fmt::Result {
+    match kind {
+        Arg::Int => write!(w, "{}", value as isize),
+        Arg::Hex => write!(w, "{:#x}", value),
+        Arg::Ptr if value == 0 => write!(w, "NULL"),
+        Arg::Ptr => write!(w, "{:#x}", value),
+        Arg::Fd => write!(w, "{}", value as c_int),
+        Arg::DirFd if value as c_int == -100 => write!(w, "AT_FDCWD"),
+        Arg::DirFd => write!(w, "{}", value as c_int),
+        Arg::Path => match resolve_path(value) {
+            Ok(path) => write!(w, "{:?}", path.as_str()),
+            Err(_) => write!(w, "{:#x}", value),
+        },
+        Arg::OpenFlags => write_open_flags(w, value),
+        Arg::Mode => write!(w, "{:#o}", value),
+    }
+}
+
+fn write_prefix(w: &mut String) -> fmt::Result {
+    let current = current_process();
+    write!(
+        w,
+        "[{}:{}] ",
+        current.pid().as_i32(),
+        current.cmdline().argv0()
+    )
+}
+
+/// Logs a system call entering [`SyscallHandler::dispatch`].
+pub fn trace_syscall_entry(n: usize, args: &[usize; 6]) {
+    if !is_tracing_current() {
+        return;
+    }
+
+    let mut line = String::new();
+    let _ = write_prefix(&mut line);
+    let _ = write!(line, "{}(", syscall_name_by_number(n));
+    for (i, (kind, value)) in arg_kinds(n).iter().zip(args.iter()).enumerate() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        let _ = write_arg(&mut line, *kind, *value);
+    }
+    line.push_str(") ...\n");
+    record(&line);
+}
+
+/// Logs a system call leaving [`SyscallHandler::dispatch`].
+pub fn trace_syscall_exit(n: usize, ret: &Result<isize>) {
+    if !is_tracing_current() {
+        return;
+    }
+
+    let mut line = String::new();
+    let _ = write_prefix(&mut line);
+    let _ = match ret {
+        Ok(value) if matches!(n, SYS_MMAP | SYS_BRK) => {
+            writeln!(line, "{} = {:#x}", syscall_name_by_number(n), value)
+        }
+        Ok(value) => writeln!(line, "{} = {}", syscall_name_by_number(n), value),
+        Err(err) => writeln!(
+            line,
+            "{} = -1 {:?} ({})",
+            syscall_name_by_number(n),
+            err.errno(),
+            err.errno() as i32
+        ),
+    };
+    record(&line);
+}
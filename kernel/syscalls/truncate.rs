@@ -0,0 +1,25 @@
+use crate::{
+    fs::path::Path,
+    prelude::*,
+    process::{credentials::AccessMode, current_process},
+    syscalls::SyscallHandler,
+};
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_truncate(&mut self, path: &Path, length: isize) -> Result<isize> {
+        if length < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        let creds = current.credentials();
+        let inode = current.root_fs().lock().lookup(path, &creds)?;
+        if inode.is_dir() {
+            return Err(Errno::EISDIR.into());
+        }
+
+        creds.check_access(&inode.stat()?, AccessMode::W_OK)?;
+        inode.truncate(length as usize)?;
+        Ok(0)
+    }
+}
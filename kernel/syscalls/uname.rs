@@ -1,3 +1,4 @@
+use crate::process::current_process;
 use crate::result::Result;
 use crate::syscalls::SyscallHandler;
 use kerla_runtime::address::UserVAddr;
@@ -10,11 +11,12 @@ const UTS_FIELD_LEN: usize = 65;
 
 impl<'a> SyscallHandler<'a> {
     pub fn sys_uname(&mut self, buf: UserVAddr) -> Result<isize> {
+        let uts = current_process().namespaces().uts().clone();
         let mut writer = UserBufWriter::from_uaddr(buf, 6 * UTS_FIELD_LEN);
         // sysname
         writer.write_bytes_or_zeroes(b"Linux", UTS_FIELD_LEN)?;
         // nodename
-        writer.write_bytes_or_zeroes(b"", UTS_FIELD_LEN)?;
+        writer.write_bytes_or_zeroes(uts.hostname().as_bytes(), UTS_FIELD_LEN)?;
         // release
         // We use a hard-coded release number instead of using our own version
         // because glibc checks the kernel version to determine supported
@@ -25,7 +27,7 @@ impl<'a> SyscallHandler<'a> {
         // machine
         writer.write_bytes_or_zeroes(b"", UTS_FIELD_LEN)?;
         // domainname
-        writer.write_bytes_or_zeroes(b"", UTS_FIELD_LEN)?;
+        writer.write_bytes_or_zeroes(uts.domainname().as_bytes(), UTS_FIELD_LEN)?;
         Ok(0)
     }
 }
@@ -0,0 +1,74 @@
+use crate::{
+    fs::{
+        inode::{Directory, INode},
+        path::Path,
+        stat::S_ISVTX,
+    },
+    prelude::*,
+    process::{
+        credentials::{AccessMode, Credentials},
+        current_process,
+    },
+    syscalls::{AtFlags, CwdOrFd, SyscallHandler},
+};
+
+/// Checks if the process is permitted to remove (or rename) the entry `name`
+/// in `dir`.
+pub(super) fn check_removable(
+    dir: &Arc<dyn Directory>,
+    name: &str,
+    creds: &Credentials,
+) -> Result<()> {
+    let dir_stat = dir.stat()?;
+    creds.check_access(&dir_stat, AccessMode::W_OK | AccessMode::X_OK)?;
+
+    // In a sticky directory (e.g. /tmp), only the owner of the entry or the
+    // directory can remove it.
+    let target_stat = dir.lookup(name)?.stat()?;
+    let mode = dir_stat.mode;
+    if mode.permission() & S_ISVTX != 0 && !creds.owns(&dir_stat) && !creds.owns(&target_stat) {
+        return Err(Errno::EPERM.into());
+    }
+
+    Ok(())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_unlinkat(&mut self, dir: CwdOrFd, path: &Path, flags: AtFlags) -> Result<isize> {
+        if !(flags - AtFlags::AT_REMOVEDIR).is_empty() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        let creds = current.credentials();
+        let root_fs = current.root_fs().clone();
+        let root_fs = root_fs.lock();
+        let opened_files = current.opened_files().lock();
+        let (parent_dir, name) =
+            root_fs.lookup_parent_path_at(&opened_files, &dir, path, true, &creds)?;
+
+        let remove_dir = flags.contains(AtFlags::AT_REMOVEDIR);
+        match name {
+            "." if remove_dir => return Err(Errno::EINVAL.into()),
+            ".." if remove_dir => return Err(Errno::ENOTEMPTY.into()),
+            "." | ".." => return Err(Errno::EISDIR.into()),
+            _ => {}
+        }
+
+        let parent_dir = parent_dir.inode.as_dir()?;
+        check_removable(parent_dir, name, &creds)?;
+        if remove_dir {
+            if let INode::Directory(dir) = parent_dir.lookup(name)? {
+                if root_fs.is_mount_point(&dir)? {
+                    return Err(Errno::EBUSY.into());
+                }
+            }
+
+            parent_dir.rmdir(name)?;
+        } else {
+            parent_dir.unlink(name)?;
+        }
+
+        Ok(0)
+    }
+}
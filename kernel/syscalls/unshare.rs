@@ -0,0 +1,55 @@
+use crate::{
+    ctypes::CloneFlags,
+    prelude::*,
+    process::{current_process, Process},
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::spinlock::SpinLock;
+
+/// Namespaces which can be created by `clone(2)` and `unshare(2)`.
+pub(super) const NAMESPACE_FLAGS: CloneFlags = CloneFlags::from_bits_truncate(
+    CloneFlags::CLONE_NEWNS.bits()
+        | CloneFlags::CLONE_NEWUTS.bits()
+        | CloneFlags::CLONE_NEWPID.bits(),
+);
+
+/// Returns `EINVAL` if the current process has other threads: they share the
+/// mount namespace.
+pub(super) fn check_single_threaded(current: &Process) -> Result<()> {
+    let threads = current.thread_group().threads().len();
+    if threads > 1 {
+        return Err(Errno::EINVAL.into());
+    }
+
+    Ok(())
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_unshare(&mut self, flags: CloneFlags) -> Result<isize> {
+        // Unsharing other resources (e.g. CLONE_FILES) is not supported.
+        if !NAMESPACE_FLAGS.contains(flags) {
+            debug_warn!("unshare: unsupported flags: {:?}", flags);
+            return Err(Errno::EINVAL.into());
+        }
+
+        let current = current_process();
+        if !flags.is_empty() && !current.credentials().is_privileged() {
+            return Err(Errno::EPERM.into());
+        }
+
+        if flags.contains(CloneFlags::CLONE_NEWNS) {
+            check_single_threaded(current)?;
+            let root_fs = current.root_fs().lock().clone();
+            current.set_root_fs(Arc::new(SpinLock::new(root_fs)));
+        }
+
+        // A new PID namespace is used by children: the current process stays
+        // in the current one.
+        if flags.intersects(CloneFlags::CLONE_NEWUTS | CloneFlags::CLONE_NEWPID) {
+            let namespaces = current.namespaces().unshare(flags)?;
+            current.set_namespaces(namespaces);
+        }
+
+        Ok(0)
+    }
+}
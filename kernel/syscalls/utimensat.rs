@@ -0,0 +1,106 @@
+use kerla_runtime::address::UserVAddr;
+
+use crate::ctypes::c_long;
+use crate::fs::{inode::INode, path::Path, stat::Time};
+use crate::prelude::*;
+use crate::process::credentials::AccessMode;
+use crate::timer::Timespec;
+use crate::{
+    process::current_process,
+    syscalls::{lookup_path_at, AtFlags, CwdOrFd, SyscallHandler},
+};
+
+/// Set the timestamp to the current time.
+const UTIME_NOW: c_long = (1 << 30) - 1;
+/// Leave the timestamp unchanged.
+const UTIME_OMIT: c_long = (1 << 30) - 2;
+
+/// A new timestamp requested in `utimensat(2)` and `utimes(2)`.
+#[derive(Clone, Copy)]
+pub(super) enum UtimeSpec {
+    Now,
+    Omit,
+    Time(Time),
+}
+
+impl UtimeSpec {
+    fn from_timespec(time: &Timespec) -> Result<UtimeSpec> {
+        match time.tv_nsec() {
+            UTIME_NOW => Ok(UtimeSpec::Now),
+            UTIME_OMIT => Ok(UtimeSpec::Omit),
+            _ if time.is_valid() => Ok(UtimeSpec::Time(Time::from_nanosecs(time.as_nanosecs()))),
+            _ => Err(Errno::EINVAL.into()),
+        }
+    }
+
+    fn resolve(self, now: Time) -> Option<Time> {
+        match self {
+            UtimeSpec::Now => Some(now),
+            UtimeSpec::Omit => None,
+            UtimeSpec::Time(time) => Some(time),
+        }
+    }
+}
+
+/// Updates the access and modification time of `inode`.
+pub(super) fn utimes_inode(inode: &INode, atime: UtimeSpec, mtime: UtimeSpec) -> Result<()> {
+    if matches!((atime, mtime), (UtimeSpec::Omit, UtimeSpec::Omit)) {
+        return Ok(());
+    }
+
+    // Setting the current time requires the write permission. Other values
+    // can be set only by the owner.
+    let creds = current_process().credentials();
+    let stat = inode.stat()?;
+    if !creds.owns(&stat) {
+        let only_now = matches!(atime, UtimeSpec::Now | UtimeSpec::Omit)
+            && matches!(mtime, UtimeSpec::Now | UtimeSpec::Omit);
+        if !only_now {
+            return Err(Errno::EPERM.into());
+        }
+
+        creds.check_access(&stat, AccessMode::W_OK)?;
+    }
+
+    let now = Time::now();
+    inode.set_times(atime.resolve(now), mtime.resolve(now))
+}
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_utimensat(
+        &mut self,
+        dir: CwdOrFd,
+        path: Option<&Path>,
+        times: Option<UserVAddr>,
+        flags: AtFlags,
+    ) -> Result<isize> {
+        if !(flags - (AtFlags::AT_SYMLINK_NOFOLLOW | AtFlags::AT_EMPTY_PATH)).is_empty() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let (atime, mtime) = match times {
+            Some(times) => {
+                let [atime, mtime]: [Timespec; 2] = times.read()?;
+                (
+                    UtimeSpec::from_timespec(&atime)?,
+                    UtimeSpec::from_timespec(&mtime)?,
+                )
+            }
+            None => (UtimeSpec::Now, UtimeSpec::Now),
+        };
+
+        let current = current_process();
+        let path_comp = match (path, &dir) {
+            (Some(path), _) => lookup_path_at(&dir, path, flags, &current.credentials())?,
+            // futimens(3): the file referred by the fd.
+            (None, CwdOrFd::Fd(fd)) if flags.is_empty() => {
+                current.get_opened_file_by_fd(*fd)?.path().clone()
+            }
+            (None, CwdOrFd::Fd(_)) => return Err(Errno::EINVAL.into()),
+            (None, CwdOrFd::AtCwd) => return Err(Errno::EFAULT.into()),
+        };
+
+        utimes_inode(&path_comp.inode, atime, mtime)?;
+        Ok(0)
+    }
+}
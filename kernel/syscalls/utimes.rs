@@ -1,14 +1,34 @@
 use kerla_runtime::address::UserVAddr;
 
-use crate::fs::path::Path;
+use super::utimensat::{utimes_inode, UtimeSpec};
+use crate::fs::{path::Path, stat::Time};
 use crate::prelude::*;
+use crate::timer::Timeval;
 use crate::{process::current_process, syscalls::SyscallHandler};
 
 impl<'a> SyscallHandler<'a> {
-    pub fn sys_utimes(&mut self, path: &Path, _times: Option<UserVAddr>) -> Result<isize> {
-        // TODO: Currently we don't modify the file metadata: Return ENOENT if
-        //       the file exists for touch(1).
-        current_process().root_fs().lock().lookup_file(path)?;
+    pub fn sys_utimes(&mut self, path: &Path, times: Option<UserVAddr>) -> Result<isize> {
+        let (atime, mtime) = match times {
+            Some(times) => {
+                let [atime, mtime]: [Timeval; 2] = times.read()?;
+                if !atime.is_valid() || !mtime.is_valid() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                (
+                    UtimeSpec::Time(Time::from_nanosecs(atime.as_nanosecs())),
+                    UtimeSpec::Time(Time::from_nanosecs(mtime.as_nanosecs())),
+                )
+            }
+            None => (UtimeSpec::Now, UtimeSpec::Now),
+        };
+
+        let current = current_process();
+        let inode = current
+            .root_fs()
+            .lock()
+            .lookup(path, &current.credentials())?;
+        utimes_inode(&inode, atime, mtime)?;
         Ok(0)
     }
 }
@@ -1,60 +1,278 @@
 use crate::{
     ctypes::*,
+    fs::stat::UId,
     prelude::*,
-    process::{current_process, PId, ProcessState, JOIN_WAIT_QUEUE},
-    syscalls::SyscallHandler,
+    process::{
+        current_process,
+        process_group::PgId,
+        signal::{
+            Signal, CLD_CONTINUED, CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, CLD_TRAPPED,
+            SIGCONT,
+        },
+        thread_group::{CpuTimes, JobControlEvent},
+        PId, Process, ProcessState, JOIN_WAIT_QUEUE, WCOREFLAG,
+    },
+    syscalls::{global_pgid, SyscallHandler},
 };
 
+use super::getrusage::RUsage;
 use bitflags::bitflags;
 use kerla_runtime::address::UserVAddr;
 
 bitflags! {
     pub struct WaitOptions: c_int {
-        const WNOHANG   = 1;
-        const WUNTRACED = 2;
+        const WNOHANG    = 1;
+        /// Also known as `WSTOPPED`.
+        const WUNTRACED  = 2;
+        const WEXITED    = 4;
+        const WCONTINUED = 8;
+        const WNOWAIT    = 0x0100_0000;
+        const __WNOTHREAD = 0x2000_0000;
+        const __WALL     = 0x4000_0000;
     }
 }
 
+/// Children to wait for.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum WaitTarget {
+    Any,
+    Pid(PId),
+    ProcessGroup(PgId),
+}
+
+/// A state change of a child.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum ChildState {
+    /// The child has exited. The value is the wait status.
+    Exited(c_int),
+    /// The child has been stopped by the signal.
+    Stopped(Signal),
+    /// The stopped child has been continued.
+    Continued,
+    /// The tracee is in a ptrace-stop. The value is the stop status.
+    Traced(c_int),
+}
+
+impl ChildState {
+    /// The status returned by `wait4(2)`.
+    pub fn wait_status(self) -> c_int {
+        match self {
+            ChildState::Exited(status) => status,
+            ChildState::Stopped(signal) => (signal << 8) | 0x7f,
+            ChildState::Continued => 0xffff,
+            ChildState::Traced(status) => (status << 8) | 0x7f,
+        }
+    }
+
+    /// `si_code` and `si_status` reported by `waitid(2)`.
+    pub fn code_and_status(self) -> (c_int, c_int) {
+        match self {
+            ChildState::Exited(status) if status & 0x7f == 0 => (CLD_EXITED, (status >> 8) & 0xff),
+            ChildState::Exited(status) if status & WCOREFLAG != 0 => (CLD_DUMPED, status & 0x7f),
+            ChildState::Exited(status) => (CLD_KILLED, status & 0x7f),
+            ChildState::Stopped(signal) => (CLD_STOPPED, signal),
+            ChildState::Continued => (CLD_CONTINUED, SIGCONT),
+            ChildState::Traced(status) => (CLD_TRAPPED, status & 0x7f),
+        }
+    }
+}
+
+/// A child whose state change has been waited for.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct WaitedChild {
+    /// The process ID in the current PID namespace.
+    pub pid: PId,
+    /// The real user ID.
+    pub uid: UId,
+    pub state: ChildState,
+    /// CPU times used by the child and its reaped descendants.
+    pub cpu_times: CpuTimes,
+}
+
+impl WaitTarget {
+    /// `pid` is the ID seen from the current PID namespace.
+    fn matches(self, proc: &Process, pid: PId) -> bool {
+        match self {
+            WaitTarget::Any => true,
+            WaitTarget::Pid(target) => pid == target,
+            WaitTarget::ProcessGroup(pgid) => proc.process_group().lock().pgid() == pgid,
+        }
+    }
+}
+
+/// Waits for a state change of a child or a tracee specified by `target` and
+/// `options`. Returns `None` if `WNOHANG` is given and no children have
+/// changed the state. An exited child is reaped unless `WNOWAIT` is given.
+pub(super) fn wait_for_child(
+    target: WaitTarget,
+    options: WaitOptions,
+) -> Result<Option<WaitedChild>> {
+    let result = JOIN_WAIT_QUEUE.sleep_signalable_until(|| {
+        let current = current_process();
+        let pid_ns = current.pid_ns();
+        let mut found = false;
+
+        // Ptrace-stops are reported regardless of WUNTRACED. Tracees are
+        // identified by their thread IDs.
+        for tracee in current.tracees().iter() {
+            if !target.matches(tracee, pid_ns.tid_of(tracee)) {
+                continue;
+            }
+
+            found = true;
+
+            let is_child = tracee
+                .parent()
+                .map_or(false, |parent| parent.pid() == current.pid());
+            if let ProcessState::ExitedWith(status) = tracee.state() {
+                // An exited child is reaped below.
+                if !is_child && options.contains(WaitOptions::WEXITED) {
+                    return Ok(Some(Some(WaitedChild {
+                        pid: pid_ns.tid_of(tracee),
+                        uid: tracee.credentials().uid,
+                        state: ChildState::Exited(status),
+                        cpu_times: tracee.cpu_times(),
+                    })));
+                }
+
+                continue;
+            }
+
+            let report = tracee.ptrace().as_mut().and_then(|ptrace| {
+                if ptrace.is_traced_by(current) {
+                    ptrace.take_report(!options.contains(WaitOptions::WNOWAIT))
+                } else {
+                    None
+                }
+            });
+
+            if let Some(status) = report {
+                return Ok(Some(Some(WaitedChild {
+                    pid: pid_ns.tid_of(tracee),
+                    uid: tracee.credentials().uid,
+                    state: ChildState::Traced(status),
+                    cpu_times: tracee.cpu_times(),
+                })));
+            }
+        }
+
+        for child in current.children().iter() {
+            if !target.matches(child, pid_ns.pid_of(child)) {
+                continue;
+            }
+
+            found = true;
+
+            if let ProcessState::ExitedWith(status) = child.state() {
+                // Wait until all threads in the child process exit.
+                if options.contains(WaitOptions::WEXITED) && child.is_thread_group_exited() {
+                    let thread_group = child.thread_group();
+                    let cpu_times = thread_group.cpu_times() + thread_group.children_cpu_times();
+                    return Ok(Some(Some(WaitedChild {
+                        pid: pid_ns.pid_of(child),
+                        uid: child.credentials().uid,
+                        state: ChildState::Exited(status),
+                        cpu_times,
+                    })));
+                }
+
+                continue;
+            }
+
+            let mut thread_group = child.thread_group();
+            let state = match thread_group.job_control_event() {
+                Some(JobControlEvent::Stopped(signal))
+                    if options.contains(WaitOptions::WUNTRACED) =>
+                {
+                    ChildState::Stopped(signal)
+                }
+                Some(JobControlEvent::Continued) if options.contains(WaitOptions::WCONTINUED) => {
+                    ChildState::Continued
+                }
+                _ => continue,
+            };
+
+            if !options.contains(WaitOptions::WNOWAIT) {
+                thread_group.clear_job_control_event();
+            }
+
+            let cpu_times = thread_group.cpu_times() + thread_group.children_cpu_times();
+            return Ok(Some(Some(WaitedChild {
+                pid: pid_ns.pid_of(child),
+                uid: child.credentials().uid,
+                state,
+                cpu_times,
+            })));
+        }
+
+        if !found {
+            return Err(Errno::ECHILD.into());
+        }
+
+        if options.contains(WaitOptions::WNOHANG) {
+            return Ok(Some(None));
+        }
+
+        Ok(None)
+    })?;
+
+    if let Some(WaitedChild {
+        pid,
+        state: ChildState::Exited(_),
+        cpu_times,
+        ..
+    }) = result
+    {
+        if !options.contains(WaitOptions::WNOWAIT) {
+            // Evict the joined processs object. An exited tracee which is
+            // not our child is reaped by its parent.
+            let current = current_process();
+            let pid_ns = current.pid_ns();
+            current.tracees().retain(|p| pid_ns.tid_of(p) != pid);
+            let mut children = current.children();
+            if children.iter().any(|p| pid_ns.pid_of(p) == pid) {
+                children.retain(|p| pid_ns.pid_of(p) != pid);
+                drop(children);
+                current.thread_group().add_children_cpu_times(cpu_times);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
 impl<'a> SyscallHandler<'a> {
     pub fn sys_wait4(
         &mut self,
         pid: PId,
         status: Option<UserVAddr>,
         options: WaitOptions,
-        _rusage: Option<UserVAddr>,
+        rusage: Option<UserVAddr>,
     ) -> Result<isize> {
-        let (got_pid, status_value) = JOIN_WAIT_QUEUE.sleep_signalable_until(|| {
-            let current = current_process();
-            for child in current.children().iter() {
-                if pid.as_i32() > 0 && child.pid() != pid {
-                    // Wait for the specific PID.
-                    continue;
-                }
+        if options.intersects(WaitOptions::WEXITED | WaitOptions::WNOWAIT) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let target = match pid.as_i32() {
+            -1 => WaitTarget::Any,
+            0 => WaitTarget::ProcessGroup(current_process().process_group().lock().pgid()),
+            pid if pid < 0 => WaitTarget::ProcessGroup(global_pgid(-pid)),
+            _ => WaitTarget::Pid(pid),
+        };
 
-                if pid.as_i32() == 0 {
-                    // TODO: Wait for any children in the same process group.
-                    todo!();
+        match wait_for_child(target, options | WaitOptions::WEXITED)? {
+            Some(child) => {
+                if let Some(status) = status {
+                    status.write::<c_int>(&child.state.wait_status())?;
                 }
 
-                if let ProcessState::ExitedWith(status_value) = child.state() {
-                    return Ok(Some((child.pid(), status_value)));
+                if let Some(rusage) = rusage {
+                    rusage.write(&RUsage::from_cpu_times(child.cpu_times))?;
                 }
-            }
 
-            if options.contains(WaitOptions::WNOHANG) {
-                return Ok(Some((PId::new(0), 0)));
+                Ok(child.pid.as_i32() as isize)
             }
-
-            Ok(None)
-        })?;
-
-        // Evict the joined processs object.
-        current_process().children().retain(|p| p.pid() != got_pid);
-
-        if let Some(status) = status {
-            // FIXME: This is NOT the correct format of `status`.
-            status.write::<c_int>(&status_value)?;
+            None => Ok(0),
         }
-        Ok(got_pid.as_i32() as isize)
     }
 }
@@ -0,0 +1,64 @@
+use crate::{
+    ctypes::*,
+    prelude::*,
+    process::{
+        signal::{SigInfo, SIGINFO_SIZE},
+        thread_group::CpuTimes,
+        PId,
+    },
+    syscalls::SyscallHandler,
+};
+use kerla_runtime::address::UserVAddr;
+
+use super::getrusage::RUsage;
+use super::{
+    global_pgid,
+    wait4::{wait_for_child, WaitOptions, WaitTarget},
+};
+
+const P_ALL: c_int = 0;
+const P_PID: c_int = 1;
+const P_PGID: c_int = 2;
+
+impl<'a> SyscallHandler<'a> {
+    pub fn sys_waitid(
+        &mut self,
+        idtype: c_int,
+        id: c_int,
+        infop: Option<UserVAddr>,
+        options: WaitOptions,
+        rusage: Option<UserVAddr>,
+    ) -> Result<isize> {
+        let states = WaitOptions::WEXITED | WaitOptions::WUNTRACED | WaitOptions::WCONTINUED;
+        if !options.intersects(states) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let target = match idtype {
+            P_ALL => WaitTarget::Any,
+            P_PID if id > 0 => WaitTarget::Pid(PId::new(id)),
+            P_PGID if id > 0 => WaitTarget::ProcessGroup(global_pgid(id)),
+            _ => return Err(Errno::EINVAL.into()),
+        };
+
+        let (info, cpu_times) = match wait_for_child(target, options)? {
+            Some(child) => {
+                let (code, status) = child.state.code_and_status();
+                let info = SigInfo::child(child.pid, child.uid, code, status);
+                (info.to_bytes(), child.cpu_times)
+            }
+            // WNOHANG: no children have changed the state.
+            None => ([0; SIGINFO_SIZE], CpuTimes::default()),
+        };
+
+        if let Some(infop) = infop {
+            infop.write_bytes(&info)?;
+        }
+
+        if let Some(rusage) = rusage {
+            rusage.write(&RUsage::from_cpu_times(cpu_times))?;
+        }
+
+        Ok(0)
+    }
+}
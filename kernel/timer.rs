@@ -1,86 +1,303 @@
 use crate::{
     ctypes::*,
     prelude::*,
-    process::{self, current_process, Process, ProcessState},
+    process::{
+        self, current_process, loadavg::update_load_average, signal::SigInfo, Process, ProcessState,
+    },
 };
+use alloc::collections::BTreeMap;
+use core::cmp::min;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use kerla_runtime::{arch::TICK_HZ, spinlock::SpinLock};
+use kerla_runtime::{
+    arch::{nanosecs_since_boot, set_timer_deadline, wallclock_at_boot, TICK_HZ},
+    spinlock::SpinLock,
+};
 use process::switch;
 
-const PREEMPT_PER_TICKS: usize = 30;
-static MONOTONIC_TICKS: AtomicUsize = AtomicUsize::new(0);
-/// Ticks from the epoch (00:00:00 on 1 January 1970, UTC).
-static WALLCLOCK_TICKS: AtomicUsize = AtomicUsize::new(0);
-static TIMERS: SpinLock<Vec<Timer>> = SpinLock::new(Vec::new());
+/// The interval of the periodic timer tick.
+const TICK_NANOSECS: u64 = 1_000_000_000 / TICK_HZ as u64;
+
+/// Timers indexed by their deadlines and IDs.
+static TIMERS: SpinLock<BTreeMap<(MonotonicClock, usize), TimerAction>> =
+    SpinLock::new(BTreeMap::new());
+static NEXT_TIMER_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// What to do when a timer expires.
+enum TimerAction {
+    /// Resumes the process.
+    Resume(Arc<Process>),
+    /// Expires the interval timer. It's a weak reference not to keep a
+    /// deleted timer alive.
+    Interval(Weak<IntervalTimer>),
+}
+
+/// The ID of a timer set by [`set_timeout`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimerId {
+    deadline: MonotonicClock,
+    id: usize,
+}
+
+/// Suspends the current process until `deadline`. Returns `EINTR` if a signal
+/// has arrived in the meantime.
+pub fn sleep_until(deadline: MonotonicClock) -> Result<()> {
+    let current = current_process();
+    let timer = set_timeout(deadline);
+    let result = loop {
+        current.set_state(ProcessState::BlockedSignalable);
+
+        if is_timeout_expired(timer) {
+            break Ok(());
+        }
+
+        if current.has_pending_signals() {
+            break Err(Errno::EINTR.into());
+        }
+
+        switch();
+    };
+
+    current.resume();
+    cancel_timeout(timer);
+    result
+}
+
+/// Resumes the current process at `deadline`. The caller is responsible for
+/// checking if the timeout has actually expired.
+pub fn set_timeout(deadline: MonotonicClock) -> TimerId {
+    let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+    TIMERS.lock().insert(
+        (deadline, id),
+        TimerAction::Resume(current_process().clone()),
+    );
 
-struct Timer {
-    current: usize,
-    process: Arc<Process>,
+    // The timer might expire earlier than the next timer interrupt.
+    program_next_event(true);
+    TimerId { deadline, id }
 }
 
-/// Suspends the current process at least `ms` milliseconds.
-pub fn _sleep_ms(ms: usize) {
-    TIMERS.lock().push(Timer {
-        current: ms * TICK_HZ / 1000,
-        process: current_process().clone(),
-    });
+/// Returns `true` if the timer set by [`set_timeout`] has been expired.
+pub fn is_timeout_expired(timer: TimerId) -> bool {
+    !TIMERS.lock().contains_key(&(timer.deadline, timer.id))
+}
+
+/// Cancels the timer set by [`set_timeout`].
+pub fn cancel_timeout(timer: TimerId) {
+    TIMERS.lock().remove(&(timer.deadline, timer.id));
+}
+
+/// Programs the timer interrupt of the current CPU to fire at the earliest
+/// timer deadline.
+///
+/// If `periodic_tick` is `true`, it fires at the next tick at the latest
+/// for the preemptive scheduling. Idle CPUs don't need it (tickless idle).
+pub fn program_next_event(periodic_tick: bool) {
+    let next_timer = TIMERS.lock().keys().next().map(|(deadline, _)| *deadline);
+    let next_tick = periodic_tick.then(|| read_monotonic_clock().add_nanosecs(TICK_NANOSECS));
+    let next_event = match (next_timer, next_tick) {
+        (Some(timer), Some(tick)) => min(timer, tick),
+        (Some(deadline), None) | (None, Some(deadline)) => deadline,
+        (None, None) => return,
+    };
+
+    set_timer_deadline(next_event.nanosecs);
+}
+
+/// A timer which expires at the given time and then periodically: used for
+/// `ITIMER_REAL` and POSIX timers.
+pub struct IntervalTimer {
+    /// The process to be notified on expiry.
+    target: Weak<Process>,
+    /// The signal sent on expiry. If it's `None`, expirations are only
+    /// counted.
+    signal: Option<SigInfo>,
+    state: SpinLock<IntervalTimerState>,
+}
+
+struct IntervalTimerState {
+    /// The key in `TIMERS` if the timer is armed.
+    key: Option<(MonotonicClock, usize)>,
+    /// The interval in nanoseconds. Zero if the timer is a one-shot.
+    interval: u64,
+    /// The number of expirations since the signal was last delivered.
+    expirations: u64,
+    /// The overrun count when the signal was last delivered
+    /// (`timer_getoverrun(2)`).
+    overrun: u64,
+}
+
+impl IntervalTimer {
+    pub fn new(target: Weak<Process>, signal: Option<SigInfo>) -> Arc<IntervalTimer> {
+        Arc::new(IntervalTimer {
+            target,
+            signal,
+            state: SpinLock::new(IntervalTimerState {
+                key: None,
+                interval: 0,
+                expirations: 0,
+                overrun: 0,
+            }),
+        })
+    }
+
+    /// Arms the timer to expire at `deadline` and then every `interval`
+    /// nanoseconds if it's not zero. Disarms the timer if `deadline` is
+    /// `None`.
+    pub fn set(self: &Arc<IntervalTimer>, deadline: Option<MonotonicClock>, interval: u64) {
+        {
+            let mut timers = TIMERS.lock();
+            let mut state = self.state.lock();
+            if let Some(key) = state.key.take() {
+                timers.remove(&key);
+            }
+
+            state.interval = interval;
+            state.expirations = 0;
+            if let Some(deadline) = deadline {
+                let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+                timers.insert((deadline, id), TimerAction::Interval(Arc::downgrade(self)));
+                state.key = Some((deadline, id));
+            }
+        }
+
+        program_next_event(true);
+    }
+
+    /// Returns the remaining time until the next expiration (zero if
+    /// disarmed) and the interval in nanoseconds.
+    pub fn get(&self) -> (u64, u64) {
+        let state = self.state.lock();
+        let remaining = match state.key {
+            // A timer being expired is still armed.
+            Some((deadline, _)) => read_monotonic_clock().nanosecs_until(deadline).max(1),
+            None => 0,
+        };
+
+        (remaining, state.interval)
+    }
+
+    /// The overrun count when the signal was last delivered.
+    pub fn overrun(&self) -> u64 {
+        self.state.lock().overrun
+    }
+
+    /// Called when the signal of the timer is delivered: expirations after
+    /// the first one are overruns since the signal is sent only once while
+    /// it's pending. Returns the overrun count.
+    pub fn deliver(&self) -> u64 {
+        let mut state = self.state.lock();
+        state.overrun = state.expirations.saturating_sub(1);
+        state.expirations = 0;
+        state.overrun
+    }
+
+    /// Called when the timer has expired. Rearms the timer if it's periodic.
+    /// Returns the signal to be sent.
+    fn expire(
+        self: &Arc<IntervalTimer>,
+        timers: &mut BTreeMap<(MonotonicClock, usize), TimerAction>,
+        deadline: MonotonicClock,
+        now: MonotonicClock,
+    ) -> Option<(Arc<Process>, SigInfo)> {
+        let mut state = self.state.lock();
+        state.key = None;
+        state.expirations += 1;
+        if state.interval > 0 {
+            // Count expirations missed while the interrupt was delayed.
+            let missed = deadline.nanosecs_until(now) / state.interval;
+            state.expirations += missed;
+            let next = deadline.add_nanosecs((missed + 1) * state.interval);
+            let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+            timers.insert((next, id), TimerAction::Interval(Arc::downgrade(self)));
+            state.key = Some((next, id));
+        }
+        drop(state);
 
-    current_process().set_state(ProcessState::BlockedSignalable);
-    switch();
+        Some((self.target.upgrade()?, self.signal?))
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct WallClock {
-    ticks_from_epoch: usize,
+    nanosecs_from_epoch: u64,
 }
 
 impl WallClock {
-    pub fn secs_from_epoch(self) -> usize {
-        self.ticks_from_epoch / TICK_HZ
+    pub fn from_nanosecs_from_epoch(nanosecs_from_epoch: u64) -> WallClock {
+        WallClock {
+            nanosecs_from_epoch,
+        }
     }
 
-    pub fn msecs_from_epoch(self) -> usize {
-        self.ticks_from_epoch / (TICK_HZ / 1000)
+    pub fn secs_from_epoch(self) -> usize {
+        (self.nanosecs_from_epoch / 1_000_000_000) as usize
     }
 
     pub fn nanosecs_from_epoch(self) -> usize {
-        self.msecs_from_epoch() * 1_000_000
+        self.nanosecs_from_epoch as usize
+    }
+
+    /// The monotonic clock at this wall clock time.
+    pub fn to_monotonic(self) -> MonotonicClock {
+        MonotonicClock {
+            nanosecs: self.nanosecs_from_epoch.saturating_sub(wallclock_at_boot()),
+        }
     }
 }
 
 pub fn read_wall_clock() -> WallClock {
     WallClock {
-        ticks_from_epoch: WALLCLOCK_TICKS.load(Ordering::Relaxed),
+        nanosecs_from_epoch: wallclock_at_boot() + nanosecs_since_boot(),
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The elapsed time since boot.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct MonotonicClock {
-    ticks: usize,
+    nanosecs: u64,
 }
 
 impl MonotonicClock {
+    pub fn from_nanosecs(nanosecs: u64) -> MonotonicClock {
+        MonotonicClock { nanosecs }
+    }
+
     pub fn secs(self) -> usize {
-        self.ticks / TICK_HZ
+        (self.nanosecs / 1_000_000_000) as usize
     }
 
     pub fn msecs(self) -> usize {
-        self.ticks / (TICK_HZ / 1000)
+        (self.nanosecs / 1_000_000) as usize
     }
 
     pub fn nanosecs(self) -> usize {
-        self.msecs() * 1_000_000
+        self.nanosecs as usize
     }
 
     pub fn elapsed_msecs(self) -> usize {
-        // FIXME: Consider wrapping.
-        (read_monotonic_clock().ticks - self.ticks) / (TICK_HZ / 1000)
+        (read_monotonic_clock()
+            .nanosecs
+            .saturating_sub(self.nanosecs)
+            / 1_000_000) as usize
+    }
+
+    /// Returns the time `nanosecs` later.
+    pub fn add_nanosecs(self, nanosecs: u64) -> MonotonicClock {
+        MonotonicClock {
+            nanosecs: self.nanosecs.saturating_add(nanosecs),
+        }
+    }
+
+    /// Returns the time from `self` until `later` in nanoseconds (or zero if
+    /// `later` is in the past).
+    pub fn nanosecs_until(self, later: MonotonicClock) -> u64 {
+        later.nanosecs.saturating_sub(self.nanosecs)
     }
 }
 
 pub fn read_monotonic_clock() -> MonotonicClock {
     MonotonicClock {
-        ticks: MONOTONIC_TICKS.load(Ordering::Relaxed),
+        nanosecs: nanosecs_since_boot(),
     }
 }
 
@@ -93,32 +310,94 @@ pub struct Timeval {
 }
 
 impl Timeval {
-    pub fn as_msecs(&self) -> usize {
-        (self.tv_sec as usize) * 1000 + (self.tv_usec as usize) / 1000
+    pub fn from_nanosecs(nanosecs: u64) -> Timeval {
+        Timeval {
+            tv_sec: (nanosecs / 1_000_000_000) as c_time,
+            tv_usec: ((nanosecs % 1_000_000_000) / 1000) as c_suseconds,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let (tv_sec, tv_usec) = (self.tv_sec, self.tv_usec);
+        tv_sec >= 0 && (0..1_000_000).contains(&tv_usec)
+    }
+
+    pub fn as_nanosecs(&self) -> u64 {
+        (self.tv_sec as u64)
+            .saturating_mul(1_000_000_000)
+            .saturating_add(self.tv_usec as u64 * 1000)
     }
 }
 
+/// `struct timespec`
+#[derive(Debug, Copy, Clone)]
+#[repr(C, packed)]
+pub struct Timespec {
+    tv_sec: c_time,
+    tv_nsec: c_long,
+}
+
+impl Timespec {
+    pub fn from_nanosecs(nanosecs: u64) -> Timespec {
+        Timespec {
+            tv_sec: (nanosecs / 1_000_000_000) as c_time,
+            tv_nsec: (nanosecs % 1_000_000_000) as c_long,
+        }
+    }
+
+    pub fn tv_nsec(&self) -> c_long {
+        self.tv_nsec
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let (tv_sec, tv_nsec) = (self.tv_sec, self.tv_nsec);
+        tv_sec >= 0 && (0..1_000_000_000).contains(&tv_nsec)
+    }
+
+    pub fn as_nanosecs(&self) -> u64 {
+        (self.tv_sec as u64)
+            .saturating_mul(1_000_000_000)
+            .saturating_add(self.tv_nsec as u64)
+    }
+}
+
+/// Handles the timer interrupt. Called in each CPU.
 pub fn handle_timer_irq() {
+    let now = read_monotonic_clock();
+    // Signals are sent after releasing the lock.
+    let mut signals = Vec::new();
     {
         let mut timers = TIMERS.lock();
-        for timer in timers.iter_mut() {
-            if timer.current > 0 {
-                timer.current -= 1;
+        while let Some((&(deadline, id), _)) = timers.first_key_value() {
+            if deadline > now {
+                break;
             }
-        }
 
-        timers.retain(|timer| {
-            if timer.current == 0 {
-                timer.process.resume();
+            match timers.remove(&(deadline, id)).unwrap() {
+                TimerAction::Resume(process) => process.resume(),
+                TimerAction::Interval(timer) => {
+                    if let Some(signal) = timer
+                        .upgrade()
+                        .and_then(|timer| timer.expire(&mut timers, deadline, now))
+                    {
+                        signals.push(signal);
+                    }
+                }
             }
+        }
+    }
 
-            timer.current > 0
-        })
+    for (target, info) in signals {
+        target.send_siginfo(info);
     }
 
-    WALLCLOCK_TICKS.fetch_add(1, Ordering::Relaxed);
-    let ticks = MONOTONIC_TICKS.fetch_add(1, Ordering::Relaxed);
-    if ticks % PREEMPT_PER_TICKS == 0 {
-        process::switch();
+    let current = current_process();
+    if !current.is_idle() {
+        current.account_cpu_time();
     }
+
+    update_load_average(now.nanosecs() as u64);
+
+    program_next_event(!current.is_idle());
+    process::preempt_if_needed();
 }
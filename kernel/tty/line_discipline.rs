@@ -1,18 +1,28 @@
 //! Line discipline.
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::{
+    ctypes::*,
     prelude::*,
-    process::{current_process, process_group::ProcessGroup, signal::SIGINT, WaitQueue},
+    process::{
+        current_process,
+        process_group::{self, PgId, ProcessGroup},
+        session::Session,
+        signal::{SigInfo, Signal, SIGCONT, SIGHUP, SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU},
+        PId, Process, WaitQueue,
+    },
     user_buffer::{UserBufReader, UserBufWriter, UserBuffer, UserBufferMut},
 };
 use bitflags::bitflags;
-use kerla_runtime::spinlock::SpinLock;
+use kerla_runtime::{address::UserVAddr, spinlock::SpinLock};
 use kerla_utils::ring_buffer::RingBuffer;
 
 bitflags! {
     pub struct LFlag: u32 {
         const ICANON = 0o0000002;
         const ECHO   = 0o0000010;
+        const TOSTOP = 0o0000400;
     }
 }
 
@@ -80,12 +90,22 @@ pub enum LineControl {
     Echo(u8),
 }
 
+const TIOCSCTTY: usize = 0x540e;
+const TIOCGPGRP: usize = 0x540f;
+const TIOCSPGRP: usize = 0x5410;
+const TIOCNOTTY: usize = 0x5422;
+const TIOCGSID: usize = 0x5429;
+
 pub struct LineDiscipline {
     wait_queue: WaitQueue,
     current_line: SpinLock<LineEdit>,
     buf: SpinLock<RingBuffer<u8, 4096>>,
     termios: SpinLock<Termios>,
     foreground_process_group: SpinLock<Weak<SpinLock<ProcessGroup>>>,
+    /// The session which the terminal controls.
+    session: SpinLock<Weak<SpinLock<Session>>>,
+    /// Whether the terminal has been hung up (e.g. the pty master is closed).
+    hung_up: AtomicBool,
 }
 
 impl LineDiscipline {
@@ -96,6 +116,8 @@ impl LineDiscipline {
             buf: SpinLock::new(RingBuffer::new()),
             termios: SpinLock::new(Default::default()),
             foreground_process_group: SpinLock::new(Weak::new()),
+            session: SpinLock::new(Weak::new()),
+            hung_up: AtomicBool::new(false),
         }
     }
 
@@ -115,12 +137,203 @@ impl LineDiscipline {
         *self.foreground_process_group.lock() = pg;
     }
 
-    fn is_current_foreground(&self) -> bool {
-        let foreground_pg = &*self.foreground_process_group.lock();
-        current_process().belongs_to_process_group(foreground_pg)
-        // If the foreground process is not yet set, allow any processes to read
-        // from the tty. I'm not sure whether it is a correct behaviour.
-        || foreground_pg.upgrade().is_none()
+    /// The session which the terminal controls.
+    pub fn session(&self) -> Option<Arc<SpinLock<Session>>> {
+        self.session.lock().upgrade()
+    }
+
+    /// Makes the terminal the controlling terminal of `session` and `pg` the
+    /// foreground process group.
+    pub fn set_controlling_session(
+        self: &Arc<Self>,
+        session: &Arc<SpinLock<Session>>,
+        pg: &Arc<SpinLock<ProcessGroup>>,
+    ) {
+        session.lock().set_controlling_terminal(Some(self.clone()));
+        *self.session.lock() = Arc::downgrade(session);
+        self.set_foreground_process_group(Arc::downgrade(pg));
+    }
+
+    /// Disassociates the terminal from the session it controls: sends
+    /// `SIGHUP` and `SIGCONT` to the foreground process group.
+    pub fn disassociate(&self) {
+        let session = core::mem::take(&mut *self.session.lock());
+        if let Some(session) = session.upgrade() {
+            session.lock().set_controlling_terminal(None);
+        }
+
+        let foreground_pg = core::mem::take(&mut *self.foreground_process_group.lock());
+        if let Some(pg) = foreground_pg.upgrade() {
+            let mut pg = pg.lock();
+            pg.signal(SigInfo::kernel(SIGHUP));
+            pg.signal(SigInfo::kernel(SIGCONT));
+        }
+    }
+
+    /// Hangs up the terminal: `SIGHUP` is also sent to the session leader and
+    /// reads return EOF from now on.
+    pub fn hangup(&self) {
+        self.hung_up.store(true, Ordering::SeqCst);
+        if let Some(session) = self.session() {
+            let sid = session.lock().sid();
+            if let Some(leader) = Process::find_by_pid(PId::new(sid.as_i32())) {
+                leader.send_siginfo(SigInfo::kernel(SIGHUP));
+                leader.send_siginfo(SigInfo::kernel(SIGCONT));
+            }
+        }
+
+        self.disassociate();
+        self.wait_queue.wake_all();
+    }
+
+    /// Whether the terminal has been hung up.
+    pub fn is_hung_up(&self) -> bool {
+        self.hung_up.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the terminal is the controlling terminal of the
+    /// **current** process.
+    fn is_current_controlling_terminal(&self) -> bool {
+        let session = current_process().process_group().lock().session().clone();
+        Weak::ptr_eq(&self.session.lock(), &Arc::downgrade(&session))
+    }
+
+    /// Checks if the **current** process is allowed to access the terminal.
+    /// A process in the background sends `signal` (`SIGTTIN` or `SIGTTOU`) to
+    /// its process group and the system call is restarted once it's
+    /// continued.
+    fn check_background_access(&self, signal: Signal) -> Result<()> {
+        if !self.is_current_controlling_terminal() {
+            return Ok(());
+        }
+
+        let foreground_pg = self.foreground_process_group.lock().clone();
+        let current = current_process();
+        // If the foreground process is not set, allow any processes to access
+        // the tty.
+        if foreground_pg.upgrade().is_none() || current.belongs_to_process_group(&foreground_pg) {
+            return Ok(());
+        }
+
+        if current.signals().lock().is_ignored(signal) || current.signal_mask().contains(signal) {
+            // Reading from the background fails whereas writing is allowed.
+            return if signal == SIGTTIN {
+                Err(Errno::EIO.into())
+            } else {
+                Ok(())
+            };
+        }
+
+        // No one would continue an orphaned process group once it's stopped.
+        let pg = current.process_group();
+        if process_group::is_orphaned(&pg) {
+            return Err(Errno::EIO.into());
+        }
+
+        pg.lock().signal(SigInfo::kernel(signal));
+        Err(Errno::ERESTARTSYS.into())
+    }
+
+    /// Checks if the **current** process is allowed to write into the
+    /// terminal: `SIGTTOU` is sent to a background process if `TOSTOP` is set.
+    pub fn check_write_access(&self) -> Result<()> {
+        let tostop = self.termios.lock().lflag.contains(LFlag::TOSTOP);
+        if tostop {
+            self.check_background_access(SIGTTOU)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles ioctls on the job control: the controlling terminal and the
+    /// foreground process group.
+    pub fn ioctl(self: &Arc<Self>, cmd: usize, arg: usize) -> Result<isize> {
+        match cmd {
+            TIOCSCTTY => {
+                let current = current_process();
+                let pg = current.process_group();
+                let session = pg.lock().session().clone();
+                let is_leader = session.lock().sid().as_i32() == current.pid().as_i32();
+                if !is_leader || session.lock().controlling_terminal().is_some() {
+                    return Err(Errno::EPERM.into());
+                }
+
+                // A privileged process can steal the terminal from another
+                // session if `arg` is 1.
+                if let Some(other) = self.session() {
+                    if arg != 1 || !current.credentials().is_privileged() {
+                        return Err(Errno::EPERM.into());
+                    }
+
+                    other.lock().set_controlling_terminal(None);
+                }
+
+                self.set_controlling_session(&session, &pg);
+            }
+            TIOCNOTTY => {
+                if !self.is_current_controlling_terminal() {
+                    return Err(Errno::ENOTTY.into());
+                }
+
+                let current = current_process();
+                let sid = current.process_group().lock().session().lock().sid();
+                if sid.as_i32() == current.pid().as_i32() {
+                    self.disassociate();
+                }
+            }
+            TIOCGPGRP => {
+                if self.session().is_some() && !self.is_current_controlling_terminal() {
+                    return Err(Errno::ENOTTY.into());
+                }
+
+                let process_group = self
+                    .foreground_process_group()
+                    .ok_or_else(|| Error::new(Errno::ENOENT))?;
+
+                let pgid = process_group.lock().pgid().as_i32();
+                let pgid = current_process().pid_ns().local_id(pgid);
+                let arg = UserVAddr::new_nonnull(arg)?;
+                arg.write::<c_int>(&pgid)?;
+            }
+            TIOCSPGRP => {
+                if self.session().is_some() && !self.is_current_controlling_terminal() {
+                    return Err(Errno::ENOTTY.into());
+                }
+
+                self.check_background_access(SIGTTOU)?;
+
+                let arg = UserVAddr::new_nonnull(arg)?;
+                let pgid = arg.read::<c_int>()?;
+                let pgid = current_process()
+                    .pid_ns()
+                    .global_id(pgid)
+                    .ok_or_else(|| Error::new(Errno::ESRCH))?;
+                let pg = ProcessGroup::find_by_pgid(PgId::new(pgid))
+                    .ok_or_else(|| Error::new(Errno::ESRCH))?;
+
+                if let Some(session) = self.session() {
+                    if !Arc::ptr_eq(pg.lock().session(), &session) {
+                        return Err(Errno::EPERM.into());
+                    }
+                }
+
+                self.set_foreground_process_group(Arc::downgrade(&pg));
+            }
+            TIOCGSID => {
+                if !self.is_current_controlling_terminal() {
+                    return Err(Errno::ENOTTY.into());
+                }
+
+                let current = current_process();
+                let sid = current.process_group().lock().session().lock().sid();
+                let sid = current.pid_ns().local_id(sid.as_i32());
+                let arg = UserVAddr::new_nonnull(arg)?;
+                arg.write::<c_int>(&sid)?;
+            }
+            _ => return Err(Errno::ENOSYS.into()),
+        }
+
+        Ok(0)
     }
 
     pub fn write<F>(&self, buf: UserBuffer<'_>, callback: F) -> Result<usize>
@@ -139,7 +352,17 @@ impl LineDiscipline {
                 match ch {
                     0x03 /* ETX: End of Text (^C) */  if termios.is_cooked_mode() => {
                         if let Some(pg) = self.foreground_process_group() {
-                            pg.lock().signal(SIGINT);
+                            pg.lock().signal(SigInfo::kernel(SIGINT));
+                        }
+                    }
+                    0x1a /* SUB: Substitute (^Z) */ if termios.is_cooked_mode() => {
+                        if let Some(pg) = self.foreground_process_group() {
+                            pg.lock().signal(SigInfo::kernel(SIGTSTP));
+                        }
+                    }
+                    0x1c /* FS: File Separator (^\) */ if termios.is_cooked_mode() => {
+                        if let Some(pg) = self.foreground_process_group() {
+                            pg.lock().signal(SigInfo::kernel(SIGQUIT));
                         }
                     }
                     0x7f /* backspace */ if termios.is_cooked_mode() => {
@@ -191,9 +414,7 @@ impl LineDiscipline {
     pub fn read(&self, dst: UserBufferMut<'_>) -> Result<usize> {
         let mut writer = UserBufWriter::from(dst);
         self.wait_queue.sleep_signalable_until(|| {
-            if !self.is_current_foreground() {
-                return Ok(None);
-            }
+            self.check_background_access(SIGTTIN)?;
 
             let mut buf_lock = self.buf.lock();
             while writer.remaining_len() > 0 {
@@ -205,7 +426,8 @@ impl LineDiscipline {
                 }
             }
 
-            if writer.written_len() > 0 {
+            if writer.written_len() > 0 || self.is_hung_up() {
+                // Return EOF if the terminal has been hung up.
                 Ok(Some(writer.written_len()))
             } else {
                 Ok(None)
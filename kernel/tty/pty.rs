@@ -24,37 +24,50 @@ use super::line_discipline::{LineControl, LineDiscipline};
 
 static PTY_INDEX_TABLE: SpinLock<IdTable<16>> = SpinLock::new(IdTable::new());
 
-pub struct PtyMaster {
+/// The state shared between a pty master and its slave.
+struct Pty {
     index: usize,
     wait_queue: WaitQueue,
     buf: SpinLock<Vec<u8>>,
-    discipline: LineDiscipline,
+    discipline: Arc<LineDiscipline>,
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        PTY_INDEX_TABLE.lock().free(self.index);
+    }
+}
+
+pub struct PtyMaster {
+    pty: Arc<Pty>,
 }
 
 impl PtyMaster {
     pub fn new() -> Result<(Arc<PtyMaster>, Arc<PtySlave>)> {
-        let master = Arc::new(PtyMaster {
+        let pty = Arc::new(Pty {
             index: PTY_INDEX_TABLE
                 .lock()
                 .alloc()
                 .ok_or_else(|| Error::new(Errno::ENOMEM))?,
             wait_queue: WaitQueue::new(),
             buf: SpinLock::new(Vec::new()),
-            discipline: LineDiscipline::new(),
+            discipline: Arc::new(LineDiscipline::new()),
         });
 
-        let slave = Arc::new(PtySlave::new(master.clone()));
+        let master = Arc::new(PtyMaster { pty: pty.clone() });
+        let slave = Arc::new(PtySlave { pty });
         Ok((master, slave))
     }
 
     pub fn index(&self) -> usize {
-        self.index
+        self.pty.index
     }
 }
 
 impl Drop for PtyMaster {
     fn drop(&mut self) {
-        PTY_INDEX_TABLE.lock().free(self.index);
+        // The master is closed: hang up the session on the slave.
+        self.pty.discipline.hangup();
     }
 }
 
@@ -66,8 +79,8 @@ impl FileLike for PtyMaster {
         _options: &OpenOptions,
     ) -> Result<usize> {
         let mut writer = UserBufWriter::from(buf);
-        let read_len = self.wait_queue.sleep_signalable_until(|| {
-            let mut buf_lock = self.buf.lock();
+        let read_len = self.pty.wait_queue.sleep_signalable_until(|| {
+            let mut buf_lock = self.pty.buf.lock();
             if buf_lock.is_empty() {
                 // TODO: NOBLOCK
                 return Ok(None);
@@ -87,8 +100,8 @@ impl FileLike for PtyMaster {
     }
 
     fn write(&self, _offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
-        let written_len = self.discipline.write(buf, |ctrl| {
-            let mut master_buf = self.buf.lock();
+        let written_len = self.pty.discipline.write(buf, |ctrl| {
+            let mut master_buf = self.pty.buf.lock();
             match ctrl {
                 LineControl::Backspace => {
                     // Remove the previous character by overwriting with a whitespace.
@@ -123,11 +136,11 @@ impl FileLike for PtyMaster {
     fn poll(&self) -> Result<PollStatus> {
         let mut status = PollStatus::empty();
 
-        if !self.buf.lock().is_empty() {
+        if !self.pty.buf.lock().is_empty() {
             status |= PollStatus::POLLIN;
         }
 
-        if self.discipline.is_writable() {
+        if self.pty.discipline.is_writable() {
             status |= PollStatus::POLLOUT;
         }
 
@@ -138,19 +151,13 @@ impl FileLike for PtyMaster {
 impl fmt::Debug for PtyMaster {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PtyMaster")
-            .field("index", &self.index)
+            .field("index", &self.pty.index)
             .finish()
     }
 }
 
 pub struct PtySlave {
-    master: Arc<PtyMaster>,
-}
-
-impl PtySlave {
-    pub fn new(master: Arc<PtyMaster>) -> PtySlave {
-        PtySlave { master }
-    }
+    pty: Arc<Pty>,
 }
 
 impl FileLike for PtySlave {
@@ -160,7 +167,7 @@ impl FileLike for PtySlave {
         buf: UserBufferMut<'_>,
         _options: &OpenOptions,
     ) -> Result<usize> {
-        let read_len = self.master.discipline.read(buf)?;
+        let read_len = self.pty.discipline.read(buf)?;
         if read_len > 0 {
             POLL_WAIT_QUEUE.wake_all();
         }
@@ -168,8 +175,14 @@ impl FileLike for PtySlave {
     }
 
     fn write(&self, _offset: usize, buf: UserBuffer<'_>, _options: &OpenOptions) -> Result<usize> {
+        if self.pty.discipline.is_hung_up() {
+            return Err(Errno::EIO.into());
+        }
+
+        self.pty.discipline.check_write_access()?;
+
         let mut written_len = 0;
-        let mut master_buf = self.master.buf.lock();
+        let mut master_buf = self.pty.buf.lock();
         let mut reader = UserBufReader::from(buf);
         while reader.remaining_len() > 0 {
             let mut tmp = [0; 128];
@@ -206,25 +219,28 @@ impl FileLike for PtySlave {
         })
     }
 
-    fn ioctl(&self, cmd: usize, _arg: usize) -> Result<isize> {
+    fn ioctl(&self, cmd: usize, arg: usize) -> Result<isize> {
         const TIOCSPTLCK: usize = 0x40045431;
         match cmd {
             TIOCSPTLCK => Ok(0),
-            _ => {
-                debug_warn!("pty_slave: unknown cmd={:x}", cmd);
-                Ok(0)
-            }
+            _ => match self.pty.discipline.ioctl(cmd, arg) {
+                Err(err) if err.errno() == Errno::ENOSYS => {
+                    debug_warn!("pty_slave: unknown cmd={:x}", cmd);
+                    Ok(0)
+                }
+                result => result,
+            },
         }
     }
 
     fn poll(&self) -> Result<PollStatus> {
         let mut status = PollStatus::empty();
 
-        if self.master.discipline.is_readable() {
+        if self.pty.discipline.is_readable() {
             status |= PollStatus::POLLIN;
         }
 
-        // TODO: if self.master.discipline.lock().len() > FULL {
+        // TODO: if self.pty.discipline.lock().len() > FULL {
         status |= PollStatus::POLLOUT;
 
         Ok(status)
@@ -234,7 +250,7 @@ impl FileLike for PtySlave {
 impl fmt::Debug for PtySlave {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PtySlave")
-            .field("master", &self.master.index)
+            .field("master", &self.pty.index)
             .finish()
     }
 }
@@ -3,18 +3,24 @@ use core::any::Any;
 
 pub trait Downcastable: Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
+    fn as_any_arc(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
 }
 
 impl<T: Any + Send + Sync> Downcastable for T {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn as_any_arc(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
 }
 
-pub fn downcast<S, T>(arc: &Arc<S>) -> Option<&Arc<T>>
+/// Downcasts a trait object (e.g. `Arc<dyn FileLike>`) into the concrete type.
+pub fn downcast<S, T>(arc: &Arc<S>) -> Option<Arc<T>>
 where
     S: Downcastable + ?Sized,
     T: Send + Sync + 'static,
 {
-    arc.as_any().downcast_ref::<Arc<T>>()
+    S::as_any_arc(arc.clone()).downcast::<T>().ok()
 }
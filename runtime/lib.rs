@@ -24,13 +24,18 @@ mod x64;
 pub mod arch {
     #[cfg(target_arch = "x86_64")]
     pub use super::x64::{
-        enable_irq, halt, idle, read_clock_counter, semihosting_halt, x64_specific, Backtrace,
-        PageFaultReason, PageTable, PtRegs, SavedInterruptStatus, SemihostingExitStatus,
+        cpu_id, enable_irq, halt, idle, is_cpu_online, nanosecs_since_boot, num_cpus,
+        read_clock_counter, semihosting_halt, send_reschedule_ipi, set_timer_deadline,
+        start_application_processors, wallclock_at_boot, x64_specific, Backtrace, PageFaultReason,
+        PageProtection, PageTable, PtRegs, SavedInterruptStatus, SemihostingExitStatus,
         KERNEL_BASE_ADDR, KERNEL_STRAIGHT_MAP_PADDR_END, PAGE_SIZE, TICK_HZ,
     };
+
+    #[cfg(target_arch = "x86_64")]
+    pub(crate) use super::x64::{handle_tlb_shootdown, try_cpu_id};
 }
 
-use address::UserVAddr;
+use address::{PAddr, UserVAddr};
 use kerla_utils::static_cell::StaticCell;
 
 pub trait Handler: Sync {
@@ -57,6 +62,20 @@ pub trait Handler: Sync {
         frame: *mut arch::PtRegs,
     ) -> isize;
 
+    /// Called on a debug exception (single-stepping) or a breakpoint in the
+    /// userspace.
+    fn handle_debug_exception(&self) {}
+
+    /// Called before returning to the userspace from an interrupt or an
+    /// exception handler. `frame` is the user context to be restored.
+    fn handle_interrupt_return_to_user(&self, _frame: &mut arch::PtRegs) {}
+
+    /// Called when another CPU requested the current CPU to reschedule.
+    fn handle_reschedule_ipi(&self) {}
+
+    /// Called when a page is freed by [`page_allocator::unref_page`].
+    fn handle_page_freed(&self, _paddr: PAddr) {}
+
     #[cfg(debug_assertions)]
     fn usercopy_hook(&self) {}
 }
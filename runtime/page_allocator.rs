@@ -1,10 +1,12 @@
+use core::mem::size_of;
 use core::ops::Deref;
+use core::slice;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::{address::PAddr, arch::PAGE_SIZE, bootinfo::RamArea, spinlock::SpinLock};
 use arrayvec::ArrayVec;
 use bitflags::bitflags;
-use kerla_utils::alignment::is_aligned;
+use kerla_utils::alignment::{align_up, is_aligned};
 use kerla_utils::byte_size::ByteSize;
 
 use kerla_utils::bitmap_allocator::BitMapAllocator as Allocator;
@@ -15,10 +17,52 @@ use kerla_utils::bitmap_allocator::BitMapAllocator as Allocator;
 // Comment out the following line to use BumpAllocator.
 // use kerla_utils::bump_allocator::BumpAllocator as Allocator;
 
-static ZONES: SpinLock<ArrayVec<Allocator, 8>> = SpinLock::new(ArrayVec::new_const());
+static ZONES: SpinLock<ArrayVec<Zone, 8>> = SpinLock::new(ArrayVec::new_const());
 static NUM_FREE_PAGES: AtomicUsize = AtomicUsize::new(0);
 static NUM_TOTAL_PAGES: AtomicUsize = AtomicUsize::new(0);
 
+/// A physically contiguous memory area managed by the page allocator.
+struct Zone {
+    allocator: Allocator,
+    /// The beginning of the area. Reference counters are indexed from here.
+    base: usize,
+    /// The number of references to each page in the zone. A page is freed when
+    /// its counter reaches to zero in [`unref_page`].
+    ref_counts: &'static mut [u32],
+}
+
+impl Zone {
+    fn includes(&mut self, paddr: PAddr) -> bool {
+        self.allocator.includes(paddr.value())
+    }
+
+    fn ref_count_mut(&mut self, paddr: PAddr) -> &mut u32 {
+        &mut self.ref_counts[(paddr.value() - self.base) / PAGE_SIZE]
+    }
+
+    fn alloc_pages(&mut self, num_pages: usize) -> Option<PAddr> {
+        let paddr = self
+            .allocator
+            .alloc_pages(num_pages_to_order(num_pages))
+            .map(PAddr::new)?;
+
+        for i in 0..num_pages {
+            *self.ref_count_mut(paddr.add(i * PAGE_SIZE)) = 1;
+        }
+
+        Some(paddr)
+    }
+
+    fn free_pages(&mut self, paddr: PAddr, num_pages: usize) {
+        for i in 0..num_pages {
+            *self.ref_count_mut(paddr.add(i * PAGE_SIZE)) = 0;
+        }
+
+        self.allocator
+            .free_pages(paddr.value(), num_pages_to_order(num_pages));
+    }
+}
+
 fn num_pages_to_order(num_pages: usize) -> usize {
     // TODO: Use log2 instead
     for order in 0..16 {
@@ -88,10 +132,9 @@ impl Drop for OwnedPages {
 
 // TODO: Use alloc_page
 pub fn alloc_pages(num_pages: usize, flags: AllocPageFlags) -> Result<PAddr, PageAllocError> {
-    let order = num_pages_to_order(num_pages);
     let mut zones = ZONES.lock();
     for zone in zones.iter_mut() {
-        if let Some(paddr) = zone.alloc_pages(order).map(PAddr::new) {
+        if let Some(paddr) = zone.alloc_pages(num_pages) {
             if !flags.contains(AllocPageFlags::DIRTY_OK) {
                 unsafe {
                     paddr
@@ -112,24 +155,7 @@ pub fn alloc_pages_owned(
     num_pages: usize,
     flags: AllocPageFlags,
 ) -> Result<OwnedPages, PageAllocError> {
-    let order = num_pages_to_order(num_pages);
-    let mut zones = ZONES.lock();
-    for zone in zones.iter_mut() {
-        if let Some(paddr) = zone.alloc_pages(order).map(PAddr::new) {
-            if !flags.contains(AllocPageFlags::DIRTY_OK) {
-                unsafe {
-                    paddr
-                        .as_mut_ptr::<u8>()
-                        .write_bytes(0, num_pages * PAGE_SIZE);
-                }
-            }
-
-            NUM_FREE_PAGES.fetch_sub(num_pages, Ordering::SeqCst);
-            return Ok(OwnedPages::new(paddr, num_pages));
-        }
-    }
-
-    Err(PageAllocError)
+    alloc_pages(num_pages, flags).map(|paddr| OwnedPages::new(paddr, num_pages))
 }
 
 /// The caller must ensure that the pages are not already freed. Keep holding
@@ -144,17 +170,70 @@ pub fn free_pages(paddr: PAddr, num_pages: usize) {
         }
     }
 
-    let order = num_pages_to_order(num_pages);
     let mut zones = ZONES.lock();
     for zone in zones.iter_mut() {
-        if zone.includes(paddr.value()) {
-            zone.free_pages(paddr.value(), order);
+        if zone.includes(paddr) {
+            zone.free_pages(paddr, num_pages);
             NUM_FREE_PAGES.fetch_add(num_pages, Ordering::SeqCst);
             return;
         }
     }
 }
 
+/// Returns the number of references to the page. Pages returned from
+/// [`alloc_pages`] are referenced once.
+pub fn page_ref_count(paddr: PAddr) -> usize {
+    let mut zones = ZONES.lock();
+    for zone in zones.iter_mut() {
+        if zone.includes(paddr) {
+            return *zone.ref_count_mut(paddr) as usize;
+        }
+    }
+
+    0
+}
+
+/// Increments the reference count of the page. It's used for sharing a page
+/// among multiple page tables (e.g. copy-on-write pages).
+pub fn ref_page(paddr: PAddr) {
+    let mut zones = ZONES.lock();
+    for zone in zones.iter_mut() {
+        if zone.includes(paddr) {
+            let ref_count = zone.ref_count_mut(paddr);
+            debug_assert!(*ref_count > 0, "referenced a free page");
+            *ref_count += 1;
+            return;
+        }
+    }
+}
+
+/// Decrements the reference count of the page and frees it if it's no longer
+/// referenced.
+pub fn unref_page(paddr: PAddr) {
+    let freed = {
+        let mut zones = ZONES.lock();
+        let mut freed = false;
+        for zone in zones.iter_mut() {
+            if zone.includes(paddr) {
+                let ref_count = zone.ref_count_mut(paddr);
+                debug_assert!(*ref_count > 0, "unreferenced a free page");
+                *ref_count -= 1;
+                freed = *ref_count == 0;
+                break;
+            }
+        }
+
+        freed
+    };
+
+    if freed {
+        // Notify before freeing: once freed, the page may be allocated and
+        // charged again by another CPU.
+        crate::handler().handle_page_freed(paddr);
+        free_pages(paddr, 1);
+    }
+}
+
 pub fn init(areas: &[RamArea]) {
     let mut zones = ZONES.lock();
     for area in areas {
@@ -165,10 +244,28 @@ pub fn init(areas: &[RamArea]) {
         );
 
         debug_assert!(is_aligned(area.base.value(), PAGE_SIZE));
-        let allocator =
-            unsafe { Allocator::new(area.base.as_mut_ptr(), area.base.value(), area.len) };
+
+        // Reserve the beginning of the area for reference counters.
+        let num_pages = area.len / PAGE_SIZE;
+        let ref_counts_len = align_up(num_pages * size_of::<u32>(), PAGE_SIZE);
+        let ref_counts =
+            unsafe { slice::from_raw_parts_mut(area.base.as_mut_ptr::<u32>(), num_pages) };
+        ref_counts.fill(0);
+
+        let allocator_base = area.base.add(ref_counts_len);
+        let allocator = unsafe {
+            Allocator::new(
+                allocator_base.as_mut_ptr(),
+                allocator_base.value(),
+                area.len - ref_counts_len,
+            )
+        };
         NUM_FREE_PAGES.fetch_add(allocator.num_total_pages(), Ordering::SeqCst);
         NUM_TOTAL_PAGES.fetch_add(allocator.num_total_pages(), Ordering::SeqCst);
-        zones.push(allocator);
+        zones.push(Zone {
+            allocator,
+            base: area.base.value(),
+            ref_counts,
+        });
     }
 }
@@ -1,9 +1,10 @@
 use cfg_if::cfg_if;
-use core::arch::asm;
+use core::hint::spin_loop;
 use core::mem::ManuallyDrop;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::arch::SavedInterruptStatus;
+use crate::arch::{handle_tlb_shootdown, try_cpu_id, SavedInterruptStatus};
 use crate::backtrace::backtrace;
 
 #[cfg(debug_assertions)]
@@ -13,9 +14,15 @@ use crate::global_allocator::is_kernel_heap_enabled;
 #[cfg(debug_assertions)]
 use atomic_refcell::AtomicRefCell;
 
+/// The number of spins to consider that the lock is in a dead lock.
+const DEAD_LOCK_SPINS: usize = 100_000_000;
+
 pub struct SpinLock<T: ?Sized> {
     #[cfg(debug_assertions)]
     locked_by: AtomicRefCell<Option<CapturedBacktrace>>,
+    /// The CPU ID holding the lock plus 1. 0 if it's not locked or the CPU is
+    /// unknown.
+    owner: AtomicUsize,
     inner: spin::mutex::SpinMutex<T>,
 }
 
@@ -23,6 +30,7 @@ impl<T> SpinLock<T> {
     pub const fn new(value: T) -> SpinLock<T> {
         SpinLock {
             inner: spin::mutex::SpinMutex::new(value),
+            owner: AtomicUsize::new(0),
             #[cfg(debug_assertions)]
             locked_by: AtomicRefCell::new(None),
         }
@@ -31,38 +39,33 @@ impl<T> SpinLock<T> {
 
 impl<T: ?Sized> SpinLock<T> {
     pub fn lock(&self) -> SpinLockGuard<'_, T> {
-        if self.inner.is_locked() {
-            // Since we don't yet support multiprocessors and interrupts are
-            // disabled until all locks are released, `lock()` will never fail
-            // unless a dead lock has occurred.
-            //
-            // TODO: Remove when we got SMP support.
-            cfg_if! {
-                if #[cfg(debug_assertions)] {
-                    let trace = self.locked_by.borrow();
-                    if let Some(trace) = trace.as_ref() {
-                        debug_warn!(
-                            "DEAD LOCK: already locked from the following context\n{:?}",
-                            trace
-                        );
-                    } else {
-                        debug_warn!("DEAD LOCK: already locked");
-                    }
-                } else {
-                    debug_warn!("DEAD LOCK: already locked");
-                }
+        let saved_intr_status = SavedInterruptStatus::disable();
+        let owner = try_cpu_id().map_or(0, |cpu_id| cpu_id + 1);
+        let mut spins: usize = 0;
+        let guard = loop {
+            if let Some(guard) = self.inner.try_lock() {
+                break guard;
             }
 
-            debug_warn!("Tried to lock from:");
-            backtrace();
-        }
+            // Interrupts are disabled: the lock is never released if the
+            // current CPU holds it.
+            if spins == 0 && owner != 0 && self.owner.load(Ordering::Relaxed) == owner {
+                self.warn_dead_lock();
+            }
 
-        let saved_intr_status = SavedInterruptStatus::save();
-        unsafe {
-            asm!("cli");
-        }
+            // Another CPU might be waiting for us to flush TLB while holding
+            // the lock.
+            handle_tlb_shootdown();
+            spin_loop();
+
+            spins += 1;
+            if spins == DEAD_LOCK_SPINS {
+                // It's very likely to be a dead lock among CPUs.
+                self.warn_dead_lock();
+            }
+        };
 
-        let guard = self.inner.lock();
+        self.owner.store(owner, Ordering::Relaxed);
 
         #[cfg(debug_assertions)]
         if is_kernel_heap_enabled() {
@@ -71,6 +74,7 @@ impl<T: ?Sized> SpinLock<T> {
 
         SpinLockGuard {
             inner: ManuallyDrop::new(guard),
+            owner: &self.owner,
             saved_intr_status: ManuallyDrop::new(saved_intr_status),
             #[cfg(debug_assertions)]
             locked_by: &self.locked_by,
@@ -80,6 +84,27 @@ impl<T: ?Sized> SpinLock<T> {
     pub fn is_locked(&self) -> bool {
         self.inner.is_locked()
     }
+
+    fn warn_dead_lock(&self) {
+        cfg_if! {
+            if #[cfg(debug_assertions)] {
+                let trace = self.locked_by.try_borrow();
+                if let Ok(Some(trace)) = trace.as_deref() {
+                    debug_warn!(
+                        "DEAD LOCK: already locked from the following context\n{:?}",
+                        trace
+                    );
+                } else {
+                    debug_warn!("DEAD LOCK: already locked");
+                }
+            } else {
+                debug_warn!("DEAD LOCK: already locked");
+            }
+        }
+
+        debug_warn!("Tried to lock from:");
+        backtrace();
+    }
 }
 
 unsafe impl<T: ?Sized + Send> Sync for SpinLock<T> {}
@@ -87,6 +112,7 @@ unsafe impl<T: ?Sized + Send> Send for SpinLock<T> {}
 
 pub struct SpinLockGuard<'a, T: ?Sized> {
     inner: ManuallyDrop<spin::mutex::SpinMutexGuard<'a, T>>,
+    owner: &'a AtomicUsize,
     #[cfg(debug_assertions)]
     locked_by: &'a AtomicRefCell<Option<CapturedBacktrace>>,
     saved_intr_status: ManuallyDrop<SavedInterruptStatus>,
@@ -94,6 +120,7 @@ pub struct SpinLockGuard<'a, T: ?Sized> {
 
 impl<'a, T: ?Sized> Drop for SpinLockGuard<'a, T> {
     fn drop(&mut self) {
+        self.owner.store(0, Ordering::Relaxed);
         unsafe {
             ManuallyDrop::drop(&mut self.inner);
         }
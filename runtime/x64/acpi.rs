@@ -0,0 +1,159 @@
+//! A minimal ACPI table parser to enumerate CPUs.
+use crate::address::PAddr;
+use arrayvec::ArrayVec;
+use core::mem::size_of;
+use core::ptr::read_unaligned;
+use core::slice;
+
+use super::smp::MAX_CPUS;
+
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    checksum: u8,
+    oem_id: [u8; 6],
+    revision: u8,
+    rsdt_address: u32,
+    // Fields below are available in ACPI 2.0 or later.
+    length: u32,
+    xsdt_address: u64,
+    extended_checksum: u8,
+    reserved: [u8; 3],
+}
+
+#[repr(C, packed)]
+struct SdtHeader {
+    signature: [u8; 4],
+    length: u32,
+    revision: u8,
+    checksum: u8,
+    oem_id: [u8; 6],
+    oem_table_id: [u8; 8],
+    oem_revision: u32,
+    creator_id: u32,
+    creator_revision: u32,
+}
+
+/// The header of Multiple APIC Description Table (MADT).
+#[repr(C, packed)]
+struct MadtHeader {
+    header: SdtHeader,
+    local_apic_address: u32,
+    flags: u32,
+}
+
+#[repr(C, packed)]
+struct MadtEntryHeader {
+    entry_type: u8,
+    len: u8,
+}
+
+/// The Processor Local APIC structure in MADT.
+#[repr(C, packed)]
+struct MadtLocalApic {
+    header: MadtEntryHeader,
+    acpi_processor_id: u8,
+    apic_id: u8,
+    flags: u32,
+}
+
+const MADT_ENTRY_LOCAL_APIC: u8 = 0;
+const LOCAL_APIC_ENABLED: u32 = 1 << 0;
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 1 << 1;
+
+unsafe fn read<T>(paddr: PAddr) -> T {
+    read_unaligned(paddr.as_ptr::<T>())
+}
+
+unsafe fn is_valid_checksum(paddr: PAddr, len: usize) -> bool {
+    let bytes = slice::from_raw_parts(paddr.as_ptr::<u8>(), len);
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// Looks for the Root System Description Pointer (RSDP) in the BIOS memory
+/// areas.
+unsafe fn find_rsdp() -> Option<PAddr> {
+    // The first 1KiB of the Extended BIOS Data Area (EBDA) and the BIOS
+    // read-only memory area.
+    let ebda = (read::<u16>(PAddr::new(0x40e)) as usize) << 4;
+    let areas = [(ebda, ebda + 1024), (0xe0000, 0x100000)];
+    for (start, end) in areas {
+        if start == 0 {
+            continue;
+        }
+
+        for addr in (start..end).step_by(16) {
+            let paddr = PAddr::new(addr);
+            if read::<[u8; 8]>(paddr) == *b"RSD PTR " && is_valid_checksum(paddr, 20) {
+                return Some(paddr);
+            }
+        }
+    }
+
+    None
+}
+
+/// Looks for the table with `signature` in RSDT or XSDT.
+unsafe fn find_table(rsdp: PAddr, signature: &[u8; 4]) -> Option<PAddr> {
+    let rsdp = read::<Rsdp>(rsdp);
+    let (sdt, entry_size) = if rsdp.revision >= 2 && rsdp.xsdt_address != 0 {
+        (PAddr::new(rsdp.xsdt_address as usize), size_of::<u64>())
+    } else {
+        (PAddr::new(rsdp.rsdt_address as usize), size_of::<u32>())
+    };
+
+    let header = read::<SdtHeader>(sdt);
+    let num_entries = (header.length as usize).saturating_sub(size_of::<SdtHeader>()) / entry_size;
+    for i in 0..num_entries {
+        let entry = sdt.add(size_of::<SdtHeader>() + i * entry_size);
+        let table = if entry_size == size_of::<u64>() {
+            PAddr::new(read::<u64>(entry) as usize)
+        } else {
+            PAddr::new(read::<u32>(entry) as usize)
+        };
+
+        let table_header = read::<SdtHeader>(table);
+        if table_header.signature == *signature
+            && is_valid_checksum(table, table_header.length as usize)
+        {
+            return Some(table);
+        }
+    }
+
+    None
+}
+
+/// Returns the local APIC IDs of available CPUs listed in MADT. Returns an
+/// empty list if MADT is not available.
+pub unsafe fn enumerate_cpus() -> ArrayVec<u8, MAX_CPUS> {
+    let mut apic_ids = ArrayVec::new();
+    let madt = match find_rsdp().and_then(|rsdp| find_table(rsdp, b"APIC")) {
+        Some(madt) => madt,
+        None => {
+            warn!("acpi: MADT not found");
+            return apic_ids;
+        }
+    };
+
+    let madt_len = read::<SdtHeader>(madt).length as usize;
+    let mut offset = size_of::<MadtHeader>();
+    while offset + size_of::<MadtEntryHeader>() <= madt_len {
+        let entry_header = read::<MadtEntryHeader>(madt.add(offset));
+        if entry_header.len == 0 {
+            break;
+        }
+
+        if entry_header.entry_type == MADT_ENTRY_LOCAL_APIC {
+            let entry = read::<MadtLocalApic>(madt.add(offset));
+            if entry.flags & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE) != 0
+                && apic_ids.try_push(entry.apic_id).is_err()
+            {
+                warn!("acpi: too many CPUs, ignoring APIC ID {}", entry.apic_id);
+            }
+        }
+
+        offset += entry_header.len as usize;
+    }
+
+    apic_ids
+}
@@ -1,7 +1,10 @@
+use super::{pit::busy_wait_us, tsc};
 use crate::address::PAddr;
 use crate::spinlock::SpinLock;
 use core::ptr::{read_volatile, write_volatile};
-use x86::msr::{self, rdmsr, wrmsr};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x86::cpuid::CpuId;
+use x86::msr::{self, rdmsr, wrmsr, IA32_TSC_DEADLINE};
 
 /// The base index of interrupt vectors.
 const APIC_BASE_EN: u64 = 1 << 11;
@@ -9,11 +12,41 @@ const SIVR_SOFT_EN: u32 = 1 << 8;
 
 static APIC: SpinLock<LocalApic> = SpinLock::new(LocalApic::new(PAddr::new(0xfee0_0000)));
 
+/// The interrupt vector of the local APIC timer.
+pub const APIC_TIMER_VECTOR: u8 = 0xee;
+
+// Fields in the LVT Timer Register.
+const LVT_TIMER_MASKED: u32 = 1 << 16;
+const LVT_TIMER_MODE_TSC_DEADLINE: u32 = 0b10 << 17;
+/// The value in the Divide Configuration Register: divide by 16.
+const TIMER_DIVIDE_BY_16: u32 = 0b0011;
+/// The period to measure the local APIC timer frequency.
+const TIMER_CALIBRATION_US: u64 = 10_000;
+
+/// `true` if the local APIC timer supports the TSC-deadline mode.
+static TSC_DEADLINE_MODE: AtomicBool = AtomicBool::new(false);
+/// The frequency of the local APIC timer (divided by 16) in Hz. Used if the
+/// TSC-deadline mode is not available.
+static TIMER_HZ: AtomicU64 = AtomicU64::new(0);
+
+// Fields in the Interrupt Command Register (ICR).
+const ICR_DELIVERY_MODE_INIT: u32 = 0b101 << 8;
+const ICR_DELIVERY_MODE_STARTUP: u32 = 0b110 << 8;
+const ICR_DELIVERY_STATUS: u32 = 1 << 12;
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+
 #[derive(Debug, Copy, Clone)]
 #[repr(u32)]
 enum LocalApicReg {
+    Id = 0x20,
     Eoi = 0xb0,
     SpuriousInterrupt = 0xf0,
+    IcrLow = 0x300,
+    IcrHigh = 0x310,
+    LvtTimer = 0x320,
+    TimerInitialCount = 0x380,
+    TimerCurrentCount = 0x390,
+    TimerDivideConfig = 0x3e0,
 }
 
 struct LocalApic {
@@ -34,8 +67,24 @@ impl LocalApic {
         self.mmio_write(LocalApicReg::SpuriousInterrupt, value);
     }
 
+    pub unsafe fn read_id(&self) -> u8 {
+        (self.mmio_read(LocalApicReg::Id) >> 24) as u8
+    }
+
+    /// Sends an Inter-Processor Interrupt (IPI). `dest` is the destination
+    /// APIC ID, ignored if a destination shorthand is specified in `icr`.
+    pub unsafe fn send_ipi(&self, dest: u8, icr: u32) {
+        self.mmio_write(LocalApicReg::IcrHigh, (dest as u32) << 24);
+        self.mmio_write(LocalApicReg::IcrLow, icr);
+
+        // Wait until the IPI is accepted.
+        while self.mmio_read(LocalApicReg::IcrLow) & ICR_DELIVERY_STATUS != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
     #[inline(always)]
-    unsafe fn _mmio_read(&self, reg: LocalApicReg) -> u32 {
+    unsafe fn mmio_read(&self, reg: LocalApicReg) -> u32 {
         read_volatile(self.base.add(reg as usize).as_ptr())
     }
 
@@ -51,6 +100,96 @@ pub fn ack_interrupt() {
     }
 }
 
+/// Returns the local APIC ID of the current CPU.
+pub fn apic_id() -> u8 {
+    unsafe { APIC.lock().read_id() }
+}
+
+/// Sends an IPI with `vector` to the CPU.
+pub fn send_ipi(apic_id: u8, vector: u8) {
+    unsafe {
+        APIC.lock()
+            .send_ipi(apic_id, ICR_LEVEL_ASSERT | vector as u32);
+    }
+}
+
+/// Sends an INIT IPI to reset the CPU.
+pub unsafe fn send_init_ipi(apic_id: u8) {
+    APIC.lock()
+        .send_ipi(apic_id, ICR_DELIVERY_MODE_INIT | ICR_LEVEL_ASSERT);
+}
+
+/// Sends a Startup IPI (SIPI): the CPU starts executing at
+/// `start_page * 0x1000` in the real mode.
+pub unsafe fn send_startup_ipi(apic_id: u8, start_page: u8) {
+    APIC.lock().send_ipi(
+        apic_id,
+        ICR_DELIVERY_MODE_STARTUP | ICR_LEVEL_ASSERT | start_page as u32,
+    );
+}
+
+/// Determines the local APIC timer mode and measures its frequency if needed.
+/// Called once in the BSP.
+pub unsafe fn calibrate_timer() {
+    let tsc_deadline = CpuId::new()
+        .get_feature_info()
+        .map(|feats| feats.has_tsc_deadline())
+        .unwrap_or(false);
+
+    if tsc_deadline {
+        info!("apic: using TSC-deadline timer");
+        TSC_DEADLINE_MODE.store(true, Ordering::SeqCst);
+        return;
+    }
+
+    let apic = APIC.lock();
+    apic.mmio_write(LocalApicReg::TimerDivideConfig, TIMER_DIVIDE_BY_16);
+    apic.mmio_write(LocalApicReg::LvtTimer, LVT_TIMER_MASKED);
+    apic.mmio_write(LocalApicReg::TimerInitialCount, u32::MAX);
+    busy_wait_us(TIMER_CALIBRATION_US);
+    let elapsed = u32::MAX - apic.mmio_read(LocalApicReg::TimerCurrentCount);
+    apic.mmio_write(LocalApicReg::TimerInitialCount, 0);
+
+    let hz = elapsed as u64 * 1_000_000 / TIMER_CALIBRATION_US;
+    info!("apic: timer frequency: {} kHz", hz / 1000);
+    TIMER_HZ.store(hz, Ordering::SeqCst);
+}
+
+/// Enables the local APIC timer in the one-shot mode. Called in each CPU.
+pub unsafe fn init_timer() {
+    let apic = APIC.lock();
+    if TSC_DEADLINE_MODE.load(Ordering::Relaxed) {
+        apic.mmio_write(
+            LocalApicReg::LvtTimer,
+            LVT_TIMER_MODE_TSC_DEADLINE | APIC_TIMER_VECTOR as u32,
+        );
+    } else {
+        apic.mmio_write(LocalApicReg::TimerDivideConfig, TIMER_DIVIDE_BY_16);
+        apic.mmio_write(LocalApicReg::LvtTimer, APIC_TIMER_VECTOR as u32);
+    }
+}
+
+/// Programs the local APIC timer of the current CPU to fire at `deadline`
+/// (in nanoseconds since boot). It fires immediately if the deadline has
+/// already passed.
+pub fn set_timer_deadline(deadline: u64) {
+    if TSC_DEADLINE_MODE.load(Ordering::Relaxed) {
+        // Writing zero disarms the timer.
+        let tsc_deadline = tsc::nanosecs_to_tsc(deadline).max(1);
+        unsafe {
+            wrmsr(IA32_TSC_DEADLINE, tsc_deadline);
+        }
+    } else {
+        let delay = deadline.saturating_sub(tsc::nanosecs_since_boot()) as u128;
+        let hz = TIMER_HZ.load(Ordering::Relaxed) as u128;
+        let count = (delay * hz / 1_000_000_000).clamp(1, u32::MAX as u128) as u32;
+        unsafe {
+            APIC.lock()
+                .mmio_write(LocalApicReg::TimerInitialCount, count);
+        }
+    }
+}
+
 pub unsafe fn init() {
     // Activate Local APIC.
     let apic_base = rdmsr(msr::APIC_BASE);
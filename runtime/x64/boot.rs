@@ -1,13 +1,16 @@
-use super::{apic, bootinfo, cpu_local, gdt, idt, ioapic, pit, serial, syscall, tss, vga};
+use super::{
+    apic, bootinfo, cpu_local, gdt, idt, ioapic, rtc, serial, smp, syscall, tsc, tss, vga,
+};
 use crate::address::{PAddr, VAddr};
 use crate::bootinfo::BootInfo;
 use crate::logger;
 use crate::page_allocator;
 
 use x86::{
-    controlregs::{self, Cr4, Xcr0},
+    controlregs::{self, Cr0, Cr4, Xcr0},
     cpuid::CpuId,
     io::outb,
+    msr::{rdmsr, wrmsr, IA32_EFER},
 };
 
 fn check_cpuid_feature(name: &str, supported: bool) {
@@ -16,12 +19,28 @@ fn check_cpuid_feature(name: &str, supported: bool) {
     }
 }
 
-/// Enables some CPU features.
+/// Enables some CPU features. Called in each CPU.
 unsafe fn common_setup(cpu_local_area: VAddr) {
     let feats = CpuId::new().get_feature_info().unwrap();
     let ex_feats = CpuId::new().get_extended_feature_info().unwrap();
     check_cpuid_feature("XSAVE", feats.has_xsave());
     check_cpuid_feature("FSGSBASE", ex_feats.has_fsgsbase());
+    check_cpuid_feature(
+        "NX",
+        CpuId::new()
+            .get_extended_processor_and_feature_identifiers()
+            .unwrap()
+            .has_execute_disable(),
+    );
+
+    // Make the kernel respect read-only user pages too: usercopy functions
+    // need to trigger page faults on copy-on-write pages.
+    let mut cr0 = controlregs::cr0();
+    cr0 |= Cr0::CR0_WRITE_PROTECT;
+    controlregs::cr0_write(cr0);
+
+    // Enable the no-execute bit in page table entries.
+    wrmsr(IA32_EFER, rdmsr(IA32_EFER) | (1 << 11));
 
     let mut cr4 = controlregs::cr4();
     cr4 |= Cr4::CR4_ENABLE_FSGSBASE
@@ -36,11 +55,9 @@ unsafe fn common_setup(cpu_local_area: VAddr) {
 
     cpu_local::init(cpu_local_area);
     apic::init();
-    ioapic::init();
     gdt::init();
     tss::init();
     idt::init();
-    pit::init();
     syscall::init();
 }
 
@@ -64,6 +81,7 @@ unsafe fn init_pic() {
 
 extern "Rust" {
     fn boot_kernel(bootinfo: &BootInfo) -> !;
+    fn boot_ap() -> !;
 }
 
 /// Initializes the CPU. This function is called exactly once in the Bootstrap
@@ -87,6 +105,23 @@ unsafe extern "C" fn bsp_early_init(boot_magic: u32, boot_params: u64) -> ! {
     serial::init(boot_info.use_second_serialport);
     init_pic();
     common_setup(VAddr::new(&__bsp_cpu_local as *const _ as usize));
+    ioapic::init();
+    tsc::init();
+    rtc::init();
+    apic::calibrate_timer();
+    apic::init_timer();
+    smp::init();
 
     boot_kernel(&boot_info);
 }
+
+/// Initializes the CPU. This function is called in each Application Processor
+/// (AP) started by `smp::start_application_processors`.
+#[no_mangle]
+unsafe extern "C" fn ap_early_init() -> ! {
+    common_setup(smp::ap_cpu_local_area());
+    apic::init_timer();
+    smp::ap_online();
+
+    boot_ap();
+}
@@ -2,6 +2,7 @@ use super::gdt::KERNEL_CS;
 use super::tss::IST_RSP0;
 use core::mem::size_of;
 use x86::dtables::{lidt, DescriptorTablePointer};
+use x86::irq::BREAKPOINT_VECTOR;
 
 const HANDLER_SIZE: usize = 16;
 const NUM_IDT_DESCS: usize = 256;
@@ -41,7 +42,12 @@ pub unsafe fn init() {
         idt[i].offset1 = (handler & 0xffff) as u16;
         idt[i].seg = KERNEL_CS;
         idt[i].ist = IST_RSP0;
-        idt[i].info = 0x8e;
+        // Allow INT3 in the userspace (DPL=3).
+        idt[i].info = if i == BREAKPOINT_VECTOR as usize {
+            0xee
+        } else {
+            0x8e
+        };
         idt[i].offset2 = ((handler >> 16) & 0xffff) as u16;
         idt[i].offset3 = ((handler >> 32) & 0xffffffff) as u32;
         idt[i].reserved = 0;
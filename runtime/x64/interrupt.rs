@@ -1,8 +1,15 @@
 use crate::{address::UserVAddr, handler};
 
+use core::arch::asm;
 use core::fmt;
 
-use super::{apic::ack_interrupt, ioapic::VECTOR_IRQ_BASE, serial::SERIAL0_IRQ, PageFaultReason};
+use super::{
+    apic::{ack_interrupt, APIC_TIMER_VECTOR},
+    ioapic::VECTOR_IRQ_BASE,
+    serial::SERIAL0_IRQ,
+    smp::{handle_tlb_shootdown, IPI_RESCHEDULE_VECTOR, IPI_TLB_SHOOTDOWN_VECTOR},
+    PageFaultReason, PtRegs,
+};
 use x86::{
     controlregs::cr2,
     current::rflags::{self, RFlags},
@@ -36,6 +43,58 @@ struct InterruptFrame {
     ss: u64,
 }
 
+impl InterruptFrame {
+    fn to_pt_regs(self) -> PtRegs {
+        PtRegs {
+            r15: self.r15,
+            r14: self.r14,
+            r13: self.r13,
+            r12: self.r12,
+            rbp: self.rbp,
+            rbx: self.rbx,
+            r11: self.r11,
+            r10: self.r10,
+            r9: self.r9,
+            r8: self.r8,
+            rax: self.rax,
+            rcx: self.rcx,
+            rdx: self.rdx,
+            rsi: self.rsi,
+            rdi: self.rdi,
+            // Not in a system call.
+            orig_rax: u64::MAX,
+            rip: self.rip,
+            cs: self.cs,
+            rflags: self.rflags,
+            rsp: self.rsp,
+            ss: self.ss,
+        }
+    }
+
+    /// Updates general-purpose registers, RIP, RFLAGS and RSP. Segment
+    /// registers are left as they are.
+    fn update_from_pt_regs(&mut self, regs: &PtRegs) {
+        self.r15 = regs.r15;
+        self.r14 = regs.r14;
+        self.r13 = regs.r13;
+        self.r12 = regs.r12;
+        self.rbp = regs.rbp;
+        self.rbx = regs.rbx;
+        self.r11 = regs.r11;
+        self.r10 = regs.r10;
+        self.r9 = regs.r9;
+        self.r8 = regs.r8;
+        self.rax = regs.rax;
+        self.rcx = regs.rcx;
+        self.rdx = regs.rdx;
+        self.rsi = regs.rsi;
+        self.rdi = regs.rdi;
+        self.rip = regs.rip;
+        self.rflags = regs.rflags;
+        self.rsp = regs.rsp;
+    }
+}
+
 impl fmt::Debug for InterruptFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let rip = self.rip;
@@ -57,16 +116,14 @@ extern "C" {
 }
 
 #[no_mangle]
-unsafe extern "C" fn x64_handle_interrupt(vec: u8, frame: *const InterruptFrame) {
-    let frame = &*frame;
-
-    // FIXME: Check "Legacy replacement" mapping
-    const TIMER_IRQ: u8 = 0;
-    const TIMER_IRQ2: u8 = 2;
-    if vec != VECTOR_IRQ_BASE + TIMER_IRQ
-        && vec != VECTOR_IRQ_BASE + TIMER_IRQ2
+unsafe extern "C" fn x64_handle_interrupt(vec: u8, frame: *mut InterruptFrame) {
+    let frame = &mut *frame;
+
+    if vec != APIC_TIMER_VECTOR
         && vec != 14
         && vec != 36
+        && vec != IPI_RESCHEDULE_VECTOR
+        && vec != IPI_TLB_SHOOTDOWN_VECTOR
     {
         let rip = frame.rip;
         let rsp = frame.rsp;
@@ -82,14 +139,23 @@ unsafe extern "C" fn x64_handle_interrupt(vec: u8, frame: *const InterruptFrame)
     }
 
     match vec {
+        APIC_TIMER_VECTOR => {
+            ack_interrupt();
+            handler().handle_timer_irq();
+        }
+        IPI_RESCHEDULE_VECTOR => {
+            ack_interrupt();
+            handler().handle_reschedule_ipi();
+        }
+        IPI_TLB_SHOOTDOWN_VECTOR => {
+            ack_interrupt();
+            handle_tlb_shootdown();
+        }
         _ if vec >= VECTOR_IRQ_BASE => {
             ack_interrupt();
 
             let irq = vec - VECTOR_IRQ_BASE;
             match irq {
-                TIMER_IRQ | TIMER_IRQ2 => {
-                    handler().handle_timer_irq();
-                }
                 SERIAL0_IRQ => {
                     super::serial::serial0_irq_handler();
                 }
@@ -102,6 +168,10 @@ unsafe extern "C" fn x64_handle_interrupt(vec: u8, frame: *const InterruptFrame)
             // TODO:
             panic!("unsupported exception: DIVIDE_ERROR\n{:?}", frame);
         }
+        DEBUG_VECTOR | BREAKPOINT_VECTOR if frame.cs & 3 == 3 => {
+            // Single-stepping or INT3 in the userspace.
+            handler().handle_debug_exception();
+        }
         DEBUG_VECTOR => {
             // TODO:
             panic!("unsupported exception: DEBUG\n{:?}", frame);
@@ -207,6 +277,14 @@ unsafe extern "C" fn x64_handle_interrupt(vec: u8, frame: *const InterruptFrame)
             panic!("unexpected interrupt: vec={}", vec);
         }
     }
+
+    if frame.cs & 3 == 3 {
+        // Let the kernel modify the user context (e.g. to call a signal
+        // handler).
+        let mut regs = frame.to_pt_regs();
+        handler().handle_interrupt_return_to_user(&mut regs);
+        frame.update_from_pt_regs(&regs);
+    }
 }
 
 pub struct SavedInterruptStatus {
@@ -219,6 +297,16 @@ impl SavedInterruptStatus {
             rflags: rflags::read(),
         }
     }
+
+    /// Disables interrupts until the returned value is dropped.
+    pub fn disable() -> SavedInterruptStatus {
+        let saved = SavedInterruptStatus::save();
+        unsafe {
+            asm!("cli");
+        }
+
+        saved
+    }
 }
 
 impl Drop for SavedInterruptStatus {
@@ -7,6 +7,7 @@ global_asm!(include_str!("usercopy.S"));
 #[macro_use]
 mod cpu_local;
 
+mod acpi;
 mod apic;
 mod backtrace;
 mod boot;
@@ -19,20 +20,29 @@ mod ioapic;
 mod paging;
 mod pit;
 mod profile;
+mod rtc;
 mod semihosting;
 mod serial;
+mod smp;
 mod syscall;
+mod tsc;
 mod tss;
 mod vga;
 
+pub use apic::set_timer_deadline;
 pub use backtrace::Backtrace;
 pub use idle::{halt, idle};
 pub use interrupt::SavedInterruptStatus;
 pub use ioapic::enable_irq;
-pub use paging::{PageFaultReason, PageTable};
+pub use paging::{PageFaultReason, PageProtection, PageTable};
 pub use profile::read_clock_counter;
+pub use rtc::wallclock_at_boot;
 pub use semihosting::{semihosting_halt, SemihostingExitStatus};
+pub use smp::{cpu_id, is_cpu_online, num_cpus, send_reschedule_ipi, start_application_processors};
 pub use syscall::PtRegs;
+pub use tsc::nanosecs_since_boot;
+
+pub(crate) use smp::{handle_tlb_shootdown, try_cpu_id};
 
 pub mod x64_specific {
     pub use super::cpu_local::cpu_local_head;
@@ -41,6 +51,7 @@ pub mod x64_specific {
 }
 
 pub const PAGE_SIZE: usize = 4096;
+/// The frequency of the periodic timer interrupt while the CPU is busy.
 pub const TICK_HZ: usize = 1000;
 
 /// The base virtual address of straight mapping.
@@ -1,6 +1,9 @@
-use super::PAGE_SIZE;
+use super::{smp, PAGE_SIZE};
 use crate::address::{PAddr, UserVAddr};
-use crate::page_allocator::{alloc_pages, AllocPageFlags, PageAllocError};
+use crate::page_allocator::{
+    alloc_pages, free_pages, ref_page, unref_page, AllocPageFlags, PageAllocError,
+};
+use alloc::vec::Vec;
 use bitflags::bitflags;
 use core::{
     debug_assert,
@@ -16,6 +19,38 @@ bitflags! {
         const PRESENT = 1 << 0;
         const WRITABLE = 1 << 1;
         const USER = 1 << 2;
+        const NO_EXECUTE = 1 << 63;
+    }
+}
+
+bitflags! {
+    /// Allowed accesses to a user page.
+    pub struct PageProtection: u32 {
+        const READABLE = 1 << 0;
+        const WRITABLE = 1 << 1;
+        const EXECUTABLE = 1 << 2;
+    }
+}
+
+impl From<PageProtection> for PageAttrs {
+    fn from(prot: PageProtection) -> PageAttrs {
+        // Pages with no permissions are marked as not present: the kernel
+        // must not access them either. The physical address is kept in the
+        // entry to retain their contents.
+        if prot.is_empty() {
+            return PageAttrs::NO_EXECUTE;
+        }
+
+        // x86 does not support write-only or execute-only pages: they're
+        // readable too.
+        let mut attrs = PageAttrs::PRESENT | PageAttrs::USER;
+        if prot.contains(PageProtection::WRITABLE) {
+            attrs |= PageAttrs::WRITABLE;
+        }
+        if !prot.contains(PageProtection::EXECUTABLE) {
+            attrs |= PageAttrs::NO_EXECUTE;
+        }
+        attrs
     }
 }
 
@@ -41,13 +76,9 @@ fn nth_level_table_index(vaddr: UserVAddr, level: usize) -> isize {
     ((vaddr.value() >> ((((level) - 1) * 9) + 12)) & 0x1ff) as isize
 }
 
-fn traverse(
-    pml4: PAddr,
-    vaddr: UserVAddr,
-    allocate: bool,
-    attrs: PageAttrs,
-) -> Option<NonNull<PageTableEntry>> {
+fn traverse(pml4: PAddr, vaddr: UserVAddr, allocate: bool) -> Option<NonNull<PageTableEntry>> {
     debug_assert!(is_aligned(vaddr.value(), PAGE_SIZE));
+    let attrs = PageAttrs::PRESENT | PageAttrs::USER | PageAttrs::WRITABLE;
     let mut table = pml4.as_mut_ptr::<PageTableEntry>();
     for level in (2..=4).rev() {
         let index = nth_level_table_index(vaddr, level);
@@ -63,12 +94,13 @@ fn traverse(
                 alloc_pages(1, AllocPageFlags::KERNEL).expect("failed to allocate page table");
             unsafe {
                 new_table.as_mut_ptr::<u8>().write_bytes(0, PAGE_SIZE);
-                *entry = new_table.value() as u64 | attrs.bits()
             };
 
             table_paddr = new_table;
         }
 
+        // Intermediate tables allow all accesses: permissions are controlled
+        // by the last-level entries.
         unsafe { *entry = table_paddr.value() as u64 | attrs.bits() };
         table = table_paddr.as_mut_ptr::<PageTableEntry>();
     }
@@ -80,8 +112,14 @@ fn traverse(
     }
 }
 
-/// Duplicates entires (and referenced memory pages if `level == 1`) in the
-/// nth-level page table. Returns the newly created copy of the page table.
+/// Duplicates entires in the nth-level page table. Returns the newly created
+/// copy of the page table.
+///
+/// Memory pages referenced from the last-level table are not copied: they're
+/// shared between the original and the copy as read-only pages and copied
+/// later in the page fault handler (copy-on-write). The original table is left
+/// unchanged: write-protect it by [`write_protect_table`] once the copy
+/// succeeds. On failure, the partial copy is freed.
 ///
 /// fork(2) uses this funciton to duplicate the memory space.
 fn duplicate_table(original_table_paddr: PAddr, level: usize) -> Result<PAddr, PageAllocError> {
@@ -101,12 +139,10 @@ fn duplicate_table(original_table_paddr: PAddr, level: usize) -> Result<PAddr, P
 
         // Create a deep copy of the page table entry.
         let new_paddr = if level == 1 {
-            // Copy a physical page referenced from the last-level page table.
-            let new_paddr = alloc_pages(1, AllocPageFlags::KERNEL)?;
-            unsafe {
-                ptr::copy_nonoverlapping::<u8>(paddr.as_ptr(), new_paddr.as_mut_ptr(), PAGE_SIZE);
-            }
-            new_paddr
+            // Share the physical page referenced from the last-level page
+            // table. The first write to it copies the page.
+            ref_page(paddr);
+            paddr
         } else {
             // Copy the page table (PML4, PDPT, ...).
             if level == 4 && i >= 0x80 {
@@ -114,29 +150,98 @@ fn duplicate_table(original_table_paddr: PAddr, level: usize) -> Result<PAddr, P
                 entry_paddr(entry)
             } else {
                 // Create the deep copy of the referenced page table recursively...
-                duplicate_table(paddr, level - 1)?
+                match duplicate_table(paddr, level - 1) {
+                    Ok(new_paddr) => new_paddr,
+                    Err(err) => {
+                        // Unreference the pages and free the tables copied so
+                        // far. Unfilled entries are zeroes.
+                        free_table(new_table_paddr, level);
+                        return Err(err);
+                    }
+                }
             }
         };
 
         // Fill the new table's entry.
+        let flags = if level == 1 {
+            entry_flags(entry) & !PageAttrs::WRITABLE.bits()
+        } else {
+            entry_flags(entry)
+        };
+
         unsafe {
-            *new_table.offset(i) = new_paddr.value() as u64 | entry_flags(entry);
+            *new_table.offset(i) = new_paddr.value() as u64 | flags;
         }
     }
 
     Ok(new_table_paddr)
 }
 
-fn allocate_pml4() -> Result<PAddr, PageAllocError> {
+/// Write-protects memory pages mapped in the user space of the nth-level page
+/// table to share them with its copy by [`duplicate_table`].
+fn write_protect_table(table_paddr: PAddr, level: usize) {
+    let table = table_paddr.as_mut_ptr::<PageTableEntry>();
+    for i in 0..ENTRIES_PER_TABLE {
+        if level == 4 && i >= 0x80 {
+            // Kernel page table entries are shared among all page tables.
+            break;
+        }
+
+        let entry = unsafe { *table.offset(i) };
+        let paddr = entry_paddr(entry);
+        if paddr.is_null() {
+            continue;
+        }
+
+        if level == 1 {
+            unsafe {
+                *table.offset(i) = entry & !PageAttrs::WRITABLE.bits();
+            }
+        } else {
+            write_protect_table(paddr, level - 1);
+        }
+    }
+}
+
+/// Frees the nth-level page table, its descendant tables, and unreferences
+/// memory pages mapped in the user space.
+fn free_table(table_paddr: PAddr, level: usize) {
+    let table = table_paddr.as_ptr::<PageTableEntry>();
+    for i in 0..ENTRIES_PER_TABLE {
+        if level == 4 && i >= 0x80 {
+            // Kernel page table entries are shared among all page tables.
+            break;
+        }
+
+        let paddr = entry_paddr(unsafe { *table.offset(i) });
+        if paddr.is_null() {
+            continue;
+        }
+
+        if level == 1 {
+            unref_page(paddr);
+        } else {
+            free_table(paddr, level - 1);
+        }
+    }
+
+    free_pages(table_paddr, 1);
+}
+
+pub(super) fn kernel_pml4() -> PAddr {
     extern "C" {
         static __kernel_pml4: u8;
     }
 
+    unsafe { PAddr::new(&__kernel_pml4 as *const u8 as usize) }
+}
+
+fn allocate_pml4() -> Result<PAddr, PageAllocError> {
     let pml4 = alloc_pages(1, AllocPageFlags::KERNEL)?;
 
     // Map kernel pages.
     unsafe {
-        let kernel_pml4 = PAddr::new(&__kernel_pml4 as *const u8 as usize).as_vaddr();
+        let kernel_pml4 = kernel_pml4().as_vaddr();
         pml4.as_mut_ptr::<u8>().write_bytes(0, PAGE_SIZE);
         ptr::copy_nonoverlapping::<u8>(kernel_pml4.as_ptr(), pml4.as_mut_ptr(), PAGE_SIZE);
     }
@@ -163,32 +268,147 @@ impl PageTable {
         Ok(PageTable { pml4 })
     }
 
-    pub fn duplicate_from(original: &PageTable) -> Result<PageTable, PageAllocError> {
-        // TODO: Implement copy-on-write.
-        Ok(PageTable {
-            pml4: duplicate_table(original.pml4, 4)?,
-        })
+    /// Duplicates the page table. Pages are shared in copy-on-write manner:
+    /// they're mapped as read-only in both page tables.
+    pub fn duplicate_from(original: &mut PageTable) -> Result<PageTable, PageAllocError> {
+        let pml4 = duplicate_table(original.pml4, 4)?;
+        write_protect_table(original.pml4, 4);
+
+        // Writable entries in the original page table have been changed to
+        // read-only. Flush TLB to take effect. The page table might be in use
+        // by other threads running on other CPUs.
+        if original.is_active() {
+            original.switch();
+        }
+        smp::tlb_shootdown_all(original.pml4);
+
+        Ok(PageTable { pml4 })
+    }
+
+    fn is_active(&self) -> bool {
+        unsafe { x86::controlregs::cr3() & 0x7ffffffffffff000 == self.pml4.value() as u64 }
     }
 
     pub fn switch(&self) {
+        smp::switch_page_table(self.pml4);
+    }
+
+    pub fn map_user_page(&mut self, vaddr: UserVAddr, paddr: PAddr, prot: PageProtection) {
+        self.map_page(vaddr, paddr, prot.into());
+    }
+
+    /// Unmaps the page at `vaddr`. Returns the physical address of the page
+    /// if it was mapped. The caller is responsible for freeing the page.
+    pub fn unmap_user_page(&mut self, vaddr: UserVAddr) -> Option<PAddr> {
+        let paddr = self.clear_entry(vaddr)?;
+        self.flush_tlb(vaddr, PAGE_SIZE, true);
+        Some(paddr)
+    }
+
+    /// Unmaps pages in `[start, start + len)` and flushes TLB at once. Returns
+    /// the physical addresses of the unmapped pages: the caller is
+    /// responsible for freeing them.
+    pub fn unmap_user_pages(&mut self, start: UserVAddr, len: usize) -> Vec<PAddr> {
+        debug_assert!(is_aligned(start.value(), PAGE_SIZE));
+        let paddrs: Vec<PAddr> = (0..len)
+            .step_by(PAGE_SIZE)
+            .filter_map(|offset| self.clear_entry(start.add(offset)))
+            .collect();
+
+        if !paddrs.is_empty() {
+            self.flush_tlb(start, len, true);
+        }
+
+        paddrs
+    }
+
+    /// Changes the protection of mapped pages in `[start, start + len)` and
+    /// flushes TLB at once. `prot_of` returns the new protection of the page
+    /// at the physical address.
+    pub fn protect_user_pages<F>(&mut self, start: UserVAddr, len: usize, mut prot_of: F)
+    where
+        F: FnMut(PAddr) -> PageProtection,
+    {
+        debug_assert!(is_aligned(start.value(), PAGE_SIZE));
+        let mut updated = false;
+        for offset in (0..len).step_by(PAGE_SIZE) {
+            let vaddr = start.add(offset);
+            if let Some(paddr) = self.lookup_user_page(vaddr) {
+                let attrs: PageAttrs = prot_of(paddr).into();
+                let mut entry = traverse(self.pml4, vaddr, false).unwrap();
+                unsafe {
+                    *entry.as_mut() = paddr.value() as u64 | attrs.bits();
+                }
+                updated = true;
+            }
+        }
+
+        if updated {
+            self.flush_tlb(start, len, true);
+        }
+    }
+
+    /// Clears the page table entry for `vaddr` without flushing TLB. Returns
+    /// the physical address of the page if it was mapped.
+    fn clear_entry(&mut self, vaddr: UserVAddr) -> Option<PAddr> {
+        let mut entry = traverse(self.pml4, vaddr, false)?;
+        let paddr = entry_paddr(unsafe { *entry.as_ref() });
+        if paddr.is_null() {
+            return None;
+        }
+
         unsafe {
-            x86::controlregs::cr3_write(self.pml4.value() as u64);
+            *entry.as_mut() = 0;
         }
+
+        Some(paddr)
     }
 
-    pub fn map_user_page(&mut self, vaddr: UserVAddr, paddr: PAddr) {
-        self.map_page(
-            vaddr,
-            paddr,
-            PageAttrs::PRESENT | PageAttrs::USER | PageAttrs::WRITABLE,
-        );
+    /// Returns the physical address of the page mapped at `vaddr`.
+    pub fn lookup_user_page(&self, vaddr: UserVAddr) -> Option<PAddr> {
+        let entry = traverse(self.pml4, vaddr, false)?;
+        let paddr = entry_paddr(unsafe { *entry.as_ref() });
+        if paddr.is_null() {
+            None
+        } else {
+            Some(paddr)
+        }
     }
 
     fn map_page(&mut self, vaddr: UserVAddr, paddr: PAddr, attrs: PageAttrs) {
         debug_assert!(is_aligned(vaddr.value(), PAGE_SIZE));
-        let mut entry = traverse(self.pml4, vaddr, true, attrs).unwrap();
+        let mut entry = traverse(self.pml4, vaddr, true).unwrap();
+        let was_present = unsafe { *entry.as_ref() } & PageAttrs::PRESENT.bits() != 0;
         unsafe {
             *entry.as_mut() = paddr.value() as u64 | attrs.bits();
         }
+
+        // The page might have been mapped with different attributes.
+        self.flush_tlb(vaddr, PAGE_SIZE, was_present);
+    }
+
+    /// Flushes the TLB entries for `[start, start + len)`. If `shootdown` is
+    /// true, other CPUs using the page table flush them too: TLB never caches
+    /// non-present entries.
+    fn flush_tlb(&self, start: UserVAddr, len: usize, shootdown: bool) {
+        if self.is_active() {
+            smp::flush_tlb_range(start.value(), len);
+        }
+
+        if shootdown {
+            smp::tlb_shootdown_range(self.pml4, start.value(), len);
+        }
+    }
+}
+
+impl Drop for PageTable {
+    fn drop(&mut self) {
+        // Don't free the page table in use. Switch to the kernel's one.
+        if self.is_active() {
+            smp::switch_page_table(kernel_pml4());
+        }
+        smp::release_page_table(self.pml4);
+
+        free_table(self.pml4, 4);
     }
 }
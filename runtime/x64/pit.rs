@@ -1,15 +1,32 @@
-use super::ioapic::enable_irq;
-use x86::io::outb;
+use core::cmp::min;
+use x86::io::{inb, outb};
 
-const DIVISOR: u16 = (1193182u32 / 1000) as u16;
+const PIT_HZ: u64 = 1193182;
 
-pub unsafe fn init() {
-    trace!("enabling PIT (i8254) timer: divisor={}", DIVISOR);
-    outb(0x43, 0x35);
-    outb(0x40, (DIVISOR & 0xff) as u8);
-    outb(0x40, (DIVISOR >> 8) as u8);
+/// Busy-waits for `us` microseconds using the PIT channel 2.
+pub fn busy_wait_us(mut us: u64) {
+    while us > 0 {
+        // The counter is 16-bit: wait up to 50 milliseconds at once.
+        let chunk = min(us, 50_000);
+        let count = (PIT_HZ * chunk / 1_000_000).clamp(1, 0xffff) as u16;
+        unsafe {
+            // Enable the gate of channel 2 and disconnect the speaker.
+            let port61 = inb(0x61);
+            outb(0x61, (port61 & !0x02) | 0x01);
 
-    // FIXME: Check "Legacy replacement" mapping
-    enable_irq(0);
-    enable_irq(2);
+            // Channel 2, lobyte/hibyte, mode 0 (interrupt on terminal count).
+            outb(0x43, 0xb0);
+            outb(0x42, (count & 0xff) as u8);
+            outb(0x42, (count >> 8) as u8);
+
+            // OUT2 goes high when the counter reaches zero.
+            while inb(0x61) & 0x20 == 0 {
+                core::hint::spin_loop();
+            }
+
+            outb(0x61, port61);
+        }
+
+        us -= chunk;
+    }
 }
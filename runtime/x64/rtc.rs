@@ -0,0 +1,134 @@
+//! The real-time clock (RTC) in CMOS. It's read once at boot to initialize
+//! the wall clock.
+use super::tsc::nanosecs_since_boot;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86::io::{inb, outb};
+
+const CMOS_ADDR: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const RTC_SECONDS: u8 = 0x00;
+const RTC_MINUTES: u8 = 0x02;
+const RTC_HOURS: u8 = 0x04;
+const RTC_DAY: u8 = 0x07;
+const RTC_MONTH: u8 = 0x08;
+const RTC_YEAR: u8 = 0x09;
+const RTC_STATUS_A: u8 = 0x0a;
+const RTC_STATUS_B: u8 = 0x0b;
+
+/// Status A: the RTC is updating its registers.
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+/// Status B: the hour is in the 24-hour format.
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+/// Status B: values are in binary instead of BCD.
+const STATUS_B_BINARY: u8 = 1 << 2;
+/// The PM bit in the hour register in the 12-hour format.
+const HOUR_PM: u8 = 1 << 7;
+
+/// The wall clock at boot in nanoseconds from the epoch.
+static WALLCLOCK_AT_BOOT: AtomicU64 = AtomicU64::new(0);
+
+fn read_cmos(reg: u8) -> u8 {
+    unsafe {
+        outb(CMOS_ADDR, reg);
+        inb(CMOS_DATA)
+    }
+}
+
+#[derive(PartialEq, Eq)]
+struct RtcTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+}
+
+fn read_registers() -> RtcTime {
+    while read_cmos(RTC_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+
+    RtcTime {
+        second: read_cmos(RTC_SECONDS),
+        minute: read_cmos(RTC_MINUTES),
+        hour: read_cmos(RTC_HOURS),
+        day: read_cmos(RTC_DAY),
+        month: read_cmos(RTC_MONTH),
+        year: read_cmos(RTC_YEAR),
+    }
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0f) + (value >> 4) * 10
+}
+
+/// The number of days from the epoch to the date (the proleptic Gregorian
+/// calendar).
+fn days_from_epoch(year: u64, month: u64, day: u64) -> u64 {
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 12)
+    } else {
+        (year, month)
+    };
+
+    // Days from 0000-03-01 to the date minus those to 1970-01-01.
+    365 * year + year / 4 - year / 100 + year / 400 + (153 * (month - 3) + 2) / 5 + day - 1 - 719468
+}
+
+/// Reads the RTC and returns the seconds from the epoch. It assumes the RTC
+/// is in UTC and in the 21st century.
+fn read_rtc() -> u64 {
+    // Read registers until we get the same values twice not to read them
+    // while they're being updated.
+    let mut time = read_registers();
+    loop {
+        let next = read_registers();
+        if next == time {
+            break;
+        }
+
+        time = next;
+    }
+
+    let status_b = read_cmos(RTC_STATUS_B);
+    let pm = time.hour & HOUR_PM != 0;
+    let mut hour = time.hour & !HOUR_PM;
+    let (mut second, mut minute, mut day, mut month, mut year) =
+        (time.second, time.minute, time.day, time.month, time.year);
+    if status_b & STATUS_B_BINARY == 0 {
+        second = bcd_to_binary(second);
+        minute = bcd_to_binary(minute);
+        hour = bcd_to_binary(hour);
+        day = bcd_to_binary(day);
+        month = bcd_to_binary(month);
+        year = bcd_to_binary(year);
+    }
+
+    if status_b & STATUS_B_24_HOUR == 0 {
+        // 12 AM is 0 o'clock and 12 PM is 12 o'clock.
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let days = days_from_epoch(2000 + year as u64, month as u64, day as u64);
+    days * 86400 + hour as u64 * 3600 + minute as u64 * 60 + second as u64
+}
+
+/// Initializes the wall clock from the RTC. Called in the BSP after the TSC
+/// is calibrated.
+pub unsafe fn init() {
+    let secs = read_rtc();
+    let wallclock = (secs * 1_000_000_000).saturating_sub(nanosecs_since_boot());
+    WALLCLOCK_AT_BOOT.store(wallclock, Ordering::SeqCst);
+    info!("rtc: {} seconds from the epoch", secs);
+}
+
+/// The wall clock at boot in nanoseconds from the epoch (00:00:00 on 1
+/// January 1970, UTC).
+pub fn wallclock_at_boot() -> u64 {
+    WALLCLOCK_AT_BOOT.load(Ordering::Relaxed)
+}
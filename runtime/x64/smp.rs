@@ -0,0 +1,341 @@
+//! Symmetric multiprocessing (SMP) support: starting Application Processors
+//! (APs) and Inter-Processor Interrupts (IPIs).
+use super::{acpi, apic, paging::kernel_pml4, pit::busy_wait_us, PAGE_SIZE};
+use crate::{
+    address::{PAddr, VAddr},
+    page_allocator::{alloc_pages, AllocPageFlags},
+};
+use core::hint::spin_loop;
+use core::ptr;
+use core::sync::atomic::{fence, AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use kerla_utils::alignment::align_up;
+use x86::controlregs::{cr3, cr3_write};
+
+pub const MAX_CPUS: usize = 64;
+
+/// The interrupt vector to request rescheduling.
+pub const IPI_RESCHEDULE_VECTOR: u8 = 0xf0;
+/// The interrupt vector to request flushing TLB.
+pub const IPI_TLB_SHOOTDOWN_VECTOR: u8 = 0xf1;
+
+/// The physical address where APs start executing in the real mode. Must be
+/// consistent with `AP_TRAMPOLINE_PADDR` in boot.S.
+const AP_TRAMPOLINE_PADDR: usize = 0x8000;
+const AP_BOOT_STACK_SIZE: usize = 0x10000;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const APIC_ID_INIT: AtomicU8 = AtomicU8::new(0);
+/// The local APIC IDs indexed by the CPU ID. The BSP is always CPU #0.
+static APIC_IDS: [AtomicU8; MAX_CPUS] = [APIC_ID_INIT; MAX_CPUS];
+static NUM_CPUS: AtomicUsize = AtomicUsize::new(1);
+/// The bitmap of CPUs which have been started.
+static ONLINE_CPUS: AtomicU64 = AtomicU64::new(1);
+
+/// Only the BSP is running.
+const CPUS_BSP_ONLY: u8 = 0;
+/// APs are being started: ones not yet initialized don't know their CPU IDs.
+const CPUS_STARTING: u8 = 1;
+/// All APs have been started.
+const CPUS_STARTED: u8 = 2;
+static CPUS_STATE: AtomicU8 = AtomicU8::new(CPUS_BSP_ONLY);
+
+/// The CPU ID and the CPU-local area for the AP being started.
+static AP_CPU_ID: AtomicUsize = AtomicUsize::new(0);
+static AP_CPU_LOCAL: AtomicUsize = AtomicUsize::new(0);
+
+cpu_local! {
+    static ref CPU_ID: usize = 0;
+}
+
+/// Returns the ID of the current CPU (`0` to `num_cpus() - 1`).
+pub fn cpu_id() -> usize {
+    *CPU_ID.get()
+}
+
+/// Returns the ID of the current CPU, or `None` while APs are being started:
+/// the CPU might be an AP which has not yet initialized its CPU-local area.
+/// Unlike [`cpu_id`], it's available before the CPU-local area of the BSP is
+/// initialized.
+pub fn try_cpu_id() -> Option<usize> {
+    match CPUS_STATE.load(Ordering::Acquire) {
+        CPUS_BSP_ONLY => Some(0),
+        CPUS_STARTED => Some(cpu_id()),
+        _ => None,
+    }
+}
+
+/// Returns the number of CPUs in the system (including ones not yet started).
+pub fn num_cpus() -> usize {
+    NUM_CPUS.load(Ordering::Relaxed)
+}
+
+/// Returns `true` if the CPU has been started.
+pub fn is_cpu_online(cpu_id: usize) -> bool {
+    ONLINE_CPUS.load(Ordering::SeqCst) & (1 << cpu_id) != 0
+}
+
+/// Requests the CPU to reschedule.
+pub fn send_reschedule_ipi(cpu_id: usize) {
+    if is_cpu_online(cpu_id) {
+        apic::send_ipi(
+            APIC_IDS[cpu_id].load(Ordering::Relaxed),
+            IPI_RESCHEDULE_VECTOR,
+        );
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ACTIVE_PML4_INIT: AtomicUsize = AtomicUsize::new(0);
+/// The page table (PML4) loaded in each CPU. Only CPUs using the page table
+/// are requested to flush TLB.
+static ACTIVE_PML4: [AtomicUsize; MAX_CPUS] = [ACTIVE_PML4_INIT; MAX_CPUS];
+
+/// Flushes pages one by one up to this number. Otherwise, flushes all.
+const MAX_FLUSH_PAGES: usize = 32;
+
+const SHOOTDOWN_RANGE: usize = 0;
+const SHOOTDOWN_ALL: usize = 1;
+const SHOOTDOWN_RELEASE_PML4: usize = 2;
+
+/// Set while a CPU is requesting a TLB shootdown.
+static SHOOTDOWN_LOCK: AtomicBool = AtomicBool::new(false);
+static SHOOTDOWN_KIND: AtomicUsize = AtomicUsize::new(0);
+static SHOOTDOWN_PML4: AtomicUsize = AtomicUsize::new(0);
+static SHOOTDOWN_START: AtomicUsize = AtomicUsize::new(0);
+static SHOOTDOWN_LEN: AtomicUsize = AtomicUsize::new(0);
+/// The bitmap of CPUs which have not yet handled the request.
+static SHOOTDOWN_PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// Loads the page table into the current CPU.
+pub(super) fn switch_page_table(pml4: PAddr) {
+    // Publish the page table before loading it: a CPU updating its entries
+    // either sees this CPU using it or this CPU loads the updated entries.
+    ACTIVE_PML4[cpu_id()].store(pml4.value(), Ordering::SeqCst);
+    unsafe {
+        cr3_write(pml4.value() as u64);
+    }
+}
+
+/// Flushes the TLB entries for `[start, start + len)` in the current CPU.
+pub(super) fn flush_tlb_range(start: usize, len: usize) {
+    unsafe {
+        if len / PAGE_SIZE > MAX_FLUSH_PAGES {
+            cr3_write(cr3());
+        } else {
+            for vaddr in (start..start + len).step_by(PAGE_SIZE) {
+                x86::tlb::flush(vaddr);
+            }
+        }
+    }
+}
+
+/// Handles the TLB shootdown request from another CPU if any.
+///
+/// The requester waits for all other CPUs to handle the request with
+/// interrupts disabled. To avoid dead locks, this is also called while
+/// spinning on a lock.
+pub fn handle_tlb_shootdown() {
+    let pending = SHOOTDOWN_PENDING.load(Ordering::SeqCst);
+    if pending == 0 {
+        return;
+    }
+
+    let bit = 1 << cpu_id();
+    if pending & bit == 0 {
+        return;
+    }
+
+    let pml4 = SHOOTDOWN_PML4.load(Ordering::SeqCst);
+    match SHOOTDOWN_KIND.load(Ordering::SeqCst) {
+        SHOOTDOWN_RANGE => flush_tlb_range(
+            SHOOTDOWN_START.load(Ordering::SeqCst),
+            SHOOTDOWN_LEN.load(Ordering::SeqCst),
+        ),
+        SHOOTDOWN_RELEASE_PML4 if ACTIVE_PML4[cpu_id()].load(Ordering::SeqCst) == pml4 => {
+            switch_page_table(kernel_pml4());
+        }
+        _ => unsafe { cr3_write(cr3()) },
+    }
+
+    SHOOTDOWN_PENDING.fetch_and(!bit, Ordering::SeqCst);
+}
+
+/// Requests other CPUs using the page table `pml4` to flush TLB and waits
+/// for them.
+fn tlb_shootdown(pml4: PAddr, kind: usize, start: usize, len: usize) {
+    // The page table entries have been updated before checking which CPUs
+    // are using it (see `switch_page_table`).
+    fence(Ordering::SeqCst);
+    let online = ONLINE_CPUS.load(Ordering::SeqCst);
+    if online.count_ones() <= 1 {
+        return;
+    }
+
+    let current = cpu_id();
+    let targets = (0..num_cpus())
+        .filter(|cpu| {
+            *cpu != current
+                && online & (1 << cpu) != 0
+                && ACTIVE_PML4[*cpu].load(Ordering::SeqCst) == pml4.value()
+        })
+        .fold(0u64, |targets, cpu| targets | (1 << cpu));
+    if targets == 0 {
+        return;
+    }
+
+    while SHOOTDOWN_LOCK
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        handle_tlb_shootdown();
+        spin_loop();
+    }
+
+    SHOOTDOWN_KIND.store(kind, Ordering::SeqCst);
+    SHOOTDOWN_PML4.store(pml4.value(), Ordering::SeqCst);
+    SHOOTDOWN_START.store(start, Ordering::SeqCst);
+    SHOOTDOWN_LEN.store(len, Ordering::SeqCst);
+    SHOOTDOWN_PENDING.store(targets, Ordering::SeqCst);
+    for (cpu, apic_id) in APIC_IDS.iter().enumerate().take(num_cpus()) {
+        if targets & (1 << cpu) != 0 {
+            apic::send_ipi(apic_id.load(Ordering::Relaxed), IPI_TLB_SHOOTDOWN_VECTOR);
+        }
+    }
+
+    while SHOOTDOWN_PENDING.load(Ordering::SeqCst) != 0 {
+        spin_loop();
+    }
+
+    SHOOTDOWN_LOCK.store(false, Ordering::Release);
+}
+
+/// Flushes the TLB entries for `[start, start + len)` in other CPUs using the
+/// page table.
+pub fn tlb_shootdown_range(pml4: PAddr, start: usize, len: usize) {
+    tlb_shootdown(pml4, SHOOTDOWN_RANGE, start, len);
+}
+
+/// Flushes all TLB entries in other CPUs using the page table.
+pub fn tlb_shootdown_all(pml4: PAddr) {
+    tlb_shootdown(pml4, SHOOTDOWN_ALL, 0, 0);
+}
+
+/// Makes other CPUs stop using the page table: they switch to the kernel's one
+/// if it's active.
+pub fn release_page_table(pml4: PAddr) {
+    tlb_shootdown(pml4, SHOOTDOWN_RELEASE_PML4, 0, 0);
+}
+
+/// Enumerates CPUs. Called in the BSP.
+pub unsafe fn init() {
+    let bsp_apic_id = apic::apic_id();
+    APIC_IDS[0].store(bsp_apic_id, Ordering::Relaxed);
+
+    let mut num_cpus = 1;
+    for apic_id in acpi::enumerate_cpus() {
+        if apic_id != bsp_apic_id {
+            APIC_IDS[num_cpus].store(apic_id, Ordering::Relaxed);
+            num_cpus += 1;
+        }
+    }
+
+    info!("smp: found {} CPU(s)", num_cpus);
+    NUM_CPUS.store(num_cpus, Ordering::SeqCst);
+}
+
+/// Starts APs. They call `boot_ap` once they're initialized.
+///
+/// # Safety
+///
+/// Must be called only once in the BSP after the kernel has been initialized.
+pub unsafe fn start_application_processors() {
+    extern "C" {
+        static ap_trampoline: u8;
+        static ap_trampoline_end: u8;
+        static ap_boot_stack: u8;
+        static __cpu_local_size: u8;
+    }
+
+    // The trampoline is in the .boot section: its symbols are physical
+    // addresses.
+    let trampoline = &ap_trampoline as *const _ as usize;
+    let trampoline_len = &ap_trampoline_end as *const _ as usize - trampoline;
+    let boot_stack_offset = &ap_boot_stack as *const _ as usize - trampoline;
+    ptr::copy_nonoverlapping::<u8>(
+        PAddr::new(trampoline).as_ptr(),
+        PAddr::new(AP_TRAMPOLINE_PADDR).as_mut_ptr(),
+        trampoline_len,
+    );
+
+    CPUS_STATE.store(CPUS_STARTING, Ordering::Release);
+    let mut all_started = true;
+    let cpu_local_size = &__cpu_local_size as *const _ as usize;
+    for (cpu_id, apic_id) in APIC_IDS.iter().enumerate().take(num_cpus()).skip(1) {
+        let apic_id = apic_id.load(Ordering::Relaxed);
+        let boot_stack = alloc_pages(AP_BOOT_STACK_SIZE / PAGE_SIZE, AllocPageFlags::KERNEL)
+            .expect("failed to allocate the AP boot stack");
+        let cpu_local = alloc_pages(
+            align_up(cpu_local_size, PAGE_SIZE) / PAGE_SIZE,
+            AllocPageFlags::KERNEL,
+        )
+        .expect("failed to allocate the AP cpu-local area");
+
+        // The AP uses the stack in the 32-bit mode.
+        let boot_stack_top = boot_stack.value() + AP_BOOT_STACK_SIZE;
+        debug_assert!(boot_stack_top <= u32::MAX as usize);
+        PAddr::new(AP_TRAMPOLINE_PADDR + boot_stack_offset)
+            .as_mut_ptr::<u32>()
+            .write_volatile(boot_stack_top as u32);
+        AP_CPU_ID.store(cpu_id, Ordering::SeqCst);
+        AP_CPU_LOCAL.store(cpu_local.as_vaddr().value(), Ordering::SeqCst);
+
+        // The INIT-SIPI-SIPI sequence.
+        apic::send_init_ipi(apic_id);
+        busy_wait_us(10_000);
+        for _ in 0..2 {
+            apic::send_startup_ipi(apic_id, (AP_TRAMPOLINE_PADDR / PAGE_SIZE) as u8);
+            busy_wait_us(200);
+            if is_cpu_online(cpu_id) {
+                break;
+            }
+        }
+
+        // Wait for the AP to be initialized.
+        for _ in 0..1000 {
+            if is_cpu_online(cpu_id) {
+                break;
+            }
+
+            busy_wait_us(100);
+        }
+
+        if is_cpu_online(cpu_id) {
+            trace!("smp: started CPU #{} (APIC ID {})", cpu_id, apic_id);
+        } else {
+            warn!("smp: failed to start CPU #{} (APIC ID {})", cpu_id, apic_id);
+            all_started = false;
+        }
+    }
+
+    // The AP which has failed to start might start later.
+    if all_started {
+        CPUS_STATE.store(CPUS_STARTED, Ordering::Release);
+    }
+
+    info!(
+        "smp: {} CPU(s) online",
+        ONLINE_CPUS.load(Ordering::SeqCst).count_ones()
+    );
+}
+
+/// Returns the CPU-local area for the AP being started.
+pub(super) fn ap_cpu_local_area() -> VAddr {
+    VAddr::new(AP_CPU_LOCAL.load(Ordering::SeqCst))
+}
+
+/// Marks the current AP as online. Called once the AP has been initialized.
+pub(super) fn ap_online() {
+    let cpu_id = AP_CPU_ID.load(Ordering::SeqCst);
+    CPU_ID.set(cpu_id);
+    ONLINE_CPUS.fetch_or(1 << cpu_id, Ordering::SeqCst);
+}
@@ -4,8 +4,9 @@ use super::gdt::{KERNEL_CS, USER_CS32};
 use x86::msr::{self, rdmsr, wrmsr};
 
 // Clear IF bit to disable interrupts when we enter the syscall handler
-// or an interrupt occurs before doing SWAPGS.
-const SYSCALL_RFLAGS_MASK: u64 = 0x200;
+// or an interrupt occurs before doing SWAPGS. TF is also cleared not to
+// single-step the kernel.
+const SYSCALL_RFLAGS_MASK: u64 = 0x300;
 
 #[repr(C, packed)]
 #[derive(Clone, Copy)]
@@ -0,0 +1,52 @@
+//! The clocksource based on Time Stamp Counter (TSC).
+use super::pit::busy_wait_us;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86::{cpuid::CpuId, time::rdtsc};
+
+/// The period to measure the TSC frequency.
+const CALIBRATION_US: u64 = 10_000;
+
+/// The TSC frequency in Hz.
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+/// The TSC value at boot.
+static TSC_AT_BOOT: AtomicU64 = AtomicU64::new(0);
+
+/// Measures the TSC frequency using PIT. Called in the BSP.
+///
+/// We assume that TSC is synchronized among CPUs. It must be invariant, i.e.
+/// it runs at a constant rate regardless of the CPU frequency and C-states.
+pub unsafe fn init() {
+    let invariant = CpuId::new()
+        .get_advanced_power_mgmt_info()
+        .map_or(false, |info| info.has_invariant_tsc());
+    if !invariant {
+        warn!("tsc: TSC is not invariant, the clock may drift");
+    }
+
+    let start = rdtsc();
+    busy_wait_us(CALIBRATION_US);
+    let end = rdtsc();
+
+    let hz = (end - start) * 1_000_000 / CALIBRATION_US;
+    TSC_HZ.store(hz, Ordering::SeqCst);
+    TSC_AT_BOOT.store(start, Ordering::SeqCst);
+    info!("tsc: {}.{:03} MHz", hz / 1_000_000, (hz / 1_000) % 1_000);
+}
+
+/// Returns the TSC value at `nanosecs` since boot.
+pub fn nanosecs_to_tsc(nanosecs: u64) -> u64 {
+    let hz = TSC_HZ.load(Ordering::Relaxed) as u128;
+    TSC_AT_BOOT.load(Ordering::Relaxed) + (nanosecs as u128 * hz / 1_000_000_000) as u64
+}
+
+/// Returns the elapsed time since boot in nanoseconds.
+pub fn nanosecs_since_boot() -> u64 {
+    let hz = TSC_HZ.load(Ordering::Relaxed) as u128;
+    if hz == 0 {
+        // Not yet calibrated.
+        return 0;
+    }
+
+    let elapsed = unsafe { rdtsc() }.saturating_sub(TSC_AT_BOOT.load(Ordering::Relaxed));
+    (elapsed as u128 * 1_000_000_000 / hz) as u64
+}
@@ -0,0 +1,198 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+
+#include "test_util.h"
+
+#define DIR_PATH "/tmp/at_syscalls"
+
+#ifndef SYS_faccessat2
+#define SYS_faccessat2 439
+#endif
+
+// Old musl doesn't have statx(2).
+struct statx_timestamp_
+{
+    int64_t tv_sec;
+    uint32_t tv_nsec;
+    int32_t reserved;
+};
+
+struct statx_
+{
+    uint32_t stx_mask;
+    uint32_t stx_blksize;
+    uint64_t stx_attributes;
+    uint32_t stx_nlink;
+    uint32_t stx_uid;
+    uint32_t stx_gid;
+    uint16_t stx_mode;
+    uint16_t spare0;
+    uint64_t stx_ino;
+    uint64_t stx_size;
+    uint64_t stx_blocks;
+    uint64_t stx_attributes_mask;
+    struct statx_timestamp_ stx_atime, stx_btime, stx_ctime, stx_mtime;
+    uint32_t stx_rdev_major, stx_rdev_minor, stx_dev_major, stx_dev_minor;
+    uint64_t spare[14];
+};
+
+static void test_open_and_stat(int dirfd)
+{
+    int fd = openat(dirfd, "file", O_RDWR | O_CREAT, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5)
+    {
+        fail("openat failed");
+    }
+
+    struct stat st;
+    if (fstatat(dirfd, "file", &st, 0) < 0 || st.st_size != 5 || !S_ISREG(st.st_mode))
+    {
+        fail("fstatat failed");
+    }
+
+    if (fstatat(fd, "", &st, AT_EMPTY_PATH) < 0 || st.st_size != 5)
+    {
+        fail("fstatat with AT_EMPTY_PATH failed");
+    }
+
+    if (fstatat(dirfd, "file", &st, AT_REMOVEDIR) != -1 || errno != EINVAL)
+    {
+        fail("fstatat with an invalid flag does not return EINVAL");
+    }
+
+    if (openat(fd, "file", O_RDONLY) != -1 || errno != ENOTDIR)
+    {
+        fail("openat relative to a file does not return ENOTDIR");
+    }
+
+    if (symlinkat("file", dirfd, "link") < 0)
+    {
+        fail("symlinkat failed");
+    }
+
+    if (fstatat(dirfd, "link", &st, AT_SYMLINK_NOFOLLOW) < 0 || !S_ISLNK(st.st_mode))
+    {
+        fail("fstatat with AT_SYMLINK_NOFOLLOW followed the symlink");
+    }
+
+    if (openat(dirfd, "link", O_RDONLY | O_NOFOLLOW) != -1 || errno != ELOOP)
+    {
+        fail("O_NOFOLLOW does not return ELOOP");
+    }
+
+    char buf[16];
+    if (readlinkat(dirfd, "link", buf, sizeof(buf)) != 4 || memcmp(buf, "file", 4))
+    {
+        fail("readlinkat failed");
+    }
+
+    if (readlink("/proc/self/fd/foo", buf, sizeof(buf)) != -1 || errno != ENOENT)
+    {
+        fail("readlink of an invalid fd link does not return ENOENT");
+    }
+
+    struct statx_ stx;
+    if (syscall(SYS_statx, dirfd, "file", 0, 0x7ff, &stx) < 0 || stx.stx_size != 5 ||
+        !S_ISREG(stx.stx_mode))
+    {
+        fail("statx failed");
+    }
+
+    if (fstat(fd, &st) < 0 || stx.stx_ino != st.st_ino || stx.stx_nlink != 1)
+    {
+        fail("statx returned a wrong inode");
+    }
+
+    close(fd);
+}
+
+static void test_access_and_chmod(int dirfd)
+{
+    if (faccessat(dirfd, "file", R_OK | W_OK, 0) < 0 ||
+        syscall(SYS_faccessat2, dirfd, "file", R_OK, AT_EACCESS) < 0)
+    {
+        fail("faccessat failed");
+    }
+
+    if (syscall(SYS_faccessat2, dirfd, "", F_OK, AT_EMPTY_PATH) < 0)
+    {
+        fail("faccessat2 with AT_EMPTY_PATH failed");
+    }
+
+    if (fchmodat(dirfd, "file", 0600, 0) < 0)
+    {
+        fail("fchmodat failed");
+    }
+
+    struct timespec times[2] = {{0, UTIME_NOW}, {0, UTIME_OMIT}};
+    if (utimensat(dirfd, "file", times, 0) < 0)
+    {
+        fail("utimensat failed");
+    }
+
+    times[0].tv_nsec = 1000000000;
+    if (utimensat(dirfd, "file", times, 0) != -1 || errno != EINVAL)
+    {
+        fail("utimensat with an invalid timespec does not return EINVAL");
+    }
+}
+
+static void test_mkdir_and_unlink(int dirfd)
+{
+    if (mkdirat(dirfd, "subdir", 0755) < 0)
+    {
+        fail("mkdirat failed");
+    }
+
+    if (unlinkat(dirfd, "subdir", 0) != -1 || errno != EISDIR)
+    {
+        fail("unlinkat on a directory does not return EISDIR");
+    }
+
+    if (unlinkat(dirfd, "file", AT_REMOVEDIR) != -1 || errno != ENOTDIR)
+    {
+        fail("unlinkat with AT_REMOVEDIR on a file does not return ENOTDIR");
+    }
+
+    if (unlinkat(dirfd, "subdir", AT_REMOVEDIR) < 0 || unlinkat(dirfd, "link", 0) < 0 ||
+        unlinkat(dirfd, "file", 0) < 0)
+    {
+        fail("unlinkat failed");
+    }
+
+    if (faccessat(dirfd, "file", F_OK, 0) != -1 || errno != ENOENT)
+    {
+        fail("unlinkat did not remove the file");
+    }
+}
+
+int main(void)
+{
+    if (mkdir(DIR_PATH, 0755) < 0)
+    {
+        fail("failed to create a directory");
+    }
+
+    int dirfd = open(DIR_PATH, O_RDONLY | O_DIRECTORY);
+    if (dirfd < 0)
+    {
+        fail("failed to open the directory");
+    }
+
+    test_open_and_stat(dirfd);
+    test_access_and_chmod(dirfd);
+    test_mkdir_and_unlink(dirfd);
+    close(dirfd);
+    rmdir(DIR_PATH);
+
+    printf("passed\n");
+    return 0;
+}
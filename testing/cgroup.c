@@ -0,0 +1,213 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define CGROUP_DIR "/tmp/cgroup/test"
+
+// Writes `value` into the interface file. Returns -1 and sets errno on error.
+static int write_file(const char *name, const char *value)
+{
+    char path[128];
+    snprintf(path, sizeof(path), "%s/%s", CGROUP_DIR, name);
+    int fd = open(path, O_WRONLY);
+    if (fd < 0)
+    {
+        return -1;
+    }
+
+    int ret = write(fd, value, strlen(value));
+    close(fd);
+    return ret < 0 ? -1 : 0;
+}
+
+static int file_contains(const char *name, const char *expected)
+{
+    char path[128];
+    char buf[256];
+    snprintf(path, sizeof(path), "%s/%s", CGROUP_DIR, name);
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+    {
+        return 0;
+    }
+
+    int len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (len < 0)
+    {
+        return 0;
+    }
+
+    buf[len] = '\0';
+    return strstr(buf, expected) != NULL;
+}
+
+static int join_cgroup(void)
+{
+    return write_file("cgroup.procs", "0");
+}
+
+static int test_pids_limit(void)
+{
+    char pid[16];
+    snprintf(pid, sizeof(pid), "%d\n", getpid());
+    if (join_cgroup() < 0 || !file_contains("cgroup.procs", pid))
+    {
+        return 1;
+    }
+
+    // The limit (1) is already reached by the process itself.
+    if (fork() >= 0 || errno != EAGAIN)
+    {
+        return 1;
+    }
+
+    return 0;
+}
+
+static void test_memory_limit(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        join_cgroup();
+        size_t len = 8 * 1024 * 1024;
+        volatile char *p = malloc(len);
+        for (size_t i = 0; i < len; i += 4096)
+        {
+            p[i] = 1;
+        }
+
+        // Should be killed before reaching here.
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL)
+    {
+        fail("the process exceeding memory.max is not killed");
+    }
+
+    if (!file_contains("memory.events", "oom_kill 1"))
+    {
+        fail("memory.events does not count the OOM kill");
+    }
+}
+
+// Pages in the page cache are charged to the group which has filled them.
+static void test_page_cache_limit(void)
+{
+    size_t len = 8 * 1024 * 1024;
+    int fd = open("/tmp/cgroup_mapped", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || ftruncate(fd, len) < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        join_cgroup();
+        volatile char *p = mmap(NULL, len, PROT_READ, MAP_SHARED, fd, 0);
+        if (p == MAP_FAILED)
+        {
+            exit(1);
+        }
+
+        for (size_t i = 0; i < len; i += 4096)
+        {
+            (void)p[i];
+        }
+
+        // Should be killed before reaching here.
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL)
+    {
+        fail("page cache pages are not charged");
+    }
+
+    if (!file_contains("memory.events", "oom_kill 2"))
+    {
+        fail("memory.events does not count the OOM kill");
+    }
+
+    close(fd);
+    unlink("/tmp/cgroup_mapped");
+}
+
+int main(int argc, char **argv)
+{
+    mkdir("/tmp/cgroup", 0755);
+    if (mount("none", "/tmp/cgroup", "cgroup2", 0, NULL) < 0)
+    {
+        fail("failed to mount cgroup2");
+    }
+
+    if (mkdir(CGROUP_DIR, 0755) < 0)
+    {
+        fail("failed to create a cgroup");
+    }
+
+    if (!file_contains("cgroup.controllers", "memory") || !file_contains("memory.max", "max"))
+    {
+        fail("unexpected cgroup interface files");
+    }
+
+    if (write_file("pids.max", "1") < 0 || !file_contains("pids.max", "1"))
+    {
+        fail("failed to set pids.max");
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        exit(test_pids_limit());
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail("fork(2) is not limited by pids.max");
+    }
+
+    if (!file_contains("pids.current", "0"))
+    {
+        fail("pids.current is not decremented");
+    }
+
+    write_file("pids.max", "max");
+    if (write_file("memory.max", "1M") < 0 || !file_contains("memory.max", "1048576"))
+    {
+        fail("failed to set memory.max");
+    }
+
+    test_memory_limit();
+    test_page_cache_limit();
+
+    if (write_file("cpu.weight", "200") < 0 || !file_contains("cpu.weight", "200"))
+    {
+        fail("failed to set cpu.weight");
+    }
+
+    if (write_file("cpu.weight", "0") == 0 || errno != ERANGE)
+    {
+        fail("cpu.weight accepts an out-of-range value");
+    }
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,277 @@
+// Checks threads and the flags of clone(2).
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <pthread.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+static long raw_clone(unsigned long flags, pid_t *parent_tid, pid_t *child_tid)
+{
+    // x86_64: clone(flags, stack, parent_tid, child_tid, tls)
+    return syscall(SYS_clone, flags, NULL, parent_tid, child_tid, NULL);
+}
+
+static __thread int tls_value = 1;
+static int shared_value = 0;
+
+static void *thread_main(void *arg)
+{
+    // Each thread has its own TLS and thread ID in the same thread group.
+    tls_value = 2;
+    shared_value = 1;
+    if (syscall(SYS_gettid) == getpid())
+    {
+        return (void *)1;
+    }
+
+    return NULL;
+}
+
+static void test_threads(void)
+{
+    pthread_t thread;
+    void *ret;
+    if (pthread_create(&thread, NULL, thread_main, NULL) != 0)
+    {
+        fail("pthread_create failed");
+    }
+
+    // pthread_join waits for CLONE_CHILD_CLEARTID.
+    if (pthread_join(thread, &ret) != 0 || ret != NULL)
+    {
+        fail("the thread has the same thread ID as the process");
+    }
+
+    if (shared_value != 1)
+    {
+        fail("the thread does not share the memory space");
+    }
+
+    if (tls_value != 1)
+    {
+        fail("the thread does not have its own TLS");
+    }
+}
+
+// CLONE_CHILD_SETTID writes the thread ID into the child's memory space.
+static void test_child_settid(void)
+{
+    static pid_t parent_tid = 0;
+    static pid_t child_tid = 0;
+    long pid = raw_clone(SIGCHLD | CLONE_PARENT_SETTID | CLONE_CHILD_SETTID, &parent_tid,
+                         &child_tid);
+    if (pid < 0)
+    {
+        fail("clone failed");
+    }
+
+    if (pid == 0)
+    {
+        exit(child_tid == getpid() ? 0 : 1);
+    }
+
+    if (parent_tid != pid)
+    {
+        fail("CLONE_PARENT_SETTID did not write the thread ID");
+    }
+
+    if (child_tid != 0)
+    {
+        fail("CLONE_CHILD_SETTID wrote into the parent's memory space");
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail("CLONE_CHILD_SETTID did not write into the child's memory space");
+    }
+}
+
+// A child is not left behind if the thread ID can't be written.
+static void test_settid_fault(void)
+{
+    long pid = raw_clone(SIGCHLD | CLONE_PARENT_SETTID, (pid_t *)1, NULL);
+    if (pid == 0)
+    {
+        exit(0);
+    }
+
+    if (pid < 0 && errno != EFAULT)
+    {
+        fail("CLONE_PARENT_SETTID with an invalid address does not return EFAULT");
+    }
+
+    if (pid > 0 && waitpid(pid, NULL, 0) != pid)
+    {
+        fail("waitpid failed");
+    }
+
+    if (waitpid(-1, NULL, WNOHANG) != -1 || errno != ECHILD)
+    {
+        fail("a child is left behind by the failed clone");
+    }
+}
+
+static volatile sig_atomic_t exit_signal_received = 0;
+
+static void exit_signal_handler(int signo)
+{
+    exit_signal_received = 1;
+}
+
+// The signal in the lowest byte of the flags is sent on the child's exit.
+static void test_exit_signal(void)
+{
+    signal(SIGUSR1, exit_signal_handler);
+    long pid = raw_clone(SIGUSR1, NULL, NULL);
+    if (pid == 0)
+    {
+        _exit(0);
+    }
+
+    int status;
+    if (pid < 0 || waitpid(pid, &status, __WALL) != pid || !WIFEXITED(status))
+    {
+        fail("failed to wait for the child with an exit signal");
+    }
+
+    if (!exit_signal_received)
+    {
+        fail("the exit signal is not sent to the parent");
+    }
+
+    signal(SIGUSR1, SIG_DFL);
+}
+
+static volatile int vfork_value = 0;
+
+// The parent waits for the child sharing its memory space to exit.
+static void test_vfork(void)
+{
+    pid_t pid = vfork();
+    if (pid == 0)
+    {
+        vfork_value = 1;
+        _exit(0);
+    }
+
+    if (pid < 0 || vfork_value != 1)
+    {
+        fail("vfork(2) does not wait for the child in the same memory space");
+    }
+
+    if (waitpid(pid, NULL, 0) != pid)
+    {
+        fail("waitpid failed");
+    }
+}
+
+// CLONE_PARENT creates a sibling of the calling process.
+static void test_clone_parent(void)
+{
+    pid_t parent = getpid();
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        long sibling = raw_clone(SIGCHLD | CLONE_PARENT, NULL, NULL);
+        if (sibling == 0)
+        {
+            _exit(getppid() == parent ? 0 : 1);
+        }
+
+        _exit(sibling > 0 ? 0 : 1);
+    }
+
+    // Both the child and its sibling are waited for by this process.
+    for (int i = 0; i < 2; i++)
+    {
+        int status;
+        if (wait(&status) < 0 || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        {
+            fail("CLONE_PARENT does not create a sibling");
+        }
+    }
+}
+
+static const char *exec_path;
+
+static void *exec_thread_main(void *arg)
+{
+    execl(exec_path, exec_path, "exec", NULL);
+    return NULL;
+}
+
+// The new executable is the only thread with the process ID as its thread ID.
+static int report_exec(void)
+{
+    return syscall(SYS_gettid) == getpid() ? 0 : 1;
+}
+
+// execve(2) from a thread terminates other threads.
+static void test_exec_from_thread(const char *self)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        pthread_t thread;
+        exec_path = self;
+        if (pthread_create(&thread, NULL, exec_thread_main, NULL) != 0)
+        {
+            _exit(2);
+        }
+
+        // The thread group leader is killed by execve(2).
+        for (;;)
+        {
+            pause();
+        }
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail("execve(2) from a thread does not take over the process");
+    }
+}
+
+static void test_invalid_flags(void)
+{
+    if (raw_clone(CLONE_VM | CLONE_THREAD, NULL, NULL) != -1 || errno != EINVAL)
+    {
+        fail("CLONE_THREAD without CLONE_SIGHAND does not return EINVAL");
+    }
+
+    if (raw_clone(CLONE_SIGHAND, NULL, NULL) != -1 || errno != EINVAL)
+    {
+        fail("CLONE_SIGHAND without CLONE_VM does not return EINVAL");
+    }
+}
+
+int main(int argc, char **argv)
+{
+    if (argc > 1 && !strcmp(argv[1], "exec"))
+    {
+        return report_exec();
+    }
+
+    test_threads();
+    test_child_settid();
+    test_settid_fault();
+    test_exit_signal();
+    test_vfork();
+    test_clone_parent();
+    test_exec_from_thread(argv[0]);
+    test_invalid_flags();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,89 @@
+// Checks the CPU time reported by getrusage(2) and the CPU-time clocks.
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/resource.h>
+#include <sys/time.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+static long long clock_nanosecs(clockid_t clock)
+{
+    struct timespec ts;
+    if (clock_gettime(clock, &ts) < 0)
+    {
+        fail("clock_gettime failed");
+    }
+
+    return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+static long long timeval_microsecs(struct timeval tv)
+{
+    return tv.tv_sec * 1000000LL + tv.tv_usec;
+}
+
+// Spins in the userspace for `nanosecs` of the thread's CPU time.
+static void busy_loop(long long nanosecs)
+{
+    long long start = clock_nanosecs(CLOCK_THREAD_CPUTIME_ID);
+    volatile unsigned long counter = 0;
+    while (clock_nanosecs(CLOCK_THREAD_CPUTIME_ID) - start < nanosecs)
+    {
+        for (int i = 0; i < 100000; i++)
+        {
+            counter++;
+        }
+    }
+}
+
+int main(void)
+{
+    long long process_before = clock_nanosecs(CLOCK_PROCESS_CPUTIME_ID);
+    busy_loop(100000000);
+    long long thread_after = clock_nanosecs(CLOCK_THREAD_CPUTIME_ID);
+    long long process_after = clock_nanosecs(CLOCK_PROCESS_CPUTIME_ID);
+    if (process_after - process_before < 100000000)
+    {
+        fail("CLOCK_PROCESS_CPUTIME_ID does not count the busy loop");
+    }
+
+    if (thread_after > process_after)
+    {
+        fail("the thread used more CPU time than its process");
+    }
+
+    // The busy loop runs in the userspace.
+    struct rusage usage;
+    if (getrusage(RUSAGE_SELF, &usage) < 0)
+    {
+        fail("getrusage failed");
+    }
+
+    long long utime = timeval_microsecs(usage.ru_utime);
+    long long stime = timeval_microsecs(usage.ru_stime);
+    if (utime < 50000 || utime < stime)
+    {
+        fail("the busy loop is not charged as the user time");
+    }
+
+    // CPU times of waited children are reported in RUSAGE_CHILDREN.
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        busy_loop(50000000);
+        exit(0);
+    }
+
+    waitpid(pid, NULL, 0);
+    if (getrusage(RUSAGE_CHILDREN, &usage) < 0 || timeval_microsecs(usage.ru_utime) == 0)
+    {
+        fail("RUSAGE_CHILDREN does not include the child");
+    }
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,246 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <sys/time.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define PATH "/tmp/file_metadata"
+#define DIR_PATH "/tmp/file_metadata_dir"
+
+static struct stat stat_path(const char *path)
+{
+    struct stat st;
+    if (stat(path, &st) < 0)
+    {
+        fail("stat failed");
+    }
+
+    return st;
+}
+
+static void test_create(void)
+{
+    time_t now = time(NULL);
+    int fd = open(PATH, O_RDWR | O_CREAT | O_EXCL, 0640);
+    if (fd < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    struct stat st = stat_path(PATH);
+    if ((st.st_mode & 07777) != 0640 || !S_ISREG(st.st_mode) || st.st_uid != getuid())
+    {
+        fail("the file is created with a wrong mode or owner");
+    }
+
+    if (st.st_mtime < now - 1 || st.st_ctime < now - 1 || st.st_atime < now - 1)
+    {
+        fail("the timestamps are not set on creation");
+    }
+
+    if (mkdir(DIR_PATH, 0711) < 0 || (stat_path(DIR_PATH).st_mode & 07777) != 0711)
+    {
+        fail("the directory is created with a wrong mode");
+    }
+
+    close(fd);
+}
+
+static void test_chmod(void)
+{
+    if (chmod(PATH, 0604) < 0 || (stat_path(PATH).st_mode & 07777) != 0604)
+    {
+        fail("chmod did not change the mode");
+    }
+
+    int fd = open(PATH, O_RDONLY);
+    if (fchmod(fd, 0600) < 0 || (stat_path(PATH).st_mode & 07777) != 0600 ||
+        !S_ISREG(stat_path(PATH).st_mode))
+    {
+        fail("fchmod did not change the mode");
+    }
+
+    close(fd);
+
+    if (chmod(DIR_PATH, 0755) < 0 || !S_ISDIR(stat_path(DIR_PATH).st_mode))
+    {
+        fail("chmod on a directory failed");
+    }
+}
+
+static void test_utimes(void)
+{
+    struct timespec times[2] = {{1000, 500}, {2000, 0}};
+    if (utimensat(AT_FDCWD, PATH, times, 0) < 0)
+    {
+        fail("utimensat failed");
+    }
+
+    struct stat st = stat_path(PATH);
+    if (st.st_atim.tv_sec != 1000 || st.st_atim.tv_nsec != 500 || st.st_mtime != 2000)
+    {
+        fail("utimensat did not set the timestamps");
+    }
+
+    times[0].tv_nsec = UTIME_OMIT;
+    times[1].tv_nsec = UTIME_NOW;
+    time_t now = time(NULL);
+    int fd = open(PATH, O_RDONLY);
+    if (futimens(fd, times) < 0)
+    {
+        fail("futimens failed");
+    }
+
+    close(fd);
+    st = stat_path(PATH);
+    if (st.st_atime != 1000 || st.st_mtime < now - 1)
+    {
+        fail("UTIME_OMIT or UTIME_NOW is not respected");
+    }
+
+    struct timeval tv[2] = {{3000, 0}, {4000, 0}};
+    if (utimes(PATH, tv) < 0 || stat_path(PATH).st_atime != 3000 ||
+        stat_path(PATH).st_mtime != 4000)
+    {
+        fail("utimes did not set the timestamps");
+    }
+}
+
+// The access time is updated on a read only if it's older than the
+// modification time or the status change time (relatime).
+static void test_relatime(void)
+{
+    char buf[1];
+    time_t now = time(NULL);
+    struct timeval tv[2] = {{1000, 0}, {2000, 0}};
+    utimes(PATH, tv);
+    int fd = open(PATH, O_RDONLY);
+    read(fd, buf, sizeof(buf));
+    close(fd);
+    if (stat_path(PATH).st_atime < now - 1)
+    {
+        fail("read did not update the old access time");
+    }
+
+    struct timeval future[2] = {{now + 1000, 0}, {now - 1000, 0}};
+    utimes(PATH, future);
+    fd = open(PATH, O_RDONLY);
+    read(fd, buf, sizeof(buf));
+    close(fd);
+    if (stat_path(PATH).st_atime != now + 1000)
+    {
+        fail("read updated the recent access time");
+    }
+}
+
+// Files in initramfs (the root file system) can't be modified.
+static void test_read_only(void)
+{
+    struct stat st = stat_path("/bin");
+    if (chmod("/bin", st.st_mode & 07777) != -1 || errno != EROFS)
+    {
+        fail("chmod on initramfs does not return EROFS");
+    }
+
+    if (utimensat(AT_FDCWD, "/bin", NULL, 0) != -1 || errno != EROFS)
+    {
+        fail("utimensat on initramfs does not return EROFS");
+    }
+}
+
+static void test_modify(void)
+{
+    int fd = open(PATH, O_WRONLY);
+    if (write(fd, "hello", 5) != 5)
+    {
+        fail("write failed");
+    }
+
+    close(fd);
+    if (stat_path(PATH).st_mtime == 4000)
+    {
+        fail("write did not update the modification time");
+    }
+
+    struct timespec times[2] = {{0, UTIME_OMIT}, {5000, 0}};
+    utimensat(AT_FDCWD, DIR_PATH, times, 0);
+    if (rmdir(DIR_PATH "/nonexistent") == 0 || stat_path(DIR_PATH).st_mtime != 5000)
+    {
+        fail("a failed rmdir changed the directory");
+    }
+
+    fd = open(DIR_PATH "/file", O_WRONLY | O_CREAT, 0644);
+    close(fd);
+    if (stat_path(DIR_PATH).st_mtime == 5000)
+    {
+        fail("creating a file did not update the directory");
+    }
+
+    unlink(DIR_PATH "/file");
+}
+
+static void test_permissions(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (setuid(1000) < 0)
+        {
+            exit(1);
+        }
+
+        if (chmod(PATH, 0777) != -1 || errno != EPERM)
+        {
+            printf("chmod by a non-owner does not return EPERM\n");
+            exit(1);
+        }
+
+        struct timespec times[2] = {{1, 0}, {1, 0}};
+        if (utimensat(AT_FDCWD, PATH, times, 0) != -1 || errno != EPERM)
+        {
+            printf("utimensat by a non-owner does not return EPERM\n");
+            exit(1);
+        }
+
+        if (utimensat(AT_FDCWD, PATH, NULL, 0) != -1 || errno != EACCES)
+        {
+            printf("utimensat without the write permission does not return EACCES\n");
+            exit(1);
+        }
+
+        exit(0);
+    }
+
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        exit(1);
+    }
+}
+
+int main(void)
+{
+    test_create();
+    test_chmod();
+    test_utimes();
+    test_relatime();
+    test_read_only();
+    test_modify();
+    if (getuid() == 0)
+    {
+        test_permissions();
+    }
+
+    unlink(PATH);
+    rmdir(DIR_PATH);
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,234 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define PATH "/tmp/file_offsets"
+#define APPEND_PATH "/tmp/file_offsets_append"
+#define NUM_WRITERS 4
+#define NUM_RECORDS 200
+#define RECORD_LEN 8
+
+static void test_lseek(int fd)
+{
+    if (write(fd, "0123456789", 10) != 10)
+    {
+        fail("write failed");
+    }
+
+    if (lseek(fd, 0, SEEK_CUR) != 10 || lseek(fd, 2, SEEK_SET) != 2 ||
+        lseek(fd, 3, SEEK_CUR) != 5 || lseek(fd, -1, SEEK_END) != 9)
+    {
+        fail("SEEK_SET/CUR/END returned a wrong offset");
+    }
+
+    char buf[4];
+    if (read(fd, buf, sizeof(buf)) != 1 || buf[0] != '9')
+    {
+        fail("read after lseek returned wrong data");
+    }
+
+    if (lseek(fd, -1, SEEK_SET) != -1 || errno != EINVAL)
+    {
+        fail("lseek to a negative offset does not return EINVAL");
+    }
+
+    if (lseek(fd, 3, SEEK_DATA) != 3 || lseek(fd, 3, SEEK_HOLE) != 10)
+    {
+        fail("SEEK_DATA/SEEK_HOLE returned a wrong offset");
+    }
+
+    if (lseek(fd, 10, SEEK_DATA) != -1 || errno != ENXIO)
+    {
+        fail("SEEK_DATA beyond EOF does not return ENXIO");
+    }
+}
+
+static void test_positional_io(int fd)
+{
+    off_t pos = lseek(fd, 4, SEEK_SET);
+    char buf[4];
+    if (pread(fd, buf, 3, 1) != 3 || memcmp(buf, "123", 3))
+    {
+        fail("pread returned wrong data");
+    }
+
+    if (pwrite(fd, "ab", 2, 7) != 2)
+    {
+        fail("pwrite failed");
+    }
+
+    if (lseek(fd, 0, SEEK_CUR) != pos)
+    {
+        fail("pread/pwrite moved the file offset");
+    }
+
+    char a[3], b[5];
+    struct iovec iov[2] = {{a, sizeof(a)}, {b, sizeof(b)}};
+    if (preadv(fd, iov, 2, 4) != 6 || memcmp(a, "456", 3) || memcmp(b, "ab9", 3))
+    {
+        fail("preadv returned wrong data");
+    }
+
+    struct iovec wiov[2] = {{"xy", 2}, {"z", 1}};
+    if (pwritev(fd, wiov, 2, 0) != 3 || lseek(fd, 0, SEEK_CUR) != pos)
+    {
+        fail("pwritev failed");
+    }
+
+    lseek(fd, 0, SEEK_SET);
+    if (readv(fd, iov, 2) != 8 || memcmp(a, "xyz", 3) || memcmp(b, "3456a", 5))
+    {
+        fail("readv returned wrong data");
+    }
+
+    if (lseek(fd, 0, SEEK_CUR) != 8)
+    {
+        fail("readv did not advance the file offset");
+    }
+}
+
+static void test_append(void)
+{
+    int fd = open(PATH, O_RDWR | O_APPEND);
+    if (fd < 0)
+    {
+        fail("failed to open the file with O_APPEND");
+    }
+
+    lseek(fd, 0, SEEK_SET);
+    if (write(fd, "!", 1) != 1 || lseek(fd, 0, SEEK_CUR) != 11)
+    {
+        fail("O_APPEND write did not append to the end of the file");
+    }
+
+    char c;
+    if (pread(fd, &c, 1, 10) != 1 || c != '!')
+    {
+        fail("O_APPEND write wrote to a wrong offset");
+    }
+
+    close(fd);
+}
+
+// Appends from multiple processes never overwrite each other.
+static void test_concurrent_append(void)
+{
+    int fd = open(APPEND_PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    pid_t pids[NUM_WRITERS];
+    for (int i = 0; i < NUM_WRITERS; i++)
+    {
+        pids[i] = fork();
+        if (pids[i] == 0)
+        {
+            int child_fd = open(APPEND_PATH, O_WRONLY | O_APPEND);
+            char record[RECORD_LEN];
+            memset(record, 'a' + i, RECORD_LEN);
+            for (int j = 0; j < NUM_RECORDS; j++)
+            {
+                if (write(child_fd, record, RECORD_LEN) != RECORD_LEN)
+                {
+                    exit(1);
+                }
+            }
+
+            exit(0);
+        }
+    }
+
+    for (int i = 0; i < NUM_WRITERS; i++)
+    {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0)
+        {
+            fail("an O_APPEND writer failed");
+        }
+    }
+
+    if (lseek(fd, 0, SEEK_END) != NUM_WRITERS * NUM_RECORDS * RECORD_LEN)
+    {
+        fail("concurrent O_APPEND writes overwrote each other");
+    }
+
+    char record[RECORD_LEN];
+    for (int i = 0; i < NUM_WRITERS * NUM_RECORDS; i++)
+    {
+        if (pread(fd, record, RECORD_LEN, i * RECORD_LEN) != RECORD_LEN ||
+            memcmp(record, record + 1, RECORD_LEN - 1))
+        {
+            fail("concurrent O_APPEND writes are interleaved");
+        }
+    }
+
+    close(fd);
+    unlink(APPEND_PATH);
+}
+
+static void test_pipe(void)
+{
+    int fds[2];
+    if (pipe(fds) < 0)
+    {
+        fail("pipe failed");
+    }
+
+    char c;
+    if (lseek(fds[0], 0, SEEK_SET) != -1 || errno != ESPIPE ||
+        pread(fds[0], &c, 1, 0) != -1 || errno != ESPIPE ||
+        pwrite(fds[1], "a", 1, 0) != -1 || errno != ESPIPE)
+    {
+        fail("seeking on a pipe does not return ESPIPE");
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+}
+
+static void test_tty(void)
+{
+    int fd = open("/dev/console", O_WRONLY);
+    if (fd < 0)
+    {
+        fail("failed to open the console");
+    }
+
+    if (lseek(fd, 0, SEEK_SET) != -1 || errno != ESPIPE)
+    {
+        fail("seeking on a tty does not return ESPIPE");
+    }
+
+    close(fd);
+}
+
+int main(void)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT, 0644);
+    if (fd < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    test_lseek(fd);
+    test_positional_io(fd);
+    close(fd);
+    test_append();
+    test_concurrent_append();
+    test_pipe();
+    test_tty();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,121 @@
+// Checks if memory pages are shared between parent and child after fork(2)
+// in copy-on-write manner: writes in one process must not be visible from
+// the other one, except in MAP_SHARED mappings.
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define SHARED_FILE_PATH "/tmp/fork_copy_on_write"
+
+static volatile int value = 1;
+
+static void wait_success(pid_t pid, const char *msg)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+// The child keeps the old value after the parent writes to the page.
+static void test_parent_write(void)
+{
+    int fds[2];
+    pipe(fds);
+    value = 1;
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        char c;
+        read(fds[0], &c, 1);
+        exit(value == 1 ? 0 : 1);
+    }
+
+    value = 2;
+    write(fds[1], "x", 1);
+    wait_success(pid, "the child sees the parent's write");
+    if (value != 2)
+    {
+        fail("the parent lost its write");
+    }
+}
+
+// The parent keeps the old contents after the child writes to the page.
+static void test_child_write(void)
+{
+    size_t len = 64 * 1024;
+    char *buf = malloc(len);
+    memset(buf, 'a', len);
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        memset(buf, 'b', len);
+        exit(buf[0] == 'b' && buf[len - 1] == 'b' ? 0 : 1);
+    }
+
+    wait_success(pid, "the child failed to write to the copied page");
+    for (size_t i = 0; i < len; i++)
+    {
+        if (buf[i] != 'a')
+        {
+            fail("the parent sees the child's write");
+        }
+    }
+
+    // The page is no longer shared: the parent can write to it.
+    memset(buf, 'c', len);
+    free(buf);
+}
+
+// Writes to a MAP_SHARED mapping are visible from both processes.
+static void test_shared_mapping(void)
+{
+    long page_size = sysconf(_SC_PAGESIZE);
+    int fd = open(SHARED_FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || ftruncate(fd, page_size) < 0)
+    {
+        fail("failed to create the file");
+    }
+
+    char *p = mmap(NULL, page_size, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    strcpy(p, "parent");
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        strcpy(p, "child");
+        exit(0);
+    }
+
+    wait_success(pid, "the child failed to write to the shared page");
+    if (strcmp(p, "child") != 0)
+    {
+        fail("the parent does not see the child's write to the shared page");
+    }
+
+    munmap(p, page_size);
+    close(fd);
+    unlink(SHARED_FILE_PATH);
+}
+
+int main(void)
+{
+    test_parent_write();
+    test_child_write();
+    test_shared_mapping();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,185 @@
+// Checks futex(2) and robust futex lists.
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <linux/futex.h>
+#include <pthread.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+
+#include "test_util.h"
+
+static long futex(volatile uint32_t *uaddr, int op, uint32_t val, const struct timespec *timeout,
+                  volatile uint32_t *uaddr2, uint32_t val3)
+{
+    return syscall(SYS_futex, uaddr, op, val, timeout, uaddr2, val3);
+}
+
+static void test_wait_errors(void)
+{
+    volatile uint32_t word = 1;
+    if (futex(&word, FUTEX_WAIT_PRIVATE, 0, NULL, NULL, 0) != -1 || errno != EAGAIN)
+    {
+        fail("FUTEX_WAIT with a different value does not return EAGAIN");
+    }
+
+    struct timespec timeout = {.tv_nsec = 10000000};
+    if (futex(&word, FUTEX_WAIT_PRIVATE, 1, &timeout, NULL, 0) != -1 || errno != ETIMEDOUT)
+    {
+        fail("FUTEX_WAIT does not time out");
+    }
+}
+
+static volatile uint32_t wake_word = 0;
+static volatile uint32_t requeue_word = 0;
+static volatile int waiting = 0;
+
+static void *waiter_main(void *arg)
+{
+    __sync_fetch_and_add(&waiting, 1);
+    while (wake_word == 0)
+    {
+        futex(&wake_word, FUTEX_WAIT_PRIVATE, 0, NULL, NULL, 0);
+    }
+
+    return NULL;
+}
+
+// Waits until `n` threads are waiting on a futex.
+static void wait_for_waiters(int n)
+{
+    while (waiting < n)
+    {
+        usleep(1000);
+    }
+
+    usleep(50000);
+}
+
+static void test_wake(void)
+{
+    pthread_t thread;
+    pthread_create(&thread, NULL, waiter_main, NULL);
+    wait_for_waiters(1);
+
+    wake_word = 1;
+    if (futex(&wake_word, FUTEX_WAKE_PRIVATE, 1, NULL, NULL, 0) != 1)
+    {
+        fail("FUTEX_WAKE did not wake the waiter");
+    }
+
+    pthread_join(thread, NULL);
+}
+
+static void test_requeue(void)
+{
+    wake_word = 0;
+    waiting = 0;
+    pthread_t threads[2];
+    for (int i = 0; i < 2; i++)
+    {
+        pthread_create(&threads[i], NULL, waiter_main, NULL);
+    }
+
+    wait_for_waiters(2);
+
+    // Wake one waiter and move the other one to `requeue_word`.
+    wake_word = 1;
+    if (futex(&wake_word, FUTEX_CMP_REQUEUE_PRIVATE, 1, (void *)1, &requeue_word, 1) != 2)
+    {
+        fail("FUTEX_CMP_REQUEUE did not wake and requeue the waiters");
+    }
+
+    if (futex(&wake_word, FUTEX_WAKE_PRIVATE, 1, NULL, NULL, 0) != 0)
+    {
+        fail("the requeued waiter is still waiting on the original futex");
+    }
+
+    if (futex(&requeue_word, FUTEX_WAKE_PRIVATE, 1, NULL, NULL, 0) != 1)
+    {
+        fail("the waiter is not requeued");
+    }
+
+    for (int i = 0; i < 2; i++)
+    {
+        pthread_join(threads[i], NULL);
+    }
+}
+
+struct robust_entry
+{
+    struct robust_list list;
+    volatile uint32_t futex;
+};
+
+static struct robust_list_head robust_head;
+static struct robust_entry robust_lock;
+
+// Holds the lock in the robust list and exits without releasing it.
+static void *owner_main(void *arg)
+{
+    robust_head.list.next = &robust_lock.list;
+    robust_head.futex_offset = offsetof(struct robust_entry, futex);
+    robust_head.list_op_pending = NULL;
+    robust_lock.list.next = &robust_head.list;
+    robust_lock.futex = syscall(SYS_gettid);
+    if (syscall(SYS_set_robust_list, &robust_head, sizeof(robust_head)) < 0)
+    {
+        fail("set_robust_list failed");
+    }
+
+    // Exit the thread without running the cleanup in libc.
+    syscall(SYS_exit, 0);
+    return NULL;
+}
+
+static void test_robust_list(void)
+{
+    pthread_t thread;
+    pthread_create(&thread, NULL, owner_main, NULL);
+
+    while (robust_lock.futex == 0)
+    {
+        usleep(1000);
+    }
+
+    // The kernel marks the lock with FUTEX_OWNER_DIED and wakes a waiter when
+    // the owner exits.
+    struct timespec timeout = {.tv_sec = 5};
+    uint32_t value;
+    while (!((value = robust_lock.futex) & FUTEX_OWNER_DIED))
+    {
+        uint32_t waiting_value = value | FUTEX_WAITERS;
+        if (!__sync_bool_compare_and_swap(&robust_lock.futex, value, waiting_value))
+        {
+            continue;
+        }
+
+        if (futex(&robust_lock.futex, FUTEX_WAIT, waiting_value, &timeout, NULL, 0) < 0 &&
+            errno == ETIMEDOUT)
+        {
+            fail("the lock held by the exited thread is not released");
+        }
+    }
+
+    if ((value & FUTEX_TID_MASK) != 0)
+    {
+        fail("the owner's thread ID is not cleared");
+    }
+}
+
+int main(void)
+{
+    test_wait_errors();
+    test_wake();
+    test_requeue();
+    test_robust_list();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,193 @@
+// Checks job control: stop/continue notifications, foreground process
+// groups and orphaned process groups.
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+static void wait_success(pid_t pid, const char *msg)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+// waitpid(2) reports stopped and continued children.
+static void test_waitpid(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        raise(SIGSTOP);
+        exit(3);
+    }
+
+    int status;
+    if (waitpid(pid, &status, WUNTRACED) != pid || !WIFSTOPPED(status) ||
+        WSTOPSIG(status) != SIGSTOP)
+    {
+        fail("WUNTRACED does not report the stopped child");
+    }
+
+    kill(pid, SIGCONT);
+    if (waitpid(pid, &status, WCONTINUED) != pid || !WIFCONTINUED(status))
+    {
+        fail("WCONTINUED does not report the continued child");
+    }
+
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 3)
+    {
+        fail("the continued child did not exit normally");
+    }
+}
+
+// Creates a new session with a pseudo terminal as the controlling terminal.
+static int new_session_with_tty(void)
+{
+    if (setsid() < 0)
+    {
+        fail("setsid failed");
+    }
+
+    int master = posix_openpt(O_RDWR | O_NOCTTY);
+    if (master < 0 || grantpt(master) < 0 || unlockpt(master) < 0)
+    {
+        fail("failed to open a pseudo terminal");
+    }
+
+    int slave = open(ptsname(master), O_RDWR | O_NOCTTY);
+    if (slave < 0 || ioctl(slave, TIOCSCTTY, 0) < 0)
+    {
+        fail("failed to set the controlling terminal");
+    }
+
+    return slave;
+}
+
+static void foreground_group(void)
+{
+    int tty = new_session_with_tty();
+    if (tcgetpgrp(tty) != getpid())
+    {
+        fail("the session leader is not in the foreground");
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        setpgid(0, 0);
+        pause();
+        exit(0);
+    }
+
+    setpgid(pid, pid);
+    if (tcsetpgrp(tty, pid) < 0 || tcgetpgrp(tty) != pid)
+    {
+        fail("tcsetpgrp failed");
+    }
+
+    // A background process can't change the foreground group without
+    // blocking SIGTTOU.
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGTTOU);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    if (tcsetpgrp(tty, getpgrp()) < 0 || tcgetpgrp(tty) != getpid())
+    {
+        fail("tcsetpgrp from the background failed");
+    }
+
+    kill(pid, SIGKILL);
+    waitpid(pid, NULL, 0);
+    exit(0);
+}
+
+static void test_foreground_group(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        foreground_group();
+    }
+
+    wait_success(pid, "the foreground process group test failed");
+}
+
+static int hangup_pipe[2];
+
+static void notify_hangup(int signal)
+{
+    write(hangup_pipe[1], "h", 1);
+}
+
+// A stopped process in a newly orphaned process group gets SIGHUP and
+// SIGCONT.
+static void orphaned_group(void)
+{
+    new_session_with_tty();
+    pipe(hangup_pipe);
+
+    pid_t middle = fork();
+    if (middle == 0)
+    {
+        // The group is kept from being orphaned by the session leader.
+        setpgid(0, 0);
+        pid_t stopped = fork();
+        if (stopped == 0)
+        {
+            signal(SIGHUP, notify_hangup);
+            raise(SIGSTOP);
+            exit(0);
+        }
+
+        int status;
+        if (waitpid(stopped, &status, WUNTRACED) != stopped || !WIFSTOPPED(status))
+        {
+            exit(1);
+        }
+
+        // Orphan the group.
+        exit(0);
+    }
+
+    wait_success(middle, "failed to stop a process");
+    alarm(5);
+    char c;
+    if (read(hangup_pipe[0], &c, 1) != 1)
+    {
+        fail("the orphaned process group does not get SIGHUP");
+    }
+
+    exit(0);
+}
+
+static void test_orphaned_group(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        orphaned_group();
+    }
+
+    wait_success(pid, "the orphaned process group test failed");
+}
+
+int main(void)
+{
+    test_waitpid();
+    test_foreground_group();
+    test_orphaned_group();
+
+    printf("passed\n");
+    return 0;
+}
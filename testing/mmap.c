@@ -0,0 +1,186 @@
+// Checks munmap(2) and mprotect(2).
+
+#include <errno.h>
+#include <fcntl.h>
+#include <setjmp.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+
+#include "test_util.h"
+
+#define PATH "/tmp/mmap_readonly"
+
+static sigjmp_buf fault_env;
+static volatile int fault_code;
+
+static void handle_segv(int signal, siginfo_t *info, void *ctx)
+{
+    fault_code = info->si_code;
+    siglongjmp(fault_env, 1);
+}
+
+// Returns the si_code of SIGSEGV caused by the access, or 0 if it succeeds.
+static int try_access(volatile char *addr, int write)
+{
+    fault_code = 0;
+    if (sigsetjmp(fault_env, 1) == 0)
+    {
+        if (write)
+        {
+            *addr = 'x';
+        }
+        else
+        {
+            (void)*addr;
+        }
+    }
+
+    return fault_code;
+}
+
+static void test_munmap(long page_size)
+{
+    char *p = mmap(NULL, page_size * 3, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1,
+                   0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    memset(p, 'a', page_size * 3);
+    if (munmap(p + page_size, page_size) < 0)
+    {
+        fail("munmap failed");
+    }
+
+    // Only the middle page is unmapped.
+    if (try_access(p + page_size, 0) != SEGV_MAPERR)
+    {
+        fail("the unmapped page is still accessible");
+    }
+
+    if (try_access(p, 1) != 0 || try_access(p + page_size * 2, 1) != 0 ||
+        p[page_size * 2 + 1] != 'a')
+    {
+        fail("munmap unmapped the neighboring pages");
+    }
+
+    if (munmap(p + 1, page_size) != -1 || errno != EINVAL)
+    {
+        fail("munmap with an unaligned address does not return EINVAL");
+    }
+
+    if (mprotect(p, page_size * 3, PROT_READ) != -1 || errno != ENOMEM)
+    {
+        fail("mprotect on an unmapped range does not return ENOMEM");
+    }
+
+    munmap(p, page_size * 3);
+}
+
+static void test_mprotect(long page_size)
+{
+    char *p = mmap(NULL, page_size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    strcpy(p, "hello");
+    if (mprotect(p, page_size, PROT_READ) < 0)
+    {
+        fail("mprotect failed");
+    }
+
+    if (try_access(p, 1) != SEGV_ACCERR)
+    {
+        fail("a read-only page is writable");
+    }
+
+    if (try_access(p, 0) != 0 || strcmp(p, "hello") != 0)
+    {
+        fail("a read-only page is not readable");
+    }
+
+    // PROT_NONE pages are not accessible but keep their contents.
+    if (mprotect(p, page_size, PROT_NONE) < 0 || try_access(p, 0) != SEGV_ACCERR)
+    {
+        fail("a PROT_NONE page is readable");
+    }
+
+    if (mprotect(p, page_size, PROT_READ | PROT_WRITE) < 0 || strcmp(p, "hello") != 0)
+    {
+        fail("the contents are lost after PROT_NONE");
+    }
+
+    if (try_access(p, 1) != 0)
+    {
+        fail("the page is not writable again");
+    }
+
+    munmap(p, page_size);
+}
+
+// A shared mapping of a file opened read-only can't be writable.
+static void test_shared_readonly_file(long page_size)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || ftruncate(fd, page_size) < 0)
+    {
+        fail("failed to create a file");
+    }
+    close(fd);
+
+    fd = open(PATH, O_RDONLY);
+    if (fd < 0)
+    {
+        fail("open failed");
+    }
+
+    if (mmap(NULL, page_size, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0) != MAP_FAILED ||
+        errno != EACCES)
+    {
+        fail("a writable shared mapping of a read-only file does not return EACCES");
+    }
+
+    char *p = mmap(NULL, page_size, PROT_READ, MAP_SHARED, fd, 0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    if (mprotect(p, page_size, PROT_READ | PROT_WRITE) != -1 || errno != EACCES)
+    {
+        fail("mprotect makes a shared mapping of a read-only file writable");
+    }
+
+    // A private mapping is copied on write.
+    char *q = mmap(NULL, page_size, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if (q == MAP_FAILED)
+    {
+        fail("a private writable mapping of a read-only file failed");
+    }
+
+    munmap(p, page_size);
+    munmap(q, page_size);
+    close(fd);
+    unlink(PATH);
+}
+
+int main(void)
+{
+    struct sigaction act = {.sa_sigaction = handle_segv, .sa_flags = SA_SIGINFO};
+    sigaction(SIGSEGV, &act, NULL);
+
+    long page_size = sysconf(_SC_PAGESIZE);
+    test_munmap(page_size);
+    test_mprotect(page_size);
+    test_shared_readonly_file(page_size);
+
+    printf("passed\n");
+    return 0;
+}
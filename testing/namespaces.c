@@ -0,0 +1,234 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#ifndef SYS_pidfd_open
+#define SYS_pidfd_open 434
+#endif
+
+static int nodename_is(const char *name)
+{
+    struct utsname uts;
+    uname(&uts);
+    return strcmp(uts.nodename, name) == 0;
+}
+
+// Runs `f` in a child process and checks if it succeeds.
+static void run_in_child(int (*f)(void), const char *msg)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        exit(f());
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+static int test_uts_namespace(void)
+{
+    if (unshare(CLONE_NEWUTS) < 0 || sethostname("container", 9) < 0)
+    {
+        return 1;
+    }
+
+    return nodename_is("container") ? 0 : 1;
+}
+
+static int test_pid_namespace(void)
+{
+    if (unshare(CLONE_NEWPID) < 0)
+    {
+        return 1;
+    }
+
+    // The first child becomes the init process of the new namespace.
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        exit(getpid() == 1 && getppid() == 0 ? 0 : 1);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+    {
+        return 1;
+    }
+
+    return WEXITSTATUS(status);
+}
+
+// Runs as the init process of a new PID namespace.
+static int pid_namespace_init(void)
+{
+    // The sender's process ID is the one in the receiver's namespace.
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    pid_t sender = fork();
+    if (sender == 0)
+    {
+        kill(getppid(), SIGUSR1);
+        exit(0);
+    }
+
+    siginfo_t info;
+    if (sigwaitinfo(&set, &info) != SIGUSR1 || info.si_pid != sender)
+    {
+        return 1;
+    }
+
+    waitpid(sender, NULL, 0);
+
+    // An orphaned process is adopted by the init process.
+    int fds[2];
+    pipe(fds);
+    pid_t middle = fork();
+    if (middle == 0)
+    {
+        if (fork() == 0)
+        {
+            close(fds[0]);
+            while (getppid() != 1)
+            {
+                usleep(1000);
+            }
+
+            write(fds[1], "x", 1);
+            exit(0);
+        }
+
+        exit(0);
+    }
+
+    close(fds[1]);
+    waitpid(middle, NULL, 0);
+    char c;
+    if (read(fds[0], &c, 1) != 1)
+    {
+        return 1;
+    }
+
+    int status;
+    if (waitpid(-1, &status, 0) < 0 || !WIFEXITED(status))
+    {
+        return 1;
+    }
+
+    return 0;
+}
+
+static int test_pid_namespace_reaper(void)
+{
+    if (unshare(CLONE_NEWPID) < 0)
+    {
+        return 1;
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        exit(pid_namespace_init());
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+    {
+        return 1;
+    }
+
+    return WEXITSTATUS(status);
+}
+
+static int test_mount_namespace(void)
+{
+    if (unshare(CLONE_NEWNS) < 0)
+    {
+        return 1;
+    }
+
+    if (mount("none", "/tmp/ns_mnt", "tmpfs", 0, NULL) < 0)
+    {
+        return 1;
+    }
+
+    // The mounted tmpfs is empty.
+    if (access("/tmp/ns_mnt/marker", F_OK) == 0)
+    {
+        return 1;
+    }
+
+    return creat("/tmp/ns_mnt/private", 0644) < 0;
+}
+
+int main(int argc, char **argv)
+{
+    struct utsname uts;
+    uname(&uts);
+    run_in_child(test_uts_namespace, "failed to change the host name in a new UTS namespace");
+    if (!nodename_is(uts.nodename))
+    {
+        fail("the host name is changed outside the UTS namespace");
+    }
+
+    run_in_child(test_pid_namespace, "the process is not init in a new PID namespace");
+    run_in_child(test_pid_namespace_reaper,
+                 "the init process does not adopt orphans or see the sender's ID");
+
+    mkdir("/tmp/ns_mnt", 0755);
+    close(creat("/tmp/ns_mnt/marker", 0644));
+    run_in_child(test_mount_namespace, "failed to mount tmpfs in a new mount namespace");
+    if (access("/tmp/ns_mnt/marker", F_OK) < 0 || access("/tmp/ns_mnt/private", F_OK) == 0)
+    {
+        fail("the mount is visible outside the mount namespace");
+    }
+
+    // Join the UTS namespace of another process through a pidfd.
+    int fds[2];
+    pipe(fds);
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        unshare(CLONE_NEWUTS);
+        sethostname("joined", 6);
+        write(fds[1], "x", 1);
+        pause();
+        exit(0);
+    }
+
+    char c;
+    read(fds[0], &c, 1);
+    int pidfd = syscall(SYS_pidfd_open, pid, 0);
+    if (pidfd < 0 || setns(pidfd, CLONE_NEWUTS) < 0)
+    {
+        fail("setns(2) failed");
+    }
+
+    if (!nodename_is("joined"))
+    {
+        fail("failed to join the UTS namespace");
+    }
+
+    kill(pid, SIGKILL);
+    waitpid(pid, NULL, 0);
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,160 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define PATH "/tmp/page_cache"
+#define SIZE 8192
+
+static void expect_file(int fd, off_t offset, const char *expected)
+{
+    char buf[32];
+    size_t len = strlen(expected);
+    if (pread(fd, buf, len, offset) != (ssize_t)len || memcmp(buf, expected, len))
+    {
+        fail(expected);
+    }
+}
+
+static char *map(int fd, int flags)
+{
+    char *p = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, flags, fd, 0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    return p;
+}
+
+// Writes through a MAP_SHARED mapping are visible to read(2) and vice versa.
+static void test_read_write(int fd)
+{
+    char *p = map(fd, MAP_SHARED);
+    memcpy(p + 4096, "mapped", 6);
+    expect_file(fd, 4096, "mapped");
+
+    if (pwrite(fd, "written", 7, 4100) != 7)
+    {
+        fail("pwrite failed");
+    }
+
+    if (memcmp(p + 4096, "mappwritten", 11))
+    {
+        fail("the mapping does not see write(2)");
+    }
+
+    if (msync(p, SIZE, MS_SYNC) < 0 || munmap(p, SIZE) < 0)
+    {
+        fail("msync or munmap failed");
+    }
+
+    expect_file(fd, 4096, "mappwritten");
+}
+
+// MAP_SHARED pages are shared with the child; MAP_PRIVATE ones are not.
+static void test_fork(int fd)
+{
+    char *shared = map(fd, MAP_SHARED);
+    char *private = map(fd, MAP_PRIVATE);
+    private[0] = 'p';
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        memcpy(shared, "child", 5);
+        private[0] = 'c';
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status))
+    {
+        fail("the child failed");
+    }
+
+    if (memcmp(shared, "child", 5))
+    {
+        fail("the shared mapping does not see the child's write");
+    }
+
+    if (private[0] != 'p')
+    {
+        fail("the private mapping sees the child's write");
+    }
+
+    expect_file(fd, 0, "child");
+    munmap(shared, SIZE);
+    munmap(private, SIZE);
+
+    // The page cache outlives the mappings.
+    char *p = map(fd, MAP_SHARED);
+    if (memcmp(p, "child", 5))
+    {
+        fail("the new mapping does not see the previous one");
+    }
+
+    munmap(p, SIZE);
+}
+
+// Writes through a MAP_SHARED mapping are written back without msync(2) once
+// the mapping is gone: the cached pages may be evicted.
+static void test_writeback(void)
+{
+    int fd = open(PATH, O_RDWR);
+    if (fd < 0)
+    {
+        fail("open failed");
+    }
+
+    char *p = map(fd, MAP_SHARED);
+    memcpy(p + 100, "unmapped", 8);
+    if (munmap(p, SIZE) < 0)
+    {
+        fail("munmap failed");
+    }
+    close(fd);
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        fd = open(PATH, O_RDWR);
+        p = map(fd, MAP_SHARED);
+        memcpy(p + 200, "exited", 6);
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status))
+    {
+        fail("the child failed");
+    }
+
+    fd = open(PATH, O_RDONLY);
+    expect_file(fd, 100, "unmapped");
+    expect_file(fd, 200, "exited");
+    close(fd);
+}
+
+int main(void)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || ftruncate(fd, SIZE) < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    test_read_write(fd);
+    test_fork(fd);
+
+    close(fd);
+    test_writeback();
+    unlink(PATH);
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,160 @@
+// Checks permission checks based on user IDs.
+
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define UID 1000
+#define GID 1000
+#define SECRET_PATH "/tmp/permissions_secret"
+#define SETUID_PATH "/tmp/permissions_setuid"
+#define SCRIPT_PATH "/tmp/permissions_script"
+
+static void create_file(const char *path, const char *contents, mode_t mode)
+{
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, mode);
+    if (fd < 0 || write(fd, contents, strlen(contents)) != (ssize_t)strlen(contents))
+    {
+        fail("failed to create a file");
+    }
+
+    close(fd);
+    if (chmod(path, mode) < 0)
+    {
+        fail("chmod failed");
+    }
+}
+
+// Runs `test` as an unprivileged user in a child process.
+static void run_as_user(void (*test)(void), const char *msg)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (setgid(GID) < 0 || setuid(UID) < 0)
+        {
+            fail("setuid failed");
+        }
+
+        test();
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+static void test_chown(void)
+{
+    create_file(SECRET_PATH, "secret", 0600);
+    if (chown(SECRET_PATH, UID, GID) < 0)
+    {
+        fail("chown failed");
+    }
+
+    // The superuser can change the group of a file owned by another user.
+    if (chown(SECRET_PATH, -1, 0) < 0)
+    {
+        fail("chgrp of another user's file failed");
+    }
+
+    struct stat st;
+    if (stat(SECRET_PATH, &st) < 0 || st.st_uid != UID || st.st_gid != 0)
+    {
+        fail("chown did not change the owner");
+    }
+
+    unlink(SECRET_PATH);
+}
+
+static void test_chown_drops_setuid(void)
+{
+    create_file(SETUID_PATH, "", 06755);
+    if (chown(SETUID_PATH, UID, GID) < 0)
+    {
+        fail("chown failed");
+    }
+
+    struct stat st;
+    if (stat(SETUID_PATH, &st) < 0 || (st.st_mode & 07777) != 0755)
+    {
+        fail("chown did not clear the setuid and setgid bits");
+    }
+
+    unlink(SETUID_PATH);
+}
+
+static void access_secret(void)
+{
+    if (open(SECRET_PATH, O_RDONLY) != -1 || errno != EACCES)
+    {
+        fail("opened a file without the read permission");
+    }
+
+    if (execl(SECRET_PATH, SECRET_PATH, NULL) != -1 || errno != EACCES)
+    {
+        fail("executed a file without the execute permission");
+    }
+}
+
+static void test_eacces(void)
+{
+    create_file(SECRET_PATH, "secret", 0600);
+    run_as_user(access_secret, "the permission check failed");
+    unlink(SECRET_PATH);
+}
+
+static void kill_root(void)
+{
+    if (kill(getppid(), 0) != -1 || errno != EPERM)
+    {
+        fail("signaled a process owned by another user");
+    }
+}
+
+static void test_kill_eperm(void)
+{
+    run_as_user(kill_root, "kill(2) does not return EPERM");
+}
+
+static void exec_script(void)
+{
+    execl(SCRIPT_PATH, SCRIPT_PATH, NULL);
+    fail("failed to execute the script");
+}
+
+// The setuid bit of an interpreted file is ignored.
+static void test_setuid_script(void)
+{
+    create_file(SCRIPT_PATH, "#!/bin/sh\n[ \"$(id -u)\" = 1000 ]\n", 04755);
+    run_as_user(exec_script, "a setuid script is executed as its owner");
+    unlink(SCRIPT_PATH);
+}
+
+int main(void)
+{
+    if (getuid() != 0)
+    {
+        fail("must be run as root");
+    }
+
+    test_chown();
+    test_chown_drops_setuid();
+    test_eacces();
+    test_kill_eperm();
+    test_setuid_script();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,108 @@
+// Checks permissions to change nice values and scheduling policies.
+
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define UID 1000
+
+static pid_t root_pid;
+
+static void test_unprivileged(void)
+{
+    if (setuid(UID) < 0)
+    {
+        fail("setuid failed");
+    }
+
+    // The nice value can be raised but not lowered (RLIMIT_NICE is 0).
+    if (setpriority(PRIO_PROCESS, 0, 5) < 0 || getpriority(PRIO_PROCESS, 0) != 5)
+    {
+        fail("failed to raise the nice value");
+    }
+
+    if (setpriority(PRIO_PROCESS, 0, 0) != -1 || errno != EACCES)
+    {
+        fail("an unprivileged process lowered the nice value");
+    }
+
+    if (setpriority(PRIO_PROCESS, root_pid, 10) != -1 || errno != EPERM)
+    {
+        fail("changed the nice value of a process owned by another user");
+    }
+
+    errno = 0;
+    if (getpriority(PRIO_USER, UID) != 5 || errno != 0)
+    {
+        fail("getpriority(PRIO_USER) failed");
+    }
+
+    struct sched_param param = {.sched_priority = 1};
+    if (sched_setscheduler(0, SCHED_FIFO, &param) != -1 || errno != EPERM)
+    {
+        fail("an unprivileged process became a real-time process");
+    }
+
+    param.sched_priority = 0;
+    if (sched_setscheduler(root_pid, SCHED_OTHER, &param) != -1 || errno != EPERM)
+    {
+        fail("changed the policy of a process owned by another user");
+    }
+
+    exit(0);
+}
+
+// RLIMIT_RTPRIO allows real-time priorities up to the limit.
+static void test_rtprio_limit(void)
+{
+    struct rlimit limit = {.rlim_cur = 10, .rlim_max = 10};
+    if (setrlimit(RLIMIT_RTPRIO, &limit) < 0 || setuid(UID) < 0)
+    {
+        fail("failed to set RLIMIT_RTPRIO");
+    }
+
+    struct sched_param param = {.sched_priority = 10};
+    if (sched_setscheduler(0, SCHED_FIFO, &param) < 0)
+    {
+        fail("RLIMIT_RTPRIO does not allow a real-time priority");
+    }
+
+    param.sched_priority = 11;
+    if (sched_setparam(0, &param) != -1 || errno != EPERM)
+    {
+        fail("the real-time priority exceeds RLIMIT_RTPRIO");
+    }
+
+    exit(0);
+}
+
+static void run(void (*test)(void), const char *msg)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        test();
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+int main(void)
+{
+    root_pid = getpid();
+    run(test_unprivileged, "the unprivileged process test failed");
+    run(test_rtprio_limit, "the RLIMIT_RTPRIO test failed");
+
+    printf("passed\n");
+    return 0;
+}
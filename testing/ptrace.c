@@ -0,0 +1,296 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/ptrace.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/user.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define SETUID_PATH "/tmp/ptrace_setuid"
+
+// Modified by the tracer while the tracee is stopped.
+static volatile long value = 123;
+
+static int wait_child(pid_t pid)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid)
+    {
+        fail("waitpid failed");
+    }
+
+    return status;
+}
+
+static void wait_stop(pid_t pid, int signal)
+{
+    int status = wait_child(pid);
+    if (!WIFSTOPPED(status) || WSTOPSIG(status) != signal)
+    {
+        fail("the tracee is not stopped by the expected signal");
+    }
+}
+
+static void wait_exit(pid_t pid, int code, const char *msg)
+{
+    int status = wait_child(pid);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != code)
+    {
+        fail(msg);
+    }
+}
+
+static void test_memory_and_syscall_stops(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        ptrace(PTRACE_TRACEME, 0, 0, 0);
+        kill(getpid(), SIGSTOP);
+        if (value != 456)
+        {
+            exit(1);
+        }
+
+        syscall(SYS_getppid);
+        exit(42);
+    }
+
+    wait_stop(pid, SIGSTOP);
+
+    if (ptrace(PTRACE_PEEKDATA, pid, &value, 0) != 123)
+    {
+        fail("PTRACE_PEEKDATA returned a wrong value");
+    }
+
+    if (ptrace(PTRACE_POKEDATA, pid, &value, (void *)456) < 0)
+    {
+        fail("PTRACE_POKEDATA failed");
+    }
+
+    ptrace(PTRACE_SETOPTIONS, pid, 0, PTRACE_O_TRACESYSGOOD);
+
+    // Look for the exit of getppid(2) in system call stops. SIGSTOP is
+    // suppressed by the first PTRACE_SYSCALL.
+    int status;
+    int found = 0;
+    while (1)
+    {
+        ptrace(PTRACE_SYSCALL, pid, 0, 0);
+        status = wait_child(pid);
+        if (WIFEXITED(status))
+        {
+            break;
+        }
+
+        if (!WIFSTOPPED(status) || WSTOPSIG(status) != (SIGTRAP | 0x80))
+        {
+            fail("the tracee is not in a system call stop");
+        }
+
+        struct user_regs_struct regs;
+        ptrace(PTRACE_GETREGS, pid, 0, &regs);
+        if (regs.orig_rax == SYS_getppid && regs.rax == (unsigned long)getpid())
+        {
+            found = 1;
+        }
+    }
+
+    if (!found)
+    {
+        fail("getppid(2) is not traced");
+    }
+
+    if (WEXITSTATUS(status) != 42)
+    {
+        fail("the tracee exited with a wrong status");
+    }
+}
+
+// Checks that registers modified in a system call stop are restored,
+// including RCX and R11 which are clobbered by SYSCALL and SYSRET.
+static void test_modified_registers(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        ptrace(PTRACE_TRACEME, 0, 0, 0);
+        kill(getpid(), SIGSTOP);
+
+        register long r11 asm("r11");
+        long rax = SYS_getppid;
+        long rcx;
+        asm volatile("syscall" : "+a"(rax), "=c"(rcx), "=r"(r11) : : "memory");
+        exit((rax == 77 && rcx == 0x1234 && r11 == 0x5678) ? 0 : 1);
+    }
+
+    wait_stop(pid, SIGSTOP);
+
+    while (1)
+    {
+        ptrace(PTRACE_SYSCALL, pid, 0, 0);
+        wait_stop(pid, SIGTRAP);
+
+        struct user_regs_struct regs;
+        ptrace(PTRACE_GETREGS, pid, 0, &regs);
+        if (regs.orig_rax == SYS_getppid && (long)regs.rax != -ENOSYS)
+        {
+            regs.rax = 77;
+            regs.rcx = 0x1234;
+            regs.r11 = 0x5678;
+            if (ptrace(PTRACE_SETREGS, pid, 0, &regs) < 0)
+            {
+                fail("PTRACE_SETREGS failed");
+            }
+
+            break;
+        }
+    }
+
+    ptrace(PTRACE_CONT, pid, 0, 0);
+    wait_exit(pid, 0, "registers modified by the tracer are not restored");
+}
+
+static void test_fpu_registers(void)
+{
+    uint64_t original = 0x3ff8000000000000; // 1.5
+    uint64_t modified = 0x4004000000000000; // 2.5
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        ptrace(PTRACE_TRACEME, 0, 0, 0);
+
+        // Stop with the value in XMM0: the tracer replaces it.
+        uint64_t restored;
+        long rax = SYS_kill;
+        asm volatile("movq %[in], %%xmm0\n\t"
+                     "syscall\n\t"
+                     "movq %%xmm0, %[out]"
+                     : "+a"(rax), [out] "=r"(restored)
+                     : [in] "r"(original), "D"((long)getpid()), "S"((long)SIGSTOP)
+                     : "rcx", "r11", "xmm0", "memory");
+        exit(restored == modified ? 0 : 1);
+    }
+
+    wait_stop(pid, SIGSTOP);
+
+    struct user_fpregs_struct fpregs;
+    if (ptrace(PTRACE_GETFPREGS, pid, 0, &fpregs) < 0)
+    {
+        fail("PTRACE_GETFPREGS failed");
+    }
+
+    uint64_t xmm0;
+    memcpy(&xmm0, fpregs.xmm_space, sizeof(xmm0));
+    if (xmm0 != original)
+    {
+        fail("PTRACE_GETFPREGS returned a wrong XMM0");
+    }
+
+    memcpy(fpregs.xmm_space, &modified, sizeof(modified));
+    if (ptrace(PTRACE_SETFPREGS, pid, 0, &fpregs) < 0)
+    {
+        fail("PTRACE_SETFPREGS failed");
+    }
+
+    ptrace(PTRACE_CONT, pid, 0, 0);
+    wait_exit(pid, 0, "XMM0 modified by the tracer is not restored");
+}
+
+static void copy_file(const char *src, const char *dst)
+{
+    int in = open(src, O_RDONLY);
+    int out = open(dst, O_WRONLY | O_CREAT | O_TRUNC, 0755);
+    if (in < 0 || out < 0)
+    {
+        fail("failed to open files to copy");
+    }
+
+    char buf[4096];
+    ssize_t len;
+    while ((len = read(in, buf, sizeof(buf))) > 0)
+    {
+        if (write(out, buf, len) != len)
+        {
+            fail("failed to copy a file");
+        }
+    }
+
+    close(in);
+    close(out);
+}
+
+// The exit status of the setuid binary: whether it's running as root.
+static int report_euid(void)
+{
+    return geteuid() == 0 ? 10 : 11;
+}
+
+// Checks that an unprivileged tracer can't take over a setuid binary.
+static void test_setuid_exec(const char *self)
+{
+    copy_file(self, SETUID_PATH);
+    if (chown(SETUID_PATH, 0, 0) < 0 || chmod(SETUID_PATH, 04755) < 0)
+    {
+        fail("failed to create a setuid binary");
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (setuid(1000) < 0)
+        {
+            fail("setuid failed");
+        }
+
+        pid_t child = fork();
+        if (child == 0)
+        {
+            execl(SETUID_PATH, SETUID_PATH, "euid", NULL);
+            exit(1);
+        }
+
+        wait_exit(child, 10, "the setuid bit is not honored");
+
+        child = fork();
+        if (child == 0)
+        {
+            ptrace(PTRACE_TRACEME, 0, 0, 0);
+            execl(SETUID_PATH, SETUID_PATH, "euid", NULL);
+            exit(1);
+        }
+
+        wait_stop(child, SIGTRAP);
+        ptrace(PTRACE_CONT, child, 0, 0);
+        wait_exit(child, 11, "the traced setuid binary is running as root");
+        exit(0);
+    }
+
+    wait_exit(pid, 0, "the setuid exec test failed");
+    unlink(SETUID_PATH);
+}
+
+int main(int argc, char **argv)
+{
+    if (argc > 1 && !strcmp(argv[1], "euid"))
+    {
+        return report_euid();
+    }
+
+    test_memory_and_syscall_stops();
+    test_modified_registers();
+    test_fpu_registers();
+    test_setuid_exec(argv[0]);
+
+    printf("passed\n");
+    return 0;
+}
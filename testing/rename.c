@@ -0,0 +1,198 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define DIR "/tmp/rename"
+
+#define RENAME_NOREPLACE (1 << 0)
+#define RENAME_EXCHANGE (1 << 1)
+
+static int renameat2(const char *old_path, const char *new_path, unsigned flags)
+{
+    return syscall(SYS_renameat2, AT_FDCWD, old_path, AT_FDCWD, new_path, flags);
+}
+
+static void create_file(const char *path, const char *contents)
+{
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, contents, strlen(contents)) != (ssize_t)strlen(contents))
+    {
+        fail("failed to create a file");
+    }
+
+    close(fd);
+}
+
+static void expect_file(const char *path, const char *expected)
+{
+    char buf[32];
+    int fd = open(path, O_RDONLY);
+    ssize_t len = fd < 0 ? -1 : read(fd, buf, sizeof(buf));
+    if (len != (ssize_t)strlen(expected) || memcmp(buf, expected, len))
+    {
+        fail(path);
+    }
+
+    close(fd);
+}
+
+static void test_noreplace(void)
+{
+    create_file(DIR "/a", "a");
+    create_file(DIR "/b", "b");
+    if (renameat2(DIR "/a", DIR "/b", RENAME_NOREPLACE) != -1 || errno != EEXIST)
+    {
+        fail("RENAME_NOREPLACE replaced the destination");
+    }
+
+    expect_file(DIR "/a", "a");
+    expect_file(DIR "/b", "b");
+
+    if (renameat2(DIR "/a", DIR "/c", RENAME_NOREPLACE) < 0)
+    {
+        fail("RENAME_NOREPLACE failed");
+    }
+
+    expect_file(DIR "/c", "a");
+    unlink(DIR "/b");
+    unlink(DIR "/c");
+}
+
+static void test_exchange(void)
+{
+    create_file(DIR "/a", "a");
+    if (mkdir(DIR "/d", 0755) < 0)
+    {
+        fail("mkdir failed");
+    }
+
+    create_file(DIR "/d/b", "b");
+    if (renameat2(DIR "/a", DIR "/d/b", RENAME_EXCHANGE) < 0)
+    {
+        fail("RENAME_EXCHANGE failed");
+    }
+
+    expect_file(DIR "/a", "b");
+    expect_file(DIR "/d/b", "a");
+
+    // A file and a directory can be exchanged.
+    if (renameat2(DIR "/a", DIR "/d", RENAME_EXCHANGE) < 0)
+    {
+        fail("RENAME_EXCHANGE of a directory failed");
+    }
+
+    expect_file(DIR "/d", "b");
+    expect_file(DIR "/a/b", "a");
+
+    if (renameat2(DIR "/d", DIR "/e", RENAME_EXCHANGE) != -1 || errno != ENOENT)
+    {
+        fail("RENAME_EXCHANGE without the destination does not return ENOENT");
+    }
+
+    if (renameat2(DIR "/d", DIR "/a", RENAME_EXCHANGE | RENAME_NOREPLACE) != -1 ||
+        errno != EINVAL)
+    {
+        fail("RENAME_EXCHANGE with RENAME_NOREPLACE does not return EINVAL");
+    }
+
+    unlink(DIR "/d");
+    unlink(DIR "/a/b");
+    rmdir(DIR "/a");
+}
+
+// Only the owner of an entry or the directory can rename it in a sticky
+// directory.
+static void test_sticky(void)
+{
+    if (mkdir(DIR "/sticky", 01777) < 0 || chmod(DIR "/sticky", 01777) < 0)
+    {
+        fail("failed to create a sticky directory");
+    }
+
+    create_file(DIR "/sticky/root", "root");
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (setuid(1000) < 0)
+        {
+            fail("setuid failed");
+        }
+
+        if (rename(DIR "/sticky/root", DIR "/sticky/moved") != -1 || errno != EPERM)
+        {
+            fail("an entry owned by another user is renamed in a sticky directory");
+        }
+
+        if (unlink(DIR "/sticky/root") != -1 || errno != EPERM)
+        {
+            fail("an entry owned by another user is removed in a sticky directory");
+        }
+
+        create_file(DIR "/sticky/user", "user");
+        if (rename(DIR "/sticky/user", DIR "/sticky/moved") < 0)
+        {
+            fail("the owner can't rename an entry in a sticky directory");
+        }
+
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail("the sticky directory test failed");
+    }
+
+    expect_file(DIR "/sticky/root", "root");
+    expect_file(DIR "/sticky/moved", "user");
+    unlink(DIR "/sticky/root");
+    unlink(DIR "/sticky/moved");
+    rmdir(DIR "/sticky");
+}
+
+static void test_mount_points(void)
+{
+    if (rmdir("/tmp") != -1 || errno != EBUSY)
+    {
+        fail("rmdir of a mount point does not return EBUSY");
+    }
+
+    if (rename("/dev", "/dev2") != -1 || errno != EBUSY)
+    {
+        fail("rename of a mount point does not return EBUSY");
+    }
+
+    // devfs is backed by tmpfs but mounted elsewhere.
+    create_file(DIR "/file", "file");
+    if (rename(DIR "/file", "/dev/file") != -1 || errno != EXDEV)
+    {
+        fail("rename across mount points does not return EXDEV");
+    }
+
+    unlink(DIR "/file");
+}
+
+int main(void)
+{
+    if (mkdir(DIR, 0755) < 0)
+    {
+        fail("mkdir failed");
+    }
+
+    test_noreplace();
+    test_exchange();
+    test_sticky();
+    test_mount_points();
+
+    rmdir(DIR);
+    printf("passed\n");
+    return 0;
+}
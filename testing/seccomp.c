@@ -0,0 +1,384 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/prctl.h>
+#include <sys/ptrace.h>
+#include <sys/syscall.h>
+#include <sys/user.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+// Kernel headers are not available in musl-gcc.
+#define SECCOMP_MODE_STRICT 1
+#define SECCOMP_MODE_FILTER 2
+#define SECCOMP_SET_MODE_FILTER 1
+#define SECCOMP_FILTER_FLAG_TSYNC 1
+#define SECCOMP_RET_KILL_PROCESS 0x80000000U
+#define SECCOMP_RET_TRAP 0x00030000U
+#define SECCOMP_RET_ERRNO 0x00050000U
+#define SECCOMP_RET_TRACE 0x7ff00000U
+#define SECCOMP_RET_ALLOW 0x7fff0000U
+#define AUDIT_ARCH_X86_64 0xc000003eU
+#define SYS_SECCOMP 1
+
+#define BPF_LD 0x00
+#define BPF_JMP 0x05
+#define BPF_RET 0x06
+#define BPF_W 0x00
+#define BPF_ABS 0x20
+#define BPF_JEQ 0x10
+#define BPF_K 0x00
+
+#define BPF_STMT(code, k) {(unsigned short)(code), 0, 0, k}
+#define BPF_JUMP(code, k, jt, jf) {(unsigned short)(code), jt, jf, k}
+
+struct sock_filter
+{
+    uint16_t code;
+    uint8_t jt;
+    uint8_t jf;
+    uint32_t k;
+};
+
+struct sock_fprog
+{
+    unsigned short len;
+    struct sock_filter *filter;
+};
+
+struct seccomp_data
+{
+    int nr;
+    uint32_t arch;
+    uint64_t instruction_pointer;
+    uint64_t args[6];
+};
+
+static long install_filter(unsigned int flags)
+{
+    struct sock_filter filter[] = {
+        BPF_STMT(BPF_LD | BPF_W | BPF_ABS, offsetof(struct seccomp_data, arch)),
+        BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH_X86_64, 1, 0),
+        BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+        BPF_STMT(BPF_LD | BPF_W | BPF_ABS, offsetof(struct seccomp_data, nr)),
+        BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getppid, 0, 1),
+        BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | EPERM),
+        BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getuid, 0, 1),
+        BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_TRAP | 42),
+        BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getpgid, 0, 1),
+        BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+        BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+    };
+    struct sock_fprog prog = {
+        .len = sizeof(filter) / sizeof(filter[0]),
+        .filter = filter,
+    };
+
+    if (prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0)
+    {
+        return -1;
+    }
+
+    return syscall(SYS_seccomp, SECCOMP_SET_MODE_FILTER, flags, &prog);
+}
+
+static volatile int trapped = 0;
+
+static void sigsys_handler(int signo, siginfo_t *info, void *ucontext)
+{
+    if (info->si_code == SYS_SECCOMP && info->si_syscall == SYS_getuid &&
+        info->si_arch == AUDIT_ARCH_X86_64 && info->si_errno == 42)
+    {
+        trapped = 1;
+    }
+}
+
+// Checks the filter inherited from the parent process.
+static int check_inherited_filter(void)
+{
+    if (prctl(PR_GET_SECCOMP) != SECCOMP_MODE_FILTER || prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0) != 1)
+    {
+        return 1;
+    }
+
+    return (getppid() == -1 && errno == EPERM) ? 0 : 1;
+}
+
+static int wait_child(pid_t pid)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid)
+    {
+        fail("waitpid failed");
+    }
+
+    return status;
+}
+
+static void test_filter(const char *self)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (install_filter(0) < 0)
+        {
+            fail("failed to install a filter");
+        }
+
+        if (getppid() != -1 || errno != EPERM)
+        {
+            fail("SECCOMP_RET_ERRNO is not returned");
+        }
+
+        struct sigaction act;
+        memset(&act, 0, sizeof(act));
+        act.sa_sigaction = sigsys_handler;
+        act.sa_flags = SA_SIGINFO;
+        sigaction(SIGSYS, &act, NULL);
+        getuid();
+        if (!trapped)
+        {
+            fail("SECCOMP_RET_TRAP does not send SIGSYS");
+        }
+
+        pid_t child = fork();
+        if (child == 0)
+        {
+            exit(check_inherited_filter());
+        }
+
+        int status = wait_child(child);
+        if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        {
+            fail("the filter is not inherited by fork(2)");
+        }
+
+        child = fork();
+        if (child == 0)
+        {
+            execl(self, self, "exec", NULL);
+            exit(1);
+        }
+
+        status = wait_child(child);
+        if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        {
+            fail("the filter is not kept across execve(2)");
+        }
+
+        // Should be killed here.
+        getpgid(0);
+        exit(0);
+    }
+
+    int status = wait_child(pid);
+    if (WIFEXITED(status) && WEXITSTATUS(status) != 0)
+    {
+        exit(1);
+    }
+
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGSYS)
+    {
+        fail("SECCOMP_RET_KILL_PROCESS does not kill the process");
+    }
+}
+
+// Checks that the tracer handles SECCOMP_RET_TRACE: it replaces getppid(2)
+// with getpid(2).
+static void test_trace(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        struct sock_filter filter[] = {
+            BPF_STMT(BPF_LD | BPF_W | BPF_ABS, offsetof(struct seccomp_data, nr)),
+            BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getppid, 0, 1),
+            BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_TRACE | 42),
+            BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+        };
+        struct sock_fprog prog = {
+            .len = sizeof(filter) / sizeof(filter[0]),
+            .filter = filter,
+        };
+
+        ptrace(PTRACE_TRACEME, 0, 0, 0);
+        kill(getpid(), SIGSTOP);
+        if (prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 ||
+            syscall(SYS_seccomp, SECCOMP_SET_MODE_FILTER, 0, &prog) < 0)
+        {
+            exit(1);
+        }
+
+        exit(syscall(SYS_getppid) == getpid() ? 0 : 1);
+    }
+
+    int status = wait_child(pid);
+    if (!WIFSTOPPED(status) || WSTOPSIG(status) != SIGSTOP)
+    {
+        fail("the tracee is not stopped by SIGSTOP");
+    }
+
+    ptrace(PTRACE_SETOPTIONS, pid, 0, PTRACE_O_TRACESECCOMP);
+    ptrace(PTRACE_CONT, pid, 0, 0);
+    status = wait_child(pid);
+    if (!WIFSTOPPED(status) || status >> 8 != (SIGTRAP | (PTRACE_EVENT_SECCOMP << 8)))
+    {
+        fail("the tracee is not in a seccomp stop");
+    }
+
+    unsigned long msg;
+    struct user_regs_struct regs;
+    ptrace(PTRACE_GETEVENTMSG, pid, 0, &msg);
+    ptrace(PTRACE_GETREGS, pid, 0, &regs);
+    if (msg != 42 || regs.orig_rax != SYS_getppid)
+    {
+        fail("the seccomp stop reports a wrong system call");
+    }
+
+    regs.orig_rax = SYS_getpid;
+    ptrace(PTRACE_SETREGS, pid, 0, &regs);
+    ptrace(PTRACE_CONT, pid, 0, 0);
+    status = wait_child(pid);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail("the system call is not replaced by the tracer");
+    }
+}
+
+static volatile int tsync_step = 0;
+static volatile pid_t tsync_tid = 0;
+
+static void *tsync_thread(void *arg)
+{
+    tsync_tid = syscall(SYS_gettid);
+    tsync_step = 1;
+    while (tsync_step != 2)
+    {
+    }
+
+    // The filter installed by the other thread is applied.
+    if (syscall(SYS_getppid) != -1 || errno != EPERM)
+    {
+        tsync_step = 3;
+        return (void *)1;
+    }
+
+    // Diverge from the other thread.
+    if (install_filter(0) < 0)
+    {
+        tsync_step = 3;
+        return (void *)1;
+    }
+
+    tsync_step = 3;
+    while (tsync_step != 4)
+    {
+    }
+
+    return NULL;
+}
+
+static void test_tsync(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        pthread_t thread;
+        if (pthread_create(&thread, NULL, tsync_thread, NULL) != 0)
+        {
+            fail("pthread_create failed");
+        }
+
+        while (tsync_step != 1)
+        {
+        }
+
+        if (install_filter(SECCOMP_FILTER_FLAG_TSYNC) != 0)
+        {
+            fail("SECCOMP_FILTER_FLAG_TSYNC failed");
+        }
+
+        tsync_step = 2;
+        while (tsync_step != 3)
+        {
+        }
+
+        // The thread has a filter not installed in this thread.
+        if (install_filter(SECCOMP_FILTER_FLAG_TSYNC) != tsync_tid)
+        {
+            fail("SECCOMP_FILTER_FLAG_TSYNC does not return the thread ID");
+        }
+
+        tsync_step = 4;
+        void *ret;
+        if (pthread_join(thread, &ret) != 0 || ret != NULL)
+        {
+            fail("the filter is not synchronized to the thread");
+        }
+
+        exit(0);
+    }
+
+    int status = wait_child(pid);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        exit(1);
+    }
+}
+
+static void test_strict_mode(void)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        if (prctl(PR_SET_SECCOMP, SECCOMP_MODE_STRICT) < 0)
+        {
+            syscall(SYS_exit, 1);
+        }
+
+        // write(2) is allowed.
+        if (write(STDOUT_FILENO, "strict mode\n", 12) != 12)
+        {
+            syscall(SYS_exit, 1);
+        }
+
+        // Should be killed here.
+        syscall(SYS_getpid);
+        syscall(SYS_exit, 1);
+    }
+
+    int status = wait_child(pid);
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL)
+    {
+        fail("the strict mode does not kill the process");
+    }
+}
+
+int main(int argc, char **argv)
+{
+    if (argc > 1 && !strcmp(argv[1], "exec"))
+    {
+        return check_inherited_filter();
+    }
+
+    if (prctl(PR_GET_SECCOMP) != 0 || prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0) != 0)
+    {
+        fail("unexpected initial seccomp state");
+    }
+
+    test_filter(argv[0]);
+    test_trace();
+    test_tsync();
+    test_strict_mode();
+
+    printf("passed\n");
+    return 0;
+}
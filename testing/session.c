@@ -0,0 +1,133 @@
+// Checks sessions and controlling terminals.
+
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <termios.h>
+#include <unistd.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+// Runs `f` in a child process and checks if it succeeds.
+static void run_in_child(void (*f)(void), const char *msg)
+{
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        f();
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+static void new_session(void)
+{
+    pid_t sid = getsid(0);
+    if (sid < 0 || getsid(getpid()) != sid || getsid(getppid()) != sid)
+    {
+        exit(1);
+    }
+
+    if (setsid() != getpid() || getsid(0) != getpid() || getpgrp() != getpid())
+    {
+        exit(1);
+    }
+
+    // The session leader can't create a new session.
+    if (setsid() != -1 || errno != EPERM)
+    {
+        exit(1);
+    }
+}
+
+static void group_leader(void)
+{
+    if (setpgid(0, 0) < 0)
+    {
+        exit(1);
+    }
+
+    // A process group leader can't create a new session.
+    if (setsid() != -1 || errno != EPERM || getsid(0) == getpid())
+    {
+        exit(1);
+    }
+}
+
+// Opens the slave side of a new pseudo terminal without making it the
+// controlling terminal.
+static int open_pty(void)
+{
+    int master = posix_openpt(O_RDWR | O_NOCTTY);
+    if (master < 0 || grantpt(master) < 0 || unlockpt(master) < 0)
+    {
+        exit(1);
+    }
+
+    int slave = open(ptsname(master), O_RDWR | O_NOCTTY);
+    if (slave < 0)
+    {
+        exit(1);
+    }
+
+    return slave;
+}
+
+static void controlling_tty(void)
+{
+    setsid();
+    int tty = open_pty();
+    if (tcgetsid(tty) != -1)
+    {
+        exit(1);
+    }
+
+    if (ioctl(tty, TIOCSCTTY, 0) < 0 || tcgetsid(tty) != getpid() || tcgetpgrp(tty) != getpid())
+    {
+        exit(1);
+    }
+
+    // Another session can't take the terminal unless it steals the terminal
+    // with the privilege.
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        setsid();
+        if (ioctl(tty, TIOCSCTTY, 0) != -1 || errno != EPERM)
+        {
+            exit(1);
+        }
+
+        if (ioctl(tty, TIOCSCTTY, 1) < 0 || tcgetsid(tty) != getpid())
+        {
+            exit(1);
+        }
+
+        exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        exit(1);
+    }
+}
+
+int main(void)
+{
+    run_in_child(new_session, "failed to create a new session");
+    run_in_child(group_leader, "a process group leader created a new session");
+    run_in_child(controlling_tty, "failed to acquire the controlling terminal");
+
+    printf("passed\n");
+    return 0;
+}
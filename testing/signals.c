@@ -0,0 +1,174 @@
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+static volatile sig_atomic_t handled = 0;
+static volatile uid_t sender_uid = -1;
+
+static void clobber_fpu(int signal)
+{
+    // Clobber XMM0 and the SSE control register.
+    uint64_t value = 0x4004000000000000; // 2.5
+    uint32_t mxcsr = 0x0;
+    asm volatile("movq %0, %%xmm0\n\t"
+                 "ldmxcsr %1"
+                 :
+                 : "r"(value), "m"(mxcsr)
+                 : "xmm0");
+    handled = 1;
+}
+
+// The FPU/SSE registers are restored after the handler returns.
+static void test_fpu_state(void)
+{
+    struct sigaction act = {.sa_handler = clobber_fpu};
+    sigaction(SIGUSR1, &act, NULL);
+
+    uint64_t original = 0x3ff8000000000000; // 1.5
+    uint64_t restored;
+    uint32_t mxcsr;
+    long rax = SYS_kill;
+    asm volatile("movq %[in], %%xmm0\n\t"
+                 "syscall\n\t"
+                 "movq %%xmm0, %[out]\n\t"
+                 "stmxcsr %[mxcsr]"
+                 : "+a"(rax), [out] "=r"(restored), [mxcsr] "=m"(mxcsr)
+                 : [in] "r"(original), "D"((long)getpid()), "S"((long)SIGUSR1)
+                 : "rcx", "r11", "xmm0", "memory");
+
+    if (!handled)
+    {
+        fail("the signal handler is not called");
+    }
+
+    if (restored != original)
+    {
+        fail("XMM0 is not restored after the signal handler");
+    }
+
+    if (mxcsr != 0x1f80)
+    {
+        fail("MXCSR is not restored after the signal handler");
+    }
+}
+
+static void save_sender(int signal, siginfo_t *info, void *ctx)
+{
+    sender_uid = info->si_uid;
+    handled = 1;
+}
+
+static pid_t fork_as(uid_t uid)
+{
+    pid_t pid = fork();
+    if (pid == 0 && setuid(uid) < 0)
+    {
+        fail("setuid failed");
+    }
+
+    return pid;
+}
+
+static void wait_success(pid_t pid, const char *msg)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        fail(msg);
+    }
+}
+
+// si_uid is the real user ID of the sender.
+static void test_sender_uid(void)
+{
+    handled = 0;
+    struct sigaction act = {.sa_sigaction = save_sender, .sa_flags = SA_SIGINFO};
+    sigaction(SIGUSR2, &act, NULL);
+
+    pid_t pid = fork_as(1000);
+    if (pid == 0)
+    {
+        while (!handled)
+        {
+            pause();
+        }
+
+        exit(sender_uid == 1000 ? 0 : 1);
+    }
+
+    // Signal as the same user.
+    pid_t sender = fork_as(1000);
+    if (sender == 0)
+    {
+        exit(kill(pid, SIGUSR2) < 0);
+    }
+
+    wait_success(sender, "kill as the same user failed");
+    wait_success(pid, "si_uid is not the real user ID of the sender");
+}
+
+// kill(-1, sig) signals every process the caller is permitted to signal but
+// itself.
+static void test_kill_all(void)
+{
+    handled = 0;
+    struct sigaction act = {.sa_handler = clobber_fpu};
+    sigaction(SIGUSR1, &act, NULL);
+
+    int pipe_fds[2];
+    if (pipe(pipe_fds) < 0)
+    {
+        fail("pipe failed");
+    }
+
+    pid_t target = fork_as(1000);
+    if (target == 0)
+    {
+        close(pipe_fds[0]);
+        write(pipe_fds[1], "r", 1);
+        while (!handled)
+        {
+            pause();
+        }
+
+        exit(0);
+    }
+
+    char c;
+    read(pipe_fds[0], &c, 1);
+
+    pid_t sender = fork_as(1000);
+    if (sender == 0)
+    {
+        // The parent is not signaled: it's owned by root.
+        if (kill(-1, SIGUSR1) < 0 || handled)
+        {
+            exit(1);
+        }
+
+        exit(0);
+    }
+
+    wait_success(sender, "kill(-1) failed or signaled the caller");
+    wait_success(target, "kill(-1) did not signal a process");
+    if (handled)
+    {
+        fail("kill(-1) signaled a process owned by another user");
+    }
+}
+
+int main(void)
+{
+    test_fpu_state();
+    test_sender_uid();
+    test_kill_all();
+
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,123 @@
+// Wakes up processes and threads across CPUs. Run the kernel with multiple
+// CPUs (e.g. `make run SMP=4`): lost or duplicated wakeups hang or crash it.
+
+#include <pthread.h>
+#include <sched.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <sys/wait.h>
+
+#include "test_util.h"
+
+#define NUM_PAIRS 4
+#define NUM_ROUND_TRIPS 2000
+#define NUM_THREADS 4
+#define NUM_INCREMENTS 20000
+
+static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
+static long counter = 0;
+
+// Bounces a byte between two processes through pipes.
+static void ping_pong(void)
+{
+    int to_child[2], to_parent[2];
+    if (pipe(to_child) < 0 || pipe(to_parent) < 0)
+    {
+        fail("pipe failed");
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+    {
+        char c;
+        for (int i = 0; i < NUM_ROUND_TRIPS; i++)
+        {
+            if (read(to_child[0], &c, 1) != 1 || write(to_parent[1], &c, 1) != 1)
+            {
+                exit(1);
+            }
+        }
+
+        exit(0);
+    }
+
+    for (int i = 0; i < NUM_ROUND_TRIPS; i++)
+    {
+        char c = i;
+        if (write(to_child[1], &c, 1) != 1 || read(to_parent[0], &c, 1) != 1 || c != (char)i)
+        {
+            exit(1);
+        }
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+    {
+        exit(1);
+    }
+
+    exit(0);
+}
+
+// Contends for a mutex: waiters are woken up by futex(2).
+static void *increment(void *arg)
+{
+    for (int i = 0; i < NUM_INCREMENTS; i++)
+    {
+        pthread_mutex_lock(&mutex);
+        counter++;
+        pthread_mutex_unlock(&mutex);
+        if (i % 1000 == 0)
+        {
+            sched_yield();
+        }
+    }
+
+    return NULL;
+}
+
+int main(void)
+{
+    pid_t pids[NUM_PAIRS];
+    for (int i = 0; i < NUM_PAIRS; i++)
+    {
+        pids[i] = fork();
+        if (pids[i] == 0)
+        {
+            ping_pong();
+        }
+    }
+
+    pthread_t threads[NUM_THREADS];
+    for (int i = 0; i < NUM_THREADS; i++)
+    {
+        if (pthread_create(&threads[i], NULL, increment, NULL) != 0)
+        {
+            fail("pthread_create failed");
+        }
+    }
+
+    for (int i = 0; i < NUM_THREADS; i++)
+    {
+        pthread_join(threads[i], NULL);
+    }
+
+    if (counter != NUM_THREADS * NUM_INCREMENTS)
+    {
+        fail("the mutex does not exclude other threads");
+    }
+
+    for (int i = 0; i < NUM_PAIRS; i++)
+    {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0)
+        {
+            fail("the ping-pong between processes failed");
+        }
+    }
+
+    printf("passed\n");
+    return 0;
+}
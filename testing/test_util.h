@@ -0,0 +1,19 @@
+// Helpers shared by the C integration tests.
+//
+// Each test is built into a static executable:
+// musl-gcc -static -o <name>.test <name>.c
+
+#ifndef TEST_UTIL_H
+#define TEST_UTIL_H
+
+#include <stdio.h>
+#include <stdlib.h>
+
+// Prints the message and exits with a failure.
+static void fail(const char *msg)
+{
+    printf("%s\n", msg);
+    exit(1);
+}
+
+#endif
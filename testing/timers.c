@@ -0,0 +1,62 @@
+// Checks the overrun count of POSIX timers.
+
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_util.h"
+
+int main(void)
+{
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+
+    timer_t timer;
+    struct sigevent sev = {.sigev_notify = SIGEV_SIGNAL, .sigev_signo = SIGUSR1};
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) < 0)
+    {
+        fail("timer_create failed");
+    }
+
+    struct itimerspec spec = {
+        .it_value = {.tv_nsec = 1000000},
+        .it_interval = {.tv_nsec = 1000000},
+    };
+    if (timer_settime(timer, 0, &spec, NULL) < 0)
+    {
+        fail("timer_settime failed");
+    }
+
+    // The signal stays pending while the timer expires several times.
+    usleep(50000);
+    if (timer_getoverrun(timer) != 0)
+    {
+        fail("the overrun count is updated before the signal is delivered");
+    }
+
+    siginfo_t info;
+    if (sigwaitinfo(&set, &info) != SIGUSR1 || info.si_code != SI_TIMER)
+    {
+        fail("the timer signal is not delivered");
+    }
+
+    if (info.si_overrun <= 0)
+    {
+        fail("si_overrun does not count the missed expirations");
+    }
+
+    // timer_getoverrun(2) returns the same count until the next delivery.
+    int overrun = timer_getoverrun(timer);
+    if (overrun != info.si_overrun || timer_getoverrun(timer) != overrun)
+    {
+        fail("timer_getoverrun does not return the count of the last delivery");
+    }
+
+    timer_delete(timer);
+    printf("passed\n");
+    return 0;
+}
@@ -0,0 +1,228 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+
+#include "test_util.h"
+
+#define PATH "/tmp/truncate"
+
+static off_t file_size(int fd)
+{
+    struct stat st;
+    if (fstat(fd, &st) < 0)
+    {
+        fail("fstat failed");
+    }
+
+    return st.st_size;
+}
+
+static void test_truncate(int fd)
+{
+    if (write(fd, "0123456789", 10) != 10 || file_size(fd) != 10)
+    {
+        fail("st_size does not reflect the written data");
+    }
+
+    if (ftruncate(fd, 4) < 0 || file_size(fd) != 4)
+    {
+        fail("ftruncate did not shrink the file");
+    }
+
+    if (truncate(PATH, 8) < 0 || file_size(fd) != 8)
+    {
+        fail("truncate did not extend the file");
+    }
+
+    char buf[16];
+    if (pread(fd, buf, sizeof(buf), 0) != 8 || memcmp(buf, "0123\0\0\0\0", 8))
+    {
+        fail("the extended part is not filled with zeroes");
+    }
+
+    if (ftruncate(fd, -1) != -1 || errno != EINVAL)
+    {
+        fail("ftruncate with a negative length does not return EINVAL");
+    }
+
+    if (truncate("/tmp", 0) != -1 || errno != EISDIR)
+    {
+        fail("truncate on a directory does not return EISDIR");
+    }
+}
+
+static void test_mmap(int fd)
+{
+    ftruncate(fd, 4096);
+    char *p = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (p == MAP_FAILED)
+    {
+        fail("mmap failed");
+    }
+
+    memset(p, 'a', 4096);
+    if (ftruncate(fd, 100) < 0 || file_size(fd) != 100)
+    {
+        fail("ftruncate on a mapped file failed");
+    }
+
+    char c;
+    if (pread(fd, &c, 1, 99) != 1 || c != 'a' || p[100] != 0)
+    {
+        fail("the page cache is not updated by ftruncate");
+    }
+
+    munmap(p, 4096);
+}
+
+static void test_fallocate(int fd)
+{
+    ftruncate(fd, 0);
+    if (fallocate(fd, 0, 0, 16) < 0 || file_size(fd) != 16)
+    {
+        fail("fallocate did not extend the file");
+    }
+
+    if (fallocate(fd, FALLOC_FL_KEEP_SIZE, 0, 64) < 0 || file_size(fd) != 16)
+    {
+        fail("FALLOC_FL_KEEP_SIZE changed the file size");
+    }
+
+    pwrite(fd, "abcdefghijklmnop", 16, 0);
+    if (fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 4, 100) < 0 ||
+        file_size(fd) != 16)
+    {
+        fail("FALLOC_FL_PUNCH_HOLE failed");
+    }
+
+    char buf[16];
+    if (pread(fd, buf, sizeof(buf), 0) != 16 || memcmp(buf, "abcd", 4) ||
+        memcmp(buf + 4, "\0\0\0\0\0\0\0\0\0\0\0\0", 12))
+    {
+        fail("FALLOC_FL_PUNCH_HOLE did not zero the range");
+    }
+
+    if (fallocate(fd, FALLOC_FL_PUNCH_HOLE, 0, 1) != -1 || errno != EOPNOTSUPP)
+    {
+        fail("FALLOC_FL_PUNCH_HOLE without FALLOC_FL_KEEP_SIZE is accepted");
+    }
+
+    if (fallocate(fd, 0, 0, 0) != -1 || errno != EINVAL)
+    {
+        fail("fallocate with zero length does not return EINVAL");
+    }
+
+    int fds[2];
+    pipe(fds);
+    if (fallocate(fds[1], 0, 0, 1) != -1 || errno != ESPIPE)
+    {
+        fail("fallocate on a pipe does not return ESPIPE");
+    }
+}
+
+// Punched pages are reported by SEEK_HOLE.
+static void test_seek_hole(int fd)
+{
+    char page[4096];
+    memset(page, 'a', sizeof(page));
+    ftruncate(fd, 0);
+    lseek(fd, 0, SEEK_SET);
+    for (int i = 0; i < 4; i++)
+    {
+        write(fd, page, sizeof(page));
+    }
+
+    if (lseek(fd, 0, SEEK_HOLE) != 4 * 4096)
+    {
+        fail("SEEK_HOLE does not return the end of the file");
+    }
+
+    if (fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 4096, 2 * 4096) < 0)
+    {
+        fail("FALLOC_FL_PUNCH_HOLE failed");
+    }
+
+    if (lseek(fd, 100, SEEK_HOLE) != 4096 || lseek(fd, 5000, SEEK_HOLE) != 5000)
+    {
+        fail("SEEK_HOLE does not find the punched hole");
+    }
+
+    if (lseek(fd, 4096, SEEK_DATA) != 3 * 4096 || lseek(fd, 100, SEEK_DATA) != 100)
+    {
+        fail("SEEK_DATA does not skip the punched hole");
+    }
+
+    // Writing into the hole fills it.
+    pwrite(fd, "b", 1, 4096);
+    if (lseek(fd, 0, SEEK_HOLE) != 2 * 4096)
+    {
+        fail("a written page is still a hole");
+    }
+}
+
+// ftruncate(2) and fallocate(2) require a file descriptor opened for writing.
+static void test_read_only(void)
+{
+    int fd = open(PATH, O_RDONLY);
+    if (fd < 0)
+    {
+        fail("failed to open the file");
+    }
+
+    if (ftruncate(fd, 0) != -1 || errno != EINVAL)
+    {
+        fail("ftruncate on a read-only file descriptor does not return EINVAL");
+    }
+
+    if (fallocate(fd, 0, 0, 1) != -1 || errno != EBADF)
+    {
+        fail("fallocate on a read-only file descriptor does not return EBADF");
+    }
+
+    close(fd);
+}
+
+static void test_o_trunc(void)
+{
+    int fd = open(PATH, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || file_size(fd) != 0)
+    {
+        fail("O_TRUNC did not truncate the file");
+    }
+
+    close(fd);
+
+    fd = open("/dev/null", O_WRONLY | O_TRUNC);
+    if (fd < 0)
+    {
+        fail("O_TRUNC on a device file failed");
+    }
+
+    close(fd);
+}
+
+int main(void)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT, 0644);
+    if (fd < 0)
+    {
+        fail("failed to create a file");
+    }
+
+    test_truncate(fd);
+    test_mmap(fd);
+    test_fallocate(fd);
+    test_seek_hole(fd);
+    close(fd);
+    test_read_only();
+    test_o_trunc();
+
+    printf("passed\n");
+    return 0;
+}
@@ -44,6 +44,7 @@ def main():
     parser.add_argument("--gui", action="store_true")
     parser.add_argument("--gdb", action="store_true")
     parser.add_argument("--kvm", action="store_true")
+    parser.add_argument("--smp", help="The number of CPUs.")
     parser.add_argument("--append-cmdline", action="append")
     parser.add_argument("--log-serial")
     parser.add_argument("--qemu")
@@ -80,6 +81,8 @@ def main():
         argv += ["-gdb", "tcp::7789", "-S"]
     if args.kvm:
         argv += ["-accel", "kvm"]
+    if args.smp:
+        argv += ["-smp", args.smp]
     if args.append_cmdline:
         cmdline += args.append_cmdline
     if args.log_serial: